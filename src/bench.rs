@@ -0,0 +1,262 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//! Load generator behind `ccr-rust bench`.
+//!
+//! Keeps `concurrency` requests in flight against a running server's
+//! `/v1/messages` for a fixed duration, then reports throughput, latency
+//! percentiles, error rate, and which tiers served the requests (from the
+//! `x-ccr-tier` response header). Unlike `ccr-stress`, requests go through
+//! the real routing path and upstream providers, so a run costs tokens.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use tokio::task::JoinSet;
+
+/// Settings for one bench run.
+#[derive(Debug, Clone)]
+pub struct BenchOptions {
+    /// Server base URL, e.g. `http://127.0.0.1:3456`.
+    pub base_url: String,
+    pub model: String,
+    pub concurrency: usize,
+    pub duration: Duration,
+    pub stream: bool,
+    pub max_tokens: u32,
+    pub prompt: String,
+    /// Sent as `x-api-key` when the server requires `CLIENT_KEYS`.
+    pub api_key: Option<String>,
+    /// Per-request timeout.
+    pub timeout: Duration,
+}
+
+/// Outcome of one request.
+#[derive(Debug, Clone)]
+pub struct BenchSample {
+    /// Time until the whole response body, or the whole stream, was read.
+    pub latency: Duration,
+    pub ok: bool,
+    /// `x-ccr-tier` of the response, if the server set it.
+    pub tier: Option<String>,
+}
+
+/// Aggregated results of a bench run.
+#[derive(Debug, Clone)]
+pub struct BenchReport {
+    pub elapsed: Duration,
+    pub requests: u64,
+    pub errors: u64,
+    /// Latencies of successful requests, sorted ascending.
+    pub latencies: Vec<Duration>,
+    /// Requests per `x-ccr-tier` value; `(none)` when the header was absent.
+    pub tiers: BTreeMap<String, u64>,
+}
+
+impl BenchReport {
+    pub fn from_samples(samples: &[BenchSample], elapsed: Duration) -> Self {
+        let mut latencies: Vec<Duration> =
+            samples.iter().filter(|s| s.ok).map(|s| s.latency).collect();
+        latencies.sort();
+        let mut tiers = BTreeMap::new();
+        for sample in samples {
+            let tier = sample.tier.as_deref().unwrap_or("(none)");
+            *tiers.entry(tier.to_string()).or_insert(0) += 1;
+        }
+        Self {
+            elapsed,
+            requests: samples.len() as u64,
+            errors: samples.iter().filter(|s| !s.ok).count() as u64,
+            latencies,
+            tiers,
+        }
+    }
+
+    /// Completed requests per second.
+    pub fn throughput(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            self.requests as f64 / secs
+        } else {
+            0.0
+        }
+    }
+
+    pub fn error_rate(&self) -> f64 {
+        if self.requests == 0 {
+            0.0
+        } else {
+            self.errors as f64 / self.requests as f64
+        }
+    }
+
+    /// Nearest-rank percentile of successful-request latency; `None` when no
+    /// request succeeded.
+    pub fn percentile(&self, p: f64) -> Option<Duration> {
+        if self.latencies.is_empty() {
+            return None;
+        }
+        let rank = (p / 100.0 * self.latencies.len() as f64).ceil() as usize;
+        Some(self.latencies[rank.clamp(1, self.latencies.len()) - 1])
+    }
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Requests:   {} in {:.1}s ({:.2} req/s)",
+            self.requests,
+            self.elapsed.as_secs_f64(),
+            self.throughput()
+        )?;
+        writeln!(
+            f,
+            "Errors:     {} ({:.1}%)",
+            self.errors,
+            self.error_rate() * 100.0
+        )?;
+        write!(f, "Latency:   ")?;
+        for (label, p) in [("p50", 50.0), ("p95", 95.0), ("p99", 99.0)] {
+            match self.percentile(p) {
+                Some(latency) => write!(f, " {} {} ms", label, latency.as_millis())?,
+                None => write!(f, " {} -", label)?,
+            }
+        }
+        writeln!(f)?;
+        writeln!(f, "Tiers:")?;
+        for (tier, count) in &self.tiers {
+            writeln!(
+                f,
+                "  {:<24} {:>6} ({:.1}%)",
+                tier,
+                count,
+                *count as f64 * 100.0 / self.requests.max(1) as f64
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Run the bench and collect every request's outcome.
+pub async fn run(options: BenchOptions) -> anyhow::Result<BenchReport> {
+    let client = reqwest::Client::builder()
+        .timeout(options.timeout)
+        .build()?;
+    let url = format!("{}/v1/messages", options.base_url.trim_end_matches('/'));
+    let body = serde_json::json!({
+        "model": options.model,
+        "max_tokens": options.max_tokens,
+        "stream": options.stream,
+        "messages": [{"role": "user", "content": options.prompt}],
+    });
+
+    let started = Instant::now();
+    let deadline = started + options.duration;
+    let mut workers = JoinSet::new();
+    for _ in 0..options.concurrency.max(1) {
+        let client = client.clone();
+        let url = url.clone();
+        let body = body.clone();
+        let api_key = options.api_key.clone();
+        workers.spawn(async move {
+            let mut samples = Vec::new();
+            while Instant::now() < deadline {
+                samples.push(send_one(&client, &url, &body, api_key.as_deref()).await);
+            }
+            samples
+        });
+    }
+
+    let mut samples = Vec::new();
+    while let Some(worker) = workers.join_next().await {
+        samples.extend(worker?);
+    }
+    Ok(BenchReport::from_samples(&samples, started.elapsed()))
+}
+
+async fn send_one(
+    client: &reqwest::Client,
+    url: &str,
+    body: &serde_json::Value,
+    api_key: Option<&str>,
+) -> BenchSample {
+    let started = Instant::now();
+    let mut request = client.post(url).json(body);
+    if let Some(key) = api_key {
+        request = request.header("x-api-key", key);
+    }
+    let response = match request.send().await {
+        Ok(response) => response,
+        Err(_) => {
+            return BenchSample {
+                latency: started.elapsed(),
+                ok: false,
+                tier: None,
+            }
+        }
+    };
+    let tier = response
+        .headers()
+        .get("x-ccr-tier")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let success = response.status().is_success();
+    // Read to the end so streaming latency covers the whole stream. A stream
+    // can fail after its 200 with an `error` event.
+    let ok = match response.bytes().await {
+        Ok(bytes) => {
+            success
+                && !String::from_utf8_lossy(&bytes)
+                    .lines()
+                    .any(|line| line.trim_end() == "event: error")
+        }
+        Err(_) => false,
+    };
+    BenchSample {
+        latency: started.elapsed(),
+        ok,
+        tier,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(ms: u64, ok: bool, tier: Option<&str>) -> BenchSample {
+        BenchSample {
+            latency: Duration::from_millis(ms),
+            ok,
+            tier: tier.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn report_summarizes_samples() {
+        let mut samples: Vec<BenchSample> = (1..=100)
+            .map(|ms| sample(ms, true, Some("deepseek")))
+            .collect();
+        samples.push(sample(5, false, Some("zai")));
+        samples.push(sample(1, false, None));
+
+        let report = BenchReport::from_samples(&samples, Duration::from_secs(2));
+        assert_eq!(report.requests, 102);
+        assert_eq!(report.errors, 2);
+        assert_eq!(report.throughput(), 51.0);
+        assert_eq!(report.percentile(50.0), Some(Duration::from_millis(50)));
+        assert_eq!(report.percentile(95.0), Some(Duration::from_millis(95)));
+        assert_eq!(report.percentile(99.0), Some(Duration::from_millis(99)));
+        assert_eq!(report.tiers["deepseek"], 100);
+        assert_eq!(report.tiers["zai"], 1);
+        assert_eq!(report.tiers["(none)"], 1);
+    }
+
+    #[test]
+    fn report_without_successes_has_no_percentiles() {
+        let report = BenchReport::from_samples(&[sample(3, false, None)], Duration::ZERO);
+        assert_eq!(report.percentile(50.0), None);
+        assert_eq!(report.error_rate(), 1.0);
+        assert_eq!(report.throughput(), 0.0);
+        assert!(report.to_string().contains("p50 -"));
+    }
+}
@@ -1,8 +1,11 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
+mod source;
 mod types;
+pub use source::{ConfigSource, CONFIG_FETCH_TIMEOUT};
 pub use types::*;
 
 use anyhow::{Context, Result};
+use arc_swap::ArcSwap;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
@@ -23,6 +26,35 @@ pub struct PresetConfig {
     /// Optional temperature override
     #[serde(default)]
     pub temperature: Option<f32>,
+
+    /// Optional forced `tool_choice`, replacing the request's (e.g.
+    /// `{"type": "tool", "name": "web_search"}` for a must-search profile).
+    #[serde(default)]
+    pub tool_choice: Option<serde_json::Value>,
+
+    /// Optional system prompt placed ahead of the request's own.
+    #[serde(default)]
+    pub system: Option<String>,
+
+    /// Send a one-token request with `system` to `route` at startup and after
+    /// each reload, so the provider has the prompt cached before real traffic.
+    #[serde(default)]
+    pub warm: bool,
+
+    /// Interval in milliseconds between `: ping` SSE comments sent to a
+    /// streaming client while the upstream has not answered yet (0 = off).
+    #[serde(default)]
+    pub keepalive_ms: u64,
+}
+
+impl PresetConfig {
+    /// Keepalive interval for streaming requests; `None` when disabled.
+    pub fn keepalive_interval(&self) -> Option<std::time::Duration> {
+        match self.keepalive_ms {
+            0 => None,
+            ms => Some(std::time::Duration::from_millis(ms)),
+        }
+    }
 }
 
 /// Parsed JSON configuration (deserializable).
@@ -60,11 +92,58 @@ pub struct ConfigFile {
     #[serde(rename = "POOL_IDLE_TIMEOUT_MS")]
     pub pool_idle_timeout_ms: u64,
 
+    /// Speak HTTP/2 to upstreams without negotiating it first (prior
+    /// knowledge). A provider's `http2_prior_knowledge` overrides this.
+    #[serde(default)]
+    #[serde(rename = "HTTP2_PRIOR_KNOWLEDGE")]
+    pub http2_prior_knowledge: bool,
+
     /// SSE channel buffer size per stream (number of chunks).
     #[serde(default = "default_sse_buffer_size")]
     #[serde(rename = "SSE_BUFFER_SIZE")]
     pub sse_buffer_size: usize,
 
+    /// Bounds for the per-request `x-ccr-sse-buffer` header. Unset ignores
+    /// the header.
+    #[serde(default)]
+    #[serde(rename = "SSE_BUFFER_OVERRIDE")]
+    pub sse_buffer_override: Option<SseBufferOverrideConfig>,
+
+    /// Provider connectivity check behind `GET /ready`. Unset makes `/ready`
+    /// answer 200 as soon as the server is up.
+    #[serde(default)]
+    #[serde(rename = "READINESS_CHECK")]
+    pub readiness_check: Option<ReadinessCheckConfig>,
+
+    /// Interval in milliseconds between `: ping` SSE comments sent while a
+    /// stream is idle (0 = disabled).
+    #[serde(default)]
+    #[serde(rename = "SSE_HEARTBEAT_INTERVAL_MS")]
+    pub sse_heartbeat_interval_ms: u64,
+
+    /// Emit an interim `message_delta` usage event every this many estimated
+    /// output tokens on translated streams (0 = disabled).
+    #[serde(default)]
+    #[serde(rename = "STREAM_USAGE_INTERVAL_TOKENS")]
+    pub stream_usage_interval_tokens: u64,
+
+    /// Cap on concurrent upstream requests across all tiers, streaming or
+    /// not (0 = unlimited).
+    #[serde(default)]
+    #[serde(rename = "MAX_UPSTREAM_INFLIGHT")]
+    pub max_upstream_inflight: usize,
+
+    /// How long a request waits for an upstream slot before it gets 503.
+    #[serde(default = "default_upstream_queue_timeout_ms")]
+    #[serde(rename = "UPSTREAM_QUEUE_TIMEOUT_MS")]
+    pub upstream_queue_timeout_ms: u64,
+
+    /// Largest request body the proxy endpoints accept, measured after
+    /// `content-encoding` is decoded. Larger requests get 413.
+    #[serde(default = "default_max_body_bytes")]
+    #[serde(rename = "MAX_BODY_BYTES", alias = "max_body_bytes")]
+    pub max_body_bytes: usize,
+
     /// Named preset configurations.
     #[serde(default)]
     #[serde(rename = "Presets")]
@@ -87,6 +166,51 @@ pub struct ConfigFile {
     #[serde(default)]
     #[serde(rename = "BROKER_SOCKET")]
     pub broker_socket: Option<String>,
+
+    /// Bearer token required on admin routes (`/v1/reload`, `/metrics`).
+    /// Unset leaves them open; proxy endpoints are never authenticated.
+    #[serde(default)]
+    #[serde(rename = "ADMIN_TOKEN")]
+    pub admin_token: Option<String>,
+
+    /// Start in maintenance mode: proxy endpoints answer 503 without
+    /// dispatching upstream. Toggled at runtime by `POST /v1/maintenance`.
+    #[serde(default)]
+    #[serde(rename = "MAINTENANCE_MODE")]
+    pub maintenance_mode: bool,
+
+    /// API keys clients must present on the proxy endpoints, as `x-api-key`
+    /// or `Authorization: Bearer`. Empty disables client authentication.
+    #[serde(default)]
+    #[serde(rename = "CLIENT_KEYS")]
+    pub client_keys: Vec<String>,
+
+    /// Token-bucket limit on proxy requests per client, keyed by the API
+    /// key presented or by peer IP. Unset disables it.
+    #[serde(default)]
+    #[serde(rename = "INGRESS_RATE_LIMIT")]
+    pub ingress_rate_limit: Option<IngressRateLimitConfig>,
+
+    /// Master switch for `Router.faultInjection`. Leave off outside of
+    /// resilience testing; without it the per-tier faults never fire.
+    #[serde(default)]
+    #[serde(rename = "ENABLE_FAULT_INJECTION")]
+    pub enable_fault_injection: bool,
+
+    /// Pass `reasoning_content` through for models not flagged as reasoning.
+    /// When false, it is dropped from their translated responses.
+    #[serde(default = "default_emit_unsolicited_reasoning")]
+    #[serde(
+        rename = "EMIT_UNSOLICITED_REASONING",
+        alias = "emit_unsolicited_reasoning"
+    )]
+    pub emit_unsolicited_reasoning: bool,
+
+    /// Clamp `max_tokens` to the model's `max_output_tokens` from
+    /// `model_metadata`. A provider's `max_tokens_cap` overrides this.
+    #[serde(default)]
+    #[serde(rename = "MAX_TOKENS_CAP", alias = "max_tokens_cap")]
+    pub max_tokens_cap: bool,
 }
 
 /// Runtime configuration shared across all handlers via Axum state.
@@ -100,7 +224,11 @@ pub struct Config {
 #[derive(Debug)]
 struct ConfigInner {
     file: ConfigFile,
+    /// Where the config was loaded from, used by hot reload.
+    source: ConfigSource,
     http_client: reqwest::Client,
+    /// Clients for providers with their own HTTP/2 setting, keyed by provider name.
+    provider_clients: HashMap<String, reqwest::Client>,
 }
 
 impl Config {
@@ -121,11 +249,63 @@ impl Config {
         self.inner.file.sse_buffer_size
     }
 
+    /// Range clients may set their SSE buffer size in, if they may at all.
+    pub fn sse_buffer_override(&self) -> Option<&SseBufferOverrideConfig> {
+        self.inner.file.sse_buffer_override.as_ref()
+    }
+
+    /// Connectivity check `/ready` requires to have passed recently, if any.
+    pub fn readiness_check(&self) -> Option<&ReadinessCheckConfig> {
+        self.inner.file.readiness_check.as_ref()
+    }
+
+    /// Global upstream concurrency cap; 0 means unlimited.
+    pub fn max_upstream_inflight(&self) -> usize {
+        self.inner.file.max_upstream_inflight
+    }
+
+    pub fn upstream_queue_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.inner.file.upstream_queue_timeout_ms)
+    }
+
+    /// Request body size limit for the proxy endpoints, in bytes.
+    pub fn max_body_bytes(&self) -> usize {
+        self.inner.file.max_body_bytes
+    }
+
+    /// Idle interval after which streams emit an SSE heartbeat comment.
+    /// `None` when heartbeats are disabled.
+    pub fn sse_heartbeat_interval(&self) -> Option<std::time::Duration> {
+        match self.inner.file.sse_heartbeat_interval_ms {
+            0 => None,
+            ms => Some(std::time::Duration::from_millis(ms)),
+        }
+    }
+
+    /// Output-token interval between interim usage events on translated
+    /// streams. `None` when interim usage is disabled.
+    pub fn stream_usage_interval_tokens(&self) -> Option<u64> {
+        match self.inner.file.stream_usage_interval_tokens {
+            0 => None,
+            tokens => Some(tokens),
+        }
+    }
+
     /// Get the shared HTTP client. One pool for all requests.
     pub fn http_client(&self) -> &reqwest::Client {
         &self.inner.http_client
     }
 
+    /// Get the HTTP client for `provider`: its dedicated client when it sets
+    /// its own `http2_prior_knowledge`, otherwise the shared client. A
+    /// provider's `timeout_ms` is applied per request by the dispatcher.
+    pub fn http_client_for(&self, provider: &Provider) -> &reqwest::Client {
+        self.inner
+            .provider_clients
+            .get(&provider.name)
+            .unwrap_or(&self.inner.http_client)
+    }
+
     /// Get a preset by name.
     pub fn get_preset(&self, name: &str) -> Option<&PresetConfig> {
         self.presets.get(name)
@@ -152,43 +332,341 @@ impl Config {
             .or_else(|| std::env::var("CCR_BROKER_SOCKET").ok())
     }
 
+    /// Token admin routes require, if one is configured.
+    pub fn admin_token(&self) -> Option<&str> {
+        self.inner.file.admin_token.as_deref()
+    }
+
+    /// Whether the config asks for maintenance mode.
+    pub fn maintenance_mode(&self) -> bool {
+        self.inner.file.maintenance_mode
+    }
+
+    /// API keys accepted from clients; empty when client auth is disabled.
+    pub fn client_keys(&self) -> &[String] {
+        &self.inner.file.client_keys
+    }
+
+    /// Per-client proxy request limit, if one is configured.
+    pub fn ingress_rate_limit(&self) -> Option<&IngressRateLimitConfig> {
+        self.inner.file.ingress_rate_limit.as_ref()
+    }
+
     /// List all preset names.
     pub fn preset_names(&self) -> Vec<&str> {
         self.presets.keys().map(|s| s.as_str()).collect()
     }
+
+    /// Where this config was loaded from.
+    pub fn source(&self) -> &ConfigSource {
+        &self.inner.source
+    }
+
+    /// Check that every `provider,model` tier names a configured provider.
+    pub fn validate(&self) -> Result<()> {
+        for tier in self.backend_tiers() {
+            if tier.contains(',') && self.resolve_provider(&tier).is_none() {
+                anyhow::bail!("Tier '{}' references an unknown provider", tier);
+            }
+            if let Some(provider) = self.resolve_provider(&tier) {
+                let model = tier.split(',').nth(1).unwrap_or_default();
+                if provider.upstream_model(model).is_none() {
+                    anyhow::bail!(
+                        "Tier '{}' names a model provider '{}' neither lists in models nor maps in model_aliases",
+                        tier,
+                        provider.name
+                    );
+                }
+            }
+        }
+        if let Some(token) = self.admin_token() {
+            if token.is_empty()
+                || !token.is_ascii()
+                || token
+                    .bytes()
+                    .any(|byte| byte.is_ascii_whitespace() || byte.is_ascii_control())
+            {
+                anyhow::bail!("ADMIN_TOKEN must be nonempty ASCII without whitespace");
+            }
+        }
+        if self.max_body_bytes() == 0 {
+            anyhow::bail!("MAX_BODY_BYTES must be greater than 0");
+        }
+        if self.client_keys().iter().any(|key| key.is_empty()) {
+            anyhow::bail!("CLIENT_KEYS must not contain empty keys");
+        }
+        if let Some(limit) = self.ingress_rate_limit() {
+            if limit.requests_per_minute == 0 || limit.burst() == 0 {
+                anyhow::bail!(
+                    "INGRESS_RATE_LIMIT requests_per_minute and burst must be greater than 0"
+                );
+            }
+        }
+        if let Some(window) = self.router().latency_window {
+            if window == 0 || window > crate::routing::MAX_LATENCY_WINDOW {
+                anyhow::bail!(
+                    "latencyWindow must be between 1 and {}, got {}",
+                    crate::routing::MAX_LATENCY_WINDOW,
+                    window
+                );
+            }
+        }
+        for (route, headers) in &self.router().tier_headers {
+            if self.resolve_provider(route).is_none() {
+                anyhow::bail!(
+                    "tierHeaders key '{}' must be a provider,model route with a configured provider",
+                    route
+                );
+            }
+            validate_headers(&format!("tierHeaders for '{route}'"), headers)?;
+        }
+        for (frontend, route) in &self.router().frontend_defaults {
+            let model = route.split(',').nth(1).unwrap_or_default();
+            match self.resolve_provider(route) {
+                Some(provider) if provider.upstream_model(model).is_some() => {}
+                _ => anyhow::bail!(
+                    "frontendDefaults route '{}' for {:?} must be a provider,model route with a configured provider and model",
+                    route,
+                    frontend
+                ),
+            }
+        }
+        if self.sse_buffer_size() == 0 {
+            anyhow::bail!("SSE_BUFFER_SIZE must be greater than 0");
+        }
+        if let Some(range) = self.sse_buffer_override() {
+            if range.min == 0 || range.min > range.max {
+                anyhow::bail!(
+                    "SSE_BUFFER_OVERRIDE needs 0 < min <= max, got min {} and max {}",
+                    range.min,
+                    range.max
+                );
+            }
+        }
+        if let Some(check) = self.readiness_check() {
+            if check.interval_ms == 0 || check.timeout_ms == 0 {
+                anyhow::bail!("READINESS_CHECK interval_ms and timeout_ms must be greater than 0");
+            }
+        }
+        for provider in self.providers() {
+            if provider.sse_buffer_size == Some(0) {
+                anyhow::bail!(
+                    "sse_buffer_size for provider '{}' must be greater than 0",
+                    provider.name
+                );
+            }
+        }
+        if let Some(status) = self
+            .router()
+            .retryable_statuses
+            .iter()
+            .find(|status| !(400..=599).contains(*status))
+        {
+            anyhow::bail!(
+                "retryableStatuses must be HTTP error statuses (400-599), got {}",
+                status
+            );
+        }
+        if self.router().hedging.hedge_after_ms == Some(0) {
+            anyhow::bail!("hedging.hedge_after_ms must be greater than 0");
+        }
+        for (tier, alpha) in &self.router().ewma_alpha {
+            if !(*alpha > 0.0 && *alpha <= 1.0) {
+                anyhow::bail!(
+                    "ewmaAlpha for tier '{}' must be in (0, 1], got {}",
+                    tier,
+                    alpha
+                );
+            }
+        }
+        for (name, preset) in &self.presets {
+            if preset.warm
+                && (preset.system.as_deref().is_none_or(str::is_empty)
+                    || self.resolve_provider(&preset.route).is_none())
+            {
+                anyhow::bail!(
+                    "warm preset '{}' needs a system prompt and a provider,model route with a configured provider",
+                    name
+                );
+            }
+        }
+        for (tier, faults) in &self.router().fault_injection {
+            let rates = [faults.error_rate, faults.rate_limit_rate];
+            if rates.iter().any(|rate| !(0.0..=1.0).contains(rate))
+                || rates.iter().sum::<f64>() > 1.0
+            {
+                anyhow::bail!(
+                    "faultInjection for tier '{}' needs error_rate and rate_limit_rate in [0, 1] with a sum of at most 1",
+                    tier
+                );
+            }
+        }
+        for provider in self.providers() {
+            if provider.azure.is_some() && provider.protocol != ProviderProtocol::Openai {
+                anyhow::bail!(
+                    "azure for provider '{}' requires protocol=openai",
+                    provider.name
+                );
+            }
+            if let Some(headers) = &provider.extra_headers {
+                validate_headers(
+                    &format!("headers for provider '{}'", provider.name),
+                    headers,
+                )?;
+            }
+            for rule in &provider.error_classification {
+                match (&rule.contains, &rule.regex) {
+                    (Some(needle), None) if !needle.is_empty() => {}
+                    (None, Some(pattern)) => {
+                        if let Err(e) = regex::Regex::new(pattern) {
+                            anyhow::bail!(
+                                "error_classification regex for provider '{}' is invalid: {}",
+                                provider.name,
+                                e
+                            );
+                        }
+                    }
+                    _ => anyhow::bail!(
+                        "error_classification rules for provider '{}' need exactly one non-empty 'contains' or 'regex'",
+                        provider.name
+                    ),
+                }
+            }
+            if let Some(path) = provider
+                .soft_errors
+                .error_paths
+                .iter()
+                .find(|path| !path.starts_with('/'))
+            {
+                anyhow::bail!(
+                    "soft_errors.error_paths for provider '{}' must be JSON Pointers starting with '/', got '{}'",
+                    provider.name,
+                    path
+                );
+            }
+        }
+        let persistence = self.persistence();
+        if persistence.shared_rate_limits {
+            let has_redis_url = persistence.redis_url.is_some()
+                || std::env::var("CCR_REDIS_URL").is_ok_and(|url| !url.is_empty());
+            if persistence.mode != PersistenceMode::Redis || !has_redis_url {
+                anyhow::bail!(
+                    "Persistence.shared_rate_limits requires mode=redis and a redis_url or CCR_REDIS_URL"
+                );
+            }
+            if persistence.shared_rate_limit_sync_ms == 0 {
+                anyhow::bail!("Persistence.shared_rate_limit_sync_ms must be positive");
+            }
+        }
+        let table_prefix = &self.persistence().postgres_table_prefix;
+        if table_prefix.is_empty()
+            || !table_prefix
+                .bytes()
+                .all(|byte| byte.is_ascii_lowercase() || byte.is_ascii_digit() || byte == b'_')
+        {
+            anyhow::bail!(
+                "Persistence.postgres_table_prefix must be nonempty and use only [a-z0-9_], got '{}'",
+                table_prefix
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Configured headers are merged into upstream requests, where an illegal
+/// name or value would otherwise be skipped without notice.
+fn validate_headers(owner: &str, headers: &HashMap<String, String>) -> Result<()> {
+    for (name, value) in headers {
+        if reqwest::header::HeaderName::from_bytes(name.as_bytes()).is_err() {
+            anyhow::bail!("{} has an invalid header name '{}'", owner, name);
+        }
+        if reqwest::header::HeaderValue::from_str(value).is_err() {
+            anyhow::bail!("{} has an invalid value for header '{}'", owner, name);
+        }
+    }
+    Ok(())
+}
+
+/// Hot-swappable handle to the active [`Config`].
+///
+/// Handlers take one snapshot per request with [`SharedConfig::load`], so a
+/// reload never changes the config under an in-flight request.
+#[derive(Debug, Clone)]
+pub struct SharedConfig(Arc<ArcSwap<Config>>);
+
+impl SharedConfig {
+    pub fn new(config: Config) -> Self {
+        Self(Arc::new(ArcSwap::from_pointee(config)))
+    }
+
+    /// Snapshot of the current config.
+    pub fn load(&self) -> Arc<Config> {
+        self.0.load_full()
+    }
+
+    /// Atomically replace the config seen by subsequent requests.
+    pub fn store(&self, config: Config) {
+        self.0.store(Arc::new(config));
+    }
+}
+
+impl From<Config> for SharedConfig {
+    fn from(config: Config) -> Self {
+        Self::new(config)
+    }
 }
 
 impl Config {
     pub fn from_file(path: &str) -> Result<Self> {
         let raw_content =
             fs::read_to_string(path).context(format!("Failed to read config file: {}", path))?;
+        Self::from_json(&raw_content, ConfigSource::File(path.to_string()))
+    }
+
+    /// Load config from a file, stdin, or URL. The text goes through the same
+    /// env expansion and parsing as [`Config::from_file`].
+    pub async fn from_source(source: &ConfigSource) -> Result<Self> {
+        let raw_content = source.read().await?;
+        Self::from_json(&raw_content, source.clone())
+    }
+
+    fn from_json(raw_content: &str, source: ConfigSource) -> Result<Self> {
         // Expand ${VAR} env var references in config values (e.g., api_key: "${ZAI_API_KEY}")
         let content = shellexpand::env(&raw_content)
             .map(|s| s.into_owned())
             .unwrap_or_else(|e| {
                 tracing::warn!("Failed to expand env vars in config, using raw: {e}");
-                raw_content.clone()
+                raw_content.to_string()
             });
         let file: ConfigFile =
             serde_json::from_str(&content).context("Failed to parse config JSON")?;
 
         // Build a single shared reqwest::Client with a properly-sized connection pool.
-        let mut client_builder = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_millis(file.api_timeout_ms))
-            .pool_max_idle_per_host(file.pool_max_idle_per_host)
-            .tcp_keepalive(std::time::Duration::from_secs(30))
-            .tcp_nodelay(true);
-
-        if file.pool_idle_timeout_ms > 0 {
-            client_builder = client_builder
-                .pool_idle_timeout(std::time::Duration::from_millis(file.pool_idle_timeout_ms));
+        let http_client =
+            build_http_client(&file, file.api_timeout_ms, file.http2_prior_knowledge)?;
+
+        // Providers with their own HTTP/2 setting get a dedicated client.
+        let mut provider_clients = HashMap::new();
+        for provider in &file.providers {
+            let http2 = provider
+                .http2_prior_knowledge
+                .unwrap_or(file.http2_prior_knowledge);
+            if http2 != file.http2_prior_knowledge {
+                provider_clients.insert(
+                    provider.name.clone(),
+                    build_http_client(&file, file.api_timeout_ms, http2)?,
+                );
+            }
         }
-
-        let http_client = client_builder.build()?;
         let presets = file.presets.clone();
 
         Ok(Config {
-            inner: Arc::new(ConfigInner { file, http_client }),
+            inner: Arc::new(ConfigInner {
+                file,
+                source,
+                http_client,
+                provider_clients,
+            }),
             presets,
         })
     }
@@ -269,6 +747,88 @@ impl Config {
             .cloned()
             .unwrap_or_default()
     }
+
+    /// Finish reasons that fail a non-streaming response from `tier_name`
+    /// over to the next tier: its `tierRetries` list, else `retryOnFinishReason`.
+    pub fn retry_on_finish_reason(&self, tier_name: &str) -> &[String] {
+        let router = self.router();
+        router
+            .tier_retries
+            .get(tier_name)
+            .and_then(|retry| retry.retry_on_finish_reason.as_deref())
+            .unwrap_or(&router.retry_on_finish_reason)
+    }
+
+    /// Extra headers configured for the `provider,model` route, if any.
+    pub fn tier_headers(&self, provider: &str, model: &str) -> Option<&HashMap<String, String>> {
+        self.router()
+            .tier_headers
+            .get(&format!("{},{}", provider, model))
+    }
+
+    /// EWMA smoothing factor for `tier_name`, falling back to the default.
+    pub fn ewma_alpha(&self, tier_name: &str) -> f64 {
+        self.router()
+            .ewma_alpha
+            .get(tier_name)
+            .copied()
+            .unwrap_or(crate::routing::DEFAULT_EWMA_ALPHA)
+    }
+
+    /// Latency samples kept per tier for quantiles.
+    pub fn latency_window(&self) -> usize {
+        self.router()
+            .latency_window
+            .unwrap_or(crate::routing::DEFAULT_LATENCY_WINDOW)
+    }
+
+    /// Samples a tier needs before its EWMA drives routing.
+    pub fn ewma_min_samples(&self) -> u64 {
+        self.router()
+            .ewma_min_samples
+            .unwrap_or(crate::routing::DEFAULT_MIN_SAMPLES)
+            .max(1)
+    }
+
+    /// Whether reasoning from models not flagged as reasoning reaches the
+    /// client (`EMIT_UNSOLICITED_REASONING`).
+    pub fn emit_unsolicited_reasoning(&self) -> bool {
+        self.inner.file.emit_unsolicited_reasoning
+    }
+
+    /// Whether requests to `provider` have `max_tokens` clamped to the
+    /// model's `max_output_tokens`: its `max_tokens_cap`, else `MAX_TOKENS_CAP`.
+    pub fn max_tokens_cap(&self, provider: &Provider) -> bool {
+        provider
+            .max_tokens_cap
+            .unwrap_or(self.inner.file.max_tokens_cap)
+    }
+
+    /// Whether `ENABLE_FAULT_INJECTION` is set.
+    pub fn fault_injection_enabled(&self) -> bool {
+        self.inner.file.enable_fault_injection
+    }
+
+    /// Faults to inject on `tier_name`, when fault injection is enabled.
+    pub fn fault_injection(&self, tier_name: &str) -> Option<&FaultInjectionConfig> {
+        if !self.fault_injection_enabled() {
+            return None;
+        }
+        self.router().fault_injection.get(tier_name)
+    }
+
+    /// The blackout window excluding `tier_name` at `now`, if any.
+    pub fn active_blackout(
+        &self,
+        tier_name: &str,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Option<&BlackoutWindow> {
+        self.router()
+            .blackout_windows
+            .get(tier_name)?
+            .iter()
+            .find(|window| window.contains(now))
+    }
 }
 
 fn default_port() -> u16 {
@@ -279,6 +839,28 @@ fn default_host() -> String {
     "127.0.0.1".to_string()
 }
 
+fn build_http_client(
+    file: &ConfigFile,
+    timeout_ms: u64,
+    http2_prior_knowledge: bool,
+) -> Result<reqwest::Client> {
+    let mut client_builder = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_millis(timeout_ms))
+        .pool_max_idle_per_host(file.pool_max_idle_per_host)
+        .tcp_keepalive(std::time::Duration::from_secs(30))
+        .tcp_nodelay(true);
+
+    if file.pool_idle_timeout_ms > 0 {
+        client_builder = client_builder
+            .pool_idle_timeout(std::time::Duration::from_millis(file.pool_idle_timeout_ms));
+    }
+    if http2_prior_knowledge {
+        client_builder = client_builder.http2_prior_knowledge();
+    }
+
+    Ok(client_builder.build()?)
+}
+
 fn default_timeout() -> u64 {
     600000 // 10 minutes
 }
@@ -295,6 +877,18 @@ fn default_sse_buffer_size() -> usize {
     32
 }
 
+fn default_upstream_queue_timeout_ms() -> u64 {
+    1000
+}
+
+fn default_max_body_bytes() -> usize {
+    8 * 1024 * 1024
+}
+
+fn default_emit_unsolicited_reasoning() -> bool {
+    true
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -388,6 +982,36 @@ mod tests {
         assert!(p.model_transformers("qwen2.5-coder:latest").is_none());
     }
 
+    #[test]
+    fn model_aliases_rewrite_and_restrict_models() {
+        let p = parse_provider(
+            r#"{
+                "name": "azure",
+                "api_base_url": "https://example.test/v1",
+                "api_key": "x",
+                "models": ["gpt-4o"],
+                "modelAliases": {"claude-3-5-sonnet": "sonnet-deployment"}
+            }"#,
+        );
+        assert_eq!(
+            p.upstream_model("claude-3-5-sonnet"),
+            Some("sonnet-deployment")
+        );
+        assert_eq!(p.upstream_model("gpt-4o"), Some("gpt-4o"));
+        assert_eq!(p.upstream_model("unknown"), None);
+
+        let unaliased = parse_provider(
+            r#"{"name": "plain", "api_base_url": "x", "api_key": "x", "models": ["gpt-4o"]}"#,
+        );
+        assert_eq!(unaliased.upstream_model("gpt-4o"), Some("gpt-4o"));
+        assert_eq!(unaliased.upstream_model("unknown"), None);
+
+        let open = parse_provider(
+            r#"{"name": "open", "api_base_url": "x", "api_key": "x", "models": []}"#,
+        );
+        assert_eq!(open.upstream_model("anything"), Some("anything"));
+    }
+
     #[test]
     fn provider_pricing_parses_with_model_override() {
         let p = parse_provider(
@@ -616,4 +1240,168 @@ mod tests {
         );
         assert_eq!(config.persistence.redis_prefix, "ccr:test");
     }
+
+    #[test]
+    fn persistence_postgres_parses() {
+        let config: ConfigFile = serde_json::from_str(
+            r#"{
+                "Providers": [{
+                    "name": "mock",
+                    "api_base_url": "http://localhost:9999",
+                    "api_key": "x",
+                    "models": ["m"]
+                }],
+                "Router": {"default": "mock,m"},
+                "Persistence": {
+                    "mode": "postgres",
+                    "postgres_url": "postgres://ccr@127.0.0.1/ccr"
+                }
+            }"#,
+        )
+        .expect("parse ConfigFile");
+
+        assert_eq!(config.persistence.mode, PersistenceMode::Postgres);
+        assert_eq!(
+            config.persistence.postgres_url.as_deref(),
+            Some("postgres://ccr@127.0.0.1/ccr")
+        );
+        assert_eq!(config.persistence.postgres_table_prefix, "ccr_");
+    }
+
+    fn router_with_blackouts(blackouts: &str) -> RouterConfig {
+        serde_json::from_str(&format!(
+            r#"{{"default": "mock,m", "blackoutWindows": {}}}"#,
+            blackouts
+        ))
+        .expect("parse RouterConfig")
+    }
+
+    fn at(rfc3339: &str) -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::parse_from_rfc3339(rfc3339)
+            .unwrap()
+            .with_timezone(&chrono::Utc)
+    }
+
+    #[test]
+    fn blackout_window_excludes_tier_only_inside_window() {
+        let router = router_with_blackouts(
+            r#"{"cheap": [{"start": "09:00", "end": "17:00", "timezone": "-05:00",
+                           "days": ["mon", "tue", "wed", "thu", "fri"]}]}"#,
+        );
+        let window = &router.blackout_windows["cheap"][0];
+
+        // Wednesday 10:00 at -05:00.
+        assert!(window.contains(at("2026-10-14T15:00:00Z")));
+        // Wednesday 17:00 at -05:00: end is exclusive.
+        assert!(!window.contains(at("2026-10-14T22:00:00Z")));
+        // Wednesday 08:59 at -05:00.
+        assert!(!window.contains(at("2026-10-14T13:59:00Z")));
+        // Saturday 10:00 at -05:00.
+        assert!(!window.contains(at("2026-10-17T15:00:00Z")));
+        assert_eq!(window.to_string(), "09:00-17:00 -05:00");
+    }
+
+    #[test]
+    fn blackout_window_wraps_past_midnight() {
+        let router = router_with_blackouts(
+            r#"{"maint": [{"start": "23:00", "end": "02:00", "days": ["sun"]}]}"#,
+        );
+        let window = &router.blackout_windows["maint"][0];
+
+        assert!(window.contains(at("2026-10-18T23:30:00Z"))); // Sunday night
+        assert!(window.contains(at("2026-10-19T01:59:00Z"))); // Monday morning
+        assert!(!window.contains(at("2026-10-19T23:30:00Z"))); // Monday night
+        assert!(!window.contains(at("2026-10-18T01:00:00Z"))); // Sunday morning
+    }
+
+    #[test]
+    fn blackout_window_rejects_invalid_values() {
+        for bad in [
+            r#"{"t": [{"start": "9am", "end": "17:00"}]}"#,
+            r#"{"t": [{"start": "09:00", "end": "17:00", "timezone": "Mars/Base"}]}"#,
+            r#"{"t": [{"start": "09:00", "end": "17:00", "days": ["someday"]}]}"#,
+        ] {
+            let parsed: Result<RouterConfig, _> = serde_json::from_str(&format!(
+                r#"{{"default": "mock,m", "blackoutWindows": {}}}"#,
+                bad
+            ));
+            assert!(parsed.is_err(), "expected error for {bad}");
+        }
+    }
+
+    #[test]
+    fn hedge_accepts_delay_ms_spelling() {
+        let router: RouterConfig =
+            serde_json::from_str(r#"{"default": "mock,m", "hedge": {"delay_ms": 250}}"#)
+                .expect("parse RouterConfig");
+        assert_eq!(
+            router.hedging.hedge_after(),
+            Some(std::time::Duration::from_millis(250))
+        );
+        assert_eq!(router.hedging.max_concurrent, 4);
+    }
+
+    #[test]
+    fn http2_override_gets_dedicated_client() {
+        let raw = serde_json::json!({
+            "Providers": ["shared", "http1", "h2"].map(|name| serde_json::json!({
+                "name": name,
+                "api_base_url": "http://localhost:9999",
+                "api_key": "x",
+                "models": ["m"],
+                "http2_prior_knowledge": match name {
+                    "http1" => serde_json::json!(false),
+                    "h2" => serde_json::json!(true),
+                    _ => serde_json::Value::Null,
+                }
+            })),
+            "Router": {"default": "shared,m"},
+            "HTTP2_PRIOR_KNOWLEDGE": true
+        });
+        let temp = tempfile::NamedTempFile::new().expect("temp config file");
+        std::fs::write(temp.path(), raw.to_string()).expect("write config file");
+        let config = Config::from_file(temp.path().to_str().expect("config path"))
+            .expect("load Config from file");
+
+        let dedicated = |name: &str| {
+            let provider = config
+                .providers()
+                .iter()
+                .find(|p| p.name == name)
+                .expect("provider");
+            !std::ptr::eq(config.http_client_for(provider), config.http_client())
+        };
+        assert!(!dedicated("shared"));
+        assert!(dedicated("http1"));
+        assert!(!dedicated("h2"), "matches the global setting");
+    }
+
+    #[test]
+    fn error_classification_matches_status_and_body() {
+        let raw = serde_json::json!({
+            "Providers": [{
+                "name": "gateway",
+                "api_base_url": "http://localhost:9999",
+                "api_key": "x",
+                "models": ["m"],
+                "error_classification": [
+                    {"regex": "^529 ", "class": "rate_limit"},
+                    {"regex": "localhost", "class": "terminal"}
+                ]
+            }],
+            "Router": {"default": "gateway,m"}
+        });
+        let temp = tempfile::NamedTempFile::new().expect("temp config file");
+        std::fs::write(temp.path(), raw.to_string()).expect("write config file");
+        let config = Config::from_file(temp.path().to_str().expect("config path"))
+            .expect("load Config from file");
+        let provider = &config.providers()[0];
+
+        assert_eq!(
+            provider.classify_status_error(529, "overloaded"),
+            Some(ErrorClass::RateLimit)
+        );
+        // The request URL is not part of the matched text.
+        assert_eq!(provider.classify_status_error(500, "boom"), None);
+    }
 }
@@ -0,0 +1,88 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Where the config JSON comes from: a file, stdin, or an HTTP(S) URL.
+
+use anyhow::{Context, Result};
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+
+/// How long a config fetch over HTTP may take before startup fails.
+pub const CONFIG_FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Origin of the config, as given to `--config`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigSource {
+    /// A path on disk.
+    File(String),
+    /// Standard input (`--config -`). It can only be read once, so a config
+    /// from stdin cannot be reloaded.
+    Stdin,
+    /// An `http://` or `https://` URL, fetched with an optional
+    /// `Authorization` header value.
+    Url {
+        url: String,
+        authorization: Option<String>,
+    },
+}
+
+impl ConfigSource {
+    /// Interpret a `--config` value. `authorization` only applies to URLs.
+    pub fn parse(spec: &str, authorization: Option<String>) -> Self {
+        if spec == "-" {
+            ConfigSource::Stdin
+        } else if spec.starts_with("http://") || spec.starts_with("https://") {
+            ConfigSource::Url {
+                url: spec.to_string(),
+                authorization,
+            }
+        } else {
+            ConfigSource::File(shellexpand::tilde(spec).into_owned())
+        }
+    }
+
+    /// Read the raw config text.
+    pub async fn read(&self) -> Result<String> {
+        match self {
+            ConfigSource::File(path) => std::fs::read_to_string(path)
+                .context(format!("Failed to read config file: {}", path)),
+            ConfigSource::Stdin => {
+                let mut content = String::new();
+                tokio::io::stdin()
+                    .read_to_string(&mut content)
+                    .await
+                    .context("Failed to read config from stdin")?;
+                Ok(content)
+            }
+            ConfigSource::Url { url, authorization } => {
+                let client = reqwest::Client::builder()
+                    .timeout(CONFIG_FETCH_TIMEOUT)
+                    .build()?;
+                let mut request = client.get(url);
+                if let Some(value) = authorization {
+                    request = request.header(reqwest::header::AUTHORIZATION, value);
+                }
+                let response = request
+                    .send()
+                    .await
+                    .context(format!("Failed to fetch config from {}", url))?;
+                let status = response.status();
+                if !status.is_success() {
+                    anyhow::bail!("Fetching config from {} returned {}", url, status);
+                }
+                response
+                    .text()
+                    .await
+                    .context(format!("Failed to read config body from {}", url))
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigSource::File(path) => write!(f, "{}", path),
+            ConfigSource::Stdin => write!(f, "stdin"),
+            ConfigSource::Url { url, .. } => write!(f, "{}", url),
+        }
+    }
+}
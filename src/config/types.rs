@@ -3,6 +3,7 @@ use serde::de::{self, SeqAccess, Visitor};
 use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::HashMap;
 use std::fmt;
+use std::sync::OnceLock;
 
 use super::PresetConfig;
 
@@ -233,6 +234,39 @@ impl ModelPricing {
     }
 }
 
+/// Context window and capabilities of one model. Unknown fields are left
+/// out of `/v1/models`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ModelMetadata {
+    /// Context window in tokens.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_length: Option<u64>,
+    /// Largest completion the model produces, in tokens.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_output_tokens: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supports_tools: Option<bool>,
+    /// Whether the model accepts image input.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supports_vision: Option<bool>,
+    /// Whether the model is expected to return reasoning. Unset falls back
+    /// to the model name (`reasoner`, `r1`, `thinking`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supports_reasoning: Option<bool>,
+}
+
+/// Sampling parameters for one model. Defaults fill in values the client
+/// left out; `max_temperature` lowers values the model would reject.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct ModelSampling {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_temperature: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_temperature: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_top_p: Option<f64>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Provider {
     pub name: String,
@@ -248,10 +282,27 @@ pub struct Provider {
     #[serde(default, alias = "modelPricing")]
     pub model_pricing: HashMap<String, ModelPricing>,
 
+    /// Optional per-model context and capability info, reported by
+    /// `/v1/models`.
+    #[serde(default, alias = "modelMetadata")]
+    pub model_metadata: HashMap<String, ModelMetadata>,
+
+    /// Optional per-model sampling defaults and limits, applied to every
+    /// request routed to the model.
+    #[serde(default, alias = "modelSampling")]
+    pub model_sampling: HashMap<String, ModelSampling>,
+
+    /// Route model names rewritten to the name this provider serves before
+    /// dispatch (e.g. `{"claude-3-5-sonnet": "my-sonnet-deployment"}`).
+    #[serde(default, alias = "modelAliases")]
+    pub model_aliases: HashMap<String, String>,
+
     /// Upstream API protocol for this provider.
     ///
     /// - `openai` (default): send OpenAI-compatible `/chat/completions` requests.
     /// - `anthropic`: send Anthropic-compatible `/messages` requests.
+    /// - `openai_responses`: like `openai`, but `/v1/responses` requests are
+    ///   forwarded natively to `/responses`.
     #[serde(default)]
     pub protocol: ProviderProtocol,
 
@@ -261,6 +312,29 @@ pub struct Provider {
     #[serde(default)]
     pub anthropic_version: Option<String>,
 
+    /// Beta features sent as `anthropic-beta` to this `protocol=anthropic`
+    /// provider (e.g. `["prompt-caching-2024-07-31"]`).
+    #[serde(default)]
+    pub anthropic_beta: Vec<String>,
+
+    /// Forward the client's own `anthropic-beta` header as well, merged
+    /// with `anthropic_beta`.
+    #[serde(default)]
+    pub forward_anthropic_beta: bool,
+
+    /// Azure OpenAI conventions for `protocol=openai`: requests go to
+    /// `{api_base_url}/openai/deployments/{model}/chat/completions` with an
+    /// `api-version` query parameter, and the key is sent as `api-key`.
+    /// The model in a route is the deployment name.
+    #[serde(default)]
+    pub azure: Option<AzureConfig>,
+
+    /// Moonshot/Kimi context caching for `protocol=openai`: requests carry
+    /// the cache id so the provider serves the cached prefix, and the cached
+    /// tokens it reports are recorded as cache reads.
+    #[serde(default)]
+    pub kimi_context_cache: Option<KimiContextCacheConfig>,
+
     /// Authentication header style for `protocol=anthropic`.
     ///
     /// Defaults to `x-api-key`, matching Anthropic. Set to `authorization`
@@ -276,11 +350,14 @@ pub struct Provider {
     #[serde(default)]
     pub tier_name: Option<String>,
 
-    /// Extra headers to include in requests to this provider.
+    /// Extra headers to include in requests to this provider, also accepted
+    /// as `headers`. Merged after the built-in headers, so an entry can
+    /// replace one (e.g. `Content-Type`).
     ///
     /// Useful for providers that require specific headers (e.g., `User-Agent`
-    /// for Kimi's coding agent identity check).
-    #[serde(default)]
+    /// for Kimi's coding agent identity check, `x-portkey-provider` for
+    /// Portkey, or Azure's `api-version`).
+    #[serde(default, alias = "headers")]
     pub extra_headers: Option<std::collections::HashMap<String, String>>,
 
     /// When true (default), proactively skip this tier if upstream reports
@@ -295,18 +372,226 @@ pub struct Provider {
     /// non-streaming mode causes excessive latency (e.g. Gemini).
     #[serde(default)]
     pub allow_streaming: bool,
+
+    /// Request timeout for this provider in milliseconds, overriding the
+    /// global `API_TIMEOUT_MS`. Lets a fast tier fail over quickly while a
+    /// slow reasoning tier keeps a longer deadline. Streams are bounded by it
+    /// only until their response headers arrive.
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+
+    /// SSE channel capacity, in chunks, for streams from this provider.
+    /// Unset uses the global `SSE_BUFFER_SIZE`.
+    #[serde(default)]
+    pub sse_buffer_size: Option<usize>,
+
+    /// When true (default), streams to this `protocol=openai` provider ask
+    /// for a final usage chunk with `stream_options.include_usage`. Set to
+    /// `false` for gateways that reject the field; stream usage is then
+    /// estimated locally.
+    #[serde(default = "default_stream_usage")]
+    pub stream_usage: bool,
+
+    /// Speak HTTP/2 to this provider without negotiating it first. Unset
+    /// follows the global `HTTP2_PRIOR_KNOWLEDGE`; `false` keeps normal
+    /// negotiation for gateways that only speak HTTP/1.1.
+    #[serde(default)]
+    pub http2_prior_knowledge: Option<bool>,
+
+    /// Minimum `max_completion_tokens` sent to reasoning models on this
+    /// provider. A smaller client `max_tokens` is raised to this floor so
+    /// reasoning does not consume the whole budget before the answer.
+    #[serde(default)]
+    pub min_completion_tokens_for_reasoning: Option<u32>,
+
+    /// Clamp `max_tokens` to the model's `max_output_tokens` from
+    /// `model_metadata`. Unset follows the global `MAX_TOKENS_CAP`.
+    #[serde(default)]
+    pub max_tokens_cap: Option<bool>,
+
+    /// How an error returned inside an HTTP 200 body is recognized.
+    #[serde(default)]
+    pub soft_errors: SoftErrorConfig,
+
+    /// Extra streaming `delta` fields that carry reasoning, for providers
+    /// that use neither `reasoning_content` nor `reasoning` (e.g.
+    /// `["thinking"]`). Checked in order when the standard field is absent.
+    #[serde(default)]
+    pub reasoning_field_aliases: Vec<String>,
+
+    /// Extra streaming `delta` fields that carry answer text, checked in
+    /// order when `content` is absent.
+    #[serde(default)]
+    pub content_field_aliases: Vec<String>,
+
+    /// Top-level request fields sent as explicit `null` when unset, for
+    /// providers that reject an omitted field (e.g. `["max_tokens"]`).
+    /// Applies to OpenAI-protocol requests.
+    #[serde(default)]
+    pub explicit_null_fields: Vec<String>,
+
+    /// Rules that classify a failed attempt by its error message, checked in
+    /// order. The first match overrides the status-based handling.
+    #[serde(default)]
+    pub error_classification: Vec<ErrorClassificationRule>,
+}
+
+impl Provider {
+    /// Class of the first `error_classification` rule matching `message`.
+    pub fn classify_error(&self, message: &str) -> Option<ErrorClass> {
+        self.error_classification
+            .iter()
+            .find(|rule| rule.matches(message))
+            .map(|rule| rule.class)
+    }
+
+    /// Classify an upstream HTTP error by its status and body, matched as
+    /// `"<status> <body>"`.
+    pub fn classify_status_error(&self, status: u16, body: &str) -> Option<ErrorClass> {
+        self.classify_error(&format!("{status} {body}"))
+    }
+}
+
+/// How a failed attempt is handled once its error message is classified.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorClass {
+    /// Retry the tier with backoff, then cascade (the default for non-429s).
+    Retryable,
+    /// Stop the cascade and return the error to the client.
+    Terminal,
+    /// Back the tier off as if it had returned 429, then cascade.
+    RateLimit,
+    /// The tier's credentials were rejected: skip its remaining retries and
+    /// cascade.
+    Auth,
+}
+
+/// One `error_classification` entry. Exactly one of `contains`
+/// (case-insensitive substring) and `regex` is set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorClassificationRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contains: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regex: Option<String>,
+    pub class: ErrorClass,
+    /// `regex`, compiled on first match; `None` if the pattern is invalid,
+    /// which `validate` reports.
+    #[serde(skip)]
+    compiled: OnceLock<Option<regex::Regex>>,
+}
+
+impl ErrorClassificationRule {
+    fn matches(&self, message: &str) -> bool {
+        if let Some(needle) = &self.contains {
+            return message.to_lowercase().contains(&needle.to_lowercase());
+        }
+        let Some(pattern) = &self.regex else {
+            return false;
+        };
+        self.compiled
+            .get_or_init(|| regex::Regex::new(pattern).ok())
+            .as_ref()
+            .is_some_and(|re| re.is_match(message))
+    }
 }
 
 fn default_honor_ratelimit_headers() -> bool {
     true
 }
 
+fn default_stream_usage() -> bool {
+    true
+}
+
+/// Azure OpenAI settings for a provider.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AzureConfig {
+    /// Sent as the `api-version` query parameter. Default: `2024-10-21`.
+    #[serde(default = "default_azure_api_version")]
+    pub api_version: String,
+}
+
+fn default_azure_api_version() -> String {
+    "2024-10-21".to_string()
+}
+
+/// Moonshot/Kimi context cache used by a provider.
+///
+/// ```json
+/// "kimi_context_cache": { "cache_id": "cache-abc123", "reset_ttl_secs": 3600 }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KimiContextCacheConfig {
+    /// Cache created through Moonshot's caching API, sent as
+    /// `X-Msh-Context-Cache`.
+    pub cache_id: String,
+    /// Extend the cache's lifetime to this many seconds on every request,
+    /// sent as `X-Msh-Context-Cache-Reset-TTL`. Unset leaves it unchanged.
+    #[serde(default)]
+    pub reset_ttl_secs: Option<u64>,
+}
+
+/// Detection of provider errors returned with HTTP 200.
+///
+/// A response is a soft error when one of `error_paths` resolves to a
+/// non-empty value and the body carries no `choices`, `content`, or
+/// `output`. The attempt then fails and the request cascades.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SoftErrorConfig {
+    /// Default: true.
+    #[serde(default = "default_soft_errors_enabled")]
+    pub enabled: bool,
+    /// JSON Pointers to error fields. A value that is null, `false`, `0`, or
+    /// empty does not count. Default: `["/error"]`.
+    #[serde(default = "default_soft_error_paths")]
+    pub error_paths: Vec<String>,
+}
+
+impl Default for SoftErrorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_soft_errors_enabled(),
+            error_paths: default_soft_error_paths(),
+        }
+    }
+}
+
+fn default_soft_errors_enabled() -> bool {
+    true
+}
+
+fn default_soft_error_paths() -> Vec<String> {
+    vec!["/error".to_string()]
+}
+
 impl Provider {
     /// Resolve model-specific pricing, falling back to the provider default.
     pub fn pricing_for_model(&self, model: &str) -> Option<&ModelPricing> {
         self.model_pricing.get(model).or(self.pricing.as_ref())
     }
 
+    /// Sampling defaults and limits for `model`, if configured.
+    pub fn sampling_for_model(&self, model: &str) -> Option<&ModelSampling> {
+        self.model_sampling.get(model)
+    }
+
+    /// Context and capability info for `model`, if configured.
+    pub fn metadata_for_model(&self, model: &str) -> Option<&ModelMetadata> {
+        self.model_metadata.get(model)
+    }
+
+    /// Name sent upstream for the route model `model`: its alias, or `model`
+    /// itself. `None` when `model` is neither aliased nor listed in `models`;
+    /// a provider with no `models` serves any model.
+    pub fn upstream_model<'a>(&'a self, model: &'a str) -> Option<&'a str> {
+        if let Some(target) = self.model_aliases.get(model) {
+            return Some(target);
+        }
+        (self.models.is_empty() || self.models.iter().any(|known| known == model)).then_some(model)
+    }
+
     /// Get the provider-level transformer chain, or an empty slice if none.
     pub fn provider_transformers(&self) -> &[TransformerEntry] {
         self.transformer
@@ -330,16 +615,47 @@ pub enum ProviderProtocol {
     #[default]
     Openai,
     Anthropic,
+    /// OpenAI-compatible provider that also serves the Responses API.
+    /// `/v1/responses` requests are forwarded to its `/responses` endpoint
+    /// unchanged; other traffic uses `/chat/completions` as for `openai`.
+    #[serde(rename = "openai_responses", alias = "openai-responses")]
+    OpenaiResponses,
 }
 
 /// Configuration for web search routing.
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WebSearchConfig {
-    /// Enable [search] tag detection
+    /// Enable search trigger detection
     #[serde(default)]
     pub enabled: bool,
     /// Provider,model for search-enabled requests
     pub search_provider: Option<String>,
+    /// Tokens in message text that trigger search routing.
+    #[serde(default = "default_web_search_tags")]
+    pub tags: Vec<String>,
+    /// Remove the trigger tags before forwarding. When false the search
+    /// provider sees the original text.
+    #[serde(default = "default_web_search_strip_tags")]
+    pub strip_tags: bool,
+}
+
+fn default_web_search_tags() -> Vec<String> {
+    vec!["[search]".to_string(), "[web]".to_string()]
+}
+
+fn default_web_search_strip_tags() -> bool {
+    true
+}
+
+impl Default for WebSearchConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            search_provider: None,
+            tags: default_web_search_tags(),
+            strip_tags: true,
+        }
+    }
 }
 
 /// Acquisition strategies for the experimental GP reranker.
@@ -433,6 +749,7 @@ pub enum PersistenceMode {
     #[default]
     None,
     Redis,
+    Postgres,
 }
 
 /// Runtime persistence configuration.
@@ -451,6 +768,27 @@ pub struct PersistenceConfig {
     /// Prefix for Redis keys used by CCR-Rust persistence.
     #[serde(default = "default_redis_prefix")]
     pub redis_prefix: String,
+
+    /// Postgres connection string used when mode = `postgres`.
+    ///
+    /// Example: `postgres://ccr@127.0.0.1/ccr`
+    #[serde(default)]
+    pub postgres_url: Option<String>,
+
+    /// Prefix for the Postgres tables used by CCR-Rust persistence.
+    /// Lowercase letters, digits, and underscores only.
+    #[serde(default = "default_postgres_table_prefix")]
+    pub postgres_table_prefix: String,
+
+    /// Share 429 backoffs and exhausted quotas with other instances through
+    /// Redis (`mode = redis` only), so a fleet stops sending to a limited
+    /// provider together. Default: false.
+    #[serde(default)]
+    pub shared_rate_limits: bool,
+
+    /// How often the fleet's rate-limit state is pulled from Redis.
+    #[serde(default = "default_shared_rate_limit_sync_ms")]
+    pub shared_rate_limit_sync_ms: u64,
 }
 
 impl Default for PersistenceConfig {
@@ -459,10 +797,78 @@ impl Default for PersistenceConfig {
             mode: PersistenceMode::None,
             redis_url: None,
             redis_prefix: default_redis_prefix(),
+            postgres_url: None,
+            postgres_table_prefix: default_postgres_table_prefix(),
+            shared_rate_limits: false,
+            shared_rate_limit_sync_ms: default_shared_rate_limit_sync_ms(),
         }
     }
 }
 
+/// Per-client request rate limit on the proxy endpoints.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IngressRateLimitConfig {
+    /// Sustained rate each client may send.
+    pub requests_per_minute: u32,
+
+    /// Requests a client may send back to back before the rate applies.
+    /// Defaults to `requests_per_minute`.
+    #[serde(default)]
+    pub burst: Option<u32>,
+}
+
+impl IngressRateLimitConfig {
+    /// Bucket capacity, falling back to `requests_per_minute`.
+    pub fn burst(&self) -> u32 {
+        self.burst.unwrap_or(self.requests_per_minute)
+    }
+}
+
+/// Range a client may choose its stream's SSE buffer size from with the
+/// `x-ccr-sse-buffer` request header.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SseBufferOverrideConfig {
+    pub min: usize,
+    pub max: usize,
+}
+
+impl SseBufferOverrideConfig {
+    /// `requested` clamped into `[min, max]`.
+    pub fn clamp(&self, requested: usize) -> usize {
+        requested.clamp(self.min, self.max)
+    }
+}
+
+/// Periodic provider connectivity check for `GET /ready`.
+///
+/// Every `interval_ms` each provider's `api_base_url` is requested; any HTTP
+/// answer within `timeout_ms` counts as reachable. `/ready` answers 200 while
+/// at least one provider was reachable in the last interval.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ReadinessCheckConfig {
+    pub interval_ms: u64,
+    #[serde(default = "default_readiness_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_retryable_statuses() -> Vec<u16> {
+    vec![408, 429, 500, 502, 503, 504, 529]
+}
+
+fn default_readiness_timeout_ms() -> u64 {
+    2000
+}
+
+impl ReadinessCheckConfig {
+    pub fn interval(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.interval_ms)
+    }
+
+    pub fn timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.timeout_ms)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RouterConfig {
     pub default: String,
@@ -487,6 +893,40 @@ pub struct RouterConfig {
     #[serde(rename = "ignoreDirect")]
     pub ignore_direct: bool,
 
+    /// Report fields CCR dropped or changed on a request in an
+    /// `x-ccr-warnings` response header (a JSON array of strings).
+    /// Default: false.
+    #[serde(default)]
+    #[serde(rename = "warningsHeader")]
+    pub warnings_header: bool,
+
+    /// Report the transformer chain and the model name sent upstream on
+    /// successful responses, in `x-ccr-transformers` and
+    /// `x-ccr-model-resolved`. Default: false.
+    #[serde(default)]
+    #[serde(rename = "routeHeaders", alias = "route_headers")]
+    pub route_headers: bool,
+
+    /// List every upstream attempt of a cascaded request, with its tier and
+    /// duration, in a `Server-Timing` response header. Default: false.
+    #[serde(default)]
+    #[serde(rename = "serverTimingHeader")]
+    pub server_timing_header: bool,
+
+    /// Upstream finish reasons (e.g. `content_filter`) that make a
+    /// non-streaming response fail over to the next tier instead of being
+    /// returned. `tierRetries` entries can override it per tier.
+    #[serde(default)]
+    #[serde(rename = "retryOnFinishReason")]
+    pub retry_on_finish_reason: Vec<String>,
+
+    /// Upstream HTTP statuses that count as retryable: the tier is retried
+    /// and the request cascades. Any other non-2xx status is returned to the
+    /// client at once, unless an `error_classification` rule matches it.
+    #[serde(default = "default_retryable_statuses")]
+    #[serde(rename = "retryableStatuses")]
+    pub retryable_statuses: Vec<u16>,
+
     /// Explicit tier ordering for cascading fallback.
     /// If present, overrides automatic tier construction from default/background/think.
     #[serde(default)]
@@ -526,6 +966,369 @@ pub struct RouterConfig {
     #[serde(default)]
     #[serde(rename = "gpRouting")]
     pub gp_routing: GpRoutingRuntimeConfig,
+
+    /// How `system`-role entries inside `messages` are rewritten before a
+    /// request reaches an Anthropic-protocol provider, which rejects them.
+    #[serde(default)]
+    #[serde(rename = "systemMessageMode")]
+    pub system_message_mode: SystemMessageMode,
+
+    /// Daily windows during which a tier is not routed to. Keys are tier
+    /// names, as in `tierRetries`.
+    #[serde(default)]
+    #[serde(rename = "blackoutWindows")]
+    pub blackout_windows: HashMap<String, Vec<BlackoutWindow>>,
+
+    /// Pin conversations to the tier that last served them.
+    #[serde(default)]
+    #[serde(rename = "stickySessions")]
+    pub sticky_sessions: StickySessionConfig,
+
+    /// Per-tier EWMA smoothing factor in (0, 1]. Keys are tier names, as in
+    /// `tierRetries`. Unlisted tiers use 0.3.
+    #[serde(default)]
+    #[serde(rename = "ewmaAlpha", alias = "ewma_alpha")]
+    pub ewma_alpha: HashMap<String, f64>,
+
+    /// Samples a tier needs before its EWMA is used for ordering. Tiers below
+    /// this are tried ahead of measured tiers. Default: 3.
+    #[serde(default)]
+    #[serde(rename = "ewmaMinSamples", alias = "min_samples")]
+    pub ewma_min_samples: Option<u64>,
+
+    /// Relax the EWMA of tiers that have had no samples for a while toward a
+    /// neutral baseline, so a stale low estimate stops winning. Off when unset.
+    #[serde(default)]
+    #[serde(rename = "ewmaDecay", alias = "ewma_decay")]
+    pub ewma_decay: Option<EwmaDecayConfig>,
+
+    /// Extra request headers per `provider,model` route, merged after the
+    /// provider's `extra_headers`.
+    #[serde(default)]
+    #[serde(rename = "tierHeaders", alias = "tier_headers")]
+    pub tier_headers: HashMap<String, HashMap<String, String>>,
+
+    /// Recent successful-attempt latencies kept per tier for p50/p95/p99.
+    /// Default: 256.
+    #[serde(default)]
+    #[serde(rename = "latencyWindow")]
+    pub latency_window: Option<usize>,
+
+    /// Race the next tier against a slow one instead of waiting for it to
+    /// fail. Off unless `hedge_after_ms` is set.
+    #[serde(default, alias = "hedge")]
+    pub hedging: HedgingConfig,
+
+    /// Only route to healthy tiers, and fail fast with 503 when there are
+    /// none instead of cascading through unhealthy ones.
+    #[serde(default)]
+    #[serde(rename = "requireHealthy", alias = "require_healthy")]
+    pub require_healthy: RequireHealthyConfig,
+
+    /// Frontend assumed for `/v1/messages` requests whose headers and body
+    /// give no hint of the client. Unset rejects them with 400.
+    #[serde(default)]
+    #[serde(rename = "defaultFrontend", alias = "default_frontend")]
+    pub default_frontend: Option<crate::frontend::FrontendType>,
+
+    /// Default route per frontend (`codex`, `claude_code`). When a request
+    /// names no explicit `provider,model`, its frontend's route is tried first
+    /// instead of `default`.
+    #[serde(default)]
+    #[serde(rename = "frontendDefaults", alias = "frontend_defaults")]
+    pub frontend_defaults: HashMap<crate::frontend::FrontendType, String>,
+
+    /// Synthetic delays and failures per tier name, for exercising client
+    /// retry and failover. Ignored unless `ENABLE_FAULT_INJECTION` is set.
+    #[serde(default)]
+    #[serde(rename = "faultInjection", alias = "fault_injection")]
+    pub fault_injection: HashMap<String, FaultInjectionConfig>,
+}
+
+/// Hedged requests: when a tier has not answered within `hedge_after_ms`, the
+/// next tier is tried alongside it and the first success wins.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HedgingConfig {
+    /// Delay before firing a hedge. Unset disables hedging.
+    #[serde(default, alias = "delay_ms")]
+    pub hedge_after_ms: Option<u64>,
+    /// Hedged attempts allowed in flight across all requests. Default: 4.
+    #[serde(default = "default_max_concurrent_hedges")]
+    pub max_concurrent: usize,
+}
+
+fn default_max_concurrent_hedges() -> usize {
+    4
+}
+
+impl Default for HedgingConfig {
+    fn default() -> Self {
+        Self {
+            hedge_after_ms: None,
+            max_concurrent: default_max_concurrent_hedges(),
+        }
+    }
+}
+
+impl HedgingConfig {
+    pub fn hedge_after(&self) -> Option<std::time::Duration> {
+        self.hedge_after_ms.map(std::time::Duration::from_millis)
+    }
+}
+
+/// Time-based EWMA decay for idle tiers. Once a tier has gone
+/// `decay_after_secs` without a sample, its EWMA moves toward the baseline,
+/// closing half the remaining gap every `half_life_secs`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EwmaDecayConfig {
+    /// Idle time, in seconds, before decay starts.
+    #[serde(alias = "decay_after")]
+    pub decay_after_secs: u64,
+    /// Default: `decay_after_secs`.
+    #[serde(default)]
+    pub half_life_secs: Option<u64>,
+    /// Latency, in seconds, idle tiers decay toward. Default: the slowest
+    /// EWMA among the measured tiers being ordered.
+    #[serde(default)]
+    pub baseline_secs: Option<f64>,
+}
+
+impl EwmaDecayConfig {
+    pub fn decay_after(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.decay_after_secs)
+    }
+
+    pub fn half_life(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.half_life_secs.unwrap_or(self.decay_after_secs))
+    }
+}
+
+/// Strict routing mode. A tier is unhealthy while it is rate limited, inside
+/// a blackout window, or has failed `failure_threshold` attempts in a row
+/// within the last `cooldown_secs`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequireHealthyConfig {
+    /// Skip unhealthy tiers and return 503 when none are left. Default: false.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Consecutive failures that mark a tier unhealthy. Default: 3.
+    #[serde(default = "default_unhealthy_failure_threshold")]
+    pub failure_threshold: u64,
+    /// How long after its last failure a failing tier stays unhealthy, in
+    /// seconds. Default: 30.
+    #[serde(default = "default_unhealthy_cooldown_secs")]
+    pub cooldown_secs: u64,
+}
+
+/// Faults injected into every attempt on one tier, before it is dispatched.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FaultInjectionConfig {
+    /// Delay added before each attempt, in milliseconds.
+    #[serde(default)]
+    pub delay_ms: u64,
+    /// Probability in [0, 1] that an attempt fails with a synthetic 503.
+    #[serde(default)]
+    pub error_rate: f64,
+    /// Probability in [0, 1] that an attempt fails with a synthetic 429.
+    #[serde(default)]
+    pub rate_limit_rate: f64,
+}
+
+/// A failure chosen by [`FaultInjectionConfig::roll`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InjectedFault {
+    ServiceUnavailable,
+    RateLimited,
+}
+
+impl FaultInjectionConfig {
+    /// Pick the fault, if any, for one attempt. A single draw covers both
+    /// rates, so they add up rather than overlap.
+    pub fn roll<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> Option<InjectedFault> {
+        let draw: f64 = rng.gen();
+        if draw < self.rate_limit_rate {
+            Some(InjectedFault::RateLimited)
+        } else if draw < self.rate_limit_rate + self.error_rate {
+            Some(InjectedFault::ServiceUnavailable)
+        } else {
+            None
+        }
+    }
+}
+
+fn default_unhealthy_failure_threshold() -> u64 {
+    3
+}
+
+fn default_unhealthy_cooldown_secs() -> u64 {
+    30
+}
+
+impl Default for RequireHealthyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            failure_threshold: default_unhealthy_failure_threshold(),
+            cooldown_secs: default_unhealthy_cooldown_secs(),
+        }
+    }
+}
+
+impl RequireHealthyConfig {
+    pub fn cooldown(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.cooldown_secs)
+    }
+}
+
+/// Sticky-session routing keyed by the `x-ccr-session` request header.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StickySessionConfig {
+    /// Try a session's pinned tier first. Default: false.
+    #[serde(default)]
+    pub enabled: bool,
+    /// How long a pin lasts after the session's last success, in seconds.
+    #[serde(default = "default_sticky_ttl_secs")]
+    pub ttl_secs: u64,
+}
+
+fn default_sticky_ttl_secs() -> u64 {
+    1800
+}
+
+impl Default for StickySessionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl_secs: default_sticky_ttl_secs(),
+        }
+    }
+}
+
+impl StickySessionConfig {
+    pub fn ttl(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.ttl_secs)
+    }
+}
+
+/// Blackout window as written in the config file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RawBlackoutWindow {
+    start: String,
+    end: String,
+    #[serde(default)]
+    timezone: Option<String>,
+    #[serde(default)]
+    days: Vec<String>,
+}
+
+/// A daily `HH:MM` time range during which a tier is excluded from routing.
+///
+/// `end` is exclusive; an `end` earlier than `start` wraps past midnight, and
+/// `start == end` covers the whole day. `timezone` is a fixed UTC offset such
+/// as `"+09:00"` (default `"UTC"`). `days` restricts the window to the listed
+/// weekdays (`"mon"`..`"sun"`), matched against the day the window starts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "RawBlackoutWindow", into = "RawBlackoutWindow")]
+pub struct BlackoutWindow {
+    start: chrono::NaiveTime,
+    end: chrono::NaiveTime,
+    offset: chrono::FixedOffset,
+    days: Vec<chrono::Weekday>,
+}
+
+impl BlackoutWindow {
+    /// Whether `now` falls inside this window.
+    pub fn contains(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        use chrono::Datelike;
+
+        let local = now.with_timezone(&self.offset);
+        let time = local.time();
+        let today = local.weekday();
+        let on_day = |day: chrono::Weekday| self.days.is_empty() || self.days.contains(&day);
+
+        if self.start < self.end {
+            on_day(today) && time >= self.start && time < self.end
+        } else if self.start > self.end {
+            // Overnight: the early-morning part belongs to yesterday's window.
+            (on_day(today) && time >= self.start) || (on_day(today.pred()) && time < self.end)
+        } else {
+            on_day(today)
+        }
+    }
+}
+
+impl fmt::Display for BlackoutWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}-{} {}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M"),
+            self.offset
+        )
+    }
+}
+
+impl TryFrom<RawBlackoutWindow> for BlackoutWindow {
+    type Error = String;
+
+    fn try_from(raw: RawBlackoutWindow) -> Result<Self, Self::Error> {
+        let parse_time = |value: &str| {
+            chrono::NaiveTime::parse_from_str(value, "%H:%M")
+                .map_err(|_| format!("invalid blackout time '{value}', expected HH:MM"))
+        };
+        let offset = match raw.timezone.as_deref() {
+            None | Some("UTC") | Some("utc") | Some("Z") => {
+                chrono::FixedOffset::east_opt(0).expect("zero offset is valid")
+            }
+            Some(tz) => tz.parse::<chrono::FixedOffset>().map_err(|_| {
+                format!("invalid blackout timezone '{tz}', expected UTC or an offset like +09:00")
+            })?,
+        };
+        let days = raw
+            .days
+            .iter()
+            .map(|day| {
+                day.parse::<chrono::Weekday>()
+                    .map_err(|_| format!("invalid blackout day '{day}'"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            start: parse_time(&raw.start)?,
+            end: parse_time(&raw.end)?,
+            offset,
+            days,
+        })
+    }
+}
+
+impl From<BlackoutWindow> for RawBlackoutWindow {
+    fn from(window: BlackoutWindow) -> Self {
+        Self {
+            start: window.start.format("%H:%M").to_string(),
+            end: window.end.format("%H:%M").to_string(),
+            timezone: Some(window.offset.to_string()),
+            days: window
+                .days
+                .iter()
+                .map(|day| day.to_string().to_lowercase())
+                .collect(),
+        }
+    }
+}
+
+/// Handling for `system`-role messages found inside the `messages` array.
+///
+/// OpenAI clients (e.g. Codex) send the system prompt as a message, while the
+/// Anthropic Messages API only accepts it as the top-level `system` field.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SystemMessageMode {
+    /// Append the message text to the top-level `system` field.
+    #[default]
+    Merge,
+    /// Keep the text in the conversation as a leading user turn.
+    User,
 }
 
 /// Per-tier retry limits and backoff configuration.
@@ -560,6 +1363,45 @@ pub struct TierRetryConfig {
         alias = "streamIdleTimeoutMs"
     )]
     pub stream_idle_timeout_ms: u64,
+
+    /// Randomization applied to each computed backoff delay.
+    #[serde(default)]
+    pub jitter: BackoffJitter,
+
+    /// Finish reasons that fail a non-streaming response from this tier over
+    /// to the next one. Unset uses the Router-wide `retryOnFinishReason`.
+    #[serde(default, alias = "retryOnFinishReason")]
+    pub retry_on_finish_reason: Option<Vec<String>>,
+}
+
+/// Jitter strategy for retry backoff, so concurrent failures do not retry in lockstep.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BackoffJitter {
+    /// Use the computed delay as-is.
+    #[default]
+    None,
+    /// Pick uniformly from `[0, delay]`.
+    Full,
+    /// Keep half the delay and pick the other half uniformly from `[0, delay / 2]`.
+    Equal,
+}
+
+impl BackoffJitter {
+    /// Apply this jitter strategy to `delay_ms` using `rng`.
+    pub fn apply<R: rand::Rng + ?Sized>(self, delay_ms: f64, rng: &mut R) -> f64 {
+        if delay_ms <= 0.0 {
+            return 0.0;
+        }
+        match self {
+            BackoffJitter::None => delay_ms,
+            BackoffJitter::Full => rng.gen_range(0.0..=delay_ms),
+            BackoffJitter::Equal => {
+                let half = delay_ms / 2.0;
+                half + rng.gen_range(0.0..=half)
+            }
+        }
+    }
 }
 
 /// Request batching configuration.
@@ -583,6 +1425,8 @@ impl Default for TierRetryConfig {
             max_backoff_ms: default_max_backoff_ms(),
             stream_first_event_timeout_ms: default_stream_first_event_timeout_ms(),
             stream_idle_timeout_ms: default_stream_idle_timeout_ms(),
+            jitter: BackoffJitter::default(),
+            retry_on_finish_reason: None,
         }
     }
 }
@@ -617,6 +1461,7 @@ impl TierRetryConfig {
     ///
     /// # Returns
     /// The scaled backoff duration, clamped to `[base_backoff_ms, max_backoff_ms]`
+    /// before `jitter` is applied
     pub fn backoff_duration_with_ewma(
         &self,
         attempt: usize,
@@ -648,7 +1493,10 @@ impl TierRetryConfig {
         let clamped_ms = scaled_delay_ms.min(self.max_backoff_ms as f64);
         let min_backoff = self.base_backoff_ms as f64 * 0.5;
 
-        std::time::Duration::from_millis(clamped_ms.max(min_backoff) as u64)
+        let delay_ms = clamped_ms.max(min_backoff);
+        let jittered_ms = self.jitter.apply(delay_ms, &mut rand::thread_rng());
+
+        std::time::Duration::from_millis(jittered_ms as u64)
     }
 
     pub fn stream_first_event_timeout(&self) -> std::time::Duration {
@@ -692,10 +1540,18 @@ fn default_batching_max_latency_ms() -> u64 {
     100
 }
 
+fn default_shared_rate_limit_sync_ms() -> u64 {
+    1000
+}
+
 fn default_redis_prefix() -> String {
     "ccr-rust:persistence:v1".to_string()
 }
 
+fn default_postgres_table_prefix() -> String {
+    "ccr_".to_string()
+}
+
 fn default_gp_max_candidates() -> usize {
     // Matches the vendored GP encoder's bounded backend capacity. Keeping this
     // literal here avoids making config parsing depend on the optional crate.
@@ -792,6 +1648,8 @@ mod backoff_tests {
             max_backoff_ms: 500,
             stream_first_event_timeout_ms: default_stream_first_event_timeout_ms(),
             stream_idle_timeout_ms: default_stream_idle_timeout_ms(),
+            jitter: BackoffJitter::None,
+            retry_on_finish_reason: None,
         };
         // Even with slow tier scaling, should clamp to max
         let duration = config.backoff_duration_with_ewma(2, Some(5.0));
@@ -815,6 +1673,62 @@ mod backoff_tests {
         assert_eq!(d2.as_millis(), 800);
     }
 
+    #[test]
+    fn injected_faults_follow_configured_rates() {
+        use rand::SeedableRng;
+
+        let faults = FaultInjectionConfig {
+            delay_ms: 0,
+            error_rate: 0.2,
+            rate_limit_rate: 0.1,
+        };
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        let (mut errors, mut limited) = (0, 0);
+        for _ in 0..10_000 {
+            match faults.roll(&mut rng) {
+                Some(InjectedFault::ServiceUnavailable) => errors += 1,
+                Some(InjectedFault::RateLimited) => limited += 1,
+                None => {}
+            }
+        }
+        assert!((1800..=2200).contains(&errors), "errors {errors}");
+        assert!((850..=1150).contains(&limited), "rate limited {limited}");
+
+        let never = FaultInjectionConfig::default();
+        assert!((0..1000).all(|_| never.roll(&mut rng).is_none()));
+        let always = FaultInjectionConfig {
+            error_rate: 1.0,
+            ..Default::default()
+        };
+        assert!((0..1000).all(|_| always.roll(&mut rng) == Some(InjectedFault::ServiceUnavailable)));
+    }
+
+    #[test]
+    fn jittered_backoff_stays_within_bounds() {
+        use rand::SeedableRng;
+
+        for attempt in 0..64u64 {
+            let mut rng = rand::rngs::StdRng::seed_from_u64(attempt);
+            let full = BackoffJitter::Full.apply(400.0, &mut rng);
+            assert!((0.0..=400.0).contains(&full), "full jitter {full}");
+            let equal = BackoffJitter::Equal.apply(400.0, &mut rng);
+            assert!((200.0..=400.0).contains(&equal), "equal jitter {equal}");
+        }
+        assert_eq!(
+            BackoffJitter::None.apply(400.0, &mut rand::thread_rng()),
+            400.0
+        );
+
+        let config = TierRetryConfig {
+            jitter: BackoffJitter::Equal,
+            ..default_retry_config()
+        };
+        for _ in 0..32 {
+            let ms = config.backoff_duration_with_ewma(2, Some(1.0)).as_millis();
+            assert!((200..=400).contains(&ms), "equal jitter backoff {ms}");
+        }
+    }
+
     #[test]
     fn ewma_backoff_never_below_base() {
         let config = default_retry_config();
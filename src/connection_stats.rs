@@ -0,0 +1,84 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//! Upstream connection reuse accounting.
+//!
+//! reqwest does not report whether a request went over a pooled connection,
+//! but every response carries the local and remote address of the TCP
+//! connection it arrived on. That pair identifies the connection, so dispatch
+//! calls `record_connection` with each upstream response: a pair no earlier
+//! response used is a new connection, any other is a reused one. Requests
+//! that share the pool without being counted (readiness checks, probes,
+//! warm-ups) call `note_connection`, so the request that next reuses their
+//! connection is not mistaken for opening it.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+use hyper::client::connect::HttpInfo;
+use parking_lot::Mutex;
+
+use crate::metrics::record_upstream_connection;
+
+/// Pruning starts once this many connections are remembered.
+const PRUNE_AT: usize = 4096;
+
+/// Connections unused this long are forgotten when pruning; well past any
+/// pool idle timeout, so a forgotten connection has been closed.
+const FORGET_AFTER: Duration = Duration::from_secs(15 * 60);
+
+/// Connections seen so far, by (local, remote) address, with when a response
+/// last arrived on each.
+static SEEN_CONNECTIONS: LazyLock<Mutex<HashMap<(SocketAddr, SocketAddr), Instant>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Mark the connection `response` arrived on as seen. Returns whether it was
+/// new, or `None` when the response carries no connection info.
+fn claim(response: &reqwest::Response) -> Option<bool> {
+    let info = response.extensions().get::<HttpInfo>()?;
+    Some(claim_addrs(info.local_addr(), info.remote_addr()))
+}
+
+fn claim_addrs(local: SocketAddr, remote: SocketAddr) -> bool {
+    let now = Instant::now();
+    let mut seen = SEEN_CONNECTIONS.lock();
+    if seen.len() >= PRUNE_AT {
+        seen.retain(|_, last_used| now.duration_since(*last_used) < FORGET_AFTER);
+    }
+    seen.insert((local, remote), now).is_none()
+}
+
+/// Count one upstream response for `provider` as arriving on a new or a
+/// reused connection.
+pub fn record_connection(provider: &str, response: &reqwest::Response) {
+    if let Some(new) = claim(response) {
+        record_upstream_connection(provider, new);
+    }
+}
+
+/// Note the connection of a response that is not itself counted, so a later
+/// request reusing it counts as reused.
+pub fn note_connection(response: &reqwest::Response) {
+    let _ = claim(response);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connections_are_told_apart_by_address_pair() {
+        let remote: SocketAddr = "10.0.0.1:443".parse().unwrap();
+        let other_remote: SocketAddr = "10.0.0.2:443".parse().unwrap();
+        let local: SocketAddr = "10.0.0.9:50001".parse().unwrap();
+        let second_local: SocketAddr = "10.0.0.9:50002".parse().unwrap();
+
+        assert!(claim_addrs(local, remote));
+        assert!(!claim_addrs(local, remote));
+        // A second connection to the same host, e.g. from another provider
+        // or a concurrent request, is new in its own right.
+        assert!(claim_addrs(second_local, remote));
+        assert!(claim_addrs(local, other_remote));
+        assert!(!claim_addrs(second_local, remote));
+    }
+}
@@ -21,7 +21,8 @@ use std::time::{Duration, Instant};
 use tracing::info;
 
 use crate::metrics::{
-    FrontendMetrics, TierLatency, TierThroughput, TierTokenDrift, TierUsage, UsageSummary,
+    FrontendMetrics, ProviderFilter, TierLatency, TierThroughput, TierTokenDrift, TierUsage,
+    UsageSummary,
 };
 
 /// Aggregated dashboard data fetched from the CCR-Rust API.
@@ -49,8 +50,21 @@ pub type SharedDashboardState = Arc<RwLock<Option<DashboardData>>>;
 ///
 /// Returns a `SharedDashboardState` that holds the latest data fetched from
 /// `http://{host}:{port}/v1/usage`, `/v1/latencies`, and `/v1/token-drift`.
-pub fn spawn_dashboard_fetcher(host: String, port: u16) -> SharedDashboardState {
-    info!(%host, port, "starting dashboard data fetcher");
+///
+/// When `providers` is set, usage and latencies are filtered server-side via
+/// `?providers=`; per-tier drift and throughput rows are filtered locally.
+pub fn spawn_dashboard_fetcher(
+    host: String,
+    port: u16,
+    providers: Option<String>,
+) -> SharedDashboardState {
+    info!(%host, port, ?providers, "starting dashboard data fetcher");
+    let filter = providers
+        .as_deref()
+        .map(ProviderFilter::parse)
+        .unwrap_or_default();
+    let provider_query: Vec<(&str, String)> =
+        providers.into_iter().map(|p| ("providers", p)).collect();
     let state: SharedDashboardState = Arc::new(RwLock::new(None));
     let state_clone = Arc::clone(&state);
 
@@ -74,6 +88,7 @@ pub fn spawn_dashboard_fetcher(host: String, port: u16) -> SharedDashboardState
             let usage_url = format!("{}/v1/usage", base_url);
             let usage_result: Option<UsageSummary> = client
                 .get(&usage_url)
+                .query(&provider_query)
                 .send()
                 .ok()
                 .and_then(|r| r.json().ok());
@@ -82,6 +97,7 @@ pub fn spawn_dashboard_fetcher(host: String, port: u16) -> SharedDashboardState
             let latencies_url = format!("{}/v1/latencies", base_url);
             let latencies_result: Option<Vec<TierLatency>> = client
                 .get(&latencies_url)
+                .query(&provider_query)
                 .send()
                 .ok()
                 .and_then(|r| r.json().ok());
@@ -92,7 +108,12 @@ pub fn spawn_dashboard_fetcher(host: String, port: u16) -> SharedDashboardState
                 .get(&drift_url)
                 .send()
                 .ok()
-                .and_then(|r| r.json().ok());
+                .and_then(|r| r.json().ok())
+                .map(|rows: Vec<TierTokenDrift>| {
+                    rows.into_iter()
+                        .filter(|d| filter.matches(&d.tier))
+                        .collect()
+                });
 
             // Fetch frontend metrics data
             let frontend_url = format!("{}/v1/frontend-metrics", base_url);
@@ -108,7 +129,12 @@ pub fn spawn_dashboard_fetcher(host: String, port: u16) -> SharedDashboardState
                 .get(&throughput_url)
                 .send()
                 .ok()
-                .and_then(|r| r.json().ok());
+                .and_then(|r| r.json().ok())
+                .map(|rows: Vec<TierThroughput>| {
+                    rows.into_iter()
+                        .filter(|t| filter.matches(&t.tier))
+                        .collect()
+                });
 
             // Update shared state if we got usage data (the core metric)
             if let Some(usage) = usage_result {
@@ -242,7 +268,7 @@ fn sync_ui_state(shared: &SharedDashboardState, ui_state: &mut UiState) {
     }
 }
 
-pub fn run_dashboard(host: String, port: u16) -> Result<()> {
+pub fn run_dashboard(host: String, port: u16, providers: Option<String>) -> Result<()> {
     info!(%host, port, "launching TUI dashboard");
     // Setup terminal
     enable_raw_mode()?;
@@ -252,7 +278,7 @@ pub fn run_dashboard(host: String, port: u16) -> Result<()> {
     let mut terminal = Terminal::new(backend)?;
 
     // Start background data fetcher thread
-    let shared_state = spawn_dashboard_fetcher(host.clone(), port);
+    let shared_state = spawn_dashboard_fetcher(host.clone(), port, providers);
 
     // Run the UI loop
     let tick_rate = Duration::from_millis(250);
@@ -329,9 +355,19 @@ fn ui(f: &mut ratatui::Frame, host: &str, port: u16, state: &UiState, session_in
     let drift_widget = create_token_drift_table(state);
     f.render_widget(drift_widget, main_chunks[1]);
 
+    // Split the middle section into frontend metrics and tier health
+    let middle_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(55), Constraint::Percentage(45)].as_ref())
+        .split(main_chunks[2]);
+
     // Frontend Metrics section
     let frontend_widget = create_frontend_metrics_table(state);
-    f.render_widget(frontend_widget, main_chunks[2]);
+    f.render_widget(frontend_widget, middle_chunks[0]);
+
+    // Tier Health section
+    let health_widget = create_tier_health_table(state);
+    f.render_widget(health_widget, middle_chunks[1]);
 
     // Split the bottom section into left (session info) and right (tier stats) panels
     let body_chunks = Layout::default()
@@ -553,6 +589,98 @@ fn get_drift_style(drift_pct: f64) -> Style {
     }
 }
 
+/// Routing health of a tier and its color:
+/// - circuit open: Red
+/// - backing off from a 429, or quota exhausted until reset: Yellow
+/// - Otherwise: Green (healthy)
+fn tier_health(latency: &TierLatency) -> (&'static str, Color) {
+    if latency.circuit_open {
+        ("circuit open", Color::Red)
+    } else if latency.backoff_remaining_seconds.is_some() {
+        ("backing off", Color::Yellow)
+    } else if latency.rate_limit_remaining == Some(0) && latency.rate_limit_reset_seconds.is_some()
+    {
+        ("quota exhausted", Color::Yellow)
+    } else {
+        ("healthy", Color::Green)
+    }
+}
+
+/// Format an optional number of seconds, or a dash when unset.
+fn format_optional_secs(secs: Option<f64>) -> String {
+    secs.map(|s| format!("{:.0}", s.ceil()))
+        .unwrap_or_else(|| "—".to_string())
+}
+
+/// Create a table widget showing per-tier rate-limit and circuit-breaker state.
+fn create_tier_health_table(state: &UiState) -> Table<'_> {
+    let header_cells = ["Tier", "Status", "Remaining", "Reset (s)", "Backoff (s)"]
+        .into_iter()
+        .map(|title| {
+            Cell::from(title).style(
+                Style::default()
+                    .fg(Color::White)
+                    .add_modifier(Modifier::BOLD),
+            )
+        });
+    let header = Row::new(header_cells)
+        .style(Style::default().bg(Color::Blue))
+        .height(1);
+
+    let rows: Vec<Row> = if state.tier_latencies.is_empty() {
+        vec![Row::new(vec![
+            Cell::from("No data available"),
+            Cell::from(""),
+            Cell::from(""),
+            Cell::from(""),
+            Cell::from(""),
+        ])
+        .height(1)]
+    } else {
+        let mut latencies: Vec<&TierLatency> = state.tier_latencies.iter().collect();
+        latencies.sort_by(|a, b| a.tier.cmp(&b.tier));
+        latencies
+            .into_iter()
+            .map(|latency| {
+                let (status, color) = tier_health(latency);
+                let mut status_style = Style::default().fg(color);
+                if color == Color::Red {
+                    status_style = status_style.add_modifier(Modifier::BOLD);
+                }
+                let remaining = latency
+                    .rate_limit_remaining
+                    .map(|r| format_number(u64::from(r)))
+                    .unwrap_or_else(|| "—".to_string());
+
+                Row::new(vec![
+                    Cell::from(latency.tier.clone()),
+                    Cell::from(status).style(status_style),
+                    Cell::from(remaining),
+                    Cell::from(format_optional_secs(latency.rate_limit_reset_seconds)),
+                    Cell::from(format_optional_secs(latency.backoff_remaining_seconds)),
+                ])
+                .height(1)
+            })
+            .collect()
+    };
+
+    let block = Block::default().borders(Borders::ALL).title("Tier Health");
+
+    Table::new(
+        rows,
+        [
+            Constraint::Percentage(24),
+            Constraint::Percentage(24),
+            Constraint::Percentage(16),
+            Constraint::Percentage(18),
+            Constraint::Percentage(18),
+        ],
+    )
+    .header(header)
+    .block(block)
+    .column_spacing(1)
+}
+
 /// Create a table widget showing per-tier statistics with latency, requests, tokens, and duration.
 fn create_tier_stats_table(state: &UiState) -> Table<'_> {
     let header_cells = vec![
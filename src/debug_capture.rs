@@ -13,11 +13,18 @@
 //!     "providers": ["minimax"],
 //!     "output_dir": "~/.ccr-rust/captures",
 //!     "max_files": 100,
+//!     "sample_rate": 0.1,
+//!     "max_disk_bytes": 52428800,
 //!     "include_headers": false
 //!   }
 //! }
 //! ```
 //!
+//! With `"trigger": "on_drift"`, only successful non-streaming responses
+//! whose upstream input token count differs from the local estimate by at
+//! least `drift_threshold_pct` percent are kept, with the drift recorded in
+//! the capture's `metadata.token_drift_pct`.
+//!
 //! Captured files are stored as JSON with timestamped filenames:
 //! `ccr_capture_v1_{provider}_{tier_name}_{timestamp}_{request_id}.json`
 
@@ -41,6 +48,38 @@ const MAX_CAPTURE_FILE_BYTES: usize = 4 * 1024 * 1024;
 const HARD_MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
 const MAX_LIST_FILES: usize = 100;
 const MAX_LIST_BYTES: u64 = 16 * 1024 * 1024;
+const REDACTED: &str = "[REDACTED]";
+
+/// Header, body field, and query parameter names whose values are always
+/// redacted before a capture is written. Matched case-insensitively.
+const ALWAYS_REDACTED: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "x-api-key",
+    "api-key",
+    "api_key",
+    "apikey",
+    "x-goog-api-key",
+    "access_token",
+    "client_secret",
+];
+
+/// Query parameters that carry credentials in URLs only (Gemini `key`, SAS
+/// `sig`), too generic to redact as body fields.
+const REDACTED_QUERY_PARAMS: &[&str] = &["key", "sig", "token"];
+
+/// Which interactions a capture is kept for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureTrigger {
+    /// Failed requests, plus successful ones with `capture_success`, subject
+    /// to `sample_rate`.
+    #[default]
+    All,
+    /// Only successful non-streaming requests whose token drift reaches
+    /// `drift_threshold_pct`. `sample_rate` and `capture_success` are ignored.
+    OnDrift,
+}
 
 /// Configuration for debug capture.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -63,6 +102,17 @@ pub struct DebugCaptureConfig {
     #[serde(default = "default_max_files")]
     pub max_files: usize,
 
+    /// Fraction of matching requests to capture, from 0.0 to 1.0. Each
+    /// request is sampled independently. Out-of-range values are clamped.
+    #[serde(default = "default_sample_rate")]
+    pub sample_rate: f64,
+
+    /// Cap on the total size of CCR-owned capture files. When a new capture
+    /// would exceed it, the oldest captures are pruned first. Unset means
+    /// only `max_files` bounds retention.
+    #[serde(default)]
+    pub max_disk_bytes: Option<u64>,
+
     /// Include raw HTTP headers in capture.
     #[serde(default)]
     pub include_headers: bool,
@@ -74,6 +124,20 @@ pub struct DebugCaptureConfig {
     /// Maximum response body size to capture (bytes).
     #[serde(default = "default_max_body_size")]
     pub max_body_size: usize,
+
+    /// Which interactions to keep.
+    #[serde(default)]
+    pub trigger: CaptureTrigger,
+
+    /// Absolute token drift, in percent of the upstream input count, at which
+    /// the `on_drift` trigger keeps a capture.
+    #[serde(default = "default_drift_threshold_pct")]
+    pub drift_threshold_pct: f64,
+
+    /// Extra header, body field, and query parameter names whose values are
+    /// redacted before writing, on top of the built-in credential names.
+    #[serde(default)]
+    pub redact_fields: Vec<String>,
 }
 
 impl Default for DebugCaptureConfig {
@@ -83,9 +147,14 @@ impl Default for DebugCaptureConfig {
             providers: vec![],
             output_dir: default_output_dir(),
             max_files: default_max_files(),
+            sample_rate: default_sample_rate(),
+            max_disk_bytes: None,
             include_headers: false,
             capture_success: default_capture_success(),
             max_body_size: default_max_body_size(),
+            trigger: CaptureTrigger::default(),
+            drift_threshold_pct: default_drift_threshold_pct(),
+            redact_fields: vec![],
         }
     }
 }
@@ -98,6 +167,10 @@ fn default_max_files() -> usize {
     DEFAULT_MAX_FILES
 }
 
+fn default_sample_rate() -> f64 {
+    1.0
+}
+
 fn default_capture_success() -> bool {
     false
 }
@@ -106,11 +179,16 @@ fn default_max_body_size() -> usize {
     1024 * 1024 // 1MB default
 }
 
+fn default_drift_threshold_pct() -> f64 {
+    20.0
+}
+
 /// Captured request/response pair.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CapturedInteraction {
-    /// Unique request ID.
-    pub request_id: u64,
+    /// Request ID: the `x-ccr-request-id` the client received, or a
+    /// process-local sequence number for captures made outside a request.
+    pub request_id: String,
 
     /// Provider name (e.g., "minimax").
     pub provider: String,
@@ -174,6 +252,8 @@ pub struct DebugCapture {
     config: DebugCaptureConfig,
     output_path: PathBuf,
     provider_filter: HashSet<String>,
+    /// Lowercased names whose values are redacted before writing.
+    redacted_names: HashSet<String>,
     retention_lock: Mutex<()>,
 }
 
@@ -209,6 +289,25 @@ impl DebugCapture {
             config.max_body_size = HARD_MAX_BODY_BYTES;
         }
 
+        if config.sample_rate.is_nan() {
+            warn!("Debug capture sample_rate is not a number; capturing every request");
+            config.sample_rate = default_sample_rate();
+        } else if !(0.0..=1.0).contains(&config.sample_rate) {
+            warn!(
+                requested = config.sample_rate,
+                "Debug capture sample_rate must be between 0.0 and 1.0; clamping"
+            );
+            config.sample_rate = config.sample_rate.clamp(0.0, 1.0);
+        }
+        if config.drift_threshold_pct.is_nan() || config.drift_threshold_pct < 0.0 {
+            warn!(
+                requested = config.drift_threshold_pct,
+                default = default_drift_threshold_pct(),
+                "Debug capture drift_threshold_pct must be a non-negative number; using the default"
+            );
+            config.drift_threshold_pct = default_drift_threshold_pct();
+        }
+
         let output_path = expand_tilde(&config.output_dir);
 
         // Capture is opt-in. Merely parsing or constructing the default
@@ -228,17 +327,38 @@ impl DebugCapture {
 
         let provider_filter: HashSet<String> =
             config.providers.iter().map(|s| s.to_lowercase()).collect();
+        let redacted_names: HashSet<String> = ALWAYS_REDACTED
+            .iter()
+            .map(|name| name.to_string())
+            .chain(config.redact_fields.iter().map(|name| name.to_lowercase()))
+            .collect();
 
         Ok(Self {
             config,
             output_path,
             provider_filter,
+            redacted_names,
             retention_lock: Mutex::new(()),
         })
     }
 
-    /// Check if capture is enabled for a given provider.
+    /// Decide whether to capture a request to `provider`. Requests that pass
+    /// the provider filter are kept with probability `sample_rate`, except
+    /// under the `on_drift` trigger, where every one is a candidate.
     pub fn should_capture(&self, provider: &str) -> bool {
+        self.matches_provider(provider) && (self.captures_on_drift() || self.sampled())
+    }
+
+    fn sampled(&self) -> bool {
+        self.config.sample_rate >= 1.0 || rand::random::<f64>() < self.config.sample_rate
+    }
+
+    /// Whether captures wait for the request's token drift (`on_drift`).
+    pub fn captures_on_drift(&self) -> bool {
+        self.config.trigger == CaptureTrigger::OnDrift
+    }
+
+    fn matches_provider(&self, provider: &str) -> bool {
         if !self.config.enabled {
             debug!("should_capture: disabled globally");
             return false;
@@ -260,8 +380,8 @@ impl DebugCapture {
     }
 
     /// Generate a new request ID.
-    pub fn next_request_id(&self) -> u64 {
-        REQUEST_COUNTER.fetch_add(1, Ordering::SeqCst)
+    pub fn next_request_id(&self) -> String {
+        REQUEST_COUNTER.fetch_add(1, Ordering::SeqCst).to_string()
     }
 
     /// Create a builder with all configured privacy limits applied.
@@ -276,11 +396,19 @@ impl DebugCapture {
         self.config.include_headers
     }
 
-    /// Record a captured interaction.
+    /// Record a captured interaction. A no-op under the `on_drift` trigger;
+    /// see [`Self::record_drift`].
     pub async fn record(&self, interaction: CapturedInteraction) -> Result<()> {
         if !self.config.enabled {
             return Ok(());
         }
+        if self.captures_on_drift() {
+            debug!(
+                "Skipping capture for {} until its token drift is known",
+                interaction.provider
+            );
+            return Ok(());
+        }
 
         // Check if we should capture based on success/failure
         if interaction.success && !self.config.capture_success {
@@ -291,6 +419,53 @@ impl DebugCapture {
             return Ok(());
         }
 
+        self.write(interaction).await
+    }
+
+    /// Record a successful interaction whose upstream input tokens drifted
+    /// `drift_pct` percent from the local estimate, if the trigger is
+    /// `on_drift` and the drift reaches `drift_threshold_pct`. Returns
+    /// whether it was recorded.
+    pub async fn record_drift(
+        &self,
+        mut interaction: CapturedInteraction,
+        drift_pct: f64,
+    ) -> Result<bool> {
+        if !self.config.enabled
+            || !self.captures_on_drift()
+            || drift_pct.abs() < self.config.drift_threshold_pct
+        {
+            return Ok(false);
+        }
+        let metadata = interaction
+            .metadata
+            .get_or_insert_with(|| serde_json::json!({}));
+        if let Some(fields) = metadata.as_object_mut() {
+            fields.insert("token_drift_pct".to_string(), drift_pct.into());
+        }
+        self.write(interaction).await?;
+        Ok(true)
+    }
+
+    /// Scrub credentials from everything a capture stores about the request:
+    /// URL query parameters, header values, and string fields of the body.
+    fn redact(&self, interaction: &mut CapturedInteraction) {
+        interaction.url = redact_query(&interaction.url, &self.redacted_names);
+        for value in [
+            Some(&mut interaction.request_body),
+            interaction.request_headers.as_mut(),
+            interaction.response_headers.as_mut(),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            redact_fields(value, &self.redacted_names);
+        }
+    }
+
+    async fn write(&self, mut interaction: CapturedInteraction) -> Result<()> {
+        self.redact(&mut interaction);
+
         // Generate filename
         let filename = format!(
             "{}{}_{}_{}_{}.json",
@@ -298,7 +473,7 @@ impl DebugCapture {
             sanitize_filename_segment(&interaction.provider),
             sanitize_filename_segment(&interaction.tier_name),
             chrono::Utc::now().format("%Y%m%d_%H%M%S_%f"),
-            interaction.request_id
+            sanitize_filename_segment(&interaction.request_id)
         );
         let filepath = self.output_path.join(&filename);
 
@@ -312,6 +487,9 @@ impl DebugCapture {
                 MAX_CAPTURE_FILE_BYTES
             );
         }
+        if let Some(max_disk_bytes) = self.config.max_disk_bytes {
+            self.prune_to_fit(json.len() as u64, max_disk_bytes).await?;
+        }
         let write_result = (|| -> Result<()> {
             let mut options = OpenOptions::new();
             options.write(true).create_new(true);
@@ -360,24 +538,7 @@ impl DebugCapture {
     /// existing corpus.
     async fn rotate_files(&self) -> Result<()> {
         let _guard = self.retention_lock.lock().await;
-        let entries: Vec<_> = fs::read_dir(&self.output_path)?
-            .filter_map(|e| e.ok())
-            .filter(is_managed_capture_file)
-            .collect();
-
-        // Sort by modification time (oldest first)
-        let mut files_with_time: Vec<_> = entries
-            .into_iter()
-            .filter_map(|e| {
-                e.metadata()
-                    .ok()
-                    .and_then(|m| m.modified().ok())
-                    .map(|t| (e.path(), t))
-            })
-            .collect();
-
-        files_with_time
-            .sort_by(|left, right| left.1.cmp(&right.1).then_with(|| left.0.cmp(&right.0)));
+        let files_with_time = self.managed_files_oldest_first()?;
 
         if files_with_time.len() <= self.config.max_files {
             return Ok(());
@@ -416,20 +577,61 @@ impl DebugCapture {
         Ok(())
     }
 
-    /// List recent captures for a provider.
-    pub fn list_captures(
-        &self,
-        provider: Option<&str>,
-        limit: usize,
-    ) -> Result<Vec<CapturedInteraction>> {
-        let mut captures = Vec::new();
+    /// Delete the oldest CCR-owned captures until `incoming` more bytes fit
+    /// under `max_disk_bytes`.
+    async fn prune_to_fit(&self, incoming: u64, max_disk_bytes: u64) -> Result<()> {
+        if incoming > max_disk_bytes {
+            bail!("debug capture of {incoming} bytes exceeds max_disk_bytes ({max_disk_bytes})");
+        }
+        let _guard = self.retention_lock.lock().await;
+        let files = self.managed_files_oldest_first()?;
+        let mut used: u64 = files.iter().map(|(_, len)| len).sum();
+        for (path, len) in files {
+            if used + incoming <= max_disk_bytes {
+                break;
+            }
+            match fs::remove_file(&path) {
+                Ok(()) => {
+                    used -= len;
+                    debug!("Pruned capture file for disk budget: {}", path.display());
+                }
+                // Already gone, e.g. removed by an operator.
+                Err(error) if error.kind() == std::io::ErrorKind::NotFound => used -= len,
+                Err(error) => {
+                    warn!("Failed to prune capture file {}: {}", path.display(), error);
+                }
+            }
+        }
+        if used + incoming > max_disk_bytes {
+            bail!("debug capture retention could not free space under max_disk_bytes");
+        }
+        Ok(())
+    }
 
+    /// CCR-owned capture files with their sizes, oldest first.
+    fn managed_files_oldest_first(&self) -> Result<Vec<(PathBuf, u64)>> {
+        let mut files: Vec<_> = fs::read_dir(&self.output_path)?
+            .filter_map(|e| e.ok())
+            .filter(is_managed_capture_file)
+            .filter_map(|e| {
+                let metadata = e.metadata().ok()?;
+                Some((e.path(), metadata.modified().ok()?, metadata.len()))
+            })
+            .collect();
+        files.sort_by(|left, right| left.1.cmp(&right.1).then_with(|| left.0.cmp(&right.0)));
+        Ok(files
+            .into_iter()
+            .map(|(path, _, len)| (path, len))
+            .collect())
+    }
+
+    /// CCR-owned capture files, newest first.
+    fn managed_entries_newest_first(&self) -> Result<Vec<fs::DirEntry>> {
         let mut entries: Vec<_> = fs::read_dir(&self.output_path)?
             .filter_map(|e| e.ok())
             .filter(is_managed_capture_file)
             .collect();
 
-        // Sort by modification time (newest first)
         entries.sort_by(|a, b| {
             b.metadata()
                 .and_then(|m| m.modified())
@@ -440,6 +642,49 @@ impl DebugCapture {
                         .unwrap_or(std::time::SystemTime::UNIX_EPOCH),
                 )
         });
+        Ok(entries)
+    }
+
+    /// Find the newest capture with `request_id`. A request that fails over
+    /// leaves one capture per attempt, and sequence ids restart with the
+    /// process, so several captures may share an id.
+    pub fn find_capture(&self, request_id: &str) -> Result<Option<CapturedInteraction>> {
+        let suffix = format!("_{}", sanitize_filename_segment(request_id));
+        for entry in self.managed_entries_newest_first()? {
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.len() > MAX_CAPTURE_FILE_BYTES as u64 {
+                continue;
+            }
+            // The id ends the filename, so most files are skipped without
+            // being read.
+            let path = entry.path();
+            let id_matches = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .is_some_and(|stem| stem.ends_with(&suffix));
+            if !id_matches {
+                continue;
+            }
+            match read_managed_capture(&path) {
+                Ok(capture) if capture.request_id == request_id => return Ok(Some(capture)),
+                Ok(_) => {}
+                Err(e) if was_pruned(&e) => {}
+                Err(e) => warn!("Failed to read capture file {}: {}", path.display(), e),
+            }
+        }
+        Ok(None)
+    }
+
+    /// List recent captures for a provider.
+    pub fn list_captures(
+        &self,
+        provider: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<CapturedInteraction>> {
+        let mut captures = Vec::new();
+        let entries = self.managed_entries_newest_first()?;
 
         let bounded_limit = limit.min(MAX_LIST_FILES);
         let mut listed_bytes = 0_u64;
@@ -462,6 +707,7 @@ impl DebugCapture {
                         captures.push(capture);
                     }
                 }
+                Err(e) if was_pruned(&e) => {}
                 Err(e) => {
                     warn!(
                         "Failed to read capture file {}: {}",
@@ -532,6 +778,185 @@ pub struct CaptureStats {
     pub by_provider: std::collections::HashMap<String, usize>,
 }
 
+/// The parts of a response that `ccr-rust captures replay` compares: status,
+/// content blocks in order, and stop reason. Accepts Anthropic and OpenAI
+/// bodies, either as one JSON document or as an SSE stream.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResponseSummary {
+    pub status: u16,
+    /// One entry per block: `text: ...`, `thinking`, or `tool_use: <name>`.
+    pub content: Vec<String>,
+    pub stop_reason: Option<String>,
+}
+
+impl ResponseSummary {
+    pub fn from_response(status: u16, body: &str) -> Self {
+        let mut summary = Self {
+            status,
+            ..Self::default()
+        };
+        if let Ok(value) = serde_json::from_str::<serde_json::Value>(body) {
+            summary.absorb_message(&value);
+        } else {
+            for line in body.lines() {
+                let Some(data) = line.strip_prefix("data:").map(str::trim) else {
+                    continue;
+                };
+                if let Ok(event) = serde_json::from_str::<serde_json::Value>(data) {
+                    summary.absorb_event(&event);
+                }
+            }
+        }
+        summary
+    }
+
+    /// Human-readable differences from `captured` to `self`; empty when the
+    /// two match.
+    pub fn diff(&self, captured: &Self) -> Vec<String> {
+        let mut changes = Vec::new();
+        if self.status != captured.status {
+            changes.push(format!("status: {} -> {}", captured.status, self.status));
+        }
+        if self.stop_reason != captured.stop_reason {
+            changes.push(format!(
+                "stop_reason: {} -> {}",
+                captured.stop_reason.as_deref().unwrap_or("(none)"),
+                self.stop_reason.as_deref().unwrap_or("(none)")
+            ));
+        }
+        for index in 0..self.content.len().max(captured.content.len()) {
+            let before = captured.content.get(index);
+            let after = self.content.get(index);
+            if before != after {
+                changes.push(format!(
+                    "content[{index}]: {} -> {}",
+                    before.map_or("(missing)".to_string(), |b| preview(b)),
+                    after.map_or("(missing)".to_string(), |b| preview(b))
+                ));
+            }
+        }
+        changes
+    }
+
+    /// A complete Anthropic message or OpenAI chat completion.
+    fn absorb_message(&mut self, value: &serde_json::Value) {
+        if let Some(blocks) = value.get("content").and_then(|c| c.as_array()) {
+            for block in blocks {
+                self.start_block(block);
+                if let Some(text) = block.get("text").and_then(|t| t.as_str()) {
+                    self.push_text(text);
+                }
+            }
+        }
+        if let Some(stop) = value.get("stop_reason").and_then(|s| s.as_str()) {
+            self.stop_reason = Some(stop.to_string());
+        }
+        if let Some(choice) = value.pointer("/choices/0") {
+            if let Some(message) = choice.get("message") {
+                self.absorb_openai_delta(message);
+            }
+            if let Some(finish) = choice.get("finish_reason").and_then(|f| f.as_str()) {
+                self.stop_reason = Some(finish.to_string());
+            }
+        }
+    }
+
+    /// One SSE event: an Anthropic stream event or an OpenAI chunk.
+    fn absorb_event(&mut self, event: &serde_json::Value) {
+        match event.get("type").and_then(|t| t.as_str()) {
+            Some("content_block_start") => {
+                if let Some(block) = event.get("content_block") {
+                    self.start_block(block);
+                }
+            }
+            Some("content_block_delta") => {
+                if let Some(text) = event.pointer("/delta/text").and_then(|t| t.as_str()) {
+                    self.push_text(text);
+                }
+            }
+            Some("message_delta") => {
+                if let Some(stop) = event.pointer("/delta/stop_reason").and_then(|s| s.as_str()) {
+                    self.stop_reason = Some(stop.to_string());
+                }
+            }
+            _ => {
+                if let Some(choice) = event.pointer("/choices/0") {
+                    if let Some(delta) = choice.get("delta") {
+                        self.absorb_openai_delta(delta);
+                    }
+                    if let Some(finish) = choice.get("finish_reason").and_then(|f| f.as_str()) {
+                        self.stop_reason = Some(finish.to_string());
+                    }
+                }
+            }
+        }
+    }
+
+    fn absorb_openai_delta(&mut self, delta: &serde_json::Value) {
+        let reasoning = ["reasoning_content", "reasoning"]
+            .iter()
+            .filter_map(|key| delta.get(key).and_then(|r| r.as_str()))
+            .any(|r| !r.is_empty());
+        if reasoning {
+            self.push_block("thinking".to_string());
+        }
+        if let Some(text) = delta.get("content").and_then(|c| c.as_str()) {
+            self.push_text(text);
+        }
+        for call in delta
+            .get("tool_calls")
+            .and_then(|c| c.as_array())
+            .into_iter()
+            .flatten()
+        {
+            if let Some(name) = call.pointer("/function/name").and_then(|n| n.as_str()) {
+                self.push_block(format!("tool_use: {name}"));
+            }
+        }
+    }
+
+    fn start_block(&mut self, block: &serde_json::Value) {
+        match block.get("type").and_then(|t| t.as_str()) {
+            Some("text") => self.content.push("text: ".to_string()),
+            Some("thinking") | Some("redacted_thinking") => self.push_block("thinking".to_string()),
+            Some("tool_use") => {
+                let name = block.get("name").and_then(|n| n.as_str()).unwrap_or("");
+                self.push_block(format!("tool_use: {name}"));
+            }
+            _ => {}
+        }
+    }
+
+    /// Append to the open text block, starting one if needed.
+    fn push_text(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        match self.content.last_mut() {
+            Some(last) if last.starts_with("text: ") => last.push_str(text),
+            _ => self.content.push(format!("text: {text}")),
+        }
+    }
+
+    /// Add a non-text block; reasoning streamed over several chunks counts
+    /// as one.
+    fn push_block(&mut self, block: String) {
+        if block == "thinking" && self.content.last().is_some_and(|last| last == "thinking") {
+            return;
+        }
+        self.content.push(block);
+    }
+}
+
+fn preview(block: &str) -> String {
+    const MAX_PREVIEW_CHARS: usize = 80;
+    if block.chars().count() <= MAX_PREVIEW_CHARS {
+        return format!("{block:?}");
+    }
+    let head: String = block.chars().take(MAX_PREVIEW_CHARS).collect();
+    format!("{head:?}...")
+}
+
 /// Expand ~ to home directory.
 fn expand_tilde(path: &str) -> PathBuf {
     if let Some(rest) = path.strip_prefix("~/") {
@@ -603,6 +1028,14 @@ fn is_managed_capture_file(entry: &fs::DirEntry) -> bool {
         .is_some_and(|name| name.starts_with(CAPTURE_FILE_PREFIX) && name.ends_with(".json"))
 }
 
+/// Whether a capture read failed because retention removed the file after the
+/// directory was listed.
+fn was_pruned(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<std::io::Error>()
+        .is_some_and(|error| error.kind() == std::io::ErrorKind::NotFound)
+}
+
 fn read_managed_capture(path: &Path) -> Result<CapturedInteraction> {
     let metadata = fs::symlink_metadata(path)?;
     if !metadata.file_type().is_file() {
@@ -625,7 +1058,7 @@ fn read_managed_capture(path: &Path) -> Result<CapturedInteraction> {
 /// Builder for captured interactions.
 #[derive(Debug, Default)]
 pub struct CaptureBuilder {
-    request_id: u64,
+    request_id: String,
     provider: String,
     tier_name: String,
     model: String,
@@ -640,9 +1073,13 @@ pub struct CaptureBuilder {
 }
 
 impl CaptureBuilder {
-    pub fn new(request_id: u64, provider: impl Into<String>, tier_name: impl Into<String>) -> Self {
+    pub fn new(
+        request_id: impl Into<String>,
+        provider: impl Into<String>,
+        tier_name: impl Into<String>,
+    ) -> Self {
         Self {
-            request_id,
+            request_id: request_id.into(),
             provider: provider.into(),
             tier_name: tier_name.into(),
             method: "POST".to_string(),
@@ -651,6 +1088,14 @@ impl CaptureBuilder {
         }
     }
 
+    /// Use the client-facing request ID instead of the generated one.
+    pub fn request_id(mut self, id: Option<&str>) -> Self {
+        if let Some(id) = id {
+            self.request_id = id.to_string();
+        }
+        self
+    }
+
     pub fn model(mut self, model: impl Into<String>) -> Self {
         self.model = model.into();
         self
@@ -752,6 +1197,61 @@ impl CaptureBuilder {
     }
 }
 
+/// Replace string and number values of fields named in `names`, at any
+/// depth. Objects and arrays under such a name are searched, not replaced, so
+/// e.g. a tool schema property called `api_key` survives.
+fn redact_fields(value: &mut serde_json::Value, names: &HashSet<String>) {
+    match value {
+        serde_json::Value::Object(fields) => {
+            for (name, field) in fields.iter_mut() {
+                let scalar = field.is_string() || field.is_number();
+                if scalar && names.contains(&name.to_ascii_lowercase()) {
+                    *field = serde_json::Value::String(REDACTED.to_string());
+                } else {
+                    redact_fields(field, names);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                redact_fields(item, names);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Redact the values of credential query parameters in `url`.
+fn redact_query(url: &str, names: &HashSet<String>) -> String {
+    let Some((base, rest)) = url.split_once('?') else {
+        return url.to_string();
+    };
+    let (query, fragment) = match rest.split_once('#') {
+        Some((query, fragment)) => (query, Some(fragment)),
+        None => (rest, None),
+    };
+    let params: Vec<String> = query
+        .split('&')
+        .map(|param| match param.split_once('=') {
+            Some((name, _)) => {
+                let lower = name.to_ascii_lowercase();
+                if names.contains(&lower) || REDACTED_QUERY_PARAMS.contains(&lower.as_str()) {
+                    format!("{name}={REDACTED}")
+                } else {
+                    param.to_string()
+                }
+            }
+            None => param.to_string(),
+        })
+        .collect();
+    let mut redacted = format!("{base}?{}", params.join("&"));
+    if let Some(fragment) = fragment {
+        redacted.push('#');
+        redacted.push_str(fragment);
+    }
+    redacted
+}
+
 fn truncate_utf8(value: &str, max_bytes: usize) -> (String, bool) {
     if value.len() <= max_bytes {
         return (value.to_string(), false);
@@ -769,7 +1269,7 @@ mod tests {
     use tempfile::tempdir;
 
     fn interaction(request_id: u64) -> CapturedInteraction {
-        CaptureBuilder::new(request_id, "minimax", "ccr-mm")
+        CaptureBuilder::new(request_id.to_string(), "minimax", "ccr-mm")
             .model("MiniMax-M2.5")
             .request_body(serde_json::json!({"request": request_id}))
             .complete(500, r#"{"error": "test"}"#, None, Some("test".to_string()))
@@ -777,7 +1277,7 @@ mod tests {
 
     #[test]
     fn test_capture_builder() {
-        let capture = CaptureBuilder::new(1, "minimax", "ccr-mm")
+        let capture = CaptureBuilder::new("1", "minimax", "ccr-mm")
             .model("MiniMax-M2.5")
             .url("https://api.minimax.chat/v1/chat/completions")
             .request_body(serde_json::json!({"model": "test", "messages": []}))
@@ -794,7 +1294,7 @@ mod tests {
 
     #[test]
     fn test_capture_builder_error() {
-        let capture = CaptureBuilder::new(2, "minimax", "ccr-mm")
+        let capture = CaptureBuilder::new("2", "minimax", "ccr-mm")
             .model("MiniMax-M2.5")
             .url("https://api.minimax.chat/v1/chat/completions")
             .request_body(serde_json::json!({"model": "test"}))
@@ -808,7 +1308,7 @@ mod tests {
     #[test]
     fn test_truncation() {
         let long_body = "x".repeat(2000);
-        let capture = CaptureBuilder::new(3, "minimax", "ccr-mm")
+        let capture = CaptureBuilder::new("3", "minimax", "ccr-mm")
             .request_body(serde_json::json!({}))
             .max_body_size(1000)
             .complete(200, &long_body, None, None);
@@ -819,7 +1319,7 @@ mod tests {
 
     #[test]
     fn test_truncation_preserves_utf8_boundaries() {
-        let capture = CaptureBuilder::new(4, "minimax", "ccr-mm")
+        let capture = CaptureBuilder::new("4", "minimax", "ccr-mm")
             .max_body_size(3)
             .complete(500, "éé", None, Some("failed".to_string()));
 
@@ -829,7 +1329,7 @@ mod tests {
 
     #[test]
     fn test_http_200_with_error_is_not_successful() {
-        let capture = CaptureBuilder::new(5, "minimax", "ccr-mm").complete(
+        let capture = CaptureBuilder::new("5", "minimax", "ccr-mm").complete(
             200,
             r#"{"error":"quota"}"#,
             None,
@@ -865,6 +1365,70 @@ mod tests {
         assert_eq!(capture.response_body, "é");
     }
 
+    #[tokio::test]
+    async fn test_written_capture_redacts_secrets() {
+        let dir = tempdir().unwrap();
+        let capture_mgr = DebugCapture::new(DebugCaptureConfig {
+            enabled: true,
+            output_dir: dir.path().to_string_lossy().to_string(),
+            include_headers: true,
+            redact_fields: vec!["X-Org-Secret".to_string()],
+            ..Default::default()
+        })
+        .unwrap();
+
+        let interaction = capture_mgr
+            .builder("azure", "ccr-az")
+            .url("https://example.openai.azure.com/chat?api-version=2024-10-21&api-key=sk-leak-1")
+            .request_headers(serde_json::json!({
+                "Authorization": "Bearer sk-leak-2",
+                "x-org-secret": "sk-leak-3",
+                "x-request-id": "req-1"
+            }))
+            .request_body(serde_json::json!({
+                "model": "gpt-5.5",
+                "api_key": "sk-leak-4",
+                "tools": [{
+                    "name": "login",
+                    "input_schema": {"properties": {"api_key": {"type": "string"}}}
+                }]
+            }))
+            .complete(500, "{}", None, Some("failed".to_string()));
+        capture_mgr.record(interaction).await.unwrap();
+
+        let entry = fs::read_dir(dir.path()).unwrap().next().unwrap().unwrap();
+        let written = fs::read_to_string(entry.path()).unwrap();
+        assert!(!written.contains("sk-leak"), "secret written: {written}");
+
+        let capture: CapturedInteraction = serde_json::from_str(&written).unwrap();
+        assert_eq!(
+            capture.url,
+            "https://example.openai.azure.com/chat?api-version=2024-10-21&api-key=[REDACTED]"
+        );
+        let headers = capture.request_headers.unwrap();
+        assert_eq!(headers["Authorization"], REDACTED);
+        assert_eq!(headers["x-org-secret"], REDACTED);
+        assert_eq!(headers["x-request-id"], "req-1");
+        assert_eq!(capture.request_body["api_key"], REDACTED);
+        assert_eq!(
+            capture.request_body["tools"][0]["input_schema"]["properties"]["api_key"]["type"],
+            "string"
+        );
+    }
+
+    #[test]
+    fn test_redact_query_keeps_other_params() {
+        let names: HashSet<String> = ALWAYS_REDACTED.iter().map(|n| n.to_string()).collect();
+        assert_eq!(
+            redact_query("https://g.example/v1?alt=sse&key=AIza1#frag", &names),
+            "https://g.example/v1?alt=sse&key=[REDACTED]#frag"
+        );
+        assert_eq!(
+            redact_query("https://g.example/v1", &names),
+            "https://g.example/v1"
+        );
+    }
+
     #[tokio::test]
     async fn test_debug_capture_manager() {
         let dir = tempdir().unwrap();
@@ -884,7 +1448,7 @@ mod tests {
         assert!(!capture_mgr.should_capture("deepseek"));
 
         // Record a capture
-        let interaction = CaptureBuilder::new(1, "minimax", "ccr-mm")
+        let interaction = CaptureBuilder::new("1", "minimax", "ccr-mm")
             .model("MiniMax-M2.5")
             .request_body(serde_json::json!({"test": true}))
             .complete(200, r#"{"result": "ok"}"#, None, None);
@@ -897,6 +1461,106 @@ mod tests {
         assert_eq!(captures[0].provider, "minimax");
     }
 
+    #[tokio::test]
+    async fn test_on_drift_trigger_keeps_only_drifted_successes() {
+        let dir = tempdir().unwrap();
+        let capture_mgr = DebugCapture::new(DebugCaptureConfig {
+            enabled: true,
+            output_dir: dir.path().to_string_lossy().to_string(),
+            sample_rate: 0.0,
+            trigger: CaptureTrigger::OnDrift,
+            drift_threshold_pct: 25.0,
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(capture_mgr.should_capture("minimax"));
+
+        // Errors and plain records are skipped under this trigger.
+        capture_mgr.record(interaction(1)).await.unwrap();
+        let success =
+            |id: &str| CaptureBuilder::new(id, "minimax", "ccr-mm").complete(200, "{}", None, None);
+        assert!(!capture_mgr.record_drift(success("2"), -10.0).await.unwrap());
+        assert!(capture_mgr.record_drift(success("3"), -40.0).await.unwrap());
+
+        let captures = capture_mgr.list_captures(None, 10).unwrap();
+        assert_eq!(captures.len(), 1);
+        assert_eq!(captures[0].request_id, "3");
+        assert_eq!(
+            captures[0].metadata.as_ref().unwrap()["token_drift_pct"],
+            -40.0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_find_capture_by_request_id() {
+        let dir = tempdir().unwrap();
+        let capture_mgr = DebugCapture::new(DebugCaptureConfig {
+            enabled: true,
+            output_dir: dir.path().to_string_lossy().to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        for request_id in [7, 17] {
+            capture_mgr.record(interaction(request_id)).await.unwrap();
+        }
+
+        let found = capture_mgr.find_capture("7").unwrap().unwrap();
+        assert_eq!(found.request_body, serde_json::json!({"request": 7}));
+        assert!(capture_mgr.find_capture("1").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_response_summary_matches_json_and_sse() {
+        let json = serde_json::json!({
+            "content": [
+                {"type": "thinking", "thinking": "hmm"},
+                {"type": "text", "text": "Hello"},
+                {"type": "tool_use", "id": "t1", "name": "read_file", "input": {}}
+            ],
+            "stop_reason": "tool_use"
+        });
+        let sse = [
+            r#"{"type":"content_block_start","index":0,"content_block":{"type":"thinking","thinking":""}}"#,
+            r#"{"type":"content_block_start","index":1,"content_block":{"type":"text","text":""}}"#,
+            r#"{"type":"content_block_delta","index":1,"delta":{"type":"text_delta","text":"Hel"}}"#,
+            r#"{"type":"content_block_delta","index":1,"delta":{"type":"text_delta","text":"lo"}}"#,
+            r#"{"type":"content_block_start","index":2,"content_block":{"type":"tool_use","id":"t1","name":"read_file","input":{}}}"#,
+            r#"{"type":"message_delta","delta":{"stop_reason":"tool_use"}}"#,
+        ]
+        .iter()
+        .map(|event| format!("data: {event}\n\n"))
+        .collect::<String>();
+
+        let from_json = ResponseSummary::from_response(200, &json.to_string());
+        let from_sse = ResponseSummary::from_response(200, &sse);
+        assert_eq!(
+            from_json.content,
+            vec!["thinking", "text: Hello", "tool_use: read_file"]
+        );
+        assert_eq!(from_json, from_sse);
+        assert!(from_sse.diff(&from_json).is_empty());
+    }
+
+    #[test]
+    fn test_response_summary_diff_reports_changes() {
+        let captured = ResponseSummary::from_response(
+            200,
+            r#"{"choices":[{"message":{"role":"assistant","content":"Hi"},"finish_reason":"stop"}]}"#,
+        );
+        let replayed = ResponseSummary::from_response(
+            200,
+            r#"{"choices":[{"message":{"role":"assistant","content":"Hi","tool_calls":[{"id":"c1","type":"function","function":{"name":"ls","arguments":"{}"}}]},"finish_reason":"tool_calls"}]}"#,
+        );
+
+        assert_eq!(
+            replayed.diff(&captured),
+            vec![
+                "stop_reason: stop -> tool_calls".to_string(),
+                "content[1]: (missing) -> \"tool_use: ls\"".to_string(),
+            ]
+        );
+    }
+
     #[test]
     fn test_expand_tilde() {
         let path = expand_tilde("~/.ccr-rust/captures");
@@ -1023,6 +1687,67 @@ mod tests {
         assert!(legacy_symlink.unwrap().symlink_metadata().is_ok());
     }
 
+    #[tokio::test]
+    async fn test_disk_budget_prunes_oldest_captures() {
+        let dir = tempdir().unwrap();
+        let output = dir.path().to_string_lossy().to_string();
+        let size = serde_json::to_string_pretty(&interaction(1)).unwrap().len() as u64;
+        let manager = DebugCapture::new(DebugCaptureConfig {
+            enabled: true,
+            output_dir: output,
+            max_disk_bytes: Some(size * 2 + size / 2),
+            ..Default::default()
+        })
+        .unwrap();
+
+        for request_id in 1..=4 {
+            manager.record(interaction(request_id)).await.unwrap();
+        }
+
+        let mut kept: Vec<String> = manager
+            .list_captures(None, usize::MAX)
+            .unwrap()
+            .iter()
+            .map(|capture| capture.request_id.clone())
+            .collect();
+        kept.sort();
+        assert_eq!(kept, ["3", "4"]);
+        assert_eq!(manager.get_stats().unwrap().total_captures, 2);
+        assert!(manager.find_capture("1").unwrap().is_none());
+
+        let too_small = DebugCapture::new(DebugCaptureConfig {
+            enabled: true,
+            output_dir: dir.path().to_string_lossy().to_string(),
+            max_disk_bytes: Some(size / 2),
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(too_small.record(interaction(5)).await.is_err());
+    }
+
+    #[test]
+    fn test_sample_rate_is_clamped_and_applied() {
+        let dir = tempdir().unwrap();
+        let capture_mgr = DebugCapture::new(DebugCaptureConfig {
+            enabled: true,
+            output_dir: dir.path().to_string_lossy().to_string(),
+            sample_rate: 0.0,
+            ..Default::default()
+        })
+        .unwrap();
+        assert!((0..100).all(|_| !capture_mgr.should_capture("minimax")));
+
+        let capture_mgr = DebugCapture::new(DebugCaptureConfig {
+            enabled: true,
+            output_dir: dir.path().to_string_lossy().to_string(),
+            sample_rate: 1.5,
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(capture_mgr.config.sample_rate, 1.0);
+        assert!((0..100).all(|_| capture_mgr.should_capture("minimax")));
+    }
+
     #[test]
     fn test_unbounded_retention_value_is_replaced() {
         let manager = DebugCapture::new(DebugCaptureConfig {
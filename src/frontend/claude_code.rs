@@ -113,6 +113,8 @@ impl Frontend for ClaudeCodeFrontend {
             .and_then(|v| v.as_f64())
             .map(|v| v as f32);
 
+        let top_p = body.get("top_p").and_then(|v| v.as_f64()).map(|v| v as f32);
+
         let stream = body.get("stream").and_then(|v| v.as_bool());
 
         // Parse tools if present
@@ -167,6 +169,7 @@ impl Frontend for ClaudeCodeFrontend {
             system,
             max_tokens,
             temperature,
+            top_p,
             stream,
             tools,
             tool_choice,
@@ -240,6 +243,9 @@ impl Frontend for ClaudeCodeFrontend {
                         })
                     }
                 },
+                ContentBlock::Audio { audio } => {
+                    serde_json::json!({"type": "audio", "audio": audio})
+                }
             })
             .collect();
 
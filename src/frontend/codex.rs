@@ -267,6 +267,8 @@ impl Frontend for CodexFrontend {
             .and_then(|v| v.as_f64())
             .map(|v| v as f32);
 
+        let top_p = body.get("top_p").and_then(|v| v.as_f64()).map(|v| v as f32);
+
         let stream = body.get("stream").and_then(|v| v.as_bool());
 
         // Parse tools if present
@@ -275,12 +277,16 @@ impl Frontend for CodexFrontend {
         // Parse tool_choice if present
         let tool_choice = body.get("tool_choice").cloned();
 
-        // Parse stop sequences if present
-        let stop_sequences = body.get("stop").and_then(|v| v.as_array()).map(|arr| {
-            arr.iter()
-                .filter_map(|v| v.as_str().map(|s| s.to_string()))
-                .collect::<Vec<_>>()
-        });
+        // Parse stop sequences if present (a single string or an array)
+        let stop_sequences = match body.get("stop") {
+            Some(Value::String(stop)) => Some(vec![stop.clone()]),
+            Some(Value::Array(arr)) => Some(
+                arr.iter()
+                    .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                    .collect::<Vec<_>>(),
+            ),
+            _ => None,
+        };
 
         // Collect extra params that are not standard
         let standard_keys: &[&str] = &[
@@ -322,6 +328,7 @@ impl Frontend for CodexFrontend {
             system: None, // OpenAI uses system message in messages array
             max_tokens,
             temperature,
+            top_p,
             stream,
             tools,
             tool_choice,
@@ -338,6 +345,7 @@ impl Frontend for CodexFrontend {
         let mut content = String::new();
         let mut reasoning_content = String::new();
         let mut tool_calls: Vec<Value> = Vec::new();
+        let mut audio: Option<Value> = None;
 
         for block in &response.content {
             match block {
@@ -368,6 +376,9 @@ impl Frontend for CodexFrontend {
                     }
                     reasoning_content.push_str(thinking);
                 }
+                ContentBlock::Audio { audio: block_audio } => {
+                    audio = Some(block_audio.clone());
+                }
             }
         }
 
@@ -384,8 +395,10 @@ impl Frontend for CodexFrontend {
         });
 
         // Build the message object
+        // Chat completion replies are always `assistant`, whatever role the
+        // upstream or a response transformer put on the internal response.
         let mut message = serde_json::json!({
-            "role": response.role,
+            "role": "assistant",
             "content": content
         });
 
@@ -405,6 +418,10 @@ impl Frontend for CodexFrontend {
             }
         }
 
+        if let Some(audio) = audio {
+            message["audio"] = audio;
+        }
+
         // Add tool_calls if present
         if !tool_calls.is_empty() {
             message["tool_calls"] = Value::Array(tool_calls);
@@ -2,11 +2,13 @@
 //! Frontend detection helpers for Codex and Claude Code clients.
 
 use axum::http::{header::USER_AGENT, HeaderMap};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use tracing::debug;
 
 /// Frontend type inferred from headers and request body.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum FrontendType {
     /// OpenAI-compatible Codex frontend.
     Codex,
@@ -14,41 +16,72 @@ pub enum FrontendType {
     ClaudeCode,
 }
 
-/// Detect the request frontend using lightweight format heuristics.
-///
-/// Rules (in priority order):
-/// 1. Definitive header signals win: `anthropic-*` → ClaudeCode, `codex` User-Agent → Codex
-/// 2. When both header signals conflict → Codex (explicit Codex UA overrides anthropic headers)
-/// 3. Body-only heuristics: Anthropic format (model+messages) without OpenAI role fields → ClaudeCode
-/// 4. Default: Codex
-///
-/// Note: The Anthropic Messages API includes `role` on every message, which overlaps
-/// with OpenAI's format. Header-based detection takes priority to resolve this ambiguity.
-pub fn detect_frontend(headers: &HeaderMap, body: &Value) -> FrontendType {
-    let has_anthropic_hdrs = has_anthropic_headers(headers);
-    let has_codex_ua = has_codex_user_agent(headers);
+/// The header and body signals frontend detection looks at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct FrontendSignals {
+    /// Any `anthropic-*` request header.
+    pub anthropic_headers: bool,
+    /// A User-Agent containing `codex`.
+    pub codex_user_agent: bool,
+    /// A string `model` and a `messages` array.
+    pub anthropic_body: bool,
+    /// A non-empty `messages` array whose entries all carry a `role`.
+    pub openai_body: bool,
+}
 
-    // Header signals are definitive — resolve conflicts at the header level first.
-    if has_anthropic_hdrs && !has_codex_ua {
-        return FrontendType::ClaudeCode;
-    }
-    if has_codex_ua {
-        return FrontendType::Codex;
+impl FrontendSignals {
+    pub fn collect(headers: &HeaderMap, body: &Value) -> Self {
+        Self {
+            anthropic_headers: has_anthropic_headers(headers),
+            codex_user_agent: has_codex_user_agent(headers),
+            anthropic_body: has_anthropic_format(body),
+            openai_body: has_openai_format(body),
+        }
     }
 
-    // No header signals — fall back to body format heuristics.
-    let claude_body = has_anthropic_format(body);
-    let codex_body = has_openai_format(body);
+    /// The frontend these signals point to, or `None` when there is none.
+    ///
+    /// Rules (in priority order):
+    /// 1. Definitive header signals win: `anthropic-*` → ClaudeCode, `codex` User-Agent → Codex
+    /// 2. When both header signals conflict → Codex (explicit Codex UA overrides anthropic headers)
+    /// 3. Body-only heuristics: Anthropic format (model+messages) without OpenAI role fields → ClaudeCode
+    /// 4. Any other body with OpenAI role fields → Codex
+    ///
+    /// Note: The Anthropic Messages API includes `role` on every message, which overlaps
+    /// with OpenAI's format. Header-based detection takes priority to resolve this ambiguity.
+    pub fn frontend(&self) -> Option<FrontendType> {
+        // Header signals are definitive — resolve conflicts at the header level first.
+        if self.codex_user_agent {
+            return Some(FrontendType::Codex);
+        }
+        if self.anthropic_headers {
+            return Some(FrontendType::ClaudeCode);
+        }
+
+        // No header signals — fall back to body format heuristics.
+        if self.anthropic_body && !self.openai_body {
+            Some(FrontendType::ClaudeCode)
+        } else if self.openai_body {
+            Some(FrontendType::Codex)
+        } else {
+            None
+        }
+    }
+}
 
-    let result = if claude_body && !codex_body {
-        FrontendType::ClaudeCode
-    } else {
-        FrontendType::Codex
-    };
+/// Detect the request frontend, or `None` when neither the headers nor the
+/// body carry any signal. See [`FrontendSignals::frontend`] for the rules.
+pub fn try_detect_frontend(headers: &HeaderMap, body: &Value) -> Option<FrontendType> {
+    let result = FrontendSignals::collect(headers, body).frontend();
     debug!(?result, "frontend detected");
     result
 }
 
+/// Detect the request frontend, guessing Codex when nothing matches.
+pub fn detect_frontend(headers: &HeaderMap, body: &Value) -> FrontendType {
+    try_detect_frontend(headers, body).unwrap_or(FrontendType::Codex)
+}
+
 fn has_codex_user_agent(headers: &HeaderMap) -> bool {
     headers
         .get(USER_AGENT)
@@ -159,6 +192,23 @@ mod tests {
         );
     }
 
+    #[test]
+    fn reports_no_frontend_without_any_signal() {
+        let headers = HeaderMap::new();
+        let body = json!({"prompt": "Hello", "max_tokens": 10});
+
+        assert_eq!(try_detect_frontend(&headers, &body), None);
+        assert_eq!(
+            FrontendSignals::collect(&headers, &body),
+            FrontendSignals {
+                anthropic_headers: false,
+                codex_user_agent: false,
+                anthropic_body: false,
+                openai_body: false,
+            }
+        );
+    }
+
     #[test]
     fn detects_claude_code_when_anthropic_headers_with_role_in_body() {
         // Real-world case: Claude Code CLI sends anthropic-version header
@@ -14,7 +14,7 @@ pub mod claude_code;
 pub mod codex;
 pub mod detection;
 
-pub use detection::{detect_frontend, FrontendType};
+pub use detection::{detect_frontend, try_detect_frontend, FrontendSignals, FrontendType};
 
 /// A normalized message format that works for both OpenAI and Anthropic.
 ///
@@ -38,6 +38,7 @@ pub struct Message {
 /// - Image: Image data (base64 or URL)
 /// - ToolUse: A request to use a tool (Anthropic format)
 /// - ToolResult: The result of a tool execution
+/// - Audio: Generated audio output
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ContentBlock {
@@ -64,6 +65,9 @@ pub enum ContentBlock {
         #[serde(skip_serializing_if = "Option::is_none")]
         signature: Option<String>,
     },
+    /// Generated audio output (OpenAI `message.audio`)
+    #[serde(rename = "audio")]
+    Audio { audio: Value },
 }
 
 /// Image source specification for image content blocks.
@@ -128,6 +132,9 @@ pub struct InternalRequest {
     /// Sampling temperature (0.0 to 2.0)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f32>,
+    /// Nucleus sampling cutoff (0.0 to 1.0)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
     /// Whether to stream the response
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stream: Option<bool>,
@@ -316,6 +323,7 @@ mod tests {
             system: None,
             max_tokens: None,
             temperature: None,
+            top_p: None,
             stream: None,
             tools: None,
             tool_choice: None,
@@ -534,9 +534,17 @@ mod tests {
             system: None,
             max_tokens: Some(64),
             temperature: Some(0.2),
+            top_p: None,
             stream: Some(false),
             tools: None,
+            tool_choice: None,
+            metadata: None,
             openai_passthrough_body: None,
+            excluded_tier: None,
+            stop_sequences: None,
+            modalities: None,
+            audio: None,
+            unrecognized: serde_json::Map::new(),
         }
     }
 
@@ -0,0 +1,126 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//! Token-bucket limit on proxy requests per client (`INGRESS_RATE_LIMIT`).
+//!
+//! Each client gets a bucket holding up to `burst` tokens that refills at
+//! `requests_per_minute`. The rate is passed in on each check, so a config
+//! reload applies to existing buckets on their next request.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+
+/// Bucket count above which refilled buckets are dropped before adding a
+/// new one. A full bucket behaves exactly like a missing one.
+const PRUNE_THRESHOLD: usize = 4096;
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    refilled: Instant,
+}
+
+/// Per-client token buckets, keyed by an opaque client key.
+#[derive(Debug, Default)]
+pub struct IngressLimiter {
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl IngressLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take a token from `key`'s bucket. When it is empty, returns how long
+    /// until the next token is available.
+    pub fn check(&self, key: &str, requests_per_minute: u32, burst: u32) -> Result<(), Duration> {
+        self.check_at(key, requests_per_minute, burst, Instant::now())
+    }
+
+    fn check_at(
+        &self,
+        key: &str,
+        requests_per_minute: u32,
+        burst: u32,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        let per_sec = f64::from(requests_per_minute.max(1)) / 60.0;
+        let burst = f64::from(burst.max(1));
+        let refill = |bucket: &Bucket| {
+            let elapsed = now.saturating_duration_since(bucket.refilled).as_secs_f64();
+            (bucket.tokens + elapsed * per_sec).min(burst)
+        };
+
+        let mut buckets = self.buckets.lock();
+        if buckets.len() >= PRUNE_THRESHOLD && !buckets.contains_key(key) {
+            buckets.retain(|_, bucket| refill(bucket) < burst);
+        }
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: burst,
+            refilled: now,
+        });
+        bucket.tokens = refill(bucket);
+        bucket.refilled = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / per_sec))
+        }
+    }
+
+    /// Clients currently tracked.
+    pub fn tracked(&self) -> usize {
+        self.buckets.lock().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn burst_then_refill_at_rate() {
+        let limiter = IngressLimiter::new();
+        let start = Instant::now();
+        for _ in 0..3 {
+            assert!(limiter.check_at("a", 60, 3, start).is_ok());
+        }
+        let wait = limiter.check_at("a", 60, 3, start).unwrap_err();
+        assert_eq!(wait, Duration::from_secs(1));
+        assert!(
+            limiter.check_at("b", 60, 3, start).is_ok(),
+            "buckets are per key"
+        );
+
+        let later = start + Duration::from_secs(1);
+        assert!(limiter.check_at("a", 60, 3, later).is_ok());
+        assert!(limiter.check_at("a", 60, 3, later).is_err());
+    }
+
+    #[test]
+    fn refill_is_capped_at_burst() {
+        let limiter = IngressLimiter::new();
+        let start = Instant::now();
+        assert!(limiter.check_at("a", 60, 2, start).is_ok());
+
+        let later = start + Duration::from_secs(600);
+        assert!(limiter.check_at("a", 60, 2, later).is_ok());
+        assert!(limiter.check_at("a", 60, 2, later).is_ok());
+        assert!(limiter.check_at("a", 60, 2, later).is_err());
+    }
+
+    #[test]
+    fn full_buckets_are_pruned() {
+        let limiter = IngressLimiter::new();
+        let start = Instant::now();
+        for i in 0..PRUNE_THRESHOLD {
+            limiter.check_at(&i.to_string(), 60, 5, start).unwrap();
+        }
+        assert_eq!(limiter.tracked(), PRUNE_THRESHOLD);
+
+        let later = start + Duration::from_secs(60);
+        limiter.check_at("new", 60, 5, later).unwrap();
+        assert_eq!(limiter.tracked(), 1);
+    }
+}
@@ -0,0 +1,203 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//! JSON log lines for log aggregation (`--log-format json`).
+//!
+//! Each event is written as one JSON object: `timestamp`, `level`, `target`,
+//! then the `request_id`, `tier`, and `frontend` of the request it was
+//! logged under (taken from the enclosing `ccr.http`, `ccr.request`, and
+//! `ccr.attempt` spans), then the event's own fields including `message`.
+//! Which events are written is left to the `EnvFilter` installed beside it.
+
+use std::fmt;
+use std::io::Write;
+
+use serde_json::{Map, Value};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+/// Span fields repeated at the top level of every event inside the span.
+const CONTEXT_FIELDS: [&str; 3] = ["request_id", "tier", "frontend"];
+
+/// Context field values recorded so far on one span.
+struct ContextFields(Map<String, Value>);
+
+struct JsonVisitor<'a> {
+    fields: &'a mut Map<String, Value>,
+    /// Record only these fields, or every field when `None`.
+    only: Option<&'a [&'a str]>,
+}
+
+impl JsonVisitor<'_> {
+    fn insert(&mut self, field: &Field, value: Value) {
+        if self.only.is_some_and(|only| !only.contains(&field.name())) {
+            return;
+        }
+        self.fields.insert(field.name().to_string(), value);
+    }
+}
+
+impl Visit for JsonVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.insert(field, Value::from(format!("{value:?}")));
+    }
+}
+
+/// Layer writing each event as a JSON line to `make_writer`.
+pub struct JsonLayer<W> {
+    make_writer: W,
+}
+
+/// JSON lines on stdout, where the text format writes too.
+pub fn layer() -> JsonLayer<fn() -> std::io::Stdout> {
+    JsonLayer {
+        make_writer: std::io::stdout,
+    }
+}
+
+impl<W> JsonLayer<W> {
+    /// Write lines to `make_writer` instead of stdout.
+    pub fn with_writer<W2>(self, make_writer: W2) -> JsonLayer<W2> {
+        JsonLayer { make_writer }
+    }
+}
+
+impl<S, W> Layer<S> for JsonLayer<W>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+    W: for<'writer> MakeWriter<'writer> + 'static,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut fields = Map::new();
+        attrs.record(&mut JsonVisitor {
+            fields: &mut fields,
+            only: Some(&CONTEXT_FIELDS),
+        });
+        span.extensions_mut().insert(ContextFields(fields));
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        if let Some(ContextFields(fields)) = extensions.get_mut::<ContextFields>() {
+            values.record(&mut JsonVisitor {
+                fields,
+                only: Some(&CONTEXT_FIELDS),
+            });
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let mut line = Map::new();
+        line.insert(
+            "timestamp".to_string(),
+            Value::from(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Micros, true)),
+        );
+        line.insert(
+            "level".to_string(),
+            Value::from(metadata.level().to_string()),
+        );
+        line.insert("target".to_string(), Value::from(metadata.target()));
+        if let Some(scope) = ctx.event_scope(event) {
+            // Outer spans first, so the innermost value of a field wins.
+            for span in scope.from_root() {
+                if let Some(ContextFields(fields)) = span.extensions().get::<ContextFields>() {
+                    line.extend(fields.clone());
+                }
+            }
+        }
+        event.record(&mut JsonVisitor {
+            fields: &mut line,
+            only: None,
+        });
+
+        let Ok(mut buf) = serde_json::to_vec(&line) else {
+            return;
+        };
+        buf.push(b'\n');
+        let _ = self.make_writer.make_writer().write_all(&buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn request_context_fields_are_top_level() {
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let subscriber =
+            tracing_subscriber::registry().with(layer().with_writer(move || writer.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let request = tracing::info_span!(
+                "ccr.request",
+                request_id = "req-1",
+                model = "sonnet",
+                frontend = tracing::field::Empty,
+                tier = tracing::field::Empty,
+            );
+            let _entered = request.enter();
+            request.record("frontend", "claude_code");
+            let attempt = tracing::info_span!("ccr.attempt", tier = "glm", attempt = 1);
+            let _attempt = attempt.enter();
+            tracing::warn!(attempt = 2, "Failed glm attempt");
+        });
+
+        let output = buffer.0.lock().unwrap().clone();
+        let line: Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(line["level"], "WARN");
+        assert_eq!(line["message"], "Failed glm attempt");
+        assert_eq!(line["request_id"], "req-1");
+        assert_eq!(line["frontend"], "claude_code");
+        assert_eq!(line["tier"], "glm");
+        assert_eq!(line["attempt"], 2);
+        assert!(line.get("model").is_none());
+        assert!(line["timestamp"].as_str().unwrap().ends_with('Z'));
+    }
+}
@@ -1,19 +1,27 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
+pub mod bench;
 pub mod config;
+pub mod connection_stats;
 #[cfg(feature = "dashboard")]
 pub mod dashboard;
 pub mod debug_capture;
 pub mod frontend;
 #[cfg(feature = "gp")]
 pub mod gp_router;
+pub mod ingress_limit;
+pub mod json_log;
 pub mod mcp;
 pub mod metrics;
+#[cfg(feature = "otel")]
+pub mod otel;
 pub mod proxy;
 pub mod ratelimit;
 pub mod router;
 pub mod routing;
 pub mod schema_validate;
 pub mod sse;
+pub mod sticky;
 pub mod transform;
 pub mod transformer;
 pub mod turn_capture;
+pub mod upstream_limit;
@@ -1,18 +1,13 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 use anyhow::{anyhow, Result};
-use axum::{
-    extract::State,
-    routing::{get, post},
-    Router,
-};
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use std::net::SocketAddr;
-use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::signal::ctrl_c;
 #[cfg(unix)]
 use tokio::signal::unix::{signal, SignalKind};
-use tower_http::{cors::CorsLayer, trace::TraceLayer};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 mod config {
@@ -45,8 +40,12 @@ mod transformer {
     pub use ccr_rust::transformer::*;
 }
 
-use crate::config::Config;
-use ccr_rust::debug_capture::DebugCapture;
+use crate::config::{Config, ConfigSource};
+use ccr_rust::bench::BenchOptions;
+use ccr_rust::debug_capture::{DebugCapture, ResponseSummary};
+use ccr_rust::ingress_limit::IngressLimiter;
+use ccr_rust::sticky::StickySessions;
+use ccr_rust::upstream_limit::UpstreamLimiter;
 #[cfg(feature = "gp")]
 use gp_router::GpRequestRouter;
 use ratelimit::RateLimitTracker;
@@ -61,9 +60,31 @@ struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
 
-    /// Path to config file (global option)
+    /// Config file path, `-` for stdin, or an http(s) URL (global option)
     #[arg(short, long, env = "CCR_CONFIG", global = true)]
     config: Option<String>,
+
+    /// `Authorization` header value sent when fetching the config from a URL
+    #[arg(long, env = "CCR_CONFIG_AUTH", global = true, hide_env_values = true)]
+    config_auth: Option<String>,
+
+    /// Log output format (global option)
+    #[arg(
+        long,
+        env = "CCR_LOG_FORMAT",
+        global = true,
+        value_enum,
+        default_value_t = LogFormat::Text
+    )]
+    log_format: LogFormat,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum LogFormat {
+    /// Human-readable lines
+    Text,
+    /// One JSON object per line, with `request_id`, `tier`, and `frontend` at the top level
+    Json,
 }
 
 #[derive(Subcommand)]
@@ -93,8 +114,51 @@ enum Commands {
         #[arg(short, long, default_value = "3456")]
         port: u16,
     },
+    /// Load-test a running server and report throughput, latency, and tiers
+    Bench {
+        /// Server host
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+
+        /// Server port
+        #[arg(short, long, default_value = "3456")]
+        port: u16,
+
+        /// Model to request; `provider,model` pins a tier
+        #[arg(long, default_value = "claude-sonnet-4-5")]
+        model: String,
+
+        /// Requests kept in flight
+        #[arg(long, default_value = "8")]
+        concurrency: usize,
+
+        /// How long to send requests, in seconds
+        #[arg(long, default_value = "30")]
+        duration: u64,
+
+        /// Send streaming requests
+        #[arg(long)]
+        stream: bool,
+
+        /// max_tokens for each request
+        #[arg(long, default_value = "64")]
+        max_tokens: u32,
+
+        /// Prompt sent as the only user message
+        #[arg(long, default_value = "Reply with one short sentence.")]
+        prompt: String,
+
+        /// Client key, sent as x-api-key when the server sets CLIENT_KEYS
+        #[arg(long, env = "CCR_BENCH_API_KEY", hide_env_values = true)]
+        api_key: Option<String>,
+    },
     /// Validate config file syntax and providers
-    Validate,
+    Validate {
+        /// Also send a 1-token request to each provider and report
+        /// reachability, auth, and latency; exits non-zero if any fails
+        #[arg(long)]
+        probe: bool,
+    },
     #[cfg(feature = "dashboard")]
     /// Launch interactive TUI dashboard
     Dashboard {
@@ -105,6 +169,10 @@ enum Commands {
         /// Tracker port (override with CCR_DASHBOARD_PORT env var)
         #[arg(short, long, env = "CCR_DASHBOARD_PORT", default_value = "3456")]
         port: u16,
+
+        /// Only show these providers/tiers (comma-separated, e.g. zai,deepseek)
+        #[arg(long)]
+        providers: Option<String>,
     },
     /// Show version and build info
     Version,
@@ -176,6 +244,27 @@ enum Commands {
         /// Show full response body (by default truncated)
         #[arg(long)]
         full: bool,
+
+        #[command(subcommand)]
+        action: Option<CapturesCommand>,
+    },
+}
+
+#[derive(Subcommand)]
+enum CapturesCommand {
+    /// Re-send a captured request and diff the response against the capture
+    Replay {
+        /// Request id shown by `ccr-rust captures` (the `x-ccr-request-id`
+        /// the client received)
+        request_id: String,
+
+        /// URL to send the request to (defaults to the captured upstream URL)
+        #[arg(long)]
+        target: Option<String>,
+
+        /// Bearer token for the target (also sent as x-api-key)
+        #[arg(long, env = "CCR_REPLAY_API_KEY")]
+        api_key: Option<String>,
     },
 }
 
@@ -188,12 +277,12 @@ fn show_version() {
     println!("Features: streaming, ewma-routing, transformers, rate-limiting");
 }
 
-fn resolve_redis_target(
-    config_path: &str,
+async fn resolve_redis_target(
+    config_source: &ConfigSource,
     redis_url_override: Option<String>,
     redis_prefix_override: Option<String>,
 ) -> anyhow::Result<(String, String)> {
-    let config = Config::from_file(config_path)?;
+    let config = Config::from_source(config_source).await?;
     let persistence = config.persistence();
 
     let redis_url = redis_url_override
@@ -210,13 +299,13 @@ fn resolve_redis_target(
     Ok((redis_url, redis_prefix))
 }
 
-fn clear_stats(
-    config_path: &str,
+async fn clear_stats(
+    config_source: &ConfigSource,
     redis_url_override: Option<String>,
     redis_prefix_override: Option<String>,
 ) -> anyhow::Result<()> {
     let (redis_url, redis_prefix) =
-        resolve_redis_target(config_path, redis_url_override, redis_prefix_override)?;
+        resolve_redis_target(config_source, redis_url_override, redis_prefix_override).await?;
     let deleted = metrics::clear_redis_persistence(&redis_url, &redis_prefix)?;
     println!(
         "Cleared {} Redis key(s) for prefix '{}'",
@@ -225,15 +314,95 @@ fn clear_stats(
     Ok(())
 }
 
-fn list_captures(
-    config_path: &str,
+async fn replay_capture(
+    config_source: &ConfigSource,
+    output_dir_override: Option<String>,
+    request_id: String,
+    target: Option<String>,
+    api_key: Option<String>,
+) -> anyhow::Result<()> {
+    let config = Config::from_source(config_source).await?;
+    let mut debug_config = config.debug_capture().clone();
+    if let Some(dir) = output_dir_override {
+        debug_config.output_dir = dir;
+    }
+    debug_config.enabled = true; // Enable to read directory
+    let capture = DebugCapture::new(debug_config)?;
+
+    let cap = capture
+        .find_capture(&request_id)?
+        .ok_or_else(|| anyhow!("No capture found with request id {}", request_id))?;
+    let target = target.unwrap_or_else(|| cap.url.clone());
+    if target.is_empty() {
+        return Err(anyhow!(
+            "Capture {} has no URL; pass --target",
+            cap.request_id
+        ));
+    }
+
+    println!(
+        "Replaying [{}] {} @ {} against {}",
+        cap.request_id, cap.provider, cap.timestamp, target
+    );
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_millis(config.api_timeout_ms()))
+        .build()?;
+    let mut request = client.post(&target).json(&cap.request_body);
+    match config.providers().iter().find(|p| p.name == cap.provider) {
+        Some(provider) => {
+            let mut provider = provider.clone();
+            if let Some(key) = api_key {
+                provider.api_key = key;
+            }
+            let headers = router::provider_request_headers(&config, &provider, &cap.model)
+                .map_err(|e| anyhow!("{}", e))?;
+            request = request.headers(headers);
+        }
+        None => {
+            println!(
+                "Provider '{}' is not in the config; sending without its headers.",
+                cap.provider
+            );
+            if let Some(key) = api_key {
+                request = request.bearer_auth(&key).header("x-api-key", key);
+            }
+        }
+    }
+    let response = request.send().await?;
+    let status = response.status().as_u16();
+    let body = response.text().await?;
+
+    let captured = ResponseSummary::from_response(cap.response_status, &cap.response_body);
+    let replayed = ResponseSummary::from_response(status, &body);
+    if cap.response_truncated {
+        println!("Note: the captured response was truncated; trailing content may differ.");
+    }
+    let changes = replayed.diff(&captured);
+    if changes.is_empty() {
+        println!(
+            "No differences (status {}, {} content blocks, stop_reason {})",
+            status,
+            replayed.content.len(),
+            replayed.stop_reason.as_deref().unwrap_or("(none)")
+        );
+        return Ok(());
+    }
+    println!("{} difference(s) from the capture:", changes.len());
+    for change in &changes {
+        println!("  {}", change);
+    }
+    Err(anyhow!("Replay of capture {} differs", cap.request_id))
+}
+
+async fn list_captures(
+    config_source: &ConfigSource,
     provider: Option<String>,
     limit: usize,
     stats: bool,
     output_dir_override: Option<String>,
     full: bool,
 ) -> anyhow::Result<()> {
-    let config = Config::from_file(config_path)?;
+    let config = Config::from_source(config_source).await?;
     let mut debug_config = config.debug_capture().clone();
 
     // Override output directory if specified
@@ -351,24 +520,67 @@ fn list_captures(
     Ok(())
 }
 
+/// A rate-limit tracker, joined to the fleet through Redis when
+/// `Persistence.shared_rate_limits` is on. A background thread pulls the
+/// shared state every `shared_rate_limit_sync_ms`.
+fn shared_ratelimit_tracker(
+    persistence: &ccr_rust::config::PersistenceConfig,
+) -> anyhow::Result<Arc<RateLimitTracker>> {
+    if !persistence.shared_rate_limits {
+        return Ok(Arc::new(RateLimitTracker::new()));
+    }
+    // Same URL resolution as `init_persistence`.
+    let redis_url = persistence
+        .redis_url
+        .clone()
+        .or_else(|| std::env::var("CCR_REDIS_URL").ok())
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "Persistence.shared_rate_limits requires Persistence.redis_url or CCR_REDIS_URL"
+            )
+        })?;
+    let store = ratelimit::RedisRateLimitStore::open(&redis_url, &persistence.redis_prefix)?;
+    let tracker = Arc::new(RateLimitTracker::with_shared_store(Arc::new(store)));
+    let interval = std::time::Duration::from_millis(persistence.shared_rate_limit_sync_ms);
+    let weak = Arc::downgrade(&tracker);
+    std::thread::Builder::new()
+        .name("ccr-ratelimit-pull".to_string())
+        .spawn(move || {
+            while let Some(tracker) = weak.upgrade() {
+                if let Err(error) = tracker.sync_from_shared() {
+                    tracing::warn!("Failed to pull shared rate-limit state: {error}");
+                }
+                drop(tracker);
+                std::thread::sleep(interval);
+            }
+        })?;
+    tracing::info!(
+        "Sharing rate-limit state through Redis (sync every {:?})",
+        interval
+    );
+    Ok(tracker)
+}
+
 async fn run_server(
-    config_path: &str,
+    config_source: &ConfigSource,
     host: String,
     port: u16,
     max_streams: usize,
     shutdown_timeout: u64,
 ) -> anyhow::Result<()> {
-    let config = Config::from_file(config_path)?;
+    let config = Config::from_source(config_source).await?;
     ensure_gp_build_support(&config)?;
-    tracing::info!("Loaded config from {}", config_path);
+    ensure_postgres_build_support(&config)?;
+    tracing::info!("Loaded config from {}", config_source);
     tracing::info!("Tier order: {:?}", config.backend_tiers());
     tracing::info!("Max concurrent streams: {}", max_streams);
     tracing::info!("Shutdown timeout: {}s", shutdown_timeout);
 
     let ewma_tracker = std::sync::Arc::new(EwmaTracker::new());
+    ewma_tracker.set_latency_window(config.latency_window());
     metrics::init_persistence(config.persistence(), &ewma_tracker)?;
     let transformer_registry = std::sync::Arc::new(TransformerRegistry::new());
-    let ratelimit_tracker = std::sync::Arc::new(RateLimitTracker::new());
+    let ratelimit_tracker = shared_ratelimit_tracker(config.persistence())?;
     #[cfg(feature = "gp")]
     let gp_router = if config.router().gp_routing.enabled {
         let tiers = config.backend_tiers();
@@ -382,6 +594,16 @@ async fn run_server(
     #[cfg(not(feature = "gp"))]
     let gp_router: Option<()> = None;
 
+    if config.fault_injection_enabled() && !config.router().fault_injection.is_empty() {
+        #[cfg(debug_assertions)]
+        tracing::warn!(
+            tiers = ?config.router().fault_injection.keys().collect::<Vec<_>>(),
+            "Fault injection is enabled; configured tiers will see synthetic delays and errors"
+        );
+        #[cfg(not(debug_assertions))]
+        tracing::warn!("ENABLE_FAULT_INJECTION is ignored: release builds do not inject faults");
+    }
+
     // Initialize debug capture if enabled
     let debug_capture = if config.debug_capture().enabled {
         match DebugCapture::new(config.debug_capture().clone()) {
@@ -395,62 +617,77 @@ async fn run_server(
         None
     };
 
+    let maintenance = Arc::new(AtomicBool::new(config.maintenance_mode()));
     let state = AppState {
-        config,
+        config: config.into(),
         ewma_tracker,
         gp_router,
         transformer_registry,
         active_streams: Arc::new(AtomicUsize::new(0)),
+        hedges_in_flight: Arc::new(AtomicUsize::new(0)),
         max_streams,
         ratelimit_tracker,
         shutdown_timeout,
+        draining: Arc::new(AtomicBool::new(false)),
         debug_capture,
+        sticky_sessions: Arc::new(StickySessions::new()),
+        upstream_limiter: Arc::new(UpstreamLimiter::new()),
+        ingress_limiter: Arc::new(IngressLimiter::new()),
+        maintenance,
+        readiness: Arc::new(router::Readiness::new()),
     };
 
-    let app = Router::new()
-        .route("/v1/messages", post(router::handle_messages))
-        .route(
-            "/v1/chat/completions",
-            post(router::handle_chat_completions),
-        )
-        .route("/v1/responses", post(router::handle_responses))
-        .route("/v1/models", get(router::list_models))
-        .route(
-            "/preset/{name}/v1/messages",
-            post(router::handle_preset_messages),
-        )
-        .route("/v1/presets", get(router::list_presets))
-        .route("/v1/latencies", get(latencies_handler))
-        .route("/v1/usage", get(metrics::usage_handler))
-        .route("/v1/token-drift", get(metrics::token_drift_handler))
-        .route("/v1/token-audit", get(metrics::token_audit_handler))
-        .route("/v1/throughput", get(metrics::throughput_handler))
-        .route(
-            "/v1/frontend-metrics",
-            get(metrics::frontend_metrics_handler),
-        )
-        .route("/health", get(health))
-        .route("/metrics", get(metrics::metrics_handler))
-        .layer(CorsLayer::permissive())
-        .layer(TraceLayer::new_for_http())
-        .with_state(state);
+    let draining = state.draining.clone();
+    let active_streams = state.active_streams.clone();
+    router::spawn_preset_warmups(&state);
+    router::spawn_readiness_checks(&state);
+
+    let app = router::app(state);
 
     let addr = SocketAddr::from((host.parse::<std::net::IpAddr>()?, port));
     tracing::info!("CCR-Rust listening on {}", addr);
 
+    let (force_exit, forced) = tokio::sync::oneshot::channel::<usize>();
+    let drain = async move {
+        shutdown_signal().await;
+        draining.store(true, Ordering::SeqCst);
+        tracing::info!(
+            "Draining {} active stream(s) (timeout {}s)...",
+            active_streams.load(Ordering::SeqCst),
+            shutdown_timeout
+        );
+        let remaining =
+            router::wait_for_streams(&active_streams, Duration::from_secs(shutdown_timeout)).await;
+        if remaining > 0 {
+            let _ = force_exit.send(remaining);
+        } else {
+            tracing::info!("All streams drained");
+        }
+    };
+
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal(shutdown_timeout))
-        .await?;
+    tokio::select! {
+        result = axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(drain) => result?,
+        Ok(remaining) = forced => tracing::warn!(
+            "Shutdown timeout elapsed with {} stream(s) still active; exiting",
+            remaining
+        ),
+    }
 
     Ok(())
 }
 
-fn validate_config(config_path: &str) -> anyhow::Result<()> {
-    println!("Validating: {}", config_path);
+async fn validate_config(config_source: &ConfigSource, probe: bool) -> anyhow::Result<()> {
+    println!("Validating: {}", config_source);
 
-    let config = Config::from_file(config_path)?;
+    let config = Config::from_source(config_source).await?;
     ensure_gp_build_support(&config)?;
+    ensure_postgres_build_support(&config)?;
+    config.validate()?;
 
     let providers = config.providers();
     println!("✓ {} provider(s)", providers.len());
@@ -465,6 +702,47 @@ fn validate_config(config_path: &str) -> anyhow::Result<()> {
     }
 
     println!("\n✓ Configuration valid");
+
+    if probe {
+        probe_providers(&config).await?;
+    }
+    Ok(())
+}
+
+/// Send a 1-token request to every provider and print what came back.
+async fn probe_providers(config: &Config) -> anyhow::Result<()> {
+    let probed = config
+        .providers()
+        .iter()
+        .filter(|provider| !provider.models.is_empty())
+        .count();
+    println!("\nProbing {} provider(s)...", probed);
+    let probes = router::probe_providers(config).await;
+    for probe in &probes {
+        let mark = if probe.outcome.is_success() {
+            "✓"
+        } else {
+            "✗"
+        };
+        println!(
+            "  {} {} ({}): {} in {} ms",
+            mark,
+            probe.provider,
+            probe.model,
+            probe.outcome,
+            probe.latency.as_millis()
+        );
+    }
+
+    let failed = probes.iter().filter(|p| !p.outcome.is_success()).count();
+    if failed > 0 {
+        anyhow::bail!(
+            "{} of {} provider(s) failed the probe",
+            failed,
+            probes.len()
+        );
+    }
+    println!("\n✓ All providers reachable");
     Ok(())
 }
 
@@ -485,6 +763,18 @@ fn ensure_gp_build_support(config: &Config) -> anyhow::Result<()> {
     Ok(())
 }
 
+fn ensure_postgres_build_support(config: &Config) -> anyhow::Result<()> {
+    #[cfg(not(feature = "postgres"))]
+    if config.persistence().mode == config::PersistenceMode::Postgres {
+        anyhow::bail!(
+            "Persistence.mode=postgres requires a CCR-Rust build with the `postgres` feature"
+        );
+    }
+
+    let _ = config;
+    Ok(())
+}
+
 #[cfg(feature = "gp")]
 fn validate_gp_runtime_config(config: &config::GpRoutingRuntimeConfig) -> anyhow::Result<()> {
     if let Some(kpls_dim) = config.kpls_dim {
@@ -531,21 +821,48 @@ async fn check_status(host: &str, port: u16) -> anyhow::Result<()> {
     }
 }
 
+async fn run_bench(options: BenchOptions) -> anyhow::Result<()> {
+    println!(
+        "Benchmarking {} with model {} ({} concurrent, {}s{})",
+        options.base_url,
+        options.model,
+        options.concurrency,
+        options.duration.as_secs(),
+        if options.stream { ", streaming" } else { "" }
+    );
+    let report = ccr_rust::bench::run(options).await?;
+    print!("\n{}", report);
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
+    // Flushes exported spans when main returns.
+    #[cfg(feature = "otel")]
+    let (otel_layer, _otel_guard) = ccr_rust::otel::layer().unzip();
+    #[cfg(not(feature = "otel"))]
+    let otel_layer: Option<tracing_subscriber::layer::Identity> = None;
+    let cli = Cli::parse();
+    let (text_layer, json_layer) = match cli.log_format {
+        LogFormat::Text => (Some(tracing_subscriber::fmt::layer()), None),
+        LogFormat::Json => (None, Some(ccr_rust::json_log::layer())),
+    };
     tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| "ccr_rust=info,tower_http=info".into()),
         )
-        .with(tracing_subscriber::fmt::layer())
+        .with(text_layer)
+        .with(json_layer)
+        .with(otel_layer)
         .init();
 
-    let cli = Cli::parse();
-    let config_path = cli
-        .config
-        .map(|p| shellexpand::tilde(&p).to_string())
-        .unwrap_or_else(|| shellexpand::tilde("~/.claude-code-router/config.json").to_string());
+    let config_source = ConfigSource::parse(
+        cli.config
+            .as_deref()
+            .unwrap_or("~/.claude-code-router/config.json"),
+        cli.config_auth,
+    );
 
     match cli.command {
         Some(Commands::Start {
@@ -554,21 +871,49 @@ async fn main() -> Result<()> {
             max_streams,
             shutdown_timeout,
         }) => {
-            run_server(&config_path, host, port, max_streams, shutdown_timeout).await?;
+            run_server(&config_source, host, port, max_streams, shutdown_timeout).await?;
         }
         None => {
             // Default: start server with defaults
-            run_server(&config_path, "127.0.0.1".into(), 3456, 512, 30).await?;
+            run_server(&config_source, "127.0.0.1".into(), 3456, 512, 30).await?;
         }
         Some(Commands::Status { host, port }) => {
             check_status(&host, port).await?;
         }
-        Some(Commands::Validate) => {
-            validate_config(&config_path)?;
+        Some(Commands::Bench {
+            host,
+            port,
+            model,
+            concurrency,
+            duration,
+            stream,
+            max_tokens,
+            prompt,
+            api_key,
+        }) => {
+            run_bench(BenchOptions {
+                base_url: format!("http://{}:{}", host, port),
+                model,
+                concurrency,
+                duration: Duration::from_secs(duration),
+                stream,
+                max_tokens,
+                prompt,
+                api_key,
+                timeout: Duration::from_secs(600),
+            })
+            .await?;
+        }
+        Some(Commands::Validate { probe }) => {
+            validate_config(&config_source, probe).await?;
         }
         #[cfg(feature = "dashboard")]
-        Some(Commands::Dashboard { host, port }) => {
-            dashboard::run_dashboard(host, port)?;
+        Some(Commands::Dashboard {
+            host,
+            port,
+            providers,
+        }) => {
+            dashboard::run_dashboard(host, port, providers)?;
         }
         Some(Commands::Version) => {
             show_version();
@@ -577,7 +922,7 @@ async fn main() -> Result<()> {
             redis_url,
             redis_prefix,
         }) => {
-            clear_stats(&config_path, redis_url, redis_prefix)?;
+            clear_stats(&config_source, redis_url, redis_prefix).await?;
         }
         Some(Commands::Mcp {
             level,
@@ -617,14 +962,24 @@ async fn main() -> Result<()> {
             stats,
             output_dir,
             full,
-        }) => {
-            list_captures(&config_path, provider, limit, stats, output_dir, full)?;
-        }
+            action,
+        }) => match action {
+            Some(CapturesCommand::Replay {
+                request_id,
+                target,
+                api_key,
+            }) => {
+                replay_capture(&config_source, output_dir, request_id, target, api_key).await?;
+            }
+            None => {
+                list_captures(&config_source, provider, limit, stats, output_dir, full).await?;
+            }
+        },
     }
     Ok(())
 }
 
-async fn shutdown_signal(timeout: u64) {
+async fn shutdown_signal() {
     let ctrl_c = async { ctrl_c().await.expect("failed to listen for ctrl+c") };
     #[cfg(unix)]
     let terminate = async {
@@ -640,18 +995,6 @@ async fn shutdown_signal(timeout: u64) {
         _ = ctrl_c => tracing::info!("Received SIGINT"),
         _ = terminate => tracing::info!("Received SIGTERM"),
     }
-    tracing::info!(
-        "Received shutdown signal, draining connections (timeout {}s)...",
-        timeout
-    );
-}
-
-async fn latencies_handler(State(state): State<AppState>) -> impl axum::response::IntoResponse {
-    axum::Json(metrics::get_latency_entries(&state.ewma_tracker))
-}
-
-async fn health() -> &'static str {
-    "ok"
 }
 
 #[cfg(all(test, feature = "gp"))]
@@ -5,26 +5,31 @@
 // /v1/usage, /v1/token-drift, /v1/token-audit, /v1/frontend-metrics,
 // /v1/throughput, /metrics, and /v1/latencies.
 
+use axum::extract::{Query, State};
+use axum::http::HeaderMap;
 use axum::response::IntoResponse;
 use axum::Json;
 use prometheus::core::Collector;
 use prometheus::{Encoder, TextEncoder};
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::sync::atomic::Ordering;
 use tracing::debug;
 
+use crate::config::Config;
+use crate::ratelimit::RateLimitTracker;
+use crate::router::AppState;
 use crate::routing::EwmaTracker;
 
 use super::{
-    get_hist_offset, get_throughput_state, merge_histogram_offsets, PreRequestAuditEntry,
-    ThroughputSample, ACTIVE_REQUESTS, ACTIVE_STREAMS, AUDIT_LOG, CACHE_CREATION_TOKENS_TOTAL,
-    CACHE_READ_TOKENS_TOTAL, COST_USD_TOTAL, FAILURES_TOTAL, FRONTEND_REQUESTS_TOTAL,
-    FRONTEND_REQUEST_LATENCY, INPUT_TOKENS_TOTAL, METRIC_FRONTEND_REQUEST_DURATION_SECONDS,
-    METRIC_OUTPUT_TOKENS_PER_SECOND, METRIC_REQUEST_DURATION_SECONDS, METRIC_TTFT_SECONDS,
-    OUTPUT_TOKENS_PER_SECOND, OUTPUT_TOKENS_TOTAL, REQUESTS_TOTAL, REQUEST_DURATION,
-    TOKEN_DRIFT_STATE, TOTAL_FAILURES, TOTAL_INPUT_TOKENS, TOTAL_OUTPUT_TOKENS, TOTAL_REQUESTS,
-    TTFT_SECONDS,
+    get_hist_offset, get_throughput_state, merge_histogram_offsets, openmetrics, tier_and_mode,
+    PreRequestAuditEntry, RequestMode, ThroughputSample, ACTIVE_REQUESTS, ACTIVE_STREAMS,
+    AUDIT_LOG, CACHE_CREATION_TOKENS_TOTAL, CACHE_READ_TOKENS_TOTAL, COST_USD_TOTAL,
+    FAILURES_TOTAL, FRONTEND_REQUESTS_TOTAL, FRONTEND_REQUEST_LATENCY, INPUT_TOKENS_TOTAL,
+    METRIC_FRONTEND_REQUEST_DURATION_SECONDS, METRIC_OUTPUT_TOKENS_PER_SECOND,
+    METRIC_REQUEST_DURATION_SECONDS, METRIC_TTFT_SECONDS, OUTPUT_TOKENS_PER_SECOND,
+    OUTPUT_TOKENS_TOTAL, REQUESTS_TOTAL, REQUEST_DURATION, TOKEN_DRIFT_STATE, TOTAL_FAILURES,
+    TOTAL_INPUT_TOKENS, TOTAL_OUTPUT_TOKENS, TOTAL_REQUESTS, TTFT_SECONDS,
 };
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -100,6 +105,10 @@ pub struct UsageSummary {
     pub active_streams: f64,
     pub active_requests: f64,
     pub tiers: Vec<TierUsage>,
+    /// Token and cost totals per model summed across the tiers serving it,
+    /// present only with `?group_by=model`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub models: Option<Vec<ModelUsage>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -113,34 +122,153 @@ pub struct TierUsage {
     pub cache_creation_tokens: u64,
     pub cost_usd: f64,
     pub avg_duration_seconds: f64,
+    /// Requests and durations split by `stream`/`nonstream`, present only
+    /// with `?by_mode=true`. Token and cost counters are not split.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modes: Option<BTreeMap<RequestMode, ModeUsage>>,
+}
+
+/// Usage of one upstream model, whichever providers served it.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ModelUsage {
+    pub model: String,
+    /// Tiers that reported usage for this model.
+    pub tiers: Vec<String>,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub cache_creation_tokens: u64,
+    pub cost_usd: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ModeUsage {
+    pub requests: u64,
+    pub avg_duration_seconds: f64,
+}
+
+/// Query parameters accepted by /v1/usage and /v1/latencies.
+#[derive(Debug, Default, Deserialize)]
+pub struct ProviderFilterQuery {
+    /// Comma-separated provider or tier names, e.g. `?providers=zai,deepseek`.
+    #[serde(default)]
+    pub providers: Option<String>,
+}
+
+impl ProviderFilterQuery {
+    pub fn filter(&self) -> ProviderFilter {
+        provider_filter(self.providers.as_deref())
+    }
+}
+
+/// Query parameters accepted by /v1/usage.
+#[derive(Debug, Default, Deserialize)]
+pub struct UsageQuery {
+    /// Comma-separated provider or tier names, as for `ProviderFilterQuery`.
+    #[serde(default)]
+    pub providers: Option<String>,
+    /// Break each tier's requests and durations down by mode.
+    #[serde(default)]
+    pub by_mode: bool,
+    /// Add a breakdown summed over another dimension, e.g. `?group_by=model`.
+    #[serde(default)]
+    pub group_by: Option<UsageGrouping>,
+}
+
+/// Extra aggregation requested from /v1/usage with `?group_by=`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UsageGrouping {
+    /// Sum token and cost usage per upstream model across tiers.
+    Model,
+}
+
+impl UsageQuery {
+    pub fn filter(&self) -> ProviderFilter {
+        provider_filter(self.providers.as_deref())
+    }
+}
+
+fn provider_filter(providers: Option<&str>) -> ProviderFilter {
+    providers.map(ProviderFilter::parse).unwrap_or_default()
+}
+
+/// Restricts per-tier output to a set of providers or tiers.
+///
+/// A name matches a tier exactly, or as the provider prefix of a full
+/// `provider,model` tier. An empty filter matches everything.
+#[derive(Debug, Clone, Default)]
+pub struct ProviderFilter {
+    names: Vec<String>,
+}
+
+impl ProviderFilter {
+    /// Parse a comma-separated list, ignoring blank entries.
+    pub fn parse(spec: &str) -> Self {
+        Self {
+            names: spec
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(str::to_string)
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn matches(&self, tier: &str) -> bool {
+        self.is_empty()
+            || self.names.iter().any(|name| {
+                tier == name
+                    || tier
+                        .strip_prefix(name.as_str())
+                        .is_some_and(|rest| rest.starts_with(','))
+            })
+    }
 }
 
 /// Handler for GET /v1/usage - returns JSON usage summary.
-pub async fn usage_handler() -> impl IntoResponse {
+///
+/// With `?providers=`, only matching tiers are listed and the request/token
+/// totals are summed over those tiers instead of the global counters. With
+/// `?by_mode=true`, each tier also lists its stream and nonstream requests.
+/// With `?group_by=model`, a `models` list sums usage per model over the
+/// (filtered) tiers that served it.
+pub async fn usage_handler(
+    State(state): State<AppState>,
+    Query(query): Query<UsageQuery>,
+) -> impl IntoResponse {
     debug!("usage_handler called");
+    let filter = query.filter();
     let mut tiers: HashMap<String, TierUsage> = HashMap::new();
 
-    // Collect per-tier request counts
+    // Collect per-tier request counts, summed over modes
+    let mut mode_usage: HashMap<(String, RequestMode), ModeUsage> = HashMap::new();
     let req_metrics: Vec<prometheus::proto::MetricFamily> = REQUESTS_TOTAL.collect();
     for mf in &req_metrics {
         for m in mf.get_metric() {
-            for label in m.get_label() {
-                if label.name() == "tier" {
-                    let tier = label.value().to_string();
-                    let entry = tiers.entry(tier.clone()).or_insert_with(|| TierUsage {
-                        tier,
-                        requests: 0,
-                        failures: 0,
-                        input_tokens: 0,
-                        output_tokens: 0,
-                        cache_read_tokens: 0,
-                        cache_creation_tokens: 0,
-                        cost_usd: 0.0,
-                        avg_duration_seconds: 0.0,
-                    });
-                    entry.requests = m.get_counter().value() as u64;
-                }
-            }
+            let Some((tier, mode)) = tier_and_mode(m) else {
+                continue;
+            };
+            let tier = tier.to_string();
+            let requests = m.get_counter().value() as u64;
+            let entry = tiers.entry(tier.clone()).or_insert_with(|| TierUsage {
+                tier: tier.clone(),
+                requests: 0,
+                failures: 0,
+                input_tokens: 0,
+                output_tokens: 0,
+                cache_read_tokens: 0,
+                cache_creation_tokens: 0,
+                cost_usd: 0.0,
+                avg_duration_seconds: 0.0,
+                modes: None,
+            });
+            entry.requests += requests;
+            mode_usage.entry((tier, mode)).or_default().requests = requests;
         }
     }
 
@@ -165,6 +293,7 @@ pub async fn usage_handler() -> impl IntoResponse {
                     cache_creation_tokens: 0,
                     cost_usd: 0.0,
                     avg_duration_seconds: 0.0,
+                    modes: None,
                 });
                 entry.failures += m.get_counter().value() as u64;
             }
@@ -249,68 +378,140 @@ pub async fn usage_handler() -> impl IntoResponse {
     }
 
     // Collect avg durations (live histogram + persisted offset histogram)
-    let mut duration_processed_tiers: HashSet<String> = HashSet::new();
+    let mut durations: HashMap<(String, RequestMode), (f64, u64)> = HashMap::new();
     let dur_metrics: Vec<prometheus::proto::MetricFamily> = REQUEST_DURATION.collect();
     for mf in &dur_metrics {
         for m in mf.get_metric() {
-            for label in m.get_label() {
-                if label.name() == "tier" {
-                    let tier = label.value().to_string();
-                    if let Some(entry) = tiers.get_mut(&tier) {
-                        let h = m.get_histogram();
-                        let mut sample_sum = h.get_sample_sum();
-                        let mut count = h.get_sample_count();
-                        if let Some(offset) =
-                            get_hist_offset(METRIC_REQUEST_DURATION_SECONDS, &[("tier", &tier)])
-                        {
-                            sample_sum += offset.sample_sum;
-                            count += offset.sample_count;
-                        }
-                        if count > 0 {
-                            entry.avg_duration_seconds = sample_sum / count as f64;
-                        }
-                        duration_processed_tiers.insert(tier);
-                    }
-                }
+            if let Some((tier, mode)) = tier_and_mode(m) {
+                let h = m.get_histogram();
+                durations.insert(
+                    (tier.to_string(), mode),
+                    (h.get_sample_sum(), h.get_sample_count()),
+                );
             }
         }
     }
-
-    // Fill duration averages for tiers that only exist in restored histogram offsets.
     for entry in tiers.values_mut() {
-        if duration_processed_tiers.contains(&entry.tier) {
-            continue;
+        let (mut tier_sum, mut tier_count) = (0.0, 0);
+        for mode in [RequestMode::Stream, RequestMode::NonStream] {
+            let key = (entry.tier.clone(), mode);
+            let (mut sample_sum, mut count) = durations.get(&key).copied().unwrap_or_default();
+            if let Some(offset) = get_hist_offset(
+                METRIC_REQUEST_DURATION_SECONDS,
+                &[("tier", &entry.tier), ("mode", mode.label())],
+            ) {
+                sample_sum += offset.sample_sum;
+                count += offset.sample_count;
+            }
+            tier_sum += sample_sum;
+            tier_count += count;
+            if count > 0 {
+                mode_usage.entry(key).or_default().avg_duration_seconds = sample_sum / count as f64;
+            }
         }
-        if let Some(offset) =
-            get_hist_offset(METRIC_REQUEST_DURATION_SECONDS, &[("tier", &entry.tier)])
-        {
-            if offset.sample_count > 0 {
-                entry.avg_duration_seconds = offset.sample_sum / offset.sample_count as f64;
+        if tier_count > 0 {
+            entry.avg_duration_seconds = tier_sum / tier_count as f64;
+        }
+    }
+
+    if query.by_mode {
+        for ((tier, mode), usage) in mode_usage {
+            if let Some(entry) = tiers.get_mut(&tier) {
+                entry
+                    .modes
+                    .get_or_insert_with(BTreeMap::new)
+                    .insert(mode, usage);
             }
         }
     }
 
-    let mut tier_list: Vec<TierUsage> = tiers.into_values().collect();
+    let mut tier_list: Vec<TierUsage> = tiers
+        .into_values()
+        .filter(|t| filter.matches(&t.tier))
+        .collect();
     tier_list.sort_by(|a, b| a.tier.cmp(&b.tier));
 
     // `+ 0.0` normalizes a `-0.0` sum (which serializes as an ugly "-0.0")
     // back to positive zero when no priced traffic has accrued.
     let total_cost_usd: f64 = tier_list.iter().map(|t| t.cost_usd).sum::<f64>() + 0.0;
 
+    let (total_requests, total_failures, total_input_tokens, total_output_tokens) =
+        if filter.is_empty() {
+            (
+                TOTAL_REQUESTS.load(Ordering::Relaxed),
+                TOTAL_FAILURES.load(Ordering::Relaxed),
+                TOTAL_INPUT_TOKENS.load(Ordering::Relaxed),
+                TOTAL_OUTPUT_TOKENS.load(Ordering::Relaxed),
+            )
+        } else {
+            tier_list.iter().fold((0, 0, 0, 0), |acc, t| {
+                (
+                    acc.0 + t.requests,
+                    acc.1 + t.failures,
+                    acc.2 + t.input_tokens,
+                    acc.3 + t.output_tokens,
+                )
+            })
+        };
+
+    let models = (query.group_by == Some(UsageGrouping::Model))
+        .then(|| model_usage(&state.config.load(), &tier_list));
     let summary = UsageSummary {
-        total_requests: TOTAL_REQUESTS.load(Ordering::Relaxed),
-        total_failures: TOTAL_FAILURES.load(Ordering::Relaxed),
-        total_input_tokens: TOTAL_INPUT_TOKENS.load(Ordering::Relaxed),
-        total_output_tokens: TOTAL_OUTPUT_TOKENS.load(Ordering::Relaxed),
+        total_requests,
+        total_failures,
+        total_input_tokens,
+        total_output_tokens,
         total_cost_usd,
         active_streams: ACTIVE_STREAMS.get(),
         active_requests: ACTIVE_REQUESTS.get(),
         tiers: tier_list,
+        models,
     };
 
     Json(summary)
 }
 
+/// Per-model token and cost totals over `tiers`. A tier label names a
+/// provider (or its `tier_name`), so its usage is attributed to a model only
+/// when the providers behind that label list exactly one model between them.
+fn model_usage(config: &Config, tiers: &[TierUsage]) -> Vec<ModelUsage> {
+    let mut models: BTreeMap<String, ModelUsage> = BTreeMap::new();
+    for tier in tiers {
+        let Some(model) = tier_model(config, &tier.tier) else {
+            continue;
+        };
+        let usage = models
+            .entry(model.to_string())
+            .or_insert_with(|| ModelUsage {
+                model: model.to_string(),
+                ..Default::default()
+            });
+        usage.tiers.push(tier.tier.clone());
+        usage.input_tokens += tier.input_tokens;
+        usage.output_tokens += tier.output_tokens;
+        usage.cache_read_tokens += tier.cache_read_tokens;
+        usage.cache_creation_tokens += tier.cache_creation_tokens;
+        usage.cost_usd += tier.cost_usd;
+    }
+    models.into_values().collect()
+}
+
+/// The one model the providers labelled `tier` serve, if they serve exactly
+/// one.
+fn tier_model<'a>(config: &'a Config, tier: &str) -> Option<&'a str> {
+    let models: BTreeSet<&str> = config
+        .providers()
+        .iter()
+        .filter(|p| p.tier_name.as_deref().unwrap_or(&p.name) == tier)
+        .flat_map(|p| p.models.iter().map(String::as_str))
+        .collect();
+    if models.len() == 1 {
+        models.into_iter().next()
+    } else {
+        None
+    }
+}
+
 pub async fn frontend_metrics_handler() -> impl IntoResponse {
     let mut frontend_metrics: HashMap<String, FrontendMetrics> = HashMap::new();
 
@@ -532,10 +733,16 @@ pub async fn throughput_handler() -> impl IntoResponse {
     Json(tier_list)
 }
 
-pub async fn metrics_handler() -> impl IntoResponse {
+pub async fn metrics_handler(headers: HeaderMap) -> impl IntoResponse {
     let encoder = TextEncoder::new();
     let mut metric_families = prometheus::gather();
     merge_histogram_offsets(&mut metric_families);
+    if openmetrics::wants_openmetrics(&headers) {
+        return (
+            [("content-type", openmetrics::OPENMETRICS_CONTENT_TYPE)],
+            openmetrics::encode(&metric_families).into_bytes(),
+        );
+    }
     let mut buffer = vec![];
     encoder.encode(&metric_families, &mut buffer).unwrap();
 
@@ -548,17 +755,71 @@ pub struct TierLatency {
     pub tier: String,
     pub ewma_seconds: f64,
     pub sample_count: u64,
+    /// Effective EWMA smoothing factor for this tier.
+    #[serde(default)]
+    pub alpha: f64,
+    /// Fewer than `ewmaMinSamples` samples, so the tier is tried ahead of
+    /// measured tiers rather than ranked by its EWMA.
+    #[serde(default)]
+    pub cold_start: bool,
+    /// Latency quantiles over the tier's recent successful attempts.
+    #[serde(default)]
+    pub p50_seconds: Option<f64>,
+    #[serde(default)]
+    pub p95_seconds: Option<f64>,
+    #[serde(default)]
+    pub p99_seconds: Option<f64>,
+    /// Requests left in the provider's quota window, from its rate-limit
+    /// headers.
+    #[serde(default)]
+    pub rate_limit_remaining: Option<u32>,
+    /// Seconds until the provider's quota window resets.
+    #[serde(default)]
+    pub rate_limit_reset_seconds: Option<f64>,
+    /// Seconds left on the tier's backoff from a 429.
+    #[serde(default)]
+    pub backoff_remaining_seconds: Option<f64>,
+    /// The tier has failed `requireHealthy.failure_threshold` times in a row
+    /// within the cooldown.
+    #[serde(default)]
+    pub circuit_open: bool,
 }
 
-/// Handler for GET /v1/latencies - returns per-tier EWMA latencies as JSON.
-pub fn get_latency_entries(tracker: &EwmaTracker) -> Vec<TierLatency> {
+/// Handler for GET /v1/latencies - returns per-tier EWMA latencies as JSON,
+/// restricted to tiers accepted by `filter`.
+pub fn get_latency_entries(
+    tracker: &EwmaTracker,
+    ratelimit: &RateLimitTracker,
+    config: &Config,
+    filter: &ProviderFilter,
+) -> Vec<TierLatency> {
+    let min_samples = config.ewma_min_samples();
+    let circuit = &config.router().require_healthy;
     tracker
         .get_all_latencies()
         .into_iter()
-        .map(|(tier, ewma, count)| TierLatency {
-            tier,
-            ewma_seconds: ewma,
-            sample_count: count,
+        .filter(|(tier, _, _)| filter.matches(tier))
+        .map(|(tier, ewma, count)| {
+            let quantiles = tracker.get_quantiles(&tier);
+            let rate_limit = ratelimit.status(&tier);
+            TierLatency {
+                alpha: config.ewma_alpha(&tier),
+                cold_start: count < min_samples,
+                p50_seconds: quantiles.map(|q| q.p50),
+                p95_seconds: quantiles.map(|q| q.p95),
+                p99_seconds: quantiles.map(|q| q.p99),
+                rate_limit_remaining: rate_limit.remaining,
+                rate_limit_reset_seconds: rate_limit.reset_in.map(|d| d.as_secs_f64()),
+                backoff_remaining_seconds: rate_limit.backoff_remaining.map(|d| d.as_secs_f64()),
+                circuit_open: tracker.is_circuit_open(
+                    &tier,
+                    circuit.failure_threshold,
+                    circuit.cooldown(),
+                ),
+                tier,
+                ewma_seconds: ewma,
+                sample_count: count,
+            }
         })
         .collect()
 }
@@ -2,6 +2,8 @@
 mod handlers;
 pub use handlers::*;
 
+mod openmetrics;
+
 mod persistence;
 use persistence::*;
 pub use persistence::{clear_redis_persistence, init_persistence};
@@ -25,15 +27,15 @@ use crate::routing::EwmaTracker;
 lazy_static! {
     static ref REQUESTS_TOTAL: CounterVec = register_counter_vec!(
         "ccr_requests_total",
-        "Total number of requests per tier",
-        &["tier"]
+        "Total number of requests per tier and mode (stream or nonstream)",
+        &["tier", "mode"]
     )
     .unwrap();
 
     static ref REQUEST_DURATION: HistogramVec = register_histogram_vec!(
         "ccr_request_duration_seconds",
-        "Request duration in seconds per tier",
-        &["tier"],
+        "Request duration in seconds per tier and mode (stream or nonstream)",
+        &["tier", "mode"],
         vec![0.1, 0.5, 1.0, 2.0, 5.0, 10.0, 30.0, 60.0]
     )
     .unwrap();
@@ -73,6 +75,12 @@ lazy_static! {
     )
     .unwrap();
 
+    static ref UPSTREAM_INFLIGHT: Gauge = register_gauge!(
+        "ccr_upstream_inflight",
+        "Current number of upstream requests holding a global concurrency permit"
+    )
+    .unwrap();
+
     static ref ACTIVE_REQUESTS: Gauge = register_gauge!(
         "ccr_active_requests",
         "Current number of in-flight requests (streaming or non-streaming)"
@@ -110,14 +118,22 @@ lazy_static! {
 
     static ref TIER_EWMA_LATENCY: GaugeVec = register_gauge_vec!(
         "ccr_tier_ewma_latency_seconds",
-        "EWMA latency per tier in seconds (alpha=0.3)",
+        "EWMA latency per tier in seconds",
         &["tier"]
     )
     .unwrap();
 
-    static ref STREAM_BACKPRESSURE: Counter = register_counter!(
+    static ref TIER_LATENCY_QUANTILE: GaugeVec = register_gauge_vec!(
+        "ccr_tier_latency_quantile",
+        "Per-tier latency quantile in seconds over the recent-sample window",
+        &["tier", "quantile"]
+    )
+    .unwrap();
+
+    static ref STREAM_BACKPRESSURE: CounterVec = register_counter_vec!(
         "ccr_stream_backpressure_total",
-        "Number of times an SSE stream producer blocked due to full channel buffer"
+        "Number of times an SSE stream producer blocked due to full channel buffer, per tier",
+        &["tier"]
     )
     .unwrap();
 
@@ -156,6 +172,61 @@ lazy_static! {
     )
     .unwrap();
 
+    static ref MAINTENANCE_REJECTIONS: Counter = register_counter!(
+        "ccr_maintenance_rejections_total",
+        "Proxy requests rejected while maintenance mode was on"
+    )
+    .unwrap();
+
+    static ref INGRESS_THROTTLED: CounterVec = register_counter_vec!(
+        "ccr_ingress_throttled_total",
+        "Proxy requests rejected by INGRESS_RATE_LIMIT, by whether the client was keyed by API key or IP",
+        &["by"]
+    )
+    .unwrap();
+
+    static ref SOFT_ERRORS: CounterVec = register_counter_vec!(
+        "ccr_soft_errors_total",
+        "Number of HTTP 200 responses per tier whose body was a provider error",
+        &["tier"]
+    )
+    .unwrap();
+
+    static ref CONNECTION_REUSED: CounterVec = register_counter_vec!(
+        "ccr_connection_reused_total",
+        "Upstream requests per provider sent over a pooled connection",
+        &["provider"]
+    )
+    .unwrap();
+
+    static ref CONNECTION_NEW: CounterVec = register_counter_vec!(
+        "ccr_connection_new_total",
+        "Upstream requests per provider that opened a new connection",
+        &["provider"]
+    )
+    .unwrap();
+
+    static ref HEDGE_WINS: CounterVec = register_counter_vec!(
+        "ccr_hedge_wins_total",
+        "Hedged races per winning tier; attempt is \"hedge\" when the hedged tier answered first",
+        &["tier", "attempt"]
+    )
+    .unwrap();
+
+    static ref WARM_REQUESTS: CounterVec = register_counter_vec!(
+        "ccr_warm_requests_total",
+        "Prompt-cache warm-up requests sent for warm presets, per tier and outcome",
+        &["tier", "outcome"]
+    )
+    .unwrap();
+
+    static ref NONSTANDARD_ROLES: CounterVec = register_counter_vec!(
+        "ccr_nonstandard_role_total",
+        "Number of responses per tier whose assistant role was not \"assistant\"",
+        &["tier"]
+    )
+    .unwrap();
+
     // Token drift verification: absolute difference between local estimate and upstream reported
     static ref TOKEN_DRIFT_ABS: GaugeVec = register_gauge_vec!(
         "ccr_token_drift_absolute",
@@ -187,6 +258,18 @@ lazy_static! {
     )
     .unwrap();
 
+    // Time from sending the upstream request to the first content, reasoning,
+    // or tool_use delta of its stream. Unlike `ccr_ttft_seconds`, which starts
+    // when response headers arrive and feeds `/v1/throughput`, this includes
+    // the upstream's wait before answering.
+    static ref TIME_TO_FIRST_TOKEN_SECONDS: HistogramVec = register_histogram_vec!(
+        "ccr_time_to_first_token_seconds",
+        "Time from upstream dispatch to the first content delta of a stream, per tier",
+        &["tier"],
+        vec![0.1, 0.25, 0.5, 1.0, 2.0, 3.0, 5.0, 10.0, 20.0, 30.0, 60.0]
+    )
+    .unwrap();
+
     // Output throughput: tokens per second
     static ref OUTPUT_TOKENS_PER_SECOND: HistogramVec = register_histogram_vec!(
         "ccr_output_tokens_per_second",
@@ -216,14 +299,19 @@ const METRIC_PRE_REQUEST_TOKENS_TOTAL: &str = "ccr_pre_request_tokens_total";
 const METRIC_PRE_REQUEST_TOKENS: &str = "ccr_pre_request_tokens";
 const METRIC_RATE_LIMIT_HITS_TOTAL: &str = "ccr_rate_limit_hits_total";
 const METRIC_RATE_LIMIT_BACKOFFS_TOTAL: &str = "ccr_rate_limit_backoffs_total";
+const METRIC_SOFT_ERRORS_TOTAL: &str = "ccr_soft_errors_total";
+const METRIC_NONSTANDARD_ROLE_TOTAL: &str = "ccr_nonstandard_role_total";
 const METRIC_TIER_EWMA_LATENCY_SECONDS: &str = "ccr_tier_ewma_latency_seconds";
 const METRIC_TOKEN_DRIFT_ABSOLUTE: &str = "ccr_token_drift_absolute";
 const METRIC_TOKEN_DRIFT_PCT: &str = "ccr_token_drift_pct";
 const METRIC_TOKEN_DRIFT_ALERTS_TOTAL: &str = "ccr_token_drift_alerts_total";
 const METRIC_TTFT_SECONDS: &str = "ccr_ttft_seconds";
+const METRIC_TIME_TO_FIRST_TOKEN_SECONDS: &str = "ccr_time_to_first_token_seconds";
 const METRIC_OUTPUT_TOKENS_PER_SECOND: &str = "ccr_output_tokens_per_second";
 
 const REQUEST_DURATION_BUCKETS: &[f64] = &[0.1, 0.5, 1.0, 2.0, 5.0, 10.0, 30.0, 60.0];
+const TIME_TO_FIRST_TOKEN_BUCKETS: &[f64] =
+    &[0.1, 0.25, 0.5, 1.0, 2.0, 3.0, 5.0, 10.0, 20.0, 30.0, 60.0];
 const PRE_REQUEST_TOKENS_BUCKETS: &[f64] = &[
     100.0, 500.0, 1000.0, 2000.0, 5000.0, 10000.0, 25000.0, 50000.0, 100000.0, 200000.0,
 ];
@@ -303,16 +391,71 @@ fn frontend_label(frontend: FrontendType) -> &'static str {
     }
 }
 
-pub fn record_request(tier: &str) {
-    REQUESTS_TOTAL.with_label_values(&[tier]).inc();
-    TOTAL_REQUESTS.fetch_add(1, Ordering::Relaxed);
-    persist_counter_inc(METRIC_REQUESTS_TOTAL, &[("tier", tier)], 1.0);
+/// Whether a request asked for a streamed response. These are the only two
+/// values of the `mode` label, so it at most doubles the per-tier series.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RequestMode {
+    Stream,
+    NonStream,
+}
+
+impl RequestMode {
+    pub fn from_stream(stream: bool) -> Self {
+        if stream {
+            Self::Stream
+        } else {
+            Self::NonStream
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Stream => "stream",
+            Self::NonStream => "nonstream",
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Self> {
+        match label {
+            "stream" => Some(Self::Stream),
+            "nonstream" => Some(Self::NonStream),
+            _ => None,
+        }
+    }
+}
+
+/// Value of the label `name` on a gathered series.
+fn metric_label<'a>(metric: &'a prometheus::proto::Metric, name: &str) -> Option<&'a str> {
+    metric
+        .get_label()
+        .iter()
+        .find(|pair| pair.name() == name)
+        .map(|pair| pair.value())
 }
 
-pub fn record_request_with_frontend(tier: &str, frontend: FrontendType) {
-    REQUESTS_TOTAL.with_label_values(&[tier]).inc();
+/// The `tier` and `mode` labels of a request counter or duration series.
+fn tier_and_mode(metric: &prometheus::proto::Metric) -> Option<(&str, RequestMode)> {
+    Some((
+        metric_label(metric, "tier")?,
+        RequestMode::from_label(metric_label(metric, "mode")?)?,
+    ))
+}
+
+pub fn record_request(tier: &str, mode: RequestMode) {
+    REQUESTS_TOTAL
+        .with_label_values(&[tier, mode.label()])
+        .inc();
     TOTAL_REQUESTS.fetch_add(1, Ordering::Relaxed);
-    persist_counter_inc(METRIC_REQUESTS_TOTAL, &[("tier", tier)], 1.0);
+    persist_counter_inc(
+        METRIC_REQUESTS_TOTAL,
+        &[("tier", tier), ("mode", mode.label())],
+        1.0,
+    );
+}
+
+pub fn record_request_with_frontend(tier: &str, mode: RequestMode, frontend: FrontendType) {
+    record_request(tier, mode);
     FRONTEND_REQUESTS_TOTAL
         .with_label_values(&[frontend_label(frontend)])
         .inc();
@@ -325,19 +468,26 @@ pub fn record_request_with_frontend(tier: &str, frontend: FrontendType) {
 
 /// Record request duration in the Prometheus histogram. EWMA tracking is handled
 /// by `routing::EwmaTracker` directly; this only updates the histogram.
-pub fn record_request_duration(tier: &str, duration: f64) {
+pub fn record_request_duration(tier: &str, mode: RequestMode, duration: f64) {
     REQUEST_DURATION
-        .with_label_values(&[tier])
+        .with_label_values(&[tier, mode.label()])
         .observe(duration);
-    persist_histogram_observe(METRIC_REQUEST_DURATION_SECONDS, &[("tier", tier)], duration);
+    openmetrics::record_duration_exemplar(tier, mode, duration);
+    persist_histogram_observe(
+        METRIC_REQUEST_DURATION_SECONDS,
+        &[("tier", tier), ("mode", mode.label())],
+        duration,
+    );
 }
 
 /// Record request duration with frontend information.
-pub fn record_request_duration_with_frontend(tier: &str, duration: f64, frontend: FrontendType) {
-    REQUEST_DURATION
-        .with_label_values(&[tier])
-        .observe(duration);
-    persist_histogram_observe(METRIC_REQUEST_DURATION_SECONDS, &[("tier", tier)], duration);
+pub fn record_request_duration_with_frontend(
+    tier: &str,
+    mode: RequestMode,
+    duration: f64,
+    frontend: FrontendType,
+) {
+    record_request_duration(tier, mode, duration);
     FRONTEND_REQUEST_LATENCY
         .with_label_values(&[frontend_label(frontend)])
         .observe(duration);
@@ -348,13 +498,35 @@ pub fn record_request_duration_with_frontend(tier: &str, duration: f64, frontend
     );
 }
 
-/// Sync the Prometheus EWMA gauge from the routing tracker. Called after the
-/// tracker records a success or failure so the gauge stays in sync for scraping.
+/// Drop the EWMA and quantile gauges for tiers whose tracker state was
+/// reset, and delete their persisted EWMA state so a restart does not
+/// restore it.
+pub fn clear_ewma_state(tiers: &[String]) {
+    for tier in tiers {
+        let _ = TIER_EWMA_LATENCY.remove_label_values(&[tier]);
+        for label in ["0.5", "0.95", "0.99"] {
+            let _ = TIER_LATENCY_QUANTILE.remove_label_values(&[tier, label]);
+        }
+        persist_ewma_clear(tier);
+    }
+}
+
+/// Sync the Prometheus EWMA and quantile gauges from the routing tracker.
+/// Called after the tracker records a success or failure so the gauges stay
+/// in sync for scraping.
 pub fn sync_ewma_gauge(tracker: &EwmaTracker) {
     for (tier, ewma, count) in tracker.get_all_latencies() {
         TIER_EWMA_LATENCY.with_label_values(&[&tier]).set(ewma);
         persist_gauge_set(METRIC_TIER_EWMA_LATENCY_SECONDS, &[("tier", &tier)], ewma);
         persist_ewma_state(&tier, ewma, count);
+        // The latency window lives in memory only, so quantiles are not persisted.
+        if let Some(q) = tracker.get_quantiles(&tier) {
+            for (label, value) in [("0.5", q.p50), ("0.95", q.p95), ("0.99", q.p99)] {
+                TIER_LATENCY_QUANTILE
+                    .with_label_values(&[&tier, label])
+                    .set(value);
+            }
+        }
     }
 }
 
@@ -383,14 +555,19 @@ pub fn increment_active_streams(delta: i64) {
     }
 }
 
-/// Record that an SSE producer hit a full channel buffer (backpressure event).
-pub fn record_stream_backpressure() {
-    STREAM_BACKPRESSURE.inc();
-    persist_counter_inc(METRIC_STREAM_BACKPRESSURE_TOTAL, &[], 1.0);
+/// Set the number of upstream requests in flight across all tiers.
+pub fn set_upstream_inflight(count: usize) {
+    UPSTREAM_INFLIGHT.set(count as f64);
+}
+
+/// Record that an SSE producer for `tier` hit a full channel buffer
+/// (backpressure event).
+pub fn record_stream_backpressure(tier: &str) {
+    STREAM_BACKPRESSURE.with_label_values(&[tier]).inc();
+    persist_counter_inc(METRIC_STREAM_BACKPRESSURE_TOTAL, &[("tier", tier)], 1.0);
 }
 
 /// Record that a stream request was rejected due to concurrency limit.
-#[allow(dead_code)]
 pub fn record_rejected() {
     REJECTED_STREAMS.inc();
     persist_counter_inc(METRIC_REJECTED_STREAMS_TOTAL, &[], 1.0);
@@ -402,6 +579,75 @@ pub fn record_rate_limit_hit(tier: &str) {
     persist_counter_inc(METRIC_RATE_LIMIT_HITS_TOTAL, &[("tier", tier)], 1.0);
 }
 
+/// Count a proxy request rejected by maintenance mode.
+pub fn record_maintenance_rejection() {
+    MAINTENANCE_REJECTIONS.inc();
+}
+
+/// Count a proxy request rejected by the ingress limit; `by` is `key` or `ip`.
+pub fn record_ingress_throttled(by: &str) {
+    INGRESS_THROTTLED.with_label_values(&[by]).inc();
+}
+
+/// Count a provider error returned with HTTP 200 for a tier.
+pub fn record_soft_error(tier: &str) {
+    SOFT_ERRORS.with_label_values(&[tier]).inc();
+    persist_counter_inc(METRIC_SOFT_ERRORS_TOTAL, &[("tier", tier)], 1.0);
+}
+
+/// Count an upstream request as opening a new connection or reusing one.
+pub fn record_upstream_connection(provider: &str, new: bool) {
+    let counter = if new {
+        &*CONNECTION_NEW
+    } else {
+        &*CONNECTION_REUSED
+    };
+    counter.with_label_values(&[provider]).inc();
+}
+
+/// Count the tier that answered first in a hedged race.
+pub fn record_hedge_win(tier: &str, hedged: bool) {
+    let attempt = if hedged { "hedge" } else { "primary" };
+    HEDGE_WINS.with_label_values(&[tier, attempt]).inc();
+}
+
+/// Count a preset warm-up request. Warm-ups are kept out of the regular
+/// request, token, and latency series.
+pub fn record_warm_request(tier: &str, outcome: WarmOutcome) {
+    WARM_REQUESTS
+        .with_label_values(&[tier, outcome.label()])
+        .inc();
+}
+
+/// Result of a preset warm-up, the `outcome` label of
+/// `ccr_warm_requests_total`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarmOutcome {
+    /// The provider answered with a 2xx.
+    Ok,
+    /// The tier was in rate-limit backoff and was not contacted, or it
+    /// answered 429.
+    RateLimited,
+    /// The request could not be sent or the provider returned another error.
+    Failed,
+}
+
+impl WarmOutcome {
+    pub fn label(self) -> &'static str {
+        match self {
+            WarmOutcome::Ok => "ok",
+            WarmOutcome::RateLimited => "rate_limited",
+            WarmOutcome::Failed => "failed",
+        }
+    }
+}
+
+/// Count a response whose role had to be normalized to `assistant`.
+pub fn record_nonstandard_role(tier: &str) {
+    NONSTANDARD_ROLES.with_label_values(&[tier]).inc();
+    persist_counter_inc(METRIC_NONSTANDARD_ROLE_TOTAL, &[("tier", tier)], 1.0);
+}
+
 /// Persist 429 backoff counter state managed by `ratelimit.rs`.
 pub fn record_rate_limit_backoff(tier: &str) {
     persist_counter_inc(METRIC_RATE_LIMIT_BACKOFFS_TOTAL, &[("tier", tier)], 1.0);
@@ -433,6 +679,18 @@ pub fn record_ttft(tier: &str, ttft_secs: f64) {
     persist_histogram_observe(METRIC_TTFT_SECONDS, &[("tier", tier)], ttft_secs);
 }
 
+/// Record the time from dispatching a streaming request to `tier` until its
+/// first content, reasoning, or tool_use delta arrived.
+///
+/// [`record_ttft`] measures the same delta from the response headers instead,
+/// so the difference between the two is the upstream's time to answer.
+pub fn record_time_to_first_token(tier: &str, secs: f64) {
+    TIME_TO_FIRST_TOKEN_SECONDS
+        .with_label_values(&[tier])
+        .observe(secs);
+    persist_histogram_observe(METRIC_TIME_TO_FIRST_TOKEN_SECONDS, &[("tier", tier)], secs);
+}
+
 /// Record output token throughput (tok/s) for a tier.
 ///
 /// `output_tokens` is the number of output tokens generated. `generation_secs`
@@ -499,6 +757,25 @@ fn count_tokens_json(value: &serde_json::Value) -> u64 {
     BPE.encode_ordinary(&text).len() as u64
 }
 
+/// Estimate the token count of a text fragment with the cl100k_base tokenizer.
+pub fn count_text_tokens(text: &str) -> u64 {
+    BPE.encode_ordinary(text).len() as u64
+}
+
+/// Keep at most `max_tokens` leading tokens of `text`. A cut that would split
+/// a multi-byte character drops the partial token instead.
+pub fn truncate_text_to_tokens(text: &str, max_tokens: u64) -> String {
+    let mut tokens = BPE.encode_ordinary(text);
+    tokens.truncate(max_tokens as usize);
+    while !tokens.is_empty() {
+        if let Ok(decoded) = BPE.decode(tokens.clone()) {
+            return decoded;
+        }
+        tokens.pop();
+    }
+    String::new()
+}
+
 /// Pre-request token audit: estimate the number of input tokens from the
 /// request body before dispatching to the backend. Logs per-component counts
 /// and records them to Prometheus for observability.
@@ -669,9 +946,11 @@ pub fn record_cost(tier: &str, cost_usd: f64) {
 /// upstream value comes from the response `usage.input_tokens` field.
 ///
 /// Drift = local_estimate - upstream_reported (positive means we over-estimated).
-pub fn verify_token_usage(tier: &str, local_estimate: u64, upstream_input: u64) {
+/// Returns the drift in percent of the upstream count, or `None` when the
+/// upstream reported no input tokens.
+pub fn verify_token_usage(tier: &str, local_estimate: u64, upstream_input: u64) -> Option<f64> {
     if upstream_input == 0 {
-        return;
+        return None;
     }
 
     let drift_abs = local_estimate as i64 - upstream_input as i64;
@@ -744,6 +1023,7 @@ pub fn verify_token_usage(tier: &str, local_estimate: u64, upstream_input: u64)
     entry.last_local = local_estimate;
     entry.last_upstream = upstream_input;
     persist_token_drift_state(tier, entry);
+    Some(drift_pct)
 }
 
 #[cfg(test)]
@@ -774,6 +1054,16 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn provider_filter_matches_exact_tier_or_provider_prefix() {
+        let filter = super::ProviderFilter::parse("zai, deepseek,");
+        assert!(filter.matches("zai"));
+        assert!(filter.matches("deepseek,deepseek-chat"));
+        assert!(!filter.matches("zai-coding"));
+        assert!(!filter.matches("openrouter"));
+        assert!(super::ProviderFilter::parse(" , ").matches("openrouter"));
+    }
+
     #[test]
     fn token_audit_records_tool_tokens() {
         use serde_json::json;
@@ -0,0 +1,304 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// OpenMetrics exposition for /metrics.
+//
+// Scrapers that send `Accept: application/openmetrics-text` get this format
+// instead of the Prometheus 0.0.4 text format. It is the only one that can
+// carry exemplars, which link `ccr_request_duration_seconds` buckets to the
+// trace of a request that landed in them.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::sync::LazyLock;
+
+use axum::http::{header, HeaderMap};
+use parking_lot::Mutex;
+use prometheus::proto::{Metric, MetricFamily, MetricType};
+
+use super::{
+    tier_and_mode, RequestMode, METRIC_REQUEST_DURATION_SECONDS, REQUEST_DURATION_BUCKETS,
+};
+
+pub(super) const OPENMETRICS_CONTENT_TYPE: &str =
+    "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Latest traced observation that fell into one histogram bucket.
+#[derive(Debug, Clone)]
+struct Exemplar {
+    trace_id: String,
+    value: f64,
+    timestamp: f64,
+}
+
+/// Request-duration exemplars keyed by tier, mode, and bucket upper bound
+/// (as bits).
+type ExemplarKey = (String, RequestMode, u64);
+
+static DURATION_EXEMPLARS: LazyLock<Mutex<HashMap<ExemplarKey, Exemplar>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Whether the client asked for OpenMetrics in its `Accept` header.
+pub(super) fn wants_openmetrics(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .any(|accept| accept.contains("application/openmetrics-text"))
+}
+
+/// Remember the current trace as the exemplar for the bucket `duration`
+/// falls into. A no-op unless OTel tracing is active for this request.
+pub(super) fn record_duration_exemplar(tier: &str, mode: RequestMode, duration: f64) {
+    #[cfg(feature = "otel")]
+    if let Some(trace_id) = crate::otel::current_trace_id() {
+        store_duration_exemplar(tier, mode, duration, trace_id);
+    }
+    #[cfg(not(feature = "otel"))]
+    let _ = (tier, mode, duration);
+}
+
+#[cfg_attr(not(feature = "otel"), allow(dead_code))]
+fn store_duration_exemplar(tier: &str, mode: RequestMode, duration: f64, trace_id: String) {
+    let bound = REQUEST_DURATION_BUCKETS
+        .iter()
+        .copied()
+        .find(|bound| duration <= *bound)
+        .unwrap_or(f64::INFINITY);
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64();
+    DURATION_EXEMPLARS.lock().insert(
+        (tier.to_string(), mode, bound.to_bits()),
+        Exemplar {
+            trace_id,
+            value: duration,
+            timestamp,
+        },
+    );
+}
+
+/// Encode `families` in the OpenMetrics text format, terminated by `# EOF`.
+pub(super) fn encode(families: &[MetricFamily]) -> String {
+    let exemplars = DURATION_EXEMPLARS.lock().clone();
+    let mut out = String::new();
+    for family in families {
+        let name = family.name();
+        let metric_type = family.get_field_type();
+        // OpenMetrics names a counter family without its `_total` suffix.
+        let family_name = match metric_type {
+            MetricType::COUNTER => name.strip_suffix("_total").unwrap_or(name),
+            _ => name,
+        };
+        let type_name = match metric_type {
+            MetricType::COUNTER => "counter",
+            MetricType::GAUGE => "gauge",
+            MetricType::HISTOGRAM => "histogram",
+            MetricType::SUMMARY => "summary",
+            MetricType::UNTYPED => "unknown",
+        };
+        let _ = writeln!(out, "# TYPE {family_name} {type_name}");
+        if !family.help().is_empty() {
+            let _ = writeln!(out, "# HELP {family_name} {}", escape(family.help()));
+        }
+
+        for metric in family.get_metric() {
+            match metric_type {
+                MetricType::COUNTER => {
+                    let value = metric.get_counter().value();
+                    write_sample(&mut out, family_name, "_total", metric, None, value);
+                }
+                MetricType::GAUGE => {
+                    let value = metric.get_gauge().value();
+                    write_sample(&mut out, family_name, "", metric, None, value);
+                }
+                MetricType::UNTYPED => {
+                    let value = metric.untyped.value();
+                    write_sample(&mut out, family_name, "", metric, None, value);
+                }
+                MetricType::HISTOGRAM => {
+                    let histogram = metric.get_histogram();
+                    let series = tier_and_mode(metric);
+                    let mut inf_seen = false;
+                    for bucket in histogram.get_bucket() {
+                        let bound = bucket.upper_bound();
+                        inf_seen |= bound == f64::INFINITY;
+                        write_sample(
+                            &mut out,
+                            family_name,
+                            "_bucket",
+                            metric,
+                            Some(("le", &format_float(bound))),
+                            bucket.cumulative_count() as f64,
+                        );
+                        if name == METRIC_REQUEST_DURATION_SECONDS {
+                            append_exemplar(&mut out, &exemplars, series, bound);
+                        }
+                    }
+                    if !inf_seen {
+                        write_sample(
+                            &mut out,
+                            family_name,
+                            "_bucket",
+                            metric,
+                            Some(("le", "+Inf")),
+                            histogram.get_sample_count() as f64,
+                        );
+                        if name == METRIC_REQUEST_DURATION_SECONDS {
+                            append_exemplar(&mut out, &exemplars, series, f64::INFINITY);
+                        }
+                    }
+                    let sum = histogram.get_sample_sum();
+                    let count = histogram.get_sample_count() as f64;
+                    write_sample(&mut out, family_name, "_sum", metric, None, sum);
+                    write_sample(&mut out, family_name, "_count", metric, None, count);
+                }
+                MetricType::SUMMARY => {
+                    let summary = metric.get_summary();
+                    for quantile in summary.get_quantile() {
+                        write_sample(
+                            &mut out,
+                            family_name,
+                            "",
+                            metric,
+                            Some(("quantile", &format_float(quantile.quantile()))),
+                            quantile.value(),
+                        );
+                    }
+                    let sum = summary.sample_sum();
+                    let count = summary.sample_count() as f64;
+                    write_sample(&mut out, family_name, "_sum", metric, None, sum);
+                    write_sample(&mut out, family_name, "_count", metric, None, count);
+                }
+            }
+        }
+    }
+    out.push_str("# EOF\n");
+    out
+}
+
+/// Write one sample line. `append_exemplar` reopens the line it just wrote.
+fn write_sample(
+    out: &mut String,
+    name: &str,
+    suffix: &str,
+    metric: &Metric,
+    extra_label: Option<(&str, &str)>,
+    value: f64,
+) {
+    out.push_str(name);
+    out.push_str(suffix);
+    let labels = metric
+        .get_label()
+        .iter()
+        .map(|pair| (pair.name(), pair.value()))
+        .chain(extra_label);
+    let mut separator = "{";
+    for (label, label_value) in labels {
+        let _ = write!(out, "{separator}{label}=\"{}\"", escape(label_value));
+        separator = ",";
+    }
+    if separator == "," {
+        out.push('}');
+    }
+    let _ = write!(out, " {}", format_float(value));
+    out.push('\n');
+}
+
+fn append_exemplar(
+    out: &mut String,
+    exemplars: &HashMap<ExemplarKey, Exemplar>,
+    series: Option<(&str, RequestMode)>,
+    bound: f64,
+) {
+    let Some(exemplar) =
+        series.and_then(|(tier, mode)| exemplars.get(&(tier.to_string(), mode, bound.to_bits())))
+    else {
+        return;
+    };
+    out.pop();
+    let _ = writeln!(
+        out,
+        " # {{trace_id=\"{}\"}} {} {:.3}",
+        escape(&exemplar.trace_id),
+        format_float(exemplar.value),
+        exemplar.timestamp
+    );
+}
+
+fn format_float(value: f64) -> String {
+    if value == f64::INFINITY {
+        "+Inf".to_string()
+    } else if value == f64::NEG_INFINITY {
+        "-Inf".to_string()
+    } else if value.is_nan() {
+        "NaN".to_string()
+    } else {
+        format!("{value:?}")
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('\n', r"\n")
+        .replace('"', r#"\""#)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_counters_histograms_and_exemplars() {
+        let registry = prometheus::Registry::new();
+        let counter = prometheus::CounterVec::new(
+            prometheus::Opts::new("ccr_requests_total", "Requests"),
+            &["tier", "mode"],
+        )
+        .unwrap();
+        let histogram = prometheus::HistogramVec::new(
+            prometheus::HistogramOpts::new(METRIC_REQUEST_DURATION_SECONDS, "Duration")
+                .buckets(REQUEST_DURATION_BUCKETS.to_vec()),
+            &["tier", "mode"],
+        )
+        .unwrap();
+        registry.register(Box::new(counter.clone())).unwrap();
+        registry.register(Box::new(histogram.clone())).unwrap();
+        counter.with_label_values(&["om-tier", "stream"]).inc();
+        histogram
+            .with_label_values(&["om-tier", "stream"])
+            .observe(0.3);
+        histogram
+            .with_label_values(&["om-tier", "nonstream"])
+            .observe(0.3);
+        store_duration_exemplar(
+            "om-tier",
+            RequestMode::Stream,
+            0.3,
+            "4bf92f3577b34da6a3ce929d0e0e4736".into(),
+        );
+
+        let text = encode(&registry.gather());
+
+        assert!(text.contains("# TYPE ccr_requests counter\n"), "{text}");
+        assert!(
+            text.contains("ccr_requests_total{mode=\"stream\",tier=\"om-tier\"} 1.0\n"),
+            "{text}"
+        );
+        assert!(text.contains("# TYPE ccr_request_duration_seconds histogram\n"));
+        assert!(text.contains(
+            "ccr_request_duration_seconds_bucket{mode=\"stream\",tier=\"om-tier\",le=\"0.5\"} 1.0 \
+             # {trace_id=\"4bf92f3577b34da6a3ce929d0e0e4736\"} 0.3 "
+        ));
+        assert!(
+            text.contains("ccr_request_duration_seconds_bucket{mode=\"stream\",tier=\"om-tier\",le=\"0.1\"} 0.0\n")
+        );
+        assert!(text
+            .contains("ccr_request_duration_seconds_bucket{mode=\"stream\",tier=\"om-tier\",le=\"+Inf\"} 1.0\n"));
+        // The exemplar belongs to the stream series only.
+        assert!(text.contains(
+            "ccr_request_duration_seconds_bucket{mode=\"nonstream\",tier=\"om-tier\",le=\"0.5\"} 1.0\n"
+        ));
+        assert!(text.ends_with("# EOF\n"));
+    }
+}
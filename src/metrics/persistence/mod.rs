@@ -1,15 +1,26 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
-// Redis persistence for metrics.
+// Metrics persistence.
 //
 // Saves and restores Prometheus counters, gauges, histograms, token drift
-// state, audit log, and EWMA latency state to/from Redis.
+// state, audit log, and EWMA latency state. Metric updates are queued as
+// `PersistEvent`s and written by a worker thread to the `PersistenceBackend`
+// selected by `Persistence.mode`; the same backend supplies the snapshot that
+// is restored on boot. The backend is opened and the snapshot read on that
+// thread too, since the sync Postgres client cannot run on a tokio worker.
 
-use anyhow::{anyhow, Context, Result};
-use redis::Commands;
+#[cfg(feature = "postgres")]
+mod postgres_backend;
+mod redis_backend;
+
+pub use redis_backend::clear_redis_persistence;
+#[cfg(test)]
+pub(super) use redis_backend::key_matches_persistence_prefix;
+
+use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::sync::atomic::Ordering;
-use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
 use std::sync::OnceLock;
 use std::thread;
 use tracing::{info, warn};
@@ -18,32 +29,88 @@ use crate::config::{PersistenceConfig, PersistenceMode};
 use crate::ratelimit::restore_rate_limit_backoff_counter;
 use crate::routing::EwmaTracker;
 
+#[cfg(feature = "postgres")]
+use postgres_backend::PostgresBackend;
+use redis_backend::RedisBackend;
+
 use super::sync_ewma_gauge;
 use super::{
-    PreRequestAuditEntry, TokenDriftEntry, AUDIT_LOG, AUDIT_LOG_CAPACITY,
+    PreRequestAuditEntry, RequestMode, TokenDriftEntry, AUDIT_LOG, AUDIT_LOG_CAPACITY,
     CACHE_CREATION_TOKENS_TOTAL, CACHE_READ_TOKENS_TOTAL, COST_USD_TOTAL, FAILURES_TOTAL,
     FRONTEND_REQUESTS_TOTAL, INPUT_TOKENS_TOTAL, METRIC_CACHE_CREATION_TOKENS_TOTAL,
     METRIC_CACHE_READ_TOKENS_TOTAL, METRIC_COST_USD_TOTAL, METRIC_FAILURES_TOTAL,
     METRIC_FRONTEND_REQUESTS_TOTAL, METRIC_FRONTEND_REQUEST_DURATION_SECONDS,
-    METRIC_INPUT_TOKENS_TOTAL, METRIC_OUTPUT_TOKENS_TOTAL, METRIC_PEAK_ACTIVE_STREAMS,
-    METRIC_PRE_REQUEST_TOKENS, METRIC_PRE_REQUEST_TOKENS_TOTAL, METRIC_RATE_LIMIT_BACKOFFS_TOTAL,
-    METRIC_RATE_LIMIT_HITS_TOTAL, METRIC_REJECTED_STREAMS_TOTAL, METRIC_REQUESTS_TOTAL,
-    METRIC_REQUEST_DURATION_SECONDS, METRIC_STREAM_BACKPRESSURE_TOTAL,
-    METRIC_TIER_EWMA_LATENCY_SECONDS, METRIC_TOKEN_DRIFT_ABSOLUTE, METRIC_TOKEN_DRIFT_ALERTS_TOTAL,
-    METRIC_TOKEN_DRIFT_PCT, OUTPUT_TOKENS_TOTAL, PEAK_ACTIVE_STREAMS, PRE_REQUEST_TOKENS,
-    PRE_REQUEST_TOKENS_BUCKETS, RATE_LIMIT_HITS, REJECTED_STREAMS, REQUESTS_TOTAL,
-    REQUEST_DURATION_BUCKETS, STREAM_BACKPRESSURE, TIER_EWMA_LATENCY, TOKEN_DRIFT_ABS,
+    METRIC_INPUT_TOKENS_TOTAL, METRIC_NONSTANDARD_ROLE_TOTAL, METRIC_OUTPUT_TOKENS_TOTAL,
+    METRIC_PEAK_ACTIVE_STREAMS, METRIC_PRE_REQUEST_TOKENS, METRIC_PRE_REQUEST_TOKENS_TOTAL,
+    METRIC_RATE_LIMIT_BACKOFFS_TOTAL, METRIC_RATE_LIMIT_HITS_TOTAL, METRIC_REJECTED_STREAMS_TOTAL,
+    METRIC_REQUESTS_TOTAL, METRIC_REQUEST_DURATION_SECONDS, METRIC_SOFT_ERRORS_TOTAL,
+    METRIC_STREAM_BACKPRESSURE_TOTAL, METRIC_TIER_EWMA_LATENCY_SECONDS,
+    METRIC_TIME_TO_FIRST_TOKEN_SECONDS, METRIC_TOKEN_DRIFT_ABSOLUTE,
+    METRIC_TOKEN_DRIFT_ALERTS_TOTAL, METRIC_TOKEN_DRIFT_PCT, NONSTANDARD_ROLES,
+    OUTPUT_TOKENS_TOTAL, PEAK_ACTIVE_STREAMS, PRE_REQUEST_TOKENS, PRE_REQUEST_TOKENS_BUCKETS,
+    RATE_LIMIT_HITS, REJECTED_STREAMS, REQUESTS_TOTAL, REQUEST_DURATION_BUCKETS, SOFT_ERRORS,
+    STREAM_BACKPRESSURE, TIER_EWMA_LATENCY, TIME_TO_FIRST_TOKEN_BUCKETS, TOKEN_DRIFT_ABS,
     TOKEN_DRIFT_ALERTS, TOKEN_DRIFT_PCT, TOKEN_DRIFT_STATE, TOTAL_FAILURES, TOTAL_INPUT_TOKENS,
     TOTAL_OUTPUT_TOKENS, TOTAL_REQUESTS,
 };
 
-static REDIS_RUNTIME: OnceLock<RedisRuntime> = OnceLock::new();
+static PERSISTENCE_RUNTIME: OnceLock<PersistenceRuntime> = OnceLock::new();
 
-struct RedisRuntime {
+struct PersistenceRuntime {
     sender: Sender<PersistEvent>,
     histogram_offsets: HistogramOffsetStore,
 }
 
+/// Counters restored on boot.
+const COUNTER_METRICS: [&str; 16] = [
+    METRIC_REQUESTS_TOTAL,
+    METRIC_FRONTEND_REQUESTS_TOTAL,
+    METRIC_FAILURES_TOTAL,
+    METRIC_INPUT_TOKENS_TOTAL,
+    METRIC_OUTPUT_TOKENS_TOTAL,
+    METRIC_CACHE_READ_TOKENS_TOTAL,
+    METRIC_CACHE_CREATION_TOKENS_TOTAL,
+    METRIC_COST_USD_TOTAL,
+    METRIC_STREAM_BACKPRESSURE_TOTAL,
+    METRIC_REJECTED_STREAMS_TOTAL,
+    METRIC_PRE_REQUEST_TOKENS_TOTAL,
+    METRIC_RATE_LIMIT_HITS_TOTAL,
+    METRIC_RATE_LIMIT_BACKOFFS_TOTAL,
+    METRIC_SOFT_ERRORS_TOTAL,
+    METRIC_NONSTANDARD_ROLE_TOTAL,
+    METRIC_TOKEN_DRIFT_ALERTS_TOTAL,
+];
+
+/// Gauges restored on boot.
+const GAUGE_METRICS: [&str; 4] = [
+    METRIC_PEAK_ACTIVE_STREAMS,
+    METRIC_TIER_EWMA_LATENCY_SECONDS,
+    METRIC_TOKEN_DRIFT_ABSOLUTE,
+    METRIC_TOKEN_DRIFT_PCT,
+];
+
+/// Histograms whose totals are merged into `/metrics` output on boot.
+const HISTOGRAM_METRICS: [&str; 4] = [
+    METRIC_REQUEST_DURATION_SECONDS,
+    METRIC_FRONTEND_REQUEST_DURATION_SECONDS,
+    METRIC_PRE_REQUEST_TOKENS,
+    METRIC_TIME_TO_FIRST_TOKEN_SECONDS,
+];
+
+/// Storage for persisted metrics. Opened and called only on the persistence
+/// worker thread.
+trait PersistenceBackend: Send {
+    /// Short name used in logs.
+    fn name(&self) -> &'static str;
+
+    /// Read everything persisted so far.
+    fn load_snapshot(&mut self) -> Result<PersistedSnapshot>;
+
+    /// Apply one update. After an error the backend reconnects on the next
+    /// call; the failed update is dropped.
+    fn persist(&mut self, event: PersistEvent) -> Result<()>;
+}
+
 #[derive(Debug, Clone)]
 enum PersistEvent {
     CounterInc {
@@ -78,6 +145,10 @@ enum PersistEvent {
         ewma: f64,
         samples: u64,
     },
+    /// Delete a tier's EWMA state and its persisted EWMA gauge.
+    EwmaStateClear {
+        tier: String,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -87,7 +158,7 @@ struct PersistedEwmaState {
 }
 
 #[derive(Debug, Clone, Default)]
-struct RedisSnapshot {
+struct PersistedSnapshot {
     counters: HashMap<&'static str, HashMap<String, f64>>,
     gauges: HashMap<&'static str, HashMap<String, f64>>,
     histogram_offsets: HistogramOffsetStore,
@@ -109,102 +180,110 @@ pub(super) struct HistogramOffset {
 }
 
 pub fn init_persistence(config: &PersistenceConfig, ewma_tracker: &EwmaTracker) -> Result<()> {
-    if config.mode != PersistenceMode::Redis {
+    if PERSISTENCE_RUNTIME.get().is_some() {
         return Ok(());
     }
-    if REDIS_RUNTIME.get().is_some() {
+
+    if config.mode == PersistenceMode::None {
         return Ok(());
     }
 
-    let redis_url = config
-        .redis_url
-        .clone()
-        .or_else(|| std::env::var("CCR_REDIS_URL").ok())
-        .ok_or_else(|| anyhow!("Persistence.mode=redis requires Persistence.redis_url"))?;
-    let redis_prefix = config.redis_prefix.clone();
-
-    let client = redis::Client::open(redis_url.as_str())
-        .with_context(|| format!("Failed to create Redis client for {}", redis_url))?;
-    let mut conn = client
-        .get_connection()
-        .context("Failed to connect to Redis for persistence snapshot load")?;
-    let snapshot = load_snapshot(&mut conn, &redis_prefix)?;
+    let (ready_tx, ready_rx) = mpsc::sync_channel(1);
+    let (tx, rx) = mpsc::channel();
+    spawn_persistence_worker(config.clone(), ready_tx, rx);
+    let (name, mut snapshot) = ready_rx
+        .recv()
+        .map_err(|_| anyhow!("Metrics persistence worker exited during startup"))??;
+    add_legacy_mode_label(&mut snapshot);
     apply_snapshot(&snapshot, ewma_tracker);
     sync_ewma_gauge(ewma_tracker);
 
-    let (tx, rx) = mpsc::channel();
-    spawn_redis_worker(client, redis_prefix, rx);
-
-    REDIS_RUNTIME
-        .set(RedisRuntime {
+    PERSISTENCE_RUNTIME
+        .set(PersistenceRuntime {
             sender: tx,
             histogram_offsets: snapshot.histogram_offsets.clone(),
         })
-        .map_err(|_| anyhow!("Redis runtime is already initialized"))?;
+        .map_err(|_| anyhow!("Persistence runtime is already initialized"))?;
 
-    info!("Redis metrics persistence initialized");
+    info!(backend = name, "Metrics persistence initialized");
     Ok(())
 }
 
-/// Delete all Redis keys belonging to the configured CCR persistence prefix.
-///
-/// This is intentionally prefix-scoped so it only removes CCR persistence
-/// records, not unrelated Redis data.
-pub fn clear_redis_persistence(redis_url: &str, prefix: &str) -> Result<usize> {
-    let client = redis::Client::open(redis_url)
-        .with_context(|| format!("Failed to create Redis client for {}", redis_url))?;
-    let mut conn = client
-        .get_connection()
-        .context("Failed to connect to Redis for persistence cleanup")?;
-    clear_redis_persistence_with_conn(&mut conn, prefix)
-}
-
-fn clear_redis_persistence_with_conn(conn: &mut redis::Connection, prefix: &str) -> Result<usize> {
-    let mut cursor: u64 = 0;
-    let mut deleted: usize = 0;
-    loop {
-        let (next_cursor, keys): (u64, Vec<String>) = redis::cmd("SCAN")
-            .arg(cursor)
-            .arg("COUNT")
-            .arg(256)
-            .query(conn)?;
-
-        let keys_to_delete: Vec<String> = keys
-            .into_iter()
-            .filter(|key| key_matches_persistence_prefix(key, prefix))
-            .collect();
-
-        if !keys_to_delete.is_empty() {
-            let removed: usize = redis::cmd("DEL").arg(&keys_to_delete).query(conn)?;
-            deleted += removed;
-        }
-
-        cursor = next_cursor;
-        if cursor == 0 {
-            break;
+fn open_backend(config: &PersistenceConfig) -> Result<Box<dyn PersistenceBackend>> {
+    let backend: Box<dyn PersistenceBackend> = match config.mode {
+        PersistenceMode::None => return Err(anyhow!("Persistence.mode=none has no backend")),
+        PersistenceMode::Redis => {
+            let redis_url = config
+                .redis_url
+                .clone()
+                .or_else(|| std::env::var("CCR_REDIS_URL").ok())
+                .ok_or_else(|| anyhow!("Persistence.mode=redis requires Persistence.redis_url"))?;
+            Box::new(RedisBackend::open(&redis_url, config.redis_prefix.clone())?)
+        }
+        #[cfg(feature = "postgres")]
+        PersistenceMode::Postgres => {
+            let postgres_url = config
+                .postgres_url
+                .clone()
+                .or_else(|| std::env::var("CCR_POSTGRES_URL").ok())
+                .ok_or_else(|| {
+                    anyhow!("Persistence.mode=postgres requires Persistence.postgres_url")
+                })?;
+            Box::new(PostgresBackend::open(
+                &postgres_url,
+                &config.postgres_table_prefix,
+            )?)
+        }
+        #[cfg(not(feature = "postgres"))]
+        PersistenceMode::Postgres => {
+            return Err(anyhow!(
+                "Persistence.mode=postgres requires a CCR-Rust build with the `postgres` feature"
+            ))
         }
-    }
-    Ok(deleted)
+    };
+    Ok(backend)
 }
 
-pub(super) fn key_matches_persistence_prefix(key: &str, prefix: &str) -> bool {
-    if key == prefix {
-        return true;
-    }
-    key.strip_prefix(prefix)
-        .map(|suffix| suffix.starts_with(':'))
-        .unwrap_or(false)
+fn persistence_runtime() -> Option<&'static PersistenceRuntime> {
+    PERSISTENCE_RUNTIME.get()
 }
 
-fn redis_runtime() -> Option<&'static RedisRuntime> {
-    REDIS_RUNTIME.get()
+/// Open the backend and read its snapshot on a dedicated thread, report the
+/// result through `ready`, then apply queued events until the sender is
+/// dropped.
+fn spawn_persistence_worker(
+    config: PersistenceConfig,
+    ready: SyncSender<Result<(&'static str, PersistedSnapshot)>>,
+    rx: Receiver<PersistEvent>,
+) {
+    thread::spawn(move || {
+        let opened = open_backend(&config).and_then(|mut backend| {
+            let snapshot = backend.load_snapshot()?;
+            Ok((backend, snapshot))
+        });
+        let mut backend = match opened {
+            Ok((backend, snapshot)) => {
+                let _ = ready.send(Ok((backend.name(), snapshot)));
+                backend
+            }
+            Err(err) => {
+                let _ = ready.send(Err(err));
+                return;
+            }
+        };
+        while let Ok(event) = rx.recv() {
+            if let Err(err) = backend.persist(event) {
+                warn!(backend = backend.name(), error = %err, "Metrics persistence write failed");
+            }
+        }
+    });
 }
 
 pub(super) fn persist_counter_inc(metric: &'static str, labels: &[(&str, &str)], by: f64) {
     if by <= 0.0 {
         return;
     }
-    if let Some(runtime) = redis_runtime() {
+    if let Some(runtime) = persistence_runtime() {
         let _ = runtime.sender.send(PersistEvent::CounterInc {
             metric,
             labels: encode_labels(labels),
@@ -214,7 +293,7 @@ pub(super) fn persist_counter_inc(metric: &'static str, labels: &[(&str, &str)],
 }
 
 pub(super) fn persist_gauge_set(metric: &'static str, labels: &[(&str, &str)], value: f64) {
-    if let Some(runtime) = redis_runtime() {
+    if let Some(runtime) = persistence_runtime() {
         let _ = runtime.sender.send(PersistEvent::GaugeSet {
             metric,
             labels: encode_labels(labels),
@@ -224,7 +303,7 @@ pub(super) fn persist_gauge_set(metric: &'static str, labels: &[(&str, &str)], v
 }
 
 pub(super) fn persist_gauge_max(metric: &'static str, labels: &[(&str, &str)], value: f64) {
-    if let Some(runtime) = redis_runtime() {
+    if let Some(runtime) = persistence_runtime() {
         let _ = runtime.sender.send(PersistEvent::GaugeMax {
             metric,
             labels: encode_labels(labels),
@@ -237,7 +316,7 @@ pub(super) fn persist_histogram_observe(metric: &'static str, labels: &[(&str, &
     if !value.is_finite() || value < 0.0 {
         return;
     }
-    if let Some(runtime) = redis_runtime() {
+    if let Some(runtime) = persistence_runtime() {
         let _ = runtime.sender.send(PersistEvent::HistogramObserve {
             metric,
             labels: encode_labels(labels),
@@ -247,7 +326,7 @@ pub(super) fn persist_histogram_observe(metric: &'static str, labels: &[(&str, &
 }
 
 pub(super) fn persist_token_drift_state(tier: &str, entry: &TokenDriftEntry) {
-    if let Some(runtime) = redis_runtime() {
+    if let Some(runtime) = persistence_runtime() {
         let _ = runtime.sender.send(PersistEvent::TokenDriftStateSet {
             tier: tier.to_string(),
             entry: entry.clone(),
@@ -256,7 +335,7 @@ pub(super) fn persist_token_drift_state(tier: &str, entry: &TokenDriftEntry) {
 }
 
 pub(super) fn persist_token_audit(entry: &PreRequestAuditEntry) {
-    if let Some(runtime) = redis_runtime() {
+    if let Some(runtime) = persistence_runtime() {
         let _ = runtime.sender.send(PersistEvent::TokenAuditPush {
             entry: entry.clone(),
         });
@@ -264,7 +343,7 @@ pub(super) fn persist_token_audit(entry: &PreRequestAuditEntry) {
 }
 
 pub(super) fn persist_ewma_state(tier: &str, ewma: f64, samples: u64) {
-    if let Some(runtime) = redis_runtime() {
+    if let Some(runtime) = persistence_runtime() {
         let _ = runtime.sender.send(PersistEvent::EwmaStateSet {
             tier: tier.to_string(),
             ewma,
@@ -273,11 +352,19 @@ pub(super) fn persist_ewma_state(tier: &str, ewma: f64, samples: u64) {
     }
 }
 
+pub(super) fn persist_ewma_clear(tier: &str) {
+    if let Some(runtime) = persistence_runtime() {
+        let _ = runtime.sender.send(PersistEvent::EwmaStateClear {
+            tier: tier.to_string(),
+        });
+    }
+}
+
 pub(super) fn get_hist_offset(
     metric: &'static str,
     labels: &[(&str, &str)],
 ) -> Option<&'static HistogramOffset> {
-    let runtime = redis_runtime()?;
+    let runtime = persistence_runtime()?;
     runtime
         .histogram_offsets
         .by_metric
@@ -298,7 +385,7 @@ fn get_label<'a>(labels: &'a BTreeMap<String, String>, key: &str) -> Option<&'a
     labels.get(key).map(|s| s.as_str())
 }
 
-fn snapshot_counter_sum(snapshot: &RedisSnapshot, metric: &'static str) -> u64 {
+fn snapshot_counter_sum(snapshot: &PersistedSnapshot, metric: &'static str) -> u64 {
     snapshot
         .counters
         .get(metric)
@@ -306,121 +393,57 @@ fn snapshot_counter_sum(snapshot: &RedisSnapshot, metric: &'static str) -> u64 {
         .unwrap_or(0)
 }
 
-fn load_snapshot(conn: &mut redis::Connection, prefix: &str) -> Result<RedisSnapshot> {
-    let counter_metrics = [
-        METRIC_REQUESTS_TOTAL,
-        METRIC_FRONTEND_REQUESTS_TOTAL,
-        METRIC_FAILURES_TOTAL,
-        METRIC_INPUT_TOKENS_TOTAL,
-        METRIC_OUTPUT_TOKENS_TOTAL,
-        METRIC_CACHE_READ_TOKENS_TOTAL,
-        METRIC_CACHE_CREATION_TOKENS_TOTAL,
-        METRIC_COST_USD_TOTAL,
-        METRIC_STREAM_BACKPRESSURE_TOTAL,
-        METRIC_REJECTED_STREAMS_TOTAL,
-        METRIC_PRE_REQUEST_TOKENS_TOTAL,
-        METRIC_RATE_LIMIT_HITS_TOTAL,
-        METRIC_RATE_LIMIT_BACKOFFS_TOTAL,
-        METRIC_TOKEN_DRIFT_ALERTS_TOTAL,
-    ];
-    let gauge_metrics = [
-        METRIC_PEAK_ACTIVE_STREAMS,
-        METRIC_TIER_EWMA_LATENCY_SECONDS,
-        METRIC_TOKEN_DRIFT_ABSOLUTE,
-        METRIC_TOKEN_DRIFT_PCT,
-    ];
-    let histogram_metrics = [
-        METRIC_REQUEST_DURATION_SECONDS,
-        METRIC_FRONTEND_REQUEST_DURATION_SECONDS,
-        METRIC_PRE_REQUEST_TOKENS,
-    ];
-
-    let mut snapshot = RedisSnapshot::default();
-
-    for metric in counter_metrics {
-        let key = redis_counter_key(prefix, metric);
-        let values: HashMap<String, f64> = conn.hgetall(&key).unwrap_or_default();
-        snapshot.counters.insert(metric, values);
-    }
-
-    for metric in gauge_metrics {
-        let key = redis_gauge_key(prefix, metric);
-        let values: HashMap<String, f64> = conn.hgetall(&key).unwrap_or_default();
-        snapshot.gauges.insert(metric, values);
-    }
-
-    for metric in histogram_metrics {
-        let sums: HashMap<String, f64> = conn
-            .hgetall(redis_hist_sum_key(prefix, metric))
-            .unwrap_or_default();
-        let counts: HashMap<String, u64> = conn
-            .hgetall(redis_hist_count_key(prefix, metric))
-            .unwrap_or_default();
-
-        let mut by_label: HashMap<String, HistogramOffset> = HashMap::new();
-        for (labels, sample_sum) in sums {
-            let entry = by_label.entry(labels).or_default();
-            entry.sample_sum = sample_sum;
-        }
-        for (labels, sample_count) in counts {
-            let entry = by_label.entry(labels).or_default();
-            entry.sample_count = sample_count;
-        }
-
-        if let Some(bounds) = histogram_bounds(metric) {
-            for bound in bounds {
-                let bound_key = format_bound(*bound);
-                let values: HashMap<String, u64> = conn
-                    .hgetall(redis_hist_bucket_key(prefix, metric, &bound_key))
-                    .unwrap_or_default();
-                for (labels, count) in values {
-                    let entry = by_label.entry(labels).or_default();
-                    entry.cumulative_buckets.insert(bound_key.clone(), count);
-                }
-            }
-        }
-
-        snapshot
-            .histogram_offsets
-            .by_metric
-            .insert(metric, by_label);
-    }
-
-    let drift_raw: HashMap<String, String> = conn
-        .hgetall(redis_token_drift_state_key(prefix))
-        .unwrap_or_default();
-    for (tier, raw) in drift_raw {
-        if let Ok(entry) = serde_json::from_str::<TokenDriftEntry>(&raw) {
-            snapshot.token_drift_state.insert(tier, entry);
-        }
-    }
+/// Request counts and durations persisted before the `mode` label existed
+/// are keyed by tier alone. Count them as `nonstream`, merged with any
+/// `nonstream` values persisted since.
+fn add_legacy_mode_label(snapshot: &mut PersistedSnapshot) {
+    let with_mode = |encoded: &str| {
+        let mut labels = decode_labels(encoded)?;
+        if labels.contains_key("mode") {
+            return None;
+        }
+        labels.insert(
+            "mode".to_string(),
+            RequestMode::NonStream.label().to_string(),
+        );
+        serde_json::to_string(&labels).ok()
+    };
 
-    let audit_raw: Vec<String> = conn
-        .lrange(
-            redis_token_audit_list_key(prefix),
-            0,
-            AUDIT_LOG_CAPACITY as isize - 1,
-        )
-        .unwrap_or_default();
-    for raw in audit_raw {
-        if let Ok(entry) = serde_json::from_str::<PreRequestAuditEntry>(&raw) {
-            snapshot.token_audit_log.push(entry);
+    if let Some(counters) = snapshot.counters.get_mut(METRIC_REQUESTS_TOTAL) {
+        let legacy: Vec<(String, String)> = counters
+            .keys()
+            .filter_map(|key| Some((key.clone(), with_mode(key)?)))
+            .collect();
+        for (old, new) in legacy {
+            let value = counters.remove(&old).unwrap_or_default();
+            *counters.entry(new).or_default() += value;
         }
     }
 
-    let ewma_raw: HashMap<String, String> = conn
-        .hgetall(redis_ewma_state_key(prefix))
-        .unwrap_or_default();
-    for (tier, raw) in ewma_raw {
-        if let Ok(state) = serde_json::from_str::<PersistedEwmaState>(&raw) {
-            snapshot.ewma_state.insert(tier, state);
+    if let Some(offsets) = snapshot
+        .histogram_offsets
+        .by_metric
+        .get_mut(METRIC_REQUEST_DURATION_SECONDS)
+    {
+        let legacy: Vec<(String, String)> = offsets
+            .keys()
+            .filter_map(|key| Some((key.clone(), with_mode(key)?)))
+            .collect();
+        for (old, new) in legacy {
+            let Some(offset) = offsets.remove(&old) else {
+                continue;
+            };
+            let merged = offsets.entry(new).or_default();
+            merged.sample_sum += offset.sample_sum;
+            merged.sample_count += offset.sample_count;
+            for (bound, count) in offset.cumulative_buckets {
+                *merged.cumulative_buckets.entry(bound).or_default() += count;
+            }
         }
     }
-
-    Ok(snapshot)
 }
 
-fn apply_snapshot(snapshot: &RedisSnapshot, ewma_tracker: &EwmaTracker) {
+fn apply_snapshot(snapshot: &PersistedSnapshot, ewma_tracker: &EwmaTracker) {
     for (metric, values) in &snapshot.counters {
         for (encoded_labels, value) in values {
             apply_counter_restore(metric, encoded_labels, *value);
@@ -483,8 +506,11 @@ fn apply_counter_restore(metric: &'static str, encoded_labels: &str, value: f64)
     let labels = decode_labels(encoded_labels).unwrap_or_default();
     match metric {
         METRIC_REQUESTS_TOTAL => {
-            if let Some(tier) = get_label(&labels, "tier") {
-                REQUESTS_TOTAL.with_label_values(&[tier]).inc_by(value);
+            let mode = get_label(&labels, "mode").and_then(RequestMode::from_label);
+            if let (Some(tier), Some(mode)) = (get_label(&labels, "tier"), mode) {
+                REQUESTS_TOTAL
+                    .with_label_values(&[tier, mode.label()])
+                    .inc_by(value);
             }
         }
         METRIC_FRONTEND_REQUESTS_TOTAL => {
@@ -533,7 +559,9 @@ fn apply_counter_restore(metric: &'static str, encoded_labels: &str, value: f64)
             }
         }
         METRIC_STREAM_BACKPRESSURE_TOTAL => {
-            STREAM_BACKPRESSURE.inc_by(value);
+            // Counts persisted before the tier label existed have none.
+            let tier = get_label(&labels, "tier").unwrap_or("unknown");
+            STREAM_BACKPRESSURE.with_label_values(&[tier]).inc_by(value);
         }
         METRIC_REJECTED_STREAMS_TOTAL => {
             REJECTED_STREAMS.inc_by(value);
@@ -557,6 +585,16 @@ fn apply_counter_restore(metric: &'static str, encoded_labels: &str, value: f64)
                 restore_rate_limit_backoff_counter(tier, value);
             }
         }
+        METRIC_SOFT_ERRORS_TOTAL => {
+            if let Some(tier) = get_label(&labels, "tier") {
+                SOFT_ERRORS.with_label_values(&[tier]).inc_by(value);
+            }
+        }
+        METRIC_NONSTANDARD_ROLE_TOTAL => {
+            if let Some(tier) = get_label(&labels, "tier") {
+                NONSTANDARD_ROLES.with_label_values(&[tier]).inc_by(value);
+            }
+        }
         METRIC_TOKEN_DRIFT_ALERTS_TOTAL => {
             if let (Some(tier), Some(severity)) =
                 (get_label(&labels, "tier"), get_label(&labels, "severity"))
@@ -573,10 +611,8 @@ fn apply_counter_restore(metric: &'static str, encoded_labels: &str, value: f64)
 fn apply_gauge_restore(metric: &'static str, encoded_labels: &str, value: f64) {
     let labels = decode_labels(encoded_labels).unwrap_or_default();
     match metric {
-        METRIC_PEAK_ACTIVE_STREAMS => {
-            if value > PEAK_ACTIVE_STREAMS.get() {
-                PEAK_ACTIVE_STREAMS.set(value);
-            }
+        METRIC_PEAK_ACTIVE_STREAMS if value > PEAK_ACTIVE_STREAMS.get() => {
+            PEAK_ACTIVE_STREAMS.set(value);
         }
         METRIC_TIER_EWMA_LATENCY_SECONDS => {
             if let Some(tier) = get_label(&labels, "tier") {
@@ -597,44 +633,13 @@ fn apply_gauge_restore(metric: &'static str, encoded_labels: &str, value: f64) {
     }
 }
 
-fn redis_counter_key(prefix: &str, metric: &str) -> String {
-    format!("{}:counter:{}", prefix, metric)
-}
-
-fn redis_gauge_key(prefix: &str, metric: &str) -> String {
-    format!("{}:gauge:{}", prefix, metric)
-}
-
-fn redis_hist_sum_key(prefix: &str, metric: &str) -> String {
-    format!("{}:hist:{}:sum", prefix, metric)
-}
-
-fn redis_hist_count_key(prefix: &str, metric: &str) -> String {
-    format!("{}:hist:{}:count", prefix, metric)
-}
-
-fn redis_hist_bucket_key(prefix: &str, metric: &str, bound: &str) -> String {
-    format!("{}:hist:{}:bucket:{}", prefix, metric, bound)
-}
-
-fn redis_token_drift_state_key(prefix: &str) -> String {
-    format!("{}:state:token-drift", prefix)
-}
-
-fn redis_token_audit_list_key(prefix: &str) -> String {
-    format!("{}:list:token-audit", prefix)
-}
-
-fn redis_ewma_state_key(prefix: &str) -> String {
-    format!("{}:state:ewma", prefix)
-}
-
 fn histogram_bounds(metric: &str) -> Option<&'static [f64]> {
     match metric {
         METRIC_REQUEST_DURATION_SECONDS | METRIC_FRONTEND_REQUEST_DURATION_SECONDS => {
             Some(REQUEST_DURATION_BUCKETS)
         }
         METRIC_PRE_REQUEST_TOKENS => Some(PRE_REQUEST_TOKENS_BUCKETS),
+        METRIC_TIME_TO_FIRST_TOKEN_SECONDS => Some(TIME_TO_FIRST_TOKEN_BUCKETS),
         _ => None,
     }
 }
@@ -643,119 +648,6 @@ fn format_bound(bound: f64) -> String {
     format!("{:.6}", bound)
 }
 
-fn spawn_redis_worker(client: redis::Client, prefix: String, rx: Receiver<PersistEvent>) {
-    thread::spawn(move || {
-        let mut conn: Option<redis::Connection> = None;
-        while let Ok(event) = rx.recv() {
-            if conn.is_none() {
-                match client.get_connection() {
-                    Ok(c) => conn = Some(c),
-                    Err(err) => {
-                        warn!(error = %err, "Failed to connect to Redis persistence backend");
-                        continue;
-                    }
-                }
-            }
-
-            let Some(connection) = conn.as_mut() else {
-                continue;
-            };
-
-            if let Err(err) = persist_event(connection, &prefix, event) {
-                warn!(error = %err, "Redis persistence write failed");
-                conn = None;
-            }
-        }
-    });
-}
-
-fn persist_event(conn: &mut redis::Connection, prefix: &str, event: PersistEvent) -> Result<()> {
-    match event {
-        PersistEvent::CounterInc { metric, labels, by } => {
-            let _: f64 = redis::cmd("HINCRBYFLOAT")
-                .arg(redis_counter_key(prefix, metric))
-                .arg(labels)
-                .arg(by)
-                .query(conn)?;
-        }
-        PersistEvent::GaugeSet {
-            metric,
-            labels,
-            value,
-        } => {
-            let _: () = conn.hset(redis_gauge_key(prefix, metric), labels, value)?;
-        }
-        PersistEvent::GaugeMax {
-            metric,
-            labels,
-            value,
-        } => {
-            let key = redis_gauge_key(prefix, metric);
-            let current: Option<f64> = conn.hget(&key, &labels).ok();
-            if current.unwrap_or(f64::NEG_INFINITY) < value {
-                let _: () = conn.hset(key, labels, value)?;
-            }
-        }
-        PersistEvent::HistogramObserve {
-            metric,
-            labels,
-            value,
-        } => {
-            let mut pipe = redis::pipe();
-            pipe.cmd("HINCRBYFLOAT")
-                .arg(redis_hist_sum_key(prefix, metric))
-                .arg(&labels)
-                .arg(value)
-                .ignore()
-                .cmd("HINCRBY")
-                .arg(redis_hist_count_key(prefix, metric))
-                .arg(&labels)
-                .arg(1)
-                .ignore();
-
-            if let Some(bounds) = histogram_bounds(metric) {
-                for bound in bounds {
-                    if value <= *bound {
-                        pipe.cmd("HINCRBY")
-                            .arg(redis_hist_bucket_key(prefix, metric, &format_bound(*bound)))
-                            .arg(&labels)
-                            .arg(1)
-                            .ignore();
-                    }
-                }
-            }
-            let _: () = pipe.query(conn)?;
-        }
-        PersistEvent::TokenDriftStateSet { tier, entry } => {
-            let raw = serde_json::to_string(&entry)?;
-            let _: () = conn.hset(redis_token_drift_state_key(prefix), tier, raw)?;
-        }
-        PersistEvent::TokenAuditPush { entry } => {
-            let raw = serde_json::to_string(&entry)?;
-            let mut pipe = redis::pipe();
-            pipe.cmd("RPUSH")
-                .arg(redis_token_audit_list_key(prefix))
-                .arg(raw)
-                .ignore()
-                .cmd("LTRIM")
-                .arg(redis_token_audit_list_key(prefix))
-                .arg(-(AUDIT_LOG_CAPACITY as isize))
-                .arg(-1)
-                .ignore();
-            let _: () = pipe.query(conn)?;
-        }
-        PersistEvent::EwmaStateSet {
-            tier,
-            ewma,
-            samples,
-        } => {
-            let raw = serde_json::to_string(&PersistedEwmaState { ewma, samples })?;
-            let _: () = conn.hset(redis_ewma_state_key(prefix), tier, raw)?;
-        }
-    }
-    Ok(())
-}
-
 fn make_metric_with_labels(encoded_labels: &str) -> prometheus::proto::Metric {
     let mut metric = prometheus::proto::Metric::new();
     let labels = decode_labels(encoded_labels).unwrap_or_default();
@@ -777,15 +669,11 @@ fn encode_metric_labels(metric: &prometheus::proto::Metric) -> String {
 }
 
 pub(super) fn merge_histogram_offsets(metric_families: &mut Vec<prometheus::proto::MetricFamily>) {
-    let Some(runtime) = redis_runtime() else {
+    let Some(runtime) = persistence_runtime() else {
         return;
     };
 
-    for metric_name in [
-        METRIC_REQUEST_DURATION_SECONDS,
-        METRIC_FRONTEND_REQUEST_DURATION_SECONDS,
-        METRIC_PRE_REQUEST_TOKENS,
-    ] {
+    for metric_name in HISTOGRAM_METRICS {
         let Some(offsets) = runtime.histogram_offsets.by_metric.get(metric_name) else {
             continue;
         };
@@ -883,3 +771,50 @@ pub(super) fn merge_histogram_offsets(metric_families: &mut Vec<prometheus::prot
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_request_series_are_counted_as_nonstream() {
+        let legacy = encode_labels(&[("tier", "t")]);
+        let nonstream = encode_labels(&[("tier", "t"), ("mode", "nonstream")]);
+        let stream = encode_labels(&[("tier", "t"), ("mode", "stream")]);
+
+        let mut snapshot = PersistedSnapshot::default();
+        snapshot.counters.insert(
+            METRIC_REQUESTS_TOTAL,
+            HashMap::from([
+                (legacy.clone(), 5.0),
+                (nonstream.clone(), 2.0),
+                (stream.clone(), 1.0),
+            ]),
+        );
+        let offset = |sum: f64, count: u64| HistogramOffset {
+            sample_sum: sum,
+            sample_count: count,
+            cumulative_buckets: HashMap::from([("1".to_string(), count)]),
+        };
+        snapshot.histogram_offsets.by_metric.insert(
+            METRIC_REQUEST_DURATION_SECONDS,
+            HashMap::from([
+                (legacy.clone(), offset(3.0, 4)),
+                (nonstream.clone(), offset(1.0, 1)),
+            ]),
+        );
+
+        add_legacy_mode_label(&mut snapshot);
+
+        let counters = &snapshot.counters[METRIC_REQUESTS_TOTAL];
+        assert_eq!(counters.len(), 2);
+        assert_eq!(counters[&nonstream], 7.0);
+        assert_eq!(counters[&stream], 1.0);
+
+        let offsets = &snapshot.histogram_offsets.by_metric[METRIC_REQUEST_DURATION_SECONDS];
+        assert!(!offsets.contains_key(&legacy));
+        assert_eq!(offsets[&nonstream].sample_sum, 4.0);
+        assert_eq!(offsets[&nonstream].sample_count, 5);
+        assert_eq!(offsets[&nonstream].cumulative_buckets["1"], 5);
+    }
+}
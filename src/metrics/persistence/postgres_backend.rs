@@ -0,0 +1,399 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Postgres persistence backend.
+//
+// Counters and gauges are rows keyed by (metric, encoded labels) and updated
+// with upserts; histograms keep sum/count rows plus one row per bucket bound.
+// Token drift and EWMA state share a JSON key/value table, and the token audit
+// log is an append-only table trimmed to `AUDIT_LOG_CAPACITY` rows. Tables are
+// created on first connect.
+
+use anyhow::{Context, Result};
+use postgres::{Client, NoTls};
+use std::collections::HashMap;
+
+use super::{
+    encode_labels, format_bound, histogram_bounds, HistogramOffset, PersistEvent,
+    PersistedEwmaState, PersistedSnapshot, PersistenceBackend, COUNTER_METRICS, GAUGE_METRICS,
+    HISTOGRAM_METRICS,
+};
+use crate::metrics::{
+    PreRequestAuditEntry, TokenDriftEntry, AUDIT_LOG_CAPACITY, METRIC_TIER_EWMA_LATENCY_SECONDS,
+};
+
+const STATE_TOKEN_DRIFT: &str = "token-drift";
+const STATE_EWMA: &str = "ewma";
+
+/// Table names derived from the configured prefix. The prefix is validated to
+/// `[a-z0-9_]` at config load, so it is safe to interpolate into SQL.
+struct Tables {
+    counters: String,
+    gauges: String,
+    histograms: String,
+    histogram_buckets: String,
+    state: String,
+    token_audit: String,
+}
+
+impl Tables {
+    fn new(prefix: &str) -> Self {
+        Self {
+            counters: format!("{}counters", prefix),
+            gauges: format!("{}gauges", prefix),
+            histograms: format!("{}histograms", prefix),
+            histogram_buckets: format!("{}histogram_buckets", prefix),
+            state: format!("{}state", prefix),
+            token_audit: format!("{}token_audit", prefix),
+        }
+    }
+
+    fn schema(&self) -> String {
+        format!(
+            "CREATE TABLE IF NOT EXISTS {counters} (
+                 metric TEXT NOT NULL,
+                 labels TEXT NOT NULL,
+                 value DOUBLE PRECISION NOT NULL,
+                 PRIMARY KEY (metric, labels)
+             );
+             CREATE TABLE IF NOT EXISTS {gauges} (
+                 metric TEXT NOT NULL,
+                 labels TEXT NOT NULL,
+                 value DOUBLE PRECISION NOT NULL,
+                 PRIMARY KEY (metric, labels)
+             );
+             CREATE TABLE IF NOT EXISTS {histograms} (
+                 metric TEXT NOT NULL,
+                 labels TEXT NOT NULL,
+                 sample_sum DOUBLE PRECISION NOT NULL,
+                 sample_count BIGINT NOT NULL,
+                 PRIMARY KEY (metric, labels)
+             );
+             CREATE TABLE IF NOT EXISTS {histogram_buckets} (
+                 metric TEXT NOT NULL,
+                 labels TEXT NOT NULL,
+                 bound TEXT NOT NULL,
+                 count BIGINT NOT NULL,
+                 PRIMARY KEY (metric, labels, bound)
+             );
+             CREATE TABLE IF NOT EXISTS {state} (
+                 kind TEXT NOT NULL,
+                 key TEXT NOT NULL,
+                 value TEXT NOT NULL,
+                 PRIMARY KEY (kind, key)
+             );
+             CREATE TABLE IF NOT EXISTS {token_audit} (
+                 id BIGSERIAL PRIMARY KEY,
+                 entry TEXT NOT NULL
+             );",
+            counters = self.counters,
+            gauges = self.gauges,
+            histograms = self.histograms,
+            histogram_buckets = self.histogram_buckets,
+            state = self.state,
+            token_audit = self.token_audit,
+        )
+    }
+}
+
+pub(super) struct PostgresBackend {
+    url: String,
+    tables: Tables,
+    client: Option<Client>,
+}
+
+impl PostgresBackend {
+    pub(super) fn open(postgres_url: &str, table_prefix: &str) -> Result<Self> {
+        let mut backend = Self {
+            url: postgres_url.to_string(),
+            tables: Tables::new(table_prefix),
+            client: None,
+        };
+        backend
+            .connection()
+            .context("Failed to connect to Postgres for persistence snapshot load")?;
+        Ok(backend)
+    }
+
+    fn connection(&mut self) -> Result<(&mut Client, &Tables)> {
+        if self.client.as_ref().is_none_or(Client::is_closed) {
+            let mut client = Client::connect(&self.url, NoTls)
+                .context("Failed to connect to Postgres persistence backend")?;
+            client
+                .batch_execute(&self.tables.schema())
+                .context("Failed to create Postgres persistence tables")?;
+            self.client = Some(client);
+        }
+        let client = self.client.as_mut().expect("client was just set");
+        Ok((client, &self.tables))
+    }
+}
+
+impl PersistenceBackend for PostgresBackend {
+    fn name(&self) -> &'static str {
+        "postgres"
+    }
+
+    fn load_snapshot(&mut self) -> Result<PersistedSnapshot> {
+        let (client, tables) = self.connection()?;
+        load_snapshot(client, tables)
+    }
+
+    fn persist(&mut self, event: PersistEvent) -> Result<()> {
+        let result = self
+            .connection()
+            .and_then(|(client, tables)| persist_event(client, tables, event));
+        if result.is_err() {
+            self.client = None;
+        }
+        result
+    }
+}
+
+fn load_snapshot(client: &mut Client, tables: &Tables) -> Result<PersistedSnapshot> {
+    let mut snapshot = PersistedSnapshot::default();
+
+    let counter_query = format!(
+        "SELECT labels, value FROM {} WHERE metric = $1",
+        tables.counters
+    );
+    for metric in COUNTER_METRICS {
+        let values: HashMap<String, f64> = client
+            .query(&counter_query, &[&metric])?
+            .iter()
+            .map(|row| (row.get(0), row.get(1)))
+            .collect();
+        snapshot.counters.insert(metric, values);
+    }
+
+    let gauge_query = format!(
+        "SELECT labels, value FROM {} WHERE metric = $1",
+        tables.gauges
+    );
+    for metric in GAUGE_METRICS {
+        let values: HashMap<String, f64> = client
+            .query(&gauge_query, &[&metric])?
+            .iter()
+            .map(|row| (row.get(0), row.get(1)))
+            .collect();
+        snapshot.gauges.insert(metric, values);
+    }
+
+    let histogram_query = format!(
+        "SELECT labels, sample_sum, sample_count FROM {} WHERE metric = $1",
+        tables.histograms
+    );
+    let bucket_query = format!(
+        "SELECT labels, bound, count FROM {} WHERE metric = $1",
+        tables.histogram_buckets
+    );
+    for metric in HISTOGRAM_METRICS {
+        let mut by_label: HashMap<String, HistogramOffset> = HashMap::new();
+        for row in client.query(&histogram_query, &[&metric])? {
+            let entry = by_label.entry(row.get(0)).or_default();
+            entry.sample_sum = row.get(1);
+            entry.sample_count = row.get::<_, i64>(2).max(0) as u64;
+        }
+
+        // Only restore bounds the histogram still uses; stale rows from a
+        // bucket layout change are ignored.
+        let bounds: Vec<String> = histogram_bounds(metric)
+            .unwrap_or_default()
+            .iter()
+            .map(|bound| format_bound(*bound))
+            .collect();
+        for row in client.query(&bucket_query, &[&metric])? {
+            let bound: String = row.get(1);
+            if !bounds.contains(&bound) {
+                continue;
+            }
+            let entry = by_label.entry(row.get(0)).or_default();
+            entry
+                .cumulative_buckets
+                .insert(bound, row.get::<_, i64>(2).max(0) as u64);
+        }
+
+        snapshot
+            .histogram_offsets
+            .by_metric
+            .insert(metric, by_label);
+    }
+
+    let state_query = format!("SELECT key, value FROM {} WHERE kind = $1", tables.state);
+    for row in client.query(&state_query, &[&STATE_TOKEN_DRIFT])? {
+        let raw: String = row.get(1);
+        if let Ok(entry) = serde_json::from_str::<TokenDriftEntry>(&raw) {
+            snapshot.token_drift_state.insert(row.get(0), entry);
+        }
+    }
+
+    let audit_query = format!(
+        "SELECT entry FROM (SELECT id, entry FROM {} ORDER BY id DESC LIMIT $1) recent ORDER BY id",
+        tables.token_audit
+    );
+    for row in client.query(&audit_query, &[&(AUDIT_LOG_CAPACITY as i64)])? {
+        let raw: String = row.get(0);
+        if let Ok(entry) = serde_json::from_str::<PreRequestAuditEntry>(&raw) {
+            snapshot.token_audit_log.push(entry);
+        }
+    }
+
+    for row in client.query(&state_query, &[&STATE_EWMA])? {
+        let raw: String = row.get(1);
+        if let Ok(state) = serde_json::from_str::<PersistedEwmaState>(&raw) {
+            snapshot.ewma_state.insert(row.get(0), state);
+        }
+    }
+
+    Ok(snapshot)
+}
+
+fn persist_event(client: &mut Client, tables: &Tables, event: PersistEvent) -> Result<()> {
+    match event {
+        PersistEvent::CounterInc { metric, labels, by } => {
+            client.execute(
+                &format!(
+                    "INSERT INTO {t} (metric, labels, value) VALUES ($1, $2, $3)
+                     ON CONFLICT (metric, labels) DO UPDATE SET value = {t}.value + EXCLUDED.value",
+                    t = tables.counters
+                ),
+                &[&metric, &labels, &by],
+            )?;
+        }
+        PersistEvent::GaugeSet {
+            metric,
+            labels,
+            value,
+        } => {
+            client.execute(
+                &format!(
+                    "INSERT INTO {t} (metric, labels, value) VALUES ($1, $2, $3)
+                     ON CONFLICT (metric, labels) DO UPDATE SET value = EXCLUDED.value",
+                    t = tables.gauges
+                ),
+                &[&metric, &labels, &value],
+            )?;
+        }
+        PersistEvent::GaugeMax {
+            metric,
+            labels,
+            value,
+        } => {
+            client.execute(
+                &format!(
+                    "INSERT INTO {t} (metric, labels, value) VALUES ($1, $2, $3)
+                     ON CONFLICT (metric, labels) DO UPDATE SET value = GREATEST({t}.value, EXCLUDED.value)",
+                    t = tables.gauges
+                ),
+                &[&metric, &labels, &value],
+            )?;
+        }
+        PersistEvent::HistogramObserve {
+            metric,
+            labels,
+            value,
+        } => {
+            let mut tx = client.transaction()?;
+            tx.execute(
+                &format!(
+                    "INSERT INTO {t} (metric, labels, sample_sum, sample_count) VALUES ($1, $2, $3, 1)
+                     ON CONFLICT (metric, labels) DO UPDATE
+                     SET sample_sum = {t}.sample_sum + EXCLUDED.sample_sum,
+                         sample_count = {t}.sample_count + 1",
+                    t = tables.histograms
+                ),
+                &[&metric, &labels, &value],
+            )?;
+            if let Some(bounds) = histogram_bounds(metric) {
+                let bucket_upsert = format!(
+                    "INSERT INTO {t} (metric, labels, bound, count) VALUES ($1, $2, $3, 1)
+                     ON CONFLICT (metric, labels, bound) DO UPDATE SET count = {t}.count + 1",
+                    t = tables.histogram_buckets
+                );
+                for bound in bounds {
+                    if value <= *bound {
+                        tx.execute(&bucket_upsert, &[&metric, &labels, &format_bound(*bound)])?;
+                    }
+                }
+            }
+            tx.commit()?;
+        }
+        PersistEvent::TokenDriftStateSet { tier, entry } => {
+            let raw = serde_json::to_string(&entry)?;
+            upsert_state(client, tables, STATE_TOKEN_DRIFT, &tier, &raw)?;
+        }
+        PersistEvent::TokenAuditPush { entry } => {
+            let raw = serde_json::to_string(&entry)?;
+            let mut tx = client.transaction()?;
+            tx.execute(
+                &format!("INSERT INTO {} (entry) VALUES ($1)", tables.token_audit),
+                &[&raw],
+            )?;
+            tx.execute(
+                &format!(
+                    "DELETE FROM {t} WHERE id <= (SELECT id FROM {t} ORDER BY id DESC OFFSET $1 LIMIT 1)",
+                    t = tables.token_audit
+                ),
+                &[&(AUDIT_LOG_CAPACITY as i64)],
+            )?;
+            tx.commit()?;
+        }
+        PersistEvent::EwmaStateSet {
+            tier,
+            ewma,
+            samples,
+        } => {
+            let raw = serde_json::to_string(&PersistedEwmaState { ewma, samples })?;
+            upsert_state(client, tables, STATE_EWMA, &tier, &raw)?;
+        }
+        PersistEvent::EwmaStateClear { tier } => {
+            let labels = encode_labels(&[("tier", &tier)]);
+            let mut tx = client.transaction()?;
+            tx.execute(
+                &format!("DELETE FROM {} WHERE kind = $1 AND key = $2", tables.state),
+                &[&STATE_EWMA, &tier],
+            )?;
+            tx.execute(
+                &format!(
+                    "DELETE FROM {} WHERE metric = $1 AND labels = $2",
+                    tables.gauges
+                ),
+                &[&METRIC_TIER_EWMA_LATENCY_SECONDS, &labels],
+            )?;
+            tx.commit()?;
+        }
+    }
+    Ok(())
+}
+
+fn upsert_state(
+    client: &mut Client,
+    tables: &Tables,
+    kind: &str,
+    key: &str,
+    raw: &str,
+) -> Result<()> {
+    client.execute(
+        &format!(
+            "INSERT INTO {} (kind, key, value) VALUES ($1, $2, $3)
+             ON CONFLICT (kind, key) DO UPDATE SET value = EXCLUDED.value",
+            tables.state
+        ),
+        &[&kind, &key, &raw],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tables_use_configured_prefix() {
+        let tables = Tables::new("ccr_");
+        assert_eq!(tables.counters, "ccr_counters");
+        assert_eq!(tables.histogram_buckets, "ccr_histogram_buckets");
+
+        let schema = tables.schema();
+        assert!(schema.contains("CREATE TABLE IF NOT EXISTS ccr_gauges ("));
+        assert!(schema.contains("CREATE TABLE IF NOT EXISTS ccr_token_audit ("));
+    }
+}
@@ -0,0 +1,340 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Redis persistence backend.
+//
+// Counters and gauges live in one hash per metric keyed by encoded labels;
+// histograms use separate sum, count, and per-bucket hashes. All keys start
+// with the configured prefix.
+
+use anyhow::{Context, Result};
+use redis::Commands;
+use std::collections::HashMap;
+
+use super::{
+    encode_labels, format_bound, histogram_bounds, HistogramOffset, PersistEvent,
+    PersistedEwmaState, PersistedSnapshot, PersistenceBackend, COUNTER_METRICS, GAUGE_METRICS,
+    HISTOGRAM_METRICS,
+};
+use crate::metrics::{
+    PreRequestAuditEntry, TokenDriftEntry, AUDIT_LOG_CAPACITY, METRIC_TIER_EWMA_LATENCY_SECONDS,
+};
+
+pub(super) struct RedisBackend {
+    client: redis::Client,
+    prefix: String,
+    conn: Option<redis::Connection>,
+}
+
+impl RedisBackend {
+    pub(super) fn open(redis_url: &str, prefix: String) -> Result<Self> {
+        let client = redis::Client::open(redis_url)
+            .with_context(|| format!("Failed to create Redis client for {}", redis_url))?;
+        let conn = client
+            .get_connection()
+            .context("Failed to connect to Redis for persistence snapshot load")?;
+        Ok(Self {
+            client,
+            prefix,
+            conn: Some(conn),
+        })
+    }
+
+    fn connection(&mut self) -> Result<&mut redis::Connection> {
+        if self.conn.is_none() {
+            let conn = self
+                .client
+                .get_connection()
+                .context("Failed to connect to Redis persistence backend")?;
+            self.conn = Some(conn);
+        }
+        Ok(self.conn.as_mut().expect("connection was just set"))
+    }
+}
+
+impl PersistenceBackend for RedisBackend {
+    fn name(&self) -> &'static str {
+        "redis"
+    }
+
+    fn load_snapshot(&mut self) -> Result<PersistedSnapshot> {
+        let prefix = self.prefix.clone();
+        let conn = self.connection()?;
+        load_snapshot(conn, &prefix)
+    }
+
+    fn persist(&mut self, event: PersistEvent) -> Result<()> {
+        let prefix = self.prefix.clone();
+        let result = persist_event(self.connection()?, &prefix, event);
+        if result.is_err() {
+            self.conn = None;
+        }
+        result
+    }
+}
+
+/// Delete all Redis keys belonging to the configured CCR persistence prefix.
+///
+/// This is intentionally prefix-scoped so it only removes CCR persistence
+/// records, not unrelated Redis data.
+pub fn clear_redis_persistence(redis_url: &str, prefix: &str) -> Result<usize> {
+    let client = redis::Client::open(redis_url)
+        .with_context(|| format!("Failed to create Redis client for {}", redis_url))?;
+    let mut conn = client
+        .get_connection()
+        .context("Failed to connect to Redis for persistence cleanup")?;
+    clear_redis_persistence_with_conn(&mut conn, prefix)
+}
+
+fn clear_redis_persistence_with_conn(conn: &mut redis::Connection, prefix: &str) -> Result<usize> {
+    let mut cursor: u64 = 0;
+    let mut deleted: usize = 0;
+    loop {
+        let (next_cursor, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+            .arg(cursor)
+            .arg("COUNT")
+            .arg(256)
+            .query(conn)?;
+
+        let keys_to_delete: Vec<String> = keys
+            .into_iter()
+            .filter(|key| key_matches_persistence_prefix(key, prefix))
+            .collect();
+
+        if !keys_to_delete.is_empty() {
+            let removed: usize = redis::cmd("DEL").arg(&keys_to_delete).query(conn)?;
+            deleted += removed;
+        }
+
+        cursor = next_cursor;
+        if cursor == 0 {
+            break;
+        }
+    }
+    Ok(deleted)
+}
+
+pub(in crate::metrics) fn key_matches_persistence_prefix(key: &str, prefix: &str) -> bool {
+    if key == prefix {
+        return true;
+    }
+    key.strip_prefix(prefix)
+        .map(|suffix| suffix.starts_with(':'))
+        .unwrap_or(false)
+}
+
+fn load_snapshot(conn: &mut redis::Connection, prefix: &str) -> Result<PersistedSnapshot> {
+    let mut snapshot = PersistedSnapshot::default();
+
+    for metric in COUNTER_METRICS {
+        let key = redis_counter_key(prefix, metric);
+        let values: HashMap<String, f64> = conn.hgetall(&key).unwrap_or_default();
+        snapshot.counters.insert(metric, values);
+    }
+
+    for metric in GAUGE_METRICS {
+        let key = redis_gauge_key(prefix, metric);
+        let values: HashMap<String, f64> = conn.hgetall(&key).unwrap_or_default();
+        snapshot.gauges.insert(metric, values);
+    }
+
+    for metric in HISTOGRAM_METRICS {
+        let sums: HashMap<String, f64> = conn
+            .hgetall(redis_hist_sum_key(prefix, metric))
+            .unwrap_or_default();
+        let counts: HashMap<String, u64> = conn
+            .hgetall(redis_hist_count_key(prefix, metric))
+            .unwrap_or_default();
+
+        let mut by_label: HashMap<String, HistogramOffset> = HashMap::new();
+        for (labels, sample_sum) in sums {
+            let entry = by_label.entry(labels).or_default();
+            entry.sample_sum = sample_sum;
+        }
+        for (labels, sample_count) in counts {
+            let entry = by_label.entry(labels).or_default();
+            entry.sample_count = sample_count;
+        }
+
+        if let Some(bounds) = histogram_bounds(metric) {
+            for bound in bounds {
+                let bound_key = format_bound(*bound);
+                let values: HashMap<String, u64> = conn
+                    .hgetall(redis_hist_bucket_key(prefix, metric, &bound_key))
+                    .unwrap_or_default();
+                for (labels, count) in values {
+                    let entry = by_label.entry(labels).or_default();
+                    entry.cumulative_buckets.insert(bound_key.clone(), count);
+                }
+            }
+        }
+
+        snapshot
+            .histogram_offsets
+            .by_metric
+            .insert(metric, by_label);
+    }
+
+    let drift_raw: HashMap<String, String> = conn
+        .hgetall(redis_token_drift_state_key(prefix))
+        .unwrap_or_default();
+    for (tier, raw) in drift_raw {
+        if let Ok(entry) = serde_json::from_str::<TokenDriftEntry>(&raw) {
+            snapshot.token_drift_state.insert(tier, entry);
+        }
+    }
+
+    let audit_raw: Vec<String> = conn
+        .lrange(
+            redis_token_audit_list_key(prefix),
+            0,
+            AUDIT_LOG_CAPACITY as isize - 1,
+        )
+        .unwrap_or_default();
+    for raw in audit_raw {
+        if let Ok(entry) = serde_json::from_str::<PreRequestAuditEntry>(&raw) {
+            snapshot.token_audit_log.push(entry);
+        }
+    }
+
+    let ewma_raw: HashMap<String, String> = conn
+        .hgetall(redis_ewma_state_key(prefix))
+        .unwrap_or_default();
+    for (tier, raw) in ewma_raw {
+        if let Ok(state) = serde_json::from_str::<PersistedEwmaState>(&raw) {
+            snapshot.ewma_state.insert(tier, state);
+        }
+    }
+
+    Ok(snapshot)
+}
+
+fn redis_counter_key(prefix: &str, metric: &str) -> String {
+    format!("{}:counter:{}", prefix, metric)
+}
+
+fn redis_gauge_key(prefix: &str, metric: &str) -> String {
+    format!("{}:gauge:{}", prefix, metric)
+}
+
+fn redis_hist_sum_key(prefix: &str, metric: &str) -> String {
+    format!("{}:hist:{}:sum", prefix, metric)
+}
+
+fn redis_hist_count_key(prefix: &str, metric: &str) -> String {
+    format!("{}:hist:{}:count", prefix, metric)
+}
+
+fn redis_hist_bucket_key(prefix: &str, metric: &str, bound: &str) -> String {
+    format!("{}:hist:{}:bucket:{}", prefix, metric, bound)
+}
+
+fn redis_token_drift_state_key(prefix: &str) -> String {
+    format!("{}:state:token-drift", prefix)
+}
+
+fn redis_token_audit_list_key(prefix: &str) -> String {
+    format!("{}:list:token-audit", prefix)
+}
+
+fn redis_ewma_state_key(prefix: &str) -> String {
+    format!("{}:state:ewma", prefix)
+}
+
+fn persist_event(conn: &mut redis::Connection, prefix: &str, event: PersistEvent) -> Result<()> {
+    match event {
+        PersistEvent::CounterInc { metric, labels, by } => {
+            let _: f64 = redis::cmd("HINCRBYFLOAT")
+                .arg(redis_counter_key(prefix, metric))
+                .arg(labels)
+                .arg(by)
+                .query(conn)?;
+        }
+        PersistEvent::GaugeSet {
+            metric,
+            labels,
+            value,
+        } => {
+            let _: () = conn.hset(redis_gauge_key(prefix, metric), labels, value)?;
+        }
+        PersistEvent::GaugeMax {
+            metric,
+            labels,
+            value,
+        } => {
+            let key = redis_gauge_key(prefix, metric);
+            let current: Option<f64> = conn.hget(&key, &labels).ok();
+            if current.unwrap_or(f64::NEG_INFINITY) < value {
+                let _: () = conn.hset(key, labels, value)?;
+            }
+        }
+        PersistEvent::HistogramObserve {
+            metric,
+            labels,
+            value,
+        } => {
+            let mut pipe = redis::pipe();
+            pipe.cmd("HINCRBYFLOAT")
+                .arg(redis_hist_sum_key(prefix, metric))
+                .arg(&labels)
+                .arg(value)
+                .ignore()
+                .cmd("HINCRBY")
+                .arg(redis_hist_count_key(prefix, metric))
+                .arg(&labels)
+                .arg(1)
+                .ignore();
+
+            if let Some(bounds) = histogram_bounds(metric) {
+                for bound in bounds {
+                    if value <= *bound {
+                        pipe.cmd("HINCRBY")
+                            .arg(redis_hist_bucket_key(prefix, metric, &format_bound(*bound)))
+                            .arg(&labels)
+                            .arg(1)
+                            .ignore();
+                    }
+                }
+            }
+            let _: () = pipe.query(conn)?;
+        }
+        PersistEvent::TokenDriftStateSet { tier, entry } => {
+            let raw = serde_json::to_string(&entry)?;
+            let _: () = conn.hset(redis_token_drift_state_key(prefix), tier, raw)?;
+        }
+        PersistEvent::TokenAuditPush { entry } => {
+            let raw = serde_json::to_string(&entry)?;
+            let mut pipe = redis::pipe();
+            pipe.cmd("RPUSH")
+                .arg(redis_token_audit_list_key(prefix))
+                .arg(raw)
+                .ignore()
+                .cmd("LTRIM")
+                .arg(redis_token_audit_list_key(prefix))
+                .arg(-(AUDIT_LOG_CAPACITY as isize))
+                .arg(-1)
+                .ignore();
+            let _: () = pipe.query(conn)?;
+        }
+        PersistEvent::EwmaStateSet {
+            tier,
+            ewma,
+            samples,
+        } => {
+            let raw = serde_json::to_string(&PersistedEwmaState { ewma, samples })?;
+            let _: () = conn.hset(redis_ewma_state_key(prefix), tier, raw)?;
+        }
+        PersistEvent::EwmaStateClear { tier } => {
+            let labels = encode_labels(&[("tier", &tier)]);
+            let mut pipe = redis::pipe();
+            pipe.cmd("HDEL")
+                .arg(redis_ewma_state_key(prefix))
+                .arg(&tier)
+                .ignore()
+                .cmd("HDEL")
+                .arg(redis_gauge_key(prefix, METRIC_TIER_EWMA_LATENCY_SECONDS))
+                .arg(labels)
+                .ignore();
+            let _: () = pipe.query(conn)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,89 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//! OTLP trace export (cargo feature `otel`).
+//!
+//! When `OTEL_EXPORTER_OTLP_ENDPOINT` is set, `tracing` spans are exported over
+//! OTLP/gRPC to that collector (Jaeger, Tempo, ...). When it is unset, or the
+//! exporter cannot be built, no layer is installed and the server runs with
+//! stdout logging only.
+
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::trace::{Tracer, TracerProvider};
+use opentelemetry_sdk::{runtime, Resource};
+use tracing::Subscriber;
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_subscriber::registry::LookupSpan;
+
+pub const OTLP_ENDPOINT_ENV: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+
+/// Flushes buffered spans when dropped; keep it alive until shutdown.
+pub struct OtelGuard {
+    provider: TracerProvider,
+}
+
+impl Drop for OtelGuard {
+    fn drop(&mut self) {
+        if let Err(err) = self.provider.shutdown() {
+            eprintln!("Failed to flush OTLP spans: {err}");
+        }
+    }
+}
+
+/// Build the OTLP layer, or `None` when no endpoint is configured. Runs before
+/// the subscriber exists, so failures go to stderr.
+pub fn layer<S>() -> Option<(OpenTelemetryLayer<S, Tracer>, OtelGuard)>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    let endpoint = std::env::var(OTLP_ENDPOINT_ENV)
+        .ok()
+        .filter(|endpoint| !endpoint.trim().is_empty())?;
+    let exporter = match SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint.clone())
+        .build()
+    {
+        Ok(exporter) => exporter,
+        Err(err) => {
+            eprintln!("OTLP export to {endpoint} disabled: {err}");
+            return None;
+        }
+    };
+    let provider = TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_resource(Resource::new([KeyValue::new(
+            "service.name",
+            env!("CARGO_PKG_NAME"),
+        )]))
+        .build();
+    let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+    Some((
+        tracing_opentelemetry::layer().with_tracer(tracer),
+        OtelGuard { provider },
+    ))
+}
+
+/// Trace ID of the current span, when it belongs to an exported OTel trace.
+pub fn current_trace_id() -> Option<String> {
+    use opentelemetry::trace::TraceContextExt;
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+    let context = tracing::Span::current().context();
+    let span = context.span();
+    let span_context = span.span_context();
+    span_context
+        .is_valid()
+        .then(|| span_context.trace_id().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_layer_without_endpoint() {
+        std::env::remove_var(OTLP_ENDPOINT_ENV);
+        assert!(layer::<tracing_subscriber::Registry>().is_none());
+    }
+}
@@ -1,9 +1,13 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
+use anyhow::{Context, Result};
 use lazy_static::lazy_static;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use prometheus::{register_counter_vec, CounterVec};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::time::{Duration, Instant};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 lazy_static! {
     static ref RATE_LIMIT_BACKOFFS_TOTAL: CounterVec = register_counter_vec!(
@@ -22,9 +26,44 @@ pub struct TierRateLimitState {
     pub consecutive_429s: u32,
 }
 
+/// A tier's rate-limit state as reported by `GET /v1/latencies`. Deadlines
+/// already passed are left out.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RateLimitStatus {
+    /// Requests left in the provider's quota window, from its headers.
+    pub remaining: Option<u32>,
+    /// Time until the provider's quota window resets.
+    pub reset_in: Option<Duration>,
+    /// Time left on the backoff from a 429.
+    pub backoff_remaining: Option<Duration>,
+}
+
+/// A tier's rate-limit state as shared between CCR instances. Deadlines are
+/// wall-clock milliseconds since the Unix epoch so every instance can read
+/// them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SharedTierState {
+    #[serde(default)]
+    pub backoff_until_ms: Option<u64>,
+    #[serde(default)]
+    pub consecutive_429s: u32,
+    #[serde(default)]
+    pub remaining: Option<u32>,
+    #[serde(default)]
+    pub reset_at_ms: Option<u64>,
+}
+
+/// Fleet-wide rate-limit state. `publish` is called on the request path and
+/// must not block; `fetch_all` runs on the background sync thread.
+pub trait SharedRateLimitStore: Send + Sync {
+    fn publish(&self, tier: &str, state: SharedTierState);
+    fn fetch_all(&self) -> Result<HashMap<String, SharedTierState>>;
+}
+
 #[derive(Default)]
 pub struct RateLimitTracker {
     tiers: RwLock<HashMap<String, TierRateLimitState>>,
+    shared: Option<Arc<dyn SharedRateLimitStore>>,
 }
 
 impl RateLimitTracker {
@@ -32,6 +71,57 @@ impl RateLimitTracker {
         Self::default()
     }
 
+    /// A tracker that also publishes 429s and exhausted quotas to `store`
+    /// and, through [`Self::sync_from_shared`], skips tiers other instances
+    /// have seen rate limited.
+    pub fn with_shared_store(store: Arc<dyn SharedRateLimitStore>) -> Self {
+        Self {
+            tiers: RwLock::default(),
+            shared: Some(store),
+        }
+    }
+
+    /// Merge the fleet's view into local state. Remote deadlines only ever
+    /// extend local ones: a peer's success does not lift a backoff this
+    /// instance observed itself.
+    pub fn sync_from_shared(&self) -> Result<()> {
+        let Some(store) = self.shared.as_ref() else {
+            return Ok(());
+        };
+        let remote = store.fetch_all()?;
+        let mut tiers = self.tiers.write();
+        for (tier, shared) in remote {
+            let state = tiers.entry(tier).or_default();
+            if let Some(until) = shared.backoff_until_ms.and_then(instant_from_unix_ms) {
+                if state.backoff_until.is_none_or(|local| local < until) {
+                    state.backoff_until = Some(until);
+                }
+                state.consecutive_429s = state.consecutive_429s.max(shared.consecutive_429s);
+            }
+            if let Some(reset) = shared.reset_at_ms.and_then(instant_from_unix_ms) {
+                if state.reset_at.is_none_or(|local| local < reset) {
+                    state.remaining = shared.remaining;
+                    state.reset_at = Some(reset);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn publish(&self, tier: &str, state: &TierRateLimitState) {
+        if let Some(store) = self.shared.as_ref() {
+            store.publish(
+                tier,
+                SharedTierState {
+                    backoff_until_ms: state.backoff_until.map(unix_ms_from_instant),
+                    consecutive_429s: state.consecutive_429s,
+                    remaining: state.remaining,
+                    reset_at_ms: state.reset_at.map(unix_ms_from_instant),
+                },
+            );
+        }
+    }
+
     /// Check whether a tier should be skipped before dispatching.
     ///
     /// `honor_remaining` controls whether `X-RateLimit-Remaining: 0` from a
@@ -89,6 +179,24 @@ impl RateLimitTracker {
             .is_some_and(|until| Instant::now() < until)
     }
 
+    /// Current rate-limit state of `tier`; all `None` for a tier never seen.
+    pub fn status(&self, tier: &str) -> RateLimitStatus {
+        let tiers = self.tiers.read();
+        let Some(state) = tiers.get(tier) else {
+            return RateLimitStatus::default();
+        };
+        let now = Instant::now();
+        let until = |at: Option<Instant>| {
+            at.filter(|at| now < *at)
+                .map(|at| at.saturating_duration_since(now))
+        };
+        RateLimitStatus {
+            remaining: state.remaining,
+            reset_in: until(state.reset_at),
+            backoff_remaining: until(state.backoff_until),
+        }
+    }
+
     pub fn record_429(&self, tier: &str, retry_after: Option<Duration>) {
         let mut tiers = self.tiers.write();
         let state = tiers.entry(tier.to_string()).or_default();
@@ -102,6 +210,7 @@ impl RateLimitTracker {
             .min(Duration::from_secs(60));
 
         state.backoff_until = Some(Instant::now() + backoff);
+        self.publish(tier, state);
 
         tracing::warn!(
             tier = %tier,
@@ -119,6 +228,9 @@ impl RateLimitTracker {
         let mut tiers = self.tiers.write();
         let state = tiers.entry(tier.to_string()).or_default();
 
+        // Only publish changes peers act on, not every success.
+        let was_limited = state.backoff_until.is_some() || state.remaining == Some(0);
+
         // Clear backoff state on successful request
         state.consecutive_429s = 0;
         state.backoff_until = None;
@@ -127,6 +239,10 @@ impl RateLimitTracker {
         state.remaining = remaining;
         state.reset_at = reset_at;
 
+        if was_limited || remaining == Some(0) {
+            self.publish(tier, state);
+        }
+
         tracing::debug!(
             tier = %tier,
             remaining = ?remaining,
@@ -144,3 +260,185 @@ pub fn restore_rate_limit_backoff_counter(tier: &str, value: f64) {
             .inc_by(value);
     }
 }
+
+fn unix_ms_from_instant(instant: Instant) -> u64 {
+    let now = Instant::now();
+    let wall = if instant >= now {
+        SystemTime::now() + (instant - now)
+    } else {
+        SystemTime::now() - (now - instant)
+    };
+    wall.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// The local instant for a shared deadline, or `None` once it has passed.
+fn instant_from_unix_ms(ms: u64) -> Option<Instant> {
+    let deadline = UNIX_EPOCH + Duration::from_millis(ms);
+    let remaining = deadline.duration_since(SystemTime::now()).ok()?;
+    Some(Instant::now() + remaining)
+}
+
+/// Shared rate-limit state in one Redis hash, `{prefix}:ratelimit`, mapping
+/// tier to JSON. Writes go through a worker thread so publishing never
+/// blocks a request.
+pub struct RedisRateLimitStore {
+    client: redis::Client,
+    /// Connection reused by `fetch_all` across syncs; dropped after an error
+    /// and reopened on the next sync.
+    reader: Mutex<Option<redis::Connection>>,
+    key: String,
+    sender: Sender<(String, SharedTierState)>,
+}
+
+/// Entries outlive any backoff (capped at 60s) but not a stale deployment.
+const REDIS_RATE_LIMIT_TTL_SECS: i64 = 3600;
+
+impl RedisRateLimitStore {
+    pub fn open(redis_url: &str, prefix: &str) -> Result<Self> {
+        let client = redis::Client::open(redis_url)
+            .with_context(|| format!("Failed to create Redis client for {}", redis_url))?;
+        let key = format!("{prefix}:ratelimit");
+        let (sender, receiver) = mpsc::channel::<(String, SharedTierState)>();
+        let writer = client.clone();
+        let writer_key = key.clone();
+        std::thread::Builder::new()
+            .name("ccr-ratelimit-sync".to_string())
+            .spawn(move || {
+                let mut conn: Option<redis::Connection> = None;
+                for (tier, state) in receiver {
+                    let result = (|| -> Result<()> {
+                        if conn.is_none() {
+                            conn = Some(writer.get_connection()?);
+                        }
+                        let conn = conn.as_mut().expect("connection was just set");
+                        redis::pipe()
+                            .hset(&writer_key, &tier, serde_json::to_string(&state)?)
+                            .expire(&writer_key, REDIS_RATE_LIMIT_TTL_SECS)
+                            .query::<()>(conn)?;
+                        Ok(())
+                    })();
+                    if let Err(error) = result {
+                        conn = None;
+                        tracing::warn!(tier = %tier, "Failed to publish shared rate-limit state: {error}");
+                    }
+                }
+            })
+            .context("Failed to spawn shared rate-limit writer")?;
+        Ok(Self {
+            client,
+            reader: Mutex::new(None),
+            key,
+            sender,
+        })
+    }
+}
+
+impl SharedRateLimitStore for RedisRateLimitStore {
+    fn publish(&self, tier: &str, state: SharedTierState) {
+        let _ = self.sender.send((tier.to_string(), state));
+    }
+
+    fn fetch_all(&self) -> Result<HashMap<String, SharedTierState>> {
+        let mut reader = self.reader.lock();
+        if reader.is_none() {
+            *reader = Some(
+                self.client
+                    .get_connection()
+                    .context("Failed to connect to Redis for shared rate limits")?,
+            );
+        }
+        let conn = reader.as_mut().expect("connection was just set");
+        let raw: HashMap<String, String> = match redis::Commands::hgetall(conn, &self.key) {
+            Ok(raw) => raw,
+            Err(error) => {
+                *reader = None;
+                return Err(error.into());
+            }
+        };
+        Ok(raw
+            .into_iter()
+            .filter_map(|(tier, json)| Some((tier, serde_json::from_str(&json).ok()?)))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stands in for Redis: every tracker sharing it sees every publish.
+    #[derive(Default)]
+    struct InMemoryStore(Mutex<HashMap<String, SharedTierState>>);
+
+    impl SharedRateLimitStore for InMemoryStore {
+        fn publish(&self, tier: &str, state: SharedTierState) {
+            self.0.lock().insert(tier.to_string(), state);
+        }
+
+        fn fetch_all(&self) -> Result<HashMap<String, SharedTierState>> {
+            Ok(self.0.lock().clone())
+        }
+    }
+
+    #[test]
+    fn status_reports_only_pending_deadlines() {
+        let tracker = RateLimitTracker::new();
+        assert_eq!(tracker.status("glm"), RateLimitStatus::default());
+
+        tracker.record_429("glm", Some(Duration::from_secs(5)));
+        let status = tracker.status("glm");
+        assert!(status.backoff_remaining.is_some_and(|d| d > Duration::ZERO));
+        assert_eq!(status.remaining, None);
+
+        tracker.record_success(
+            "glm",
+            Some(0),
+            Instant::now().checked_sub(Duration::from_secs(1)),
+        );
+        let status = tracker.status("glm");
+        assert_eq!(status.backoff_remaining, None);
+        assert_eq!(status.remaining, Some(0));
+        assert_eq!(status.reset_in, None);
+    }
+
+    #[test]
+    fn shared_429_is_visible_to_other_instances() {
+        let store = Arc::new(InMemoryStore::default());
+        let a = RateLimitTracker::with_shared_store(store.clone());
+        let b = RateLimitTracker::with_shared_store(store.clone());
+
+        a.record_429("glm", Some(Duration::from_secs(5)));
+        assert!(!b.should_skip_tier("glm", true));
+        b.sync_from_shared().unwrap();
+        assert!(b.should_skip_tier("glm", true));
+        assert!(b.has_backoff("glm"));
+        assert!(!b.should_skip_tier("kimi", true));
+    }
+
+    #[test]
+    fn shared_exhausted_quota_is_visible_and_expired_state_is_ignored() {
+        let store = Arc::new(InMemoryStore::default());
+        let a = RateLimitTracker::with_shared_store(store.clone());
+        let b = RateLimitTracker::with_shared_store(store.clone());
+
+        a.record_success(
+            "glm",
+            Some(0),
+            Some(Instant::now() + Duration::from_secs(30)),
+        );
+        store.publish(
+            "kimi",
+            SharedTierState {
+                backoff_until_ms: Some(1),
+                consecutive_429s: 3,
+                ..Default::default()
+            },
+        );
+        b.sync_from_shared().unwrap();
+        assert!(b.should_skip_tier("glm", true));
+        assert!(!b.should_skip_tier("glm", false));
+        assert!(!b.should_skip_tier("kimi", true));
+    }
+}
@@ -0,0 +1,115 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// The HTTP router served by `ccr-rust start`.
+//
+// Built here rather than in the binary so integration tests exercise the same
+// routes and middleware stack the server runs.
+
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+    routing::{get, post},
+    Json, Router,
+};
+use tower_http::{cors::CorsLayer, trace::TraceLayer};
+
+use super::{
+    assign_request_id, handle_chat_completions, handle_completions, handle_messages,
+    handle_preset_messages, handle_reload, handle_responses, limit_request_body, list_models,
+    list_presets, list_providers, preview_transformers, ready, reject_during_maintenance,
+    reject_while_draining, require_admin_token, require_client_key, reset_latencies,
+    response_compression_layer, set_maintenance, throttle_ingress, AppState,
+};
+use crate::metrics;
+
+/// All routes with their guards, bound to `state`.
+pub fn app(state: AppState) -> Router {
+    // Admin routes require ADMIN_TOKEN when one is configured.
+    let admin_routes = Router::new()
+        .route("/v1/reload", post(handle_reload))
+        .route("/v1/latencies/reset", post(reset_latencies))
+        .route("/v1/maintenance", post(set_maintenance))
+        .route("/v1/providers", get(list_providers))
+        .route("/metrics", get(metrics::metrics_handler))
+        .route("/v1/transformers/preview", post(preview_transformers))
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            require_admin_token,
+        ));
+
+    // Proxy routes require one of CLIENT_KEYS when any are configured, are
+    // refused in maintenance mode, and are limited per client by
+    // INGRESS_RATE_LIMIT.
+    let proxy_routes = Router::new()
+        .route(
+            "/v1/messages",
+            post(handle_messages).layer(response_compression_layer()),
+        )
+        .route(
+            "/v1/chat/completions",
+            post(handle_chat_completions).layer(response_compression_layer()),
+        )
+        .route(
+            "/v1/responses",
+            post(handle_responses).layer(response_compression_layer()),
+        )
+        .route("/v1/completions", post(handle_completions))
+        .route("/preset/:name/v1/messages", post(handle_preset_messages))
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            limit_request_body,
+        ))
+        .route_layer(axum::extract::DefaultBodyLimit::disable())
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            throttle_ingress,
+        ))
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            reject_during_maintenance,
+        ))
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            require_client_key,
+        ))
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            reject_while_draining,
+        ));
+
+    Router::new()
+        .route("/v1/models", get(list_models))
+        .route("/v1/presets", get(list_presets))
+        .route("/v1/latencies", get(latencies_handler))
+        .route("/v1/usage", get(metrics::usage_handler))
+        .route("/v1/token-drift", get(metrics::token_drift_handler))
+        .route("/v1/token-audit", get(metrics::token_audit_handler))
+        .route("/v1/throughput", get(metrics::throughput_handler))
+        .route(
+            "/v1/frontend-metrics",
+            get(metrics::frontend_metrics_handler),
+        )
+        .route("/health", get(health))
+        .route("/ready", get(ready))
+        .merge(proxy_routes)
+        .merge(admin_routes)
+        .layer(CorsLayer::permissive())
+        .layer(TraceLayer::new_for_http())
+        .layer(axum::middleware::from_fn(assign_request_id))
+        .with_state(state)
+}
+
+async fn latencies_handler(
+    State(state): State<AppState>,
+    Query(query): Query<metrics::ProviderFilterQuery>,
+) -> impl IntoResponse {
+    Json(metrics::get_latency_entries(
+        &state.ewma_tracker,
+        &state.ratelimit_tracker,
+        &state.config.load(),
+        &query.filter(),
+    ))
+}
+
+async fn health() -> &'static str {
+    "ok"
+}
@@ -0,0 +1,179 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Token guards for the admin routes and the client-facing proxy routes.
+//
+// Each guard is a route layer on its own route group, and both are no-ops
+// until the matching config key is set. Tokens are read from the current
+// config snapshot, so a hot reload can set, rotate, or clear them.
+
+use axum::{
+    extract::{Request, State},
+    http::{
+        header::{AUTHORIZATION, WWW_AUTHENTICATE},
+        HeaderMap, StatusCode,
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+use super::AppState;
+
+/// Token from an `Authorization: Bearer <token>` header.
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    let (scheme, token) = headers.get(AUTHORIZATION)?.to_str().ok()?.split_once(' ')?;
+    (scheme.eq_ignore_ascii_case("Bearer") && !token.is_empty()).then_some(token)
+}
+
+/// Compare tokens by digest in constant time.
+fn token_eq(candidate: &str, expected: &str) -> bool {
+    let candidate: [u8; 32] = Sha256::digest(candidate.as_bytes()).into();
+    let expected: [u8; 32] = Sha256::digest(expected.as_bytes()).into();
+    candidate.ct_eq(&expected).into()
+}
+
+/// Whether `headers` carry `Authorization: Bearer <expected>`.
+fn bearer_matches(headers: &HeaderMap, expected: &str) -> bool {
+    bearer_token(headers).is_some_and(|token| token_eq(token, expected))
+}
+
+/// Keys presented by a client: `x-api-key` (Anthropic clients) and
+/// `Authorization: Bearer` (OpenAI clients), in that order.
+fn presented_keys(headers: &HeaderMap) -> impl Iterator<Item = &str> {
+    let x_api_key = headers
+        .get("x-api-key")
+        .and_then(|value| value.to_str().ok())
+        .filter(|key| !key.is_empty());
+    [x_api_key, bearer_token(headers)].into_iter().flatten()
+}
+
+/// The first presented client key that is one of `keys`. Both headers are
+/// checked, so a client that sends a placeholder in one and its real key in
+/// the other is still accepted.
+pub(super) fn verified_client_key<'a>(headers: &'a HeaderMap, keys: &[String]) -> Option<&'a str> {
+    presented_keys(headers).find(|presented| keys.iter().any(|key| token_eq(presented, key)))
+}
+
+/// Reject requests without the configured `ADMIN_TOKEN` with 401. Passes
+/// everything through when no token is configured.
+pub async fn require_admin_token(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let authorized = match state.config.load().admin_token() {
+        Some(token) => bearer_matches(request.headers(), token),
+        None => true,
+    };
+    if authorized {
+        next.run(request).await
+    } else {
+        (
+            StatusCode::UNAUTHORIZED,
+            [(WWW_AUTHENTICATE, "Bearer")],
+            axum::Json(serde_json::json!({"error": "Admin token required"})),
+        )
+            .into_response()
+    }
+}
+
+/// 401 body in the error shape of the frontend the path belongs to.
+fn client_unauthorized(path: &str) -> Response {
+    let message = "Invalid or missing API key";
+    let body = if path == "/v1/messages" || path.starts_with("/preset/") {
+        serde_json::json!({
+            "type": "error",
+            "error": {
+                "type": "authentication_error",
+                "message": message
+            }
+        })
+    } else {
+        serde_json::json!({
+            "error": {
+                "message": message,
+                "type": "invalid_request_error",
+                "param": null,
+                "code": "invalid_api_key"
+            }
+        })
+    };
+    (
+        StatusCode::UNAUTHORIZED,
+        [(WWW_AUTHENTICATE, "Bearer")],
+        axum::Json(body),
+    )
+        .into_response()
+}
+
+/// Reject proxy requests whose key is not in `CLIENT_KEYS` with 401. Passes
+/// everything through when no keys are configured.
+pub async fn require_client_key(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let config = state.config.load();
+    let keys = config.client_keys();
+    let authorized = keys.is_empty() || verified_client_key(request.headers(), keys).is_some();
+    if authorized {
+        next.run(request).await
+    } else {
+        client_unauthorized(request.uri().path())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers_with(name: &'static str, value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_static(value));
+        headers
+    }
+
+    #[test]
+    fn bearer_matches_requires_exact_token() {
+        let auth = |value| headers_with("authorization", value);
+        assert!(!bearer_matches(&HeaderMap::new(), "secret"));
+        assert!(!bearer_matches(&auth("Bearer wrong"), "secret"));
+        assert!(!bearer_matches(&auth("Basic secret"), "secret"));
+        assert!(!bearer_matches(&auth("Bearer "), "secret"));
+        assert!(bearer_matches(&auth("Bearer secret"), "secret"));
+        assert!(bearer_matches(&auth("bearer secret"), "secret"));
+    }
+
+    #[test]
+    fn presented_keys_reads_x_api_key_and_bearer() {
+        let keys = |headers: &HeaderMap| {
+            presented_keys(headers)
+                .map(str::to_owned)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(keys(&headers_with("x-api-key", "sk-ant")), ["sk-ant"]);
+        assert_eq!(
+            keys(&headers_with("authorization", "Bearer sk-oai")),
+            ["sk-oai"]
+        );
+        assert!(keys(&headers_with("x-api-key", "")).is_empty());
+        assert!(keys(&HeaderMap::new()).is_empty());
+    }
+
+    #[test]
+    fn verified_client_key_accepts_either_header() {
+        let keys = vec!["good".to_string()];
+        let mut headers = headers_with("x-api-key", "placeholder");
+        headers.insert("authorization", HeaderValue::from_static("Bearer good"));
+        assert_eq!(verified_client_key(&headers, &keys), Some("good"));
+
+        let mut headers = headers_with("x-api-key", "good");
+        headers.insert("authorization", HeaderValue::from_static("Bearer wrong"));
+        assert_eq!(verified_client_key(&headers, &keys), Some("good"));
+
+        let mut headers = headers_with("x-api-key", "wrong");
+        headers.insert("authorization", HeaderValue::from_static("Bearer wrong"));
+        assert_eq!(verified_client_key(&headers, &keys), None);
+    }
+}
@@ -0,0 +1,95 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Request body size limit for the proxy routes.
+//
+// `limit_request_body` wraps the proxy route group and buffers each body up
+// to `MAX_BODY_BYTES`, answering 413 in the frontend's error shape once it is
+// exceeded. Handlers that decode a `content-encoding` check the decoded size
+// against the same limit. axum's own extractor limit is disabled on these
+// routes so the configured value is the only one that applies.
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{Request, State},
+    http::{header::CONTENT_LENGTH, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use futures::StreamExt;
+
+use super::AppState;
+
+/// Why a request body could not be buffered.
+pub(super) enum BodyReadError {
+    /// The body is larger than the limit.
+    TooLarge,
+    /// The client connection failed mid-body.
+    Read(axum::Error),
+}
+
+/// Buffer `body`, giving up as soon as it grows past `limit` bytes.
+pub(super) async fn read_body_limited(body: Body, limit: usize) -> Result<Bytes, BodyReadError> {
+    let mut stream = body.into_data_stream();
+    let mut buffered = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(BodyReadError::Read)?;
+        if buffered.len() + chunk.len() > limit {
+            return Err(BodyReadError::TooLarge);
+        }
+        buffered.extend_from_slice(&chunk);
+    }
+    Ok(Bytes::from(buffered))
+}
+
+/// 413 body in the error shape of the frontend the path belongs to.
+pub(super) fn body_too_large_response(path: &str, limit: usize) -> Response {
+    let message = format!("Request body exceeds the {} byte limit", limit);
+    let body = if path == "/v1/messages" || path.starts_with("/preset/") {
+        serde_json::json!({
+            "type": "error",
+            "error": {"type": "request_too_large", "message": message}
+        })
+    } else {
+        serde_json::json!({
+            "error": {
+                "message": message,
+                "type": "invalid_request_error",
+                "param": null,
+                "code": "request_too_large"
+            }
+        })
+    };
+    (StatusCode::PAYLOAD_TOO_LARGE, axum::Json(body)).into_response()
+}
+
+/// Reject proxy requests whose body exceeds `MAX_BODY_BYTES` with 413.
+pub async fn limit_request_body(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let limit = state.config.load().max_body_bytes();
+    let declared = request
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+    if declared.is_some_and(|len| len > limit as u64) {
+        return body_too_large_response(request.uri().path(), limit);
+    }
+
+    let (parts, body) = request.into_parts();
+    match read_body_limited(body, limit).await {
+        Ok(bytes) => {
+            next.run(Request::from_parts(parts, Body::from(bytes)))
+                .await
+        }
+        Err(BodyReadError::TooLarge) => body_too_large_response(parts.uri.path(), limit),
+        Err(BodyReadError::Read(err)) => (
+            StatusCode::BAD_REQUEST,
+            axum::Json(serde_json::json!({
+                "error": {"message": format!("Failed to read request body: {}", err)}
+            })),
+        )
+            .into_response(),
+    }
+}
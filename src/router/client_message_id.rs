@@ -0,0 +1,102 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Client message id echo.
+//
+// Clients that multiplex requests can tag each one with
+// `metadata.client_message_id` or the `x-ccr-client-message-id` header. The id
+// is returned unchanged in the same header and, for JSON responses, as a
+// top-level `client_message_id` field, so responses can be matched to requests
+// without relying on ordering.
+
+use axum::{
+    body::{to_bytes, Body},
+    http::{header::CONTENT_LENGTH, HeaderMap, HeaderValue},
+    response::Response,
+};
+
+use super::drain::is_event_stream;
+
+pub const CLIENT_MESSAGE_ID_HEADER: &str = "x-ccr-client-message-id";
+
+/// Ids longer than this are ignored rather than echoed.
+const MAX_CLIENT_MESSAGE_ID_LEN: usize = 256;
+
+/// The id to echo, from `metadata.client_message_id` or, failing that, the
+/// request header. Ids that are empty, too long, or not valid header values
+/// are ignored.
+pub(super) fn client_message_id(
+    headers: &HeaderMap,
+    metadata: Option<&serde_json::Value>,
+) -> Option<HeaderValue> {
+    let from_metadata = metadata
+        .and_then(|metadata| metadata.get("client_message_id"))
+        .and_then(|id| id.as_str());
+    let from_header = || {
+        headers
+            .get(CLIENT_MESSAGE_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+    };
+    from_metadata
+        .or_else(from_header)
+        .filter(|id| !id.is_empty() && id.len() <= MAX_CLIENT_MESSAGE_ID_LEN)
+        .and_then(|id| HeaderValue::from_str(id).ok())
+}
+
+/// Attach `id` to the response header and, when the body is a JSON object
+/// without its own `client_message_id`, to the body. Streams get the header
+/// only.
+pub(super) async fn echo_client_message_id(response: Response, id: HeaderValue) -> Response {
+    // Translated upstream bodies are not always labelled as JSON, so anything
+    // that is not an event stream is checked by parsing it.
+    let buffered = !is_event_stream(&response);
+    let (mut parts, body) = response.into_parts();
+    let body = if buffered {
+        match to_bytes(body, usize::MAX).await {
+            Ok(bytes) => match serde_json::from_slice::<serde_json::Value>(&bytes) {
+                Ok(serde_json::Value::Object(mut object)) => {
+                    let echoed = id.to_str().unwrap_or_default().to_string();
+                    object
+                        .entry("client_message_id")
+                        .or_insert(serde_json::Value::String(echoed));
+                    parts.headers.remove(CONTENT_LENGTH);
+                    Body::from(serde_json::to_vec(&object).unwrap_or_else(|_| bytes.to_vec()))
+                }
+                _ => Body::from(bytes),
+            },
+            Err(_) => Body::empty(),
+        }
+    } else {
+        body
+    };
+    parts.headers.insert(CLIENT_MESSAGE_ID_HEADER, id);
+    Response::from_parts(parts, body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metadata_id_wins_over_header() {
+        let mut headers = HeaderMap::new();
+        headers.insert(CLIENT_MESSAGE_ID_HEADER, HeaderValue::from_static("hdr-1"));
+        let metadata = serde_json::json!({"client_message_id": "meta-1"});
+
+        let id = client_message_id(&headers, Some(&metadata)).unwrap();
+        assert_eq!(id, "meta-1");
+        assert_eq!(client_message_id(&headers, None).unwrap(), "hdr-1");
+    }
+
+    #[test]
+    fn invalid_ids_are_ignored() {
+        let headers = HeaderMap::new();
+        for id in [
+            serde_json::json!(""),
+            serde_json::json!("line\nbreak"),
+            serde_json::json!("x".repeat(MAX_CLIENT_MESSAGE_ID_LEN + 1)),
+            serde_json::json!(42),
+        ] {
+            let metadata = serde_json::json!({ "client_message_id": id });
+            assert!(client_message_id(&headers, Some(&metadata)).is_none());
+        }
+    }
+}
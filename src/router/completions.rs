@@ -0,0 +1,346 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Legacy OpenAI Completions compatibility layer.
+//
+// Wraps a `prompt` string into a single-turn Anthropic request, routes it
+// through `handle_messages`, and converts the result back into the
+// `text_completion` shape. This handles the `/v1/completions` endpoint.
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use bytes::Bytes;
+use futures::StreamExt;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::error;
+
+use crate::metrics::increment_active_streams;
+use crate::sse::SseFrameDecoder;
+
+use super::{
+    handle_anthropic_request, AnthropicContentBlock, AnthropicRequest, AnthropicResponse, AppState,
+};
+
+/// Extract the prompt text. Legacy clients send either a string or a
+/// single-element array of strings; batched prompts are not supported.
+fn extract_prompt(body: &serde_json::Value) -> Result<String, String> {
+    match body.get("prompt") {
+        Some(serde_json::Value::String(prompt)) => Ok(prompt.clone()),
+        Some(serde_json::Value::Array(items)) => match items.as_slice() {
+            [serde_json::Value::String(prompt)] => Ok(prompt.clone()),
+            _ => Err("prompt must be a string or a single-element string array".to_string()),
+        },
+        Some(_) => Err("prompt must be a string".to_string()),
+        None => Err("missing required field: prompt".to_string()),
+    }
+}
+
+/// `stop` may be a single string or an array of strings.
+fn stop_sequences(body: &serde_json::Value) -> Option<Vec<String>> {
+    match body.get("stop")? {
+        serde_json::Value::String(stop) => Some(vec![stop.clone()]),
+        serde_json::Value::Array(items) => Some(
+            items
+                .iter()
+                .filter_map(|v| v.as_str().map(str::to_string))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+pub(super) fn completions_request_to_anthropic_request(
+    body: &serde_json::Value,
+) -> Result<AnthropicRequest, String> {
+    let prompt = extract_prompt(body)?;
+    let model = body
+        .get("model")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| "missing required field: model".to_string())?;
+
+    Ok(AnthropicRequest {
+        model: model.to_string(),
+        messages: vec![super::Message {
+            role: "user".to_string(),
+            content: serde_json::Value::String(prompt),
+            tool_call_id: None,
+        }],
+        system: None,
+        max_tokens: body
+            .get("max_tokens")
+            .and_then(|v| v.as_u64())
+            .map(|v| v as u32),
+        temperature: body
+            .get("temperature")
+            .and_then(|v| v.as_f64())
+            .map(|v| v as f32),
+        top_p: body.get("top_p").and_then(|v| v.as_f64()).map(|v| v as f32),
+        stream: body.get("stream").and_then(|v| v.as_bool()),
+        tools: None,
+        tool_choice: None,
+        metadata: body.get("metadata").cloned(),
+        openai_passthrough_body: None,
+        excluded_tier: None,
+        stop_sequences: stop_sequences(body),
+        modalities: None,
+        audio: None,
+        unrecognized: serde_json::Map::new(),
+    })
+}
+
+/// Map an Anthropic `stop_reason` onto the legacy `finish_reason` values.
+fn completions_finish_reason(stop_reason: Option<&str>) -> &'static str {
+    match stop_reason {
+        Some("max_tokens") => "length",
+        _ => "stop",
+    }
+}
+
+fn anthropic_response_to_completions_json(response: &AnthropicResponse) -> serde_json::Value {
+    let text: String = response
+        .content
+        .iter()
+        .filter_map(|block| match block {
+            AnthropicContentBlock::Text { text } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect();
+
+    serde_json::json!({
+        "id": response.id,
+        "object": "text_completion",
+        "created": chrono::Utc::now().timestamp(),
+        "model": response.model,
+        "choices": [{
+            "text": text,
+            "index": 0,
+            "logprobs": null,
+            "finish_reason": completions_finish_reason(response.stop_reason.as_deref()),
+        }],
+        "usage": {
+            "prompt_tokens": response.usage.input_tokens,
+            "completion_tokens": response.usage.output_tokens,
+            "total_tokens": response.usage.input_tokens + response.usage.output_tokens,
+        }
+    })
+}
+
+async fn convert_anthropic_json_response_to_completions(response: Response) -> Response {
+    let (mut parts, body) = response.into_parts();
+    let body_bytes = match to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            error!("Failed to read Anthropic response body: {}", err);
+            return (
+                StatusCode::BAD_GATEWAY,
+                Json(serde_json::json!({"error": "Failed to read upstream response"})),
+            )
+                .into_response();
+        }
+    };
+
+    if parts.status != StatusCode::OK {
+        return Response::from_parts(parts, Body::from(body_bytes));
+    }
+
+    let anthropic_response: AnthropicResponse = match serde_json::from_slice(&body_bytes) {
+        Ok(resp) => resp,
+        Err(_) => return Response::from_parts(parts, Body::from(body_bytes)),
+    };
+
+    let completion = anthropic_response_to_completions_json(&anthropic_response);
+    parts.headers.remove(axum::http::header::CONTENT_LENGTH);
+    parts.headers.insert(
+        axum::http::header::CONTENT_TYPE,
+        axum::http::HeaderValue::from_static("application/json"),
+    );
+    Response::from_parts(parts, Body::from(completion.to_string()))
+}
+
+fn completions_stream_chunk(
+    id: &str,
+    model: &str,
+    created: i64,
+    text: &str,
+    finish_reason: Option<&str>,
+) -> Bytes {
+    let chunk = serde_json::json!({
+        "id": id,
+        "object": "text_completion",
+        "created": created,
+        "model": model,
+        "choices": [{
+            "text": text,
+            "index": 0,
+            "logprobs": null,
+            "finish_reason": finish_reason,
+        }]
+    });
+    Bytes::from(format!("data: {}\n\n", chunk))
+}
+
+async fn convert_anthropic_stream_response_to_completions(response: Response) -> Response {
+    let (mut parts, body) = response.into_parts();
+
+    if parts.status != StatusCode::OK {
+        return Response::from_parts(parts, body);
+    }
+
+    parts.headers.remove(axum::http::header::CONTENT_LENGTH);
+    parts.headers.insert(
+        axum::http::header::CONTENT_TYPE,
+        axum::http::HeaderValue::from_static("text/event-stream"),
+    );
+
+    let (tx, rx) = tokio::sync::mpsc::channel::<Result<Bytes, std::io::Error>>(100);
+
+    increment_active_streams(1);
+
+    tokio::spawn(async move {
+        let mut stream = body.into_data_stream();
+        let mut decoder = SseFrameDecoder::new();
+        let created = chrono::Utc::now().timestamp();
+        let mut id = String::new();
+        let mut model = String::new();
+        let mut sent_finish = false;
+
+        'outer: loop {
+            tokio::select! {
+                chunk = stream.next() => {
+                    let Some(chunk_res) = chunk else { break; };
+                    let bytes = match chunk_res {
+                        Ok(bytes) => bytes,
+                        Err(e) => {
+                            error!("Stream read error: {}", e);
+                            break;
+                        }
+                    };
+
+                    for frame in decoder.push(&bytes) {
+                        let Ok(event) = serde_json::from_str::<serde_json::Value>(&frame.data) else {
+                            continue;
+                        };
+                        let event_type = event
+                            .get("type")
+                            .and_then(|t| t.as_str())
+                            .or(frame.event.as_deref())
+                            .unwrap_or("");
+
+                        let out = match event_type {
+                            "message_start" => {
+                                let message = event.get("message");
+                                if let Some(v) = message.and_then(|m| m.get("id")).and_then(|v| v.as_str()) {
+                                    id = v.to_string();
+                                }
+                                if let Some(v) = message.and_then(|m| m.get("model")).and_then(|v| v.as_str()) {
+                                    model = v.to_string();
+                                }
+                                None
+                            }
+                            "content_block_delta" => event
+                                .get("delta")
+                                .filter(|d| d.get("type").and_then(|t| t.as_str()) == Some("text_delta"))
+                                .and_then(|d| d.get("text"))
+                                .and_then(|t| t.as_str())
+                                .filter(|t| !t.is_empty())
+                                .map(|text| completions_stream_chunk(&id, &model, created, text, None)),
+                            "message_delta" if !sent_finish => {
+                                sent_finish = true;
+                                let stop_reason = event
+                                    .get("delta")
+                                    .and_then(|d| d.get("stop_reason"))
+                                    .and_then(|r| r.as_str());
+                                Some(completions_stream_chunk(
+                                    &id,
+                                    &model,
+                                    created,
+                                    "",
+                                    Some(completions_finish_reason(stop_reason)),
+                                ))
+                            }
+                            _ => None,
+                        };
+
+                        if let Some(out) = out {
+                            if tx.send(Ok(out)).await.is_err() {
+                                break 'outer;
+                            }
+                        }
+                    }
+                }
+                _ = tx.closed() => break,
+            }
+        }
+
+        let _ = tx.send(Ok(Bytes::from("data: [DONE]\n\n"))).await;
+        increment_active_streams(-1);
+    });
+
+    Response::from_parts(parts, Body::from_stream(ReceiverStream::new(rx)))
+}
+
+/// Handle legacy OpenAI completion requests (`prompt` instead of `messages`).
+///
+/// The prompt becomes a single user turn routed through the normal pipeline;
+/// the Anthropic response or stream is converted to the `text_completion` shape.
+pub async fn handle_completions(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request_body): Json<serde_json::Value>,
+) -> Response {
+    let anthropic_request = match completions_request_to_anthropic_request(&request_body) {
+        Ok(req) => req,
+        Err(e) => {
+            error!("Failed to parse completions request: {}", e);
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({"error": format!("Invalid request: {}", e)})),
+            )
+                .into_response();
+        }
+    };
+    let stream_requested = anthropic_request.stream.unwrap_or(false);
+    let response = handle_anthropic_request(state, headers, anthropic_request).await;
+
+    if stream_requested {
+        convert_anthropic_stream_response_to_completions(response).await
+    } else {
+        convert_anthropic_json_response_to_completions(response).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_prompt_into_single_user_message() {
+        let req = completions_request_to_anthropic_request(&serde_json::json!({
+            "model": "mock,test-model",
+            "prompt": "Say hi",
+            "max_tokens": 16,
+            "stream": true
+        }))
+        .unwrap();
+
+        assert_eq!(req.messages.len(), 1);
+        assert_eq!(req.messages[0].role, "user");
+        assert_eq!(req.messages[0].content, "Say hi");
+        assert_eq!(req.max_tokens, Some(16));
+        assert_eq!(req.stream, Some(true));
+    }
+
+    #[test]
+    fn rejects_batched_or_missing_prompts() {
+        assert!(completions_request_to_anthropic_request(
+            &serde_json::json!({"model": "m", "prompt": ["a", "b"]})
+        )
+        .is_err());
+        assert!(
+            completions_request_to_anthropic_request(&serde_json::json!({"model": "m"})).is_err()
+        );
+    }
+}
@@ -0,0 +1,20 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Response compression for the proxy endpoints.
+//
+// Non-streaming replies are gzip- or zstd-encoded when the client's
+// `Accept-Encoding` allows it. Event streams are never compressed: the encoder
+// buffers output, which would hold back SSE frames until the buffer fills.
+
+use tower_http::compression::{predicate::DefaultPredicate, CompressionLayer};
+
+/// Compression layer for `/v1/messages`, `/v1/chat/completions`, and
+/// `/v1/responses`.
+///
+/// `DefaultPredicate` skips `text/event-stream`, gRPC, images, and bodies
+/// under 32 bytes.
+pub fn response_compression_layer() -> CompressionLayer<DefaultPredicate> {
+    CompressionLayer::new()
+        .gzip(true)
+        .zstd(true)
+        .compress_when(DefaultPredicate::new())
+}
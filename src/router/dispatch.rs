@@ -3,23 +3,30 @@ use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use std::sync::Arc;
 use std::time::Duration;
-use tracing::{trace, warn};
+use tracing::{debug, info, trace, warn};
 
 use super::streaming::{
     stream_anthropic_response_with_tracking, stream_response_translated, BoxByteStream,
 };
-use super::translate_request::translate_request_anthropic_to_openai;
-use super::translate_response::{build_transformer_chain, translate_response_openai_to_anthropic};
+use super::translate_request::{
+    hoist_system_messages, raised_reasoning_budget, tool_messages_to_tool_results,
+    translate_request_anthropic_to_openai,
+};
+use super::translate_response::{
+    build_transformer_chain, emits_reasoning, is_nonstandard_role,
+    translate_response_openai_to_anthropic,
+};
 use super::types::*;
-use crate::config::{Config, ProviderProtocol};
-use crate::debug_capture::{CaptureBuilder, DebugCapture};
+use super::warnings::RequestWarnings;
+use crate::config::{Config, ErrorClass, ModelPricing, ProviderProtocol, SoftErrorConfig};
+use crate::debug_capture::{CaptureBuilder, CapturedInteraction, DebugCapture};
 use crate::metrics::{
-    record_cost, record_rate_limit_backoff, record_rate_limit_hit, record_usage, verify_token_usage,
+    record_cost, record_nonstandard_role, record_rate_limit_backoff, record_rate_limit_hit,
+    record_soft_error, record_usage, verify_token_usage,
 };
 use crate::ratelimit::RateLimitTracker;
 use crate::sse::{SseFrameDecoder, StreamVerifyCtx};
-use crate::transform::openai_to_anthropic::OpenAiToAnthropicTransformer;
-use crate::transformer::{Transformer, TransformerChain, TransformerRegistry};
+use crate::transformer::{TransformerChain, TransformerRegistry};
 use futures::StreamExt;
 
 fn sanitized_capture_headers(headers: &reqwest::header::HeaderMap) -> serde_json::Value {
@@ -68,6 +75,40 @@ async fn persist_debug_capture(
     }
 }
 
+/// A successful interaction held back until its token drift is known.
+type PendingDriftCapture = (Arc<DebugCapture>, CapturedInteraction);
+
+/// Record the capture of a successful response, or under the `on_drift`
+/// trigger hand it back for [`persist_drift_capture`].
+async fn capture_success(
+    capture: Option<&Arc<DebugCapture>>,
+    builder: Option<CaptureBuilder>,
+    status: u16,
+    response_body: &str,
+    response_headers: Option<serde_json::Value>,
+) -> Option<PendingDriftCapture> {
+    let (capture, builder) = (capture?, builder?);
+    let interaction = builder.complete(status, response_body, response_headers, None);
+    if capture.captures_on_drift() {
+        return Some((capture.clone(), interaction));
+    }
+    if let Err(capture_err) = capture.record(interaction).await {
+        warn!("Failed to record debug capture: {}", capture_err);
+    }
+    None
+}
+
+/// Record a held-back capture if the request's token drift reached the
+/// configured threshold.
+async fn persist_drift_capture(pending: Option<PendingDriftCapture>, drift_pct: Option<f64>) {
+    let (Some((capture, interaction)), Some(drift_pct)) = (pending, drift_pct) else {
+        return;
+    };
+    if let Err(capture_err) = capture.record_drift(interaction, drift_pct).await {
+        warn!("Failed to record debug capture: {}", capture_err);
+    }
+}
+
 /// Peek at the first chunk of a streaming response to detect error payloads
 /// wrapped in HTTP 200 (e.g. Z.AI returns quota errors as raw JSON in a
 /// `text/event-stream` body).  Returns `Err` if an error is found, or a
@@ -82,7 +123,57 @@ async fn persist_debug_capture(
 /// frame or JSON object.
 const MAX_STREAM_PEEK_BYTES: usize = 1024 * 1024;
 
-fn embedded_stream_error(payload: &str) -> Option<(String, String)> {
+/// Whether an error field carries a value: null, `false`, `0`, and empty
+/// strings, arrays, or objects mean "no error" (e.g. `status_code: 0`).
+fn is_error_value(value: &serde_json::Value) -> bool {
+    match value {
+        serde_json::Value::Null => false,
+        serde_json::Value::Bool(b) => *b,
+        serde_json::Value::Number(n) => n.as_f64() != Some(0.0),
+        serde_json::Value::String(s) => !s.is_empty(),
+        serde_json::Value::Array(a) => !a.is_empty(),
+        serde_json::Value::Object(o) => !o.is_empty(),
+    }
+}
+
+/// Error code and message when `json` is a provider error per `config`: an
+/// error field is set and there is no `choices`, `content`, or `output` to
+/// forward instead.
+fn soft_error(json: &serde_json::Value, config: &SoftErrorConfig) -> Option<(String, String)> {
+    if !config.enabled {
+        return None;
+    }
+    let has_payload = ["choices", "content", "output"].iter().any(|key| {
+        json.get(key)
+            .and_then(|v| v.as_array())
+            .is_some_and(|items| !items.is_empty())
+    });
+    if has_payload {
+        return None;
+    }
+    let error = config
+        .error_paths
+        .iter()
+        .filter_map(|path| json.pointer(path))
+        .find(|value| is_error_value(value))?;
+
+    let msg = match error {
+        serde_json::Value::String(s) => s.clone(),
+        _ => error
+            .get("message")
+            .and_then(|m| m.as_str())
+            .unwrap_or("Unknown error in response body")
+            .to_string(),
+    };
+    let code = match error.get("code") {
+        Some(serde_json::Value::String(s)) => s.clone(),
+        Some(serde_json::Value::Number(n)) => n.to_string(),
+        _ => "unknown".to_string(),
+    };
+    Some((code, msg))
+}
+
+fn embedded_stream_error(payload: &str, config: &SoftErrorConfig) -> Option<(String, String)> {
     let trimmed = payload.trim();
     let json_candidate = if trimmed.starts_with('{') {
         Some(trimmed.to_string())
@@ -96,23 +187,125 @@ fn embedded_stream_error(payload: &str) -> Option<(String, String)> {
 
     let candidate = json_candidate?;
     let json = serde_json::from_str::<serde_json::Value>(&candidate).ok()?;
-    json.get("error")?;
+    soft_error(&json, config)
+}
 
-    let msg = json["error"]["message"]
-        .as_str()
-        .unwrap_or("Unknown error in stream body")
-        .to_string();
-    let code = json["error"]["code"]
-        .as_str()
-        .unwrap_or("unknown")
-        .to_string();
-    Some((code, msg))
+/// Token usage reported by an attempt that still failed (an error in a 200
+/// body, or a stream that errored before it was handed to the client).
+///
+/// The provider billed those tokens, so they are recorded under the failed
+/// tier even though the request falls through to the next one.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct PartialUsage {
+    input_tokens: u64,
+    output_tokens: u64,
+    cache_read_tokens: u64,
+    cache_creation_tokens: u64,
+}
+
+impl PartialUsage {
+    /// Fold in the `usage` of an OpenAI or Anthropic payload, including the
+    /// nested `message.usage` of an Anthropic `message_start` event. Streams
+    /// report cumulative counts, so each field keeps the largest value seen.
+    fn absorb(&mut self, value: &serde_json::Value) {
+        let Some(usage) = value
+            .get("usage")
+            .or_else(|| value.get("message").and_then(|m| m.get("usage")))
+            .filter(|u| u.is_object())
+        else {
+            return;
+        };
+        let field = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| usage.get(*name).and_then(|v| v.as_u64()))
+                .unwrap_or(0)
+        };
+        let cached = usage
+            .get("prompt_tokens_details")
+            .and_then(|d| d.get("cached_tokens"))
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0);
+
+        self.input_tokens = self
+            .input_tokens
+            .max(field(&["prompt_tokens", "input_tokens"]));
+        self.output_tokens = self
+            .output_tokens
+            .max(field(&["completion_tokens", "output_tokens"]));
+        self.cache_read_tokens = self
+            .cache_read_tokens
+            .max(cached.max(field(&["cache_read_input_tokens"])));
+        self.cache_creation_tokens = self
+            .cache_creation_tokens
+            .max(field(&["cache_creation_input_tokens"]));
+    }
+
+    fn absorb_body(&mut self, body: &[u8]) {
+        if let Ok(value) = serde_json::from_slice::<serde_json::Value>(body) {
+            self.absorb(&value);
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn record(&self, tier_name: &str, pricing: Option<&ModelPricing>) {
+        if self.is_empty() {
+            return;
+        }
+        warn!(
+            tier = tier_name,
+            input_tokens = self.input_tokens,
+            output_tokens = self.output_tokens,
+            "Recording usage reported by failed attempt"
+        );
+        record_usage(
+            tier_name,
+            self.input_tokens,
+            self.output_tokens,
+            self.cache_read_tokens,
+            self.cache_creation_tokens,
+        );
+        if let Some(cost) =
+            pricing.and_then(|p| p.estimate_request_cost_usd(self.input_tokens, self.output_tokens))
+        {
+            record_cost(tier_name, cost);
+        }
+    }
 }
 
+/// Peek at the start of a 200 stream for an embedded provider error. On
+/// failure, any usage reported in the peeked frames is recorded for the tier.
 async fn check_stream_for_embedded_error(
+    resp: reqwest::Response,
+    tier_name: &str,
+    first_event_timeout: Duration,
+    pricing: Option<&ModelPricing>,
+    soft_errors: &SoftErrorConfig,
+) -> Result<BoxByteStream, TryRequestError> {
+    let mut partial = PartialUsage::default();
+    let result = peek_stream_first_event(
+        resp,
+        tier_name,
+        first_event_timeout,
+        soft_errors,
+        &mut partial,
+    )
+    .await;
+    if result.is_err() {
+        partial.record(tier_name, pricing);
+    }
+    result
+}
+
+async fn peek_stream_first_event(
     mut resp: reqwest::Response,
     tier_name: &str,
     first_event_timeout: Duration,
+    soft_errors: &SoftErrorConfig,
+    partial: &mut PartialUsage,
 ) -> Result<BoxByteStream, TryRequestError> {
     let deadline = tokio::time::Instant::now() + first_event_timeout;
     let mut buf = Vec::new();
@@ -143,7 +336,9 @@ async fn check_stream_for_embedded_error(
                         continue;
                     }
                     saw_data_frame = true;
-                    if let Some((code, msg)) = embedded_stream_error(data) {
+                    partial.absorb_body(data.as_bytes());
+                    if let Some((code, msg)) = embedded_stream_error(data, soft_errors) {
+                        record_soft_error(tier_name);
                         warn!(
                             tier = tier_name,
                             error_code = code,
@@ -181,7 +376,9 @@ async fn check_stream_for_embedded_error(
     }
 
     let text = String::from_utf8_lossy(&buf);
-    if let Some((code, msg)) = embedded_stream_error(&text) {
+    if let Some((code, msg)) = embedded_stream_error(&text, soft_errors) {
+        partial.absorb_body(text.as_bytes());
+        record_soft_error(tier_name);
         warn!(
             tier = tier_name,
             error_code = code,
@@ -203,13 +400,14 @@ async fn check_stream_for_embedded_error(
 }
 
 /// Check a non-streaming response body for an embedded error in a 200.
-fn check_body_for_embedded_error(body: &[u8], tier_name: &str) -> Result<(), TryRequestError> {
+fn check_body_for_embedded_error(
+    body: &[u8],
+    tier_name: &str,
+    soft_errors: &SoftErrorConfig,
+) -> Result<(), TryRequestError> {
     if let Ok(json) = serde_json::from_slice::<serde_json::Value>(body) {
-        if json.get("error").is_some() {
-            let msg = json["error"]["message"]
-                .as_str()
-                .unwrap_or("Unknown error in response body");
-            let code = json["error"]["code"].as_str().unwrap_or("unknown");
+        if let Some((code, msg)) = soft_error(&json, soft_errors) {
+            record_soft_error(tier_name);
             warn!(
                 tier = tier_name,
                 error_code = code,
@@ -226,6 +424,40 @@ fn check_body_for_embedded_error(body: &[u8], tier_name: &str) -> Result<(), Try
     Ok(())
 }
 
+/// The finish reason of a non-streaming body, when it is in `retry_on` and
+/// the response should fail over to the next tier. Reads OpenAI
+/// `choices[0].finish_reason` or Anthropic `stop_reason`, as the upstream
+/// sent it.
+fn retryable_finish_reason(body: &[u8], tier_name: &str, retry_on: &[String]) -> Option<String> {
+    if retry_on.is_empty() {
+        return None;
+    }
+    let json = serde_json::from_slice::<serde_json::Value>(body).ok()?;
+    let reason = json
+        .pointer("/choices/0/finish_reason")
+        .or_else(|| json.get("stop_reason"))
+        .and_then(|reason| reason.as_str())
+        .filter(|reason| retry_on.iter().any(|r| r == reason))?;
+    debug!(
+        tier = tier_name,
+        finish_reason = reason,
+        "finish reason is in retryOnFinishReason, failing over"
+    );
+    Some(reason.to_string())
+}
+
+/// Hand back a translated non-streaming response, or fail it over as a
+/// `FinishReason` error that still carries it for the cascade to fall back on.
+fn finish_response(
+    response: Response,
+    retry_finish: Option<String>,
+) -> Result<Response, TryRequestError> {
+    match retry_finish {
+        Some(reason) => Err(TryRequestError::FinishReason(reason, response)),
+        None => Ok(response),
+    }
+}
+
 /// Extract rate limit information from upstream response headers.
 pub(super) fn extract_rate_limit_headers(
     resp: &reqwest::Response,
@@ -256,6 +488,95 @@ pub(super) fn extract_rate_limit_headers(
     (remaining, reset_at)
 }
 
+/// A non-success upstream reply. Kept typed so a `terminal` classification
+/// can hand the upstream status back to the client.
+#[derive(Debug)]
+pub(super) struct UpstreamStatusError {
+    pub(super) status: reqwest::StatusCode,
+    pub(super) url: String,
+    pub(super) body: String,
+}
+
+impl std::fmt::Display for UpstreamStatusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Provider returned {} from {}: {}",
+            self.status, self.url, self.body
+        )
+    }
+}
+
+impl std::error::Error for UpstreamStatusError {}
+
+/// Whether `error` is an upstream HTTP error whose status is not in
+/// `Router.retryableStatuses`.
+fn has_non_retryable_status(config: &Config, error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<UpstreamStatusError>()
+        .is_some_and(|upstream| {
+            !config
+                .router()
+                .retryable_statuses
+                .contains(&upstream.status.as_u16())
+        })
+}
+
+/// Apply the provider's `error_classification` to a failed attempt, then
+/// `Router.retryableStatuses` to upstream HTTP errors no rule matched. A 429
+/// already classified by dispatch keeps its rate-limit handling.
+fn classify_failure(
+    config: &Config,
+    provider: &crate::config::Provider,
+    tier_name: &str,
+    error: TryRequestError,
+) -> TryRequestError {
+    let TryRequestError::Other(e) = error else {
+        return error;
+    };
+    // Upstream HTTP errors are matched on status and body; the full error
+    // text would also include the request URL.
+    let class = match e.downcast_ref::<UpstreamStatusError>() {
+        Some(upstream) => provider.classify_status_error(upstream.status.as_u16(), &upstream.body),
+        None => provider.classify_error(&format!("{e:#}")),
+    };
+    if let Some(class) = class {
+        debug!(tier = tier_name, ?class, "error_classification matched");
+    }
+    match class {
+        None if has_non_retryable_status(config, &e) => TryRequestError::Terminal(e),
+        None | Some(ErrorClass::Retryable) => TryRequestError::Other(e),
+        Some(ErrorClass::Terminal) => TryRequestError::Terminal(e),
+        Some(ErrorClass::Auth) => TryRequestError::Auth(e),
+        Some(ErrorClass::RateLimit) => TryRequestError::RateLimited(None),
+    }
+}
+
+/// Send an upstream request, bounded by the provider's `timeout_ms` if set.
+/// A streaming request is bounded only until its response headers arrive, so
+/// a long but healthy stream is not cut off; anything else is bounded end to
+/// end.
+pub(super) async fn send_with_provider_timeout(
+    request: reqwest::RequestBuilder,
+    provider: &crate::config::Provider,
+    streaming: bool,
+) -> anyhow::Result<reqwest::Response> {
+    let Some(timeout) = provider.timeout_ms.map(Duration::from_millis) else {
+        return Ok(request.send().await?);
+    };
+    if !streaming {
+        return Ok(request.timeout(timeout).send().await?);
+    }
+    match tokio::time::timeout(timeout, request.send()).await {
+        Ok(sent) => Ok(sent?),
+        Err(_) => anyhow::bail!(
+            "{} did not send response headers within {}ms",
+            provider.name,
+            timeout.as_millis()
+        ),
+    }
+}
+
 pub(super) struct TryRequestArgs<'a> {
     pub(super) config: &'a Config,
     pub(super) registry: &'a TransformerRegistry,
@@ -265,12 +586,65 @@ pub(super) struct TryRequestArgs<'a> {
     pub(super) local_estimate: u64,
     pub(super) stream_first_event_timeout: Duration,
     pub(super) stream_idle_timeout: Duration,
+    /// SSE channel capacity for a streamed response from this tier.
+    pub(super) sse_buffer_size: usize,
     pub(super) ratelimit_tracker: Arc<RateLimitTracker>,
     pub(super) debug_capture: Option<Arc<DebugCapture>>,
+    /// ID from `x-ccr-request-id`, stored with debug captures.
+    pub(super) request_id: Option<&'a str>,
+    /// The client's `anthropic-beta` header, if it sent one.
+    pub(super) anthropic_beta: Option<&'a str>,
     /// Original OpenAI request body for passthrough to OpenAI-compatible backends.
     pub(super) openai_passthrough_body: Option<&'a serde_json::Value>,
 }
 
+/// Apply `Router.faultInjection` for `tier_name` before an attempt is sent.
+/// Synthetic failures are returned as the errors a real 429 or 503 produces,
+/// so the caller's backoff and failover handle them unchanged. Only compiled
+/// into debug builds, so a release binary cannot inject faults.
+#[cfg(debug_assertions)]
+async fn inject_configured_fault(
+    config: &Config,
+    provider: &crate::config::Provider,
+    tier_name: &str,
+) -> Result<(), TryRequestError> {
+    let Some(faults) = config.fault_injection(tier_name) else {
+        return Ok(());
+    };
+    if faults.delay_ms > 0 {
+        tokio::time::sleep(Duration::from_millis(faults.delay_ms)).await;
+    }
+    let fault = faults.roll(&mut rand::thread_rng());
+    match fault {
+        None => Ok(()),
+        Some(crate::config::InjectedFault::RateLimited) => {
+            warn!(tier = tier_name, "Injecting synthetic 429");
+            Err(TryRequestError::RateLimited(None))
+        }
+        Some(crate::config::InjectedFault::ServiceUnavailable) => {
+            warn!(tier = tier_name, "Injecting synthetic 503");
+            Err(TryRequestError::Other(
+                UpstreamStatusError {
+                    status: reqwest::StatusCode::SERVICE_UNAVAILABLE,
+                    url: provider.api_base_url.clone(),
+                    body: "injected fault".to_string(),
+                }
+                .into(),
+            ))
+        }
+    }
+}
+
+#[tracing::instrument(
+    name = "ccr.try_request",
+    skip_all,
+    fields(
+        tier = %args.tier_name,
+        provider = tracing::field::Empty,
+        model = tracing::field::Empty,
+        protocol = tracing::field::Empty,
+    )
+)]
 pub(super) async fn try_request(args: TryRequestArgs<'_>) -> Result<Response, TryRequestError> {
     let TryRequestArgs {
         config,
@@ -281,19 +655,42 @@ pub(super) async fn try_request(args: TryRequestArgs<'_>) -> Result<Response, Tr
         local_estimate,
         stream_first_event_timeout,
         stream_idle_timeout,
+        sse_buffer_size,
         ratelimit_tracker,
         debug_capture,
+        request_id,
+        anthropic_beta,
         openai_passthrough_body,
     } = args;
     let provider = config.resolve_provider(tier).ok_or_else(|| {
         TryRequestError::Other(anyhow::anyhow!("Provider not found for tier: {}", tier))
     })?;
 
+    #[cfg(debug_assertions)]
+    inject_configured_fault(config, provider, tier_name).await?;
+
     // Build transformer chain from provider config
     let chain = build_transformer_chain(registry, provider, tier.split(',').nth(1).unwrap_or(tier));
 
     // Extract the actual model name from the tier (format: "provider,model")
     let model_name = tier.split(',').nth(1).unwrap_or(tier);
+    let upstream_model = provider.upstream_model(model_name).ok_or_else(|| {
+        TryRequestError::Terminal(anyhow::anyhow!(
+            "Model '{}' is not served by provider '{}'",
+            model_name,
+            provider.name
+        ))
+    })?;
+
+    let route_headers = config
+        .router()
+        .route_headers
+        .then(|| chain.names().join(","));
+
+    let span = tracing::Span::current();
+    span.record("provider", provider.name.as_str());
+    span.record("model", model_name);
+    span.record("protocol", tracing::field::debug(&provider.protocol));
 
     // Apply request transformers if chain is not empty
     let transformed_request = if chain.is_empty() {
@@ -308,64 +705,231 @@ pub(super) async fn try_request(args: TryRequestArgs<'_>) -> Result<Response, Tr
 
     // Only use passthrough when the chain has no transformers (transformers may
     // modify the Anthropic-shaped payload in ways we need to honour).
-    let effective_passthrough = if chain.is_empty() {
+    let mut effective_passthrough = if chain.is_empty() {
         openai_passthrough_body.cloned()
     } else {
         None
     };
 
+    let mut transformed_request = transformed_request;
+    let mut warnings = RequestWarnings::default();
+    if let Some((requested, cap)) = clamp_max_tokens(
+        config,
+        provider,
+        model_name,
+        &mut transformed_request,
+        effective_passthrough.as_mut(),
+    ) {
+        info!(
+            tier = tier_name,
+            model = model_name,
+            requested,
+            cap,
+            "Clamped max_tokens to the model's max_output_tokens"
+        );
+        warnings.push(format!(
+            "clamped max_tokens from {requested} to {cap} (max output of {model_name})"
+        ));
+    }
+
+    if let Some((requested, max)) = apply_model_sampling(
+        provider,
+        model_name,
+        &mut transformed_request,
+        effective_passthrough.as_mut(),
+    ) {
+        info!(
+            tier = tier_name,
+            model = model_name,
+            requested,
+            max,
+            "Clamped temperature to the model's max_temperature"
+        );
+        warnings.push(format!(
+            "clamped temperature from {requested} to {max} (max temperature of {model_name})"
+        ));
+    }
+
     match provider.protocol {
-        ProviderProtocol::Openai => {
+        ProviderProtocol::Openai | ProviderProtocol::OpenaiResponses => {
+            if effective_passthrough.is_none() {
+                let requested = transformed_request
+                    .get("max_tokens")
+                    .and_then(|v| v.as_u64())
+                    .and_then(|v| u32::try_from(v).ok());
+                if let Some(floor) = raised_reasoning_budget(
+                    model_name,
+                    requested,
+                    provider.min_completion_tokens_for_reasoning,
+                ) {
+                    warnings.push(match requested {
+                        Some(requested) => format!(
+                            "raised max_tokens from {requested} to {floor} (reasoning floor for {tier_name})"
+                        ),
+                        None => format!(
+                            "set max_tokens to {floor} (reasoning floor for {tier_name})"
+                        ),
+                    });
+                }
+            }
             try_request_via_openai_protocol(
                 config,
                 provider,
                 TryRequestProtocolArgs {
                     transformed_request,
                     model_name,
+                    upstream_model,
                     tier_name,
                     local_estimate,
                     stream_first_event_timeout,
                     stream_idle_timeout,
+                    sse_buffer_size,
                     ratelimit_tracker,
                     chain,
                     debug_capture,
+                    request_id,
+                    anthropic_beta,
                     openai_passthrough_body: effective_passthrough,
                 },
             )
             .await
+            .map(|mut response| {
+                if let Some(transformers) = &route_headers {
+                    insert_route_headers(&mut response, transformers, upstream_model);
+                }
+                warnings.stash_on(response)
+            })
+            .map_err(|error| classify_failure(config, provider, tier_name, error))
         }
         ProviderProtocol::Anthropic => {
+            if let Some(fields) = transformed_request.as_object_mut() {
+                for field in ["modalities", "audio"] {
+                    if fields.remove(field).is_some() {
+                        warn!(
+                            tier = tier_name,
+                            field, "dropping audio output field for Anthropic-protocol provider"
+                        );
+                        warnings.push(format!(
+                            "dropped {field} (audio output not supported by {tier_name})"
+                        ));
+                    }
+                }
+            }
             try_request_via_anthropic_protocol(
                 config,
                 provider,
                 TryRequestProtocolArgs {
                     transformed_request,
                     model_name,
+                    upstream_model,
                     tier_name,
                     local_estimate,
                     stream_first_event_timeout,
                     stream_idle_timeout,
+                    sse_buffer_size,
                     ratelimit_tracker,
                     chain,
                     debug_capture,
+                    request_id,
+                    anthropic_beta,
                     openai_passthrough_body: None,
                 },
             )
             .await
+            .map(|mut response| {
+                if let Some(transformers) = &route_headers {
+                    insert_route_headers(&mut response, transformers, upstream_model);
+                }
+                warnings.stash_on(response)
+            })
+            .map_err(|error| classify_failure(config, provider, tier_name, error))
+        }
+    }
+}
+
+/// With `max_tokens_cap` on for `provider`, lower `max_tokens` (and
+/// `max_completion_tokens` in an OpenAI passthrough body) to the model's
+/// `max_output_tokens`. Returns the requested and capped values when a
+/// request asked for more.
+fn clamp_max_tokens(
+    config: &Config,
+    provider: &crate::config::Provider,
+    model_name: &str,
+    request: &mut serde_json::Value,
+    passthrough: Option<&mut serde_json::Value>,
+) -> Option<(u64, u64)> {
+    if !config.max_tokens_cap(provider) {
+        return None;
+    }
+    let cap = provider.metadata_for_model(model_name)?.max_output_tokens?;
+    let mut clamped = None;
+    for body in std::iter::once(request).chain(passthrough) {
+        for field in ["max_tokens", "max_completion_tokens"] {
+            let Some(value) = body.get_mut(field) else {
+                continue;
+            };
+            if let Some(requested) = value.as_u64().filter(|&requested| requested > cap) {
+                *value = cap.into();
+                clamped = Some((requested, cap));
+            }
         }
     }
+    clamped
+}
+
+/// Apply the model's `model_sampling`: fill `temperature` and `top_p` the
+/// client left out, and lower a `temperature` above `max_temperature`.
+/// Returns the requested and clamped temperature when it was lowered.
+fn apply_model_sampling(
+    provider: &crate::config::Provider,
+    model_name: &str,
+    request: &mut serde_json::Value,
+    passthrough: Option<&mut serde_json::Value>,
+) -> Option<(f64, f64)> {
+    let sampling = provider.sampling_for_model(model_name)?;
+    let mut clamped = None;
+    for body in std::iter::once(request).chain(passthrough) {
+        let Some(fields) = body.as_object_mut() else {
+            continue;
+        };
+        for (field, default) in [
+            ("temperature", sampling.default_temperature),
+            ("top_p", sampling.default_top_p),
+        ] {
+            if let Some(default) = default {
+                if fields.get(field).is_none_or(|value| value.is_null()) {
+                    fields.insert(field.to_string(), default.into());
+                }
+            }
+        }
+        let (Some(max), Some(value)) = (sampling.max_temperature, fields.get_mut("temperature"))
+        else {
+            continue;
+        };
+        if let Some(requested) = value.as_f64().filter(|&requested| requested > max) {
+            *value = max.into();
+            clamped = Some((requested, max));
+        }
+    }
+    clamped
 }
 
 pub(super) struct TryRequestProtocolArgs<'a> {
     pub(super) transformed_request: serde_json::Value,
     pub(super) model_name: &'a str,
+    /// Model name sent upstream: `model_name` after `model_aliases`.
+    pub(super) upstream_model: &'a str,
     pub(super) tier_name: &'a str,
     pub(super) local_estimate: u64,
     pub(super) stream_first_event_timeout: Duration,
     pub(super) stream_idle_timeout: Duration,
+    pub(super) sse_buffer_size: usize,
     pub(super) ratelimit_tracker: Arc<RateLimitTracker>,
     pub(super) chain: TransformerChain,
     pub(super) debug_capture: Option<Arc<DebugCapture>>,
+    pub(super) request_id: Option<&'a str>,
+    /// The client's `anthropic-beta` header, if it sent one.
+    pub(super) anthropic_beta: Option<&'a str>,
     /// Original OpenAI body for direct passthrough (skips Anthropic round-trip).
     pub(super) openai_passthrough_body: Option<serde_json::Value>,
 }
@@ -382,8 +946,21 @@ pub(super) fn provider_endpoint_url(provider: &crate::config::Provider, endpoint
     }
 }
 
-pub(super) fn provider_openai_chat_completions_url(provider: &crate::config::Provider) -> String {
-    provider_endpoint_url(provider, "chat/completions")
+/// Chat completions URL for an OpenAI-protocol provider. Azure addresses
+/// the model as a deployment and versions the API in the query string.
+pub(super) fn provider_openai_chat_completions_url(
+    provider: &crate::config::Provider,
+    model_name: &str,
+) -> String {
+    let Some(azure) = provider.azure.as_ref() else {
+        return provider_endpoint_url(provider, "chat/completions");
+    };
+    let base = provider.api_base_url.trim_end_matches('/');
+    let base = base.strip_suffix("/openai").unwrap_or(base);
+    format!(
+        "{}/openai/deployments/{}/chat/completions?api-version={}",
+        base, model_name, azure.api_version
+    )
 }
 
 pub(super) fn provider_anthropic_messages_url(provider: &crate::config::Provider) -> String {
@@ -403,17 +980,66 @@ pub(super) fn insert_ccr_tier_header(response: &mut Response, tier_name: &str) {
     );
 }
 
+/// Insert `x-ccr-transformers` (the chain's names, comma-separated) and
+/// `x-ccr-model-resolved` (the model sent upstream) on a successful response.
+fn insert_route_headers(response: &mut Response, transformers: &str, upstream_model: &str) {
+    if !response.status().is_success() {
+        return;
+    }
+    let headers = response.headers_mut();
+    if let Ok(value) = transformers.parse() {
+        headers.insert("x-ccr-transformers", value);
+    }
+    if let Ok(value) = upstream_model.parse() {
+        headers.insert("x-ccr-model-resolved", value);
+    }
+}
+
+/// Insert configured headers, skipping names or values that are not valid
+/// HTTP. Later merges override earlier ones.
+fn merge_headers(
+    headers: &mut reqwest::header::HeaderMap,
+    extra: &std::collections::HashMap<String, String>,
+) {
+    for (key, value) in extra {
+        if let (Ok(name), Ok(val)) = (
+            reqwest::header::HeaderName::from_bytes(key.as_bytes()),
+            value.parse::<reqwest::header::HeaderValue>(),
+        ) {
+            headers.insert(name, val);
+        }
+    }
+}
+
+/// Merge `Router.tierHeaders` for `provider,model` on top of the provider
+/// headers, e.g. a billing project that differs per model.
+pub(super) fn merge_tier_headers(
+    headers: &mut reqwest::header::HeaderMap,
+    config: &Config,
+    provider: &crate::config::Provider,
+    model_name: &str,
+) {
+    if let Some(extra) = config.tier_headers(&provider.name, model_name) {
+        merge_headers(headers, extra);
+    }
+}
+
 pub(super) fn build_openai_headers(
     provider: &crate::config::Provider,
 ) -> Result<reqwest::header::HeaderMap, TryRequestError> {
     let mut headers = reqwest::header::HeaderMap::new();
+    let (auth_name, auth_value) = if provider.azure.is_some() {
+        ("api-key", provider.api_key.clone())
+    } else {
+        ("Authorization", format!("Bearer {}", provider.api_key))
+    };
     headers.insert(
-        "Authorization",
-        format!("Bearer {}", provider.api_key).parse().map_err(
-            |e: reqwest::header::InvalidHeaderValue| {
+        auth_name,
+        auth_value
+            .parse()
+            .map_err(|e: reqwest::header::InvalidHeaderValue| {
                 TryRequestError::Other(anyhow::anyhow!("{}", e))
-            },
-        )?,
+            })?,
     );
     headers.insert(
         "Content-Type",
@@ -447,21 +1073,39 @@ pub(super) fn build_openai_headers(
         );
     }
 
+    if let Some(cache) = &provider.kimi_context_cache {
+        insert_kimi_context_cache_headers(&mut headers, cache)?;
+    }
+
     // Merge provider-level extra headers (e.g., User-Agent for Kimi).
     if let Some(ref extra) = provider.extra_headers {
-        for (key, value) in extra {
-            if let (Ok(name), Ok(val)) = (
-                reqwest::header::HeaderName::from_bytes(key.as_bytes()),
-                value.parse::<reqwest::header::HeaderValue>(),
-            ) {
-                headers.insert(name, val);
-            }
-        }
+        merge_headers(&mut headers, extra);
     }
 
     Ok(headers)
 }
 
+/// Moonshot/Kimi context cache headers: the cache to read the prompt prefix
+/// from, and optionally a new lifetime for it.
+fn insert_kimi_context_cache_headers(
+    headers: &mut reqwest::header::HeaderMap,
+    cache: &crate::config::KimiContextCacheConfig,
+) -> Result<(), TryRequestError> {
+    headers.insert(
+        "X-Msh-Context-Cache",
+        cache
+            .cache_id
+            .parse()
+            .map_err(|e: reqwest::header::InvalidHeaderValue| {
+                TryRequestError::Other(anyhow::anyhow!("{}", e))
+            })?,
+    );
+    if let Some(ttl) = cache.reset_ttl_secs {
+        headers.insert("X-Msh-Context-Cache-Reset-TTL", ttl.into());
+    }
+    Ok(())
+}
+
 pub(super) fn build_anthropic_headers(
     provider: &crate::config::Provider,
 ) -> Result<reqwest::header::HeaderMap, TryRequestError> {
@@ -521,22 +1165,55 @@ pub(super) fn build_anthropic_headers(
                 TryRequestError::Other(anyhow::anyhow!("{}", e))
             })?,
     );
+    if !provider.anthropic_beta.is_empty() {
+        headers.insert(
+            "anthropic-beta",
+            provider.anthropic_beta.join(",").parse().map_err(
+                |e: reqwest::header::InvalidHeaderValue| {
+                    TryRequestError::Other(anyhow::anyhow!("{}", e))
+                },
+            )?,
+        );
+    }
 
     // Merge provider-level extra headers (e.g., User-Agent for Kimi).
     if let Some(ref extra) = provider.extra_headers {
-        for (key, value) in extra {
-            if let (Ok(name), Ok(val)) = (
-                reqwest::header::HeaderName::from_bytes(key.as_bytes()),
-                value.parse::<reqwest::header::HeaderValue>(),
-            ) {
-                headers.insert(name, val);
-            }
-        }
+        merge_headers(&mut headers, extra);
     }
 
     Ok(headers)
 }
 
+/// Add the features in a client's `anthropic-beta` value that `headers`
+/// does not already request.
+fn merge_anthropic_beta(headers: &mut reqwest::header::HeaderMap, client_beta: &str) {
+    let mut features: Vec<&str> = headers
+        .get("anthropic-beta")
+        .and_then(|value| value.to_str().ok())
+        .into_iter()
+        .chain(std::iter::once(client_beta))
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|feature| !feature.is_empty())
+        .collect();
+    let mut seen = std::collections::HashSet::new();
+    features.retain(|feature| seen.insert(*feature));
+    if let Ok(value) = features.join(",").parse() {
+        headers.insert("anthropic-beta", value);
+    }
+}
+
+/// Add each of `fields` that `body` omits as an explicit `null`.
+/// Serialization drops unset optional fields, which some providers reject.
+fn insert_explicit_nulls(body: &mut serde_json::Value, fields: &[String]) {
+    let Some(obj) = body.as_object_mut() else {
+        return;
+    };
+    for field in fields {
+        obj.entry(field.as_str()).or_insert(serde_json::Value::Null);
+    }
+}
+
 pub(super) async fn try_request_via_openai_protocol(
     config: &Config,
     provider: &crate::config::Provider,
@@ -545,35 +1222,50 @@ pub(super) async fn try_request_via_openai_protocol(
     let TryRequestProtocolArgs {
         transformed_request,
         model_name,
+        upstream_model,
         tier_name,
         local_estimate,
         stream_first_event_timeout,
         stream_idle_timeout,
+        sse_buffer_size,
         ratelimit_tracker,
         chain,
         debug_capture,
+        request_id,
+        anthropic_beta: _, // only sent to Anthropic-protocol providers
         openai_passthrough_body,
     } = args;
 
-    let url = provider_openai_chat_completions_url(provider);
-    let headers = build_openai_headers(provider)?;
+    let url = provider_openai_chat_completions_url(provider, upstream_model);
+    let mut headers = build_openai_headers(provider)?;
+    merge_tier_headers(&mut headers, config, provider, model_name);
 
     // Fast path: when the inbound request was already OpenAI-formatted (Codex
     // frontend) and no transformers need to modify it, reuse the original body
     // directly with only a model-name swap.  This eliminates the wasteful
     // OpenAI → Anthropic → deserialize → translate → OpenAI round-trip.
-    let (openai_request_value, stream_flag) = if let Some(mut body) = openai_passthrough_body {
+    let (mut openai_request_value, stream_flag) = if let Some(mut body) = openai_passthrough_body {
         // Swap model name to the backend's expected value.
         if let Some(obj) = body.as_object_mut() {
             obj.insert(
                 "model".to_string(),
-                serde_json::Value::String(model_name.to_string()),
+                serde_json::Value::String(upstream_model.to_string()),
             );
         }
         let stream = body
             .get("stream")
             .and_then(|v| v.as_bool())
             .unwrap_or(false);
+        // Ask for the final usage chunk unless the client chose its own
+        // stream_options or the provider opted out, matching the translated
+        // path.
+        if let Some(obj) = body
+            .as_object_mut()
+            .filter(|_| stream && provider.stream_usage)
+        {
+            obj.entry("stream_options")
+                .or_insert_with(|| serde_json::json!({"include_usage": true}));
+        }
         trace!(tier = tier_name, model = model_name, url = %url, "OpenAI passthrough: sending original body directly");
         (body, stream)
     } else {
@@ -585,18 +1277,27 @@ pub(super) async fn try_request_via_openai_protocol(
             .map_err(|e| TryRequestError::Other(e.into()))?;
 
         // Translate Anthropic request to OpenAI format.
-        let openai_request = translate_request_anthropic_to_openai(&request, model_name);
+        let mut openai_request = translate_request_anthropic_to_openai(
+            &request,
+            upstream_model,
+            provider.min_completion_tokens_for_reasoning,
+        );
+        if !provider.stream_usage {
+            openai_request.stream_options = None;
+        }
         let stream = request.stream.unwrap_or(false);
         let value =
             serde_json::to_value(&openai_request).map_err(|e| TryRequestError::Other(e.into()))?;
         (value, stream)
     };
+    insert_explicit_nulls(&mut openai_request_value, &provider.explicit_null_fields);
 
     // Set up capture if enabled for this provider
     let capture_builder = if let Some(ref capture) = debug_capture {
         if capture.should_capture(&provider.name) {
             let mut builder = capture
                 .builder(&provider.name, tier_name)
+                .request_id(request_id)
                 .model(model_name)
                 .url(&url)
                 .request_body(openai_request_value.clone())
@@ -612,13 +1313,16 @@ pub(super) async fn try_request_via_openai_protocol(
         None
     };
 
-    let resp = config
-        .http_client()
+    let dispatched_at = std::time::Instant::now();
+    let upstream_request = config
+        .http_client_for(provider)
         .post(&url)
         .headers(headers)
-        .json(&openai_request_value)
-        .send()
-        .await;
+        .json(&openai_request_value);
+    let resp = send_with_provider_timeout(upstream_request, provider, stream_flag).await;
+    if let Ok(resp) = &resp {
+        crate::connection_stats::record_connection(&provider.name, resp);
+    }
 
     // Handle connection errors with capture
     let resp = match resp {
@@ -631,7 +1335,7 @@ pub(super) async fn try_request_via_openai_protocol(
                     warn!("Failed to record debug capture: {}", capture_err);
                 }
             }
-            return Err(TryRequestError::Other(e.into()));
+            return Err(TryRequestError::Other(e));
         }
     };
 
@@ -673,7 +1377,11 @@ pub(super) async fn try_request_via_openai_protocol(
         )
         .await;
 
-        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            && provider
+                .classify_status_error(status.as_u16(), &body)
+                .is_none_or(|class| class == ErrorClass::RateLimit)
+        {
             record_rate_limit_hit(tier_name);
             ratelimit_tracker.record_429(tier_name, retry_after);
             record_rate_limit_backoff(tier_name);
@@ -681,12 +1389,9 @@ pub(super) async fn try_request_via_openai_protocol(
             return Err(TryRequestError::RateLimited(retry_after));
         }
 
-        return Err(TryRequestError::Other(anyhow::anyhow!(
-            "Provider returned {} from {}: {}",
-            status,
-            url,
-            body
-        )));
+        return Err(TryRequestError::Other(
+            UpstreamStatusError { status, url, body }.into(),
+        ));
     }
 
     // Handle streaming vs non-streaming.
@@ -704,6 +1409,8 @@ pub(super) async fn try_request_via_openai_protocol(
             resp,
             tier_name,
             stream_first_event_timeout,
+            provider.pricing_for_model(model_name),
+            &provider.soft_errors,
         )
         .await
         {
@@ -728,17 +1435,26 @@ pub(super) async fn try_request_via_openai_protocol(
             ratelimit_tracker: Some(ratelimit_tracker.clone()),
             rate_limit_info: Some(rate_limit_info),
             stream_start: std::time::Instant::now(),
+            dispatched_at,
             stream_idle_timeout,
+            heartbeat_interval: config.sse_heartbeat_interval(),
             pricing: provider.pricing_for_model(model_name).copied(),
+            max_output_tokens: if chain.enforces_stream_max_tokens() {
+                transformed_request
+                    .get("max_tokens")
+                    .and_then(|v| v.as_u64())
+            } else {
+                None
+            },
+            reasoning_field_aliases: provider.reasoning_field_aliases.clone(),
+            content_field_aliases: provider.content_field_aliases.clone(),
+            emit_reasoning: emits_reasoning(config, provider, model_name),
+            usage_interval_tokens: config.stream_usage_interval_tokens(),
         };
-        Ok(stream_response_translated(
-            byte_stream,
-            config.sse_buffer_size(),
-            Some(ctx),
-            model_name,
-            chain,
+        Ok(
+            stream_response_translated(byte_stream, sse_buffer_size, Some(ctx), model_name, chain)
+                .await,
         )
-        .await)
     } else {
         // Extract rate limit headers for non-streaming.
         let rate_limit_info = extract_rate_limit_headers(&resp);
@@ -756,7 +1472,10 @@ pub(super) async fn try_request_via_openai_protocol(
 
         // Check for embedded error in 200 body BEFORE recording success,
         // otherwise a failed request corrupts tier rate-limit state.
-        if let Err(error) = check_body_for_embedded_error(&body, tier_name) {
+        if let Err(error) = check_body_for_embedded_error(&body, tier_name, &provider.soft_errors) {
+            let mut partial = PartialUsage::default();
+            partial.absorb_body(&body);
+            partial.record(tier_name, provider.pricing_for_model(model_name));
             let body_str = String::from_utf8_lossy(&body);
             persist_debug_capture(
                 debug_capture.as_ref(),
@@ -771,29 +1490,36 @@ pub(super) async fn try_request_via_openai_protocol(
         }
 
         ratelimit_tracker.record_success(tier_name, rate_limit_info.0, rate_limit_info.1);
+        // A filtered answer is still translated, and its usage recorded, so
+        // the cascade can return it if no other tier does better.
+        let retry_finish =
+            retryable_finish_reason(&body, tier_name, config.retry_on_finish_reason(tier_name));
 
         let body_str = String::from_utf8_lossy(&body);
 
         // Record capture for non-streaming response
-        if let (Some(builder), Some(capture)) = (capture_builder, debug_capture.clone()) {
-            let interaction = builder.complete(resp_status, &body_str, captured_headers, None);
-            if let Err(capture_err) = capture.record(interaction).await {
-                warn!("Failed to record debug capture: {}", capture_err);
-            }
-        }
+        let pending_capture = capture_success(
+            debug_capture.as_ref(),
+            capture_builder,
+            resp_status,
+            &body_str,
+            captured_headers,
+        )
+        .await;
 
         // Try to parse as OpenAI response and translate.
-        if let Ok(openai_resp) = serde_json::from_slice::<OpenAIResponse>(&body) {
+        if let Ok(mut openai_resp) = serde_json::from_slice::<OpenAIResponse>(&body) {
             // Record usage from the response.
             if let Some(ref usage) = openai_resp.usage {
                 record_usage(
                     tier_name,
                     usage.prompt_tokens,
                     usage.completion_tokens,
-                    0, // OpenAI doesn't have cache fields in the same way
+                    usage.cached_tokens(),
                     0,
                 );
-                verify_token_usage(tier_name, local_estimate, usage.prompt_tokens);
+                let drift = verify_token_usage(tier_name, local_estimate, usage.prompt_tokens);
+                persist_drift_capture(pending_capture, drift).await;
                 if let Some(cost) = provider.pricing_for_model(model_name).and_then(|p| {
                     p.estimate_request_cost_usd(usage.prompt_tokens, usage.completion_tokens)
                 }) {
@@ -801,6 +1527,17 @@ pub(super) async fn try_request_via_openai_protocol(
                 }
             }
 
+            let role = openai_resp.choices.first().map(|c| c.message.role.as_str());
+            if is_nonstandard_role(role) {
+                record_nonstandard_role(tier_name);
+            }
+
+            if !emits_reasoning(config, provider, model_name) {
+                for choice in &mut openai_resp.choices {
+                    choice.message.reasoning_content = None;
+                }
+            }
+
             // Translate to Anthropic format.
             let anthropic_resp = translate_response_openai_to_anthropic(openai_resp, model_name);
 
@@ -821,13 +1558,13 @@ pub(super) async fn try_request_via_openai_protocol(
 
             let mut response = (StatusCode::OK, response_body).into_response();
             insert_ccr_tier_header(&mut response, tier_name);
-            return Ok(response);
+            return finish_response(response, retry_finish);
         }
 
         // Fallback: pass through original response if translation fails.
         let mut response = (StatusCode::OK, body).into_response();
         insert_ccr_tier_header(&mut response, tier_name);
-        Ok(response)
+        finish_response(response, retry_finish)
     }
 }
 
@@ -837,49 +1574,47 @@ pub(super) async fn try_request_via_anthropic_protocol(
     args: TryRequestProtocolArgs<'_>,
 ) -> Result<Response, TryRequestError> {
     let TryRequestProtocolArgs {
-        transformed_request,
+        mut transformed_request,
         model_name,
+        upstream_model,
         tier_name,
         local_estimate,
         stream_first_event_timeout,
         stream_idle_timeout,
+        sse_buffer_size,
         ratelimit_tracker,
         chain,
         debug_capture,
+        request_id,
+        anthropic_beta,
         openai_passthrough_body: _, // not used for Anthropic protocol
     } = args;
 
     let url = provider_anthropic_messages_url(provider);
-    let headers = build_anthropic_headers(provider)?;
+    let mut headers = build_anthropic_headers(provider)?;
+    if let Some(client_beta) = anthropic_beta.filter(|_| provider.forward_anthropic_beta) {
+        merge_anthropic_beta(&mut headers, client_beta);
+    }
+    merge_tier_headers(&mut headers, config, provider, model_name);
 
     trace!(tier = tier_name, model = model_name, url = %url, "dispatching Anthropic-compatible upstream request");
 
-    let request: AnthropicRequest = serde_json::from_value(transformed_request.clone())
-        .map_err(|e| TryRequestError::Other(e.into()))?;
+    // Anthropic rejects `system` inside `messages`; OpenAI clients put it there.
+    hoist_system_messages(
+        &mut transformed_request,
+        config.router().system_message_mode,
+    );
 
-    let needs_normalization = request
-        .messages
-        .iter()
-        .any(|message| message.role == "tool");
-
-    // Only canonicalize when OpenAI-style tool result messages are present.
-    // Native Anthropic payloads should skip this round-trip to preserve
-    // provider-specific content blocks (e.g., cache_control, thinking blocks).
-    let mut normalized_request_value = if needs_normalization {
-        let openai_request = translate_request_anthropic_to_openai(&request, model_name);
-        let openai_request_value =
-            serde_json::to_value(openai_request).map_err(|e| TryRequestError::Other(e.into()))?;
-        OpenAiToAnthropicTransformer
-            .transform_request(openai_request_value)
-            .map_err(TryRequestError::Other)?
-    } else {
-        transformed_request
-    };
+    // OpenAI-style tool results become `tool_result` blocks. Everything else
+    // is sent as the client wrote it, keeping provider-specific block fields
+    // (e.g., cache_control, thinking blocks).
+    let mut normalized_request_value = transformed_request;
+    tool_messages_to_tool_results(&mut normalized_request_value);
 
     if let Some(obj) = normalized_request_value.as_object_mut() {
         obj.insert(
             "model".to_string(),
-            serde_json::Value::String(model_name.to_string()),
+            serde_json::Value::String(upstream_model.to_string()),
         );
     }
 
@@ -891,6 +1626,7 @@ pub(super) async fn try_request_via_anthropic_protocol(
         if capture.should_capture(&provider.name) {
             let mut builder = capture
                 .builder(&provider.name, tier_name)
+                .request_id(request_id)
                 .model(model_name)
                 .url(&url)
                 .request_body(normalized_request_value)
@@ -906,13 +1642,18 @@ pub(super) async fn try_request_via_anthropic_protocol(
         None
     };
 
-    let resp = config
-        .http_client()
+    let dispatched_at = std::time::Instant::now();
+    let upstream_request = config
+        .http_client_for(provider)
         .post(&url)
         .headers(headers)
-        .json(&request)
-        .send()
-        .await;
+        .json(&request);
+    let resp =
+        send_with_provider_timeout(upstream_request, provider, request.stream.unwrap_or(false))
+            .await;
+    if let Ok(resp) = &resp {
+        crate::connection_stats::record_connection(&provider.name, resp);
+    }
 
     // Handle connection errors with capture
     let resp = match resp {
@@ -925,7 +1666,7 @@ pub(super) async fn try_request_via_anthropic_protocol(
                     warn!("Failed to record debug capture: {}", capture_err);
                 }
             }
-            return Err(TryRequestError::Other(e.into()));
+            return Err(TryRequestError::Other(e));
         }
     };
 
@@ -968,7 +1709,11 @@ pub(super) async fn try_request_via_anthropic_protocol(
         .await;
 
         // For 429 rate limit, pass through to let coordinator/client handle routing
-        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            && provider
+                .classify_status_error(status.as_u16(), &body)
+                .is_none_or(|class| class == ErrorClass::RateLimit)
+        {
             record_rate_limit_hit(tier_name);
             ratelimit_tracker.record_429(tier_name, retry_after);
             record_rate_limit_backoff(tier_name);
@@ -976,12 +1721,9 @@ pub(super) async fn try_request_via_anthropic_protocol(
             return Err(TryRequestError::RateLimited(retry_after));
         }
 
-        return Err(TryRequestError::Other(anyhow::anyhow!(
-            "Provider returned {} from {}: {}",
-            status,
-            url,
-            body
-        )));
+        return Err(TryRequestError::Other(
+            UpstreamStatusError { status, url, body }.into(),
+        ));
     }
 
     if request.stream.unwrap_or(false) {
@@ -998,6 +1740,8 @@ pub(super) async fn try_request_via_anthropic_protocol(
             resp,
             tier_name,
             stream_first_event_timeout,
+            provider.pricing_for_model(model_name),
+            &provider.soft_errors,
         )
         .await
         {
@@ -1022,17 +1766,19 @@ pub(super) async fn try_request_via_anthropic_protocol(
             ratelimit_tracker: Some(ratelimit_tracker.clone()),
             rate_limit_info: Some(rate_limit_info),
             stream_start: std::time::Instant::now(),
+            dispatched_at,
             stream_idle_timeout,
+            heartbeat_interval: config.sse_heartbeat_interval(),
             pricing: provider.pricing_for_model(model_name).copied(),
+            max_output_tokens: None,
+            reasoning_field_aliases: Vec::new(),
+            content_field_aliases: Vec::new(),
+            emit_reasoning: true,
+            usage_interval_tokens: None,
         };
 
-        let mut response = stream_anthropic_response_with_tracking(
-            byte_stream,
-            config.sse_buffer_size(),
-            ctx,
-            chain,
-        )
-        .await;
+        let mut response =
+            stream_anthropic_response_with_tracking(byte_stream, sse_buffer_size, ctx, chain).await;
         insert_ccr_tier_header(&mut response, tier_name);
         Ok(response)
     } else {
@@ -1050,7 +1796,10 @@ pub(super) async fn try_request_via_anthropic_protocol(
             .map_err(|e| TryRequestError::Other(e.into()))?;
 
         // Check for embedded error before recording success.
-        if let Err(error) = check_body_for_embedded_error(&body, tier_name) {
+        if let Err(error) = check_body_for_embedded_error(&body, tier_name, &provider.soft_errors) {
+            let mut partial = PartialUsage::default();
+            partial.absorb_body(&body);
+            partial.record(tier_name, provider.pricing_for_model(model_name));
             let body_str = String::from_utf8_lossy(&body);
             persist_debug_capture(
                 debug_capture.as_ref(),
@@ -1065,16 +1814,22 @@ pub(super) async fn try_request_via_anthropic_protocol(
         }
 
         ratelimit_tracker.record_success(tier_name, rate_limit_info.0, rate_limit_info.1);
+        // A filtered answer is still translated, and its usage recorded, so
+        // the cascade can return it if no other tier does better.
+        let retry_finish =
+            retryable_finish_reason(&body, tier_name, config.retry_on_finish_reason(tier_name));
 
         let body_str = String::from_utf8_lossy(&body);
 
         // Record capture for non-streaming Anthropic response
-        if let (Some(builder), Some(capture)) = (capture_builder, debug_capture.clone()) {
-            let interaction = builder.complete(resp_status, &body_str, captured_headers, None);
-            if let Err(capture_err) = capture.record(interaction).await {
-                warn!("Failed to record debug capture: {}", capture_err);
-            }
-        }
+        let pending_capture = capture_success(
+            debug_capture.as_ref(),
+            capture_builder,
+            resp_status,
+            &body_str,
+            captured_headers,
+        )
+        .await;
 
         let transformed_response_value = match serde_json::from_slice::<serde_json::Value>(&body) {
             Ok(response_value) if !chain.is_empty() => Some(
@@ -1092,7 +1847,7 @@ pub(super) async fn try_request_via_anthropic_protocol(
                 Err(_) => {
                     let mut response = (status, body).into_response();
                     insert_ccr_tier_header(&mut response, tier_name);
-                    return Ok(response);
+                    return finish_response(response, retry_finish);
                 }
             };
             // Estimate tokens when provider returns zeros (common for some Anthropic-compatible APIs)
@@ -1118,6 +1873,11 @@ pub(super) async fn try_request_via_anthropic_protocol(
                         AnthropicContentBlock::Text { text } => text.len(),
                         AnthropicContentBlock::Thinking { thinking, .. } => thinking.len(),
                         AnthropicContentBlock::ToolUse { input, .. } => input.to_string().len(),
+                        AnthropicContentBlock::Audio { audio } => audio
+                            .get("transcript")
+                            .and_then(|t| t.as_str())
+                            .map_or(0, str::len),
+                        AnthropicContentBlock::Image { .. } => 0,
                     })
                     .sum();
                 if content_len > 0 {
@@ -1132,7 +1892,8 @@ pub(super) async fn try_request_via_anthropic_protocol(
             }
 
             record_usage(tier_name, input_tokens, output_tokens, 0, 0);
-            verify_token_usage(tier_name, local_estimate, input_tokens);
+            let drift = verify_token_usage(tier_name, local_estimate, input_tokens);
+            persist_drift_capture(pending_capture, drift).await;
             if let Some(cost) = provider
                 .pricing_for_model(model_name)
                 .and_then(|p| p.estimate_request_cost_usd(input_tokens, output_tokens))
@@ -1144,12 +1905,12 @@ pub(super) async fn try_request_via_anthropic_protocol(
                 .map_err(|e| TryRequestError::Other(e.into()))?;
             let mut response = (StatusCode::OK, response_body).into_response();
             insert_ccr_tier_header(&mut response, tier_name);
-            return Ok(response);
+            return finish_response(response, retry_finish);
         }
 
         let mut response = (status, body).into_response();
         insert_ccr_tier_header(&mut response, tier_name);
-        Ok(response)
+        finish_response(response, retry_finish)
     }
 }
 
@@ -1172,6 +1933,151 @@ mod tests {
         serde_json::from_value(value).expect("provider config should parse")
     }
 
+    #[test]
+    fn azure_provider_uses_deployment_url_and_api_key_header() {
+        let mut provider: Provider = serde_json::from_value(serde_json::json!({
+            "name": "azure",
+            "api_base_url": "https://contoso.openai.azure.com/openai/",
+            "api_key": "az-key",
+            "models": ["gpt-4o"],
+            "azure": {"api_version": "2024-06-01"}
+        }))
+        .expect("provider config should parse");
+
+        assert_eq!(
+            provider_openai_chat_completions_url(&provider, "gpt-4o"),
+            "https://contoso.openai.azure.com/openai/deployments/gpt-4o/chat/completions?api-version=2024-06-01"
+        );
+        let headers = build_openai_headers(&provider).unwrap();
+        assert_eq!(headers["api-key"], "az-key");
+        assert!(headers.get("authorization").is_none());
+
+        provider.azure = None;
+        assert_eq!(
+            provider_openai_chat_completions_url(&provider, "gpt-4o"),
+            "https://contoso.openai.azure.com/openai/chat/completions"
+        );
+        assert_eq!(
+            build_openai_headers(&provider).unwrap()["authorization"],
+            "Bearer az-key"
+        );
+    }
+
+    #[test]
+    fn partial_usage_keeps_largest_cumulative_counts() {
+        let mut partial = PartialUsage::default();
+        partial.absorb(&serde_json::json!({
+            "type": "message_start",
+            "message": {"usage": {"input_tokens": 120, "cache_read_input_tokens": 100}}
+        }));
+        partial.absorb(&serde_json::json!({
+            "type": "message_delta",
+            "usage": {"output_tokens": 7}
+        }));
+        partial.absorb(&serde_json::json!({"type": "error", "error": {"message": "boom"}}));
+
+        assert_eq!(
+            partial,
+            PartialUsage {
+                input_tokens: 120,
+                output_tokens: 7,
+                cache_read_tokens: 100,
+                cache_creation_tokens: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn partial_usage_reads_openai_error_body() {
+        let mut partial = PartialUsage::default();
+        partial.absorb_body(
+            br#"{"error":{"message":"overloaded"},"usage":{"prompt_tokens":50,"completion_tokens":3,"prompt_tokens_details":{"cached_tokens":32}}}"#,
+        );
+        assert_eq!(partial.input_tokens, 50);
+        assert_eq!(partial.output_tokens, 3);
+        assert_eq!(partial.cache_read_tokens, 32);
+        assert!(PartialUsage::default().is_empty());
+    }
+
+    #[test]
+    fn soft_error_requires_error_without_content() {
+        let defaults = SoftErrorConfig::default();
+        assert_eq!(
+            soft_error(
+                &serde_json::json!({"error": {"message": "quota", "code": 1113}}),
+                &defaults
+            ),
+            Some(("1113".to_string(), "quota".to_string()))
+        );
+        assert_eq!(
+            soft_error(
+                &serde_json::json!({"error": {"message": "partial"}, "choices": [{"index": 0}]}),
+                &defaults
+            ),
+            None
+        );
+        assert_eq!(
+            soft_error(&serde_json::json!({"error": null}), &defaults),
+            None
+        );
+
+        let custom = SoftErrorConfig {
+            enabled: true,
+            error_paths: vec!["/base_resp/status_code".to_string()],
+        };
+        assert_eq!(
+            soft_error(
+                &serde_json::json!({"base_resp": {"status_code": 0}}),
+                &custom
+            ),
+            None
+        );
+        assert_eq!(
+            soft_error(
+                &serde_json::json!({"base_resp": {"status_code": 1008}}),
+                &custom
+            ),
+            Some((
+                "unknown".to_string(),
+                "Unknown error in response body".to_string()
+            ))
+        );
+
+        let disabled = SoftErrorConfig {
+            enabled: false,
+            ..SoftErrorConfig::default()
+        };
+        assert_eq!(
+            soft_error(&serde_json::json!({"error": "boom"}), &disabled),
+            None
+        );
+    }
+
+    #[test]
+    fn kimi_context_cache_adds_cache_headers() {
+        let mut provider: Provider = serde_json::from_value(serde_json::json!({
+            "name": "kimi",
+            "api_base_url": "https://api.moonshot.cn/v1",
+            "api_key": "sk-kimi",
+            "models": ["kimi-k2"],
+            "kimi_context_cache": {"cache_id": "cache-abc123", "reset_ttl_secs": 3600}
+        }))
+        .expect("provider config should parse");
+
+        let headers = build_openai_headers(&provider).unwrap();
+        assert_eq!(headers["X-Msh-Context-Cache"], "cache-abc123");
+        assert_eq!(headers["X-Msh-Context-Cache-Reset-TTL"], "3600");
+
+        provider.kimi_context_cache.as_mut().unwrap().reset_ttl_secs = None;
+        let headers = build_openai_headers(&provider).unwrap();
+        assert_eq!(headers["X-Msh-Context-Cache"], "cache-abc123");
+        assert!(!headers.contains_key("X-Msh-Context-Cache-Reset-TTL"));
+
+        provider.kimi_context_cache = None;
+        let headers = build_openai_headers(&provider).unwrap();
+        assert!(!headers.contains_key("X-Msh-Context-Cache"));
+    }
+
     #[test]
     fn anthropic_headers_default_to_x_api_key() {
         let headers = build_anthropic_headers(&anthropic_provider(None)).unwrap();
@@ -1188,6 +2094,29 @@ mod tests {
         assert!(headers.get("x-api-key").is_none());
     }
 
+    #[test]
+    fn anthropic_beta_is_joined_and_merged_with_client_beta() {
+        let mut provider = anthropic_provider(None);
+        provider.anthropic_beta = vec![
+            "prompt-caching-2024-07-31".to_string(),
+            "token-efficient-tools-2025-02-19".to_string(),
+        ];
+        let mut headers = build_anthropic_headers(&provider).unwrap();
+        assert_eq!(
+            headers.get("anthropic-beta").unwrap(),
+            "prompt-caching-2024-07-31,token-efficient-tools-2025-02-19"
+        );
+
+        merge_anthropic_beta(
+            &mut headers,
+            "context-1m-2025-08-07, prompt-caching-2024-07-31",
+        );
+        assert_eq!(
+            headers.get("anthropic-beta").unwrap(),
+            "prompt-caching-2024-07-31,token-efficient-tools-2025-02-19,context-1m-2025-08-07"
+        );
+    }
+
     #[test]
     fn debug_capture_headers_are_bounded_and_redacted() {
         let mut headers = reqwest::header::HeaderMap::new();
@@ -0,0 +1,126 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Stream accounting and draining for graceful shutdown.
+//
+// `handle_messages` reserves a `StreamSlot` in `AppState::active_streams` for
+// each streaming request and holds it until the response body is dropped.
+// `reject_while_draining` wraps the proxy routes and answers 503 once
+// `AppState::draining` is set; on shutdown the server sets that flag and waits
+// in `wait_for_streams` for the count to reach zero.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header::CONTENT_TYPE, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use futures::StreamExt;
+
+use super::AppState;
+
+/// How often draining re-checks the active stream count.
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// How often draining logs the remaining stream count.
+const DRAIN_LOG_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Holds one slot in the active stream count until dropped.
+pub(super) struct StreamSlot(Arc<AtomicUsize>);
+
+impl StreamSlot {
+    /// Reserve a slot if fewer than `max_streams` are held (`0` = unlimited).
+    pub(super) fn try_acquire(
+        active_streams: &Arc<AtomicUsize>,
+        max_streams: usize,
+    ) -> Option<Self> {
+        active_streams
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |current| {
+                (max_streams == 0 || current < max_streams).then_some(current + 1)
+            })
+            .ok()?;
+        Some(Self(active_streams.clone()))
+    }
+}
+
+impl Drop for StreamSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+pub(super) fn is_event_stream(response: &Response) -> bool {
+    response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/event-stream"))
+}
+
+/// 503 body in the error shape of the frontend the path belongs to.
+fn draining_response(path: &str) -> Response {
+    let message = "Server is shutting down";
+    let body = if path == "/v1/messages" || path.starts_with("/preset/") {
+        serde_json::json!({
+            "type": "error",
+            "error": {"type": "overloaded_error", "message": message}
+        })
+    } else {
+        serde_json::json!({
+            "error": {
+                "type": "server_error",
+                "message": message,
+                "code": "service_unavailable"
+            }
+        })
+    };
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        [(axum::http::header::CONNECTION, "close")],
+        axum::Json(body),
+    )
+        .into_response()
+}
+
+/// Reject new requests with 503 while the server is draining.
+pub async fn reject_while_draining(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if state.draining.load(Ordering::SeqCst) {
+        return draining_response(request.uri().path());
+    }
+    next.run(request).await
+}
+
+/// Keep `guard` alive until the client finishes with the response body.
+pub(super) fn hold_until_body_dropped<T: Send + 'static>(response: Response, guard: T) -> Response {
+    let (parts, body) = response.into_parts();
+    let body = Body::from_stream(body.into_data_stream().map(move |chunk| {
+        let _ = &guard;
+        chunk
+    }));
+    Response::from_parts(parts, body)
+}
+
+/// Wait until `active_streams` reaches zero or `timeout` elapses, logging the
+/// remaining count every few seconds. Returns the number still active.
+pub async fn wait_for_streams(active_streams: &AtomicUsize, timeout: Duration) -> usize {
+    let start = tokio::time::Instant::now();
+    let mut next_log = start + DRAIN_LOG_INTERVAL;
+    loop {
+        let remaining = active_streams.load(Ordering::SeqCst);
+        if remaining == 0 || start.elapsed() >= timeout {
+            return remaining;
+        }
+        let now = tokio::time::Instant::now();
+        if now >= next_log {
+            tracing::info!("Draining: {} stream(s) still active", remaining);
+            next_log = now + DRAIN_LOG_INTERVAL;
+        }
+        tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+    }
+}
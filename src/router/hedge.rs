@@ -0,0 +1,32 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Hedged-request accounting.
+//
+// With `Router.hedging.hedge_after_ms` set, `route_messages` starts the next
+// tier alongside one that has not answered in time. Each such hedge holds a
+// `HedgeSlot` so `max_concurrent` bounds the extra upstream load across all
+// requests, not per request.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// One hedged attempt, counted in `AppState::hedges_in_flight`; released
+/// when dropped.
+pub(super) struct HedgeSlot(Arc<AtomicUsize>);
+
+impl HedgeSlot {
+    /// Reserve a slot if fewer than `limit` hedges are in flight.
+    pub(super) fn try_acquire(hedges_in_flight: &Arc<AtomicUsize>, limit: usize) -> Option<Self> {
+        hedges_in_flight
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |current| {
+                (current < limit).then_some(current + 1)
+            })
+            .ok()?;
+        Some(Self(hedges_in_flight.clone()))
+    }
+}
+
+impl Drop for HedgeSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,125 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Per-client request rate limit for the proxy routes.
+//
+// `throttle_ingress` sits inside the client-key guard and is a no-op until
+// `INGRESS_RATE_LIMIT` is set. Clients are keyed by their API key once it has
+// been checked against `CLIENT_KEYS`, and by peer address otherwise, so made-up
+// keys cannot buy fresh buckets. Keys are hashed before they are stored.
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header::RETRY_AFTER, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+
+use super::auth::verified_client_key;
+use super::AppState;
+use crate::metrics::record_ingress_throttled;
+
+/// Bucket key for `request`: `key:<sha256 prefix>` for an API key listed in
+/// `client_keys`, otherwise `ip:<peer address>`.
+fn ingress_key(request: &Request, client_keys: &[String]) -> String {
+    if let Some(key) = verified_client_key(request.headers(), client_keys) {
+        let digest = Sha256::digest(key.as_bytes());
+        return format!("key:{}", hex::encode(&digest[..6]));
+    }
+    match request.extensions().get::<ConnectInfo<SocketAddr>>() {
+        Some(ConnectInfo(addr)) => format!("ip:{}", addr.ip()),
+        None => "ip:unknown".to_string(),
+    }
+}
+
+/// 429 body in the error shape of the frontend the path belongs to.
+fn throttled_response(path: &str, retry_after: Duration) -> Response {
+    let message = "Client request rate limit exceeded";
+    let body = if path == "/v1/messages" || path.starts_with("/preset/") {
+        serde_json::json!({
+            "type": "error",
+            "error": {"type": "rate_limit_error", "message": message}
+        })
+    } else {
+        serde_json::json!({
+            "error": {
+                "message": message,
+                "type": "rate_limit_error",
+                "param": null,
+                "code": "rate_limit_exceeded"
+            }
+        })
+    };
+    // Round up so a client that waits exactly `Retry-After` finds a token.
+    let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(RETRY_AFTER, seconds.max(1).to_string())],
+        axum::Json(body),
+    )
+        .into_response()
+}
+
+/// Reject proxy requests over the client's `INGRESS_RATE_LIMIT` with 429 and
+/// `Retry-After`. Passes everything through when no limit is configured.
+pub async fn throttle_ingress(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let config = state.config.load();
+    let limit = config
+        .ingress_rate_limit()
+        .map(|limit| (limit.requests_per_minute, limit.burst()));
+    let Some((requests_per_minute, burst)) = limit else {
+        return next.run(request).await;
+    };
+
+    let key = ingress_key(&request, config.client_keys());
+    match state
+        .ingress_limiter
+        .check(&key, requests_per_minute, burst)
+    {
+        Ok(()) => next.run(request).await,
+        Err(retry_after) => {
+            let by = if key.starts_with("key:") { "key" } else { "ip" };
+            record_ingress_throttled(by);
+            throttled_response(request.uri().path(), retry_after)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+
+    #[test]
+    fn ingress_key_uses_only_verified_api_keys() {
+        let client_keys = vec!["sk-secret".to_string()];
+        let mut request = Request::builder()
+            .header("x-api-key", "sk-secret")
+            .body(Body::empty())
+            .unwrap();
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from(([10, 0, 0, 7], 4000))));
+        let key = ingress_key(&request, &client_keys);
+        assert!(key.starts_with("key:"), "{key}");
+        assert!(!key.contains("sk-secret"));
+
+        // Unchecked keys, with or without CLIENT_KEYS, fall back to the peer.
+        assert_eq!(ingress_key(&request, &[]), "ip:10.0.0.7");
+        request
+            .headers_mut()
+            .insert("x-api-key", "sk-made-up".parse().unwrap());
+        assert_eq!(ingress_key(&request, &client_keys), "ip:10.0.0.7");
+
+        request.headers_mut().remove("x-api-key");
+        assert_eq!(ingress_key(&request, &client_keys), "ip:10.0.0.7");
+        request.extensions_mut().clear();
+        assert_eq!(ingress_key(&request, &client_keys), "ip:unknown");
+    }
+}
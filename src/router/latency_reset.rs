@@ -0,0 +1,68 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// EWMA reset for `POST /v1/latencies/reset`.
+//
+// After a provider incident the EWMA keeps a recovered tier ranked low until
+// enough fast samples wash the failures out. Resetting drops the tier's
+// tracker state, gauges, and persisted EWMA state, so it starts cold and is
+// measured again on the next requests.
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+use tracing::info;
+
+use crate::metrics::{clear_ewma_state, get_latency_entries, ProviderFilter};
+
+use super::AppState;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct LatencyResetQuery {
+    /// Reset only this tier. All tiers are reset when omitted.
+    #[serde(default)]
+    pub tier: Option<String>,
+}
+
+/// Clear EWMA state for one tier or all of them.
+///
+/// Returns the entries `GET /v1/latencies` reported just before the reset,
+/// or 404 when `?tier=` names a tier with no recorded state.
+pub async fn reset_latencies(
+    State(state): State<AppState>,
+    Query(query): Query<LatencyResetQuery>,
+) -> Response {
+    let tier = query.tier.as_deref();
+    let before = get_latency_entries(
+        &state.ewma_tracker,
+        &state.ratelimit_tracker,
+        &state.config.load(),
+        &ProviderFilter::default(),
+    );
+    let cleared = state.ewma_tracker.reset(tier);
+
+    if let (Some(tier), true) = (tier, cleared.is_empty()) {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "error": format!("No latency state recorded for tier '{}'", tier)
+            })),
+        )
+            .into_response();
+    }
+
+    clear_ewma_state(&cleared);
+    info!(tiers = ?cleared, "Reset EWMA latency state");
+
+    let previous: Vec<_> = before
+        .into_iter()
+        .filter(|entry| cleared.contains(&entry.tier))
+        .collect();
+    Json(serde_json::json!({
+        "status": "reset",
+        "tiers": previous,
+    }))
+    .into_response()
+}
@@ -0,0 +1,79 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Maintenance mode: keep the server up but stop dispatching upstream.
+//
+// While `AppState::maintenance` is set, `reject_during_maintenance` answers
+// every proxy route with 503 and counts it in
+// `ccr_maintenance_rejections_total`. Metrics, usage, latency, health, and
+// admin routes keep working. The flag starts from `MAINTENANCE_MODE`, is
+// toggled by `POST /v1/maintenance`, and is reset by a reload only when the
+// reloaded `MAINTENANCE_MODE` differs from the previous one.
+
+use std::sync::atomic::Ordering;
+
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+use tracing::warn;
+
+use super::AppState;
+use crate::metrics::record_maintenance_rejection;
+
+#[derive(Debug, Deserialize)]
+pub struct MaintenanceRequest {
+    pub enabled: bool,
+}
+
+/// Turn maintenance mode on or off.
+pub(super) fn set(state: &AppState, enabled: bool) {
+    let was = state.maintenance.swap(enabled, Ordering::SeqCst);
+    if was != enabled {
+        warn!(enabled, "Maintenance mode changed");
+    }
+}
+
+/// `POST /v1/maintenance` with `{"enabled": bool}`; answers with the new state.
+pub async fn set_maintenance(
+    State(state): State<AppState>,
+    Json(request): Json<MaintenanceRequest>,
+) -> Response {
+    set(&state, request.enabled);
+    Json(serde_json::json!({"maintenance": request.enabled})).into_response()
+}
+
+/// 503 body in the error shape of the frontend the path belongs to.
+fn maintenance_response(path: &str) -> Response {
+    let message = "CCR is in maintenance mode; upstream requests are paused";
+    let body = if path == "/v1/messages" || path.starts_with("/preset/") {
+        serde_json::json!({
+            "type": "error",
+            "error": {"type": "overloaded_error", "message": message}
+        })
+    } else {
+        serde_json::json!({
+            "error": {
+                "type": "server_error",
+                "message": message,
+                "code": "maintenance"
+            }
+        })
+    };
+    (StatusCode::SERVICE_UNAVAILABLE, Json(body)).into_response()
+}
+
+/// Reject proxy requests with 503 while maintenance mode is on.
+pub async fn reject_during_maintenance(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if state.maintenance.load(Ordering::SeqCst) {
+        record_maintenance_rejection();
+        return maintenance_response(request.uri().path());
+    }
+    next.run(request).await
+}
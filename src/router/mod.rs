@@ -12,6 +12,8 @@ mod translate_response;
 mod dispatch;
 use dispatch::*;
 
+mod think_split;
+
 mod streaming;
 pub use streaming::{
     stream_anthropic_response_with_tracking, stream_response_translated, BoxByteStream,
@@ -23,24 +25,99 @@ pub use openai_compat::handle_chat_completions;
 mod responses_api;
 pub use responses_api::handle_responses;
 
+mod responses_native;
+
+mod completions;
+pub use completions::handle_completions;
+
+mod reload;
+pub use reload::handle_reload;
+
+mod latency_reset;
+pub use latency_reset::{reset_latencies, LatencyResetQuery};
+
+mod auth;
+pub use auth::{require_admin_token, require_client_key};
+
+mod transformer_preview;
+pub use transformer_preview::preview_transformers;
+
+mod providers;
+pub use providers::list_providers;
+
+mod client_message_id;
+pub use client_message_id::CLIENT_MESSAGE_ID_HEADER;
+use client_message_id::{client_message_id, echo_client_message_id};
+
+mod compression;
+pub use compression::response_compression_layer;
+
+mod hedge;
+use hedge::HedgeSlot;
+
+mod warnings;
+use warnings::RequestWarnings;
+
+mod server_timing;
+use server_timing::ServerTiming;
+
+mod drain;
+use drain::{hold_until_body_dropped, is_event_stream, StreamSlot};
+pub use drain::{reject_while_draining, wait_for_streams};
+
+mod body_limit;
+pub use body_limit::limit_request_body;
+
+mod ingress_limit;
+pub use ingress_limit::throttle_ingress;
+
+mod probe;
+pub use probe::{
+    probe_provider, probe_providers, provider_request_headers, ProbeOutcome, ProviderProbe,
+    PROBE_TIMEOUT,
+};
+
+mod preset_keepalive;
+use preset_keepalive::with_initial_keepalive;
+
+mod warm;
+pub use warm::{spawn_preset_warmups, warm_presets};
+
+mod maintenance;
+pub use maintenance::{reject_during_maintenance, set_maintenance};
+
+mod readiness;
+pub use readiness::{check_providers, ready, spawn_readiness_checks, Readiness};
+
+mod request_id;
+use request_id::request_id;
+pub use request_id::{assign_request_id, INBOUND_REQUEST_ID_HEADER, REQUEST_ID_HEADER};
+
+mod app;
+pub use app::app;
+
 use axum::{
     extract::{Path, State},
     http::{HeaderMap, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
+use futures::future::FutureExt;
+use futures::stream::{FuturesUnordered, StreamExt};
 use std::collections::BTreeSet;
-#[cfg(feature = "gp")]
 use std::sync::atomic::Ordering;
-use tracing::{error, info, warn};
+use tracing::{error, info, warn, Instrument};
 
-use crate::frontend::detect_frontend;
+use crate::config::WebSearchConfig;
+use crate::frontend::{detect_frontend, try_detect_frontend, FrontendSignals, FrontendType};
 use crate::metrics::{
-    increment_active_requests, record_failure, record_pre_request_tokens,
-    record_rate_limit_backoff, record_rate_limit_hit, record_request_duration_with_frontend,
-    record_request_with_frontend, sync_ewma_gauge,
+    increment_active_requests, record_failure, record_hedge_win, record_pre_request_tokens,
+    record_rate_limit_backoff, record_rate_limit_hit, record_rejected,
+    record_request_duration_with_frontend, record_request_with_frontend, sync_ewma_gauge,
+    RequestMode,
 };
 use crate::routing::AttemptTimer;
+use crate::sticky::SESSION_HEADER;
 
 /// RAII guard that decrements active_requests when dropped.
 struct ActiveRequestGuard;
@@ -62,30 +139,142 @@ impl Drop for ActiveRequestGuard {
 // Request Handler
 // ============================================================================
 
-/// Check if request contains [search] or [web] tags.
-fn needs_web_search(request: &AnthropicRequest) -> bool {
-    for msg in &request.messages {
+/// Check if the request asks for web search: a configured tag in message
+/// text, or `extra_params.web_search: true`.
+fn needs_web_search(request: &AnthropicRequest, web_search: &WebSearchConfig) -> bool {
+    if explicit_web_search(request) {
+        return true;
+    }
+    request.messages.iter().any(|msg| {
+        msg.content.as_str().is_some_and(|text| {
+            web_search
+                .tags
+                .iter()
+                .any(|tag| !tag.is_empty() && text.contains(tag.as_str()))
+        })
+    })
+}
+
+/// Whether the request sets `extra_params.web_search: true`.
+fn explicit_web_search(request: &AnthropicRequest) -> bool {
+    request
+        .unrecognized
+        .get("extra_params")
+        .and_then(|params| params.get("web_search"))
+        .and_then(|flag| flag.as_bool())
+        .unwrap_or(false)
+}
+
+/// Consume `extra_params.web_search` so it is not reported as dropped;
+/// `extra_params` itself goes once nothing else is left in it.
+fn take_web_search_flag(request: &mut AnthropicRequest) {
+    let Some(serde_json::Value::Object(params)) = request.unrecognized.get_mut("extra_params")
+    else {
+        return;
+    };
+    params.remove("web_search");
+    if params.is_empty() {
+        request.unrecognized.remove("extra_params");
+    }
+}
+
+/// Remove the configured search tags from message content.
+fn strip_search_tags(request: &mut AnthropicRequest, web_search: &WebSearchConfig) {
+    for msg in &mut request.messages {
         if let Some(text) = msg.content.as_str() {
-            if text.contains("[search]") || text.contains("[web]") {
-                return true;
+            let mut cleaned = text.to_string();
+            for tag in web_search.tags.iter().filter(|tag| !tag.is_empty()) {
+                cleaned = cleaned.replace(tag.as_str(), "");
             }
+            msg.content = serde_json::Value::String(cleaned.trim().to_string());
         }
     }
-    false
 }
 
-/// Remove [search] and [web] tags from message content.
-fn strip_search_tags(request: &mut AnthropicRequest) {
-    for msg in &mut request.messages {
-        if let Some(text) = msg.content.as_str() {
-            let cleaned = text
-                .replace("[search]", "")
-                .replace("[web]", "")
-                .trim()
-                .to_string();
-            msg.content = serde_json::Value::String(cleaned);
+/// The `x-ccr-sse-buffer` value clamped to `SSE_BUFFER_OVERRIDE`, or `None`
+/// when the header is absent, not a number, or not allowed.
+fn requested_sse_buffer_size(headers: &HeaderMap, config: &crate::config::Config) -> Option<usize> {
+    let range = config.sse_buffer_override()?;
+    let requested = headers
+        .get("x-ccr-sse-buffer")?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<usize>()
+        .ok()?;
+    let size = range.clamp(requested);
+    if size != requested {
+        tracing::debug!(
+            requested,
+            size,
+            "clamped x-ccr-sse-buffer to SSE_BUFFER_OVERRIDE"
+        );
+    }
+    Some(size)
+}
+
+fn upstream_saturated_response(limit: usize) -> Response {
+    let error_resp = serde_json::json!({
+        "error": {
+            "type": "overloaded_error",
+            "message": format!("All {} upstream request slots are in use", limit),
+            "code": "upstream_saturated"
+        }
+    });
+    (StatusCode::SERVICE_UNAVAILABLE, Json(error_resp)).into_response()
+}
+
+/// 400 for a direct `provider,model` route whose provider does not serve the
+/// model (see `model_aliases`).
+fn unknown_model_response(model: &str, provider: &str) -> Response {
+    let error_resp = serde_json::json!({
+        "error": {
+            "type": "invalid_request_error",
+            "message": format!(
+                "Model '{}' is not served by provider '{}'; add it to the provider's models or model_aliases",
+                model, provider
+            ),
+            "code": "model_not_found"
+        }
+    });
+    (StatusCode::BAD_REQUEST, Json(error_resp)).into_response()
+}
+
+fn no_healthy_tiers_response(tiers: usize) -> Response {
+    let error_resp = serde_json::json!({
+        "error": {
+            "type": "overloaded_error",
+            "message": format!("None of {} backend tier(s) are healthy", tiers),
+            "code": "no_healthy_tiers"
+        }
+    });
+    (StatusCode::SERVICE_UNAVAILABLE, Json(error_resp)).into_response()
+}
+
+/// Error returned when a failure is classified `terminal`. Upstream 4xx
+/// statuses are passed through; anything else becomes 502.
+fn terminal_error_response(error: &anyhow::Error, tier_name: &str) -> Response {
+    let upstream = error.downcast_ref::<dispatch::UpstreamStatusError>();
+    let status = upstream
+        .map(|u| u.status.as_u16())
+        .filter(|status| (400..500).contains(status))
+        .and_then(|status| StatusCode::from_u16(status).ok())
+        .unwrap_or(StatusCode::BAD_GATEWAY);
+    let message = upstream
+        .map(|u| u.body.clone())
+        .unwrap_or_else(|| error.to_string());
+    let error_resp = serde_json::json!({
+        "error": {
+            "type": "api_error",
+            "message": format!("{} failed with a non-retryable error: {}", tier_name, message),
+            "code": "upstream_terminal_error"
         }
+    });
+    let mut response = (status, Json(error_resp)).into_response();
+    if let Ok(value) = tier_name.parse() {
+        response.headers_mut().insert("x-ccr-tier", value);
     }
+    response
 }
 
 fn rate_limit_exhausted_response(
@@ -102,7 +291,9 @@ fn rate_limit_exhausted_response(
 
     let mut response = (StatusCode::TOO_MANY_REQUESTS, Json(error_resp)).into_response();
     if let Some(retry_after) = retry_after {
-        if let Ok(value) = retry_after.as_secs().to_string().parse() {
+        // Round up so a partial second is never advertised as `0`.
+        let secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+        if let Ok(value) = secs.to_string().parse() {
             response.headers_mut().insert("retry-after", value);
         }
     }
@@ -114,14 +305,623 @@ fn rate_limit_exhausted_response(
     response
 }
 
+/// 400 for a request whose client could not be recognized. The body carries
+/// both the Anthropic and OpenAI error fields, since either may be asking.
+fn unknown_frontend_response(signals: FrontendSignals) -> Response {
+    let error_resp = serde_json::json!({
+        "type": "error",
+        "error": {
+            "type": "invalid_request_error",
+            "message": "Could not tell whether this is an Anthropic or OpenAI request: \
+                        no anthropic-* header, no Codex User-Agent, and no messages array",
+            "code": "unknown_frontend",
+            "signals": signals,
+        }
+    });
+    (StatusCode::BAD_REQUEST, Json(error_resp)).into_response()
+}
+
+fn invalid_request_response(frontend: FrontendType, message: &str) -> Response {
+    let body = match frontend {
+        FrontendType::ClaudeCode => serde_json::json!({
+            "type": "error",
+            "error": {"type": "invalid_request_error", "message": message}
+        }),
+        FrontendType::Codex => serde_json::json!({
+            "error": {
+                "type": "invalid_request_error",
+                "message": message,
+                "param": null,
+                "code": "invalid_request_body"
+            }
+        }),
+    };
+    (StatusCode::BAD_REQUEST, Json(body)).into_response()
+}
+
+fn stream_limit_response(frontend: FrontendType, max_streams: usize) -> Response {
+    let message = format!("Concurrent stream limit of {} reached", max_streams);
+    let body = match frontend {
+        FrontendType::ClaudeCode => serde_json::json!({
+            "type": "error",
+            "error": {"type": "overloaded_error", "message": message}
+        }),
+        FrontendType::Codex => serde_json::json!({
+            "error": {
+                "type": "server_error",
+                "message": message,
+                "code": "stream_limit_exceeded"
+            }
+        }),
+    };
+    (StatusCode::SERVICE_UNAVAILABLE, Json(body)).into_response()
+}
+
+/// Handle `/v1/messages`.
+///
+/// The body is read as plain JSON first, so a request from a client that
+/// cannot be recognized is rejected with the signals that were checked
+/// instead of being parsed under a guessed frontend. `Router.defaultFrontend`
+/// restores the guess.
 pub async fn handle_messages(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(request): Json<AnthropicRequest>,
+    Json(body): Json<serde_json::Value>,
+) -> Response {
+    let default_frontend = state.config.load().router().default_frontend;
+    let Some(frontend) = try_detect_frontend(&headers, &body).or(default_frontend) else {
+        let signals = FrontendSignals::collect(&headers, &body);
+        warn!(?signals, "Rejecting request from an unrecognized frontend");
+        return unknown_frontend_response(signals);
+    };
+    match serde_json::from_value::<AnthropicRequest>(body) {
+        Ok(request) => handle_anthropic_request(state, headers, request).await,
+        Err(err) => invalid_request_response(frontend, &format!("Invalid request body: {err}")),
+    }
+}
+
+/// Handle Anthropic-format requests; every proxy frontend ends up here.
+///
+/// A streaming request holds one of `max_streams` slots until its response
+/// body is dropped, and gets 503 when none is free. A client message id on
+/// the request is echoed on whatever response comes back.
+pub(crate) async fn handle_anthropic_request(
+    state: AppState,
+    headers: HeaderMap,
+    request: AnthropicRequest,
+) -> Response {
+    let client_message_id = client_message_id(&headers, request.metadata.as_ref());
+    // `route_messages` fills in the frontend and the tier that served the
+    // request; `fallback` is true when that was not the first tier tried.
+    let span = tracing::info_span!(
+        "ccr.request",
+        request_id = request_id(&headers),
+        model = %request.model,
+        stream = request.stream.unwrap_or(false),
+        frontend = tracing::field::Empty,
+        tier = tracing::field::Empty,
+        fallback = tracing::field::Empty,
+    );
+    let response = admit_messages(state, headers, request)
+        .instrument(span)
+        .await;
+    match client_message_id {
+        Some(id) => echo_client_message_id(response, id).await,
+        None => response,
+    }
+}
+
+async fn admit_messages(
+    state: AppState,
+    headers: HeaderMap,
+    request: AnthropicRequest,
+) -> Response {
+    if !request.stream.unwrap_or(false) {
+        return route_messages(state, headers, request).await;
+    }
+    let Some(slot) = StreamSlot::try_acquire(&state.active_streams, state.max_streams) else {
+        record_rejected();
+        warn!(
+            "Rejecting stream: {} of {} stream slots in use",
+            state.active_streams.load(Ordering::SeqCst),
+            state.max_streams
+        );
+        let body_json = serde_json::to_value(&request).unwrap_or_default();
+        return stream_limit_response(detect_frontend(&headers, &body_json), state.max_streams);
+    };
+    let response = route_messages(state, headers, request).await;
+    if is_event_stream(&response) {
+        hold_until_body_dropped(response, slot)
+    } else {
+        response
+    }
+}
+
+/// Longest upstream error message reported per tier in `tier_errors`.
+const TIER_ERROR_MESSAGE_LIMIT: usize = 512;
+
+/// The last error one tier returned, reported in `tier_errors` when every
+/// tier fails.
+#[derive(Debug, serde::Serialize)]
+struct TierError {
+    tier: String,
+    /// Upstream HTTP status, when the provider answered.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<u16>,
+    message: String,
+}
+
+impl TierError {
+    fn new(tier_name: &str, status: Option<u16>, message: &str) -> Self {
+        let mut message_chars = message.chars();
+        let mut truncated: String = message_chars
+            .by_ref()
+            .take(TIER_ERROR_MESSAGE_LIMIT)
+            .collect();
+        if message_chars.next().is_some() {
+            truncated.push_str("...");
+        }
+        Self {
+            tier: tier_name.to_string(),
+            status,
+            message: truncated,
+        }
+    }
+
+    /// Upstream status and body for an HTTP error, else the error text.
+    fn from_error(tier_name: &str, error: anyhow::Error) -> Self {
+        match error.downcast_ref::<dispatch::UpstreamStatusError>() {
+            Some(upstream) => Self::new(tier_name, Some(upstream.status.as_u16()), &upstream.body),
+            None => Self::new(tier_name, None, &client_error_message(error)),
+        }
+    }
+}
+
+/// Text of an upstream error that is safe to send to the client. Transport
+/// errors name the request URL, whose query string can carry an API key.
+pub(super) fn client_error_message(error: anyhow::Error) -> String {
+    match error.downcast::<reqwest::Error>() {
+        Ok(transport) => transport.without_url().to_string(),
+        Err(error) => error.to_string(),
+    }
+}
+
+/// Why the cascade has not produced a response yet, for the final error.
+#[derive(Default)]
+struct CascadeFailures {
+    saw_rate_limit: bool,
+    saw_non_rate_limit_failure: bool,
+    retry_after_hint: Option<std::time::Duration>,
+    last_rate_limited_tier: Option<String>,
+    tier_errors: Vec<TierError>,
+}
+
+impl CascadeFailures {
+    fn failed(&mut self, error: Option<TierError>) {
+        self.saw_non_rate_limit_failure = true;
+        self.tier_errors.extend(error);
+    }
+
+    fn rate_limited(&mut self, tier_name: &str, retry_after: Option<std::time::Duration>) {
+        self.saw_rate_limit = true;
+        self.last_rate_limited_tier = Some(tier_name.to_string());
+        self.tier_errors.push(TierError::new(
+            tier_name,
+            Some(StatusCode::TOO_MANY_REQUESTS.as_u16()),
+            "rate limited",
+        ));
+        self.retry_after_hint = match (self.retry_after_hint, retry_after) {
+            (Some(current), Some(candidate)) => Some(current.max(candidate)),
+            (None, some) => some,
+            (some, None) => some,
+        };
+    }
+}
+
+/// Per-request state shared by every tier attempt, including hedges that run
+/// alongside an earlier tier.
+struct CascadeCtx<'a> {
+    state: &'a AppState,
+    config: &'a crate::config::Config,
+    request: &'a AnthropicRequest,
+    client_wants_stream: bool,
+    msg_values: &'a [serde_json::Value],
+    tool_values: Option<&'a [serde_json::Value]>,
+    frontend: FrontendType,
+    start: std::time::Instant,
+    request_span: &'a tracing::Span,
+    session_id: Option<&'a str>,
+    request_id: Option<&'a str>,
+    /// The client's `anthropic-beta` header, for providers that forward it.
+    anthropic_beta: Option<&'a str>,
+    /// SSE buffer size the client asked for with `x-ccr-sse-buffer`, already
+    /// clamped to `SSE_BUFFER_OVERRIDE`.
+    sse_buffer_size: Option<usize>,
+    /// Changes made before any tier was chosen; reported with each tier's own.
+    warnings: &'a RequestWarnings,
+    /// Every attempt's tier and duration, for the `Server-Timing` header.
+    server_timing: &'a ServerTiming,
+    #[cfg(feature = "gp")]
+    gp_plan: Option<&'a crate::gp_router::GpRoutingPlan>,
+}
+
+/// How one tier's attempts ended.
+enum TierOutcome {
+    /// Return this to the client: a success, a 429 passthrough, or an
+    /// upstream-saturation error.
+    Respond(Response),
+    /// The tier was rate limited; its remaining retries were skipped.
+    RateLimited {
+        tier_name: String,
+        retry_after: Option<std::time::Duration>,
+    },
+    /// Every attempt failed for another reason; carries the last error.
+    Failed(Option<TierError>),
+    /// The response ended with a `retryOnFinishReason` finish reason. The
+    /// cascade moves on, but returns it if no other tier succeeds.
+    Filtered(TierError, Response),
+}
+
+/// Next tier to try, skipping tiers inside a blackout window or backing off
+/// from rate limits.
+fn next_candidate<'t>(
+    candidates: &mut impl Iterator<Item = (usize, &'t (String, String))>,
+    failures: &mut CascadeFailures,
+    state: &AppState,
+    config: &crate::config::Config,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Option<(usize, &'t String, &'t String)> {
+    for (tier_index, (tier, tier_name)) in candidates {
+        if let Some(window) = config.active_blackout(tier_name, now) {
+            info!(tier = %tier_name, window = %window, "Skipping tier inside blackout window");
+            continue;
+        }
+        let honor_remaining = config
+            .resolve_provider(tier)
+            .map(|p| p.honor_ratelimit_headers)
+            .unwrap_or(true);
+        if state
+            .ratelimit_tracker
+            .should_skip_tier(tier_name, honor_remaining)
+        {
+            failures.rate_limited(tier_name, None);
+            tracing::debug!(tier = %tier_name, "Skipping rate-limited tier");
+            continue;
+        }
+        return Some((tier_index, tier, tier_name));
+    }
+    None
+}
+
+/// Result of the `requireHealthy` check for one tier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TierHealth {
+    Healthy,
+    /// Only backing off from a rate limit.
+    RateLimited,
+    /// Blacked out, or failing repeatedly within the cooldown.
+    Unhealthy,
+}
+
+/// Whether a tier passes the `requireHealthy` check: not blacked out, not
+/// rate limited, and not failing repeatedly within the cooldown.
+fn tier_health(
+    state: &AppState,
+    config: &crate::config::Config,
+    tier: &str,
+    tier_name: &str,
+    now: chrono::DateTime<chrono::Utc>,
+) -> TierHealth {
+    let strict = &config.router().require_healthy;
+    let honor_remaining = config
+        .resolve_provider(tier)
+        .map(|p| p.honor_ratelimit_headers)
+        .unwrap_or(true);
+    let health = if config.active_blackout(tier_name, now).is_some()
+        || state.ewma_tracker.is_circuit_open(
+            tier_name,
+            strict.failure_threshold,
+            strict.cooldown(),
+        ) {
+        TierHealth::Unhealthy
+    } else if state
+        .ratelimit_tracker
+        .should_skip_tier(tier_name, honor_remaining)
+    {
+        TierHealth::RateLimited
+    } else {
+        TierHealth::Healthy
+    };
+    if health != TierHealth::Healthy {
+        tracing::debug!(tier = %tier_name, ?health, "Tier excluded by requireHealthy");
+    }
+    health
+}
+
+/// Finish a tier's response for the client: add the request's warnings and,
+/// when the client wanted a stream that `forceNonStreaming` turned off, wrap
+/// the JSON as pseudo-SSE so Claude CLI can parse it.
+async fn client_response(
+    ctx: &CascadeCtx<'_>,
+    mut response: Response,
+    tier_name: &str,
+    forced_non_streaming: bool,
+) -> Response {
+    let mut warnings = ctx.warnings.clone();
+    warnings.extend(RequestWarnings::take_from(&mut response));
+
+    if ctx.client_wants_stream && forced_non_streaming {
+        response = streaming::wrap_json_response_as_sse(response).await;
+        warnings.push(format!(
+            "streamed from a non-streaming response (forceNonStreaming on {tier_name})"
+        ));
+    }
+
+    if ctx.config.router().warnings_header {
+        warnings.insert_header(&mut response);
+    }
+    response
+}
+
+/// Run one tier with its retries. `hedge` is held while a hedged attempt is
+/// in flight and released when it finishes or is cancelled.
+async fn run_tier(
+    ctx: &CascadeCtx<'_>,
+    tier_index: usize,
+    tier: &String,
+    tier_name: &String,
+    hedge: Option<HedgeSlot>,
+) -> TierOutcome {
+    let _hedge = hedge;
+    let CascadeCtx {
+        state,
+        config,
+        client_wants_stream,
+        frontend,
+        start,
+        request_span,
+        session_id,
+        request_id,
+        anthropic_beta,
+        sse_buffer_size,
+        ..
+    } = *ctx;
+    let sticky = &config.router().sticky_sessions;
+    let mut request = ctx.request.clone();
+
+    // Pre-request token audit: estimate input tokens before dispatching
+    let local_estimate = record_pre_request_tokens(
+        tier_name,
+        ctx.msg_values,
+        request.system.as_ref(),
+        ctx.tool_values,
+    );
+
+    // Per-provider streaming decision: allow_streaming bypasses forceNonStreaming
+    let provider = config.resolve_provider(tier);
+    let provider_allows_streaming = provider.map(|p| p.allow_streaming).unwrap_or(false);
+    let forced_non_streaming = config.router().force_non_streaming && !provider_allows_streaming;
+    if client_wants_stream && forced_non_streaming {
+        request.stream = Some(false);
+    } else {
+        request.stream = Some(client_wants_stream);
+    }
+
+    let retry_config = config.get_tier_retry(tier_name);
+    let max_retries = retry_config.max_retries;
+    let mut last_error = None;
+
+    for attempt in 0..=max_retries {
+        info!(
+            "Trying {} ({}), attempt {}/{}",
+            tier,
+            tier_name,
+            attempt + 1,
+            max_retries + 1
+        );
+
+        // Override model with current tier
+        request.model = tier.clone();
+
+        // Every upstream dispatch holds a global slot until its response
+        // body is released. Queueing for one is not charged to the tier.
+        let limit = config.max_upstream_inflight();
+        let Some(permit) = state
+            .upstream_limiter
+            .acquire(limit, config.upstream_queue_timeout())
+            .await
+        else {
+            warn!("Upstream concurrency cap of {} reached, rejecting", limit);
+            return TierOutcome::Respond(upstream_saturated_response(limit));
+        };
+
+        // Start per-attempt latency timer for EWMA tracking. An attempt that
+        // loses a hedge race is dropped mid-flight; that is not a failure of
+        // the tier and must not count toward opening its circuit.
+        let timer = AttemptTimer::start(&state.ewma_tracker, tier_name)
+            .with_alpha(config.ewma_alpha(tier_name))
+            .cancel_on_drop();
+
+        let attempt_span = tracing::info_span!(
+            "ccr.attempt",
+            tier = %tier_name,
+            route = %tier,
+            attempt = attempt + 1,
+        );
+        let result = try_request(TryRequestArgs {
+            config,
+            registry: &state.transformer_registry,
+            request: &request,
+            tier,
+            tier_name,
+            local_estimate,
+            stream_first_event_timeout: retry_config.stream_first_event_timeout(),
+            stream_idle_timeout: retry_config.stream_idle_timeout(),
+            sse_buffer_size: sse_buffer_size
+                .or(provider.and_then(|p| p.sse_buffer_size))
+                .unwrap_or_else(|| config.sse_buffer_size()),
+            ratelimit_tracker: state.ratelimit_tracker.clone(),
+            debug_capture: state.debug_capture.clone(),
+            request_id,
+            anthropic_beta,
+            openai_passthrough_body: request.openai_passthrough_body.as_ref(),
+        })
+        .instrument(attempt_span)
+        .await
+        .map(|response| hold_until_body_dropped(response, permit));
+        ctx.server_timing.record(
+            tier_name,
+            timer.elapsed_secs(),
+            matches!(&result, Ok(response) if response.status() != StatusCode::TOO_MANY_REQUESTS),
+        );
+        match result {
+            Ok(response) => {
+                request_span.record("tier", tier_name.as_str());
+                request_span.record("fallback", tier_index > 0);
+                if response.status() == StatusCode::TOO_MANY_REQUESTS {
+                    // 429 passthrough is an intentional non-cascading return path,
+                    // but it must be tracked as a failed attempt for EWMA scoring.
+                    timer.finish_failure();
+                    #[cfg(feature = "gp")]
+                    if let (Some(gp_router), Some(plan)) = (state.gp_router.as_ref(), ctx.gp_plan) {
+                        gp_router.record_attempt(plan, tier, attempt, None, config);
+                    }
+                    let total_duration = start.elapsed().as_secs_f64();
+                    sync_ewma_gauge(&state.ewma_tracker);
+                    info!(
+                        "Rate-limit passthrough on {} after {:.2}s",
+                        tier_name, total_duration
+                    );
+                    return TierOutcome::Respond(response);
+                }
+
+                let attempt_duration = timer.finish_success();
+                if let Some(id) = session_id {
+                    state.sticky_sessions.pin(id, tier, sticky.ttl());
+                }
+                #[cfg(feature = "gp")]
+                if let (Some(gp_router), Some(plan)) = (state.gp_router.as_ref(), ctx.gp_plan) {
+                    gp_router.record_attempt(plan, tier, attempt, Some(attempt_duration), config);
+                }
+                let total_duration = start.elapsed().as_secs_f64();
+                let mode = RequestMode::from_stream(client_wants_stream);
+                record_request_with_frontend(tier_name, mode, frontend);
+                record_request_duration_with_frontend(tier_name, mode, total_duration, frontend);
+                sync_ewma_gauge(&state.ewma_tracker);
+                info!(
+                    "Success on {} after {:.2}s (attempt {:.3}s)",
+                    tier_name, total_duration, attempt_duration
+                );
+
+                return TierOutcome::Respond(
+                    client_response(ctx, response, tier_name, forced_non_streaming).await,
+                );
+            }
+            Err(TryRequestError::RateLimited(retry_after)) => {
+                // Note: With 429 pass-through in dispatch, this arm fires
+                // only for edge cases where dispatch still returns RateLimited.
+                timer.finish_failure();
+                #[cfg(feature = "gp")]
+                if let (Some(gp_router), Some(plan)) = (state.gp_router.as_ref(), ctx.gp_plan) {
+                    gp_router.record_attempt(plan, tier, attempt, None, config);
+                }
+                sync_ewma_gauge(&state.ewma_tracker);
+                warn!(
+                    "Rate limited on {} attempt {} (retry-after: {:?})",
+                    tier_name,
+                    attempt + 1,
+                    retry_after
+                );
+                record_failure(tier_name, "rate_limited");
+                record_rate_limit_hit(tier_name);
+                state.ratelimit_tracker.record_429(tier_name, retry_after);
+                record_rate_limit_backoff(tier_name);
+                // Skip remaining retries for this tier - move to next
+                return TierOutcome::RateLimited {
+                    tier_name: tier_name.clone(),
+                    retry_after,
+                };
+            }
+            Err(TryRequestError::Terminal(e)) => {
+                timer.finish_failure();
+                #[cfg(feature = "gp")]
+                if let (Some(gp_router), Some(plan)) = (state.gp_router.as_ref(), ctx.gp_plan) {
+                    gp_router.record_attempt(plan, tier, attempt, None, config);
+                }
+                sync_ewma_gauge(&state.ewma_tracker);
+                warn!("Terminal error from {}, not cascading: {}", tier_name, e);
+                record_failure(tier_name, "terminal");
+                return TierOutcome::Respond(terminal_error_response(&e, tier_name));
+            }
+            Err(TryRequestError::Auth(e)) => {
+                timer.finish_failure();
+                #[cfg(feature = "gp")]
+                if let (Some(gp_router), Some(plan)) = (state.gp_router.as_ref(), ctx.gp_plan) {
+                    gp_router.record_attempt(plan, tier, attempt, None, config);
+                }
+                sync_ewma_gauge(&state.ewma_tracker);
+                warn!("Auth error from {}, skipping its retries: {}", tier_name, e);
+                record_failure(tier_name, "auth");
+                return TierOutcome::Failed(Some(TierError::from_error(tier_name, e)));
+            }
+            Err(TryRequestError::FinishReason(reason, response)) => {
+                timer.finish_failure();
+                #[cfg(feature = "gp")]
+                if let (Some(gp_router), Some(plan)) = (state.gp_router.as_ref(), ctx.gp_plan) {
+                    gp_router.record_attempt(plan, tier, attempt, None, config);
+                }
+                sync_ewma_gauge(&state.ewma_tracker);
+                warn!(
+                    "Finish reason {} from {}, trying the next tier",
+                    reason, tier_name
+                );
+                record_failure(tier_name, "finish_reason");
+                return TierOutcome::Filtered(
+                    TierError::new(tier_name, None, &format!("finish reason {}", reason)),
+                    client_response(ctx, response, tier_name, forced_non_streaming).await,
+                );
+            }
+            Err(TryRequestError::Other(e)) => {
+                timer.finish_failure();
+                #[cfg(feature = "gp")]
+                if let (Some(gp_router), Some(plan)) = (state.gp_router.as_ref(), ctx.gp_plan) {
+                    gp_router.record_attempt(plan, tier, attempt, None, config);
+                }
+                sync_ewma_gauge(&state.ewma_tracker);
+                warn!("Failed {} attempt {}: {}", tier_name, attempt + 1, e);
+                record_failure(tier_name, "request_failed");
+                last_error = Some(TierError::from_error(tier_name, e));
+
+                if attempt < max_retries {
+                    // Get current EWMA for this tier for dynamic backoff scaling
+                    let ewma = state.ewma_tracker.get_latency(tier_name).map(|(e, _)| e);
+                    let backoff = retry_config.backoff_duration_with_ewma(attempt, ewma);
+                    info!(
+                        tier = tier_name,
+                        attempt = attempt + 1,
+                        backoff_ms = backoff.as_millis(),
+                        ewma = ewma
+                            .map(|e| format!("{:.3}s", e))
+                            .unwrap_or_else(|| "N/A".to_string()),
+                        "sleeping before retry"
+                    );
+                    tokio::time::sleep(backoff).await;
+                }
+            }
+        }
+    }
+    TierOutcome::Failed(last_error)
+}
+
+async fn route_messages(
+    state: AppState,
+    headers: HeaderMap,
+    request: AnthropicRequest,
 ) -> Response {
     let _guard = ActiveRequestGuard::new();
     let start = std::time::Instant::now();
-    let config = &state.config;
+    let config = state.config.load();
+    let config = config.as_ref();
     let tiers = config.backend_tiers();
 
     let mut request = request;
@@ -129,6 +929,10 @@ pub async fn handle_messages(
     // Remember original stream flag; per-provider override is applied inside the tier loop
     let client_wants_stream = request.stream.unwrap_or(false);
 
+    // Detect frontend type from headers and request
+    let body_json = serde_json::to_value(&request).unwrap_or_default();
+    let frontend = detect_frontend(&headers, &body_json);
+
     let mut ordered = state.ewma_tracker.sort_tiers_with_config(&tiers, config);
     let mut pinned_prefix_len = 0_usize;
 
@@ -137,6 +941,16 @@ pub async fn handle_messages(
     // (unless ignoreDirect is enabled)
     let requested_model = request.model.clone();
     if !config.router().ignore_direct && requested_model.contains(',') {
+        if let Some(provider) = config.resolve_provider(&requested_model) {
+            let model = requested_model.split(',').nth(1).unwrap_or_default();
+            if provider.upstream_model(model).is_none() {
+                warn!(
+                    "Direct routing: {} is not served by its provider",
+                    requested_model
+                );
+                return unknown_model_response(model, &provider.name);
+            }
+        }
         // Explicit provider,model - find matching tier and prioritize it
         if let Some(pos) = ordered
             .iter()
@@ -149,9 +963,7 @@ pub async fn handle_messages(
             info!("Direct routing: {} moved to front", requested_model);
         } else {
             // Requested model not in tiers - try it directly as a single-tier request
-            let tier_name = state
-                .config
-                .backend_abbreviation_with_config(&requested_model);
+            let tier_name = config.backend_abbreviation_with_config(&requested_model);
             ordered = vec![(requested_model.clone(), tier_name)];
             pinned_prefix_len = pinned_prefix_len.max(1);
             info!("Direct routing: {} (not in tier list)", requested_model);
@@ -163,10 +975,63 @@ pub async fn handle_messages(
         );
     }
 
+    // Sticky sessions: try the tier that last served this conversation first.
+    // A pinned tier that is rate-limited or blacked out is skipped below like
+    // any other, so the request falls back to the normal order.
+    let sticky = &config.router().sticky_sessions;
+    let session_id = headers
+        .get(SESSION_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|id| sticky.enabled && !id.is_empty())
+        .map(str::to_string);
+    if pinned_prefix_len == 0 {
+        if let Some(pinned) = session_id
+            .as_deref()
+            .and_then(|id| state.sticky_sessions.pinned_tier(id))
+        {
+            if let Some(pos) = ordered.iter().position(|(tier, _)| tier == &pinned) {
+                let target = ordered.remove(pos);
+                ordered.insert(0, target);
+                pinned_prefix_len = 1;
+                tracing::debug!(tier = %pinned, "Sticky session: pinned tier moved to front");
+            }
+        }
+    }
+
+    // Per-frontend default: without a direct route or a sticky pin, the
+    // frontend's default route goes first in place of the global default.
+    if pinned_prefix_len == 0 {
+        if let Some(route) = config.router().frontend_defaults.get(&frontend) {
+            if let Some(pos) = ordered.iter().position(|(tier, _)| tier == route) {
+                let target = ordered.remove(pos);
+                ordered.insert(0, target);
+            } else {
+                let tier_name = config.backend_abbreviation_with_config(route);
+                ordered.insert(0, (route.clone(), tier_name));
+            }
+            pinned_prefix_len = 1;
+            tracing::debug!(?frontend, tier = %route, "Frontend default moved to front");
+        }
+    }
+
+    // A tier native Responses forwarding already failed on is not retried.
+    if let Some(excluded) = request.excluded_tier.as_deref() {
+        if let Some(pos) = ordered.iter().position(|(tier, _)| tier == excluded) {
+            ordered.remove(pos);
+            if pos < pinned_prefix_len {
+                pinned_prefix_len -= 1;
+            }
+        }
+    }
+
     // Check for web search
-    if state.config.router().web_search.enabled && needs_web_search(&request) {
-        strip_search_tags(&mut request);
-        if let Some(ref search_provider) = state.config.router().web_search.search_provider {
+    let web_search = &config.router().web_search;
+    if web_search.enabled && needs_web_search(&request, web_search) {
+        take_web_search_flag(&mut request);
+        if web_search.strip_tags {
+            strip_search_tags(&mut request, web_search);
+        }
+        if let Some(ref search_provider) = web_search.search_provider {
             // Prepend search provider as first tier
             ordered.insert(0, (search_provider.clone(), "search".to_string()));
             #[allow(unused_assignments)]
@@ -194,17 +1059,56 @@ pub async fn handle_messages(
         ordered = plan.ordered.clone();
     }
 
-    // Detect frontend type from headers and request
-    let body_json = serde_json::to_value(&request).unwrap_or_default();
-    let frontend = detect_frontend(&headers, &body_json);
+    let now = chrono::Utc::now();
+
+    // Strict mode: drop unhealthy tiers up front, and fail fast rather than
+    // cascading into tiers that are known to be down.
+    if config.router().require_healthy.enabled {
+        let total = ordered.len();
+        let mut rate_limited = CascadeFailures::default();
+        ordered.retain(|(tier, tier_name)| {
+            match tier_health(&state, config, tier, tier_name, now) {
+                TierHealth::Healthy => true,
+                TierHealth::RateLimited => {
+                    let status = state.ratelimit_tracker.status(tier_name);
+                    rate_limited
+                        .rate_limited(tier_name, status.backoff_remaining.or(status.reset_in));
+                    false
+                }
+                TierHealth::Unhealthy => {
+                    rate_limited.saw_non_rate_limit_failure = true;
+                    false
+                }
+            }
+        });
+        if ordered.is_empty() {
+            // Tiers that are merely rate limited will be back; answer as the
+            // cascade would, with a 429 and when to retry.
+            if rate_limited.saw_rate_limit && !rate_limited.saw_non_rate_limit_failure {
+                info!(
+                    retry_after = ?rate_limited.retry_after_hint,
+                    "All tiers rate limited (requireHealthy)"
+                );
+                return rate_limit_exhausted_response(
+                    rate_limited.retry_after_hint,
+                    rate_limited.last_rate_limited_tier.as_deref(),
+                );
+            }
+            warn!(
+                tiers = total,
+                "No healthy tiers, failing fast (requireHealthy)"
+            );
+            return no_healthy_tiers_response(total);
+        }
+    }
+
+    let request_span = tracing::Span::current();
+    request_span.record("frontend", tracing::field::debug(frontend));
     info!(
         "Incoming request for model: {} (frontend: {:?})",
         request.model, frontend
     );
-    let mut saw_rate_limit = false;
-    let mut saw_non_rate_limit_failure = false;
-    let mut retry_after_hint: Option<std::time::Duration> = None;
-    let mut last_rate_limited_tier: Option<String> = None;
+    let mut failures = CascadeFailures::default();
 
     // Serialize messages to JSON values once for pre-request token audit
     let msg_values: Vec<serde_json::Value> = request
@@ -213,196 +1117,149 @@ pub async fn handle_messages(
         .filter_map(|m| serde_json::to_value(m).ok())
         .collect();
     let tool_values: Option<Vec<serde_json::Value>> = request.tools.clone();
+    let warnings = RequestWarnings::for_request(&request);
+    let server_timing = ServerTiming::new(config.router().server_timing_header);
+
+    let ctx = CascadeCtx {
+        state: &state,
+        config,
+        request: &request,
+        client_wants_stream,
+        msg_values: &msg_values,
+        tool_values: tool_values.as_deref(),
+        frontend,
+        start,
+        request_span: &request_span,
+        session_id: session_id.as_deref(),
+        request_id: request_id(&headers),
+        anthropic_beta: headers
+            .get("anthropic-beta")
+            .and_then(|value| value.to_str().ok()),
+        sse_buffer_size: requested_sse_buffer_size(&headers, config),
+        warnings: &warnings,
+        server_timing: &server_timing,
+        #[cfg(feature = "gp")]
+        gp_plan: gp_plan.as_ref(),
+    };
 
-    // Try each tier with retries
-    for (tier, tier_name) in ordered.iter() {
-        let honor_remaining = config
-            .resolve_provider(tier)
-            .map(|p| p.honor_ratelimit_headers)
-            .unwrap_or(true);
-        if state
-            .ratelimit_tracker
-            .should_skip_tier(tier_name, honor_remaining)
-        {
-            saw_rate_limit = true;
-            last_rate_limited_tier = Some(tier_name.clone());
-            tracing::debug!(tier = %tier_name, "Skipping rate-limited tier");
-            continue;
-        }
-        // Pre-request token audit: estimate input tokens before dispatching
-        let local_estimate = record_pre_request_tokens(
-            tier_name,
-            &msg_values,
-            request.system.as_ref(),
-            tool_values.as_deref(),
-        );
-
-        // Per-provider streaming decision: allow_streaming bypasses forceNonStreaming
-        let provider_allows_streaming = config
-            .resolve_provider(tier)
-            .map(|p| p.allow_streaming)
-            .unwrap_or(false);
-        let forced_non_streaming =
-            config.router().force_non_streaming && !provider_allows_streaming;
-        if client_wants_stream && forced_non_streaming {
-            request.stream = Some(false);
-        } else {
-            request.stream = Some(client_wants_stream);
+    // Try each tier with retries. Normally one tier runs at a time; with
+    // hedging, a tier still running after `hedge_after_ms` is raced by the
+    // next one, and returning drops (cancels) whichever attempts are left.
+    // Each attempt is tagged with its tier and whether it was a hedge, so a
+    // race can report its winner. While attempts race, only a success wins:
+    // an error reply (429 passthrough, saturation, terminal error) is held
+    // and returned only if the others fail too.
+    let hedge_after = config.router().hedging.hedge_after();
+    let mut candidates = ordered.iter().enumerate();
+    let mut candidates_left = true;
+    let mut raced = false;
+    let mut in_flight = FuturesUnordered::new();
+    let mut held_error: Option<Response> = None;
+    // The last response failed over for its finish reason, returned when
+    // every tier after it fails too.
+    let mut filtered: Option<Response> = None;
+    loop {
+        if in_flight.is_empty() {
+            if let Some(mut response) = held_error.take() {
+                server_timing.insert_header(&mut response);
+                return response;
+            }
+            match next_candidate(&mut candidates, &mut failures, &state, config, now) {
+                Some((tier_index, tier, tier_name)) => {
+                    in_flight.push(
+                        run_tier(&ctx, tier_index, tier, tier_name, None)
+                            .map(move |outcome| (tier_name, false, outcome))
+                            .boxed(),
+                    );
+                }
+                None => break,
+            }
         }
 
-        let retry_config = config.get_tier_retry(tier_name);
-        let max_retries = retry_config.max_retries;
-
-        for attempt in 0..=max_retries {
-            info!(
-                "Trying {} ({}), attempt {}/{}",
-                tier,
-                tier_name,
-                attempt + 1,
-                max_retries + 1
-            );
-
-            // Override model with current tier
-            request.model = tier.clone();
-
-            // Start per-attempt latency timer for EWMA tracking
-            let timer = AttemptTimer::start(&state.ewma_tracker, tier_name);
-
-            match try_request(TryRequestArgs {
-                config,
-                registry: &state.transformer_registry,
-                request: &request,
-                tier,
-                tier_name,
-                local_estimate,
-                stream_first_event_timeout: retry_config.stream_first_event_timeout(),
-                stream_idle_timeout: retry_config.stream_idle_timeout(),
-                ratelimit_tracker: state.ratelimit_tracker.clone(),
-                debug_capture: state.debug_capture.clone(),
-                openai_passthrough_body: request.openai_passthrough_body.as_ref(),
-            })
-            .await
-            {
-                Ok(response) => {
-                    if response.status() == StatusCode::TOO_MANY_REQUESTS {
-                        // 429 passthrough is an intentional non-cascading return path,
-                        // but it must be tracked as a failed attempt for EWMA scoring.
-                        timer.finish_failure();
-                        #[cfg(feature = "gp")]
-                        if let (Some(gp_router), Some(plan)) =
-                            (state.gp_router.as_ref(), gp_plan.as_ref())
-                        {
-                            gp_router.record_attempt(plan, tier, attempt, None, config);
-                        }
-                        let total_duration = start.elapsed().as_secs_f64();
-                        sync_ewma_gauge(&state.ewma_tracker);
+        let hedge_timer = async {
+            match hedge_after.filter(|_| candidates_left) {
+                Some(delay) => tokio::time::sleep(delay).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::select! {
+            Some((tier_name, hedged, outcome)) = in_flight.next() => match outcome {
+                TierOutcome::Respond(response) => {
+                    if !response.status().is_success() && !in_flight.is_empty() {
                         info!(
-                            "Rate-limit passthrough on {} after {:.2}s",
-                            tier_name, total_duration
-                        );
-                        return response;
-                    }
-
-                    let attempt_duration = timer.finish_success();
-                    #[cfg(feature = "gp")]
-                    if let (Some(gp_router), Some(plan)) =
-                        (state.gp_router.as_ref(), gp_plan.as_ref())
-                    {
-                        gp_router.record_attempt(
-                            plan,
-                            tier,
-                            attempt,
-                            Some(attempt_duration),
-                            config,
+                            tier = %tier_name,
+                            status = %response.status(),
+                            "Raced attempt returned an error, waiting on the others"
                         );
+                        held_error.get_or_insert(response);
+                        candidates_left = false;
+                        continue;
                     }
-                    let total_duration = start.elapsed().as_secs_f64();
-                    record_request_with_frontend(tier_name, frontend);
-                    record_request_duration_with_frontend(tier_name, total_duration, frontend);
-                    sync_ewma_gauge(&state.ewma_tracker);
-                    info!(
-                        "Success on {} after {:.2}s (attempt {:.3}s)",
-                        tier_name, total_duration, attempt_duration
-                    );
-
-                    // If client wanted streaming but we forced non-streaming for this provider,
-                    // wrap the JSON response as pseudo-SSE so Claude CLI can parse it.
-                    if client_wants_stream && forced_non_streaming {
-                        return streaming::wrap_json_response_as_sse(response).await;
+                    if raced {
+                        info!(tier = %tier_name, hedged, "Hedge race won by {}", tier_name);
+                        record_hedge_win(tier_name, hedged);
                     }
-
+                    let mut response = response;
+                    server_timing.insert_header(&mut response);
                     return response;
                 }
-                Err(TryRequestError::RateLimited(retry_after)) => {
-                    // Note: With 429 pass-through in dispatch, this arm fires
-                    // only for edge cases where dispatch still returns RateLimited.
-                    timer.finish_failure();
-                    #[cfg(feature = "gp")]
-                    if let (Some(gp_router), Some(plan)) =
-                        (state.gp_router.as_ref(), gp_plan.as_ref())
-                    {
-                        gp_router.record_attempt(plan, tier, attempt, None, config);
-                    }
-                    saw_rate_limit = true;
-                    last_rate_limited_tier = Some(tier_name.clone());
-                    retry_after_hint = match (retry_after_hint, retry_after) {
-                        (Some(current), Some(candidate)) => Some(current.max(candidate)),
-                        (None, some) => some,
-                        (some, None) => some,
-                    };
-                    sync_ewma_gauge(&state.ewma_tracker);
-                    warn!(
-                        "Rate limited on {} attempt {} (retry-after: {:?})",
-                        tier_name,
-                        attempt + 1,
-                        retry_after
-                    );
-                    record_failure(tier_name, "rate_limited");
-                    record_rate_limit_hit(tier_name);
-                    state.ratelimit_tracker.record_429(tier_name, retry_after);
-                    record_rate_limit_backoff(tier_name);
-                    // Skip remaining retries for this tier - move to next
-                    break;
+                TierOutcome::RateLimited {
+                    tier_name,
+                    retry_after,
+                } => failures.rate_limited(&tier_name, retry_after),
+                TierOutcome::Failed(error) => failures.failed(error),
+                TierOutcome::Filtered(error, response) => {
+                    failures.failed(Some(error));
+                    filtered = Some(response);
                 }
-                Err(TryRequestError::Other(e)) => {
-                    timer.finish_failure();
-                    #[cfg(feature = "gp")]
-                    if let (Some(gp_router), Some(plan)) =
-                        (state.gp_router.as_ref(), gp_plan.as_ref())
-                    {
-                        gp_router.record_attempt(plan, tier, attempt, None, config);
-                    }
-                    saw_non_rate_limit_failure = true;
-                    sync_ewma_gauge(&state.ewma_tracker);
-                    warn!("Failed {} attempt {}: {}", tier_name, attempt + 1, e);
-                    record_failure(tier_name, "request_failed");
-
-                    if attempt < max_retries {
-                        // Get current EWMA for this tier for dynamic backoff scaling
-                        let ewma = state.ewma_tracker.get_latency(tier_name).map(|(e, _)| e);
-                        let backoff = retry_config.backoff_duration_with_ewma(attempt, ewma);
-                        info!(
-                            tier = tier_name,
-                            attempt = attempt + 1,
-                            backoff_ms = backoff.as_millis(),
-                            ewma = ewma
-                                .map(|e| format!("{:.3}s", e))
-                                .unwrap_or_else(|| "N/A".to_string()),
-                            "sleeping before retry"
+            },
+            _ = hedge_timer => {
+                let Some(slot) = HedgeSlot::try_acquire(
+                    &state.hedges_in_flight,
+                    config.router().hedging.max_concurrent,
+                ) else {
+                    tracing::debug!("Hedge cap reached, waiting on in-flight tiers");
+                    continue;
+                };
+                match next_candidate(&mut candidates, &mut failures, &state, config, now) {
+                    Some((tier_index, tier, tier_name)) => {
+                        info!(tier = %tier_name, "Hedging: racing {} against slower tier(s)", tier);
+                        raced = true;
+                        in_flight.push(
+                            run_tier(&ctx, tier_index, tier, tier_name, Some(slot))
+                                .map(move |outcome| (tier_name, true, outcome))
+                                .boxed(),
                         );
-                        tokio::time::sleep(backoff).await;
                     }
+                    None => candidates_left = false,
                 }
             }
         }
     }
 
+    if let Some(mut response) = filtered {
+        info!("No tier did better than a filtered response, returning it");
+        server_timing.insert_header(&mut response);
+        return response;
+    }
+
+    let CascadeFailures {
+        saw_rate_limit,
+        saw_non_rate_limit_failure,
+        retry_after_hint,
+        last_rate_limited_tier,
+        tier_errors,
+    } = failures;
+
     if saw_rate_limit && !saw_non_rate_limit_failure {
         info!(
             retry_after = ?retry_after_hint,
             "All candidate tiers exhausted due to rate limits"
         );
-        return rate_limit_exhausted_response(retry_after_hint, last_rate_limited_tier.as_deref());
+        let mut response =
+            rate_limit_exhausted_response(retry_after_hint, last_rate_limited_tier.as_deref());
+        server_timing.insert_header(&mut response);
+        return response;
     }
 
     // All tiers exhausted due to non-rate-limit failures (5xx, timeouts, etc.).
@@ -420,10 +1277,13 @@ pub async fn handle_messages(
                 ordered.len(),
                 total_attempts
             ),
-            "code": "service_unavailable"
+            "code": "service_unavailable",
+            "tier_errors": tier_errors
         }
     });
-    (StatusCode::SERVICE_UNAVAILABLE, Json(error_resp)).into_response()
+    let mut response = (StatusCode::SERVICE_UNAVAILABLE, Json(error_resp)).into_response();
+    server_timing.insert_header(&mut response);
+    response
 }
 
 // ============================================================================
@@ -432,8 +1292,8 @@ pub async fn handle_messages(
 
 /// List all configured presets.
 pub async fn list_presets(State(state): State<AppState>) -> impl IntoResponse {
-    let presets: Vec<_> = state
-        .config
+    let config = state.config.load();
+    let presets: Vec<_> = config
         .presets
         .iter()
         .map(|(name, cfg)| {
@@ -442,6 +1302,8 @@ pub async fn list_presets(State(state): State<AppState>) -> impl IntoResponse {
                 "route": cfg.route,
                 "max_tokens": cfg.max_tokens,
                 "temperature": cfg.temperature,
+                "tool_choice": cfg.tool_choice,
+                "warm": cfg.warm,
             })
         })
         .collect();
@@ -461,20 +1323,31 @@ pub async fn list_models(State(state): State<AppState>) -> impl IntoResponse {
 
     let mut seen = BTreeSet::new();
     let mut data = Vec::new();
+    let mut models = Vec::new();
 
-    for provider in state.config.providers() {
+    let config = state.config.load();
+    for provider in config.providers() {
         for model in &provider.models {
+            let metadata = provider.metadata_for_model(model);
             let ids = [format!("{},{}", provider.name, model), model.to_string()];
             for id in ids {
                 if !seen.insert(id.clone()) {
                     continue;
                 }
-                data.push(serde_json::json!({
+                let mut entry = serde_json::json!({
                     "id": id,
                     "object": "model",
                     "created": created,
                     "owned_by": provider.name,
-                }));
+                });
+                if let (Some(fields), Some(serde_json::Value::Object(known))) = (
+                    entry.as_object_mut(),
+                    metadata.and_then(|m| serde_json::to_value(m).ok()),
+                ) {
+                    fields.extend(known);
+                }
+                data.push(entry);
+                models.push(models_dev_record(&id, model, &provider.name, metadata));
             }
         }
     }
@@ -482,19 +1355,59 @@ pub async fn list_models(State(state): State<AppState>) -> impl IntoResponse {
     Json(serde_json::json!({
         "object": "list",
         "data": data,
-        // Codex currently expects a `models` field in `/v1/models` responses.
-        // Keep this present for compatibility even when rich model metadata is unavailable.
-        "models": []
+        // Codex reads the richer `models` records; fields are omitted when
+        // the config has no metadata for a model.
+        "models": models
     }))
 }
 
+/// A `/v1/models` entry in the models.dev shape (`limit`, `tool_call`,
+/// `attachment`), leaving out whatever is unknown.
+fn models_dev_record(
+    id: &str,
+    model: &str,
+    provider: &str,
+    metadata: Option<&crate::config::ModelMetadata>,
+) -> serde_json::Value {
+    let mut record = serde_json::json!({
+        "id": id,
+        "name": model,
+        "provider": provider,
+    });
+    let Some(metadata) = metadata else {
+        return record;
+    };
+    let mut limit = serde_json::Map::new();
+    if let Some(context) = metadata.context_length {
+        limit.insert("context".into(), context.into());
+    }
+    if let Some(output) = metadata.max_output_tokens {
+        limit.insert("output".into(), output.into());
+    }
+    if !limit.is_empty() {
+        record["limit"] = serde_json::Value::Object(limit);
+    }
+    if let Some(tools) = metadata.supports_tools {
+        record["tool_call"] = tools.into();
+    }
+    if let Some(vision) = metadata.supports_vision {
+        record["attachment"] = vision.into();
+    }
+    if let Some(reasoning) = metadata.supports_reasoning {
+        record["reasoning"] = reasoning.into();
+    }
+    record
+}
+
 /// Handle messages via a named preset.
 pub async fn handle_preset_messages(
     State(state): State<AppState>,
     Path(preset_name): Path<String>,
+    headers: HeaderMap,
     Json(mut request): Json<AnthropicRequest>,
 ) -> Response {
-    let preset = match state.config.get_preset(&preset_name) {
+    let config = state.config.load();
+    let preset = match config.get_preset(&preset_name) {
         Some(p) => p,
         None => {
             return (
@@ -512,12 +1425,45 @@ pub async fn handle_preset_messages(
     if let Some(temp) = preset.temperature {
         request.temperature = Some(temp);
     }
+    if let Some(ref tool_choice) = preset.tool_choice {
+        request.tool_choice = Some(tool_choice.clone());
+    }
+    if let Some(ref system) = preset.system {
+        request.system = Some(prepend_system_prompt(system, request.system.take()));
+    }
 
     // Force route to preset's tier
     request.model = preset.route.clone();
 
-    // Delegate to normal handler
-    handle_messages(State(state), HeaderMap::new(), Json(request)).await
+    // Delegate to normal handler, keeping streaming clients alive while the
+    // upstream works on the preset's prompt.
+    let keepalive = preset
+        .keepalive_interval()
+        .filter(|_| request.stream.unwrap_or(false));
+    let response = handle_anthropic_request(state, headers, request);
+    match keepalive {
+        Some(period) => with_initial_keepalive(response, period).await,
+        None => response.await,
+    }
+}
+
+/// Put a preset's system prompt ahead of the request's, keeping it first so
+/// the prompt prefix matches the preset's warm-up request.
+fn prepend_system_prompt(
+    preset_system: &str,
+    system: Option<serde_json::Value>,
+) -> serde_json::Value {
+    let block = serde_json::json!({"type": "text", "text": preset_system});
+    match system {
+        Some(serde_json::Value::Array(mut blocks)) => {
+            blocks.insert(0, block);
+            serde_json::Value::Array(blocks)
+        }
+        Some(serde_json::Value::String(text)) if !text.is_empty() => {
+            serde_json::json!([block, {"type": "text", "text": text}])
+        }
+        _ => serde_json::Value::String(preset_system.to_string()),
+    }
 }
 
 // ============================================================================
@@ -532,6 +1478,31 @@ mod tests {
     use super::translate_response::*;
     use super::*;
 
+    #[test]
+    fn preset_system_prompt_goes_first() {
+        assert_eq!(
+            prepend_system_prompt("preset", None),
+            serde_json::json!("preset")
+        );
+        assert_eq!(
+            prepend_system_prompt("preset", Some(serde_json::json!("client"))),
+            serde_json::json!([
+                {"type": "text", "text": "preset"},
+                {"type": "text", "text": "client"}
+            ])
+        );
+        assert_eq!(
+            prepend_system_prompt(
+                "preset",
+                Some(serde_json::json!([{"type": "text", "text": "client"}]))
+            ),
+            serde_json::json!([
+                {"type": "text", "text": "preset"},
+                {"type": "text", "text": "client"}
+            ])
+        );
+    }
+
     #[test]
     fn test_normalize_string_content() {
         let content = serde_json::Value::String("Hello world".to_string());
@@ -565,13 +1536,22 @@ mod tests {
             system: Some(serde_json::Value::String("You are Claude.".to_string())),
             max_tokens: Some(1000),
             temperature: Some(0.7),
+            top_p: None,
             stream: Some(false),
             tools: None,
+            tool_choice: None,
+            metadata: None,
             openai_passthrough_body: None,
+            excluded_tier: None,
+            stop_sequences: None,
+            modalities: None,
+            audio: None,
+            unrecognized: serde_json::Map::new(),
         };
 
-        let openai_req = translate_request_anthropic_to_openai(&request, "gpt-4");
+        let openai_req = translate_request_anthropic_to_openai(&request, "gpt-4", None);
 
+        assert!(openai_req.stream_options.is_none());
         assert_eq!(openai_req.messages.len(), 2);
         assert_eq!(openai_req.messages[0].role, "system");
         assert_eq!(
@@ -585,6 +1565,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_stop_sequences_round_trip() {
+        let request: AnthropicRequest = serde_json::from_value(serde_json::json!({
+            "model": "mock,test-model",
+            "messages": [{"role": "user", "content": "Count to ten."}],
+            "stop_sequences": ["5", "END"]
+        }))
+        .unwrap();
+        assert!(request.unrecognized.is_empty());
+
+        // Anthropic path: forwarded under its own name.
+        let anthropic_body = serde_json::to_value(&request).unwrap();
+        assert_eq!(
+            anthropic_body["stop_sequences"],
+            serde_json::json!(["5", "END"])
+        );
+
+        // OpenAI path: becomes `stop`.
+        let openai_req = translate_request_anthropic_to_openai(&request, "gpt-4", None);
+        let openai_body = serde_json::to_value(&openai_req).unwrap();
+        assert_eq!(openai_body["stop"], serde_json::json!(["5", "END"]));
+        assert!(openai_body.get("stop_sequences").is_none());
+    }
+
     #[test]
     fn test_translate_request_reasoning_model() {
         let request = AnthropicRequest {
@@ -597,17 +1601,79 @@ mod tests {
             system: None,
             max_tokens: Some(4000),
             temperature: None,
+            top_p: None,
             stream: Some(true),
             tools: None,
+            tool_choice: None,
+            metadata: None,
             openai_passthrough_body: None,
+            excluded_tier: None,
+            stop_sequences: None,
+            modalities: None,
+            audio: None,
+            unrecognized: serde_json::Map::new(),
         };
 
-        let openai_req = translate_request_anthropic_to_openai(&request, "deepseek-reasoner");
+        let openai_req = translate_request_anthropic_to_openai(&request, "deepseek-reasoner", None);
 
         // Should use max_completion_tokens for reasoning models
         assert!(openai_req.max_tokens.is_none());
         assert_eq!(openai_req.max_completion_tokens, Some(4000));
         assert_eq!(openai_req.reasoning_effort, Some("high".to_string()));
+        // Streamed requests ask for the final usage chunk.
+        assert_eq!(
+            openai_req.stream_options,
+            Some(OpenAIStreamOptions {
+                include_usage: true
+            })
+        );
+    }
+
+    #[test]
+    fn test_translate_request_reasoning_floor() {
+        let request = AnthropicRequest {
+            model: "deepseek-reasoner".to_string(),
+            messages: vec![Message {
+                role: "user".to_string(),
+                content: serde_json::Value::String("Solve this.".to_string()),
+                tool_call_id: None,
+            }],
+            system: None,
+            max_tokens: Some(256),
+            temperature: None,
+            top_p: None,
+            stream: Some(false),
+            tools: None,
+            tool_choice: None,
+            metadata: None,
+            openai_passthrough_body: None,
+            excluded_tier: None,
+            stop_sequences: None,
+            modalities: None,
+            audio: None,
+            unrecognized: serde_json::Map::new(),
+        };
+
+        let bumped =
+            translate_request_anthropic_to_openai(&request, "deepseek-reasoner", Some(8192));
+        assert_eq!(bumped.max_completion_tokens, Some(8192));
+
+        // Budgets already above the floor and non-reasoning models are untouched.
+        let above = translate_request_anthropic_to_openai(&request, "deepseek-reasoner", Some(128));
+        assert_eq!(above.max_completion_tokens, Some(256));
+        let chat = translate_request_anthropic_to_openai(&request, "gpt-4", Some(8192));
+        assert_eq!(chat.max_tokens, Some(256));
+        assert!(chat.max_completion_tokens.is_none());
+
+        // A request without max_tokens gets the floor too.
+        let unset = AnthropicRequest {
+            max_tokens: None,
+            ..request
+        };
+        let bumped = translate_request_anthropic_to_openai(&unset, "deepseek-reasoner", Some(8192));
+        assert_eq!(bumped.max_completion_tokens, Some(8192));
+        let chat = translate_request_anthropic_to_openai(&unset, "gpt-4", Some(8192));
+        assert!(chat.max_tokens.is_none());
     }
 
     #[test]
@@ -624,6 +1690,7 @@ mod tests {
                     content: Some(serde_json::Value::String("The answer is 42.".to_string())),
                     reasoning_content: Some("Let me think...".to_string()),
                     tool_calls: None,
+                    audio: None,
                 },
                 finish_reason: Some("stop".to_string()),
             }],
@@ -631,6 +1698,7 @@ mod tests {
                 prompt_tokens: 10,
                 completion_tokens: 20,
                 prompt_tokens_details: None,
+                context_cached_tokens: None,
             }),
         };
 
@@ -648,6 +1716,47 @@ mod tests {
         assert_eq!(anthropic_resp.usage.output_tokens, 20);
     }
 
+    #[test]
+    fn test_translate_response_with_object_tool_arguments() {
+        // GLM sometimes sends `arguments` as an object rather than a string.
+        let openai_resp: OpenAIResponse = serde_json::from_value(serde_json::json!({
+            "id": "resp_glm",
+            "object": "chat.completion",
+            "created": 1234567890,
+            "model": "glm-5.1",
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "content": null,
+                    "tool_calls": [{
+                        "id": "call_1",
+                        "type": "function",
+                        "function": {"name": "read_file", "arguments": {"path": "src/lib.rs"}}
+                    }]
+                },
+                "finish_reason": "tool_calls"
+            }]
+        }))
+        .expect("object arguments should parse");
+
+        let anthropic_resp = translate_response_openai_to_anthropic(openai_resp, "glm-5.1");
+        match &anthropic_resp.content[0] {
+            AnthropicContentBlock::ToolUse { name, input, .. } => {
+                assert_eq!(name, "read_file");
+                assert_eq!(input, &serde_json::json!({"path": "src/lib.rs"}));
+            }
+            other => panic!("expected tool_use, got {other:?}"),
+        }
+
+        let delta: OpenAIStreamToolFunction =
+            serde_json::from_value(serde_json::json!({"arguments": {"a": 1}})).unwrap();
+        assert_eq!(delta.arguments.as_deref(), Some(r#"{"a":1}"#));
+        let delta: OpenAIStreamToolFunction =
+            serde_json::from_value(serde_json::json!({"arguments": null})).unwrap();
+        assert_eq!(delta.arguments, None);
+    }
+
     #[test]
     fn test_translate_stream_chunk() {
         let chunk = OpenAIStreamChunk {
@@ -707,6 +1816,60 @@ mod tests {
         assert!(events.is_empty());
     }
 
+    #[test]
+    fn test_translate_stream_with_thinking_orders_blocks() {
+        let chunk =
+            |content: Option<&str>, reasoning: Option<&str>, tool: bool| OpenAIStreamChunk {
+                id: "chunk_1".to_string(),
+                object: "chat.completion.chunk".to_string(),
+                created: 1234567890,
+                model: "MiniMax-M2.7".to_string(),
+                choices: vec![OpenAIStreamChoice {
+                    index: 0,
+                    delta: OpenAIDelta {
+                        role: None,
+                        content: content.map(str::to_string),
+                        reasoning_content: reasoning.map(str::to_string),
+                        tool_calls: tool.then(|| {
+                            vec![OpenAIStreamToolCall {
+                                index: 0,
+                                id: Some("call_abc".to_string()),
+                                function: Some(OpenAIStreamToolFunction {
+                                    name: Some("bash".to_string()),
+                                    arguments: None,
+                                }),
+                            }]
+                        }),
+                    },
+                    finish_reason: None,
+                }],
+                usage: None,
+            };
+
+        let mut state = StreamTranslationState::with_thinking();
+        let events = translate_stream_chunk_to_anthropic(
+            &chunk(None, Some("Analyzing..."), false),
+            &mut state,
+        );
+        assert_eq!(events[0].event_type, "message_start");
+        assert_eq!(
+            events[1].content_block.as_ref().unwrap()["type"],
+            "thinking"
+        );
+        assert_eq!(events[2].delta.as_ref().unwrap()["type"], "thinking_delta");
+        assert_eq!(events[2].index, Some(0));
+
+        let events =
+            translate_stream_chunk_to_anthropic(&chunk(Some("Done."), None, false), &mut state);
+        assert_eq!(events[0].event_type, "content_block_stop");
+        assert_eq!(events[0].index, Some(0));
+        assert_eq!(events[1].content_block.as_ref().unwrap()["type"], "text");
+        assert_eq!(events[2].index, Some(1));
+
+        translate_stream_chunk_to_anthropic(&chunk(None, None, true), &mut state);
+        assert_eq!(state.active_tool_indices, vec![2]);
+    }
+
     #[test]
     fn test_translate_stream_tool_calls() {
         let mut state = StreamTranslationState::new();
@@ -855,12 +2018,20 @@ mod tests {
             system: None,
             max_tokens: Some(1000),
             temperature: None,
+            top_p: None,
             stream: None,
             tools: None,
+            tool_choice: None,
+            metadata: None,
             openai_passthrough_body: None,
+            excluded_tier: None,
+            stop_sequences: None,
+            modalities: None,
+            audio: None,
+            unrecognized: serde_json::Map::new(),
         };
 
-        let openai_req = translate_request_anthropic_to_openai(&request, "gpt-4");
+        let openai_req = translate_request_anthropic_to_openai(&request, "gpt-4", None);
 
         // Should have: user, assistant, tool messages
         let assistant_msg = openai_req
@@ -911,12 +2082,20 @@ mod tests {
             system: None,
             max_tokens: Some(1000),
             temperature: None,
+            top_p: None,
             stream: Some(false),
             tools: None,
+            tool_choice: None,
+            metadata: None,
             openai_passthrough_body: None,
+            excluded_tier: None,
+            stop_sequences: None,
+            modalities: None,
+            audio: None,
+            unrecognized: serde_json::Map::new(),
         };
 
-        let openai_req = translate_request_anthropic_to_openai(&request, "deepseek-reasoner");
+        let openai_req = translate_request_anthropic_to_openai(&request, "deepseek-reasoner", None);
         assert_eq!(openai_req.messages.len(), 1);
         let msg = &openai_req.messages[0];
         assert_eq!(msg.role, "assistant");
@@ -952,12 +2131,20 @@ mod tests {
             system: None,
             max_tokens: Some(1000),
             temperature: None,
+            top_p: None,
             stream: Some(false),
             tools: None,
+            tool_choice: None,
+            metadata: None,
             openai_passthrough_body: None,
+            excluded_tier: None,
+            stop_sequences: None,
+            modalities: None,
+            audio: None,
+            unrecognized: serde_json::Map::new(),
         };
 
-        let openai_req = translate_request_anthropic_to_openai(&request, "deepseek-reasoner");
+        let openai_req = translate_request_anthropic_to_openai(&request, "deepseek-reasoner", None);
         let tool_msg = openai_req
             .messages
             .iter()
@@ -990,6 +2177,7 @@ mod tests {
             system: None,
             max_tokens: Some(512),
             temperature: None,
+            top_p: None,
             stream: Some(false),
             tools: None,
             tool_choice: None,
@@ -1006,6 +2194,241 @@ mod tests {
         assert_eq!(tool_msg.tool_call_id.as_deref(), Some("call_abc"));
     }
 
+    fn config_with_sse_override(range: Option<serde_json::Value>) -> crate::config::Config {
+        let mut raw = serde_json::json!({
+            "Providers": [{"name": "a", "api_base_url": "http://a", "api_key": "x", "models": ["m"]}],
+            "Router": {"default": "a,m"}
+        });
+        if let Some(range) = range {
+            raw["SSE_BUFFER_OVERRIDE"] = range;
+        }
+        let temp = tempfile::NamedTempFile::new().expect("temp config file");
+        std::fs::write(temp.path(), raw.to_string()).expect("write config file");
+        crate::config::Config::from_file(temp.path().to_str().expect("config path"))
+            .expect("load Config from file")
+    }
+
+    #[test]
+    fn test_requested_sse_buffer_size_is_clamped_to_override_range() {
+        let headers = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert("x-ccr-sse-buffer", value.parse().unwrap());
+            headers
+        };
+        let config = config_with_sse_override(Some(serde_json::json!({"min": 4, "max": 256})));
+
+        assert_eq!(requested_sse_buffer_size(&headers("64"), &config), Some(64));
+        assert_eq!(requested_sse_buffer_size(&headers("1"), &config), Some(4));
+        assert_eq!(
+            requested_sse_buffer_size(&headers("100000"), &config),
+            Some(256)
+        );
+        assert_eq!(requested_sse_buffer_size(&headers("big"), &config), None);
+        assert_eq!(requested_sse_buffer_size(&HeaderMap::new(), &config), None);
+
+        let ungated = config_with_sse_override(None);
+        assert_eq!(requested_sse_buffer_size(&headers("64"), &ungated), None);
+    }
+
+    #[test]
+    fn test_hoist_system_messages_merges_into_top_level_system() {
+        let mut request = serde_json::json!({
+            "model": "claude-sonnet-4-6",
+            "system": "Base prompt",
+            "messages": [
+                {"role": "system", "content": "Be terse."},
+                {"role": "user", "content": "Hello"},
+                {"role": "system", "content": [{"type": "text", "text": "No emoji."}]}
+            ]
+        });
+
+        hoist_system_messages(&mut request, crate::config::SystemMessageMode::Merge);
+
+        assert_eq!(request["system"], "Base prompt\n\nBe terse.\n\nNo emoji.");
+        let messages = request["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0]["role"], "user");
+    }
+
+    #[test]
+    fn test_hoist_system_messages_user_mode_keeps_leading_user_turn() {
+        let mut request = serde_json::json!({
+            "model": "claude-sonnet-4-6",
+            "messages": [
+                {"role": "system", "content": "Be terse."},
+                {"role": "user", "content": "Hello"}
+            ]
+        });
+
+        hoist_system_messages(&mut request, crate::config::SystemMessageMode::User);
+
+        assert!(request.get("system").is_none());
+        let messages = request["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0]["role"], "user");
+        assert_eq!(messages[0]["content"], "Be terse.");
+        assert_eq!(messages[1]["content"], "Hello");
+    }
+
+    #[test]
+    fn test_tool_messages_to_tool_results_keeps_cache_control() {
+        let system = serde_json::json!([
+            {"type": "text", "text": "Long stable prompt", "cache_control": {"type": "ephemeral"}},
+            {"type": "text", "text": "Per-request context"}
+        ]);
+        let mut request = serde_json::json!({
+            "model": "claude-sonnet-4-6",
+            "system": system,
+            "messages": [
+                {"role": "user", "content": [
+                    {"type": "text", "text": "Read both files", "cache_control": {"type": "ephemeral"}}
+                ]},
+                {"role": "assistant", "content": [
+                    {"type": "tool_use", "id": "call_a", "name": "read", "input": {"path": "a"}},
+                    {"type": "tool_use", "id": "call_b", "name": "read", "input": {"path": "b"}}
+                ]},
+                {"role": "tool", "tool_call_id": "call_a", "content": "alpha"},
+                {"role": "tool", "tool_call_id": "call_b", "content": [{"type": "text", "text": "beta"}]}
+            ]
+        });
+
+        tool_messages_to_tool_results(&mut request);
+
+        assert_eq!(request["system"], system);
+        let messages = request["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(
+            messages[0]["content"][0]["cache_control"],
+            serde_json::json!({"type": "ephemeral"})
+        );
+        assert_eq!(
+            messages[2],
+            serde_json::json!({"role": "user", "content": [
+                {"type": "tool_result", "tool_use_id": "call_a", "content": "alpha"},
+                {"type": "tool_result", "tool_use_id": "call_b", "content": [{"type": "text", "text": "beta"}]}
+            ]})
+        );
+    }
+
+    #[test]
+    fn test_translate_request_flattens_cached_system_for_openai() {
+        let request: AnthropicRequest = serde_json::from_value(serde_json::json!({
+            "model": "claude-sonnet-4-6",
+            "system": [
+                {"type": "text", "text": "Long stable prompt", "cache_control": {"type": "ephemeral"}},
+                {"type": "text", "text": "Per-request context"}
+            ],
+            "messages": [{"role": "user", "content": "Hello"}]
+        }))
+        .unwrap();
+
+        let openai = translate_request_anthropic_to_openai(&request, "gpt-4o", None);
+
+        assert_eq!(openai.messages[0].role, "system");
+        assert_eq!(
+            openai.messages[0].content,
+            Some(serde_json::json!("Long stable prompt\nPer-request context"))
+        );
+    }
+
+    fn request_with_tool_choice(tool_choice: serde_json::Value) -> AnthropicRequest {
+        AnthropicRequest {
+            model: "test".to_string(),
+            messages: vec![Message {
+                role: "user".to_string(),
+                content: serde_json::json!("Hello"),
+                tool_call_id: None,
+            }],
+            system: None,
+            max_tokens: Some(64),
+            temperature: None,
+            top_p: None,
+            stream: None,
+            tools: Some(vec![serde_json::json!({
+                "name": "calculator",
+                "input_schema": {"type": "object", "properties": {}}
+            })]),
+            tool_choice: Some(tool_choice),
+            metadata: None,
+            openai_passthrough_body: None,
+            excluded_tier: None,
+            stop_sequences: None,
+            modalities: None,
+            audio: None,
+            unrecognized: serde_json::Map::new(),
+        }
+    }
+
+    #[test]
+    fn test_translate_tool_choice_specific_tool() {
+        let request = request_with_tool_choice(serde_json::json!({
+            "type": "tool",
+            "name": "calculator"
+        }));
+        let openai = translate_request_anthropic_to_openai(&request, "gpt-4o", None);
+        assert_eq!(
+            openai.tool_choice,
+            Some(serde_json::json!({
+                "type": "function",
+                "function": {"name": "calculator"}
+            }))
+        );
+    }
+
+    #[test]
+    fn test_translate_tool_choice_modes() {
+        for (anthropic, openai) in [
+            (serde_json::json!({"type": "auto"}), "auto"),
+            (serde_json::json!({"type": "any"}), "required"),
+            (serde_json::json!({"type": "none"}), "none"),
+            (serde_json::json!("auto"), "auto"),
+            (serde_json::json!("any"), "required"),
+            (serde_json::json!("none"), "none"),
+        ] {
+            let request = request_with_tool_choice(anthropic.clone());
+            let translated = translate_request_anthropic_to_openai(&request, "gpt-4o", None);
+            assert_eq!(
+                translated.tool_choice,
+                Some(serde_json::json!(openai)),
+                "tool_choice {anthropic} should map to {openai}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_translate_tool_choice_absent_is_omitted() {
+        let mut request = request_with_tool_choice(serde_json::json!("auto"));
+        request.tool_choice = None;
+        let openai = translate_request_anthropic_to_openai(&request, "gpt-4o", None);
+        let serialized = serde_json::to_value(&openai).unwrap();
+        assert!(serialized.get("tool_choice").is_none());
+    }
+
+    #[test]
+    fn test_internal_request_maps_openai_tool_choice_to_anthropic() {
+        use super::openai_compat::openai_tool_choice_to_anthropic;
+
+        assert_eq!(
+            openai_tool_choice_to_anthropic(serde_json::json!({
+                "type": "function",
+                "function": {"name": "calculator"}
+            })),
+            Some(serde_json::json!({"type": "tool", "name": "calculator"}))
+        );
+        assert_eq!(
+            openai_tool_choice_to_anthropic(serde_json::json!("required")),
+            Some(serde_json::json!({"type": "any"}))
+        );
+        assert_eq!(
+            openai_tool_choice_to_anthropic(serde_json::json!("auto")),
+            Some(serde_json::json!({"type": "auto"}))
+        );
+        assert_eq!(
+            openai_tool_choice_to_anthropic(serde_json::json!("none")),
+            Some(serde_json::json!({"type": "none"}))
+        );
+    }
+
     #[test]
     fn test_responses_request_normalizes_developer_role() {
         let request = serde_json::json!({
@@ -1027,6 +2450,121 @@ mod tests {
         assert_eq!(messages[0]["role"], "system");
     }
 
+    #[test]
+    fn test_responses_request_puts_instructions_before_developer_messages() {
+        let request = serde_json::json!({
+            "model": "mock,test-model",
+            "stream": false,
+            "instructions": "Be concise",
+            "input": [
+                {
+                    "type": "message",
+                    "role": "developer",
+                    "content": [{"type": "input_text", "text": "Follow policy"}]
+                },
+                {
+                    "type": "message",
+                    "role": "user",
+                    "content": [{"type": "input_text", "text": "Hello"}]
+                }
+            ]
+        });
+
+        let openai = responses_request_to_openai_chat_request(&request).unwrap();
+        let messages = openai["messages"].as_array().expect("messages array");
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0]["role"], "system");
+        assert_eq!(messages[0]["content"], "Be concise");
+        assert_eq!(messages[1]["role"], "system");
+        assert_eq!(messages[1]["content"], "Follow policy");
+        assert_eq!(messages[2]["role"], "user");
+    }
+
+    #[test]
+    fn test_responses_request_dedupes_developer_message_matching_instructions() {
+        let request = serde_json::json!({
+            "model": "mock,test-model",
+            "stream": false,
+            "instructions": "Be concise",
+            "input": [
+                {
+                    "type": "message",
+                    "role": "developer",
+                    "content": [{"type": "input_text", "text": "Be concise\n"}]
+                },
+                {
+                    "type": "message",
+                    "role": "system",
+                    "content": "Be concise"
+                },
+                {
+                    "type": "message",
+                    "role": "user",
+                    "content": "Hello"
+                }
+            ]
+        });
+
+        let openai = responses_request_to_openai_chat_request(&request).unwrap();
+        let messages = openai["messages"].as_array().expect("messages array");
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0]["role"], "system");
+        assert_eq!(messages[0]["content"], "Be concise");
+        assert_eq!(messages[1]["role"], "user");
+    }
+
+    fn web_search_request(body: serde_json::Value) -> AnthropicRequest {
+        serde_json::from_value(body).unwrap()
+    }
+
+    #[test]
+    fn test_web_search_custom_tags() {
+        let web_search = WebSearchConfig {
+            enabled: true,
+            tags: vec!["/search".to_string()],
+            ..Default::default()
+        };
+        let mut request = web_search_request(serde_json::json!({
+            "model": "test",
+            "messages": [{"role": "user", "content": "/search rust 1.80 release notes"}]
+        }));
+        assert!(needs_web_search(&request, &web_search));
+
+        strip_search_tags(&mut request, &web_search);
+        assert_eq!(request.messages[0].content, "rust 1.80 release notes");
+
+        // The default tags no longer trigger once custom ones are configured.
+        let request = web_search_request(serde_json::json!({
+            "model": "test",
+            "messages": [{"role": "user", "content": "[search] rust"}]
+        }));
+        assert!(!needs_web_search(&request, &web_search));
+    }
+
+    #[test]
+    fn test_web_search_explicit_flag() {
+        let web_search = WebSearchConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        let mut request = web_search_request(serde_json::json!({
+            "model": "test",
+            "messages": [{"role": "user", "content": "latest rust release"}],
+            "extra_params": {"web_search": true}
+        }));
+        assert!(needs_web_search(&request, &web_search));
+
+        take_web_search_flag(&mut request);
+        assert!(!request.unrecognized.contains_key("extra_params"));
+
+        let request = web_search_request(serde_json::json!({
+            "model": "test",
+            "messages": [{"role": "user", "content": "latest rust release"}],
+            "extra_params": {"web_search": false}
+        }));
+        assert!(!needs_web_search(&request, &web_search));
+    }
+
     #[test]
     fn test_responses_request_uses_max_completion_tokens() {
         let request = serde_json::json!({
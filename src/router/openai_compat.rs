@@ -23,13 +23,50 @@ use crate::sse::SseFrameDecoder;
 use crate::transform::anthropic_to_openai::AnthropicToOpenAiResponseTransformer;
 use crate::transformer::Transformer;
 
+use super::client_message_id::{client_message_id, echo_client_message_id};
 use super::{
-    handle_messages, AnthropicContentBlock, AnthropicRequest, AnthropicResponse, AppState, Message,
+    handle_anthropic_request, AnthropicContentBlock, AnthropicRequest, AnthropicResponse, AppState,
+    Message,
 };
 
+/// Convert an OpenAI `tool_choice` into its Anthropic equivalent.
+///
+/// Anthropic-shaped objects pass through unchanged; unrecognised values are
+/// dropped so they never reach an Anthropic-protocol provider.
+pub(super) fn openai_tool_choice_to_anthropic(
+    tool_choice: serde_json::Value,
+) -> Option<serde_json::Value> {
+    match tool_choice {
+        serde_json::Value::String(choice) => match choice.as_str() {
+            "auto" => Some(serde_json::json!({"type": "auto"})),
+            "required" | "any" => Some(serde_json::json!({"type": "any"})),
+            "none" => Some(serde_json::json!({"type": "none"})),
+            _ => None,
+        },
+        serde_json::Value::Object(ref map)
+            if map.get("type").and_then(|t| t.as_str()) == Some("function") =>
+        {
+            map.get("function")
+                .and_then(|f| f.get("name"))
+                .cloned()
+                .map(|name| serde_json::json!({"type": "tool", "name": name}))
+        }
+        serde_json::Value::Object(_) => Some(tool_choice),
+        _ => None,
+    }
+}
+
 pub(super) fn internal_request_to_anthropic_request(
     req: crate::frontend::InternalRequest,
 ) -> AnthropicRequest {
+    let extra_param = |key: &str| {
+        req.extra_params
+            .as_ref()
+            .and_then(|extra| extra.get(key))
+            .cloned()
+    };
+    let modalities = extra_param("modalities").and_then(|v| serde_json::from_value(v).ok());
+    let audio = extra_param("audio");
     AnthropicRequest {
         model: req.model,
         messages: req
@@ -44,6 +81,7 @@ pub(super) fn internal_request_to_anthropic_request(
         system: req.system,
         max_tokens: req.max_tokens,
         temperature: req.temperature,
+        top_p: req.top_p,
         stream: req.stream,
         tools: req.tools.map(|tools| {
             tools
@@ -57,7 +95,14 @@ pub(super) fn internal_request_to_anthropic_request(
                 })
                 .collect()
         }),
+        tool_choice: req.tool_choice.and_then(openai_tool_choice_to_anthropic),
+        metadata: None,
         openai_passthrough_body: None,
+        excluded_tier: None,
+        stop_sequences: req.stop_sequences,
+        modalities,
+        audio,
+        unrecognized: serde_json::Map::new(),
     }
 }
 
@@ -83,6 +128,12 @@ pub(super) fn anthropic_response_to_internal(
             AnthropicContentBlock::ToolUse { id, name, input } => {
                 crate::frontend::ContentBlock::ToolUse { id, name, input }
             }
+            AnthropicContentBlock::Audio { audio } => {
+                crate::frontend::ContentBlock::Audio { audio }
+            }
+            AnthropicContentBlock::Image { source } => {
+                crate::frontend::ContentBlock::Image { source }
+            }
         })
         .collect();
 
@@ -319,6 +370,16 @@ pub async fn handle_chat_completions(
     State(state): State<AppState>,
     headers: HeaderMap,
     Json(request_body): Json<serde_json::Value>,
+) -> Response {
+    chat_completions(state, headers, request_body, None).await
+}
+
+/// `handle_chat_completions` with `excluded_tier` kept out of the cascade.
+pub(super) async fn chat_completions(
+    state: AppState,
+    headers: HeaderMap,
+    request_body: serde_json::Value,
+    excluded_tier: Option<String>,
 ) -> Response {
     // Preserve the original OpenAI-formatted body for potential passthrough
     // to OpenAI-compatible backends (avoids OpenAI→Anthropic→OpenAI round-trip).
@@ -337,13 +398,21 @@ pub async fn handle_chat_completions(
         }
     };
     let stream_requested = internal_request.stream.unwrap_or(false);
+    let client_message_id = client_message_id(&headers, passthrough_body.get("metadata"));
     let mut anthropic_request = internal_request_to_anthropic_request(internal_request);
+    anthropic_request.metadata = passthrough_body.get("metadata").cloned();
     anthropic_request.openai_passthrough_body = Some(passthrough_body);
-    let response = handle_messages(State(state), headers, Json(anthropic_request)).await;
+    anthropic_request.excluded_tier = excluded_tier;
+    let response = handle_anthropic_request(state, headers, anthropic_request).await;
 
-    if stream_requested {
+    let response = if stream_requested {
         convert_anthropic_stream_response_to_openai(response).await
     } else {
         convert_anthropic_json_response_to_openai(response).await
+    };
+    // Conversion rebuilds the body, so echo into the OpenAI-shaped JSON too.
+    match client_message_id {
+        Some(id) => echo_client_message_id(response, id).await,
+        None => response,
     }
 }
@@ -0,0 +1,146 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Early keepalive for streaming preset requests.
+//
+// A preset with `keepalive_ms` keeps a streaming client's connection busy
+// while the upstream has not answered yet, which on long preset prompts can
+// outlast a client's read timeout. A response that arrives within one
+// interval is returned unchanged. Otherwise a 200 event stream is started
+// with `: ping` comments, and the upstream's events, or its error as an
+// `event: error` frame, follow once it answers. Response headers set by the
+// routing path (such as `x-ccr-tier`) are not sent in that case.
+
+use std::future::Future;
+use std::time::Duration;
+
+use axum::{
+    body::{Body, Bytes},
+    http::{
+        header::{CACHE_CONTROL, CONNECTION, CONTENT_TYPE},
+        StatusCode,
+    },
+    response::Response,
+};
+use futures::stream::{self, BoxStream, StreamExt};
+
+use super::drain::is_event_stream;
+use super::streaming::{heartbeat_ticker, SSE_HEARTBEAT_FRAME};
+
+type FrameStream = BoxStream<'static, Result<Bytes, axum::Error>>;
+
+fn heartbeat() -> FrameStream {
+    stream::once(async { Ok(Bytes::from_static(SSE_HEARTBEAT_FRAME.as_bytes())) }).boxed()
+}
+
+/// `event: error` frame carrying the `error` object of a failed response.
+fn error_frame(status: StatusCode, body: &[u8]) -> Bytes {
+    let error = serde_json::from_slice::<serde_json::Value>(body)
+        .ok()
+        .and_then(|body| body.get("error").cloned())
+        .filter(serde_json::Value::is_object)
+        .unwrap_or_else(|| {
+            serde_json::json!({
+                "type": "api_error",
+                "message": format!("Upstream request failed with status {}", status.as_u16())
+            })
+        });
+    let payload = serde_json::json!({"type": "error", "error": error});
+    Bytes::from(format!("event: error\ndata: {}\n\n", payload))
+}
+
+/// Frames that follow the pings once `response` is ready.
+async fn response_frames(response: Response) -> FrameStream {
+    if response.status().is_success() && is_event_stream(&response) {
+        return response.into_body().into_data_stream().boxed();
+    }
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap_or_default();
+    let frame = error_frame(status, &body);
+    stream::once(async move { Ok(frame) }).boxed()
+}
+
+/// Await `response`, sending `: ping` every `period` to the client once it
+/// has taken longer than one period.
+pub(super) async fn with_initial_keepalive<F>(response: F, period: Duration) -> Response
+where
+    F: Future<Output = Response> + Send + 'static,
+{
+    let mut response = Box::pin(response);
+    let Some(mut ticker) = heartbeat_ticker(Some(period)) else {
+        return response.await;
+    };
+    tokio::select! {
+        response = &mut response => return response,
+        _ = ticker.tick() => {}
+    }
+
+    let frames = stream::unfold(Some((response, ticker)), |waiting| async move {
+        let (mut response, mut ticker) = waiting?;
+        tokio::select! {
+            response = &mut response => Some((response_frames(response).await, None)),
+            _ = ticker.tick() => Some((heartbeat(), Some((response, ticker)))),
+        }
+    });
+    let body = heartbeat().chain(frames.flatten());
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, "text/event-stream")
+        .header(CACHE_CONTROL, "no-cache")
+        .header(CONNECTION, "keep-alive")
+        .body(Body::from_stream(body))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::response::IntoResponse;
+
+    async fn body_text(response: Response) -> String {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn fast_response_is_returned_unchanged() {
+        let response = with_initial_keepalive(
+            async { (StatusCode::TOO_MANY_REQUESTS, "busy").into_response() },
+            Duration::from_secs(5),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(body_text(response).await, "busy");
+    }
+
+    #[tokio::test]
+    async fn slow_error_becomes_error_event_after_pings() {
+        let response = with_initial_keepalive(
+            async {
+                tokio::time::sleep(Duration::from_millis(120)).await;
+                (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    axum::Json(serde_json::json!({
+                        "error": {"type": "rate_limit_error", "message": "limited"}
+                    })),
+                )
+                    .into_response()
+            },
+            Duration::from_millis(30),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = body_text(response).await;
+        assert!(body.starts_with(SSE_HEARTBEAT_FRAME), "{body}");
+        let error = body.rfind("event: error\n").expect("error event");
+        assert!(body[..error].matches(SSE_HEARTBEAT_FRAME).count() >= 2);
+        assert!(
+            body[error..].contains(r#""type":"rate_limit_error""#),
+            "{body}"
+        );
+    }
+}
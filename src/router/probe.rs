@@ -0,0 +1,180 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Minimal one-token upstream requests, and provider probes built on them.
+//
+// `ccr-rust validate --probe` sends one such request to every provider and
+// reports whether it answered, whether the key was accepted, and how long
+// the round trip took. Preset warm-ups send the same request with the
+// preset's system prompt.
+
+use std::time::{Duration, Instant};
+
+use serde_json::json;
+
+use crate::config::{Config, Provider, ProviderProtocol};
+
+use super::dispatch::{
+    build_anthropic_headers, build_openai_headers, merge_tier_headers,
+    provider_anthropic_messages_url, provider_openai_chat_completions_url,
+};
+use super::translate_request::is_reasoning_model;
+use super::TryRequestError;
+
+/// User turn sent in a one-token request.
+const PROBE_USER_MESSAGE: &str = "ping";
+
+/// Longest a probe waits for a provider, regardless of `API_TIMEOUT_MS`.
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Build a one-token request to `provider` for `model_name`, in the
+/// provider's protocol. `system` is sent as the system prompt; on
+/// Anthropic-protocol providers it is marked for prompt caching.
+pub(super) fn one_token_request(
+    config: &Config,
+    provider: &Provider,
+    model_name: &str,
+    system: Option<&str>,
+) -> Result<reqwest::RequestBuilder, TryRequestError> {
+    let (url, body) = if provider.protocol == ProviderProtocol::Anthropic {
+        let mut body = json!({
+            "model": model_name,
+            "max_tokens": 1,
+            "messages": [{"role": "user", "content": PROBE_USER_MESSAGE}]
+        });
+        if let Some(system) = system {
+            body["system"] = json!([{
+                "type": "text",
+                "text": system,
+                "cache_control": {"type": "ephemeral"}
+            }]);
+        }
+        (provider_anthropic_messages_url(provider), body)
+    } else {
+        let mut messages = Vec::new();
+        if let Some(system) = system {
+            messages.push(json!({"role": "system", "content": system}));
+        }
+        messages.push(json!({"role": "user", "content": PROBE_USER_MESSAGE}));
+        // Reasoning models take the budget as `max_completion_tokens`, as in
+        // translated requests.
+        let budget_field = if is_reasoning_model(model_name) {
+            "max_completion_tokens"
+        } else {
+            "max_tokens"
+        };
+        let body = json!({
+            "model": model_name,
+            budget_field: 1,
+            "messages": messages
+        });
+        let url = provider_openai_chat_completions_url(provider, model_name);
+        (url, body)
+    };
+    let headers = provider_request_headers(config, provider, model_name)?;
+    Ok(config
+        .http_client_for(provider)
+        .post(url)
+        .headers(headers)
+        .json(&body))
+}
+
+/// Headers a request to `provider` for `model_name` carries: auth and
+/// protocol headers for the provider's protocol, plus configured tier headers.
+pub fn provider_request_headers(
+    config: &Config,
+    provider: &Provider,
+    model_name: &str,
+) -> Result<reqwest::header::HeaderMap, TryRequestError> {
+    let mut headers = if provider.protocol == ProviderProtocol::Anthropic {
+        build_anthropic_headers(provider)?
+    } else {
+        build_openai_headers(provider)?
+    };
+    merge_tier_headers(&mut headers, config, provider, model_name);
+    Ok(headers)
+}
+
+/// Result of probing one provider.
+#[derive(Debug, Clone)]
+pub struct ProviderProbe {
+    pub provider: String,
+    /// Model the probe asked for: the provider's first listed model.
+    pub model: String,
+    pub outcome: ProbeOutcome,
+    /// Time until the response headers arrived, or until the request failed.
+    pub latency: Duration,
+}
+
+/// What a provider probe found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProbeOutcome {
+    /// The provider answered with a 2xx.
+    Ok,
+    /// The provider answered 429. It is reachable and accepted the key.
+    RateLimited,
+    /// The provider answered 401 or 403.
+    AuthFailed(u16),
+    /// The provider answered with another error status.
+    Status(u16),
+    /// No response: connection, TLS, or timeout failure, or a request that
+    /// could not be built.
+    Unreachable(String),
+}
+
+impl ProbeOutcome {
+    /// Whether the provider is usable: reachable with a valid key.
+    pub fn is_success(&self) -> bool {
+        matches!(self, ProbeOutcome::Ok | ProbeOutcome::RateLimited)
+    }
+}
+
+impl std::fmt::Display for ProbeOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProbeOutcome::Ok => write!(f, "ok"),
+            ProbeOutcome::RateLimited => write!(f, "reachable, rate limited (429)"),
+            ProbeOutcome::AuthFailed(status) => write!(f, "auth failed ({})", status),
+            ProbeOutcome::Status(status) => write!(f, "error status {}", status),
+            ProbeOutcome::Unreachable(reason) => write!(f, "unreachable: {}", reason),
+        }
+    }
+}
+
+/// Send a one-token request to `provider` and classify the answer.
+pub async fn probe_provider(config: &Config, provider: &Provider) -> ProviderProbe {
+    let model = provider.models.first().cloned().unwrap_or_default();
+    let start = Instant::now();
+    let outcome = match one_token_request(config, provider, &model, None) {
+        Err(err) => ProbeOutcome::Unreachable(err.to_string()),
+        Ok(request) => match request.timeout(PROBE_TIMEOUT).send().await {
+            Err(err) => ProbeOutcome::Unreachable(err.to_string()),
+            Ok(resp) => {
+                crate::connection_stats::note_connection(&resp);
+                match resp.status().as_u16() {
+                    200..=299 => ProbeOutcome::Ok,
+                    429 => ProbeOutcome::RateLimited,
+                    status @ (401 | 403) => ProbeOutcome::AuthFailed(status),
+                    status => ProbeOutcome::Status(status),
+                }
+            }
+        },
+    };
+    ProviderProbe {
+        provider: provider.name.clone(),
+        model,
+        outcome,
+        latency: start.elapsed(),
+    }
+}
+
+/// Probe every configured provider that lists a model concurrently, in
+/// config order. Providers with no models have nothing to send a request for.
+pub async fn probe_providers(config: &Config) -> Vec<ProviderProbe> {
+    futures::future::join_all(
+        config
+            .providers()
+            .iter()
+            .filter(|provider| !provider.models.is_empty())
+            .map(|provider| probe_provider(config, provider)),
+    )
+    .await
+}
@@ -0,0 +1,83 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Provider introspection for `GET /v1/providers`.
+//
+// Reports the providers of the config the server is running with, so ops
+// tooling can check what a hot reload actually loaded without reading the
+// config file. API keys are never returned, including when a provider puts
+// its key in the base URL.
+
+use axum::{extract::State, Json};
+use serde_json::{json, Map, Value};
+
+use crate::config::{Provider, TransformerEntry};
+
+use super::AppState;
+
+const REDACTED: &str = "[REDACTED]";
+
+/// List configured providers with their protocol, base URL, models, and
+/// transformers.
+pub async fn list_providers(State(state): State<AppState>) -> Json<Value> {
+    let config = state.config.load();
+    let providers: Vec<Value> = config.providers().iter().map(provider_json).collect();
+    Json(json!({ "providers": providers }))
+}
+
+fn provider_json(provider: &Provider) -> Value {
+    let (transformers, model_transformers) = match &provider.transformer {
+        Some(transformer) => (
+            entries_json(transformer.provider_transformers()),
+            transformer
+                .model_overrides
+                .iter()
+                .map(|(model, config)| (model.clone(), entries_json(&config.use_list)))
+                .collect::<Map<String, Value>>(),
+        ),
+        None => (Value::Array(Vec::new()), Map::new()),
+    };
+    json!({
+        "name": provider.name,
+        "protocol": provider.protocol,
+        "api_base_url": redact_api_key(&provider.api_base_url, &provider.api_key),
+        "api_key_set": !provider.api_key.is_empty(),
+        "models": provider.models,
+        "transformers": transformers,
+        "model_transformers": model_transformers,
+    })
+}
+
+/// Entries in config syntax: a bare name, or `[name, options]`.
+fn entries_json(entries: &[TransformerEntry]) -> Value {
+    entries
+        .iter()
+        .map(|entry| match entry.options() {
+            Some(options) => json!([entry.name(), options]),
+            None => json!(entry.name()),
+        })
+        .collect()
+}
+
+fn redact_api_key(url: &str, api_key: &str) -> String {
+    if api_key.is_empty() {
+        url.to_string()
+    } else {
+        url.replace(api_key, REDACTED)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_api_key_in_base_url() {
+        assert_eq!(
+            redact_api_key("https://example.com/v1?key=sk-123", "sk-123"),
+            "https://example.com/v1?key=[REDACTED]"
+        );
+        assert_eq!(
+            redact_api_key("https://example.com/v1", ""),
+            "https://example.com/v1"
+        );
+    }
+}
@@ -0,0 +1,139 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Readiness probe (`GET /ready`), separate from the `/health` liveness check.
+//
+// `/ready` is only served once startup has finished, and answers 503 while the
+// server drains. With `READINESS_CHECK` configured, a background task also
+// requests every provider's `api_base_url` each interval, and `/ready` answers
+// 503 unless some provider gave an HTTP answer during the last interval (plus
+// one timeout, so a check still in flight does not flap the probe).
+
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures::stream::{FuturesUnordered, StreamExt};
+use parking_lot::Mutex;
+
+use crate::config::{Config, ReadinessCheckConfig};
+
+use super::AppState;
+
+/// How often the check task looks for `READINESS_CHECK` while it is unset,
+/// so a reload that adds it takes effect.
+const UNCONFIGURED_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// When the provider connectivity check last passed.
+#[derive(Debug, Default)]
+pub struct Readiness {
+    last_pass: Mutex<Option<Instant>>,
+}
+
+impl Readiness {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_pass(&self) {
+        *self.last_pass.lock() = Some(Instant::now());
+    }
+
+    /// Whether the check passed recently enough for `check`.
+    pub fn is_fresh(&self, check: &ReadinessCheckConfig) -> bool {
+        self.last_pass
+            .lock()
+            .is_some_and(|at| at.elapsed() <= check.interval() + check.timeout())
+    }
+}
+
+/// Run [`check_providers`] every `READINESS_CHECK.interval_ms` in the
+/// background, re-reading the config each round.
+pub fn spawn_readiness_checks(state: &AppState) {
+    let state = state.clone();
+    tokio::spawn(async move {
+        loop {
+            let config = state.config.load();
+            let Some(check) = config.readiness_check().copied() else {
+                tokio::time::sleep(UNCONFIGURED_POLL_INTERVAL).await;
+                continue;
+            };
+            if check_providers(&config, check.timeout()).await {
+                state.readiness.record_pass();
+            } else {
+                tracing::warn!("Readiness check: no provider reachable");
+            }
+            drop(config);
+            tokio::time::sleep(check.interval()).await;
+        }
+    });
+}
+
+/// Whether any provider answers a GET on its `api_base_url` within
+/// `timeout`. The status does not matter; only reachability is checked.
+pub async fn check_providers(config: &Config, timeout: Duration) -> bool {
+    let mut checks: FuturesUnordered<_> = config
+        .providers()
+        .iter()
+        .map(|provider| {
+            config
+                .http_client_for(provider)
+                .get(&provider.api_base_url)
+                .timeout(timeout)
+                .send()
+        })
+        .collect();
+    while let Some(result) = checks.next().await {
+        if let Ok(resp) = result {
+            crate::connection_stats::note_connection(&resp);
+            return true;
+        }
+    }
+    false
+}
+
+/// `GET /ready`: 200 when the server can take traffic, 503 otherwise.
+pub async fn ready(State(state): State<AppState>) -> Response {
+    if state.draining.load(Ordering::SeqCst) {
+        return not_ready("Server is shutting down");
+    }
+    let config = state.config.load();
+    if let Some(check) = config.readiness_check() {
+        if !state.readiness.is_fresh(check) {
+            return not_ready("No provider reachable within the readiness check interval");
+        }
+    }
+    Json(serde_json::json!({"status": "ready"})).into_response()
+}
+
+fn not_ready(reason: &str) -> Response {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(serde_json::json!({"status": "not_ready", "reason": reason})),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pass_goes_stale_after_interval_and_timeout() {
+        let check = ReadinessCheckConfig {
+            interval_ms: 1000,
+            timeout_ms: 500,
+        };
+        let readiness = Readiness::new();
+        assert!(!readiness.is_fresh(&check));
+
+        readiness.record_pass();
+        assert!(readiness.is_fresh(&check));
+
+        *readiness.last_pass.lock() = Instant::now().checked_sub(Duration::from_millis(1600));
+        assert!(!readiness.is_fresh(&check));
+    }
+}
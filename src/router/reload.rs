@@ -0,0 +1,90 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Hot config reload for `POST /v1/reload`.
+//
+// Re-reads the config file or URL the server was started with, validates
+// it, and swaps it into `AppState`. Requests already in flight keep the
+// snapshot they loaded; new requests see the new providers and tier order.
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use std::collections::BTreeSet;
+use tracing::{error, info};
+
+use crate::config::{Config, ConfigSource};
+
+use super::AppState;
+
+/// Provider names present in `new` but not `old`, and in `old` but not `new`.
+fn provider_diff(old: &Config, new: &Config) -> (Vec<String>, Vec<String>) {
+    let old_names: BTreeSet<&str> = old.providers().iter().map(|p| p.name.as_str()).collect();
+    let new_names: BTreeSet<&str> = new.providers().iter().map(|p| p.name.as_str()).collect();
+    let added = new_names
+        .difference(&old_names)
+        .map(|s| s.to_string())
+        .collect();
+    let removed = old_names
+        .difference(&new_names)
+        .map(|s| s.to_string())
+        .collect();
+    (added, removed)
+}
+
+async fn load_config(source: &ConfigSource) -> anyhow::Result<Config> {
+    if *source == ConfigSource::Stdin {
+        anyhow::bail!("config was read from stdin and cannot be re-read; restart to change it");
+    }
+    let config = Config::from_source(source).await?;
+    config.validate()?;
+    Ok(config)
+}
+
+/// Reload the config file and atomically swap it in.
+///
+/// Returns the new tier order. Settings consumed at startup (listen address,
+/// persistence, debug capture, GP routing) still require a restart.
+pub async fn handle_reload(State(state): State<AppState>) -> Response {
+    let current = state.config.load();
+    let path = current.source();
+
+    let new_config = match load_config(path).await {
+        Ok(config) => config,
+        Err(e) => {
+            error!("Config reload from {} failed: {:#}", path, e);
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({"error": format!("Config reload failed: {:#}", e)})),
+            )
+                .into_response();
+        }
+    };
+
+    let (added, removed) = provider_diff(&current, &new_config);
+    let tiers = new_config.backend_tiers();
+    info!(
+        providers_added = ?added,
+        providers_removed = ?removed,
+        tiers = ?tiers,
+        "Reloaded config from {}",
+        path
+    );
+    state
+        .ewma_tracker
+        .set_latency_window(new_config.latency_window());
+    if new_config.maintenance_mode() != current.maintenance_mode() {
+        super::maintenance::set(&state, new_config.maintenance_mode());
+    }
+    state.config.store(new_config);
+    super::spawn_preset_warmups(&state);
+
+    Json(serde_json::json!({
+        "status": "reloaded",
+        "tiers": tiers,
+        "providers_added": added,
+        "providers_removed": removed,
+    }))
+    .into_response()
+}
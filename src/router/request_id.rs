@@ -0,0 +1,54 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Per-request correlation IDs.
+//
+// Every request gets an ID: the client's `x-request-id` when it sends a
+// usable one, otherwise a fresh UUID. The ID is written back into the request's
+// `x-request-id` header for handlers, recorded on a span that encloses the
+// whole request, stored in debug captures, and echoed to the client as
+// `x-ccr-request-id`.
+
+use axum::{
+    extract::Request,
+    http::{HeaderMap, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use tracing::Instrument;
+
+/// Inbound header a client may use to choose the ID.
+pub const INBOUND_REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Response header carrying the ID.
+pub const REQUEST_ID_HEADER: &str = "x-ccr-request-id";
+
+/// Longest client-supplied ID that is honored.
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// The request's ID, as set by [`assign_request_id`].
+pub(crate) fn request_id(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(INBOUND_REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+}
+
+fn usable_client_id(headers: &HeaderMap) -> Option<HeaderValue> {
+    let value = headers.get(INBOUND_REQUEST_ID_HEADER)?;
+    let id = value.to_str().ok()?;
+    (!id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN && id.bytes().all(|b| b.is_ascii_graphic()))
+        .then(|| value.clone())
+}
+
+/// Middleware assigning the request ID and echoing it on the response.
+pub async fn assign_request_id(mut req: Request, next: Next) -> Response {
+    let id = usable_client_id(req.headers()).unwrap_or_else(|| {
+        HeaderValue::from_str(&uuid::Uuid::new_v4().to_string())
+            .expect("UUID is a valid header value")
+    });
+    req.headers_mut()
+        .insert(INBOUND_REQUEST_ID_HEADER, id.clone());
+
+    let span = tracing::info_span!("ccr.http", request_id = id.to_str().unwrap_or_default());
+    let mut response = next.run(req).instrument(span).await;
+    response.headers_mut().insert(REQUEST_ID_HEADER, id);
+    response
+}
@@ -2,7 +2,8 @@
 // OpenAI Responses API compatibility layer.
 //
 // Converts between OpenAI Responses API format and OpenAI Chat Completions format.
-// This handles the `/v1/responses` endpoint.
+// This handles the `/v1/responses` endpoint, except for tiers whose provider
+// speaks the Responses API natively (see `responses_native`).
 
 use axum::{
     body::{to_bytes, Body},
@@ -11,9 +12,14 @@ use axum::{
     response::{IntoResponse, Response},
     Json,
 };
-use tracing::error;
+use tracing::{error, warn};
 
-use super::{openai_compat::handle_chat_completions, AppState};
+use super::{
+    body_limit::{body_too_large_response, read_body_limited, BodyReadError},
+    openai_compat::chat_completions,
+    responses_native::{forward_responses_request, native_responses_tier},
+    AppState,
+};
 
 fn parse_sse_frames(payload: &str) -> Vec<(Option<String>, String)> {
     let mut frames = Vec::new();
@@ -48,7 +54,14 @@ fn looks_like_sse_payload(payload: &str) -> bool {
     trimmed.starts_with("event:") || trimmed.starts_with("data:")
 }
 
-pub(super) fn decode_request_body(bytes: &[u8], headers: &HeaderMap) -> Result<Vec<u8>, String> {
+/// Decode `bytes` per the request's `content-encoding`. Decoding stops with
+/// an error once the output passes `max_bytes`, so a small compressed body
+/// cannot expand without bound.
+pub(super) fn decode_request_body(
+    bytes: &[u8],
+    headers: &HeaderMap,
+    max_bytes: usize,
+) -> Result<Vec<u8>, String> {
     let content_encoding = headers
         .get(axum::http::header::CONTENT_ENCODING)
         .and_then(|v| v.to_str().ok())
@@ -61,16 +74,44 @@ pub(super) fn decode_request_body(bytes: &[u8], headers: &HeaderMap) -> Result<V
     }
 
     if content_encoding.contains("zstd") || content_encoding.contains("zst") {
-        return zstd::stream::decode_all(std::io::Cursor::new(bytes))
-            .map_err(|e| format!("Failed to decode zstd request body: {}", e));
+        let decoder = zstd::stream::read::Decoder::new(bytes)
+            .map_err(|e| format!("Failed to decode zstd request body: {}", e))?;
+        return read_bounded(decoder, max_bytes, "zstd");
+    }
+
+    if content_encoding == "br" {
+        let decoder = brotli::Decompressor::new(bytes, 4096);
+        return read_bounded(decoder, max_bytes, "brotli");
     }
 
     Err(format!(
-        "Unsupported content-encoding '{}'",
+        "Unsupported content-encoding '{}' (supported: identity, zstd, br)",
         content_encoding
     ))
 }
 
+/// Read all of `reader`, failing once more than `max_bytes` come out.
+fn read_bounded(
+    reader: impl std::io::Read,
+    max_bytes: usize,
+    encoding: &str,
+) -> Result<Vec<u8>, String> {
+    use std::io::Read as _;
+
+    let mut decoded = Vec::new();
+    reader
+        .take(max_bytes as u64 + 1)
+        .read_to_end(&mut decoded)
+        .map_err(|e| format!("Failed to decode {} request body: {}", encoding, e))?;
+    if decoded.len() > max_bytes {
+        return Err(format!(
+            "Decoded {} request body exceeds the {} byte limit",
+            encoding, max_bytes
+        ));
+    }
+    Ok(decoded)
+}
+
 pub(super) fn parse_json_payload(bytes: &[u8]) -> Result<serde_json::Value, String> {
     if let Ok(value) = serde_json::from_slice(bytes) {
         return Ok(value);
@@ -107,6 +148,83 @@ pub(super) fn parse_json_payload(bytes: &[u8]) -> Result<serde_json::Value, Stri
     ))
 }
 
+/// Longest summary synthesized from raw reasoning, in characters.
+const SYNTHESIZED_SUMMARY_MAX_CHARS: usize = 400;
+
+/// Whether the request asked for a reasoning summary (`reasoning.summary` set
+/// to anything other than `"none"`).
+fn reasoning_summary_requested(body: &serde_json::Value) -> bool {
+    body.get("reasoning")
+        .and_then(|reasoning| reasoning.get("summary"))
+        .and_then(|summary| summary.as_str())
+        .is_some_and(|summary| summary != "none")
+}
+
+/// Reasoning summary emitted as `response.reasoning_summary_text.*` events.
+///
+/// Upstream summary deltas are forwarded as they arrive. When the provider
+/// only streams raw reasoning, a summary is synthesized from its first
+/// [`SYNTHESIZED_SUMMARY_MAX_CHARS`] characters once the reasoning ends.
+#[derive(Default)]
+struct ReasoningSummary {
+    text: String,
+    from_upstream: bool,
+    done: bool,
+}
+
+impl ReasoningSummary {
+    fn push_upstream(&mut self, delta: &str, output: &mut String) {
+        if self.done || delta.is_empty() {
+            return;
+        }
+        self.from_upstream = true;
+        self.push_delta(delta, output);
+    }
+
+    fn push_delta(&mut self, delta: &str, output: &mut String) {
+        self.text.push_str(delta);
+        let delta_event = serde_json::json!({
+            "type": "response.reasoning_summary_text.delta",
+            "delta": delta,
+            "summary_index": 0
+        });
+        output.push_str("event: response.reasoning_summary_text.delta\ndata: ");
+        output.push_str(&delta_event.to_string());
+        output.push_str("\n\n");
+    }
+
+    /// Close the summary once reasoning is over, synthesizing one from
+    /// `reasoning` if the upstream sent none.
+    fn finish(&mut self, reasoning: &str, output: &mut String) {
+        if self.done {
+            return;
+        }
+        if !self.from_upstream {
+            let reasoning = reasoning.trim();
+            if reasoning.is_empty() {
+                return;
+            }
+            let mut synthesized: String = reasoning
+                .chars()
+                .take(SYNTHESIZED_SUMMARY_MAX_CHARS)
+                .collect();
+            if synthesized.len() < reasoning.len() {
+                synthesized.push('…');
+            }
+            self.push_delta(&synthesized, output);
+        }
+        self.done = true;
+        let done_event = serde_json::json!({
+            "type": "response.reasoning_summary_text.done",
+            "text": self.text,
+            "summary_index": 0
+        });
+        output.push_str("event: response.reasoning_summary_text.done\ndata: ");
+        output.push_str(&done_event.to_string());
+        output.push_str("\n\n");
+    }
+}
+
 fn map_openai_usage_to_responses_usage(usage: &serde_json::Value) -> serde_json::Value {
     let prompt_tokens = usage
         .get("prompt_tokens")
@@ -320,6 +438,15 @@ fn normalize_responses_message_role(role: &str) -> &str {
     }
 }
 
+/// Comparison key for de-duplicating system messages: text content is
+/// compared with surrounding whitespace trimmed, anything else as is.
+fn system_content_key(content: &serde_json::Value) -> serde_json::Value {
+    match content {
+        serde_json::Value::String(text) => serde_json::Value::String(text.trim().to_string()),
+        other => other.clone(),
+    }
+}
+
 pub(super) fn responses_request_to_openai_chat_request(
     body: &serde_json::Value,
 ) -> Result<serde_json::Value, String> {
@@ -330,8 +457,15 @@ pub(super) fn responses_request_to_openai_chat_request(
 
     let mut messages: Vec<serde_json::Value> = Vec::new();
 
+    // `instructions` comes first, followed by any `developer`/`system` input
+    // messages in their original order. A system message repeating content
+    // already sent (clients often echo `instructions` as a leading
+    // `developer` message) is dropped.
+    let mut system_contents: Vec<serde_json::Value> = Vec::new();
+
     if let Some(instructions) = body.get("instructions").and_then(|v| v.as_str()) {
         if !instructions.is_empty() {
+            system_contents.push(serde_json::Value::String(instructions.trim().to_string()));
             messages.push(serde_json::json!({
                 "role": "system",
                 "content": instructions
@@ -350,6 +484,13 @@ pub(super) fn responses_request_to_openai_chat_request(
                         .get("content")
                         .map(responses_content_to_openai_content)
                         .unwrap_or_else(|| serde_json::Value::String(String::new()));
+                    if role == "system" {
+                        let key = system_content_key(&content);
+                        if system_contents.contains(&key) {
+                            continue;
+                        }
+                        system_contents.push(key);
+                    }
                     messages.push(serde_json::json!({
                         "role": role,
                         "content": content
@@ -464,7 +605,7 @@ pub(super) fn responses_request_to_openai_chat_request(
     Ok(request)
 }
 
-async fn convert_openai_json_response_to_responses(response: Response) -> Response {
+pub(super) async fn convert_openai_json_response_to_responses(response: Response) -> Response {
     let (mut parts, body) = response.into_parts();
     let body_bytes = match to_bytes(body, usize::MAX).await {
         Ok(bytes) => bytes,
@@ -493,7 +634,7 @@ async fn convert_openai_json_response_to_responses(response: Response) -> Respon
                 parts.status = StatusCode::OK;
                 return Response::from_parts(
                     parts,
-                    Body::from(convert_sse_payload_to_responses(&payload)),
+                    Body::from(convert_sse_payload_to_responses(&payload, false)),
                 );
             }
             return Response::from_parts(parts, Body::from(body_bytes));
@@ -559,7 +700,10 @@ async fn convert_openai_json_response_to_responses(response: Response) -> Respon
     Response::from_parts(parts, Body::from(responses_json.to_string()))
 }
 
-async fn convert_openai_stream_response_to_responses(response: Response) -> Response {
+pub(super) async fn convert_openai_stream_response_to_responses(
+    response: Response,
+    summary_requested: bool,
+) -> Response {
     let (mut parts, body) = response.into_parts();
     let body_bytes = match to_bytes(body, usize::MAX).await {
         Ok(bytes) => bytes,
@@ -620,7 +764,7 @@ async fn convert_openai_stream_response_to_responses(response: Response) -> Resp
         return Response::from_parts(parts, Body::from(output));
     }
 
-    let output = convert_sse_payload_to_responses(&payload);
+    let output = convert_sse_payload_to_responses(&payload, summary_requested);
 
     parts.headers.insert(
         axum::http::header::CONTENT_TYPE,
@@ -630,7 +774,7 @@ async fn convert_openai_stream_response_to_responses(response: Response) -> Resp
     Response::from_parts(parts, Body::from(output))
 }
 
-fn convert_sse_payload_to_responses(payload: &str) -> String {
+fn convert_sse_payload_to_responses(payload: &str, summary_requested: bool) -> String {
     #[derive(Default)]
     struct ToolAccum {
         id: String,
@@ -650,6 +794,7 @@ fn convert_sse_payload_to_responses(payload: &str) -> String {
     let mut message_item_added = false;
     let mut message_text = String::new();
     let mut reasoning_text = String::new();
+    let mut summary = summary_requested.then(ReasoningSummary::default);
     let mut tools: std::collections::BTreeMap<usize, ToolAccum> = std::collections::BTreeMap::new();
     let mut usage = map_openai_usage_to_responses_usage(&serde_json::json!({}));
 
@@ -747,7 +892,21 @@ fn convert_sse_payload_to_responses(payload: &str) -> String {
                 message_item_added = true;
             }
 
-            if let Some(text) = delta.get("content").and_then(|v| v.as_str()) {
+            if let Some(delta) = delta.get("reasoning_summary").and_then(|v| v.as_str()) {
+                if let Some(summary) = summary.as_mut() {
+                    summary.push_upstream(delta, &mut output);
+                }
+            }
+
+            let text = delta.get("content").and_then(|v| v.as_str());
+            let has_tool_calls = delta.get("tool_calls").is_some();
+            if text.is_some_and(|text| !text.is_empty()) || has_tool_calls {
+                if let Some(summary) = summary.as_mut() {
+                    summary.finish(&reasoning_text, &mut output);
+                }
+            }
+
+            if let Some(text) = text {
                 if !message_item_added {
                     let added = serde_json::json!({
                         "type": "response.output_item.added",
@@ -865,6 +1024,9 @@ fn convert_sse_payload_to_responses(payload: &str) -> String {
                 if let Some(delta) = chunk.get("delta") {
                     if let Some(text) = delta.get("text").and_then(|v| v.as_str()) {
                         if !text.is_empty() {
+                            if let Some(summary) = summary.as_mut() {
+                                summary.finish(&reasoning_text, &mut output);
+                            }
                             message_text.push_str(text);
                             let delta_event = serde_json::json!({
                                 "type": "response.output_text.delta",
@@ -904,6 +1066,10 @@ fn convert_sse_payload_to_responses(payload: &str) -> String {
         }
     }
 
+    if let Some(summary) = summary.as_mut() {
+        summary.finish(&reasoning_text, &mut output);
+    }
+
     let mut output_items = Vec::new();
 
     if message_item_added {
@@ -952,7 +1118,7 @@ fn convert_sse_payload_to_responses(payload: &str) -> String {
             "type": "function_call",
             "call_id": call_id,
             "name": name,
-            "arguments": tool.arguments
+            "arguments": finalize_tool_arguments(call_id, &tool.arguments)
         });
         output_items.push(item.clone());
         let done = serde_json::json!({
@@ -983,15 +1149,36 @@ fn convert_sse_payload_to_responses(payload: &str) -> String {
     output
 }
 
+/// Arguments for a streamed `function_call` once all deltas are in. Text that
+/// is not valid JSON is wrapped as `{"raw_arguments": ...}`, as the
+/// non-streaming translation does, so clients always get a JSON object.
+fn finalize_tool_arguments(call_id: &str, arguments: &str) -> String {
+    if arguments.trim().is_empty() {
+        return "{}".to_string();
+    }
+    if serde_json::from_str::<serde_json::Value>(arguments).is_ok() {
+        return arguments.to_string();
+    }
+    warn!(
+        "Streamed arguments for tool call {} are not valid JSON; wrapping as raw_arguments",
+        call_id
+    );
+    serde_json::json!({ "raw_arguments": arguments }).to_string()
+}
+
 /// Handle OpenAI Responses API requests.
 pub async fn handle_responses(
     State(state): State<AppState>,
     headers: HeaderMap,
     body: Body,
 ) -> Response {
-    let body_bytes = match to_bytes(body, usize::MAX).await {
+    let max_body_bytes = state.config.load().max_body_bytes();
+    let body_bytes = match read_body_limited(body, max_body_bytes).await {
         Ok(bytes) => bytes,
-        Err(err) => {
+        Err(BodyReadError::TooLarge) => {
+            return body_too_large_response("/v1/responses", max_body_bytes);
+        }
+        Err(BodyReadError::Read(err)) => {
             return (
                 StatusCode::BAD_REQUEST,
                 Json(serde_json::json!({
@@ -1004,7 +1191,7 @@ pub async fn handle_responses(
         }
     };
 
-    let decoded = match decode_request_body(&body_bytes, &headers) {
+    let decoded = match decode_request_body(&body_bytes, &headers, max_body_bytes) {
         Ok(bytes) => bytes,
         Err(err) => {
             return (
@@ -1038,6 +1225,24 @@ pub async fn handle_responses(
         .get("stream")
         .and_then(|v| v.as_bool())
         .unwrap_or(true);
+    let summary_requested = reasoning_summary_requested(&request_body);
+
+    let native_tier = native_responses_tier(&state, &state.config.load(), &request_body);
+    let mut excluded_tier = None;
+    if let Some((tier, tier_name)) = native_tier {
+        let forwarded = forward_responses_request(
+            state.clone(),
+            &tier,
+            &tier_name,
+            &request_body,
+            stream_requested,
+        )
+        .await;
+        if let Some(response) = forwarded {
+            return response;
+        }
+        excluded_tier = Some(tier);
+    }
 
     let openai_chat_request = match responses_request_to_openai_chat_request(&request_body) {
         Ok(request) => request,
@@ -1055,10 +1260,10 @@ pub async fn handle_responses(
     };
 
     let openai_response =
-        handle_chat_completions(State(state), headers, Json(openai_chat_request)).await;
+        chat_completions(state, headers, openai_chat_request, excluded_tier).await;
 
     if stream_requested {
-        convert_openai_stream_response_to_responses(openai_response).await
+        convert_openai_stream_response_to_responses(openai_response, summary_requested).await
     } else {
         convert_openai_json_response_to_responses(openai_response).await
     }
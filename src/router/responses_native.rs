@@ -0,0 +1,296 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Native Responses API forwarding for `protocol: "openai_responses"`.
+//
+// When the tier a `/v1/responses` request would go to first belongs to a
+// provider that speaks the Responses API itself, the request is sent to the
+// provider's `/responses` endpoint as-is (only `model` is rewritten) and the
+// reply or event stream is passed straight back. Nothing goes through the
+// chat/completions conversion, so fields it cannot express survive.
+//
+// The tier is picked with the cascade's own filtering (blackouts, rate-limit
+// backoff, `requireHealthy`), and the attempt holds an upstream slot and, when
+// streaming, a stream slot like any other. If the attempt fails with a
+// retryable status or a transport error, or is rate limited while another
+// tier is available, the request falls back to the converted cascade. A 429
+// with nowhere else to go is rewritten into the same `rate_limited` error the
+// converted path returns.
+
+use axum::{
+    body::Body,
+    http::{header::CONTENT_TYPE, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use tracing::{info, warn};
+
+use crate::config::{Config, ProviderProtocol};
+use crate::frontend::FrontendType;
+use crate::metrics::{
+    record_failure, record_rate_limit_backoff, record_rate_limit_hit, record_rejected,
+    record_request_duration_with_frontend, record_request_with_frontend, sync_ewma_gauge,
+    RequestMode,
+};
+use crate::routing::AttemptTimer;
+
+use super::dispatch::{
+    build_openai_headers, insert_ccr_tier_header, merge_tier_headers, provider_endpoint_url,
+    reqwest_status_to_axum, send_with_provider_timeout,
+};
+use super::responses_api::{
+    convert_openai_json_response_to_responses, convert_openai_stream_response_to_responses,
+};
+use super::{
+    client_error_message, hold_until_body_dropped, is_event_stream, next_candidate,
+    stream_limit_response, tier_health, unknown_model_response, upstream_saturated_response,
+    ActiveRequestGuard, AppState, CascadeFailures, StreamSlot, TierHealth,
+};
+
+/// The `(tier, tier_name)` the router would try first for a Responses
+/// request: an explicit `provider,model` unless `ignoreDirect` is set, then
+/// the EWMA-ordered tier list, skipping tiers the cascade would skip and
+/// `excluded`.
+fn first_candidate(
+    state: &AppState,
+    config: &Config,
+    request_body: &serde_json::Value,
+    excluded: Option<&str>,
+) -> Option<(String, String)> {
+    let mut ordered = state
+        .ewma_tracker
+        .sort_tiers_with_config(&config.backend_tiers(), config);
+    let requested_model = request_body.get("model").and_then(|m| m.as_str());
+    if let Some(model) =
+        requested_model.filter(|model| model.contains(',') && !config.router().ignore_direct)
+    {
+        ordered.retain(|(tier, _)| tier != model);
+        ordered.insert(
+            0,
+            (
+                model.to_string(),
+                config.backend_abbreviation_with_config(model),
+            ),
+        );
+    }
+    ordered.retain(|(tier, _)| Some(tier.as_str()) != excluded);
+
+    let now = chrono::Utc::now();
+    let require_healthy = config.router().require_healthy.enabled;
+    let mut candidates = ordered.iter().enumerate().filter(|(_, (tier, tier_name))| {
+        !require_healthy || tier_health(state, config, tier, tier_name, now) == TierHealth::Healthy
+    });
+    let mut skipped = CascadeFailures::default();
+    next_candidate(&mut candidates, &mut skipped, state, config, now)
+        .map(|(_, tier, tier_name)| (tier.clone(), tier_name.clone()))
+}
+
+/// The `(tier, tier_name)` a Responses request should be forwarded to
+/// natively, or `None` when it goes through chat/completions conversion.
+pub(super) fn native_responses_tier(
+    state: &AppState,
+    config: &Config,
+    request_body: &serde_json::Value,
+) -> Option<(String, String)> {
+    let (tier, tier_name) = first_candidate(state, config, request_body, None)?;
+    let provider = config.resolve_provider(&tier)?;
+    (provider.protocol == ProviderProtocol::OpenaiResponses).then_some((tier, tier_name))
+}
+
+/// Forward `request_body` to the tier's `/responses` endpoint unchanged.
+/// Returns `None` when the request should fall back to the converted cascade
+/// without this tier; that only happens while another tier is available.
+pub(super) async fn forward_responses_request(
+    state: AppState,
+    tier: &str,
+    tier_name: &str,
+    request_body: &serde_json::Value,
+    stream_requested: bool,
+) -> Option<Response> {
+    let _guard = ActiveRequestGuard::new();
+    let start = std::time::Instant::now();
+    let config = state.config.load();
+    let Some(provider) = config.resolve_provider(tier) else {
+        return Some(upstream_error_response(format!(
+            "Provider not found for tier: {}",
+            tier
+        )));
+    };
+    let model_name = tier.split(',').nth(1).unwrap_or(tier);
+    let Some(upstream_model) = provider.upstream_model(model_name) else {
+        return Some(unknown_model_response(model_name, &provider.name));
+    };
+    let mut forwarded_body = request_body.clone();
+    if let Some(fields) = forwarded_body.as_object_mut() {
+        fields.insert("model".to_string(), upstream_model.into());
+    }
+
+    let stream_slot = if stream_requested {
+        let Some(slot) = StreamSlot::try_acquire(&state.active_streams, state.max_streams) else {
+            record_rejected();
+            warn!(
+                "Rejecting stream: {} stream slots in use",
+                state.max_streams
+            );
+            return Some(stream_limit_response(
+                FrontendType::Codex,
+                state.max_streams,
+            ));
+        };
+        Some(slot)
+    } else {
+        None
+    };
+    let limit = config.max_upstream_inflight();
+    let Some(permit) = state
+        .upstream_limiter
+        .acquire(limit, config.upstream_queue_timeout())
+        .await
+    else {
+        warn!("Upstream concurrency cap of {} reached, rejecting", limit);
+        return Some(upstream_saturated_response(limit));
+    };
+
+    let url = provider_endpoint_url(provider, "responses");
+    let mut headers = match build_openai_headers(provider) {
+        Ok(headers) => headers,
+        Err(err) => return Some(upstream_error_response(err.to_string())),
+    };
+    merge_tier_headers(&mut headers, &config, provider, model_name);
+
+    info!(tier = tier_name, url = %url, "Forwarding Responses request natively");
+    let timer = AttemptTimer::start(&state.ewma_tracker, tier_name)
+        .with_alpha(config.ewma_alpha(tier_name));
+    let upstream_request = config
+        .http_client_for(provider)
+        .post(&url)
+        .headers(headers)
+        .json(&forwarded_body);
+    let sent = send_with_provider_timeout(upstream_request, provider, stream_requested).await;
+    if let Ok(resp) = &sent {
+        crate::connection_stats::record_connection(&provider.name, resp);
+    }
+
+    let can_fall_back = || first_candidate(&state, &config, request_body, Some(tier)).is_some();
+    let resp = match sent {
+        Ok(resp) => resp,
+        Err(err) => {
+            timer.finish_failure();
+            sync_ewma_gauge(&state.ewma_tracker);
+            record_failure(tier_name, "request_failed");
+            if can_fall_back() {
+                warn!(
+                    tier = tier_name,
+                    "Responses upstream request failed, falling back to conversion: {}", err
+                );
+                return None;
+            }
+            warn!(
+                tier = tier_name,
+                "Responses upstream request failed: {}", err
+            );
+            return Some(upstream_error_response(format!(
+                "Upstream request failed: {}",
+                client_error_message(err)
+            )));
+        }
+    };
+
+    let status = reqwest_status_to_axum(resp.status());
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        timer.finish_failure();
+        sync_ewma_gauge(&state.ewma_tracker);
+        let retry_after = resp
+            .headers()
+            .get("retry-after")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|s| s.parse::<u64>().ok());
+        record_rate_limit_hit(tier_name);
+        state
+            .ratelimit_tracker
+            .record_429(tier_name, retry_after.map(std::time::Duration::from_secs));
+        record_rate_limit_backoff(tier_name);
+        if can_fall_back() {
+            info!(
+                tier = tier_name,
+                "Responses tier rate limited, falling back to conversion"
+            );
+            return None;
+        }
+
+        let body = resp.bytes().await.unwrap_or_default();
+        let mut limited = Response::new(Body::from(body));
+        *limited.status_mut() = status;
+        if let Some(secs) = retry_after {
+            limited
+                .headers_mut()
+                .insert("retry-after", HeaderValue::from(secs));
+        }
+        let mut response = if stream_requested {
+            convert_openai_stream_response_to_responses(limited, false).await
+        } else {
+            convert_openai_json_response_to_responses(limited).await
+        };
+        insert_ccr_tier_header(&mut response, tier_name);
+        return Some(response);
+    }
+
+    if !status.is_success()
+        && config
+            .router()
+            .retryable_statuses
+            .contains(&status.as_u16())
+        && can_fall_back()
+    {
+        timer.finish_failure();
+        sync_ewma_gauge(&state.ewma_tracker);
+        record_failure(tier_name, "request_failed");
+        warn!(
+            tier = tier_name,
+            status = status.as_u16(),
+            "Responses upstream returned a retryable status, falling back to conversion"
+        );
+        return None;
+    }
+
+    if status.is_success() {
+        timer.finish_success();
+        let mode = RequestMode::from_stream(stream_requested);
+        record_request_with_frontend(tier_name, mode, FrontendType::Codex);
+        record_request_duration_with_frontend(
+            tier_name,
+            mode,
+            start.elapsed().as_secs_f64(),
+            FrontendType::Codex,
+        );
+    } else {
+        timer.finish_failure();
+    }
+    sync_ewma_gauge(&state.ewma_tracker);
+
+    let content_type = resp.headers().get(reqwest::header::CONTENT_TYPE).cloned();
+    let mut response = Response::new(Body::from_stream(resp.bytes_stream()));
+    *response.status_mut() = status;
+    if let Some(value) = content_type.and_then(|v| HeaderValue::from_bytes(v.as_bytes()).ok()) {
+        response.headers_mut().insert(CONTENT_TYPE, value);
+    }
+    insert_ccr_tier_header(&mut response, tier_name);
+    let response = hold_until_body_dropped(response, permit);
+    Some(match stream_slot {
+        Some(slot) if is_event_stream(&response) => hold_until_body_dropped(response, slot),
+        _ => response,
+    })
+}
+
+fn upstream_error_response(message: String) -> Response {
+    (
+        StatusCode::BAD_GATEWAY,
+        Json(serde_json::json!({
+            "error": {
+                "message": message,
+                "type": "upstream_error",
+                "param": null,
+                "code": "upstream_error"
+            }
+        })),
+    )
+        .into_response()
+}
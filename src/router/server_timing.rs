@@ -0,0 +1,120 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// `Server-Timing` header listing each upstream attempt of a request.
+//
+// Every attempt the cascade makes is recorded with its tier, how long it took
+// until dispatch returned (response headers for streams), and whether it
+// succeeded. The response carries them in order, e.g.
+// `tier-0;desc="failed";dur=812.4, tier-1;desc="ok";dur=301.9`, so browser
+// devtools and `curl -v` show where the time went. Attempts cancelled by a
+// hedge race are left out. The header is only sent with
+// `Router.serverTimingHeader` enabled, since tier names and timings describe
+// the deployment behind the proxy.
+
+use axum::http::HeaderValue;
+use axum::response::Response;
+use parking_lot::Mutex;
+
+pub(super) const SERVER_TIMING_HEADER: &str = "server-timing";
+
+#[derive(Debug)]
+struct Attempt {
+    tier: String,
+    millis: f64,
+    ok: bool,
+}
+
+/// Upstream attempts of one request, shared by the tiers the cascade runs.
+#[derive(Debug)]
+pub(super) struct ServerTiming {
+    enabled: bool,
+    attempts: Mutex<Vec<Attempt>>,
+}
+
+impl ServerTiming {
+    /// Timing for a request; `enabled` is `Router.serverTimingHeader`.
+    pub(super) fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            attempts: Mutex::default(),
+        }
+    }
+
+    pub(super) fn record(&self, tier_name: &str, secs: f64, ok: bool) {
+        self.attempts.lock().push(Attempt {
+            tier: tier_name.to_string(),
+            millis: secs * 1000.0,
+            ok,
+        });
+    }
+
+    /// Header value for the attempts so far; `None` before any attempt or
+    /// when the header is disabled.
+    fn header_value(&self) -> Option<String> {
+        let attempts = self.attempts.lock();
+        if !self.enabled || attempts.is_empty() {
+            return None;
+        }
+        let entries: Vec<String> = attempts
+            .iter()
+            .map(|attempt| {
+                format!(
+                    "{};desc=\"{}\";dur={:.1}",
+                    metric_name(&attempt.tier),
+                    if attempt.ok { "ok" } else { "failed" },
+                    attempt.millis
+                )
+            })
+            .collect();
+        Some(entries.join(", "))
+    }
+
+    /// Write the attempts to the `Server-Timing` header, if there are any.
+    pub(super) fn insert_header(&self, response: &mut Response) {
+        let Some(value) = self.header_value() else {
+            return;
+        };
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            response.headers_mut().insert(SERVER_TIMING_HEADER, value);
+        }
+    }
+}
+
+/// A tier name as a `Server-Timing` metric name: characters outside the HTTP
+/// token set become `_`.
+fn metric_name(tier_name: &str) -> String {
+    tier_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_attempts_in_order() {
+        let timing = ServerTiming::new(true);
+        assert_eq!(timing.header_value(), None);
+
+        timing.record("tier-0", 0.8124, false);
+        timing.record("glm,glm 5", 0.3019, true);
+        assert_eq!(
+            timing.header_value().unwrap(),
+            "tier-0;desc=\"failed\";dur=812.4, glm_glm_5;desc=\"ok\";dur=301.9"
+        );
+    }
+
+    #[test]
+    fn disabled_timing_has_no_header() {
+        let timing = ServerTiming::new(false);
+        timing.record("tier-0", 0.1, true);
+        assert_eq!(timing.header_value(), None);
+    }
+}
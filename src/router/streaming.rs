@@ -2,13 +2,17 @@
 use axum::body::Body;
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
-use tracing::{trace, warn};
+use tracing::warn;
 
+use super::think_split::ThinkTagSplitter;
 use super::translate_response::{
-    create_stream_stop_events, translate_stream_chunk_to_anthropic, StreamTranslationState,
+    create_stream_stop_events, is_nonstandard_role, translate_stream_chunk_to_anthropic,
+    StreamTranslationState,
 };
 use super::types::*;
-use crate::metrics::{record_cost, record_failure, record_usage, verify_token_usage};
+use crate::metrics::{
+    record_cost, record_failure, record_nonstandard_role, record_usage, verify_token_usage,
+};
 use crate::sse::{SseFrameDecoder, StreamVerifyCtx};
 use crate::transformer::TransformerChain;
 
@@ -21,7 +25,8 @@ use futures::StreamExt;
 use tokio_stream::wrappers::ReceiverStream;
 
 use crate::metrics::{
-    increment_active_streams, record_stream_backpressure, record_throughput, record_ttft,
+    increment_active_streams, record_stream_backpressure, record_throughput,
+    record_time_to_first_token, record_ttft,
 };
 
 async fn send_stream_timeout_error(
@@ -45,10 +50,294 @@ async fn send_stream_timeout_error(
     let _ = tx.send(Ok(Bytes::from(sse_data))).await;
 }
 
+/// SSE comment frame sent to keep idle client connections alive.
+pub(super) const SSE_HEARTBEAT_FRAME: &str = ": ping\n\n";
+
+/// Build the heartbeat ticker for a stream. The first tick fires one full
+/// period after the stream starts; `None` disables heartbeats.
+pub(super) fn heartbeat_ticker(
+    period: Option<std::time::Duration>,
+) -> Option<tokio::time::Interval> {
+    period.filter(|p| !p.is_zero()).map(|p| {
+        let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + p, p);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        ticker
+    })
+}
+
+/// Resolve on the next heartbeat tick, or never when heartbeats are disabled.
+async fn next_heartbeat(ticker: &mut Option<tokio::time::Interval>) {
+    match ticker {
+        Some(ticker) => {
+            ticker.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
 /// A boxed byte stream that both streaming handlers accept.
 pub type BoxByteStream =
     std::pin::Pin<Box<dyn futures::Stream<Item = Result<Bytes, reqwest::Error>> + Send>>;
 
+/// Move the think-tagged part of a chunk's content into `reasoning_content`.
+/// A partial tag held back at the end is released with the finishing chunk.
+fn split_think_tags(splitter: &mut ThinkTagSplitter, chunk: &mut OpenAIStreamChunk) {
+    let Some(choice) = chunk.choices.first_mut() else {
+        return;
+    };
+    let mut split = choice
+        .delta
+        .content
+        .take()
+        .map(|content| splitter.push(&content))
+        .unwrap_or_default();
+    if choice.finish_reason.is_some() {
+        let rest = splitter.finish();
+        split.thinking.push_str(&rest.thinking);
+        split.text.push_str(&rest.text);
+    }
+    if !split.thinking.is_empty() {
+        choice
+            .delta
+            .reasoning_content
+            .get_or_insert_with(String::new)
+            .push_str(&split.thinking);
+    }
+    if !split.text.is_empty() {
+        choice.delta.content = Some(split.text);
+    }
+}
+
+/// A chunk carrying whatever the splitter still holds back, for streams that
+/// end without a `finish_reason` (a usage-only chunk or just `[DONE]`).
+fn flush_think_splitter(splitter: &mut ThinkTagSplitter) -> Option<OpenAIStreamChunk> {
+    let rest = splitter.finish();
+    if rest.thinking.is_empty() && rest.text.is_empty() {
+        return None;
+    }
+    Some(OpenAIStreamChunk {
+        id: String::new(),
+        object: "chat.completion.chunk".to_string(),
+        created: 0,
+        model: String::new(),
+        choices: vec![OpenAIStreamChoice {
+            index: 0,
+            delta: OpenAIDelta {
+                content: Some(rest.text).filter(|t| !t.is_empty()),
+                reasoning_content: Some(rest.thinking).filter(|t| !t.is_empty()),
+                ..Default::default()
+            },
+            finish_reason: None,
+        }],
+        usage: None,
+    })
+}
+
+/// Whether an OpenAI stream chunk carries content, reasoning, or a tool call.
+fn chunk_has_first_token(chunk: &OpenAIStreamChunk) -> bool {
+    chunk.choices.iter().any(|choice| {
+        let delta = &choice.delta;
+        delta.content.as_deref().is_some_and(|s| !s.is_empty())
+            || delta
+                .reasoning_content
+                .as_deref()
+                .is_some_and(|s| !s.is_empty())
+            || delta
+                .tool_calls
+                .as_ref()
+                .is_some_and(|calls| !calls.is_empty())
+    })
+}
+
+/// Whether an Anthropic stream event carries text or thinking, or starts a
+/// `tool_use` block.
+fn event_has_first_token(event: &serde_json::Value) -> bool {
+    let non_empty = |value: Option<&serde_json::Value>| {
+        value
+            .and_then(|v| v.as_str())
+            .is_some_and(|s| !s.is_empty())
+    };
+    match event.get("type").and_then(|t| t.as_str()) {
+        Some("content_block_start") => {
+            let block = &event["content_block"];
+            block["type"] == "tool_use" || non_empty(block.get("text"))
+        }
+        Some("content_block_delta") => {
+            let delta = &event["delta"];
+            ["text", "thinking", "partial_json"]
+                .iter()
+                .any(|field| non_empty(delta.get(*field)))
+        }
+        _ => false,
+    }
+}
+
+/// Fill `reasoning_content` and `content` from provider-specific `delta`
+/// fields when the standard ones are absent. The chunk is only re-read as a
+/// `Value` when an alias could apply.
+fn apply_delta_field_aliases(
+    chunk: &mut OpenAIStreamChunk,
+    raw: &str,
+    reasoning_aliases: &[String],
+    content_aliases: &[String],
+) {
+    let Some(choice) = chunk.choices.first_mut() else {
+        return;
+    };
+    let delta = &mut choice.delta;
+    let want_reasoning = delta.reasoning_content.is_none() && !reasoning_aliases.is_empty();
+    let want_content = delta.content.is_none() && !content_aliases.is_empty();
+    if !want_reasoning && !want_content {
+        return;
+    }
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(raw) else {
+        return;
+    };
+    let Some(raw_delta) = value.pointer("/choices/0/delta") else {
+        return;
+    };
+    let lookup = |aliases: &[String]| {
+        aliases
+            .iter()
+            .find_map(|alias| raw_delta.get(alias).and_then(|v| v.as_str()))
+            .map(str::to_string)
+    };
+    if want_reasoning {
+        delta.reasoning_content = lookup(reasoning_aliases);
+    }
+    if want_content {
+        delta.content = lookup(content_aliases);
+    }
+}
+
+/// Output token budget for a translated stream whose provider may ignore
+/// `max_tokens`. Deltas are counted with the tiktoken estimator.
+struct StreamTokenBudget {
+    limit: u64,
+    used: u64,
+}
+
+impl StreamTokenBudget {
+    fn new(limit: u64) -> Self {
+        Self { limit, used: 0 }
+    }
+
+    /// Charge `text` against the budget, truncating it to the tokens that
+    /// still fit. Returns true once the budget is exceeded.
+    fn charge(&mut self, text: &mut String) -> bool {
+        if text.is_empty() {
+            return false;
+        }
+        let tokens = crate::metrics::count_text_tokens(text);
+        let remaining = self.limit.saturating_sub(self.used);
+        if tokens <= remaining {
+            self.used += tokens;
+            return false;
+        }
+        *text = crate::metrics::truncate_text_to_tokens(text, remaining);
+        self.used = self.limit;
+        true
+    }
+
+    /// Charge every delta in the chunk, in stream order. On overrun the
+    /// deltas are clipped to the budget, later tool calls are dropped, and the
+    /// chunk is marked as a `length` finish.
+    fn charge_chunk(&mut self, chunk: &mut OpenAIStreamChunk) -> bool {
+        let Some(choice) = chunk.choices.first_mut() else {
+            return false;
+        };
+        let delta = &mut choice.delta;
+        let mut exceeded = false;
+        for text in [&mut delta.reasoning_content, &mut delta.content]
+            .into_iter()
+            .flatten()
+        {
+            if exceeded {
+                text.clear();
+            } else {
+                exceeded = self.charge(text);
+            }
+        }
+        if let Some(tool_calls) = delta.tool_calls.as_mut() {
+            let mut kept = 0;
+            for tc in tool_calls.iter_mut() {
+                if exceeded {
+                    break;
+                }
+                kept += 1;
+                if let Some(args) = tc.function.as_mut().and_then(|f| f.arguments.as_mut()) {
+                    exceeded = self.charge(args);
+                }
+            }
+            tool_calls.truncate(kept);
+        }
+        if exceeded {
+            choice.finish_reason = Some("length".to_string());
+        }
+        exceeded
+    }
+}
+
+/// Interim usage reporting for a translated stream. Deltas are counted with
+/// the tiktoken estimator, and a report is due each time another `interval`
+/// tokens have been counted since the last one.
+struct StreamUsageTicker {
+    interval: u64,
+    counted: u64,
+    reported: u64,
+}
+
+impl StreamUsageTicker {
+    fn new(interval: u64) -> Self {
+        Self {
+            interval,
+            counted: 0,
+            reported: 0,
+        }
+    }
+
+    /// Count the chunk's deltas. Returns the running total when a report is
+    /// due.
+    fn count_chunk(&mut self, chunk: &OpenAIStreamChunk) -> Option<u64> {
+        let delta = &chunk.choices.first()?.delta;
+        let texts = [&delta.reasoning_content, &delta.content]
+            .into_iter()
+            .flatten()
+            .map(String::as_str);
+        let arguments = delta
+            .tool_calls
+            .iter()
+            .flatten()
+            .filter_map(|tc| tc.function.as_ref()?.arguments.as_deref());
+        for text in texts.chain(arguments) {
+            self.counted += crate::metrics::count_text_tokens(text);
+        }
+        if self.counted >= self.reported + self.interval {
+            self.reported = self.counted;
+            Some(self.counted)
+        } else {
+            None
+        }
+    }
+}
+
+/// Interim `message_delta` carrying usage so far. It has no stop reason, so
+/// clients keep reading; the final `message_delta` replaces its counts.
+fn interim_usage_event(input_tokens: u64, output_tokens: u64) -> AnthropicStreamEvent {
+    AnthropicStreamEvent {
+        event_type: "message_delta".to_string(),
+        message: None,
+        index: None,
+        content_block: None,
+        delta: Some(serde_json::json!({"stop_reason": null, "stop_sequence": null})),
+        usage: Some(AnthropicUsage {
+            input_tokens,
+            output_tokens,
+        }),
+        stop_reason: None,
+    }
+}
+
 /// Stream response with OpenAI -> Anthropic translation.
 pub async fn stream_response_translated(
     byte_stream: BoxByteStream,
@@ -65,14 +354,15 @@ pub async fn stream_response_translated(
     tokio::spawn(async move {
         let mut stream = byte_stream;
         let mut decoder = SseFrameDecoder::new();
-        let mut translation_state = StreamTranslationState::new();
         let mut accumulated_content = String::new();
         let mut accumulated_reasoning = String::new();
         let mut _has_reasoning = false;
-        let mut accumulated_tool_calls: Vec<(String, String, String)> = Vec::new();
         let mut input_tokens: u64 = 0;
         let mut output_tokens: u64 = 0;
+        let mut cache_read_tokens: u64 = 0;
         let stream_start = verify_ctx.as_ref().map(|ctx| ctx.stream_start);
+        // Taken when the first content delta arrives.
+        let mut ttft_from = verify_ctx.as_ref().map(|ctx| ctx.dispatched_at);
         let tier_name = verify_ctx
             .as_ref()
             .map(|ctx| ctx.tier_name.clone())
@@ -82,9 +372,45 @@ pub async fn stream_response_translated(
             .map(|ctx| ctx.stream_idle_timeout)
             .unwrap_or_else(|| std::time::Duration::from_secs(120));
         let mut idle_deadline = tokio::time::Instant::now() + idle_timeout;
+        let mut heartbeat =
+            heartbeat_ticker(verify_ctx.as_ref().and_then(|ctx| ctx.heartbeat_interval));
         let mut first_token_time: Option<std::time::Instant> = None;
         let mut last_token_time: Option<std::time::Instant> = None;
         let mut ended_with_timeout = false;
+        // A nonstandard role is counted once per stream, like a non-streaming
+        // reply, not once per chunk that repeats it.
+        let mut counted_nonstandard_role = false;
+        let mut token_budget = verify_ctx
+            .as_ref()
+            .and_then(|ctx| ctx.max_output_tokens)
+            .map(StreamTokenBudget::new);
+        let mut truncated = false;
+        let mut usage_ticker = verify_ctx
+            .as_ref()
+            .and_then(|ctx| ctx.usage_interval_tokens)
+            .map(StreamUsageTicker::new);
+        let local_estimate = verify_ctx.as_ref().map_or(0, |ctx| ctx.local_estimate);
+        let emit_reasoning = verify_ctx.as_ref().is_none_or(|ctx| ctx.emit_reasoning);
+        let (reasoning_aliases, content_aliases) = verify_ctx
+            .as_ref()
+            .map(|ctx| {
+                (
+                    ctx.reasoning_field_aliases.clone(),
+                    ctx.content_field_aliases.clone(),
+                )
+            })
+            .unwrap_or_default();
+        // Reasoning inside think tags (`minimax`, `thinktag`, `kimi`) is split
+        // out chunk by chunk, so text, thinking, and tool calls keep
+        // streaming in order.
+        let think_tags = chain.stream_think_tags();
+        let mut think_splitter =
+            (!think_tags.is_empty()).then(|| ThinkTagSplitter::new(think_tags));
+        let mut translation_state = if think_splitter.is_some() && emit_reasoning {
+            StreamTranslationState::with_thinking()
+        } else {
+            StreamTranslationState::new()
+        };
 
         loop {
             tokio::select! {
@@ -99,6 +425,11 @@ pub async fn stream_response_translated(
                     ended_with_timeout = true;
                     break;
                 }
+                _ = next_heartbeat(&mut heartbeat) => {
+                    if tx.send(Ok(Bytes::from_static(SSE_HEARTBEAT_FRAME.as_bytes()))).await.is_err() {
+                        break;
+                    }
+                }
                 chunk = stream.next() => {
                     let Some(chunk) = chunk else {
                         break;
@@ -113,13 +444,36 @@ pub async fn stream_response_translated(
                                 }
 
                                 // Try to parse as OpenAI stream chunk
-                                if let Ok(chunk) =
+                                if let Ok(mut chunk) =
                                     serde_json::from_str::<OpenAIStreamChunk>(json_str)
                                 {
-                                    // Accumulate usage info
+                                    apply_delta_field_aliases(
+                                        &mut chunk,
+                                        json_str,
+                                        &reasoning_aliases,
+                                        &content_aliases,
+                                    );
+                                    if let Some(splitter) = think_splitter.as_mut() {
+                                        split_think_tags(splitter, &mut chunk);
+                                    }
+                                    if chunk_has_first_token(&chunk) {
+                                        if let Some(at) = ttft_from.take() {
+                                            record_time_to_first_token(&tier_name, at.elapsed().as_secs_f64());
+                                        }
+                                    }
+                                    if let Some(budget) = token_budget.as_mut() {
+                                        truncated = budget.charge_chunk(&mut chunk);
+                                    }
+
+                                    // Keep the last real usage report. Some providers
+                                    // put zeroed usage on every chunk, which must not
+                                    // overwrite the final include_usage chunk.
                                     if let Some(ref usage) = chunk.usage {
-                                        input_tokens = usage.prompt_tokens;
-                                        output_tokens = usage.completion_tokens;
+                                        if usage.prompt_tokens > 0 || usage.completion_tokens > 0 {
+                                            input_tokens = usage.prompt_tokens;
+                                            output_tokens = usage.completion_tokens;
+                                            cache_read_tokens = usage.cached_tokens();
+                                        }
                                     }
 
                                     let was_first = translation_state.is_first;
@@ -139,7 +493,7 @@ pub async fn stream_response_translated(
                                         );
 
                                         if tx.capacity() == 0 {
-                                            record_stream_backpressure();
+                                            record_stream_backpressure(&tier_name);
                                         }
                                         if tx.send(Ok(Bytes::from(sse_data))).await.is_err() {
                                             break;
@@ -147,7 +501,33 @@ pub async fn stream_response_translated(
                                         forwarded = true;
                                     }
 
+                                    let interim_usage = usage_ticker
+                                        .as_mut()
+                                        .and_then(|ticker| ticker.count_chunk(&chunk));
+                                    if let Some(output) = interim_usage {
+                                        if !translation_state.is_first {
+                                            let event = interim_usage_event(local_estimate, output);
+                                            let event_json =
+                                                serde_json::to_string(&event).unwrap_or_default();
+                                            let sse_data = format!(
+                                                "event: {}\ndata: {}\n\n",
+                                                event.event_type, event_json
+                                            );
+                                            if tx.send(Ok(Bytes::from(sse_data))).await.is_err() {
+                                                break;
+                                            }
+                                        }
+                                    }
+
                                     if let Some(choice) = chunk.choices.first() {
+                                        // Translation always emits `assistant`;
+                                        // count providers that said otherwise.
+                                        if !counted_nonstandard_role
+                                            && is_nonstandard_role(choice.delta.role.as_deref())
+                                        {
+                                            record_nonstandard_role(&tier_name);
+                                            counted_nonstandard_role = true;
+                                        }
                                         if let Some(ref content) = choice.delta.content {
                                             accumulated_content.push_str(content);
                                         }
@@ -156,29 +536,9 @@ pub async fn stream_response_translated(
                                             accumulated_reasoning.push_str(reasoning);
                                             _has_reasoning = true;
                                         }
-                                        if let Some(ref tool_calls) = choice.delta.tool_calls {
-                                            for tc in tool_calls {
-                                                let idx = tc.index;
-                                                while accumulated_tool_calls.len() <= idx {
-                                                    accumulated_tool_calls.push((
-                                                        String::new(),
-                                                        String::new(),
-                                                        String::new(),
-                                                    ));
-                                                }
-                                                if let Some(ref id) = tc.id {
-                                                    accumulated_tool_calls[idx].0.clone_from(id);
-                                                }
-                                                if let Some(ref func) = tc.function {
-                                                    if let Some(ref name) = func.name {
-                                                        accumulated_tool_calls[idx].1.clone_from(name);
-                                                    }
-                                                    if let Some(ref args) = func.arguments {
-                                                        accumulated_tool_calls[idx].2.push_str(args);
-                                                    }
-                                                }
-                                            }
-                                        }
+                                    }
+                                    if truncated {
+                                        break;
                                     }
                                 } else {
                                     // Pass through frames that don't parse as OpenAI chunks.
@@ -192,6 +552,19 @@ pub async fn stream_response_translated(
                             if forwarded {
                                 idle_deadline = tokio::time::Instant::now() + idle_timeout;
                             }
+                            if forwarded {
+                                if let Some(ticker) = heartbeat.as_mut() {
+                                    ticker.reset();
+                                }
+                            }
+                            if truncated {
+                                warn!(
+                                    tier = %tier_name,
+                                    max_tokens = token_budget.as_ref().map(|b| b.limit),
+                                    "Upstream stream exceeded max_tokens, truncating"
+                                );
+                                break;
+                            }
                         }
                         Err(e) => {
                             let _ = tx
@@ -213,6 +586,36 @@ pub async fn stream_response_translated(
             return;
         }
 
+        // The finishing chunk releases a held-back partial tag; a stream that
+        // never sent one still owes it to the client.
+        if let Some(chunk) = think_splitter
+            .as_mut()
+            .filter(|_| !truncated)
+            .and_then(flush_think_splitter)
+        {
+            if let Some(choice) = chunk.choices.first() {
+                if let Some(ref content) = choice.delta.content {
+                    accumulated_content.push_str(content);
+                }
+                if let Some(ref reasoning) = choice.delta.reasoning_content {
+                    accumulated_reasoning.push_str(reasoning);
+                }
+            }
+            for event in translate_stream_chunk_to_anthropic(&chunk, &mut translation_state) {
+                let event_json = serde_json::to_string(&event).unwrap_or_default();
+                let sse_data = format!("event: {}\ndata: {}\n\n", event.event_type, event_json);
+                let _ = tx.send(Ok(Bytes::from(sse_data))).await;
+            }
+        }
+
+        // A truncated stream stops before the provider's final usage chunk,
+        // and the client only received the budgeted output.
+        if truncated {
+            if let Some(budget) = &token_budget {
+                output_tokens = budget.used;
+            }
+        }
+
         // Send final stop events
         let mut usage = if input_tokens > 0 || output_tokens > 0 {
             Some(AnthropicUsage {
@@ -228,44 +631,11 @@ pub async fn stream_response_translated(
             })
         };
 
-        if !chain.is_empty() {
-            let mut content_blocks = Vec::new();
-            if !accumulated_content.is_empty() {
-                content_blocks.push(serde_json::json!({
-                    "type": "text",
-                    "text": accumulated_content
-                }));
-            }
-            for (id, name, arguments) in &accumulated_tool_calls {
-                let input = serde_json::from_str::<serde_json::Value>(arguments)
-                    .unwrap_or_else(|_| serde_json::json!({"raw_arguments": arguments}));
-                content_blocks.push(serde_json::json!({
-                    "type": "tool_use",
-                    "id": id,
-                    "name": name,
-                    "input": input
-                }));
-            }
-
-            let resp_value = serde_json::json!({
-                "content": content_blocks,
-                "usage": {
-                    "input_tokens": input_tokens,
-                    "output_tokens": output_tokens
-                }
-            });
-
-            if let Ok(transformed) = chain.apply_response(resp_value) {
-                trace!(transformed_response = %serde_json::to_string(&transformed).unwrap_or_default(),
-                       "streaming response transformed");
-            }
-        }
-
-        // OpenAI-compatible streams may omit the final usage chunk, leaving
-        // input_tokens == 0 (translated Anthropic→OpenAI requests do not set
-        // stream_options.include_usage). Fall back to the pre-request estimate
-        // BEFORE serializing the stop events so the client-visible SSE usage
-        // matches what /v1/usage and cost accounting record, mirroring
+        // OpenAI-compatible streams may omit the final usage chunk even when
+        // stream_options.include_usage is set, leaving input_tokens == 0.
+        // Fall back to the pre-request estimate BEFORE serializing the stop
+        // events so the client-visible SSE usage matches what /v1/usage and
+        // cost accounting record, mirroring
         // stream_anthropic_response_with_tracking. Drift verification below
         // deliberately keeps the raw upstream value.
         if let (Some(ctx), Some(usage)) = (&verify_ctx, usage.as_mut()) {
@@ -294,7 +664,7 @@ pub async fn stream_response_translated(
                     &ctx.tier_name,
                     usage.input_tokens,
                     usage.output_tokens,
-                    0,
+                    cache_read_tokens,
                     0,
                 );
                 verify_token_usage(&ctx.tier_name, ctx.local_estimate, input_tokens);
@@ -369,8 +739,11 @@ pub async fn stream_anthropic_response_with_tracking(
         let mut accumulated_content_len: usize = 0;
         // TTFT/throughput timing
         let stream_start = verify_ctx.stream_start;
+        // Taken when the first content delta arrives.
+        let mut ttft_from = Some(verify_ctx.dispatched_at);
         let idle_timeout = verify_ctx.stream_idle_timeout;
         let mut idle_deadline = tokio::time::Instant::now() + idle_timeout;
+        let mut heartbeat = heartbeat_ticker(verify_ctx.heartbeat_interval);
         let mut first_token_time: Option<std::time::Instant> = None;
         let mut last_token_time: Option<std::time::Instant> = None;
         let mut ended_with_timeout = false;
@@ -388,6 +761,11 @@ pub async fn stream_anthropic_response_with_tracking(
                     ended_with_timeout = true;
                     break;
                 }
+                _ = next_heartbeat(&mut heartbeat) => {
+                    if tx.send(Ok(Bytes::from_static(SSE_HEARTBEAT_FRAME.as_bytes()))).await.is_err() {
+                        break;
+                    }
+                }
                 chunk = stream.next() => {
                     let Some(chunk) = chunk else {
                         break;
@@ -403,6 +781,12 @@ pub async fn stream_anthropic_response_with_tracking(
 
                                 // Parse Anthropic SSE events to extract usage
                                 if let Ok(event) = serde_json::from_str::<serde_json::Value>(json_str) {
+                                    if event_has_first_token(&event) {
+                                        if let Some(at) = ttft_from.take() {
+                                            record_time_to_first_token(&tier_name, at.elapsed().as_secs_f64());
+                                        }
+                                    }
+
                                     // Extract usage from message_delta events
                                     if let Some(usage) = event.get("usage") {
                                         if let Some(input) = usage.get("input_tokens").and_then(|v| v.as_u64()) {
@@ -465,7 +849,7 @@ pub async fn stream_anthropic_response_with_tracking(
                                 };
 
                                 if tx.capacity() == 0 {
-                                    record_stream_backpressure();
+                                    record_stream_backpressure(&tier_name);
                                 }
                                 if tx.send(Ok(Bytes::from(sse_data))).await.is_err() {
                                     break;
@@ -474,6 +858,9 @@ pub async fn stream_anthropic_response_with_tracking(
                             }
                             if forwarded {
                                 idle_deadline = tokio::time::Instant::now() + idle_timeout;
+                                if let Some(ticker) = heartbeat.as_mut() {
+                                    ticker.reset();
+                                }
                             }
                         }
                         Err(e) => {
@@ -680,6 +1067,30 @@ fn emit_anthropic_sse_events(resp: &AnthropicResponse) -> Vec<String> {
                     sig_delta
                 ));
             }
+            AnthropicContentBlock::Audio { audio } => {
+                // Audio has no delta form; the start event carries all of it.
+                let block_start = serde_json::json!({
+                    "type": "content_block_start",
+                    "index": idx,
+                    "content_block": {"type": "audio", "audio": audio}
+                });
+                events.push(format!(
+                    "event: content_block_start\ndata: {}\n\n",
+                    block_start
+                ));
+            }
+            AnthropicContentBlock::Image { source } => {
+                // Likewise for images.
+                let block_start = serde_json::json!({
+                    "type": "content_block_start",
+                    "index": idx,
+                    "content_block": {"type": "image", "source": source}
+                });
+                events.push(format!(
+                    "event: content_block_start\ndata: {}\n\n",
+                    block_start
+                ));
+            }
         }
 
         // content_block_stop (common to all block types)
@@ -900,4 +1311,429 @@ mod tests {
             "expected 3 content_block_stop events"
         );
     }
+
+    fn verify_ctx_with_heartbeat(period_ms: u64) -> StreamVerifyCtx {
+        StreamVerifyCtx {
+            tier_name: "tier-0".to_string(),
+            local_estimate: 0,
+            ratelimit_tracker: None,
+            rate_limit_info: None,
+            stream_start: std::time::Instant::now(),
+            dispatched_at: std::time::Instant::now(),
+            stream_idle_timeout: std::time::Duration::from_secs(5),
+            heartbeat_interval: Some(std::time::Duration::from_millis(period_ms)),
+            pricing: None,
+            max_output_tokens: None,
+            reasoning_field_aliases: Vec::new(),
+            emit_reasoning: true,
+            content_field_aliases: Vec::new(),
+            usage_interval_tokens: None,
+        }
+    }
+
+    /// Yields each frame after the paired delay, simulating an upstream that
+    /// goes quiet mid-stream (e.g. during a long reasoning pause).
+    fn delayed_stream(frames: Vec<(u64, &'static str)>) -> BoxByteStream {
+        Box::pin(futures::stream::unfold(
+            frames.into_iter(),
+            |mut frames| async move {
+                let (delay_ms, frame) = frames.next()?;
+                tokio::time::sleep(std::time::Duration::from_millis(delay_ms)).await;
+                Some((Ok(Bytes::from_static(frame.as_bytes())), frames))
+            },
+        ))
+    }
+
+    async fn collect_body(response: Response) -> String {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_translated_stream_sends_heartbeat_while_idle() {
+        let stream = delayed_stream(vec![
+            (
+                0,
+                "data: {\"id\":\"c1\",\"object\":\"chat.completion.chunk\",\"created\":0,\"model\":\"m\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hi\"},\"finish_reason\":null}]}\n\n",
+            ),
+            (
+                150,
+                "data: {\"id\":\"c1\",\"object\":\"chat.completion.chunk\",\"created\":0,\"model\":\"m\",\"choices\":[{\"index\":0,\"delta\":{},\"finish_reason\":\"stop\"}],\"usage\":{\"prompt_tokens\":7,\"completion_tokens\":3}}\n\n",
+            ),
+            (0, "data: [DONE]\n\n"),
+        ]);
+
+        let response = stream_response_translated(
+            stream,
+            8,
+            Some(verify_ctx_with_heartbeat(30)),
+            "m",
+            TransformerChain::new(),
+        )
+        .await;
+        let body = collect_body(response).await;
+
+        let last_ping = body.rfind(": ping\n\n").expect("expected a heartbeat");
+        let message_stop = body.find("event: message_stop").unwrap();
+        assert!(last_ping < message_stop, "heartbeat after message_stop");
+        assert!(body.ends_with("\n\n"));
+        assert!(body.contains("\"output_tokens\":3"), "usage was disturbed");
+    }
+
+    #[tokio::test]
+    async fn test_anthropic_stream_sends_heartbeat_while_idle() {
+        let stream = delayed_stream(vec![
+            (
+                0,
+                "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hi\"}}\n\n",
+            ),
+            (
+                150,
+                "event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n",
+            ),
+        ]);
+
+        let response = stream_anthropic_response_with_tracking(
+            stream,
+            8,
+            verify_ctx_with_heartbeat(30),
+            TransformerChain::new(),
+        )
+        .await;
+        let body = collect_body(response).await;
+
+        assert!(body.contains(": ping\n\n"), "expected a heartbeat");
+        assert!(body.ends_with("event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n"));
+    }
+
+    #[tokio::test]
+    async fn test_stream_without_heartbeat_sends_no_comments() {
+        let stream = delayed_stream(vec![(
+            80,
+            "event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n",
+        )]);
+        let mut ctx = verify_ctx_with_heartbeat(10);
+        ctx.heartbeat_interval = None;
+
+        let response =
+            stream_anthropic_response_with_tracking(stream, 8, ctx, TransformerChain::new()).await;
+        let body = collect_body(response).await;
+
+        assert!(!body.contains(": ping"));
+    }
+
+    fn text_deltas(body: &str) -> String {
+        body.split("\n\n")
+            .filter_map(|frame| frame.lines().find_map(|l| l.strip_prefix("data: ")))
+            .filter_map(|data| serde_json::from_str::<serde_json::Value>(data).ok())
+            .filter(|event| event["delta"]["type"] == "text_delta")
+            .filter_map(|event| event["delta"]["text"].as_str().map(str::to_string))
+            .collect()
+    }
+
+    fn sse_events(body: &str) -> Vec<serde_json::Value> {
+        body.split("\n\n")
+            .filter_map(|frame| frame.lines().find_map(|l| l.strip_prefix("data: ")))
+            .filter_map(|data| serde_json::from_str(data).ok())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_translated_stream_applies_think_tag_transformer() {
+        let stream = delayed_stream(vec![
+            (
+                0,
+                "data: {\"id\":\"c1\",\"object\":\"chat.completion.chunk\",\"created\":0,\"model\":\"m\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"<thinking>plan the\"},\"finish_reason\":null}]}\n\n",
+            ),
+            (
+                0,
+                "data: {\"id\":\"c1\",\"object\":\"chat.completion.chunk\",\"created\":0,\"model\":\"m\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\" answer</thinking>Hello\"},\"finish_reason\":null}]}\n\n",
+            ),
+            (
+                0,
+                "data: {\"id\":\"c1\",\"object\":\"chat.completion.chunk\",\"created\":0,\"model\":\"m\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\", world\"},\"finish_reason\":null}]}\n\n",
+            ),
+            (
+                0,
+                "data: {\"id\":\"c1\",\"object\":\"chat.completion.chunk\",\"created\":0,\"model\":\"m\",\"choices\":[{\"index\":0,\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"call_1\",\"type\":\"function\",\"function\":{\"name\":\"ls\",\"arguments\":\"{}\"}}]},\"finish_reason\":\"tool_calls\"}]}\n\n",
+            ),
+            (0, "data: [DONE]\n\n"),
+        ]);
+        let chain = TransformerChain::new()
+            .with_transformer(std::sync::Arc::new(crate::transformer::ThinkTagTransformer));
+
+        let response = stream_response_translated(stream, 8, None, "m", chain).await;
+        let body = collect_body(response).await;
+        let events = sse_events(&body);
+
+        // Text streams delta by delta instead of arriving in one piece at
+        // the end.
+        assert_eq!(body.matches("\"type\":\"text_delta\"").count(), 2);
+        assert_eq!(text_deltas(&body), "Hello, world");
+        assert!(!body.contains("thinking>"), "think tags leaked: {body}");
+        let thinking: String = events
+            .iter()
+            .filter(|event| event["delta"]["type"] == "thinking_delta")
+            .filter_map(|event| event["delta"]["thinking"].as_str())
+            .collect();
+        assert_eq!(thinking, "plan the answer");
+
+        let starts: Vec<&str> = events
+            .iter()
+            .filter(|event| event["type"] == "content_block_start")
+            .filter_map(|event| event["content_block"]["type"].as_str())
+            .collect();
+        assert_eq!(starts, ["thinking", "text", "tool_use"]);
+        let last_text = body.rfind("\"type\":\"text_delta\"").unwrap();
+        let tool_start = body.find("\"type\":\"tool_use\"").unwrap();
+        assert!(last_text < tool_start);
+    }
+
+    #[tokio::test]
+    async fn test_translated_stream_splits_kimi_think_tokens() {
+        let stream = delayed_stream(vec![
+            (
+                0,
+                "data: {\"id\":\"c1\",\"object\":\"chat.completion.chunk\",\"created\":0,\"model\":\"m\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"◁think▷plan◁/th\"},\"finish_reason\":null}]}\n\n",
+            ),
+            (
+                0,
+                "data: {\"id\":\"c1\",\"object\":\"chat.completion.chunk\",\"created\":0,\"model\":\"m\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"ink▷Hello\"},\"finish_reason\":\"stop\"}]}\n\n",
+            ),
+            (0, "data: [DONE]\n\n"),
+        ]);
+        let chain = TransformerChain::new()
+            .with_transformer(std::sync::Arc::new(crate::transform::KimiTransformer));
+
+        let response = stream_response_translated(stream, 8, None, "m", chain).await;
+        let body = collect_body(response).await;
+
+        assert_eq!(text_deltas(&body), "Hello");
+        assert!(!body.contains("think▷"), "think tokens leaked: {body}");
+        let thinking: String = sse_events(&body)
+            .iter()
+            .filter(|event| event["delta"]["type"] == "thinking_delta")
+            .filter_map(|event| event["delta"]["thinking"].as_str().map(str::to_string))
+            .collect();
+        assert_eq!(thinking, "plan");
+    }
+
+    #[tokio::test]
+    async fn test_translated_stream_splits_think_tags_across_chunks() {
+        let stream = delayed_stream(vec![
+            (
+                0,
+                "data: {\"id\":\"c1\",\"object\":\"chat.completion.chunk\",\"created\":0,\"model\":\"m\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"<thi\"},\"finish_reason\":null}]}\n\n",
+            ),
+            (
+                0,
+                "data: {\"id\":\"c1\",\"object\":\"chat.completion.chunk\",\"created\":0,\"model\":\"m\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"nk>plan the\"},\"finish_reason\":null}]}\n\n",
+            ),
+            (
+                0,
+                "data: {\"id\":\"c1\",\"object\":\"chat.completion.chunk\",\"created\":0,\"model\":\"m\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\" answer</th\"},\"finish_reason\":null}]}\n\n",
+            ),
+            (
+                0,
+                "data: {\"id\":\"c1\",\"object\":\"chat.completion.chunk\",\"created\":0,\"model\":\"m\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"ink>Hello, world\"},\"finish_reason\":\"stop\"}]}\n\n",
+            ),
+            (0, "data: [DONE]\n\n"),
+        ]);
+        let chain = TransformerChain::new()
+            .with_transformer(std::sync::Arc::new(crate::transform::MinimaxTransformer));
+
+        let response = stream_response_translated(stream, 8, None, "m", chain).await;
+        let body = collect_body(response).await;
+
+        let events = sse_events(&body);
+        let thinking: String = events
+            .iter()
+            .filter(|event| event["delta"]["type"] == "thinking_delta")
+            .inspect(|event| assert_eq!(event["index"], 0))
+            .filter_map(|event| event["delta"]["thinking"].as_str())
+            .collect();
+        assert_eq!(thinking, "plan the answer");
+        assert_eq!(text_deltas(&body), "Hello, world");
+        assert!(!body.contains("think>"), "think tags leaked: {body}");
+
+        let starts: Vec<&str> = events
+            .iter()
+            .filter(|event| event["type"] == "content_block_start")
+            .filter_map(|event| event["content_block"]["type"].as_str())
+            .collect();
+        assert_eq!(starts, ["thinking", "text"]);
+        let stops: Vec<u64> = events
+            .iter()
+            .filter(|event| event["type"] == "content_block_stop")
+            .filter_map(|event| event["index"].as_u64())
+            .collect();
+        assert_eq!(stops, [0, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_translated_stream_flushes_partial_tag_without_finish_reason() {
+        let stream = delayed_stream(vec![
+            (
+                0,
+                "data: {\"id\":\"c1\",\"object\":\"chat.completion.chunk\",\"created\":0,\"model\":\"m\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"<think>plan</think>Hello <\"},\"finish_reason\":null}]}\n\n",
+            ),
+            (
+                0,
+                "data: {\"id\":\"c1\",\"object\":\"chat.completion.chunk\",\"created\":0,\"model\":\"m\",\"choices\":[],\"usage\":{\"prompt_tokens\":7,\"completion_tokens\":3}}\n\n",
+            ),
+            (0, "data: [DONE]\n\n"),
+        ]);
+        let chain = TransformerChain::new()
+            .with_transformer(std::sync::Arc::new(crate::transform::MinimaxTransformer));
+
+        let response = stream_response_translated(stream, 8, None, "m", chain).await;
+        let body = collect_body(response).await;
+
+        assert_eq!(text_deltas(&body), "Hello <");
+        let last_delta = body.rfind("\"type\":\"text_delta\"").unwrap();
+        assert!(last_delta < body.find("event: message_delta").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_translated_stream_without_think_tags_streams_deltas() {
+        let stream = delayed_stream(vec![
+            (
+                0,
+                "data: {\"id\":\"c1\",\"object\":\"chat.completion.chunk\",\"created\":0,\"model\":\"m\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"<think>a</think>\"},\"finish_reason\":null}]}\n\n",
+            ),
+            (
+                0,
+                "data: {\"id\":\"c1\",\"object\":\"chat.completion.chunk\",\"created\":0,\"model\":\"m\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"b\"},\"finish_reason\":\"stop\"}]}\n\n",
+            ),
+        ]);
+        let chain = TransformerChain::new()
+            .with_transformer(std::sync::Arc::new(crate::transformer::IdentityTransformer));
+
+        let response = stream_response_translated(stream, 8, None, "m", chain).await;
+        let body = collect_body(response).await;
+
+        assert_eq!(body.matches("\"type\":\"text_delta\"").count(), 2);
+        assert_eq!(text_deltas(&body), "<think>a</think>b");
+    }
+
+    #[tokio::test]
+    async fn test_translated_stream_emits_interim_usage() {
+        let stream = delayed_stream(vec![
+            (
+                0,
+                "data: {\"id\":\"c1\",\"object\":\"chat.completion.chunk\",\"created\":0,\"model\":\"m\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"one two three four\"},\"finish_reason\":null}]}\n\n",
+            ),
+            (
+                0,
+                "data: {\"id\":\"c1\",\"object\":\"chat.completion.chunk\",\"created\":0,\"model\":\"m\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\" five\"},\"finish_reason\":null}]}\n\n",
+            ),
+            (
+                0,
+                "data: {\"id\":\"c1\",\"object\":\"chat.completion.chunk\",\"created\":0,\"model\":\"m\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\" six seven eight\"},\"finish_reason\":\"stop\"}],\"usage\":{\"prompt_tokens\":7,\"completion_tokens\":8}}\n\n",
+            ),
+        ]);
+        let mut ctx = verify_ctx_with_heartbeat(1000);
+        ctx.heartbeat_interval = None;
+        ctx.usage_interval_tokens = Some(3);
+
+        let response =
+            stream_response_translated(stream, 8, Some(ctx), "m", TransformerChain::new()).await;
+        let body = collect_body(response).await;
+
+        let deltas: Vec<serde_json::Value> = body
+            .split("\n\n")
+            .filter(|frame| frame.starts_with("event: message_delta"))
+            .filter_map(|frame| frame.lines().find_map(|l| l.strip_prefix("data: ")))
+            .map(|data| serde_json::from_str(data).unwrap())
+            .collect();
+        // Interim reports after the first and third chunks, then the final one.
+        assert_eq!(deltas.len(), 3, "{body}");
+        assert!(deltas[0]["delta"]["stop_reason"].is_null());
+        assert!(deltas[1]["delta"]["stop_reason"].is_null());
+        let first = deltas[0]["usage"]["output_tokens"].as_u64().unwrap();
+        let second = deltas[1]["usage"]["output_tokens"].as_u64().unwrap();
+        assert!(first >= 3 && second >= first + 3);
+        assert_eq!(deltas[2]["delta"]["stop_reason"], "end_turn");
+        assert_eq!(deltas[2]["usage"]["output_tokens"], 8);
+    }
+
+    #[tokio::test]
+    async fn test_translated_stream_without_usage_interval_sends_one_message_delta() {
+        let stream = delayed_stream(vec![(
+            0,
+            "data: {\"id\":\"c1\",\"object\":\"chat.completion.chunk\",\"created\":0,\"model\":\"m\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"one two three four\"},\"finish_reason\":\"stop\"}]}\n\n",
+        )]);
+        let mut ctx = verify_ctx_with_heartbeat(1000);
+        ctx.heartbeat_interval = None;
+
+        let response =
+            stream_response_translated(stream, 8, Some(ctx), "m", TransformerChain::new()).await;
+        let body = collect_body(response).await;
+
+        assert_eq!(body.matches("event: message_delta").count(), 1);
+    }
+
+    fn ttft_samples(tier: &str) -> u64 {
+        prometheus::gather()
+            .iter()
+            .filter(|family| family.get_name() == "ccr_time_to_first_token_seconds")
+            .flat_map(|family| family.get_metric())
+            .filter(|metric| metric.get_label().iter().any(|l| l.get_value() == tier))
+            .map(|metric| metric.get_histogram().get_sample_count())
+            .sum()
+    }
+
+    #[tokio::test]
+    async fn test_tool_only_stream_records_time_to_first_token() {
+        let stream = delayed_stream(vec![
+            (
+                0,
+                "data: {\"id\":\"c1\",\"object\":\"chat.completion.chunk\",\"created\":0,\"model\":\"m\",\"choices\":[{\"index\":0,\"delta\":{\"role\":\"assistant\"},\"finish_reason\":null}]}\n\n",
+            ),
+            (
+                0,
+                "data: {\"id\":\"c1\",\"object\":\"chat.completion.chunk\",\"created\":0,\"model\":\"m\",\"choices\":[{\"index\":0,\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"call_1\",\"type\":\"function\",\"function\":{\"name\":\"ls\",\"arguments\":\"{}\"}}]},\"finish_reason\":null}]}\n\n",
+            ),
+            (
+                0,
+                "data: {\"id\":\"c1\",\"object\":\"chat.completion.chunk\",\"created\":0,\"model\":\"m\",\"choices\":[{\"index\":0,\"delta\":{},\"finish_reason\":\"tool_calls\"}]}\n\n",
+            ),
+            (0, "data: [DONE]\n\n"),
+        ]);
+        let mut ctx = verify_ctx_with_heartbeat(1000);
+        ctx.tier_name = "ttft-tool-only".to_string();
+
+        let response =
+            stream_response_translated(stream, 8, Some(ctx), "m", TransformerChain::new()).await;
+        collect_body(response).await;
+
+        assert_eq!(ttft_samples("ttft-tool-only"), 1);
+    }
+
+    #[test]
+    fn test_anthropic_first_token_events() {
+        let tool_start = serde_json::json!({
+            "type": "content_block_start",
+            "index": 0,
+            "content_block": {"type": "tool_use", "id": "toolu_1", "name": "ls", "input": {}}
+        });
+        assert!(event_has_first_token(&tool_start));
+        let empty_text_start = serde_json::json!({
+            "type": "content_block_start",
+            "index": 0,
+            "content_block": {"type": "text", "text": ""}
+        });
+        assert!(!event_has_first_token(&empty_text_start));
+        let thinking = serde_json::json!({
+            "type": "content_block_delta",
+            "index": 0,
+            "delta": {"type": "thinking_delta", "thinking": "hm"}
+        });
+        assert!(event_has_first_token(&thinking));
+        assert!(!event_has_first_token(
+            &serde_json::json!({"type": "message_start", "message": {}})
+        ));
+    }
 }
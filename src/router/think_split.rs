@@ -0,0 +1,203 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Streaming split of think-tagged content into reasoning and text.
+//
+// Some providers interleave their reasoning with the answer in `content`
+// rather than sending `reasoning_content`: MiniMax and DeepSeek-style models
+// use `<think>` tags, Kimi uses `◁think▷`. In a stream the tags can be cut
+// anywhere, e.g. `<thi` at the end of one chunk and `nk>` at the start of the
+// next, so the splitter holds back a trailing partial tag until the next
+// chunk shows whether it really is one.
+
+/// One content delta divided into its reasoning and answer text.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(super) struct ThinkSplit {
+    pub thinking: String,
+    pub text: String,
+}
+
+/// Splits streamed content deltas on start/end tag pairs, keeping state
+/// across chunks.
+#[derive(Debug)]
+pub(super) struct ThinkTagSplitter {
+    tags: Vec<(&'static str, &'static str)>,
+    /// End tag of the think block currently open.
+    open_end: Option<&'static str>,
+    /// Possible start of a tag at the end of the previous chunk.
+    pending: String,
+}
+
+impl ThinkTagSplitter {
+    pub(super) fn new(tags: Vec<(&'static str, &'static str)>) -> Self {
+        Self {
+            tags,
+            open_end: None,
+            pending: String::new(),
+        }
+    }
+
+    pub(super) fn push(&mut self, chunk: &str) -> ThinkSplit {
+        let mut input = std::mem::take(&mut self.pending);
+        input.push_str(chunk);
+
+        let mut split = ThinkSplit::default();
+        let mut rest = input.as_str();
+        loop {
+            if let Some(end) = self.open_end {
+                if let Some(at) = rest.find(end) {
+                    split.thinking.push_str(&rest[..at]);
+                    rest = &rest[at + end.len()..];
+                    self.open_end = None;
+                } else {
+                    let keep = trailing_partial_len(rest, end);
+                    split.thinking.push_str(&rest[..rest.len() - keep]);
+                    self.pending.push_str(&rest[rest.len() - keep..]);
+                    break;
+                }
+            } else if let Some((at, start, end)) = self
+                .tags
+                .iter()
+                .filter_map(|&(start, end)| rest.find(start).map(|at| (at, start, end)))
+                .min_by_key(|&(at, _, _)| at)
+            {
+                split.text.push_str(&rest[..at]);
+                rest = &rest[at + start.len()..];
+                self.open_end = Some(end);
+            } else {
+                let keep = self
+                    .tags
+                    .iter()
+                    .map(|&(start, _)| trailing_partial_len(rest, start))
+                    .max()
+                    .unwrap_or(0);
+                split.text.push_str(&rest[..rest.len() - keep]);
+                self.pending.push_str(&rest[rest.len() - keep..]);
+                break;
+            }
+        }
+        split
+    }
+
+    /// Release a held-back partial tag once the stream is done; it turned out
+    /// to be plain content.
+    pub(super) fn finish(&mut self) -> ThinkSplit {
+        let pending = std::mem::take(&mut self.pending);
+        if self.open_end.is_some() {
+            ThinkSplit {
+                thinking: pending,
+                text: String::new(),
+            }
+        } else {
+            ThinkSplit {
+                thinking: String::new(),
+                text: pending,
+            }
+        }
+    }
+}
+
+/// Length of the longest suffix of `text` that is a proper prefix of `tag`.
+fn trailing_partial_len(text: &str, tag: &str) -> usize {
+    (1..tag.len().min(text.len() + 1))
+        .rev()
+        .find(|&len| {
+            text.get(text.len() - len..)
+                .is_some_and(|suffix| tag.starts_with(suffix))
+        })
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn think_splitter() -> ThinkTagSplitter {
+        ThinkTagSplitter::new(vec![("<think>", "</think>")])
+    }
+
+    fn push_all(chunks: &[&str]) -> (String, String) {
+        let mut splitter = think_splitter();
+        let (mut thinking, mut text) = (String::new(), String::new());
+        for chunk in chunks {
+            let split = splitter.push(chunk);
+            thinking.push_str(&split.thinking);
+            text.push_str(&split.text);
+        }
+        let rest = splitter.finish();
+        thinking.push_str(&rest.thinking);
+        text.push_str(&rest.text);
+        (thinking, text)
+    }
+
+    #[test]
+    fn splits_tags_within_one_chunk() {
+        assert_eq!(
+            push_all(&["<think>plan</think>Hello"]),
+            ("plan".to_string(), "Hello".to_string())
+        );
+    }
+
+    #[test]
+    fn holds_back_tags_cut_across_chunks() {
+        let mut splitter = think_splitter();
+        assert_eq!(splitter.push("<thi"), ThinkSplit::default());
+        assert_eq!(
+            splitter.push("nk>plan the"),
+            ThinkSplit {
+                thinking: "plan the".to_string(),
+                text: String::new(),
+            }
+        );
+        assert_eq!(
+            splitter.push(" answer</th"),
+            ThinkSplit {
+                thinking: " answer".to_string(),
+                text: String::new(),
+            }
+        );
+        assert_eq!(
+            splitter.push("ink>Hello"),
+            ThinkSplit {
+                thinking: String::new(),
+                text: "Hello".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn interleaved_reasoning() {
+        assert_eq!(
+            push_all(&["a<thi", "nk>b</think>c<", "think>d</", "think>e"]),
+            ("bd".to_string(), "ace".to_string())
+        );
+    }
+
+    #[test]
+    fn releases_text_that_only_looked_like_a_tag() {
+        let mut splitter = think_splitter();
+        assert_eq!(splitter.push("x <").text, "x ");
+        assert_eq!(splitter.push("y").text, "<y");
+        assert_eq!(splitter.push("1 <th").text, "1 ");
+        assert_eq!(splitter.finish().text, "<th");
+    }
+
+    #[test]
+    fn splits_each_configured_tag_pair() {
+        let mut splitter = ThinkTagSplitter::new(vec![
+            ("<think>", "</think>"),
+            ("<thinking>", "</thinking>"),
+            ("◁think▷", "◁/think▷"),
+        ]);
+        let mut thinking = String::new();
+        let mut text = String::new();
+        for chunk in [
+            "<thinking>a</think",
+            "ing>b◁th",
+            "ink▷c◁/think▷d<think>e</think>",
+        ] {
+            let split = splitter.push(chunk);
+            thinking.push_str(&split.thinking);
+            text.push_str(&split.text);
+        }
+        assert_eq!((thinking.as_str(), text.as_str()), ("ace", "bd"));
+    }
+}
@@ -0,0 +1,271 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Transformer dry-run for `POST /v1/transformers/preview`.
+//
+// Runs a sample body through a transformer chain without contacting any
+// provider, and reports what changed as a list of JSON Pointer paths. The
+// chain is either given inline or taken from a configured provider/model.
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::config::TransformerEntry;
+
+use super::translate_response::build_transformer_chain;
+use super::AppState;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum PreviewDirection {
+    #[default]
+    Request,
+    Response,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PreviewRequest {
+    /// Sample request or response body to transform.
+    body: Value,
+    /// Inline chain, using the same entry syntax as provider config.
+    #[serde(default)]
+    transformers: Option<Vec<TransformerEntry>>,
+    /// Use the chain configured for this provider instead.
+    #[serde(default)]
+    provider: Option<String>,
+    /// Model whose `model_transformers` are appended to the provider chain.
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    direction: PreviewDirection,
+}
+
+/// One changed path between the input and the transformed body.
+#[derive(Debug, PartialEq)]
+pub(super) enum JsonChange {
+    Added {
+        path: String,
+        value: Value,
+    },
+    Removed {
+        path: String,
+        value: Value,
+    },
+    Modified {
+        path: String,
+        before: Value,
+        after: Value,
+    },
+}
+
+impl JsonChange {
+    fn to_json(&self) -> Value {
+        match self {
+            Self::Added { path, value } => json!({"op": "added", "path": path, "value": value}),
+            Self::Removed { path, value } => {
+                json!({"op": "removed", "path": path, "value": value})
+            }
+            Self::Modified {
+                path,
+                before,
+                after,
+            } => json!({"op": "modified", "path": path, "before": before, "after": after}),
+        }
+    }
+}
+
+/// Escape a key for use as a JSON Pointer (RFC 6901) segment.
+fn pointer_segment(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+/// Recursively compare two JSON values, recording changes at the deepest
+/// path where they differ. Arrays are compared index by index.
+pub(super) fn json_diff(before: &Value, after: &Value) -> Vec<JsonChange> {
+    let mut changes = Vec::new();
+    diff_at("", before, after, &mut changes);
+    changes
+}
+
+fn diff_at(path: &str, before: &Value, after: &Value, changes: &mut Vec<JsonChange>) {
+    match (before, after) {
+        (Value::Object(old), Value::Object(new)) => {
+            for (key, old_value) in old {
+                let child = format!("{path}/{}", pointer_segment(key));
+                match new.get(key) {
+                    Some(new_value) => diff_at(&child, old_value, new_value, changes),
+                    None => changes.push(JsonChange::Removed {
+                        path: child,
+                        value: old_value.clone(),
+                    }),
+                }
+            }
+            for (key, new_value) in new {
+                if !old.contains_key(key) {
+                    changes.push(JsonChange::Added {
+                        path: format!("{path}/{}", pointer_segment(key)),
+                        value: new_value.clone(),
+                    });
+                }
+            }
+        }
+        (Value::Array(old), Value::Array(new)) => {
+            for (i, old_value) in old.iter().enumerate() {
+                let child = format!("{path}/{i}");
+                match new.get(i) {
+                    Some(new_value) => diff_at(&child, old_value, new_value, changes),
+                    None => changes.push(JsonChange::Removed {
+                        path: child,
+                        value: old_value.clone(),
+                    }),
+                }
+            }
+            for (i, new_value) in new.iter().enumerate().skip(old.len()) {
+                changes.push(JsonChange::Added {
+                    path: format!("{path}/{i}"),
+                    value: new_value.clone(),
+                });
+            }
+        }
+        _ if before != after => changes.push(JsonChange::Modified {
+            path: path.to_string(),
+            before: before.clone(),
+            after: after.clone(),
+        }),
+        _ => {}
+    }
+}
+
+fn bad_request(message: String) -> Response {
+    (StatusCode::BAD_REQUEST, Json(json!({"error": message}))).into_response()
+}
+
+/// Apply a transformer chain to a sample body and return the result along
+/// with a path-level diff.
+pub async fn preview_transformers(
+    State(state): State<AppState>,
+    Json(preview): Json<PreviewRequest>,
+) -> Response {
+    let config = state.config.load();
+    let registry = &state.transformer_registry;
+
+    let (entries, chain) = match (&preview.transformers, &preview.provider) {
+        (Some(entries), None) => {
+            let unknown: Vec<&str> = entries
+                .iter()
+                .map(|entry| entry.name())
+                .filter(|name| registry.create_with_options(name, &Value::Null).is_none())
+                .collect();
+            if !unknown.is_empty() {
+                return bad_request(format!("Unknown transformer(s): {}", unknown.join(", ")));
+            }
+            (entries.clone(), registry.build_chain(entries))
+        }
+        (None, Some(name)) => {
+            let Some(provider) = config.providers().iter().find(|p| &p.name == name) else {
+                return bad_request(format!("Unknown provider: {name}"));
+            };
+            let model = preview.model.as_deref().unwrap_or_default();
+            let mut entries = provider.provider_transformers().to_vec();
+            if let Some(model_entries) = provider.model_transformers(model) {
+                entries.extend(model_entries.to_vec());
+            }
+            (entries, build_transformer_chain(registry, provider, model))
+        }
+        _ => {
+            return bad_request(
+                "Specify exactly one of \"transformers\" or \"provider\"".to_string(),
+            )
+        }
+    };
+
+    let result = match preview.direction {
+        PreviewDirection::Request => chain.apply_request(preview.body.clone()),
+        PreviewDirection::Response => chain.apply_response(preview.body.clone()),
+    };
+    let transformed = match result {
+        Ok(value) => value,
+        Err(e) => {
+            return (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(json!({"error": format!("Transformer failed: {:#}", e)})),
+            )
+                .into_response()
+        }
+    };
+
+    let changes: Vec<Value> = json_diff(&preview.body, &transformed)
+        .iter()
+        .map(JsonChange::to_json)
+        .collect();
+    let names: Vec<&str> = entries.iter().map(|entry| entry.name()).collect();
+    Json(json!({
+        "transformers": names,
+        "changes": changes,
+        "result": transformed,
+    }))
+    .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_diff_reports_leaf_paths() {
+        let before = json!({
+            "a/b": 1,
+            "max_tokens": 4096,
+            "messages": [{"role": "user", "content": "hi"}],
+            "metadata": {"user_id": "u1"}
+        });
+        let after = json!({
+            "max_tokens": 1024,
+            "messages": [{"role": "user", "content": "hi"}, {"role": "assistant", "content": ""}],
+            "a/b": 1,
+            "stream": true
+        });
+
+        let changes = json_diff(&before, &after);
+        assert_eq!(
+            changes,
+            vec![
+                JsonChange::Modified {
+                    path: "/max_tokens".into(),
+                    before: json!(4096),
+                    after: json!(1024)
+                },
+                JsonChange::Added {
+                    path: "/messages/1".into(),
+                    value: json!({"role": "assistant", "content": ""})
+                },
+                JsonChange::Removed {
+                    path: "/metadata".into(),
+                    value: json!({"user_id": "u1"})
+                },
+                JsonChange::Added {
+                    path: "/stream".into(),
+                    value: json!(true)
+                },
+            ]
+        );
+        assert!(json_diff(&before, &before).is_empty());
+    }
+
+    #[test]
+    fn pointer_segments_are_escaped() {
+        let changes = json_diff(&json!({"a/b": 1, "c~d": 1}), &json!({"a/b": 2, "c~d": 2}));
+        let paths: Vec<_> = changes
+            .iter()
+            .map(|change| match change {
+                JsonChange::Modified { path, .. } => path.as_str(),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(paths, vec!["/a~1b", "/c~0d"]);
+    }
+}
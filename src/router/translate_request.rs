@@ -1,6 +1,7 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 use super::types::*;
-use tracing::debug;
+use crate::config::SystemMessageMode;
+use tracing::{debug, info};
 
 // ============================================================================
 // Request Translation: Anthropic -> OpenAI
@@ -151,10 +152,201 @@ pub(super) fn convert_anthropic_tools_to_openai(
     })
 }
 
+/// Convert an Anthropic `tool_choice` into OpenAI format.
+///
+/// Accepts both the object form (`{"type": "any"}`) and the bare string
+/// shorthand (`"any"`). OpenAI-shaped values pass through unchanged.
+pub(super) fn convert_anthropic_tool_choice_to_openai(
+    tool_choice: &Option<serde_json::Value>,
+) -> Option<serde_json::Value> {
+    let tool_choice = tool_choice.as_ref()?;
+    let choice_type = match tool_choice {
+        serde_json::Value::String(s) => s.as_str(),
+        serde_json::Value::Object(map) => map.get("type").and_then(|t| t.as_str())?,
+        _ => return None,
+    };
+
+    match choice_type {
+        "auto" => Some(serde_json::json!("auto")),
+        "any" | "required" => Some(serde_json::json!("required")),
+        "none" => Some(serde_json::json!("none")),
+        "tool" => tool_choice.get("name").cloned().map(|name| {
+            serde_json::json!({
+                "type": "function",
+                "function": {"name": name}
+            })
+        }),
+        "function" => Some(tool_choice.clone()),
+        _ => None,
+    }
+}
+
+/// Flatten a system prompt (string or array of text blocks) into plain text.
+fn flatten_system_content(system: &serde_json::Value) -> String {
+    match system {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Array(arr) => {
+            let mut result = String::new();
+            for block in arr {
+                if let Some(text) = block.get("text").and_then(|t| t.as_str()) {
+                    if !result.is_empty() {
+                        result.push('\n');
+                    }
+                    result.push_str(text);
+                }
+            }
+            result
+        }
+        serde_json::Value::Null => String::new(),
+        _ => system.to_string(),
+    }
+}
+
+/// Move `system`-role entries out of `messages` so Anthropic-protocol
+/// providers accept the request.
+///
+/// With [`SystemMessageMode::Merge`] their text is appended to the top-level
+/// `system` field, after any prompt already there. With
+/// [`SystemMessageMode::User`] it becomes a single leading user turn instead.
+pub(super) fn hoist_system_messages(request: &mut serde_json::Value, mode: SystemMessageMode) {
+    let Some(obj) = request.as_object_mut() else {
+        return;
+    };
+    let Some(messages) = obj.get_mut("messages").and_then(|m| m.as_array_mut()) else {
+        return;
+    };
+
+    let mut parts = Vec::new();
+    messages.retain(|message| {
+        if message.get("role").and_then(|r| r.as_str()) != Some("system") {
+            return true;
+        }
+        let text = message
+            .get("content")
+            .map(flatten_system_content)
+            .unwrap_or_default();
+        if !text.is_empty() {
+            parts.push(text);
+        }
+        false
+    });
+    if parts.is_empty() {
+        return;
+    }
+    let hoisted = parts.join("\n\n");
+    debug!(
+        count = parts.len(),
+        ?mode,
+        "hoisting in-array system messages"
+    );
+
+    match mode {
+        SystemMessageMode::Merge => {
+            let merged = match obj.remove("system") {
+                Some(serde_json::Value::Array(mut blocks)) => {
+                    blocks.push(serde_json::json!({"type": "text", "text": hoisted}));
+                    serde_json::Value::Array(blocks)
+                }
+                Some(serde_json::Value::String(existing)) if !existing.is_empty() => {
+                    serde_json::Value::String(format!("{existing}\n\n{hoisted}"))
+                }
+                _ => serde_json::Value::String(hoisted),
+            };
+            obj.insert("system".to_string(), merged);
+        }
+        SystemMessageMode::User => {
+            messages.insert(0, serde_json::json!({"role": "user", "content": hoisted}));
+        }
+    }
+}
+
+/// Rewrite OpenAI-style `role: "tool"` messages as `tool_result` blocks in a
+/// user turn for Anthropic-protocol providers, merging consecutive results
+/// into one turn.
+///
+/// Every other message and the top-level `system` are left as sent, so block
+/// fields such as `cache_control` reach the provider and prompt caching keeps
+/// working. Only the OpenAI translation flattens them.
+pub(super) fn tool_messages_to_tool_results(request: &mut serde_json::Value) {
+    let Some(messages) = request.get_mut("messages").and_then(|m| m.as_array_mut()) else {
+        return;
+    };
+
+    let mut converted: Vec<serde_json::Value> = Vec::with_capacity(messages.len());
+    let mut previous_was_tool = false;
+    for message in messages.drain(..) {
+        if message.get("role").and_then(|r| r.as_str()) != Some("tool") {
+            previous_was_tool = false;
+            converted.push(message);
+            continue;
+        }
+
+        let tool_use_id = message
+            .get("tool_call_id")
+            .and_then(|id| id.as_str())
+            .filter(|id| !id.is_empty())
+            .unwrap_or("toolu_unknown");
+        // tool_result accepts a string or text blocks; anything else is
+        // reduced to its text.
+        let content = match message.get("content") {
+            Some(content @ serde_json::Value::String(_)) => content.clone(),
+            Some(content @ serde_json::Value::Array(blocks))
+                if blocks
+                    .iter()
+                    .all(|block| block.get("type").and_then(|t| t.as_str()) == Some("text")) =>
+            {
+                content.clone()
+            }
+            Some(content) => serde_json::Value::String(flatten_system_content(content)),
+            None => serde_json::Value::String(String::new()),
+        };
+        let block = serde_json::json!({
+            "type": "tool_result",
+            "tool_use_id": tool_use_id,
+            "content": content
+        });
+
+        match converted.last_mut() {
+            Some(turn) if previous_was_tool => {
+                if let Some(blocks) = turn["content"].as_array_mut() {
+                    blocks.push(block);
+                }
+            }
+            _ => converted.push(serde_json::json!({"role": "user", "content": [block]})),
+        }
+        previous_was_tool = true;
+    }
+    *messages = converted;
+}
+
+pub(super) fn is_reasoning_model(model: &str) -> bool {
+    let model_lower = model.to_lowercase();
+    model_lower.contains("reasoner")
+        || model_lower.contains("r1")
+        || model_lower.contains("thinking")
+}
+
+/// The provider floor when it replaces a reasoning model's smaller or missing
+/// requested completion budget, `None` when the request is sent as asked.
+pub(super) fn raised_reasoning_budget(
+    model: &str,
+    requested: Option<u32>,
+    floor: Option<u32>,
+) -> Option<u32> {
+    let floor = floor.filter(|_| is_reasoning_model(model))?;
+    match requested {
+        Some(requested) if requested >= floor => None,
+        _ => Some(floor),
+    }
+}
+
 /// Translate Anthropic request format to OpenAI format.
+/// `reasoning_token_floor` is the provider's `min_completion_tokens_for_reasoning`:
+/// a reasoning model's completion budget is raised to at least this much.
 pub(super) fn translate_request_anthropic_to_openai(
     anthropic_req: &AnthropicRequest,
     model: &str,
+    reasoning_token_floor: Option<u32>,
 ) -> OpenAIRequest {
     debug!(
         model,
@@ -163,30 +355,13 @@ pub(super) fn translate_request_anthropic_to_openai(
     );
     let mut messages: Vec<OpenAIMessage> = Vec::new();
     let model_lower = model.to_lowercase();
-    let is_reasoning_model = model_lower.contains("reasoner")
-        || model_lower.contains("r1")
-        || model_lower.contains("thinking");
+    let is_reasoning_model = is_reasoning_model(model);
     let is_deepseek = model_lower.contains("deepseek");
 
     // Handle system prompt: Anthropic has it as a top-level field,
     // OpenAI expects it as the first message with role "system"
     if let Some(system) = &anthropic_req.system {
-        let system_content = match system {
-            serde_json::Value::String(s) => s.clone(),
-            serde_json::Value::Array(arr) => {
-                let mut result = String::new();
-                for block in arr {
-                    if let Some(text) = block.get("text").and_then(|t| t.as_str()) {
-                        if !result.is_empty() {
-                            result.push('\n');
-                        }
-                        result.push_str(text);
-                    }
-                }
-                result
-            }
-            _ => system.to_string(),
-        };
+        let system_content = flatten_system_content(system);
 
         if !system_content.is_empty() {
             messages.push(OpenAIMessage {
@@ -329,6 +504,20 @@ pub(super) fn translate_request_anthropic_to_openai(
         }
     }
 
+    // Reasoning spends the completion budget before any visible output, so a
+    // small client max_tokens would leave nothing for the answer.
+    let requested = anthropic_req.max_tokens;
+    let completion_budget = match raised_reasoning_budget(model, requested, reasoning_token_floor) {
+        Some(floor) => {
+            info!(
+                model,
+                requested, floor, "raising reasoning model completion budget to provider floor"
+            );
+            Some(floor)
+        }
+        None => requested,
+    };
+
     OpenAIRequest {
         model: model.to_string(),
         messages,
@@ -339,13 +528,22 @@ pub(super) fn translate_request_anthropic_to_openai(
             anthropic_req.max_tokens
         },
         max_completion_tokens: if is_reasoning_model {
-            anthropic_req.max_tokens
+            completion_budget
         } else {
             None
         },
         temperature: anthropic_req.temperature,
+        top_p: anthropic_req.top_p,
         stream: anthropic_req.stream,
+        // Without this, OpenAI streams carry no usage and it has to be estimated.
+        stream_options: anthropic_req
+            .stream
+            .unwrap_or(false)
+            .then_some(OpenAIStreamOptions {
+                include_usage: true,
+            }),
         tools: convert_anthropic_tools_to_openai(&anthropic_req.tools),
+        tool_choice: convert_anthropic_tool_choice_to_openai(&anthropic_req.tool_choice),
         reasoning_effort: if is_reasoning_model {
             Some("high".to_string())
         } else {
@@ -356,5 +554,8 @@ pub(super) fn translate_request_anthropic_to_openai(
         } else {
             None
         },
+        stop: anthropic_req.stop_sequences.clone(),
+        modalities: anthropic_req.modalities.clone(),
+        audio: anthropic_req.audio.clone(),
     }
 }
@@ -1,5 +1,8 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
+use super::translate_request::is_reasoning_model;
 use super::types::*;
+use crate::config::{Config, Provider};
+use crate::frontend::ImageSource;
 use crate::transformer::{TransformerChain, TransformerRegistry};
 use tracing::debug;
 
@@ -7,6 +10,51 @@ use tracing::debug;
 // Response Translation: OpenAI -> Anthropic
 // ============================================================================
 
+/// Whether a provider labelled its reply with something other than
+/// `assistant` (e.g. `model` or `bot`). Translated responses always carry
+/// `assistant`, so this only feeds `ccr_nonstandard_role_total`.
+pub(super) fn is_nonstandard_role(role: Option<&str>) -> bool {
+    role.is_some_and(|role| !role.is_empty() && role != "assistant")
+}
+
+/// Whether `reasoning_content` returned by `model` is passed on to the
+/// client. Models flagged as reasoning (by `model_metadata` or by name)
+/// always pass it on; others only while `EMIT_UNSOLICITED_REASONING` is on.
+pub(super) fn emits_reasoning(config: &Config, provider: &Provider, model: &str) -> bool {
+    config.emit_unsolicited_reasoning()
+        || provider
+            .metadata_for_model(model)
+            .and_then(|metadata| metadata.supports_reasoning)
+            .unwrap_or_else(|| is_reasoning_model(model))
+}
+
+/// Anthropic image source for an OpenAI `image_url` content part. The URL
+/// is read from `image_url.url` or from `image_url` itself when it is a
+/// plain string. Base64 data URLs become `base64` sources; anything else is
+/// passed through as a `url` source.
+fn image_source_from_openai(item: &serde_json::Value) -> Option<ImageSource> {
+    let image_url = item.get("image_url")?;
+    let url = image_url
+        .get("url")
+        .and_then(|v| v.as_str())
+        .or_else(|| image_url.as_str())?;
+
+    let base64 = url.strip_prefix("data:").and_then(|rest| {
+        let (media_type, data) = rest.split_once(";base64,")?;
+        Some(ImageSource::Base64 {
+            media_type: if media_type.is_empty() {
+                "image/png".to_string()
+            } else {
+                media_type.to_string()
+            },
+            data: data.to_string(),
+        })
+    });
+    Some(base64.unwrap_or_else(|| ImageSource::Url {
+        url: url.to_string(),
+    }))
+}
+
 /// Translate OpenAI non-streaming response to Anthropic format.
 pub(super) fn translate_response_openai_to_anthropic(
     openai_resp: OpenAIResponse,
@@ -55,11 +103,12 @@ pub(super) fn translate_response_openai_to_anthropic(
                                     }
                                 }
                             }
-                            "image_url" => {
-                                blocks.push(AnthropicContentBlock::Text {
-                                    text: item.to_string(),
-                                });
-                            }
+                            "image_url" => match image_source_from_openai(item) {
+                                Some(source) => {
+                                    blocks.push(AnthropicContentBlock::Image { source });
+                                }
+                                None => debug!("dropping image_url part without a url"),
+                            },
                             _ => {}
                         }
                     }
@@ -74,6 +123,12 @@ pub(super) fn translate_response_openai_to_anthropic(
             }
         }
 
+        if let Some(audio) = &choice.message.audio {
+            blocks.push(AnthropicContentBlock::Audio {
+                audio: audio.clone(),
+            });
+        }
+
         if let Some(tool_calls) = &choice.message.tool_calls {
             for (index, tool_call) in tool_calls.iter().enumerate() {
                 if tool_call.tool_type.as_deref().unwrap_or("function") != "function" {
@@ -146,6 +201,20 @@ pub(super) struct StreamTranslationState {
     pub active_tool_indices: Vec<usize>,
     /// The OpenAI finish_reason from the terminal chunk (e.g. "stop", "tool_calls").
     pub finish_reason: Option<String>,
+    /// Emit `reasoning_content` as thinking blocks. Blocks are then numbered
+    /// in the order they open instead of text at 0 and tools after it.
+    pub emit_thinking: bool,
+    /// The thinking or text block currently open while `emit_thinking`.
+    open_block: Option<(OrderedBlock, usize)>,
+    next_index: usize,
+    /// OpenAI tool call index -> Anthropic block index while `emit_thinking`.
+    tool_blocks: Vec<(usize, usize)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OrderedBlock {
+    Thinking,
+    Text,
 }
 
 impl StreamTranslationState {
@@ -155,6 +224,75 @@ impl StreamTranslationState {
             ..Default::default()
         }
     }
+
+    /// State for a stream whose reasoning is sent on as thinking blocks.
+    pub fn with_thinking() -> Self {
+        Self {
+            emit_thinking: true,
+            ..Self::new()
+        }
+    }
+
+    /// Index of the open `kind` block, closing the other kind and starting a
+    /// new block if needed.
+    fn ordered_block(
+        &mut self,
+        kind: OrderedBlock,
+        events: &mut Vec<AnthropicStreamEvent>,
+    ) -> usize {
+        if let Some((open, index)) = self.open_block {
+            if open == kind {
+                return index;
+            }
+            events.push(block_stop_event(index));
+        }
+        let index = self.next_index;
+        self.next_index += 1;
+        let content_block = match kind {
+            OrderedBlock::Thinking => serde_json::json!({"type": "thinking", "thinking": ""}),
+            OrderedBlock::Text => serde_json::json!({"type": "text", "text": ""}),
+        };
+        events.push(AnthropicStreamEvent {
+            event_type: "content_block_start".to_string(),
+            message: None,
+            index: Some(index),
+            content_block: Some(content_block),
+            delta: None,
+            usage: None,
+            stop_reason: None,
+        });
+        if kind == OrderedBlock::Text {
+            self.text_block_started = true;
+        }
+        self.open_block = Some((kind, index));
+        index
+    }
+
+    /// Anthropic block index for an OpenAI tool call index.
+    fn tool_block_index(&mut self, openai_index: usize) -> usize {
+        if !self.emit_thinking {
+            return openai_index + 1;
+        }
+        if let Some(&(_, index)) = self.tool_blocks.iter().find(|(i, _)| *i == openai_index) {
+            return index;
+        }
+        let index = self.next_index;
+        self.next_index += 1;
+        self.tool_blocks.push((openai_index, index));
+        index
+    }
+}
+
+fn block_stop_event(index: usize) -> AnthropicStreamEvent {
+    AnthropicStreamEvent {
+        event_type: "content_block_stop".to_string(),
+        message: None,
+        index: Some(index),
+        content_block: None,
+        delta: None,
+        usage: None,
+        stop_reason: None,
+    }
 }
 
 /// Translate an OpenAI streaming chunk to Anthropic streaming events.
@@ -185,33 +323,64 @@ pub(super) fn translate_stream_chunk_to_anthropic(
             stop_reason: None,
         });
 
-        events.push(AnthropicStreamEvent {
-            event_type: "content_block_start".to_string(),
-            message: None,
-            index: Some(0),
-            content_block: Some(serde_json::json!({
-                "type": "text",
-                "text": ""
-            })),
-            delta: None,
-            usage: None,
-            stop_reason: None,
-        });
-        state.text_block_started = true;
+        // With thinking blocks the first block is whichever arrives first.
+        if !state.emit_thinking {
+            events.push(AnthropicStreamEvent {
+                event_type: "content_block_start".to_string(),
+                message: None,
+                index: Some(0),
+                content_block: Some(serde_json::json!({
+                    "type": "text",
+                    "text": ""
+                })),
+                delta: None,
+                usage: None,
+                stop_reason: None,
+            });
+            state.text_block_started = true;
+        }
         state.is_first = false;
     }
 
     if let Some(choice) = chunk.choices.first() {
-        // Skip reasoning content from OpenAI-compatible providers.
-        // Non-Anthropic models lack thinking signatures, and emitting
-        // thinking_delta events causes Anthropic SDK parse failures.
+        // Skip reasoning content from OpenAI-compatible providers unless the
+        // stream opted into thinking blocks. Non-Anthropic models lack
+        // thinking signatures, and unsolicited thinking_delta events cause
+        // Anthropic SDK parse failures.
+        if state.emit_thinking {
+            if let Some(reasoning) = choice
+                .delta
+                .reasoning_content
+                .as_ref()
+                .filter(|r| !r.is_empty())
+            {
+                let index = state.ordered_block(OrderedBlock::Thinking, &mut events);
+                events.push(AnthropicStreamEvent {
+                    event_type: "content_block_delta".to_string(),
+                    message: None,
+                    index: Some(index),
+                    content_block: None,
+                    delta: Some(serde_json::json!({
+                        "type": "thinking_delta",
+                        "thinking": reasoning
+                    })),
+                    usage: None,
+                    stop_reason: None,
+                });
+            }
+        }
 
         if let Some(ref content) = choice.delta.content {
             if !content.is_empty() {
+                let index = if state.emit_thinking {
+                    state.ordered_block(OrderedBlock::Text, &mut events)
+                } else {
+                    0
+                };
                 events.push(AnthropicStreamEvent {
                     event_type: "content_block_delta".to_string(),
                     message: None,
-                    index: Some(0),
+                    index: Some(index),
                     content_block: None,
                     delta: Some(serde_json::json!({
                         "type": "text_delta",
@@ -225,7 +394,7 @@ pub(super) fn translate_stream_chunk_to_anthropic(
 
         if let Some(tool_calls) = &choice.delta.tool_calls {
             for tool_call in tool_calls {
-                let tool_index = tool_call.index + 1;
+                let tool_index = state.tool_block_index(tool_call.index);
 
                 if tool_call.id.is_some()
                     || tool_call
@@ -286,16 +455,12 @@ pub(super) fn translate_stream_chunk_to_anthropic(
         if let Some(ref reason) = choice.finish_reason {
             state.finish_reason = Some(reason.clone());
 
-            if state.text_block_started {
-                events.push(AnthropicStreamEvent {
-                    event_type: "content_block_stop".to_string(),
-                    message: None,
-                    index: Some(0),
-                    content_block: None,
-                    delta: None,
-                    usage: None,
-                    stop_reason: None,
-                });
+            if state.emit_thinking {
+                if let Some((_, index)) = state.open_block.take() {
+                    events.push(block_stop_event(index));
+                }
+            } else if state.text_block_started {
+                events.push(block_stop_event(0));
             }
 
             for &tool_idx in &state.active_tool_indices {
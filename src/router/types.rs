@@ -1,15 +1,20 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 use serde::{Deserialize, Serialize};
-use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::{AtomicBool, AtomicUsize};
 use std::sync::Arc;
 
-use crate::config::Config;
+use crate::config::{Config, SharedConfig};
 use crate::debug_capture::DebugCapture;
 #[cfg(feature = "gp")]
 use crate::gp_router::GpRequestRouter;
+use crate::ingress_limit::IngressLimiter;
 use crate::ratelimit::RateLimitTracker;
 use crate::routing::EwmaTracker;
+use crate::sticky::StickySessions;
 use crate::transformer::TransformerRegistry;
+use crate::upstream_limit::UpstreamLimiter;
+
+use super::Readiness;
 
 // ============================================================================
 // Error Types
@@ -22,6 +27,14 @@ pub enum TryRequestError {
     RateLimited(Option<std::time::Duration>),
     /// Other errors
     Other(anyhow::Error),
+    /// Classified `terminal`: the cascade stops and the client sees this error.
+    Terminal(anyhow::Error),
+    /// Classified `auth`: the tier's remaining retries are skipped.
+    Auth(anyhow::Error),
+    /// Ended with a `retryOnFinishReason` finish reason: the tier's remaining
+    /// retries are skipped and the request cascades. Carries the translated
+    /// response, returned if no later tier succeeds.
+    FinishReason(String, axum::response::Response),
 }
 
 impl std::fmt::Display for TryRequestError {
@@ -34,7 +47,12 @@ impl std::fmt::Display for TryRequestError {
                 }
                 Ok(())
             }
-            TryRequestError::Other(e) => write!(f, "{}", e),
+            TryRequestError::Other(e) | TryRequestError::Terminal(e) | TryRequestError::Auth(e) => {
+                write!(f, "{}", e)
+            }
+            TryRequestError::FinishReason(reason, _) => {
+                write!(f, "Response ended with finish reason {}", reason)
+            }
         }
     }
 }
@@ -54,27 +72,70 @@ impl From<anyhow::Error> for TryRequestError {
 /// Shared application state threaded through Axum handlers.
 #[derive(Clone)]
 pub struct AppState {
-    pub config: Config,
+    /// Active config; swapped atomically by `POST /v1/reload`.
+    pub config: SharedConfig,
     pub ewma_tracker: Arc<EwmaTracker>,
     #[cfg(feature = "gp")]
     pub gp_router: Option<Arc<GpRequestRouter>>,
     #[cfg(not(feature = "gp"))]
     pub gp_router: Option<()>,
     pub transformer_registry: Arc<TransformerRegistry>,
+    /// SSE responses currently held open by clients.
     pub active_streams: Arc<AtomicUsize>,
     pub max_streams: usize,
+    /// Hedged attempts in flight across all requests, bounded by
+    /// `Router.hedging.max_concurrent`.
+    pub hedges_in_flight: Arc<AtomicUsize>,
     pub ratelimit_tracker: Arc<RateLimitTracker>,
-    #[allow(dead_code)]
+    /// Seconds to wait for active streams to finish on shutdown.
     pub shutdown_timeout: u64,
+    /// Set on shutdown; proxy routes answer 503 while streams drain.
+    pub draining: Arc<AtomicBool>,
     /// Debug capture manager for recording raw API interactions.
     pub debug_capture: Option<Arc<DebugCapture>>,
+    /// Session -> tier pins for sticky-session routing.
+    pub sticky_sessions: Arc<StickySessions>,
+    /// Global cap on concurrent upstream requests (`MAX_UPSTREAM_INFLIGHT`).
+    pub upstream_limiter: Arc<UpstreamLimiter>,
+    /// Per-client token buckets for `INGRESS_RATE_LIMIT`.
+    pub ingress_limiter: Arc<IngressLimiter>,
+    /// Set while maintenance mode is on; proxy routes answer 503.
+    pub maintenance: Arc<AtomicBool>,
+    /// Last passing `READINESS_CHECK`, reported by `/ready`.
+    pub readiness: Arc<Readiness>,
+}
+
+impl AppState {
+    /// State around `config` with fresh trackers, no stream cap, and no GP
+    /// router or debug capture. Integration tests start from this and set
+    /// the fields they exercise.
+    pub fn for_tests(config: Config) -> Self {
+        Self {
+            maintenance: Arc::new(AtomicBool::new(config.maintenance_mode())),
+            config: config.into(),
+            ewma_tracker: Arc::new(EwmaTracker::new()),
+            gp_router: None,
+            transformer_registry: Arc::new(TransformerRegistry::new()),
+            active_streams: Arc::new(AtomicUsize::new(0)),
+            max_streams: 0,
+            hedges_in_flight: Arc::new(AtomicUsize::new(0)),
+            ratelimit_tracker: Arc::new(RateLimitTracker::new()),
+            shutdown_timeout: 30,
+            draining: Arc::new(AtomicBool::new(false)),
+            debug_capture: None,
+            sticky_sessions: Arc::new(StickySessions::new()),
+            upstream_limiter: Arc::new(UpstreamLimiter::new()),
+            ingress_limiter: Arc::new(IngressLimiter::new()),
+            readiness: Arc::new(Readiness::new()),
+        }
+    }
 }
 
 // ============================================================================
 // Anthropic Format Types (Input)
 // ============================================================================
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AnthropicRequest {
     pub model: String,
     pub messages: Vec<Message>,
@@ -88,12 +149,40 @@ pub struct AnthropicRequest {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f32>,
 
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub stream: Option<bool>,
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub tools: Option<Vec<serde_json::Value>>,
 
+    /// Anthropic-format tool choice (`{"type": "auto" | "any" | "none"}` or
+    /// `{"type": "tool", "name": ...}`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<serde_json::Value>,
+
+    /// Custom strings that end generation. Sent as `stop` to OpenAI-protocol
+    /// providers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_sequences: Option<Vec<String>>,
+
+    /// Output modalities for audio-capable models, e.g. `["text", "audio"]`.
+    /// Forwarded to OpenAI-protocol providers only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modalities: Option<Vec<String>>,
+
+    /// Audio output settings (`{"voice": ..., "format": ...}`) that go with
+    /// `modalities`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio: Option<serde_json::Value>,
+
+    /// Client request metadata. Only read locally (for `client_message_id`);
+    /// never forwarded upstream.
+    #[serde(default, skip_serializing)]
+    pub metadata: Option<serde_json::Value>,
+
     /// When the original inbound request was already OpenAI-formatted (e.g. from
     /// a Codex frontend), we stash the raw JSON here so that
     /// `try_request_via_openai_protocol` can send it directly to an
@@ -101,6 +190,16 @@ pub struct AnthropicRequest {
     /// `OpenAI → Anthropic → OpenAI` round-trip translation.
     #[serde(skip)]
     pub openai_passthrough_body: Option<serde_json::Value>,
+
+    /// A tier the cascade must not try, set when native Responses
+    /// forwarding to it already failed for this request.
+    #[serde(skip)]
+    pub excluded_tier: Option<String>,
+
+    /// Top-level fields CCR does not model. They are never forwarded
+    /// upstream, and are reported in `x-ccr-warnings` when that is enabled.
+    #[serde(flatten, skip_serializing)]
+    pub unrecognized: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -127,13 +226,32 @@ pub struct OpenAIRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub stream: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_options: Option<OpenAIStreamOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub tools: Option<Vec<serde_json::Value>>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub reasoning_effort: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub thinking: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modalities: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audio: Option<serde_json::Value>,
+}
+
+/// OpenAI `stream_options`. With `include_usage`, the stream ends with a
+/// chunk carrying real token usage.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct OpenAIStreamOptions {
+    pub include_usage: bool,
 }
 
 /// OpenAI message format.
@@ -174,7 +292,6 @@ pub struct OpenAIChoice {
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct OpenAIResponseMessage {
-    #[allow(dead_code)]
     pub role: String,
     #[serde(default)]
     pub content: Option<serde_json::Value>,
@@ -182,6 +299,10 @@ pub struct OpenAIResponseMessage {
     pub reasoning_content: Option<String>,
     #[serde(default)]
     pub tool_calls: Option<Vec<OpenAIToolCall>>,
+    /// Generated audio (`id`, `data`, `expires_at`, `transcript`) when the
+    /// request asked for the `audio` modality.
+    #[serde(default)]
+    pub audio: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -198,10 +319,32 @@ pub struct OpenAIToolCall {
 pub struct OpenAIToolFunction {
     #[serde(default)]
     pub name: String,
-    #[serde(default)]
+    #[serde(default, deserialize_with = "deserialize_arguments")]
     pub arguments: String,
 }
 
+/// Tool call `arguments` as a JSON string. Some providers (GLM) send the
+/// arguments object itself instead of its encoding; it is re-encoded.
+fn deserialize_arguments<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(deserialize_optional_arguments(deserializer)?.unwrap_or_default())
+}
+
+/// [`deserialize_arguments`] for stream deltas, where `null` means no
+/// arguments in this chunk.
+fn deserialize_optional_arguments<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::Null => None,
+        serde_json::Value::String(arguments) => Some(arguments),
+        arguments => Some(arguments.to_string()),
+    })
+}
+
 #[derive(Debug, Deserialize, Default)]
 pub struct OpenAIUsage {
     #[serde(default)]
@@ -209,8 +352,25 @@ pub struct OpenAIUsage {
     #[serde(default)]
     pub completion_tokens: u64,
     #[serde(default)]
-    #[allow(dead_code)]
     pub prompt_tokens_details: Option<serde_json::Value>,
+    /// Prompt tokens served from a context cache, reported at the top level
+    /// of `usage` (Moonshot/Kimi).
+    #[serde(default, rename = "cached_tokens")]
+    pub context_cached_tokens: Option<u64>,
+}
+
+impl OpenAIUsage {
+    /// Prompt tokens served from the provider's prompt cache, as reported in
+    /// `prompt_tokens_details.cached_tokens` (OpenAI, DeepSeek, and others)
+    /// or in `cached_tokens` (Moonshot/Kimi).
+    pub fn cached_tokens(&self) -> u64 {
+        self.prompt_tokens_details
+            .as_ref()
+            .and_then(|d| d.get("cached_tokens"))
+            .and_then(|v| v.as_u64())
+            .or(self.context_cached_tokens)
+            .unwrap_or(0)
+    }
 }
 
 /// OpenAI streaming response chunk.
@@ -237,7 +397,6 @@ pub struct OpenAIStreamChoice {
 #[derive(Debug, Deserialize, Default)]
 pub struct OpenAIDelta {
     #[serde(default)]
-    #[allow(dead_code)]
     pub role: Option<String>,
     #[serde(default)]
     pub content: Option<String>,
@@ -261,7 +420,7 @@ pub struct OpenAIStreamToolCall {
 pub struct OpenAIStreamToolFunction {
     #[serde(default)]
     pub name: Option<String>,
-    #[serde(default)]
+    #[serde(default, deserialize_with = "deserialize_optional_arguments")]
     pub arguments: Option<String>,
 }
 
@@ -299,6 +458,16 @@ pub enum AnthropicContentBlock {
         name: String,
         input: serde_json::Value,
     },
+    /// Audio output from an OpenAI-protocol provider, kept as the provider
+    /// sent it so clients can play it back.
+    #[serde(rename = "audio")]
+    Audio { audio: serde_json::Value },
+    /// Image output from an OpenAI-protocol provider, with its base64 data
+    /// or URL carried over from the `image_url` part.
+    #[serde(rename = "image")]
+    Image {
+        source: crate::frontend::ImageSource,
+    },
 }
 
 #[derive(Debug, Serialize, Deserialize, Default, Clone)]
@@ -16,6 +16,7 @@ pub struct NormalizedUsage {
 ///
 /// Supports:
 /// - OpenAI-style responses with `prompt_tokens_details.cached_tokens`
+/// - Moonshot/Kimi responses with a top-level `cached_tokens`
 /// - Minimax-style responses with `cache_creation_input_tokens` and `cache_read_input_tokens`
 /// - Anthropic-style responses with direct `input_tokens` and `output_tokens`
 pub fn extract_normalized_usage(response: &Value) -> NormalizedUsage {
@@ -38,10 +39,12 @@ pub fn extract_normalized_usage(response: &Value) -> NormalizedUsage {
         .and_then(Value::as_u64)
         .unwrap_or(0);
 
-    // OpenAI-style: cached tokens are reported in prompt_tokens_details.cached_tokens
+    // OpenAI-style: cached tokens are reported in prompt_tokens_details.cached_tokens;
+    // Moonshot/Kimi report them as usage.cached_tokens
     let cached_tokens = usage
         .get("prompt_tokens_details")
         .and_then(|d| d.get("cached_tokens"))
+        .or_else(|| usage.get("cached_tokens"))
         .and_then(Value::as_u64)
         .unwrap_or(0);
 
@@ -101,6 +104,21 @@ mod tests {
         assert_eq!(normalized.cache_creation_tokens, 0);
     }
 
+    #[test]
+    fn usage_metrics_extracts_kimi_cached_tokens() {
+        let response = json!({
+            "usage": {
+                "prompt_tokens": 100,
+                "completion_tokens": 50,
+                "cached_tokens": 80
+            }
+        });
+
+        let normalized = extract_normalized_usage(&response);
+        assert_eq!(normalized.input_tokens, 100);
+        assert_eq!(normalized.cache_read_tokens, 80);
+    }
+
     #[test]
     fn usage_metrics_extracts_minimax_cache_fields() {
         // Minimax-style response with separate cache creation and read tokens
@@ -0,0 +1,114 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Prompt-cache warm-up for presets with `warm: true`.
+//
+// At startup and after each reload, every warm preset sends one tiny request
+// to its route: the preset's system prompt, a one-word user turn, and
+// `max_tokens: 1`. The provider caches the system prompt, so the first real
+// request through the preset does not pay for it. A tier in rate-limit
+// backoff is left alone, a 429 starts its backoff as usual, and warm-ups are
+// counted in `ccr_warm_requests_total` instead of the regular request series.
+
+use tracing::{info, warn};
+
+use crate::config::{Config, PresetConfig};
+use crate::metrics::{
+    record_rate_limit_backoff, record_rate_limit_hit, record_warm_request, WarmOutcome,
+};
+
+use super::dispatch::extract_rate_limit_headers;
+use super::probe::one_token_request;
+use super::AppState;
+
+/// Run [`warm_presets`] in the background.
+pub fn spawn_preset_warmups(state: &AppState) {
+    let state = state.clone();
+    tokio::spawn(async move { warm_presets(&state).await });
+}
+
+/// Send one warm-up request for each `warm` preset in the current config,
+/// in preset name order.
+pub async fn warm_presets(state: &AppState) {
+    let config = state.config.load();
+    let mut presets: Vec<(&String, &PresetConfig)> =
+        config.presets.iter().filter(|(_, p)| p.warm).collect();
+    presets.sort_by_key(|(name, _)| *name);
+    for (name, preset) in presets {
+        let tier_name = config.backend_abbreviation_with_config(&preset.route);
+        let outcome = warm_preset(state, &config, preset, &tier_name).await;
+        info!(preset = %name, tier = %tier_name, outcome = outcome.label(), "Preset warm-up finished");
+        record_warm_request(&tier_name, outcome);
+    }
+}
+
+async fn warm_preset(
+    state: &AppState,
+    config: &Config,
+    preset: &PresetConfig,
+    tier_name: &str,
+) -> WarmOutcome {
+    let (Some(provider), Some(system)) = (
+        config.resolve_provider(&preset.route),
+        preset.system.as_deref(),
+    ) else {
+        return WarmOutcome::Failed;
+    };
+    if state
+        .ratelimit_tracker
+        .should_skip_tier(tier_name, provider.honor_ratelimit_headers)
+    {
+        return WarmOutcome::RateLimited;
+    }
+    let model_name = preset.route.split(',').nth(1).unwrap_or(&preset.route);
+    let request = match one_token_request(config, provider, model_name, Some(system)) {
+        Ok(request) => request,
+        Err(err) => {
+            warn!(tier = %tier_name, "Preset warm-up headers invalid: {}", err);
+            return WarmOutcome::Failed;
+        }
+    };
+
+    // A warm-up holds an upstream slot like any other dispatch.
+    let Some(_permit) = state
+        .upstream_limiter
+        .acquire(
+            config.max_upstream_inflight(),
+            config.upstream_queue_timeout(),
+        )
+        .await
+    else {
+        return WarmOutcome::Failed;
+    };
+    let resp = match request.send().await {
+        Ok(resp) => {
+            crate::connection_stats::note_connection(&resp);
+            resp
+        }
+        Err(err) => {
+            warn!(tier = %tier_name, "Preset warm-up request failed: {}", err);
+            return WarmOutcome::Failed;
+        }
+    };
+
+    let status = resp.status();
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        let retry_after = resp
+            .headers()
+            .get("retry-after")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|s| s.parse::<u64>().ok())
+            .map(std::time::Duration::from_secs);
+        record_rate_limit_hit(tier_name);
+        state.ratelimit_tracker.record_429(tier_name, retry_after);
+        record_rate_limit_backoff(tier_name);
+        return WarmOutcome::RateLimited;
+    }
+    if !status.is_success() {
+        warn!(tier = %tier_name, status = %status, "Preset warm-up rejected");
+        return WarmOutcome::Failed;
+    }
+    let (remaining, reset_at) = extract_rate_limit_headers(&resp);
+    state
+        .ratelimit_tracker
+        .record_success(tier_name, remaining, reset_at);
+    WarmOutcome::Ok
+}
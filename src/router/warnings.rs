@@ -0,0 +1,68 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Request mutations reported back to the client.
+//
+// CCR drops fields it does not model and adjusts some values per provider.
+// With `Router.warningsHeader` enabled, a successful response lists those
+// changes in `x-ccr-warnings` so clients can tell why behavior differs from
+// what they asked for.
+
+use axum::http::HeaderValue;
+use axum::response::Response;
+
+use super::types::AnthropicRequest;
+
+pub(super) const WARNINGS_HEADER: &str = "x-ccr-warnings";
+
+/// Human-readable notes on how a request was changed, in the order they were
+/// applied. Dispatch leaves tier-specific notes on the response extensions.
+#[derive(Debug, Clone, Default)]
+pub(super) struct RequestWarnings(Vec<String>);
+
+impl RequestWarnings {
+    /// Notes for top-level request fields that are dropped before dispatch.
+    pub(super) fn for_request(request: &AnthropicRequest) -> Self {
+        let mut fields: Vec<&String> = request.unrecognized.keys().collect();
+        fields.sort();
+        Self(
+            fields
+                .into_iter()
+                .map(|field| format!("dropped {field} (not supported by CCR)"))
+                .collect(),
+        )
+    }
+
+    pub(super) fn push(&mut self, warning: impl Into<String>) {
+        self.0.push(warning.into());
+    }
+
+    /// Remove the notes dispatch left on `response`.
+    pub(super) fn take_from(response: &mut Response) -> Self {
+        response
+            .extensions_mut()
+            .remove::<Self>()
+            .unwrap_or_default()
+    }
+
+    /// Keep the notes on `response` until the cascade adds the header.
+    pub(super) fn stash_on(self, mut response: Response) -> Response {
+        if !self.0.is_empty() {
+            response.extensions_mut().insert(self);
+        }
+        response
+    }
+
+    pub(super) fn extend(&mut self, other: Self) {
+        self.0.extend(other.0);
+    }
+
+    /// Write the notes to the `x-ccr-warnings` header, if there are any.
+    pub(super) fn insert_header(&self, response: &mut Response) {
+        if self.0.is_empty() {
+            return;
+        }
+        let json = serde_json::to_string(&self.0).unwrap_or_default();
+        if let Ok(value) = HeaderValue::from_str(&json) {
+            response.headers_mut().insert(WARNINGS_HEADER, value);
+        }
+    }
+}
@@ -2,23 +2,64 @@
 use parking_lot::RwLock;
 use rand::distributions::WeightedIndex;
 use rand::prelude::*;
-use std::collections::HashMap;
-use std::time::Instant;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 use tracing::{debug, info};
 
+use crate::config::EwmaDecayConfig;
+
 /// EWMA smoothing factor. 0.3 = 30% weight on new sample, 70% on history.
 /// Higher values react faster to latency changes but are noisier.
-const DEFAULT_EWMA_ALPHA: f64 = 0.3;
+/// Overridable per tier with `Router.ewmaAlpha`.
+pub const DEFAULT_EWMA_ALPHA: f64 = 0.3;
 
 /// Minimum samples before a tier's EWMA is trusted for routing decisions.
-/// Below this threshold, tiers keep their config-defined priority order.
-const DEFAULT_MIN_SAMPLES: u64 = 3;
+/// Below this threshold a tier is cold and is tried ahead of measured tiers,
+/// so that it collects samples. Overridable with `Router.ewmaMinSamples`.
+pub const DEFAULT_MIN_SAMPLES: u64 = 3;
 
 /// Penalty multiplier applied to EWMA when a request fails.
 /// A failed request is treated as if it took (current_ewma * penalty) seconds,
 /// pushing the tier down in priority without catastrophically inflating the estimate.
 const DEFAULT_FAILURE_PENALTY: f64 = 2.0;
 
+/// Successful-attempt latencies kept per tier for quantiles.
+/// Overridable with `Router.latencyWindow`.
+pub const DEFAULT_LATENCY_WINDOW: usize = 256;
+
+/// Upper bound on the latency window, so a config typo cannot grow memory
+/// without limit.
+pub const MAX_LATENCY_WINDOW: usize = 16_384;
+
+/// Latency quantiles over a tier's recent successful attempts, in seconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LatencyQuantiles {
+    pub p50: f64,
+    pub p95: f64,
+    pub p99: f64,
+}
+
+impl LatencyQuantiles {
+    /// Nearest-rank quantiles of `samples`, or `None` when empty.
+    fn from_samples(samples: &VecDeque<f64>) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<f64> = samples.iter().copied().collect();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let rank = |q: f64| {
+            let idx = (q * sorted.len() as f64).ceil() as usize;
+            sorted[idx.clamp(1, sorted.len()) - 1]
+        };
+        Some(Self {
+            p50: rank(0.50),
+            p95: rank(0.95),
+            p99: rank(0.99),
+        })
+    }
+}
+
 /// Per-tier latency tracking state.
 #[derive(Debug, Clone)]
 struct TierState {
@@ -28,6 +69,13 @@ struct TierState {
     samples: u64,
     /// Number of consecutive failures (resets on success).
     consecutive_failures: u64,
+    /// When the most recent failure was recorded.
+    last_failure: Option<Instant>,
+    /// Most recent successful-attempt latencies, oldest first.
+    window: VecDeque<f64>,
+    /// When the most recent sample (success or failure) was recorded, or the
+    /// state restored.
+    last_sample: Option<Instant>,
 }
 
 impl TierState {
@@ -36,8 +84,28 @@ impl TierState {
             ewma: 0.0,
             samples: 0,
             consecutive_failures: 0,
+            last_failure: None,
+            window: VecDeque::new(),
+            last_sample: None,
         }
     }
+
+    /// The EWMA relaxed toward `baseline` for the time the tier has been idle
+    /// past `decay.decay_after()`.
+    fn decayed_ewma(&self, decay: &EwmaDecayConfig, baseline: f64, now: Instant) -> f64 {
+        let Some(last) = self.last_sample else {
+            return self.ewma;
+        };
+        let Some(stale) = now
+            .saturating_duration_since(last)
+            .checked_sub(decay.decay_after())
+        else {
+            return self.ewma;
+        };
+        let half_life = decay.half_life().as_secs_f64().max(1e-3);
+        let weight = 0.5_f64.powf(stale.as_secs_f64() / half_life);
+        baseline + (self.ewma - baseline) * weight
+    }
 }
 
 /// EWMA-based latency tracker for backend tier routing.
@@ -53,6 +121,7 @@ pub struct EwmaTracker {
     alpha: f64,
     min_samples: u64,
     failure_penalty: f64,
+    latency_window: AtomicUsize,
 }
 
 impl Default for EwmaTracker {
@@ -68,6 +137,7 @@ impl EwmaTracker {
             alpha: DEFAULT_EWMA_ALPHA,
             min_samples: DEFAULT_MIN_SAMPLES,
             failure_penalty: DEFAULT_FAILURE_PENALTY,
+            latency_window: AtomicUsize::new(DEFAULT_LATENCY_WINDOW),
         }
     }
 
@@ -79,21 +149,42 @@ impl EwmaTracker {
             alpha: alpha.clamp(0.01, 1.0),
             min_samples: min_samples.max(1),
             failure_penalty: failure_penalty.max(1.0),
+            latency_window: AtomicUsize::new(DEFAULT_LATENCY_WINDOW),
         }
     }
 
+    /// Set how many successful-attempt latencies are kept per tier, clamped
+    /// to `1..=MAX_LATENCY_WINDOW`. Longer windows shrink on the next sample.
+    pub fn set_latency_window(&self, size: usize) {
+        self.latency_window
+            .store(size.clamp(1, MAX_LATENCY_WINDOW), Ordering::Relaxed);
+    }
+
     /// Record a successful request's latency for a tier.
     pub fn record_success(&self, tier: &str, duration_secs: f64) {
+        self.record_success_with_alpha(tier, duration_secs, self.alpha);
+    }
+
+    /// Record a successful request's latency using a tier-specific alpha.
+    pub fn record_success_with_alpha(&self, tier: &str, duration_secs: f64, alpha: f64) {
+        let alpha = alpha.clamp(0.01, 1.0);
         let mut state = self.state.write();
         let entry = state.entry(tier.to_string()).or_insert_with(TierState::new);
 
         if entry.samples == 0 {
             entry.ewma = duration_secs;
         } else {
-            entry.ewma = self.alpha * duration_secs + (1.0 - self.alpha) * entry.ewma;
+            entry.ewma = alpha * duration_secs + (1.0 - alpha) * entry.ewma;
         }
         entry.samples += 1;
         entry.consecutive_failures = 0;
+        entry.last_sample = Some(Instant::now());
+
+        let window = self.latency_window.load(Ordering::Relaxed);
+        while entry.window.len() >= window {
+            entry.window.pop_front();
+        }
+        entry.window.push_back(duration_secs);
 
         debug!(
             tier = tier,
@@ -107,18 +198,18 @@ impl EwmaTracker {
     /// without requiring a wall-clock duration (failures often hit timeouts
     /// that don't reflect backend speed).
     pub fn record_failure(&self, tier: &str) {
+        self.record_failure_with_alpha(tier, self.alpha);
+    }
+
+    /// Record a failed request using a tier-specific alpha.
+    pub fn record_failure_with_alpha(&self, tier: &str, alpha: f64) {
+        let alpha = alpha.clamp(0.01, 1.0);
         let mut state = self.state.write();
         let entry = state.entry(tier.to_string()).or_insert_with(TierState::new);
 
         entry.consecutive_failures += 1;
-        entry.samples += 1;
-
-        // Only penalize if we have a baseline EWMA to work from.
-        // Otherwise we'd be multiplying zero.
-        if entry.ewma > 0.0 {
-            let penalty_duration = entry.ewma * self.failure_penalty;
-            entry.ewma = self.alpha * penalty_duration + (1.0 - self.alpha) * entry.ewma;
-        }
+        entry.last_failure = Some(Instant::now());
+        self.apply_penalty(entry, alpha);
 
         debug!(
             tier = tier,
@@ -128,6 +219,34 @@ impl EwmaTracker {
         );
     }
 
+    /// Record an attempt that was cancelled before it finished, e.g. a hedge
+    /// race loser. The EWMA takes the failure penalty, but the attempt does
+    /// not count toward the consecutive failures that open the circuit.
+    pub fn record_cancellation_with_alpha(&self, tier: &str, alpha: f64) {
+        let alpha = alpha.clamp(0.01, 1.0);
+        let mut state = self.state.write();
+        let entry = state.entry(tier.to_string()).or_insert_with(TierState::new);
+        self.apply_penalty(entry, alpha);
+
+        debug!(
+            tier = tier,
+            ewma = entry.ewma,
+            "EWMA updated (cancelled attempt)"
+        );
+    }
+
+    fn apply_penalty(&self, entry: &mut TierState, alpha: f64) {
+        entry.last_sample = Some(Instant::now());
+        entry.samples += 1;
+
+        // Only penalize if we have a baseline EWMA to work from.
+        // Otherwise we'd be multiplying zero.
+        if entry.ewma > 0.0 {
+            let penalty_duration = entry.ewma * self.failure_penalty;
+            entry.ewma = alpha * penalty_duration + (1.0 - alpha) * entry.ewma;
+        }
+    }
+
     /// Get the current EWMA latency for a specific tier.
     /// Returns `None` if the tier has no recorded samples.
     pub fn get_latency(&self, tier: &str) -> Option<(f64, u64)> {
@@ -135,6 +254,24 @@ impl EwmaTracker {
         state.get(tier).map(|s| (s.ewma, s.samples))
     }
 
+    /// p50/p95/p99 over the tier's latency window. Failed attempts are not
+    /// included, since their duration does not reflect backend speed.
+    pub fn get_quantiles(&self, tier: &str) -> Option<LatencyQuantiles> {
+        let state = self.state.read();
+        LatencyQuantiles::from_samples(&state.get(tier)?.window)
+    }
+
+    /// Whether `tier` has failed at least `threshold` times in a row, the
+    /// latest within `cooldown`. Once the cooldown passes the tier counts as
+    /// healthy again, so strict routing can probe it.
+    pub fn is_circuit_open(&self, tier: &str, threshold: u64, cooldown: Duration) -> bool {
+        let state = self.state.read();
+        state.get(tier).is_some_and(|s| {
+            s.consecutive_failures >= threshold.max(1)
+                && s.last_failure.is_some_and(|at| at.elapsed() < cooldown)
+        })
+    }
+
     /// Get latencies for all tracked tiers.
     /// Returns `(tier_name, ewma_seconds, sample_count)` tuples.
     pub fn get_all_latencies(&self) -> Vec<(String, f64, u64)> {
@@ -145,17 +282,35 @@ impl EwmaTracker {
             .collect()
     }
 
+    /// Forget everything recorded for `tier`, or for every tier when `None`,
+    /// so the tiers start cold again. Returns the names of the tiers cleared.
+    pub fn reset(&self, tier: Option<&str>) -> Vec<String> {
+        let mut state = self.state.write();
+        match tier {
+            Some(tier) => state
+                .remove(tier)
+                .map(|_| vec![tier.to_string()])
+                .unwrap_or_default(),
+            None => state.drain().map(|(tier, _)| tier).collect(),
+        }
+    }
+
     /// Restore a tier EWMA snapshot, used by persistence backends at startup.
+    ///
+    /// Alpha is not part of the snapshot: later samples blend into the
+    /// restored EWMA with whatever alpha is configured now.
     pub fn restore_tier_state(&self, tier: &str, ewma: f64, samples: u64) {
         let mut state = self.state.write();
         let entry = state.entry(tier.to_string()).or_insert_with(TierState::new);
         entry.ewma = ewma.max(0.0);
         entry.samples = samples;
         entry.consecutive_failures = 0;
+        // The snapshot's age is unknown; decay counts from the restore.
+        entry.last_sample = Some(Instant::now());
     }
 
     /// Reorder tiers by EWMA latency (lowest first). Tiers without enough
-    /// samples keep their config-defined position.
+    /// samples go first, in config order, so they get measured.
     ///
     /// Returns `(tier_route, tier_name)` pairs in priority order.
     pub fn sort_tiers(&self, tiers: &[String]) -> Vec<(String, String)> {
@@ -177,11 +332,12 @@ impl EwmaTracker {
             })
             .collect();
 
-        // Stable sort: measured tiers by EWMA ascending, unmeasured keep config order.
+        // Stable sort: unmeasured tiers first in config order, then measured
+        // tiers by EWMA ascending.
         entries.sort_by(|a, b| match (a.3, b.3) {
             (Some(la), Some(lb)) => la.partial_cmp(&lb).unwrap_or(std::cmp::Ordering::Equal),
-            (Some(_), None) => std::cmp::Ordering::Less,
-            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (Some(_), None) => std::cmp::Ordering::Greater,
+            (None, Some(_)) => std::cmp::Ordering::Less,
             (None, None) => a.0.cmp(&b.0),
         });
 
@@ -211,60 +367,83 @@ impl EwmaTracker {
     /// Low `routing_temperature` (e.g. 0.1) → nearly deterministic (fastest tier wins).
     /// High `routing_temperature` (e.g. 5.0) → nearly uniform distribution.
     /// Default temperature is 1.0.
+    ///
+    /// Cold tiers (fewer than `ewmaMinSamples` samples) are treated
+    /// optimistically: they are shuffled and placed ahead of measured tiers,
+    /// so a new or restored tier gets tried instead of starving behind tiers
+    /// that already have an estimate. `top_k` limits only the measured tiers.
+    ///
+    /// With `Router.ewmaDecay`, measured tiers that have been idle past
+    /// `decay_after_secs` are ranked by their decayed EWMA.
     pub fn sort_tiers_with_config(
         &self,
         tiers: &[String],
         config: &crate::config::Config,
+    ) -> Vec<(String, String)> {
+        self.sort_tiers_with_config_at(tiers, config, Instant::now())
+    }
+
+    fn sort_tiers_with_config_at(
+        &self,
+        tiers: &[String],
+        config: &crate::config::Config,
+        now: Instant,
     ) -> Vec<(String, String)> {
         let router_config = config.router();
         let state = self.state.read();
+        let min_samples = router_config
+            .ewma_min_samples
+            .unwrap_or(self.min_samples)
+            .max(1);
+
+        let mut cold: Vec<(String, String)> = Vec::new();
+        let mut warm: Vec<(String, String, &TierState)> = Vec::new();
+        for tier in tiers {
+            let tier_name = config.backend_abbreviation_with_config(tier);
+            match state.get(&tier_name) {
+                Some(s) if s.samples >= min_samples => warm.push((tier.clone(), tier_name, s)),
+                _ => cold.push((tier.clone(), tier_name)),
+            }
+        }
+        let warm: Vec<(String, String, f64)> = match &router_config.ewma_decay {
+            Some(decay) => {
+                let baseline = decay
+                    .baseline_secs
+                    .unwrap_or_else(|| warm.iter().map(|(_, _, s)| s.ewma).fold(0.0, f64::max));
+                warm.into_iter()
+                    .map(|(tier, name, s)| (tier, name, s.decayed_ewma(decay, baseline, now)))
+                    .collect()
+            }
+            None => warm
+                .into_iter()
+                .map(|(tier, name, s)| (tier, name, s.ewma))
+                .collect(),
+        };
+        drop(state);
+
+        // Shuffle so cold-start doesn't always hammer the first tier.
+        let mut rng = rand::thread_rng();
+        cold.shuffle(&mut rng);
 
-        // If top_k is not specified, default to all tiers.
-        let top_k = router_config.top_k.unwrap_or(tiers.len());
-
-        // Fallback: shuffle unmeasured tiers so cold-start traffic distributes.
-        if state.is_empty() {
-            let mut entries: Vec<(String, String)> = tiers
-                .iter()
-                .map(|tier| (tier.clone(), config.backend_abbreviation_with_config(tier)))
-                .collect();
-
-            // Shuffle so cold-start doesn't always hammer the first tier.
-            let mut rng = rand::thread_rng();
-            entries.shuffle(&mut rng);
-
+        if warm.is_empty() {
             if tracing::enabled!(tracing::Level::DEBUG) {
-                let order: Vec<String> = entries.iter().map(|(_, name)| name.clone()).collect();
+                let order: Vec<String> = cold.iter().map(|(_, name)| name.clone()).collect();
                 debug!(
                     order = ?order,
                     "tier routing order (unmeasured, shuffled)"
                 );
             }
-            return entries;
+            return cold;
         }
 
+        // If top_k is not specified, default to all measured tiers.
+        let top_k = router_config.top_k.unwrap_or(warm.len());
         let temperature = router_config.routing_temperature.unwrap_or(1.0).max(1e-6);
 
-        let entries: Vec<(String, String, f64)> = tiers
-            .iter()
-            .map(|tier| {
-                let tier_name = config.backend_abbreviation_with_config(tier);
-                let ewma = state.get(&tier_name).and_then(|s| {
-                    if s.samples >= self.min_samples {
-                        Some(s.ewma)
-                    } else {
-                        None
-                    }
-                });
-
-                // Higher latency = lower score (logit). Penalize unmeasured tiers.
-                let logit = match ewma {
-                    Some(latency) => -latency / temperature,
-                    None => -1.0e9, // A large penalty for unmeasured tiers
-                };
-
-                (tier.clone(), tier_name, logit)
-            })
+        // Higher latency = lower score (logit).
+        let entries: Vec<(String, String, f64)> = warm
+            .into_iter()
+            .map(|(tier, name, latency)| (tier, name, -latency / temperature))
             .collect();
 
         // Apply softmax
@@ -292,9 +471,10 @@ impl EwmaTracker {
         // Weighted sampling without replacement: draw tiers proportional to
         // their softmax probabilities. This distributes traffic across backends
         // instead of always funneling to the fastest one.
-        let mut rng = rand::thread_rng();
         let weights: Vec<f64> = probabilities.iter().map(|(_, _, p)| *p).collect();
-        let mut result: Vec<(String, String)> = Vec::with_capacity(top_k.min(probabilities.len()));
+        let cold_count = cold.len();
+        let mut result: Vec<(String, String)> = cold;
+        result.reserve(top_k.min(probabilities.len()));
         let mut remaining: Vec<(String, String, f64)> = probabilities;
 
         for _ in 0..top_k.min(remaining.len().max(1)) {
@@ -327,9 +507,12 @@ impl EwmaTracker {
             let order: Vec<String> = result
                 .iter()
                 .enumerate()
-                .map(|(i, (_, name))| {
-                    let prob = weights.get(i).copied().unwrap_or(0.0);
-                    format!("{}({:.1}%)", name, prob * 100.0)
+                .map(|(i, (_, name))| match i.checked_sub(cold_count) {
+                    Some(j) => {
+                        let prob = weights.get(j).copied().unwrap_or(0.0);
+                        format!("{}({:.1}%)", name, prob * 100.0)
+                    }
+                    None => format!("{}(cold)", name),
                 })
                 .collect();
             debug!(order = ?order, k=top_k, "tier routing order (weighted sample)");
@@ -345,7 +528,9 @@ pub struct AttemptTimer<'a> {
     tracker: &'a EwmaTracker,
     tier: String,
     start: Instant,
+    alpha: Option<f64>,
     recorded: bool,
+    cancel_on_drop: bool,
 }
 
 impl<'a> AttemptTimer<'a> {
@@ -354,14 +539,41 @@ impl<'a> AttemptTimer<'a> {
             tracker,
             tier: tier.to_string(),
             start: Instant::now(),
+            alpha: None,
             recorded: false,
+            cancel_on_drop: false,
+        }
+    }
+
+    /// Treat a drop without a recorded outcome as a cancelled attempt rather
+    /// than a failure, for attempts that may lose a race.
+    pub fn cancel_on_drop(mut self) -> Self {
+        self.cancel_on_drop = true;
+        self
+    }
+
+    /// Record with this smoothing factor instead of the tracker default.
+    pub fn with_alpha(mut self, alpha: f64) -> Self {
+        self.alpha = Some(alpha);
+        self
+    }
+
+    fn record_failure(&self) {
+        match self.alpha {
+            Some(alpha) => self.tracker.record_failure_with_alpha(&self.tier, alpha),
+            None => self.tracker.record_failure(&self.tier),
         }
     }
 
     /// Record a successful attempt. Returns the measured duration in seconds.
     pub fn finish_success(mut self) -> f64 {
         let duration = self.start.elapsed().as_secs_f64();
-        self.tracker.record_success(&self.tier, duration);
+        match self.alpha {
+            Some(alpha) => self
+                .tracker
+                .record_success_with_alpha(&self.tier, duration, alpha),
+            None => self.tracker.record_success(&self.tier, duration),
+        }
         self.recorded = true;
         duration
     }
@@ -369,12 +581,11 @@ impl<'a> AttemptTimer<'a> {
     /// Record a failed attempt. Applies the failure penalty to the EWMA
     /// instead of using the elapsed time.
     pub fn finish_failure(mut self) {
-        self.tracker.record_failure(&self.tier);
+        self.record_failure();
         self.recorded = true;
     }
 
     /// Get elapsed time without recording. Useful for logging.
-    #[allow(dead_code)]
     pub fn elapsed_secs(&self) -> f64 {
         self.start.elapsed().as_secs_f64()
     }
@@ -382,7 +593,16 @@ impl<'a> AttemptTimer<'a> {
 
 impl Drop for AttemptTimer<'_> {
     fn drop(&mut self) {
-        if !self.recorded {
+        if !self.recorded && self.cancel_on_drop {
+            debug!(
+                tier = self.tier,
+                elapsed = self.start.elapsed().as_secs_f64(),
+                "AttemptTimer dropped, recording a cancelled attempt"
+            );
+            let alpha = self.alpha.unwrap_or(self.tracker.alpha);
+            self.tracker
+                .record_cancellation_with_alpha(&self.tier, alpha);
+        } else if !self.recorded {
             // Timer dropped without explicit success/failure. Treat as failure
             // since the caller didn't reach the success path.
             info!(
@@ -390,7 +610,7 @@ impl Drop for AttemptTimer<'_> {
                 elapsed = self.start.elapsed().as_secs_f64(),
                 "AttemptTimer dropped without recording, treating as failure"
             );
-            self.tracker.record_failure(&self.tier);
+            self.record_failure();
         }
     }
 }
@@ -475,6 +695,22 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_circuit_opens_after_consecutive_failures() {
+        let tracker = EwmaTracker::new();
+        let cooldown = Duration::from_secs(30);
+        tracker.record_failure("tier-0");
+        tracker.record_failure("tier-0");
+        assert!(!tracker.is_circuit_open("tier-0", 3, cooldown));
+        tracker.record_failure("tier-0");
+        assert!(tracker.is_circuit_open("tier-0", 3, cooldown));
+        assert!(!tracker.is_circuit_open("tier-0", 3, Duration::ZERO));
+        assert!(!tracker.is_circuit_open("tier-1", 3, cooldown));
+
+        tracker.record_success("tier-0", 1.0);
+        assert!(!tracker.is_circuit_open("tier-0", 3, cooldown));
+    }
+
     #[test]
     fn test_sort_tiers_by_latency() {
         let tracker = EwmaTracker::with_params(0.3, 1, 2.0); // min_samples=1 for test
@@ -562,6 +798,22 @@ mod tests {
         assert_eq!(count, 2, "drop should have recorded a failure");
     }
 
+    #[test]
+    fn test_cancelled_attempt_does_not_open_circuit() {
+        let tracker = EwmaTracker::new();
+        tracker.record_success("tier-0", 1.0);
+        let cooldown = Duration::from_secs(30);
+
+        for _ in 0..3 {
+            let _timer = AttemptTimer::start(&tracker, "tier-0").cancel_on_drop();
+        }
+
+        let (ewma, count) = tracker.get_latency("tier-0").unwrap();
+        assert!(ewma > 1.0, "cancellation should still apply the penalty");
+        assert_eq!(count, 4);
+        assert!(!tracker.is_circuit_open("tier-0", 3, cooldown));
+    }
+
     #[test]
     fn test_get_all_latencies() {
         let tracker = EwmaTracker::new();
@@ -573,9 +825,238 @@ mod tests {
         assert_eq!(latencies.len(), 3);
     }
 
+    #[test]
+    fn test_reset_clears_one_or_all_tiers() {
+        let tracker = EwmaTracker::new();
+        for tier in ["a", "b", "c"] {
+            tracker.record_success(tier, 1.0);
+        }
+
+        assert_eq!(tracker.reset(Some("a")), vec!["a".to_string()]);
+        assert!(tracker.get_latency("a").is_none());
+        assert!(tracker.reset(Some("a")).is_empty());
+
+        let mut cleared = tracker.reset(None);
+        cleared.sort();
+        assert_eq!(cleared, vec!["b".to_string(), "c".to_string()]);
+        assert!(tracker.get_all_latencies().is_empty());
+    }
+
     #[test]
     fn test_no_latency_for_unknown_tier() {
         let tracker = EwmaTracker::new();
         assert!(tracker.get_latency("nonexistent").is_none());
     }
+
+    #[test]
+    fn test_latency_quantiles_surface_tail_spikes() {
+        let tracker = EwmaTracker::new();
+        assert!(tracker.get_quantiles("tier-0").is_none());
+
+        // 97 fast responses and 3 very slow ones: the EWMA barely moves, but
+        // p99 shows the spikes.
+        for i in 0..100 {
+            let latency = if i % 33 == 32 { 30.0 } else { 1.0 };
+            tracker.record_success("tier-0", latency);
+        }
+        tracker.record_failure("tier-0");
+
+        let q = tracker.get_quantiles("tier-0").unwrap();
+        assert_eq!(q.p50, 1.0);
+        assert_eq!(q.p95, 1.0);
+        assert_eq!(q.p99, 30.0);
+    }
+
+    #[test]
+    fn test_latency_window_is_bounded() {
+        let tracker = EwmaTracker::new();
+        tracker.set_latency_window(4);
+        for latency in [9.0, 9.0, 9.0, 9.0, 1.0, 2.0, 3.0, 4.0] {
+            tracker.record_success("tier-0", latency);
+        }
+        // Only the last four samples remain.
+        let q = tracker.get_quantiles("tier-0").unwrap();
+        assert_eq!((q.p50, q.p99), (2.0, 4.0));
+        assert_eq!(tracker.state.read()["tier-0"].window.len(), 4);
+
+        // Shrinking the window trims on the next sample.
+        tracker.set_latency_window(2);
+        tracker.record_success("tier-0", 5.0);
+        assert_eq!(tracker.state.read()["tier-0"].window, [4.0, 5.0]);
+
+        tracker.set_latency_window(usize::MAX);
+        assert_eq!(
+            tracker.latency_window.load(Ordering::Relaxed),
+            MAX_LATENCY_WINDOW
+        );
+    }
+
+    fn config_with_router(router: serde_json::Value) -> crate::config::Config {
+        let providers: Vec<serde_json::Value> = ["a", "b", "c"]
+            .iter()
+            .map(|name| {
+                serde_json::json!({
+                    "name": name,
+                    "api_base_url": "http://localhost:9999",
+                    "api_key": "x",
+                    "models": ["m"]
+                })
+            })
+            .collect();
+        let raw = serde_json::json!({"Providers": providers, "Router": router});
+        let temp = tempfile::NamedTempFile::new().expect("temp config file");
+        std::fs::write(temp.path(), raw.to_string()).expect("write config file");
+        crate::config::Config::from_file(temp.path().to_str().expect("config path"))
+            .expect("load Config from file")
+    }
+
+    #[test]
+    fn test_cold_tiers_are_tried_before_measured_tiers() {
+        let config = config_with_router(serde_json::json!({
+            "default": "a,m",
+            "tiers": ["a,m", "b,m", "c,m"],
+            "routingTemperature": 0.001,
+            "ewmaMinSamples": 2
+        }));
+        let tiers = config.backend_tiers();
+        let tracker = EwmaTracker::new();
+        for _ in 0..2 {
+            tracker.record_success("a", 0.5);
+            tracker.record_success("b", 1.0);
+        }
+        // One sample is below ewmaMinSamples, so "c" is still cold even
+        // though its latency is the worst.
+        tracker.record_success("c", 9.0);
+
+        for _ in 0..20 {
+            let order: Vec<String> = tracker
+                .sort_tiers_with_config(&tiers, &config)
+                .into_iter()
+                .map(|(_, name)| name)
+                .collect();
+            assert_eq!(&order[..2], ["c", "a"]);
+        }
+
+        tracker.record_success("c", 9.0);
+        let order: Vec<String> = tracker
+            .sort_tiers_with_config(&tiers, &config)
+            .into_iter()
+            .map(|(_, name)| name)
+            .collect();
+        assert_eq!(order[0], "a", "warm \"c\" is now ranked by latency");
+    }
+
+    #[test]
+    fn test_decayed_ewma_relaxes_toward_baseline() {
+        let decay: EwmaDecayConfig = serde_json::from_value(serde_json::json!({
+            "decay_after_secs": 60,
+            "half_life_secs": 120
+        }))
+        .expect("decay config");
+        let last = Instant::now();
+        let mut state = TierState::new();
+        state.ewma = 1.0;
+        state.last_sample = Some(last);
+
+        let at = |secs| last + Duration::from_secs(secs);
+        assert_eq!(state.decayed_ewma(&decay, 5.0, at(30)), 1.0);
+        assert_eq!(state.decayed_ewma(&decay, 5.0, at(60)), 1.0);
+        assert!((state.decayed_ewma(&decay, 5.0, at(180)) - 3.0).abs() < 1e-9);
+        assert!((state.decayed_ewma(&decay, 5.0, at(60 + 120 * 20)) - 5.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_idle_fast_tier_decays_behind_active_tier() {
+        let config = config_with_router(serde_json::json!({
+            "default": "a,m",
+            "tiers": ["a,m", "b,m"],
+            "routingTemperature": 0.001,
+            "ewmaDecay": {"decay_after_secs": 60, "baseline_secs": 5.0}
+        }));
+        let tiers = config.backend_tiers();
+        let tracker = EwmaTracker::new();
+        for _ in 0..3 {
+            tracker.record_success("a", 0.5);
+            tracker.record_success("b", 2.0);
+        }
+        // "a" has been idle for an hour; "b" was sampled just now.
+        let now = Instant::now() + Duration::from_secs(3600);
+        tracker
+            .state
+            .write()
+            .get_mut("b")
+            .expect("tier b")
+            .last_sample = Some(now);
+
+        let order = |now| -> Vec<String> {
+            tracker
+                .sort_tiers_with_config_at(&tiers, &config, now)
+                .into_iter()
+                .map(|(_, name)| name)
+                .collect()
+        };
+        assert_eq!(order(Instant::now())[0], "a");
+        assert_eq!(order(now)[0], "b", "idle \"a\" decayed toward 5s");
+    }
+
+    #[test]
+    fn test_default_decay_baseline_is_slowest_measured_tier() {
+        let config = config_with_router(serde_json::json!({
+            "default": "a,m",
+            "tiers": ["a,m", "b,m", "c,m"],
+            "routingTemperature": 0.001,
+            "ewmaDecay": {"decay_after_secs": 60}
+        }));
+        let tiers = config.backend_tiers();
+        let tracker = EwmaTracker::new();
+        for _ in 0..3 {
+            tracker.record_success("a", 0.5);
+            tracker.record_success("b", 1.0);
+            tracker.record_success("c", 1.2);
+        }
+        // "a" has been idle for an hour; "b" and "c" were sampled just now.
+        let now = Instant::now() + Duration::from_secs(3600);
+        for tier in ["b", "c"] {
+            tracker
+                .state
+                .write()
+                .get_mut(tier)
+                .expect("measured tier")
+                .last_sample = Some(now);
+        }
+
+        // An average baseline (0.9s) would still rank "a" ahead of "b"; the
+        // slowest EWMA (1.2s) does not.
+        let order: Vec<String> = tracker
+            .sort_tiers_with_config_at(&tiers, &config, now)
+            .into_iter()
+            .map(|(_, name)| name)
+            .collect();
+        assert_eq!(order[0], "b");
+    }
+
+    #[test]
+    fn test_per_tier_alpha_applies_to_restored_state() {
+        let config = config_with_router(serde_json::json!({
+            "default": "a,m",
+            "ewmaAlpha": {"a": 0.5}
+        }));
+        assert_eq!(config.ewma_alpha("a"), 0.5);
+        assert_eq!(config.ewma_alpha("b"), DEFAULT_EWMA_ALPHA);
+
+        // State persisted under a different alpha restores as-is and blends
+        // new samples with the alpha configured now.
+        let tracker = EwmaTracker::new();
+        tracker.restore_tier_state("a", 2.0, 10);
+        tracker.record_success_with_alpha("a", 4.0, config.ewma_alpha("a"));
+        let (ewma, samples) = tracker.get_latency("a").unwrap();
+        assert!((ewma - 3.0).abs() < 1e-9, "expected 3.0, got {}", ewma);
+        assert_eq!(samples, 11);
+
+        let invalid = config_with_router(serde_json::json!({
+            "default": "a,m",
+            "ewmaAlpha": {"a": 1.5}
+        }));
+        assert!(invalid.validate().is_err());
+    }
 }
@@ -12,12 +12,34 @@ pub struct StreamVerifyCtx {
     /// Instant when the upstream HTTP response headers were received.
     /// Used to measure TTFT (time from response start to first content token).
     pub stream_start: Instant,
+    /// Instant the upstream request was sent. `ccr_time_to_first_token_seconds`
+    /// is measured from here, so it includes the wait for response headers.
+    pub dispatched_at: Instant,
     /// Maximum idle time between useful SSE frames before the stream is failed.
     pub stream_idle_timeout: Duration,
+    /// When set, a `: ping` comment is sent to the client after this much
+    /// downstream silence so proxies keep the connection open.
+    pub heartbeat_interval: Option<Duration>,
     /// Resolved token pricing for this request's model, when the provider
     /// declares one. Used to attribute estimated dollar cost per streamed
     /// response; ``None`` leaves the tier's cost unpriced.
     pub pricing: Option<crate::config::ModelPricing>,
+    /// Output token budget enforced on translated streams. Once the estimated
+    /// output exceeds it, the stream is cut off with a `max_tokens` stop.
+    /// ``None`` trusts the provider to honour `max_tokens`.
+    pub max_output_tokens: Option<u64>,
+    /// Provider-specific `delta` field names read when `reasoning_content`
+    /// is absent from an OpenAI stream chunk.
+    pub reasoning_field_aliases: Vec<String>,
+    /// Provider-specific `delta` field names read when `content` is absent.
+    pub content_field_aliases: Vec<String>,
+    /// When false, `reasoning_content` deltas are dropped before
+    /// translation (see `EMIT_UNSOLICITED_REASONING`).
+    pub emit_reasoning: bool,
+    /// When set, translated streams send an interim `message_delta` with the
+    /// estimated output tokens so far each time this many more are counted
+    /// (see `STREAM_USAGE_INTERVAL_TOKENS`).
+    pub usage_interval_tokens: Option<u64>,
 }
 
 /// Parsed SSE frame with `event` and combined multi-line `data`.
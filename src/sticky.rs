@@ -0,0 +1,118 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//! Sticky-session routing: remembers which tier last served a session so
+//! follow-up requests in the same conversation try it first, keeping prompt
+//! caches and reasoning state on one provider.
+
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Request header carrying the client's session ID.
+pub const SESSION_HEADER: &str = "x-ccr-session";
+
+/// Expired pins are swept once the map grows past this many entries.
+const SWEEP_THRESHOLD: usize = 1024;
+
+#[derive(Debug)]
+struct Pin {
+    tier: String,
+    expires_at: Instant,
+}
+
+/// Session ID -> pinned tier, with a per-entry TTL.
+#[derive(Debug, Default)]
+pub struct StickySessions {
+    pins: RwLock<HashMap<String, Pin>>,
+}
+
+impl StickySessions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tier pinned for `session`, if the pin has not expired.
+    pub fn pinned_tier(&self, session: &str) -> Option<String> {
+        self.pinned_tier_at(session, Instant::now())
+    }
+
+    fn pinned_tier_at(&self, session: &str, now: Instant) -> Option<String> {
+        self.pins
+            .read()
+            .get(session)
+            .filter(|pin| now < pin.expires_at)
+            .map(|pin| pin.tier.clone())
+    }
+
+    /// Pin `session` to `tier` for `ttl`, replacing any earlier pin.
+    pub fn pin(&self, session: &str, tier: &str, ttl: Duration) {
+        self.pin_at(session, tier, ttl, Instant::now());
+    }
+
+    fn pin_at(&self, session: &str, tier: &str, ttl: Duration, now: Instant) {
+        let mut pins = self.pins.write();
+        if pins.len() >= SWEEP_THRESHOLD {
+            pins.retain(|_, pin| now < pin.expires_at);
+        }
+        pins.insert(
+            session.to_string(),
+            Pin {
+                tier: tier.to_string(),
+                expires_at: now + ttl,
+            },
+        );
+    }
+
+    /// Number of stored pins, including expired ones not yet swept.
+    pub fn len(&self) -> usize {
+        self.pins.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pin_expires_after_ttl() {
+        let sessions = StickySessions::new();
+        let now = Instant::now();
+        sessions.pin_at(
+            "conv-1",
+            "deepseek,deepseek-chat",
+            Duration::from_secs(60),
+            now,
+        );
+
+        assert_eq!(
+            sessions.pinned_tier_at("conv-1", now + Duration::from_secs(59)),
+            Some("deepseek,deepseek-chat".to_string())
+        );
+        assert_eq!(
+            sessions.pinned_tier_at("conv-1", now + Duration::from_secs(60)),
+            None
+        );
+        assert_eq!(sessions.pinned_tier_at("conv-2", now), None);
+    }
+
+    #[test]
+    fn repin_replaces_tier_and_sweeps_expired() {
+        let sessions = StickySessions::new();
+        let now = Instant::now();
+        for i in 0..SWEEP_THRESHOLD {
+            sessions.pin_at(&format!("old-{i}"), "a,m", Duration::from_secs(1), now);
+        }
+        let later = now + Duration::from_secs(2);
+        sessions.pin_at("conv", "a,m", Duration::from_secs(60), later);
+        assert_eq!(sessions.len(), 1);
+
+        sessions.pin_at("conv", "b,m", Duration::from_secs(60), later);
+        assert_eq!(
+            sessions.pinned_tier_at("conv", later),
+            Some("b,m".to_string())
+        );
+    }
+}
@@ -6,6 +6,7 @@
 //! - Request: strip Anthropic-specific passthrough fields
 //! - Response: extract legacy `` tags for backward compatibility
 //! - Response: preserve modern `reasoning_content` field
+//! - Response: re-encode tool call `arguments` sent as a JSON object
 //!
 //! Modern Z.AI models (GLM-5.2+) return `reasoning_content` directly in
 //! OpenAI-compatible format. Older models may use `` tags which
@@ -142,6 +143,24 @@ impl GlmTransformer {
         (clean, reasoning_opt)
     }
 
+    /// GLM sometimes returns `function.arguments` as the object itself
+    /// instead of its JSON encoding. Re-encode it so the message matches the
+    /// OpenAI shape.
+    fn normalize_tool_call_arguments(parent: &mut Value) {
+        let Some(tool_calls) = parent.get_mut("tool_calls").and_then(Value::as_array_mut) else {
+            return;
+        };
+        for tool_call in tool_calls {
+            let Some(arguments) = tool_call.pointer_mut("/function/arguments") else {
+                continue;
+            };
+            if !arguments.is_string() && !arguments.is_null() {
+                trace!("Re-encoding object-valued GLM tool call arguments");
+                *arguments = Value::String(arguments.to_string());
+            }
+        }
+    }
+
     fn process_parent(&self, parent: &mut Value, is_streaming_delta: bool) {
         let Some(obj) = parent.as_object_mut() else {
             return;
@@ -255,9 +274,11 @@ impl Transformer for GlmTransformer {
         if let Some(choices) = response.get_mut("choices").and_then(|c| c.as_array_mut()) {
             for choice in choices {
                 if let Some(message) = choice.get_mut("message") {
+                    Self::normalize_tool_call_arguments(message);
                     self.process_parent(message, false);
                 }
                 if let Some(delta) = choice.get_mut("delta") {
+                    Self::normalize_tool_call_arguments(delta);
                     self.process_parent(delta, true);
                 }
             }
@@ -448,4 +469,38 @@ Then, I recall the answer from my knowledge base.</think>";
         assert_eq!(message["content"], "");
         assert_eq!(message["reasoning_content"], "This is the reasoning.");
     }
+
+    #[test]
+    fn stringifies_object_tool_call_arguments() {
+        let transformer = GlmTransformer::default();
+        let response = json!({
+            "choices": [{
+                "message": {
+                    "role": "assistant",
+                    "content": "",
+                    "tool_calls": [
+                        {
+                            "id": "call_1",
+                            "type": "function",
+                            "function": {"name": "read_file", "arguments": {"path": "a.rs"}}
+                        },
+                        {
+                            "id": "call_2",
+                            "type": "function",
+                            "function": {"name": "ls", "arguments": "{\"dir\":\".\"}"}
+                        }
+                    ]
+                }
+            }]
+        });
+
+        let transformed = transformer.transform_response(response).unwrap();
+        let tool_calls = &transformed["choices"][0]["message"]["tool_calls"];
+        let first = tool_calls[0]["function"]["arguments"].as_str().unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(first).unwrap(),
+            json!({"path": "a.rs"})
+        );
+        assert_eq!(tool_calls[1]["function"]["arguments"], "{\"dir\":\".\"}");
+    }
 }
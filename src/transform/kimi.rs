@@ -44,6 +44,10 @@ impl Transformer for KimiTransformer {
         "kimi"
     }
 
+    fn stream_think_tags(&self) -> &'static [(&'static str, &'static str)] {
+        &[("◁think▷", "◁/think▷")]
+    }
+
     fn transform_response(&self, mut response: Value) -> Result<Value> {
         trace!(response = ?response, "Starting Kimi transform");
 
@@ -16,10 +16,13 @@ use tracing::debug;
 /// Options:
 /// - `max_tokens: u32` (required) - The value to set
 /// - `override_if_higher: bool` (default: true) - Only set if higher than existing
+/// - `enforce_on_stream: bool` (default: false) - Cut off streamed responses
+///   that overrun the request's `max_tokens`
 #[derive(Debug, Clone)]
 pub struct MaxTokenTransformer {
     max_tokens: u32,
     override_if_higher: bool,
+    enforce_on_stream: bool,
 }
 
 impl MaxTokenTransformer {
@@ -32,6 +35,7 @@ impl MaxTokenTransformer {
         Self {
             max_tokens,
             override_if_higher,
+            enforce_on_stream: false,
         }
     }
 
@@ -41,7 +45,8 @@ impl MaxTokenTransformer {
     /// ```json
     /// {
     ///   "max_tokens": 65536,
-    ///   "override_if_higher": true
+    ///   "override_if_higher": true,
+    ///   "enforce_on_stream": false
     /// }
     /// ```
     pub fn from_options(options: &Value) -> Result<Self> {
@@ -56,7 +61,15 @@ impl MaxTokenTransformer {
             .and_then(|v| v.as_bool())
             .unwrap_or(true);
 
-        Ok(Self::new(max_tokens, override_if_higher))
+        let enforce_on_stream = options
+            .get("enforce_on_stream")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        Ok(Self {
+            enforce_on_stream,
+            ..Self::new(max_tokens, override_if_higher)
+        })
     }
 }
 
@@ -65,6 +78,10 @@ impl Transformer for MaxTokenTransformer {
         "maxtoken"
     }
 
+    fn enforces_stream_max_tokens(&self) -> bool {
+        self.enforce_on_stream
+    }
+
     fn transform_request(&self, mut request: Value) -> Result<Value> {
         if let Some(request_obj) = request.as_object_mut() {
             if let Some(existing) = request_obj.get("max_tokens") {
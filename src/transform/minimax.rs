@@ -9,6 +9,7 @@
 //! - Response M2.x: map `reasoning_details` -> `reasoning_content`
 //! - Response M3: preserve native `thinking` blocks, handle structured reasoning
 //! - Response: convert thinking-only Anthropic responses to text content
+//! - Stream: split `<think>` tags in content deltas into thinking blocks
 //! - Response: normalize cache tokens in usage
 //!
 //! Model capabilities:
@@ -56,6 +57,10 @@ impl Transformer for MinimaxTransformer {
         "minimax"
     }
 
+    fn stream_think_tags(&self) -> &'static [(&'static str, &'static str)] {
+        &[("<think>", "</think>")]
+    }
+
     fn transform_request(&self, mut request: Value) -> Result<Value> {
         let Some(obj) = request.as_object_mut() else {
             return Ok(request);
@@ -28,6 +28,8 @@ pub mod anthropic_to_openai;
 pub use anthropic_to_openai::AnthropicToOpenAiResponseTransformer;
 pub mod maxtoken;
 pub use maxtoken::MaxTokenTransformer;
+pub mod multimodal_reorder;
+pub use multimodal_reorder::MultimodalReorderTransformer;
 pub mod openai;
 pub mod openai_to_anthropic;
 pub use openai_to_anthropic::OpenAiToAnthropicTransformer;
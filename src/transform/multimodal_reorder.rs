@@ -0,0 +1,206 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//! Multimodal content reordering transformer.
+//!
+//! Some providers reject a user message that mixes a large image with long
+//! text unless the blocks come in a particular order, or arrive as separate
+//! messages. This transformer puts the text and image blocks of each mixed
+//! user message in the configured order and can split them into consecutive
+//! messages.
+//!
+//! Configured via options: `{ "order": "images_first" | "text_first",
+//! "split": bool }` (defaults: `images_first`, no split).
+
+use crate::transformer::Transformer;
+use anyhow::Result;
+use serde_json::Value;
+
+/// Which kind of block goes first in a mixed message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockOrder {
+    ImagesFirst,
+    TextFirst,
+}
+
+impl BlockOrder {
+    /// Parse an ordering from a string. Unknown values mean `images_first`.
+    pub fn from_str_lossy(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "text_first" | "text-first" => Self::TextFirst,
+            _ => Self::ImagesFirst,
+        }
+    }
+}
+
+/// Reorders (and optionally splits) mixed text/image user messages.
+#[derive(Debug, Clone)]
+pub struct MultimodalReorderTransformer {
+    order: BlockOrder,
+    split: bool,
+}
+
+impl MultimodalReorderTransformer {
+    pub fn new(order: BlockOrder, split: bool) -> Self {
+        Self { order, split }
+    }
+
+    /// Create a transformer from JSON options.
+    pub fn from_options(options: &Value) -> Self {
+        let order = options
+            .get("order")
+            .and_then(|v| v.as_str())
+            .map(BlockOrder::from_str_lossy)
+            .unwrap_or(BlockOrder::ImagesFirst);
+        let split = options
+            .get("split")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        Self { order, split }
+    }
+
+    /// Reordered content groups for a message, or `None` to leave it alone.
+    /// Only messages made entirely of text and image blocks, with at least
+    /// one of each, are touched, so tool blocks keep their required order.
+    fn regroup(&self, content: &[Value]) -> Option<(Vec<Value>, Vec<Value>)> {
+        let mut images = Vec::new();
+        let mut text = Vec::new();
+        for block in content {
+            match block.get("type").and_then(|t| t.as_str()) {
+                Some("image") => images.push(block.clone()),
+                Some("text") => text.push(block.clone()),
+                _ => return None,
+            }
+        }
+        if images.is_empty() || text.is_empty() {
+            return None;
+        }
+        Some(match self.order {
+            BlockOrder::ImagesFirst => (images, text),
+            BlockOrder::TextFirst => (text, images),
+        })
+    }
+}
+
+impl Default for MultimodalReorderTransformer {
+    fn default() -> Self {
+        Self::new(BlockOrder::ImagesFirst, false)
+    }
+}
+
+impl Transformer for MultimodalReorderTransformer {
+    fn name(&self) -> &str {
+        "multimodal_reorder"
+    }
+
+    fn transform_request(&self, mut request: Value) -> Result<Value> {
+        let Some(messages) = request.get_mut("messages").and_then(|v| v.as_array_mut()) else {
+            return Ok(request);
+        };
+
+        let mut rewritten = Vec::with_capacity(messages.len());
+        for message in messages.drain(..) {
+            let groups = (message.get("role").and_then(|r| r.as_str()) == Some("user"))
+                .then(|| message.get("content").and_then(|c| c.as_array()))
+                .flatten()
+                .and_then(|content| self.regroup(content));
+            let Some((first, second)) = groups else {
+                rewritten.push(message);
+                continue;
+            };
+
+            if self.split {
+                for group in [first, second] {
+                    let mut part = message.clone();
+                    part["content"] = Value::Array(group);
+                    rewritten.push(part);
+                }
+            } else {
+                let mut message = message;
+                message["content"] = Value::Array(first.into_iter().chain(second).collect());
+                rewritten.push(message);
+            }
+        }
+        *messages = rewritten;
+
+        Ok(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn image() -> Value {
+        json!({"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "iVBORw0KGgo="}})
+    }
+
+    fn mixed_request() -> Value {
+        json!({
+            "model": "m",
+            "messages": [
+                {"role": "user", "content": [
+                    {"type": "text", "text": "describe"},
+                    image(),
+                    {"type": "text", "text": "in detail"}
+                ]},
+                {"role": "assistant", "content": [{"type": "text", "text": "ok"}]}
+            ]
+        })
+    }
+
+    fn block_types(message: &Value) -> Vec<&str> {
+        message["content"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|b| b["type"].as_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn images_first_keeps_text_order() {
+        let out = MultimodalReorderTransformer::default()
+            .transform_request(mixed_request())
+            .unwrap();
+        let messages = out["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(block_types(&messages[0]), ["image", "text", "text"]);
+        assert_eq!(messages[0]["content"][1]["text"], "describe");
+        assert_eq!(messages[0]["content"][2]["text"], "in detail");
+    }
+
+    #[test]
+    fn text_first_with_split_emits_separate_messages() {
+        let transformer = MultimodalReorderTransformer::from_options(
+            &json!({"order": "text_first", "split": true}),
+        );
+        let out = transformer.transform_request(mixed_request()).unwrap();
+        let messages = out["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0]["role"], "user");
+        assert_eq!(block_types(&messages[0]), ["text", "text"]);
+        assert_eq!(messages[1]["role"], "user");
+        assert_eq!(block_types(&messages[1]), ["image"]);
+        assert_eq!(messages[2]["role"], "assistant");
+    }
+
+    #[test]
+    fn leaves_tool_and_single_kind_messages_alone() {
+        let request = json!({
+            "messages": [
+                {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "t1", "content": "done"},
+                    {"type": "text", "text": "next"},
+                    image()
+                ]},
+                {"role": "user", "content": [image(), image()]},
+                {"role": "user", "content": "plain"}
+            ]
+        });
+        let transformer = MultimodalReorderTransformer::from_options(
+            &json!({"order": "text_first", "split": true}),
+        );
+        let out = transformer.transform_request(request.clone()).unwrap();
+        assert_eq!(out, request);
+    }
+}
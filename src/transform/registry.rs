@@ -6,8 +6,9 @@
 
 use super::{
     AnthropicToOpenaiTransformer, DeepSeekTransformer, GlmTransformer, KimiTransformer,
-    MaxTokenTransformer, MinimaxTransformer, OpenAiToAnthropicTransformer,
-    OutputCompressTransformer, ThinkTagTransformer, ToolCompressTransformer,
+    MaxTokenTransformer, MinimaxTransformer, MultimodalReorderTransformer,
+    OpenAiToAnthropicTransformer, OutputCompressTransformer, ThinkTagTransformer,
+    ToolCompressTransformer,
 };
 use crate::config::TransformerEntry;
 use crate::transformer::{LongCatThinkingTransformer, Transformer, TransformerChain};
@@ -79,6 +80,12 @@ impl TransformerRegistry {
             Box::new(OutputCompressTransformer)
         });
 
+        // Content layout transformers
+        registry.register("multimodal_reorder", |opts| match opts {
+            Some(options) => Box::new(MultimodalReorderTransformer::from_options(options)),
+            None => Box::new(MultimodalReorderTransformer::default()),
+        });
+
         registry
     }
 
@@ -154,7 +161,7 @@ mod tests {
     fn registry_new_registers_provider_transformers() {
         let registry = TransformerRegistry::new();
         assert!(!registry.is_empty());
-        assert_eq!(registry.len(), 12);
+        assert_eq!(registry.len(), 13);
         assert!(registry.has("zai"));
         assert!(registry.has("minimax"));
         assert!(registry.has("moonshot"));
@@ -167,6 +174,7 @@ mod tests {
         assert!(registry.has("thinktag"));
         assert!(registry.has("toolcompress"));
         assert!(registry.has("output_compress"));
+        assert!(registry.has("multimodal_reorder"));
     }
 
     #[test]
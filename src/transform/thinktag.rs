@@ -29,6 +29,14 @@ impl Transformer for ThinkTagTransformer {
         "thinktag"
     }
 
+    fn stream_think_tags(&self) -> &'static [(&'static str, &'static str)] {
+        &[
+            ("<think>", "</think>"),
+            ("<thinking>", "</thinking>"),
+            ("<reasoning>", "</reasoning>"),
+        ]
+    }
+
     fn transform_response(&self, mut response: Value) -> Result<Value> {
         if let Some(content) = response.get_mut("content") {
             if let Some(arr) = content.as_array_mut() {
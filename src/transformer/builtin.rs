@@ -304,11 +304,22 @@ impl Transformer for ToolUseTransformer {
 #[derive(Debug, Clone)]
 pub struct MaxTokenTransformer {
     max_tokens: u32,
+    enforce_on_stream: bool,
 }
 
 impl MaxTokenTransformer {
     pub fn new(max_tokens: u32) -> Self {
-        Self { max_tokens }
+        Self {
+            max_tokens,
+            enforce_on_stream: false,
+        }
+    }
+
+    /// Also cut off streamed responses that overrun the request's
+    /// `max_tokens`, for providers that ignore the limit.
+    pub fn with_stream_enforcement(mut self, enforce: bool) -> Self {
+        self.enforce_on_stream = enforce;
+        self
     }
 }
 
@@ -317,6 +328,10 @@ impl Transformer for MaxTokenTransformer {
         "maxtoken"
     }
 
+    fn enforces_stream_max_tokens(&self) -> bool {
+        self.enforce_on_stream
+    }
+
     fn transform_request(&self, mut request: Value) -> Result<Value> {
         // Cap max_tokens if present
         if let Some(max_tokens) = request.get_mut("max_tokens") {
@@ -430,6 +445,14 @@ impl Transformer for ThinkTagTransformer {
         "thinktag"
     }
 
+    fn stream_think_tags(&self) -> &'static [(&'static str, &'static str)] {
+        &[
+            ("<think>", "</think>"),
+            ("<thinking>", "</thinking>"),
+            ("<reasoning>", "</reasoning>"),
+        ]
+    }
+
     fn transform_response(&self, mut response: Value) -> Result<Value> {
         lazy_static::lazy_static! {
             // Regex crate doesn't support backreferences, so use alternation
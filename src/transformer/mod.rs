@@ -11,6 +11,7 @@ use crate::config::TransformerEntry;
 use crate::transform::glm::GlmTransformer;
 use crate::transform::kimi::KimiTransformer;
 use crate::transform::minimax::MinimaxTransformer;
+use crate::transform::multimodal_reorder::MultimodalReorderTransformer;
 use crate::transform::openai_to_anthropic::OpenAiToAnthropicTransformer;
 use crate::transform::output_compress::OutputCompressTransformer;
 use crate::transform::toolcompress::ToolCompressTransformer;
@@ -45,6 +46,20 @@ pub trait Transformer: Send + Sync {
         Ok(response)
     }
 
+    /// Whether streamed responses must be cut off once their estimated output
+    /// exceeds the outbound request's `max_tokens`.
+    fn enforces_stream_max_tokens(&self) -> bool {
+        false
+    }
+
+    /// Start and end tags the provider wraps its reasoning in inside
+    /// `content`. Translated streams split the tagged reasoning out as it
+    /// arrives and send it as thinking blocks (or drop it when reasoning is
+    /// not emitted), since `transform_response` only sees whole responses.
+    fn stream_think_tags(&self) -> &'static [(&'static str, &'static str)] {
+        &[]
+    }
+
     /// Check if this transformer should be applied as a passthrough (no-op).
     ///
     /// Some transformers are identity passthroughs when specific conditions
@@ -207,10 +222,33 @@ impl TransformerChain {
         self.transformers.len()
     }
 
+    /// Names of the transformers in the chain, in request order.
+    pub fn names(&self) -> Vec<&str> {
+        self.transformers.iter().map(|t| t.name()).collect()
+    }
+
     /// Check if the chain is empty.
     pub fn is_empty(&self) -> bool {
         self.transformers.is_empty()
     }
+
+    /// Check if any transformer in the chain enforces `max_tokens` on streams.
+    pub fn enforces_stream_max_tokens(&self) -> bool {
+        self.transformers
+            .iter()
+            .any(|t| t.enforces_stream_max_tokens())
+    }
+
+    /// Think tag pairs split out of streams by any transformer in the chain.
+    pub fn stream_think_tags(&self) -> Vec<(&'static str, &'static str)> {
+        let mut tags: Vec<(&'static str, &'static str)> = Vec::new();
+        for &pair in self.transformers.iter().flat_map(|t| t.stream_think_tags()) {
+            if !tags.contains(&pair) {
+                tags.push(pair);
+            }
+        }
+        tags
+    }
 }
 
 impl Default for TransformerChain {
@@ -258,6 +296,10 @@ impl TransformerRegistry {
         registry.register("kimi", Arc::new(KimiTransformer));
         registry.register("toolcompress", Arc::new(ToolCompressTransformer::default()));
         registry.register("output_compress", Arc::new(OutputCompressTransformer));
+        registry.register(
+            "multimodal_reorder",
+            Arc::new(MultimodalReorderTransformer::default()),
+        );
 
         registry
     }
@@ -278,13 +320,24 @@ impl TransformerRegistry {
     pub fn create_with_options(&self, name: &str, options: &Value) -> Option<Arc<dyn Transformer>> {
         match name {
             "maxtoken" => {
-                if let Some(max_tokens) = options.get("max_tokens").and_then(|v| v.as_u64()) {
-                    return Some(Arc::new(MaxTokenTransformer::new(max_tokens as u32)));
-                }
                 // Default to 65536 if not specified
-                Some(Arc::new(MaxTokenTransformer::new(65536)))
+                let max_tokens = options
+                    .get("max_tokens")
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or(65536);
+                let enforce_on_stream = options
+                    .get("enforce_on_stream")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false);
+                Some(Arc::new(
+                    MaxTokenTransformer::new(max_tokens as u32)
+                        .with_stream_enforcement(enforce_on_stream),
+                ))
             }
             "toolcompress" => Some(Arc::new(ToolCompressTransformer::from_options(options))),
+            "multimodal_reorder" => Some(Arc::new(MultimodalReorderTransformer::from_options(
+                options,
+            ))),
             _ => self.get(name),
         }
     }
@@ -472,6 +525,25 @@ mod tests {
         assert_eq!(transformer.unwrap().name(), "maxtoken");
     }
 
+    #[test]
+    fn maxtoken_stream_enforcement_is_opt_in() {
+        let registry = TransformerRegistry::new();
+        let default = registry
+            .create_with_options("maxtoken", &serde_json::json!({"max_tokens": 100}))
+            .unwrap();
+        assert!(!default.enforces_stream_max_tokens());
+
+        let enforced = registry
+            .create_with_options(
+                "maxtoken",
+                &serde_json::json!({"max_tokens": 100, "enforce_on_stream": true}),
+            )
+            .unwrap();
+        assert!(enforced.enforces_stream_max_tokens());
+        let chain = TransformerChain::new().with_transformer(enforced);
+        assert!(chain.enforces_stream_max_tokens());
+    }
+
     #[test]
     fn registry_validates_entries() {
         let registry = TransformerRegistry::new();
@@ -0,0 +1,141 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//! Global cap on concurrent upstream requests across all tiers.
+//!
+//! Every dispatch, streaming or not, holds one permit until its response body
+//! is released. The cap is passed in on each acquire, so a config reload
+//! changes it without disturbing requests that already hold permits.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::Notify;
+
+use crate::metrics::set_upstream_inflight;
+
+/// Counts in-flight upstream requests and queues callers over the cap.
+#[derive(Debug, Default)]
+pub struct UpstreamLimiter {
+    inflight: AtomicUsize,
+    released: Notify,
+}
+
+/// One in-flight upstream request. Dropping it frees the slot.
+#[derive(Debug)]
+pub struct UpstreamPermit {
+    limiter: Arc<UpstreamLimiter>,
+}
+
+impl UpstreamLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Upstream requests currently holding a permit.
+    pub fn inflight(&self) -> usize {
+        self.inflight.load(Ordering::SeqCst)
+    }
+
+    /// Take a permit if fewer than `limit` are held (`0` = unlimited).
+    pub fn try_acquire(self: &Arc<Self>, limit: usize) -> Option<UpstreamPermit> {
+        let mut current = self.inflight.load(Ordering::SeqCst);
+        loop {
+            if limit > 0 && current >= limit {
+                return None;
+            }
+            match self.inflight.compare_exchange_weak(
+                current,
+                current + 1,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(_) => {
+                    set_upstream_inflight(current + 1);
+                    return Some(UpstreamPermit {
+                        limiter: self.clone(),
+                    });
+                }
+                Err(actual) => current = actual,
+            }
+        }
+    }
+
+    /// Take a permit, waiting up to `queue_timeout` for one to free up.
+    /// Returns `None` if the cap is still reached when the wait ends.
+    pub async fn acquire(
+        self: &Arc<Self>,
+        limit: usize,
+        queue_timeout: Duration,
+    ) -> Option<UpstreamPermit> {
+        let deadline = tokio::time::Instant::now() + queue_timeout;
+        loop {
+            // Register for wakeups before checking, so a release between the
+            // check and the wait is not missed.
+            let released = self.released.notified();
+            tokio::pin!(released);
+            released.as_mut().enable();
+
+            if let Some(permit) = self.try_acquire(limit) {
+                return Some(permit);
+            }
+            if tokio::time::timeout_at(deadline, released).await.is_err() {
+                return None;
+            }
+        }
+    }
+}
+
+impl Drop for UpstreamPermit {
+    fn drop(&mut self) {
+        let previous = self.limiter.inflight.fetch_sub(1, Ordering::SeqCst);
+        set_upstream_inflight(previous - 1);
+        self.limiter.released.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn try_acquire_respects_limit() {
+        let limiter = Arc::new(UpstreamLimiter::new());
+        let first = limiter.try_acquire(2).unwrap();
+        let _second = limiter.try_acquire(2).unwrap();
+        assert!(limiter.try_acquire(2).is_none());
+        assert_eq!(limiter.inflight(), 2);
+
+        drop(first);
+        assert_eq!(limiter.inflight(), 1);
+        assert!(limiter.try_acquire(2).is_some());
+        assert!(limiter.try_acquire(0).is_some(), "0 means unlimited");
+    }
+
+    #[tokio::test]
+    async fn queued_acquire_gets_released_permit() {
+        let limiter = Arc::new(UpstreamLimiter::new());
+        let held = limiter.try_acquire(1).unwrap();
+
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            drop(held);
+        });
+        let permit = limiter.acquire(1, Duration::from_secs(5)).await;
+        assert!(permit.is_some());
+        assert_eq!(limiter.inflight(), 1);
+    }
+
+    #[tokio::test]
+    async fn queued_acquire_times_out_when_cap_stays_full() {
+        let limiter = Arc::new(UpstreamLimiter::new());
+        let _held = limiter.try_acquire(1).unwrap();
+
+        let start = std::time::Instant::now();
+        assert!(limiter
+            .acquire(1, Duration::from_millis(100))
+            .await
+            .is_none());
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert_eq!(limiter.inflight(), 1);
+    }
+}
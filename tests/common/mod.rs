@@ -0,0 +1,43 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//! Helpers shared by the integration tests. Each test crate uses a subset,
+//! so unused ones are expected.
+#![allow(dead_code)]
+
+use ccr_rust::config::Config;
+
+/// Load a config from JSON text through the same path as `--config`.
+pub fn config_from_json(json: &str) -> Config {
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("config.json");
+    std::fs::write(&config_path, json).unwrap();
+    Config::from_file(config_path.to_str().unwrap()).unwrap()
+}
+
+/// Skip integration tests that require opening localhost sockets when the
+/// execution environment forbids binding ports.
+pub fn skip_if_localhost_bind_unavailable(test_name: &str) -> bool {
+    if std::net::TcpListener::bind("127.0.0.1:0").is_ok() {
+        return false;
+    }
+
+    eprintln!("Skipping {test_name}: cannot bind localhost sockets in this environment");
+    true
+}
+
+/// Sum of the `metric` counters carrying every `(label, value)` pair in
+/// `labels`.
+pub fn counter_value(metric: &str, labels: &[(&str, &str)]) -> f64 {
+    prometheus::gather()
+        .iter()
+        .filter(|family| family.name() == metric)
+        .flat_map(|family| family.get_metric())
+        .filter(|m| {
+            labels.iter().all(|(name, value)| {
+                m.get_label()
+                    .iter()
+                    .any(|l| l.name() == *name && l.value() == *value)
+            })
+        })
+        .map(|m| m.get_counter().value())
+        .sum()
+}
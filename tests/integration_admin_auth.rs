@@ -0,0 +1,234 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//! Integration tests for `ADMIN_TOKEN` on admin routes.
+//!
+//! When a token is configured, `/v1/reload`, `/v1/latencies/reset`, and
+//! `/metrics` require
+//! `Authorization: Bearer <token>`, while proxy endpoints stay open.
+
+use axum::body::{to_bytes, Body};
+use axum::http::{Request, StatusCode};
+use axum::routing::{get, post};
+use axum::Router;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+fn write_config(dir: &tempfile::TempDir, admin_token: Option<&str>) -> String {
+    let mut config = json!({
+        "Providers": [{
+            "name": "mock",
+            "api_base_url": "http://127.0.0.1:9",
+            "api_key": "test-key",
+            "models": ["test-model"]
+        }],
+        "Router": {"default": "mock,test-model"},
+        "API_TIMEOUT_MS": 5000
+    });
+    if let Some(token) = admin_token {
+        config["ADMIN_TOKEN"] = json!(token);
+    }
+    let path = dir.path().join("config.json");
+    std::fs::write(&path, serde_json::to_string_pretty(&config).unwrap()).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+fn build_app(config: ccr_rust::config::Config) -> Router {
+    build_app_with_tracker(
+        config,
+        std::sync::Arc::new(ccr_rust::routing::EwmaTracker::new()),
+    )
+}
+
+fn build_app_with_tracker(
+    config: ccr_rust::config::Config,
+    ewma_tracker: std::sync::Arc<ccr_rust::routing::EwmaTracker>,
+) -> Router {
+    let mut state = ccr_rust::router::AppState::for_tests(config);
+    state.ewma_tracker = ewma_tracker;
+
+    let admin_routes = Router::new()
+        .route("/v1/reload", post(ccr_rust::router::handle_reload))
+        .route(
+            "/v1/latencies/reset",
+            post(ccr_rust::router::reset_latencies),
+        )
+        .route("/metrics", get(ccr_rust::metrics::metrics_handler))
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            ccr_rust::router::require_admin_token,
+        ));
+
+    Router::new()
+        .route("/v1/models", get(ccr_rust::router::list_models))
+        .merge(admin_routes)
+        .with_state(state)
+}
+
+async fn send(app: &Router, method: &str, uri: &str, token: Option<&str>) -> (StatusCode, Value) {
+    let mut builder = Request::builder().method(method).uri(uri);
+    if let Some(token) = token {
+        builder = builder.header("authorization", format!("Bearer {token}"));
+    }
+    let resp = app
+        .clone()
+        .oneshot(builder.body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = resp.status();
+    let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn test_admin_routes_reject_missing_or_wrong_token() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_config(&dir, Some("s3cret"));
+    let app = build_app(ccr_rust::config::Config::from_file(&path).unwrap());
+
+    for (method, uri) in [
+        ("POST", "/v1/reload"),
+        ("POST", "/v1/latencies/reset"),
+        ("GET", "/metrics"),
+    ] {
+        let (status, body) = send(&app, method, uri, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED, "{uri} without token");
+        assert_eq!(body["error"], "Admin token required");
+
+        let (status, _) = send(&app, method, uri, Some("wrong")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED, "{uri} with wrong token");
+    }
+
+    // Proxy endpoints stay unauthenticated.
+    let (status, _) = send(&app, "GET", "/v1/models", None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_admin_routes_accept_configured_token() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_config(&dir, Some("s3cret"));
+    let app = build_app(ccr_rust::config::Config::from_file(&path).unwrap());
+
+    let (status, body) = send(&app, "POST", "/v1/reload", Some("s3cret")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "reloaded");
+
+    let (status, _) = send(&app, "GET", "/metrics", Some("s3cret")).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_admin_routes_open_without_token() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_config(&dir, None);
+    let app = build_app(ccr_rust::config::Config::from_file(&path).unwrap());
+
+    let (status, _) = send(&app, "GET", "/metrics", None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_reload_applies_new_admin_token() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_config(&dir, None);
+    let app = build_app(ccr_rust::config::Config::from_file(&path).unwrap());
+
+    write_config(&dir, Some("rotated"));
+    let (status, _) = send(&app, "POST", "/v1/reload", None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = send(&app, "GET", "/metrics", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = send(&app, "GET", "/metrics", Some("rotated")).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_metrics_negotiates_openmetrics_format() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_config(&dir, None);
+    let app = build_app(ccr_rust::config::Config::from_file(&path).unwrap());
+    ccr_rust::metrics::record_request_duration(
+        "openmetrics-tier",
+        ccr_rust::metrics::RequestMode::NonStream,
+        0.3,
+    );
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/metrics")
+                .header(
+                    "accept",
+                    "application/openmetrics-text;version=1.0.0,text/plain;version=0.0.4;q=0.5",
+                )
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers()["content-type"],
+        "application/openmetrics-text; version=1.0.0; charset=utf-8"
+    );
+    let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    let text = String::from_utf8(body.to_vec()).unwrap();
+    assert!(text.contains("# TYPE ccr_request_duration_seconds histogram"));
+    assert!(text.ends_with("# EOF\n"));
+
+    // Without the Accept header the Prometheus text format stays the default.
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .uri("/metrics")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.headers()["content-type"], "text/plain; version=0.0.4");
+}
+
+#[tokio::test]
+async fn test_latency_reset_clears_one_tier_or_all() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_config(&dir, Some("s3cret"));
+    let tracker = std::sync::Arc::new(ccr_rust::routing::EwmaTracker::new());
+    tracker.record_success("reset-a", 2.0);
+    tracker.record_success("reset-b", 0.5);
+    let app = build_app_with_tracker(
+        ccr_rust::config::Config::from_file(&path).unwrap(),
+        tracker.clone(),
+    );
+
+    let (status, body) = send(
+        &app,
+        "POST",
+        "/v1/latencies/reset?tier=reset-a",
+        Some("s3cret"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "reset");
+    assert_eq!(body["tiers"].as_array().unwrap().len(), 1);
+    assert_eq!(body["tiers"][0]["tier"], "reset-a");
+    assert_eq!(body["tiers"][0]["ewma_seconds"], 2.0);
+    assert_eq!(body["tiers"][0]["sample_count"], 1);
+    assert!(tracker.get_latency("reset-a").is_none());
+    assert!(tracker.get_latency("reset-b").is_some());
+
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/v1/latencies/reset?tier=reset-a",
+        Some("s3cret"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, body) = send(&app, "POST", "/v1/latencies/reset", Some("s3cret")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["tiers"][0]["tier"], "reset-b");
+    assert!(tracker.get_all_latencies().is_empty());
+}
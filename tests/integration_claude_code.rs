@@ -16,6 +16,9 @@ use wiremock::{Mock, MockServer, ResponseTemplate};
 
 use ccr_rust::frontend::{detect_frontend, FrontendType};
 
+mod common;
+use common::{config_from_json, skip_if_localhost_bind_unavailable};
+
 // ---------------------------------------------------------------------------
 // Test Helpers
 // ---------------------------------------------------------------------------
@@ -28,7 +31,7 @@ fn make_test_config(mock_url: &str) -> String {
                 "name": "mock",
                 "api_base_url": mock_url,
                 "api_key": "test-key",
-                "models": ["claude-sonnet-4-6"]
+                "models": ["claude-sonnet-4-6", "deepseek-reasoner", "quirky-reasoner"]
             }
         ],
         "Router": {
@@ -41,22 +44,7 @@ fn make_test_config(mock_url: &str) -> String {
 
 /// Build the Axum app with test state.
 fn build_app(config: ccr_rust::config::Config) -> Router {
-    let ewma_tracker = std::sync::Arc::new(ccr_rust::routing::EwmaTracker::new());
-    let transformer_registry =
-        std::sync::Arc::new(ccr_rust::transformer::TransformerRegistry::new());
-    let active_streams = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
-    let ratelimit_tracker = std::sync::Arc::new(ccr_rust::ratelimit::RateLimitTracker::new());
-    let state = ccr_rust::router::AppState {
-        config,
-        ewma_tracker,
-        gp_router: None,
-        transformer_registry,
-        active_streams,
-        max_streams: 0,
-        ratelimit_tracker,
-        shutdown_timeout: 30,
-        debug_capture: None,
-    };
+    let state = ccr_rust::router::AppState::for_tests(config);
     Router::new()
         .route("/v1/messages", post(ccr_rust::router::handle_messages))
         .with_state(state)
@@ -83,17 +71,6 @@ fn openai_request_body() -> serde_json::Value {
     })
 }
 
-/// Skip integration tests that require opening localhost sockets when the
-/// execution environment forbids binding ports.
-fn skip_if_localhost_bind_unavailable(test_name: &str) -> bool {
-    if std::net::TcpListener::bind("127.0.0.1:0").is_ok() {
-        return false;
-    }
-
-    eprintln!("Skipping {test_name}: cannot bind localhost sockets in this environment");
-    true
-}
-
 // ---------------------------------------------------------------------------
 // Frontend Detection Tests
 // ---------------------------------------------------------------------------
@@ -237,11 +214,7 @@ async fn test_claude_code_passthrough_basic_request() {
         .await;
 
     let config_json = make_test_config(&mock_server.uri());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let resp = app
@@ -310,11 +283,7 @@ async fn test_claude_code_passthrough_with_system_prompt() {
         .await;
 
     let config_json = make_test_config(&mock_server.uri());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let request_body = json!({
@@ -393,11 +362,7 @@ async fn test_claude_code_passthrough_streaming() {
         .await;
 
     let config_json = make_test_config(&mock_server.uri());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let request_body = json!({
@@ -470,11 +435,7 @@ async fn test_claude_code_thinking_preserved_in_response() {
         .await;
 
     let config_json = make_test_config(&mock_server.uri());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let request_body = json!({
@@ -568,11 +529,7 @@ async fn test_claude_code_thinking_in_streaming_response() {
         .await;
 
     let config_json = make_test_config(&mock_server.uri());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let request_body = json!({
@@ -643,11 +600,7 @@ async fn test_claude_code_thinking_empty_reasoning_skipped() {
         .await;
 
     let config_json = make_test_config(&mock_server.uri());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let request_body = json!({
@@ -714,11 +667,7 @@ async fn test_claude_code_thinking_only_no_content() {
         .await;
 
     let config_json = make_test_config(&mock_server.uri());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let request_body = json!({
@@ -751,10 +700,177 @@ async fn test_claude_code_thinking_only_no_content() {
     assert_eq!(response_json["content"].as_array().unwrap().len(), 0);
 }
 
+#[tokio::test]
+async fn test_claude_code_unsolicited_reasoning_dropped_when_disabled() {
+    if skip_if_localhost_bind_unavailable(
+        "test_claude_code_unsolicited_reasoning_dropped_when_disabled",
+    ) {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "resp_unsolicited",
+            "object": "chat.completion",
+            "created": 1234567890,
+            "model": "glm-4.6",
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "content": "Done.",
+                    "reasoning_content": "Unrequested musings"
+                },
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 5, "completion_tokens": 10}
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let config_json = json!({
+        "Providers": [{
+            "name": "mock",
+            "api_base_url": mock_server.uri(),
+            "api_key": "test-key",
+            "models": ["glm-4.6", "deepseek-reasoner", "qwen3-max"],
+            "model_metadata": {"qwen3-max": {"supports_reasoning": true}}
+        }],
+        "Router": {"default": "mock,glm-4.6"},
+        "API_TIMEOUT_MS": 5000,
+        "EMIT_UNSOLICITED_REASONING": false
+    });
+    let config = config_from_json(&config_json.to_string());
+    let app = build_app(config);
+
+    let mut reasoning_by_model = Vec::new();
+    for model in ["glm-4.6", "deepseek-reasoner", "qwen3-max"] {
+        let request_body = json!({
+            "model": format!("mock,{model}"),
+            "messages": [{"role": "user", "content": "Hi"}],
+            "max_tokens": 100
+        });
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/messages")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_vec(&request_body).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body_bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let response_json: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap();
+        assert_eq!(response_json["content"][0]["text"], "Done.");
+        reasoning_by_model.push(response_json.get("reasoning_content").cloned());
+    }
+
+    // Only the model nobody flagged as reasoning loses its reasoning.
+    assert_eq!(
+        reasoning_by_model,
+        vec![
+            None,
+            Some(json!("Unrequested musings")),
+            Some(json!("Unrequested musings")),
+        ]
+    );
+}
+
 // ---------------------------------------------------------------------------
 // End-to-end Flow Tests
 // ---------------------------------------------------------------------------
 
+#[tokio::test]
+async fn test_claude_code_streaming_reads_aliased_delta_fields() {
+    if skip_if_localhost_bind_unavailable("test_claude_code_streaming_reads_aliased_delta_fields") {
+        return;
+    }
+
+    let mock_server = MockServer::start().await;
+    let reasoning = "step ".repeat(100);
+    let sse_body = format!(
+        "data: {}\n\ndata: {}\n\ndata: [DONE]\n\n",
+        json!({
+            "id": "chunk_1",
+            "object": "chat.completion.chunk",
+            "created": 1234567890,
+            "model": "quirky-reasoner",
+            "choices": [{"index": 0, "delta": {"thinking": reasoning}, "finish_reason": null}]
+        }),
+        json!({
+            "id": "chunk_2",
+            "object": "chat.completion.chunk",
+            "created": 1234567890,
+            "model": "quirky-reasoner",
+            "choices": [{"index": 0, "delta": {"text": "Aliased answer."}, "finish_reason": "stop"}]
+        })
+    );
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(sse_body)
+                .insert_header("content-type", "text/event-stream"),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let mut config: serde_json::Value =
+        serde_json::from_str(&make_test_config(&mock_server.uri())).unwrap();
+    config["Providers"][0]["reasoning_field_aliases"] = json!(["thinking"]);
+    config["Providers"][0]["content_field_aliases"] = json!(["text"]);
+    let config = config_from_json(&config.to_string());
+
+    let resp = build_app(config)
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::to_vec(&json!({
+                        "model": "mock,quirky-reasoner",
+                        "messages": [{"role": "user", "content": "Solve this step by step"}],
+                        "max_tokens": 1000,
+                        "stream": true
+                    }))
+                    .unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body_bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body_text = String::from_utf8(body_bytes.to_vec()).unwrap();
+
+    assert!(body_text.contains("Aliased answer."));
+    // No usage was reported, so the output estimate only covers the
+    // reasoning if the `thinking` alias was read.
+    let output_tokens = body_text
+        .lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .filter_map(|data| serde_json::from_str::<serde_json::Value>(data).ok())
+        .find(|event| event["type"] == "message_delta")
+        .and_then(|event| event["usage"]["output_tokens"].as_u64())
+        .unwrap();
+    assert!(
+        output_tokens >= (reasoning.len() / 4) as u64,
+        "aliased reasoning missing from output estimate: {output_tokens}"
+    );
+}
+
 #[tokio::test]
 async fn test_claude_code_end_to_end_with_tier_retries() {
     if skip_if_localhost_bind_unavailable("test_claude_code_end_to_end_with_tier_retries") {
@@ -818,11 +934,7 @@ async fn test_claude_code_end_to_end_with_tier_retries() {
         "API_TIMEOUT_MS": 5000
     });
 
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, serde_json::to_string_pretty(&config).unwrap()).unwrap();
-
-    let cfg = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let cfg = config_from_json(&config.to_string());
     let app = build_app(cfg);
 
     let request_body = json!({
@@ -919,15 +1031,7 @@ async fn test_streamed_usage_estimate_fallback_records_no_fake_drift() {
         },
         "API_TIMEOUT_MS": 5000
     });
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(
-        &config_path,
-        serde_json::to_string_pretty(&config_value).unwrap(),
-    )
-    .unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_value.to_string());
     let app = build_app(config);
 
     let request_body = json!({
@@ -0,0 +1,304 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//! Integration tests for `CLIENT_KEYS` on the proxy endpoints.
+//!
+//! With keys configured, Anthropic-style (`x-api-key`) and OpenAI-style
+//! (`Authorization: Bearer`) clients must present one of them, and rejections
+//! use each frontend's error format. Without keys, auth is disabled.
+//! `INGRESS_RATE_LIMIT` throttles each configured key separately, and clients
+//! without a configured key by address.
+
+use axum::body::{to_bytes, Body};
+use axum::http::{Request, StatusCode};
+use axum::routing::{get, post};
+use axum::Router;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+mod common;
+use common::{config_from_json, counter_value, skip_if_localhost_bind_unavailable};
+
+fn make_test_config(mock_url: &str, client_keys: &[&str]) -> Value {
+    json!({
+        "Providers": [{
+            "name": "mock",
+            "api_base_url": mock_url,
+            "api_key": "upstream-key",
+            "models": ["test-model"]
+        }],
+        "Router": {"default": "mock,test-model"},
+        "CLIENT_KEYS": client_keys,
+        "API_TIMEOUT_MS": 5000
+    })
+}
+
+fn build_app(config: ccr_rust::config::Config) -> Router {
+    let state = ccr_rust::router::AppState::for_tests(config);
+
+    let proxy_routes = Router::new()
+        .route("/v1/messages", post(ccr_rust::router::handle_messages))
+        .route(
+            "/v1/chat/completions",
+            post(ccr_rust::router::handle_chat_completions),
+        )
+        .route("/v1/responses", post(ccr_rust::router::handle_responses))
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            ccr_rust::router::throttle_ingress,
+        ))
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            ccr_rust::router::require_client_key,
+        ));
+
+    Router::new()
+        .route("/v1/models", get(ccr_rust::router::list_models))
+        .merge(proxy_routes)
+        .with_state(state)
+}
+
+async fn mock_provider() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-auth",
+            "object": "chat.completion",
+            "created": 1234567890,
+            "model": "test-model",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "ok"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 3, "completion_tokens": 1}
+        })))
+        .mount(&server)
+        .await;
+    server
+}
+
+fn app_with_keys(mock_url: &str, keys: &[&str]) -> Router {
+    app_with_config(make_test_config(mock_url, keys))
+}
+
+fn app_with_config(config: Value) -> Router {
+    build_app(config_from_json(&config.to_string()))
+}
+
+async fn post_json(
+    app: &Router,
+    uri: &str,
+    auth: Option<(&str, &str)>,
+    body: Value,
+) -> (StatusCode, Value) {
+    let mut builder = Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header("content-type", "application/json");
+    if let Some((name, value)) = auth {
+        builder = builder.header(name, value);
+    }
+    let resp = app
+        .clone()
+        .oneshot(
+            builder
+                .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = resp.status();
+    let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+fn anthropic_body() -> Value {
+    json!({
+        "model": "claude-sonnet-4-6",
+        "messages": [{"role": "user", "content": "hello"}],
+        "max_tokens": 16
+    })
+}
+
+fn openai_body() -> Value {
+    json!({
+        "model": "gpt-4o",
+        "messages": [{"role": "user", "content": "hello"}]
+    })
+}
+
+#[tokio::test]
+async fn test_client_keys_reject_missing_or_invalid_keys_per_frontend() {
+    if skip_if_localhost_bind_unavailable(
+        "test_client_keys_reject_missing_or_invalid_keys_per_frontend",
+    ) {
+        return;
+    }
+    let server = mock_provider().await;
+    let app = app_with_keys(&server.uri(), &["team-key"]);
+
+    let (status, body) = post_json(&app, "/v1/messages", None, anthropic_body()).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["type"], "error");
+    assert_eq!(body["error"]["type"], "authentication_error");
+
+    let (status, _) = post_json(
+        &app,
+        "/v1/messages",
+        Some(("x-api-key", "wrong")),
+        anthropic_body(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    for uri in ["/v1/chat/completions", "/v1/responses"] {
+        let (status, body) = post_json(
+            &app,
+            uri,
+            Some(("authorization", "Bearer wrong")),
+            openai_body(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED, "{uri}");
+        assert_eq!(body["error"]["code"], "invalid_api_key", "{uri}");
+        assert_eq!(body["error"]["type"], "invalid_request_error", "{uri}");
+    }
+
+    // Non-proxy endpoints stay open.
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/v1/models")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_client_keys_accept_x_api_key_and_bearer() {
+    if skip_if_localhost_bind_unavailable("test_client_keys_accept_x_api_key_and_bearer") {
+        return;
+    }
+    let server = mock_provider().await;
+    let app = app_with_keys(&server.uri(), &["anthropic-key", "openai-key"]);
+
+    let (status, _) = post_json(
+        &app,
+        "/v1/messages",
+        Some(("x-api-key", "anthropic-key")),
+        anthropic_body(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = post_json(
+        &app,
+        "/v1/chat/completions",
+        Some(("authorization", "Bearer openai-key")),
+        openai_body(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_no_client_keys_disables_auth() {
+    if skip_if_localhost_bind_unavailable("test_no_client_keys_disables_auth") {
+        return;
+    }
+    let server = mock_provider().await;
+    let app = app_with_keys(&server.uri(), &[]);
+
+    let (status, _) = post_json(&app, "/v1/messages", None, anthropic_body()).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_ingress_rate_limit_throttles_each_key() {
+    if skip_if_localhost_bind_unavailable("test_ingress_rate_limit_throttles_each_key") {
+        return;
+    }
+    let server = mock_provider().await;
+    let mut config = make_test_config(&server.uri(), &["first-key", "second-key"]);
+    config["INGRESS_RATE_LIMIT"] = json!({"requests_per_minute": 1, "burst": 2});
+    let app = app_with_config(config);
+
+    let first = Some(("x-api-key", "first-key"));
+    for _ in 0..2 {
+        let (status, _) = post_json(&app, "/v1/messages", first, anthropic_body()).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json")
+                .header("x-api-key", "first-key")
+                .body(Body::from(serde_json::to_vec(&anthropic_body()).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = resp.headers()["retry-after"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!((1..=60).contains(&retry_after), "{retry_after}");
+    let body: Value =
+        serde_json::from_slice(&to_bytes(resp.into_body(), usize::MAX).await.unwrap()).unwrap();
+    assert_eq!(body["error"]["type"], "rate_limit_error");
+
+    // Another key has its own bucket; OpenAI clients get OpenAI's shape.
+    let second = Some(("authorization", "Bearer second-key"));
+    for _ in 0..2 {
+        let (status, _) = post_json(&app, "/v1/chat/completions", second, openai_body()).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let (status, body) = post_json(&app, "/v1/chat/completions", second, openai_body()).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(body["error"]["code"], "rate_limit_exceeded");
+
+    let throttled = counter_value("ccr_ingress_throttled_total", &[]);
+    assert!(throttled >= 2.0, "{throttled}");
+}
+
+#[tokio::test]
+async fn test_ingress_rate_limit_ignores_unchecked_keys() {
+    if skip_if_localhost_bind_unavailable("test_ingress_rate_limit_ignores_unchecked_keys") {
+        return;
+    }
+    let server = mock_provider().await;
+    let mut config = make_test_config(&server.uri(), &[]);
+    config["INGRESS_RATE_LIMIT"] = json!({"requests_per_minute": 1, "burst": 2});
+    let app = app_with_config(config);
+
+    // Without CLIENT_KEYS, rotating made-up keys still draws from one bucket.
+    for key in ["made-up-1", "made-up-2"] {
+        let (status, _) = post_json(
+            &app,
+            "/v1/messages",
+            Some(("x-api-key", key)),
+            anthropic_body(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let (status, _) = post_json(
+        &app,
+        "/v1/messages",
+        Some(("x-api-key", "made-up-3")),
+        anthropic_body(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+}
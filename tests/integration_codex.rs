@@ -15,6 +15,9 @@ use tower::ServiceExt;
 use wiremock::matchers::{method, path};
 use wiremock::{Mock, MockServer, ResponseTemplate};
 
+mod common;
+use common::{config_from_json, counter_value, skip_if_localhost_bind_unavailable};
+
 // ============================================================================
 // Test Fixtures and Helpers
 // ============================================================================
@@ -54,22 +57,7 @@ fn make_test_config(mock_url: &str) -> String {
 
 /// Build the Axum app with all necessary state
 fn build_app(config: ccr_rust::config::Config) -> Router {
-    let ewma_tracker = std::sync::Arc::new(ccr_rust::routing::EwmaTracker::new());
-    let transformer_registry =
-        std::sync::Arc::new(ccr_rust::transformer::TransformerRegistry::new());
-    let active_streams = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
-    let ratelimit_tracker = std::sync::Arc::new(ccr_rust::ratelimit::RateLimitTracker::new());
-    let state = ccr_rust::router::AppState {
-        config,
-        ewma_tracker,
-        gp_router: None,
-        transformer_registry,
-        active_streams,
-        max_streams: 0,
-        ratelimit_tracker,
-        shutdown_timeout: 30,
-        debug_capture: None,
-    };
+    let state = ccr_rust::router::AppState::for_tests(config);
 
     // Register both Anthropic and OpenAI endpoints
     Router::new()
@@ -86,24 +74,13 @@ fn build_app(config: ccr_rust::config::Config) -> Router {
         .with_state(state)
 }
 
-/// Skip integration tests that require opening localhost sockets when the
-/// execution environment forbids binding ports.
-fn skip_if_localhost_bind_unavailable() -> bool {
-    if std::net::TcpListener::bind("127.0.0.1:0").is_ok() {
-        return false;
-    }
-
-    eprintln!("Skipping test: cannot bind localhost sockets in this environment");
-    true
-}
-
 // ============================================================================
 // Test: Frontend Detection
 // ============================================================================
 
 #[tokio::test]
 async fn test_codex_request_detection_by_user_agent() {
-    if skip_if_localhost_bind_unavailable() {
+    if skip_if_localhost_bind_unavailable("test_codex_request_detection_by_user_agent") {
         return;
     }
     let mock_server = MockServer::start().await;
@@ -134,11 +111,7 @@ async fn test_codex_request_detection_by_user_agent() {
         .await;
 
     let config_json = make_test_config(&mock_server.uri());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     // Send request with Codex User-Agent
@@ -175,17 +148,13 @@ async fn test_codex_request_detection_by_user_agent() {
 
 #[tokio::test]
 async fn test_models_endpoint_includes_route_ids_for_codex() {
-    if skip_if_localhost_bind_unavailable() {
+    if skip_if_localhost_bind_unavailable("test_models_endpoint_includes_route_ids_for_codex") {
         return;
     }
     let mock_server = MockServer::start().await;
 
     let config_json = make_test_config(&mock_server.uri());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let resp = app
@@ -218,9 +187,82 @@ async fn test_models_endpoint_includes_route_ids_for_codex() {
     assert!(ids.contains(&"test-model".to_string()));
 }
 
+#[tokio::test]
+async fn test_models_endpoint_reports_configured_model_metadata() {
+    let config = json!({
+        "Providers": [{
+            "name": "mock",
+            "api_base_url": "http://127.0.0.1:1",
+            "api_key": "test-key",
+            "models": ["big-model", "bare-model"],
+            "model_metadata": {
+                "big-model": {
+                    "context_length": 200000,
+                    "max_output_tokens": 32000,
+                    "supports_tools": true,
+                    "supports_vision": false
+                }
+            }
+        }],
+        "Router": {"default": "mock,big-model"}
+    });
+    let config = config_from_json(&config.to_string());
+
+    let resp = build_app(config)
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/v1/models")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body_bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let response_json: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap();
+
+    let find = |list: &str, id: &str| {
+        response_json[list]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|item| item["id"] == id)
+            .cloned()
+            .unwrap_or_else(|| panic!("{id} missing from {list}"))
+    };
+
+    let big = find("data", "mock,big-model");
+    assert_eq!(big["context_length"], 200000);
+    assert_eq!(big["max_output_tokens"], 32000);
+    assert_eq!(big["supports_tools"], true);
+    assert_eq!(big["supports_vision"], false);
+    assert_eq!(
+        find("models", "mock,big-model"),
+        json!({
+            "id": "mock,big-model",
+            "name": "big-model",
+            "provider": "mock",
+            "limit": {"context": 200000, "output": 32000},
+            "tool_call": true,
+            "attachment": false
+        })
+    );
+
+    let bare = find("data", "bare-model");
+    assert!(bare.get("context_length").is_none());
+    assert!(bare.get("supports_tools").is_none());
+    assert_eq!(
+        find("models", "bare-model"),
+        json!({"id": "bare-model", "name": "bare-model", "provider": "mock"})
+    );
+}
+
 #[tokio::test]
 async fn test_frontend_metrics_records_codex_requests() {
-    if skip_if_localhost_bind_unavailable() {
+    if skip_if_localhost_bind_unavailable("test_frontend_metrics_records_codex_requests") {
         return;
     }
     let mock_server = MockServer::start().await;
@@ -251,11 +293,7 @@ async fn test_frontend_metrics_records_codex_requests() {
         .await;
 
     let config_json = make_test_config(&mock_server.uri());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let _resp = app
@@ -300,7 +338,7 @@ async fn test_frontend_metrics_records_codex_requests() {
 
 #[tokio::test]
 async fn test_codex_request_detection_by_format() {
-    if skip_if_localhost_bind_unavailable() {
+    if skip_if_localhost_bind_unavailable("test_codex_request_detection_by_format") {
         return;
     }
     let mock_server = MockServer::start().await;
@@ -331,11 +369,7 @@ async fn test_codex_request_detection_by_format() {
         .await;
 
     let config_json = make_test_config(&mock_server.uri());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     // Send request with OpenAI format but no specific User-Agent
@@ -374,7 +408,7 @@ async fn test_codex_request_detection_by_format() {
 
 #[tokio::test]
 async fn test_codex_request_transformation_openai_to_internal() {
-    if skip_if_localhost_bind_unavailable() {
+    if skip_if_localhost_bind_unavailable("test_codex_request_transformation_openai_to_internal") {
         return;
     }
     let mock_server = MockServer::start().await;
@@ -414,11 +448,7 @@ async fn test_codex_request_transformation_openai_to_internal() {
         .await;
 
     let config_json = make_test_config(&mock_server.uri());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     // Send OpenAI format request
@@ -466,7 +496,7 @@ async fn test_codex_request_transformation_openai_to_internal() {
 
 #[tokio::test]
 async fn test_codex_request_transformation_with_tools() {
-    if skip_if_localhost_bind_unavailable() {
+    if skip_if_localhost_bind_unavailable("test_codex_request_transformation_with_tools") {
         return;
     }
     let mock_server = MockServer::start().await;
@@ -505,11 +535,7 @@ async fn test_codex_request_transformation_with_tools() {
         .await;
 
     let config_json = make_test_config(&mock_server.uri());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     // Send OpenAI request with tools
@@ -572,7 +598,7 @@ async fn test_codex_request_transformation_with_tools() {
 
 #[tokio::test]
 async fn test_codex_response_transformation_simple() {
-    if skip_if_localhost_bind_unavailable() {
+    if skip_if_localhost_bind_unavailable("test_codex_response_transformation_simple") {
         return;
     }
     let mock_server = MockServer::start().await;
@@ -603,11 +629,7 @@ async fn test_codex_response_transformation_simple() {
         .await;
 
     let config_json = make_test_config(&mock_server.uri());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let resp = app
@@ -659,9 +681,75 @@ async fn test_codex_response_transformation_simple() {
     assert!(usage.get("completion_tokens").is_some());
 }
 
+#[tokio::test]
+async fn test_codex_response_normalizes_nonstandard_role() {
+    if skip_if_localhost_bind_unavailable("test_codex_response_normalizes_nonstandard_role") {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-model-role",
+            "object": "chat.completion",
+            "created": 1234567890,
+            "model": "test-model",
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "model",
+                    "content": "Hello from a model role."
+                },
+                "finish_reason": "stop"
+            }],
+            "usage": {
+                "prompt_tokens": 10,
+                "completion_tokens": 5
+            }
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let config_json = make_test_config(&mock_server.uri());
+    let config = config_from_json(&config_json);
+    let app = build_app(config);
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .header("user-agent", "codex-cli/1.0.0")
+                .body(Body::from(
+                    serde_json::to_vec(&codex_request_body()).unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    let tier = resp.headers()["x-ccr-tier"].to_str().unwrap().to_string();
+    let body_bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let response_json: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap();
+
+    let message = &response_json["choices"][0]["message"];
+    assert_eq!(message["role"], "assistant");
+    assert_eq!(message["content"], "Hello from a model role.");
+    assert_eq!(
+        counter_value("ccr_nonstandard_role_total", &[("tier", tier.as_str())]),
+        1.0
+    );
+}
+
 #[tokio::test]
 async fn test_codex_response_transformation_with_tool_calls() {
-    if skip_if_localhost_bind_unavailable() {
+    if skip_if_localhost_bind_unavailable("test_codex_response_transformation_with_tool_calls") {
         return;
     }
     let mock_server = MockServer::start().await;
@@ -702,11 +790,7 @@ async fn test_codex_response_transformation_with_tool_calls() {
         .await;
 
     let config_json = make_test_config(&mock_server.uri());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let resp = app
@@ -749,7 +833,7 @@ async fn test_codex_response_transformation_with_tool_calls() {
 
 #[tokio::test]
 async fn test_codex_response_transformation_max_tokens() {
-    if skip_if_localhost_bind_unavailable() {
+    if skip_if_localhost_bind_unavailable("test_codex_response_transformation_max_tokens") {
         return;
     }
     let mock_server = MockServer::start().await;
@@ -780,11 +864,7 @@ async fn test_codex_response_transformation_max_tokens() {
         .await;
 
     let config_json = make_test_config(&mock_server.uri());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let resp = app
@@ -819,7 +899,7 @@ async fn test_codex_response_transformation_max_tokens() {
 
 #[tokio::test]
 async fn test_codex_end_to_end_full_flow() {
-    if skip_if_localhost_bind_unavailable() {
+    if skip_if_localhost_bind_unavailable("test_codex_end_to_end_full_flow") {
         return;
     }
     let mock_server = MockServer::start().await;
@@ -851,11 +931,7 @@ async fn test_codex_end_to_end_full_flow() {
         .await;
 
     let config_json = make_test_config(&mock_server.uri());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     // Full OpenAI-style request
@@ -923,7 +999,7 @@ async fn test_codex_end_to_end_full_flow() {
 
 #[tokio::test]
 async fn test_codex_backend_error_propagation() {
-    if skip_if_localhost_bind_unavailable() {
+    if skip_if_localhost_bind_unavailable("test_codex_backend_error_propagation") {
         return;
     }
     let mock_server = MockServer::start().await;
@@ -943,11 +1019,7 @@ async fn test_codex_backend_error_propagation() {
         .await;
 
     let config_json = make_test_config(&mock_server.uri());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let resp = app
@@ -971,7 +1043,8 @@ async fn test_codex_backend_error_propagation() {
 
 #[tokio::test]
 async fn test_codex_rate_limited_error_normalization_and_headers() {
-    if skip_if_localhost_bind_unavailable() {
+    if skip_if_localhost_bind_unavailable("test_codex_rate_limited_error_normalization_and_headers")
+    {
         return;
     }
     let mock_server = MockServer::start().await;
@@ -992,11 +1065,7 @@ async fn test_codex_rate_limited_error_normalization_and_headers() {
         .await;
 
     let config_json = make_test_config(&mock_server.uri());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let resp = app
@@ -1033,7 +1102,7 @@ async fn test_codex_rate_limited_error_normalization_and_headers() {
 
 #[tokio::test]
 async fn test_codex_multimodal_content() {
-    if skip_if_localhost_bind_unavailable() {
+    if skip_if_localhost_bind_unavailable("test_codex_multimodal_content") {
         return;
     }
     let mock_server = MockServer::start().await;
@@ -1063,11 +1132,7 @@ async fn test_codex_multimodal_content() {
         .await;
 
     let config_json = make_test_config(&mock_server.uri());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     // OpenAI multimodal request
@@ -1117,7 +1182,9 @@ async fn test_codex_multimodal_content() {
 
 #[tokio::test]
 async fn test_codex_response_transformation_with_reasoning_content() {
-    if skip_if_localhost_bind_unavailable() {
+    if skip_if_localhost_bind_unavailable(
+        "test_codex_response_transformation_with_reasoning_content",
+    ) {
         return;
     }
     let mock_server = MockServer::start().await;
@@ -1153,11 +1220,7 @@ async fn test_codex_response_transformation_with_reasoning_content() {
         .await;
 
     let config_json = make_test_config(&mock_server.uri());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let resp = app
@@ -1192,7 +1255,7 @@ async fn test_codex_response_transformation_with_reasoning_content() {
 
 #[tokio::test]
 async fn test_codex_empty_messages() {
-    if skip_if_localhost_bind_unavailable() {
+    if skip_if_localhost_bind_unavailable("test_codex_empty_messages") {
         return;
     }
     let mock_server = MockServer::start().await;
@@ -1222,11 +1285,7 @@ async fn test_codex_empty_messages() {
         .await;
 
     let config_json = make_test_config(&mock_server.uri());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     // Request with just a system message
@@ -13,6 +13,9 @@ use tokio::time::Duration;
 use tokio_stream::wrappers::ReceiverStream;
 use tower::ServiceExt;
 
+mod common;
+use common::{config_from_json, skip_if_localhost_bind_unavailable};
+
 fn make_openai_test_config(base_url: &str) -> String {
     let config = json!({
         "Providers": [
@@ -34,22 +37,7 @@ fn make_openai_test_config(base_url: &str) -> String {
 }
 
 fn build_app(config: ccr_rust::config::Config) -> Router {
-    let ewma_tracker = std::sync::Arc::new(ccr_rust::routing::EwmaTracker::new());
-    let transformer_registry =
-        std::sync::Arc::new(ccr_rust::transformer::TransformerRegistry::new());
-    let active_streams = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
-    let ratelimit_tracker = std::sync::Arc::new(ccr_rust::ratelimit::RateLimitTracker::new());
-    let state = ccr_rust::router::AppState {
-        config,
-        ewma_tracker,
-        gp_router: None,
-        transformer_registry,
-        active_streams,
-        max_streams: 0,
-        ratelimit_tracker,
-        shutdown_timeout: 30,
-        debug_capture: None,
-    };
+    let state = ccr_rust::router::AppState::for_tests(config);
 
     Router::new()
         .route("/v1/messages", post(ccr_rust::router::handle_messages))
@@ -97,17 +85,6 @@ fn parse_sse_data_frames(payload: &str) -> Vec<String> {
         .collect()
 }
 
-/// Skip integration tests that require opening localhost sockets when the
-/// execution environment forbids binding ports.
-fn skip_if_localhost_bind_unavailable() -> bool {
-    if std::net::TcpListener::bind("127.0.0.1:0").is_ok() {
-        return false;
-    }
-
-    eprintln!("Skipping test: cannot bind localhost sockets in this environment");
-    true
-}
-
 async fn start_openai_stream_server(chunks: Vec<(Bytes, u64)>) -> String {
     let chunks = std::sync::Arc::new(chunks);
     let app = Router::new().route(
@@ -168,7 +145,9 @@ async fn make_codex_stream_request(app: &Router) -> Response {
 #[tokio::test]
 async fn test_codex_stream_reassembles_fragmented_openai_sse_frames() {
     // Skip if we cannot bind localhost sockets in this environment
-    if skip_if_localhost_bind_unavailable() {
+    if skip_if_localhost_bind_unavailable(
+        "test_codex_stream_reassembles_fragmented_openai_sse_frames",
+    ) {
         return;
     }
     let role_frame = format!(
@@ -238,11 +217,7 @@ async fn test_codex_stream_reassembles_fragmented_openai_sse_frames() {
 
     let upstream_url = start_openai_stream_server(stream_chunks).await;
     let config_json = make_openai_test_config(&upstream_url);
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let resp = make_codex_stream_request(&app).await;
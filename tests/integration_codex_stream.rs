@@ -14,16 +14,8 @@ use tokio::time::{timeout, Duration};
 use tokio_stream::wrappers::ReceiverStream;
 use tower::ServiceExt;
 
-/// Skip integration tests that require opening localhost sockets when the
-/// execution environment forbids binding ports.
-fn skip_if_localhost_bind_unavailable() -> bool {
-    if std::net::TcpListener::bind("127.0.0.1:0").is_ok() {
-        return false;
-    }
-
-    eprintln!("Skipping test: cannot bind localhost sockets in this environment");
-    true
-}
+mod common;
+use common::{config_from_json, skip_if_localhost_bind_unavailable};
 
 fn make_anthropic_test_config(base_url: &str) -> String {
     let config = json!({
@@ -46,22 +38,7 @@ fn make_anthropic_test_config(base_url: &str) -> String {
 }
 
 fn build_app(config: ccr_rust::config::Config) -> Router {
-    let ewma_tracker = std::sync::Arc::new(ccr_rust::routing::EwmaTracker::new());
-    let transformer_registry =
-        std::sync::Arc::new(ccr_rust::transformer::TransformerRegistry::new());
-    let active_streams = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
-    let ratelimit_tracker = std::sync::Arc::new(ccr_rust::ratelimit::RateLimitTracker::new());
-    let state = ccr_rust::router::AppState {
-        config,
-        ewma_tracker,
-        gp_router: None,
-        transformer_registry,
-        active_streams,
-        max_streams: 0,
-        ratelimit_tracker,
-        shutdown_timeout: 30,
-        debug_capture: None,
-    };
+    let state = ccr_rust::router::AppState::for_tests(config);
 
     Router::new()
         .route("/v1/messages", post(ccr_rust::router::handle_messages))
@@ -173,7 +150,9 @@ async fn make_codex_stream_request(app: &Router) -> Response {
 #[tokio::test]
 async fn test_anthropic_stream_chunk_boundary_inside_frame_is_parsed() {
     // Skip if we cannot bind localhost sockets in this environment
-    if skip_if_localhost_bind_unavailable() {
+    if skip_if_localhost_bind_unavailable(
+        "test_anthropic_stream_chunk_boundary_inside_frame_is_parsed",
+    ) {
         return;
     }
     let message_start = sse_event(
@@ -230,11 +209,7 @@ async fn test_anthropic_stream_chunk_boundary_inside_frame_is_parsed() {
 
     let upstream_url = start_anthropic_stream_server(stream_chunks).await;
     let config_json = make_anthropic_test_config(&upstream_url);
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let resp = make_codex_stream_request(&app).await;
@@ -268,7 +243,9 @@ async fn test_anthropic_stream_chunk_boundary_inside_frame_is_parsed() {
 #[tokio::test]
 async fn test_anthropic_stream_emits_first_assistant_delta_before_completion() {
     // Skip if we cannot bind localhost sockets in this environment
-    if skip_if_localhost_bind_unavailable() {
+    if skip_if_localhost_bind_unavailable(
+        "test_anthropic_stream_emits_first_assistant_delta_before_completion",
+    ) {
         return;
     }
     let stream_chunks = vec![
@@ -307,11 +284,7 @@ async fn test_anthropic_stream_emits_first_assistant_delta_before_completion() {
 
     let upstream_url = start_anthropic_stream_server(stream_chunks).await;
     let config_json = make_anthropic_test_config(&upstream_url);
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let resp = timeout(Duration::from_millis(700), make_codex_stream_request(&app))
@@ -349,7 +322,9 @@ async fn test_anthropic_stream_emits_first_assistant_delta_before_completion() {
 #[tokio::test]
 async fn test_anthropic_stream_tool_deltas_and_stop_events_are_well_formed() {
     // Skip if we cannot bind localhost sockets in this environment
-    if skip_if_localhost_bind_unavailable() {
+    if skip_if_localhost_bind_unavailable(
+        "test_anthropic_stream_tool_deltas_and_stop_events_are_well_formed",
+    ) {
         return;
     }
     let stream_chunks = vec![
@@ -417,11 +392,7 @@ async fn test_anthropic_stream_tool_deltas_and_stop_events_are_well_formed() {
 
     let upstream_url = start_anthropic_stream_server(stream_chunks).await;
     let config_json = make_anthropic_test_config(&upstream_url);
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let resp = make_codex_stream_request(&app).await;
@@ -472,7 +443,9 @@ async fn test_anthropic_stream_tool_deltas_and_stop_events_are_well_formed() {
 #[tokio::test]
 async fn test_anthropic_stream_emits_exactly_one_done_marker_at_end() {
     // Skip if we cannot bind localhost sockets in this environment
-    if skip_if_localhost_bind_unavailable() {
+    if skip_if_localhost_bind_unavailable(
+        "test_anthropic_stream_emits_exactly_one_done_marker_at_end",
+    ) {
         return;
     }
     let stream_chunks = vec![
@@ -512,11 +485,7 @@ async fn test_anthropic_stream_emits_exactly_one_done_marker_at_end() {
 
     let upstream_url = start_anthropic_stream_server(stream_chunks).await;
     let config_json = make_anthropic_test_config(&upstream_url);
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let resp = make_codex_stream_request(&app).await;
@@ -537,7 +506,7 @@ async fn test_anthropic_stream_emits_exactly_one_done_marker_at_end() {
 #[tokio::test]
 async fn test_anthropic_stream_utf8_split_across_chunks() {
     // Skip if we cannot bind localhost sockets in this environment
-    if skip_if_localhost_bind_unavailable() {
+    if skip_if_localhost_bind_unavailable("test_anthropic_stream_utf8_split_across_chunks") {
         return;
     }
     // "hello" in Japanese is "こんにちは" (Kon'nichiwa)
@@ -580,11 +549,7 @@ async fn test_anthropic_stream_utf8_split_across_chunks() {
 
     let upstream_url = start_anthropic_stream_server(stream_chunks).await;
     let config_json = make_anthropic_test_config(&upstream_url);
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let resp = make_codex_stream_request(&app).await;
@@ -610,7 +575,8 @@ async fn test_anthropic_stream_utf8_split_across_chunks() {
 #[tokio::test]
 async fn test_anthropic_stream_abrupt_closure_emits_done_marker() {
     // Skip if we cannot bind localhost sockets in this environment
-    if skip_if_localhost_bind_unavailable() {
+    if skip_if_localhost_bind_unavailable("test_anthropic_stream_abrupt_closure_emits_done_marker")
+    {
         return;
     }
     let stream_chunks = vec![
@@ -646,11 +612,7 @@ async fn test_anthropic_stream_abrupt_closure_emits_done_marker() {
 
     let upstream_url = start_anthropic_stream_server(stream_chunks).await;
     let config_json = make_anthropic_test_config(&upstream_url);
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let resp = make_codex_stream_request(&app).await;
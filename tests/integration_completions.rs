@@ -0,0 +1,193 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//! Integration tests for the legacy `/v1/completions` endpoint.
+//!
+//! Legacy clients send a `prompt` string; CCR routes it as a single user turn
+//! and answers in the `text_completion` shape, streamed or not.
+
+use axum::body::{to_bytes, Body};
+use axum::http::{Request, StatusCode};
+use axum::routing::post;
+use axum::Router;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+mod common;
+use common::{config_from_json, skip_if_localhost_bind_unavailable};
+
+fn make_test_config(mock_url: &str) -> String {
+    let config = json!({
+        "Providers": [
+            {
+                "name": "mock",
+                "api_base_url": mock_url,
+                "api_key": "test-key",
+                "models": ["test-model"]
+            }
+        ],
+        "Router": {
+            "default": "mock,test-model"
+        },
+        "API_TIMEOUT_MS": 5000
+    });
+
+    serde_json::to_string_pretty(&config).unwrap()
+}
+
+fn build_app(config: ccr_rust::config::Config) -> Router {
+    let state = ccr_rust::router::AppState::for_tests(config);
+
+    Router::new()
+        .route(
+            "/v1/completions",
+            post(ccr_rust::router::handle_completions),
+        )
+        .with_state(state)
+}
+
+async fn post_completion(app: Router, body: Value) -> axum::response::Response {
+    app.oneshot(
+        Request::builder()
+            .method("POST")
+            .uri("/v1/completions")
+            .header("content-type", "application/json")
+            .body(Body::from(serde_json::to_vec(&body).unwrap()))
+            .unwrap(),
+    )
+    .await
+    .unwrap()
+}
+
+async fn app_for(mock_server: &MockServer) -> Router {
+    let config_json = make_test_config(&mock_server.uri());
+    let config = config_from_json(&config_json);
+    build_app(config)
+}
+
+#[tokio::test]
+async fn test_legacy_completion_non_streaming() {
+    if skip_if_localhost_bind_unavailable("test_legacy_completion_non_streaming") {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(json!({
+            "messages": [{"role": "user", "content": "Say hello"}]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-legacy",
+            "object": "chat.completion",
+            "created": 1234567890,
+            "model": "test-model",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Hello there"},
+                "finish_reason": "length"
+            }],
+            "usage": {"prompt_tokens": 4, "completion_tokens": 2, "total_tokens": 6}
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let app = app_for(&mock_server).await;
+    let resp = post_completion(
+        app,
+        json!({"model": "mock,test-model", "prompt": "Say hello", "max_tokens": 2}),
+    )
+    .await;
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    let completion: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(completion["object"], "text_completion");
+    assert_eq!(completion["choices"][0]["text"], "Hello there");
+    assert_eq!(completion["choices"][0]["finish_reason"], "length");
+    assert_eq!(completion["usage"]["prompt_tokens"], 4);
+    assert_eq!(completion["usage"]["completion_tokens"], 2);
+    assert_eq!(completion["usage"]["total_tokens"], 6);
+}
+
+#[tokio::test]
+async fn test_legacy_completion_streaming() {
+    if skip_if_localhost_bind_unavailable("test_legacy_completion_streaming") {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+
+    let sse_body = format!(
+        "data: {}\n\ndata: {}\n\ndata: [DONE]\n\n",
+        json!({
+            "id": "chunk_1",
+            "object": "chat.completion.chunk",
+            "created": 1234567890,
+            "model": "test-model",
+            "choices": [{"index": 0, "delta": {"content": "Hel"}, "finish_reason": null}]
+        }),
+        json!({
+            "id": "chunk_1",
+            "object": "chat.completion.chunk",
+            "created": 1234567890,
+            "model": "test-model",
+            "choices": [{"index": 0, "delta": {"content": "lo"}, "finish_reason": "stop"}]
+        })
+    );
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(sse_body)
+                .insert_header("content-type", "text/event-stream"),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let app = app_for(&mock_server).await;
+    let resp = post_completion(
+        app,
+        json!({"model": "mock,test-model", "prompt": "Say hello", "stream": true}),
+    )
+    .await;
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers().get("content-type").unwrap(),
+        "text/event-stream"
+    );
+    let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    let payload = String::from_utf8(body.to_vec()).unwrap();
+
+    let frames: Vec<&str> = payload
+        .split("\n\n")
+        .filter_map(|frame| frame.strip_prefix("data: "))
+        .collect();
+    assert_eq!(frames.last(), Some(&"[DONE]"));
+
+    let chunks: Vec<Value> = frames[..frames.len() - 1]
+        .iter()
+        .map(|f| serde_json::from_str(f).unwrap())
+        .collect();
+    assert!(chunks.iter().all(|c| c["object"] == "text_completion"));
+    let text: String = chunks
+        .iter()
+        .filter_map(|c| c["choices"][0]["text"].as_str())
+        .collect();
+    assert_eq!(text, "Hello");
+    assert_eq!(
+        chunks.last().unwrap()["choices"][0]["finish_reason"],
+        "stop"
+    );
+}
+
+#[tokio::test]
+async fn test_legacy_completion_requires_prompt() {
+    let config = config_from_json(&make_test_config("http://127.0.0.1:9"));
+
+    let resp = post_completion(build_app(config), json!({"model": "mock,test-model"})).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
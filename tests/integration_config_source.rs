@@ -0,0 +1,143 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//! Integration tests for loading config from stdin and URLs.
+//!
+//! `--config -` reads the JSON from stdin and `--config https://...` fetches
+//! it, both going through the same parsing and validation as a file.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use ccr_rust::config::{Config, ConfigSource};
+use serde_json::json;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+mod common;
+use common::skip_if_localhost_bind_unavailable;
+
+fn test_config() -> String {
+    json!({
+        "Providers": [
+            {
+                "name": "piped",
+                "api_base_url": "http://127.0.0.1:9",
+                "api_key": "test-key",
+                "models": ["test-model"]
+            }
+        ],
+        "Router": {
+            "default": "piped,test-model"
+        }
+    })
+    .to_string()
+}
+
+fn validate_from_stdin(input: &str) -> std::process::Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_ccr-rust"))
+        .args(["--config", "-", "validate"])
+        .env_remove("CCR_CONFIG")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("spawn ccr-rust");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn test_validate_reads_config_from_stdin() {
+    let output = validate_from_stdin(&test_config());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "stdout: {stdout}");
+    assert!(stdout.contains("Validating: stdin"));
+    assert!(stdout.contains("piped: 1 model(s)"));
+
+    let output = validate_from_stdin("{not json");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Failed to parse config JSON"));
+}
+
+#[test]
+fn test_config_source_parse() {
+    assert_eq!(ConfigSource::parse("-", None), ConfigSource::Stdin);
+    assert_eq!(
+        ConfigSource::parse("https://cfg.example/ccr.json", Some("Bearer t".into())),
+        ConfigSource::Url {
+            url: "https://cfg.example/ccr.json".into(),
+            authorization: Some("Bearer t".into()),
+        }
+    );
+    assert_eq!(
+        ConfigSource::parse("/etc/ccr/config.json", Some("Bearer t".into())),
+        ConfigSource::File("/etc/ccr/config.json".into())
+    );
+}
+
+#[tokio::test]
+async fn test_config_fetched_from_url_with_authorization() {
+    if skip_if_localhost_bind_unavailable("test_config_fetched_from_url_with_authorization") {
+        return;
+    }
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/ccr.json"))
+        .and(header("authorization", "Bearer cfg-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(test_config()))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let source = ConfigSource::parse(
+        &format!("{}/ccr.json", server.uri()),
+        Some("Bearer cfg-token".to_string()),
+    );
+    let config = Config::from_source(&source).await.unwrap();
+    config.validate().unwrap();
+    assert_eq!(config.providers()[0].name, "piped");
+    assert_eq!(config.source(), &source);
+}
+
+#[tokio::test]
+async fn test_config_fetch_failures_are_reported() {
+    if skip_if_localhost_bind_unavailable("test_config_fetch_failures_are_reported") {
+        return;
+    }
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/ccr.json"))
+        .and(header("authorization", "Bearer cfg-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(test_config()))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/broken.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("{\"Providers\": ["))
+        .mount(&server)
+        .await;
+
+    // Missing credentials fall through to wiremock's 404.
+    let source = ConfigSource::parse(&format!("{}/ccr.json", server.uri()), None);
+    let err = Config::from_source(&source).await.unwrap_err();
+    assert!(format!("{err:#}").contains("returned 404"), "{err:#}");
+
+    let source = ConfigSource::parse(&format!("{}/broken.json", server.uri()), None);
+    let err = Config::from_source(&source).await.unwrap_err();
+    assert!(format!("{err:#}").contains("Failed to parse config JSON"));
+
+    let unreachable = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let source = ConfigSource::parse(&format!("http://{unreachable}/ccr.json"), None);
+    let err = Config::from_source(&source).await.unwrap_err();
+    assert!(
+        format!("{err:#}").contains("Failed to fetch config"),
+        "{err:#}"
+    );
+}
@@ -13,23 +13,17 @@ use axum::http::{Request, StatusCode};
 use axum::routing::post;
 use axum::Router;
 use serde_json::json;
-use std::io::Write;
 use tower::ServiceExt;
 use wiremock::matchers::{header, method, path};
 use wiremock::{Mock, MockServer, ResponseTemplate};
 
+mod common;
+use common::{config_from_json, skip_if_localhost_bind_unavailable};
+
 // ---------------------------------------------------------------------------
 // Test Helpers
 // ---------------------------------------------------------------------------
 
-/// Write test config to temp file and return the path.
-fn write_config_file(config_json: &serde_json::Value) -> tempfile::NamedTempFile {
-    let mut f = tempfile::NamedTempFile::new().unwrap();
-    serde_json::to_writer_pretty(&mut f, config_json).unwrap();
-    f.flush().unwrap();
-    f
-}
-
 /// Build test config with Kimi provider pointing at mock server.
 fn make_kimi_config(mock_url: &str) -> serde_json::Value {
     json!({
@@ -55,22 +49,7 @@ fn make_kimi_config(mock_url: &str) -> serde_json::Value {
 
 /// Build the Axum app with test state.
 fn build_app(config: ccr_rust::config::Config) -> Router {
-    let ewma_tracker = std::sync::Arc::new(ccr_rust::routing::EwmaTracker::new());
-    let transformer_registry =
-        std::sync::Arc::new(ccr_rust::transformer::TransformerRegistry::new());
-    let active_streams = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
-    let ratelimit_tracker = std::sync::Arc::new(ccr_rust::ratelimit::RateLimitTracker::new());
-    let state = ccr_rust::router::AppState {
-        config,
-        ewma_tracker,
-        gp_router: None,
-        transformer_registry,
-        active_streams,
-        max_streams: 0,
-        ratelimit_tracker,
-        shutdown_timeout: 30,
-        debug_capture: None,
-    };
+    let state = ccr_rust::router::AppState::for_tests(config);
     Router::new()
         .route("/v1/messages", post(ccr_rust::router::handle_messages))
         .with_state(state)
@@ -85,15 +64,6 @@ fn kimi_request_body(model: &str) -> serde_json::Value {
     })
 }
 
-/// Skip integration tests that require opening localhost sockets.
-fn skip_if_localhost_bind_unavailable(test_name: &str) -> bool {
-    if std::net::TcpListener::bind("127.0.0.1:0").is_ok() {
-        return false;
-    }
-    eprintln!("Skipping {test_name}: cannot bind localhost sockets");
-    true
-}
-
 // ---------------------------------------------------------------------------
 // Kimi Routing Tests
 // ---------------------------------------------------------------------------
@@ -127,8 +97,7 @@ async fn test_kimi_direct_routing_with_provider_model() {
         .await;
 
     let config_json = make_kimi_config(&mock_server.uri());
-    let config_file = write_config_file(&config_json);
-    let config = ccr_rust::config::Config::from_file(config_file.path().to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json.to_string());
     let app = build_app(config);
 
     // Request with kimi,kimi-k2.5 (direct routing)
@@ -178,8 +147,7 @@ async fn test_kimi_thinking_tokens_extracted() {
         .await;
 
     let config_json = make_kimi_config(&mock_server.uri());
-    let config_file = write_config_file(&config_json);
-    let config = ccr_rust::config::Config::from_file(config_file.path().to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json.to_string());
     let app = build_app(config);
 
     let request = Request::builder()
@@ -233,8 +201,7 @@ async fn test_kimi_429_rate_limit_passthrough() {
         .await;
 
     let config_json = make_kimi_config(&mock_server.uri());
-    let config_file = write_config_file(&config_json);
-    let config = ccr_rust::config::Config::from_file(config_file.path().to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json.to_string());
     let app = build_app(config);
 
     let request = Request::builder()
@@ -280,8 +247,7 @@ async fn test_kimi_anthropic_version_header_sent() {
         .await;
 
     let config_json = make_kimi_config(&mock_server.uri());
-    let config_file = write_config_file(&config_json);
-    let config = ccr_rust::config::Config::from_file(config_file.path().to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json.to_string());
     let app = build_app(config);
 
     let request = Request::builder()
@@ -298,3 +264,60 @@ async fn test_kimi_anthropic_version_header_sent() {
     let response = app.oneshot(request).await.unwrap();
     assert_eq!(response.status(), StatusCode::OK);
 }
+
+#[tokio::test]
+async fn test_anthropic_beta_header_sent_and_client_beta_forwarded() {
+    if skip_if_localhost_bind_unavailable("test_anthropic_beta_header") {
+        return;
+    }
+
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_kimi_beta",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "OK"}],
+            "model": "kimi-for-coding",
+            "stop_reason": "end_turn",
+            "usage": {"input_tokens": 10, "output_tokens": 1}
+        })))
+        .expect(2)
+        .mount(&mock_server)
+        .await;
+
+    let mut config_json = make_kimi_config(&mock_server.uri());
+    config_json["Providers"][0]["anthropic_beta"] = json!(["prompt-caching-2024-07-31"]);
+    config_json["Providers"][0]["forward_anthropic_beta"] = json!(true);
+    let config = config_from_json(&config_json.to_string());
+    let app = build_app(config);
+
+    for client_beta in [
+        None,
+        Some("context-1m-2025-08-07, prompt-caching-2024-07-31"),
+    ] {
+        let mut request = Request::builder()
+            .method("POST")
+            .uri("/v1/messages")
+            .header("Content-Type", "application/json")
+            .header("x-api-key", "client-key")
+            .header("anthropic-version", "2023-06-01");
+        if let Some(beta) = client_beta {
+            request = request.header("anthropic-beta", beta);
+        }
+        let request = request
+            .body(Body::from(
+                serde_json::to_string(&kimi_request_body("kimi,kimi-k2.5")).unwrap(),
+            ))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let received = mock_server.received_requests().await.unwrap();
+    let beta = |index: usize| received[index].headers["anthropic-beta"].to_str().unwrap();
+    assert_eq!(beta(0), "prompt-caching-2024-07-31");
+    assert_eq!(beta(1), "prompt-caching-2024-07-31,context-1m-2025-08-07");
+}
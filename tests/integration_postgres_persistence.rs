@@ -0,0 +1,99 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//! Integration test for starting the server with Postgres metrics persistence.
+//!
+//! Needs a reachable database in `CCR_TEST_POSTGRES_URL`; skipped otherwise.
+//! The server is run as a subprocess so persistence starts inside the real
+//! tokio runtime, where a blocking Postgres call would panic.
+#![cfg(feature = "postgres")]
+
+use std::time::Duration;
+
+use serde_json::json;
+
+fn test_postgres_url(test_name: &str) -> Option<String> {
+    let url = std::env::var("CCR_TEST_POSTGRES_URL").ok();
+    if url.is_none() {
+        eprintln!("Skipping {test_name}: CCR_TEST_POSTGRES_URL is not set");
+    }
+    url
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_server_starts_with_postgres_persistence() {
+    let Some(postgres_url) = test_postgres_url("test_server_starts_with_postgres_persistence")
+    else {
+        return;
+    };
+    let table_prefix = format!("ccr_test_{}_", std::process::id());
+    let config = json!({
+        "Providers": [
+            {
+                "name": "mock",
+                "api_base_url": "http://127.0.0.1:9",
+                "api_key": "test-key",
+                "models": ["test-model"]
+            }
+        ],
+        "Router": {
+            "default": "mock,test-model"
+        },
+        "Persistence": {
+            "mode": "postgres",
+            "postgres_url": postgres_url,
+            "postgres_table_prefix": table_prefix
+        }
+    });
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("config.json");
+    std::fs::write(&config_path, config.to_string()).unwrap();
+
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let mut server = tokio::process::Command::new(env!("CARGO_BIN_EXE_ccr-rust"))
+        .args(["--config", config_path.to_str().unwrap(), "start"])
+        .args(["--port", &port.to_string()])
+        .env_remove("CCR_CONFIG")
+        .kill_on_drop(true)
+        .spawn()
+        .expect("spawn ccr-rust start");
+
+    let client = reqwest::Client::new();
+    let health_url = format!("http://127.0.0.1:{port}/health");
+    let mut healthy = false;
+    for _ in 0..100 {
+        if let Some(status) = server.try_wait().unwrap() {
+            panic!("server exited during startup: {status}");
+        }
+        if let Ok(resp) = client.get(&health_url).send().await {
+            healthy = resp.status().is_success();
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(healthy, "server did not become healthy");
+
+    let counters_table = format!("{table_prefix}counters");
+    let table_exists = tokio::task::spawn_blocking(move || {
+        let mut client = postgres::Client::connect(&postgres_url, postgres::NoTls).unwrap();
+        let row = client
+            .query_one("SELECT to_regclass($1) IS NOT NULL", &[&counters_table])
+            .unwrap();
+        let exists: bool = row.get(0);
+        client
+            .batch_execute(&format!(
+                "DROP TABLE IF EXISTS {p}counters, {p}gauges, {p}histograms, \
+                 {p}histogram_buckets, {p}state, {p}token_audit",
+                p = table_prefix
+            ))
+            .unwrap();
+        exists
+    })
+    .await
+    .unwrap();
+    assert!(table_exists, "persistence tables were not created");
+
+    server.kill().await.unwrap();
+}
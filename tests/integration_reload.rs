@@ -0,0 +1,178 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//! Integration tests for `POST /v1/reload`.
+//!
+//! Reload re-reads the config file and swaps it into the shared state, so new
+//! requests route with the new providers while a bad file leaves the running
+//! config untouched.
+
+use axum::body::{to_bytes, Body};
+use axum::http::{Request, StatusCode};
+use axum::routing::post;
+use axum::Router;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+mod common;
+use common::skip_if_localhost_bind_unavailable;
+
+fn make_test_config(providers: &[(&str, &str)], default: &str) -> String {
+    let providers: Vec<Value> = providers
+        .iter()
+        .map(|(name, url)| {
+            json!({
+                "name": name,
+                "api_base_url": url,
+                "api_key": "test-key",
+                "models": ["test-model"]
+            })
+        })
+        .collect();
+    let config = json!({
+        "Providers": providers,
+        "Router": {"default": default},
+        "API_TIMEOUT_MS": 5000
+    });
+    serde_json::to_string_pretty(&config).unwrap()
+}
+
+fn build_app(config: ccr_rust::config::Config) -> Router {
+    let state = ccr_rust::router::AppState::for_tests(config);
+
+    Router::new()
+        .route("/v1/messages", post(ccr_rust::router::handle_messages))
+        .route("/v1/reload", post(ccr_rust::router::handle_reload))
+        .with_state(state)
+}
+
+async fn post_json(app: &Router, uri: &str, body: Value) -> (StatusCode, Value) {
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = resp.status();
+    let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+async fn mock_provider(reply: &str) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-reload",
+            "object": "chat.completion",
+            "created": 1234567890,
+            "model": "test-model",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": reply},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 3, "completion_tokens": 1}
+        })))
+        .mount(&server)
+        .await;
+    server
+}
+
+fn reply_text(body: &Value) -> &str {
+    body["content"][0]["text"].as_str().unwrap_or_default()
+}
+
+#[tokio::test]
+async fn test_reload_swaps_providers_for_new_requests() {
+    if skip_if_localhost_bind_unavailable("test_reload_swaps_providers_for_new_requests") {
+        return;
+    }
+    let old_server = mock_provider("from old").await;
+    let new_server = mock_provider("from new").await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("config.json");
+    std::fs::write(
+        &config_path,
+        make_test_config(&[("oldprov", &old_server.uri())], "oldprov,test-model"),
+    )
+    .unwrap();
+    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let app = build_app(config);
+
+    let request = json!({
+        "model": "claude-sonnet-4-6",
+        "messages": [{"role": "user", "content": "hello"}],
+        "max_tokens": 16
+    });
+    let (status, body) = post_json(&app, "/v1/messages", request.clone()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(reply_text(&body), "from old");
+
+    std::fs::write(
+        &config_path,
+        make_test_config(&[("newprov", &new_server.uri())], "newprov,test-model"),
+    )
+    .unwrap();
+    let (status, body) = post_json(&app, "/v1/reload", Value::Null).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["tiers"], json!(["newprov,test-model"]));
+    assert_eq!(body["providers_added"], json!(["newprov"]));
+    assert_eq!(body["providers_removed"], json!(["oldprov"]));
+
+    let (status, body) = post_json(&app, "/v1/messages", request).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(reply_text(&body), "from new");
+}
+
+#[tokio::test]
+async fn test_reload_rejects_invalid_config_and_keeps_current() {
+    if skip_if_localhost_bind_unavailable("test_reload_rejects_invalid_config_and_keeps_current") {
+        return;
+    }
+    let server = mock_provider("still here").await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("config.json");
+    std::fs::write(
+        &config_path,
+        make_test_config(&[("keepprov", &server.uri())], "keepprov,test-model"),
+    )
+    .unwrap();
+    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let app = build_app(config);
+
+    // Default tier points at a provider that does not exist.
+    std::fs::write(
+        &config_path,
+        make_test_config(&[("keepprov", &server.uri())], "missing,test-model"),
+    )
+    .unwrap();
+    let (status, body) = post_json(&app, "/v1/reload", Value::Null).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["error"].as_str().unwrap().contains("unknown provider"));
+
+    std::fs::write(&config_path, "{ not json").unwrap();
+    let (status, _) = post_json(&app, "/v1/reload", Value::Null).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, body) = post_json(
+        &app,
+        "/v1/messages",
+        json!({
+            "model": "claude-sonnet-4-6",
+            "messages": [{"role": "user", "content": "hello"}],
+            "max_tokens": 16
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(reply_text(&body), "still here");
+}
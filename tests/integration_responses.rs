@@ -7,9 +7,12 @@ use axum::routing::post;
 use axum::Router;
 use serde_json::json;
 use tower::ServiceExt;
-use wiremock::matchers::{method, path};
+use wiremock::matchers::{body_partial_json, method, path};
 use wiremock::{Mock, MockServer, ResponseTemplate};
 
+mod common;
+use common::{config_from_json, skip_if_localhost_bind_unavailable};
+
 #[derive(Debug)]
 struct SseEvent {
     event: String,
@@ -107,23 +110,28 @@ fn make_test_config_anthropic(mock_url: &str) -> String {
     serde_json::to_string_pretty(&config).unwrap()
 }
 
+fn make_test_config_native_responses(mock_url: &str) -> String {
+    let config = json!({
+        "Providers": [
+            {
+                "name": "mock",
+                "api_base_url": mock_url,
+                "api_key": "test-key",
+                "models": ["test-model"],
+                "protocol": "openai_responses"
+            }
+        ],
+        "Router": {
+            "default": "mock,test-model"
+        },
+        "API_TIMEOUT_MS": 5000
+    });
+
+    serde_json::to_string_pretty(&config).unwrap()
+}
+
 fn build_app(config: ccr_rust::config::Config) -> Router {
-    let ewma_tracker = std::sync::Arc::new(ccr_rust::routing::EwmaTracker::new());
-    let transformer_registry =
-        std::sync::Arc::new(ccr_rust::transformer::TransformerRegistry::new());
-    let active_streams = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
-    let ratelimit_tracker = std::sync::Arc::new(ccr_rust::ratelimit::RateLimitTracker::new());
-    let state = ccr_rust::router::AppState {
-        config,
-        ewma_tracker,
-        gp_router: None,
-        transformer_registry,
-        active_streams,
-        max_streams: 0,
-        ratelimit_tracker,
-        shutdown_timeout: 30,
-        debug_capture: None,
-    };
+    let state = ccr_rust::router::AppState::for_tests(config);
 
     Router::new()
         .route("/v1/messages", post(ccr_rust::router::handle_messages))
@@ -132,20 +140,14 @@ fn build_app(config: ccr_rust::config::Config) -> Router {
             post(ccr_rust::router::handle_chat_completions),
         )
         .route("/v1/responses", post(ccr_rust::router::handle_responses))
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            ccr_rust::router::limit_request_body,
+        ))
+        .route_layer(axum::extract::DefaultBodyLimit::disable())
         .with_state(state)
 }
 
-/// Skip integration tests that require opening localhost sockets when the
-/// execution environment forbids binding ports.
-fn skip_if_localhost_bind_unavailable(test_name: &str) -> bool {
-    if std::net::TcpListener::bind("127.0.0.1:0").is_ok() {
-        return false;
-    }
-
-    eprintln!("Skipping {test_name}: cannot bind localhost sockets in this environment");
-    true
-}
-
 #[tokio::test]
 async fn test_responses_non_stream_returns_response_object() {
     if skip_if_localhost_bind_unavailable("test_responses_non_stream_returns_response_object") {
@@ -179,10 +181,7 @@ async fn test_responses_non_stream_returns_response_object() {
         .await;
 
     let config_json = make_test_config(&mock_server.uri());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
 
     let app = build_app(config);
     let request = json!({
@@ -262,10 +261,7 @@ async fn test_responses_accepts_zstd_encoded_request_body() {
         .await;
 
     let config_json = make_test_config(&mock_server.uri());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let request = json!({
@@ -296,6 +292,214 @@ async fn test_responses_accepts_zstd_encoded_request_body() {
     assert_eq!(resp.status(), StatusCode::OK);
 }
 
+#[tokio::test]
+async fn test_oversized_request_bodies_get_413_in_frontend_shape() {
+    let mut config: serde_json::Value =
+        serde_json::from_str(&make_test_config("http://127.0.0.1:9")).unwrap();
+    config["MAX_BODY_BYTES"] = json!(1024);
+    let config = config_from_json(&config.to_string());
+    let app = build_app(config);
+
+    let oversized = json!({
+        "model": "mock,test-model",
+        "max_tokens": 16,
+        "messages": [{"role": "user", "content": "x".repeat(4096)}],
+        "input": "x".repeat(4096),
+    });
+    let raw = serde_json::to_vec(&oversized).unwrap();
+
+    for uri in ["/v1/messages", "/v1/chat/completions", "/v1/responses"] {
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(uri)
+                    .header("content-type", "application/json")
+                    .body(Body::from(raw.clone()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE, "{uri}");
+        let body: serde_json::Value = serde_json::from_slice(
+            &axum::body::to_bytes(resp.into_body(), usize::MAX)
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+        if uri == "/v1/messages" {
+            assert_eq!(body["type"], "error");
+            assert_eq!(body["error"]["type"], "request_too_large");
+        } else {
+            assert_eq!(body["error"]["code"], "request_too_large", "{uri}");
+        }
+        assert!(body["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("1024 byte limit"));
+    }
+}
+
+fn brotli_compress(raw: &[u8]) -> Vec<u8> {
+    let mut compressed = Vec::new();
+    let mut reader = brotli::CompressorReader::new(raw, 4096, 9, 22);
+    std::io::Read::read_to_end(&mut reader, &mut compressed).unwrap();
+    compressed
+}
+
+#[tokio::test]
+async fn test_responses_accepts_brotli_encoded_request_body() {
+    if skip_if_localhost_bind_unavailable("test_responses_accepts_brotli_encoded_request_body") {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-br",
+            "object": "chat.completion",
+            "created": 1730000000,
+            "model": "test-model",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "brotli ok"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 3, "completion_tokens": 2, "total_tokens": 5}
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let config_json = make_test_config(&mock_server.uri());
+    let config = config_from_json(&config_json);
+    let app = build_app(config);
+
+    let request = json!({
+        "model": "mock,test-model",
+        "input": [{
+            "type": "message",
+            "role": "user",
+            "content": [{"type": "input_text", "text": "hi"}]
+        }],
+        "stream": false
+    });
+    let compressed = brotli_compress(&serde_json::to_vec(&request).unwrap());
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/responses")
+                .header("content-type", "application/json")
+                .header("content-encoding", "br")
+                .body(Body::from(compressed))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: serde_json::Value = serde_json::from_slice(
+        &axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap(),
+    )
+    .unwrap();
+    assert!(body.to_string().contains("brotli ok"), "{body}");
+}
+
+#[tokio::test]
+async fn test_request_content_encoding_errors() {
+    let config = make_test_config("http://127.0.0.1:9");
+    let config = config_from_json(&config);
+    let app = build_app(config);
+
+    // Default MAX_BODY_BYTES is 8 MiB; this decodes to 16 MiB.
+    let bomb = json!({
+        "model": "mock,test-model",
+        "input": " ".repeat(16 * 1024 * 1024),
+    });
+    let bomb = brotli_compress(&serde_json::to_vec(&bomb).unwrap());
+    assert!(bomb.len() < 64 * 1024);
+
+    let cases = [
+        (
+            "br",
+            bomb,
+            "Decoded brotli request body exceeds the 8388608 byte limit",
+        ),
+        (
+            "gzip",
+            b"{}".to_vec(),
+            "Unsupported content-encoding 'gzip' (supported: identity, zstd, br)",
+        ),
+    ];
+    for (encoding, body, message) in cases {
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/responses")
+                    .header("content-type", "application/json")
+                    .header("content-encoding", encoding)
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{encoding}");
+        let body: serde_json::Value = serde_json::from_slice(
+            &axum::body::to_bytes(resp.into_body(), usize::MAX)
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(body["error"]["message"], message);
+    }
+}
+
+#[tokio::test]
+async fn test_zstd_bodies_that_decode_past_the_limit_are_rejected() {
+    // Default MAX_BODY_BYTES is 8 MiB; this decodes to 16 MiB.
+    let config = make_test_config("http://127.0.0.1:9");
+    let config = config_from_json(&config);
+    let app = build_app(config);
+
+    let bomb = json!({
+        "model": "mock,test-model",
+        "input": " ".repeat(16 * 1024 * 1024),
+    });
+    let raw = serde_json::to_vec(&bomb).unwrap();
+    let compressed = zstd::stream::encode_all(std::io::Cursor::new(raw), 19).unwrap();
+    assert!(compressed.len() < 64 * 1024);
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/responses")
+                .header("content-type", "application/json")
+                .header("content-encoding", "zstd")
+                .body(Body::from(compressed))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let body: serde_json::Value = serde_json::from_slice(
+        &axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        body["error"]["message"],
+        "Decoded zstd request body exceeds the 8388608 byte limit"
+    );
+}
+
 #[tokio::test]
 async fn test_responses_normalizes_developer_role_for_backend() {
     if skip_if_localhost_bind_unavailable("test_responses_normalizes_developer_role_for_backend") {
@@ -336,10 +540,7 @@ async fn test_responses_normalizes_developer_role_for_backend() {
         .await;
 
     let config_json = make_test_config(&mock_server.uri());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let request = json!({
@@ -405,10 +606,7 @@ async fn test_responses_anthropic_protocol_routes_to_messages_endpoint() {
         .await;
 
     let config_json = make_test_config_anthropic(&mock_server.uri());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let request = json!({
@@ -443,6 +641,274 @@ async fn test_responses_anthropic_protocol_routes_to_messages_endpoint() {
     assert_eq!(backend_request["messages"][0]["role"], "user");
 }
 
+#[tokio::test]
+async fn test_responses_native_protocol_forwards_request_and_events_unchanged() {
+    if skip_if_localhost_bind_unavailable(
+        "test_responses_native_protocol_forwards_request_and_events_unchanged",
+    ) {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+
+    let captured_request = std::sync::Arc::new(std::sync::Mutex::new(None));
+    let captured_clone = captured_request.clone();
+    let upstream_events = "event: response.created\n\
+        data: {\"type\":\"response.created\",\"response\":{\"id\":\"resp_native\"}}\n\n\
+        event: response.reasoning_summary_text.delta\n\
+        data: {\"type\":\"response.reasoning_summary_text.delta\",\"delta\":\"thinking\"}\n\n\
+        event: response.completed\n\
+        data: {\"type\":\"response.completed\",\"response\":{\"id\":\"resp_native\",\"status\":\"completed\"}}\n\n";
+
+    Mock::given(method("POST"))
+        .and(path("/responses"))
+        .respond_with(move |req: &wiremock::Request| {
+            let body: serde_json::Value = req.body_json().unwrap();
+            *captured_clone.lock().unwrap() = Some(body);
+            ResponseTemplate::new(200).set_body_raw(upstream_events, "text/event-stream")
+        })
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(0)
+        .mount(&mock_server)
+        .await;
+
+    let config_json = make_test_config_native_responses(&mock_server.uri());
+    let config = config_from_json(&config_json);
+    let app = build_app(config);
+
+    let request = json!({
+        "model": "mock,test-model",
+        "stream": true,
+        "previous_response_id": "resp_prev",
+        "include": ["reasoning.encrypted_content"],
+        "input": [{
+            "type": "message",
+            "role": "user",
+            "content": [{"type": "input_text", "text": "hello"}]
+        }]
+    });
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/responses")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(&request).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers()["content-type"], "text/event-stream");
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&bytes), upstream_events);
+
+    let captured = captured_request.lock().unwrap();
+    let mut expected = request.clone();
+    expected["model"] = json!("test-model");
+    assert_eq!(captured.as_ref(), Some(&expected));
+}
+
+#[tokio::test]
+async fn test_responses_native_protocol_normalizes_rate_limits() {
+    if skip_if_localhost_bind_unavailable("test_responses_native_protocol_normalizes_rate_limits") {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/responses"))
+        .respond_with(
+            ResponseTemplate::new(429)
+                .insert_header("retry-after", "7")
+                .set_body_json(json!({
+                    "error": {"message": "slow down", "type": "rate_limit_exceeded"}
+                })),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let config_json = make_test_config_native_responses(&mock_server.uri());
+    let config = config_from_json(&config_json);
+    let app = build_app(config);
+
+    let request = json!({
+        "model": "mock,test-model",
+        "stream": false,
+        "input": "hello"
+    });
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/responses")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(&request).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(resp.headers()["retry-after"], "7");
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(body["status"], "failed");
+    assert_eq!(body["error"]["code"], "rate_limited");
+    assert_eq!(body["error"]["message"], "slow down");
+    assert_eq!(body["error"]["retry_after"], 7);
+}
+
+#[tokio::test]
+async fn test_responses_native_protocol_falls_back_to_conversion_on_retryable_error() {
+    if skip_if_localhost_bind_unavailable(
+        "test_responses_native_protocol_falls_back_to_conversion_on_retryable_error",
+    ) {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/responses"))
+        .respond_with(ResponseTemplate::new(503).set_body_string("overloaded"))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    // Only the backup tier may be tried through chat/completions; the native
+    // tier that just failed is left out of the fallback.
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(json!({"model": "backup-model"})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-fallback",
+            "object": "chat.completion",
+            "created": 1730000000,
+            "model": "test-model",
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "content": "Converted reply"
+                },
+                "finish_reason": "stop"
+            }]
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let config_json = json!({
+        "Providers": [
+            {
+                "name": "mock",
+                "api_base_url": mock_server.uri(),
+                "api_key": "test-key",
+                "models": ["test-model"],
+                "protocol": "openai_responses"
+            },
+            {
+                "name": "backup",
+                "api_base_url": mock_server.uri(),
+                "api_key": "test-key",
+                "models": ["backup-model"]
+            }
+        ],
+        "Router": {
+            "default": "mock,test-model",
+            "think": "backup,backup-model"
+        },
+        "API_TIMEOUT_MS": 5000
+    });
+    let config = config_from_json(&config_json.to_string());
+    let app = build_app(config);
+
+    let request = json!({
+        "model": "mock,test-model",
+        "stream": false,
+        "input": "hello"
+    });
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/responses")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(&request).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(body["status"], "completed");
+    assert_eq!(body["output"][0]["content"][0]["text"], "Converted reply");
+}
+
+#[tokio::test]
+async fn test_responses_native_protocol_passes_error_through_without_fallback_tier() {
+    if skip_if_localhost_bind_unavailable(
+        "test_responses_native_protocol_passes_error_through_without_fallback_tier",
+    ) {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/responses"))
+        .respond_with(ResponseTemplate::new(503).set_body_string("overloaded"))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&mock_server)
+        .await;
+
+    let config_json = make_test_config_native_responses(&mock_server.uri());
+    let config = config_from_json(&config_json);
+    let app = build_app(config);
+
+    let request = json!({
+        "model": "mock,test-model",
+        "stream": false,
+        "input": "hello"
+    });
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/responses")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(&request).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(resp.headers()["x-ccr-tier"], "mock");
+}
+
 #[tokio::test]
 async fn test_responses_stream_emits_required_events() {
     if skip_if_localhost_bind_unavailable("test_responses_stream_emits_required_events") {
@@ -536,10 +1002,7 @@ async fn test_responses_stream_emits_required_events() {
         .await;
 
     let config_json = make_test_config(&mock_server.uri());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let request = json!({
@@ -710,10 +1173,7 @@ async fn test_responses_stream_merges_tool_call_deltas_across_chunks() {
         .await;
 
     let config_json = make_test_config(&mock_server.uri());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let request = json!({
@@ -790,6 +1250,113 @@ async fn test_responses_stream_merges_tool_call_deltas_across_chunks() {
     assert_eq!(completed_tools[0]["arguments"], "{\"expr\":\"2+2\"}");
 }
 
+#[tokio::test]
+async fn test_responses_stream_wraps_malformed_tool_arguments() {
+    if skip_if_localhost_bind_unavailable("test_responses_stream_wraps_malformed_tool_arguments") {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+
+    let tool_chunk = |tool_call: serde_json::Value| {
+        json!({
+            "id": "chatcmpl-bad-args",
+            "object": "chat.completion.chunk",
+            "created": 1730000300,
+            "model": "test-model",
+            "choices": [{
+                "index": 0,
+                "delta": {"tool_calls": [tool_call]},
+                "finish_reason": null
+            }]
+        })
+    };
+    let sse = build_openai_sse(&[
+        tool_chunk(json!({
+            "index": 0,
+            "id": "call_bad",
+            "type": "function",
+            "function": {"name": "calculator", "arguments": "{\"expr\":"}
+        })),
+        tool_chunk(json!({"index": 0, "function": {"arguments": "\"2+2"}})),
+        json!({
+            "id": "chatcmpl-bad-args",
+            "object": "chat.completion.chunk",
+            "created": 1730000300,
+            "model": "test-model",
+            "choices": [{
+                "index": 0,
+                "delta": {},
+                "finish_reason": "tool_calls"
+            }]
+        }),
+    ]);
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(sse),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let config_json = make_test_config(&mock_server.uri());
+    let config = config_from_json(&config_json);
+    let app = build_app(config);
+
+    let request = json!({
+        "model": "mock,test-model",
+        "input": [{
+            "type": "message",
+            "role": "user",
+            "content": [{"type": "input_text", "text": "Calculate 2+2"}]
+        }],
+        "stream": true
+    });
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/responses")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(&request).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let events = parse_sse_events(&String::from_utf8_lossy(&bytes));
+
+    let tool_done = events
+        .iter()
+        .find(|event| {
+            event.event == "response.output_item.done"
+                && event.data["item"]["type"] == "function_call"
+        })
+        .expect("function call should be finalized");
+    let arguments: serde_json::Value = serde_json::from_str(
+        tool_done.data["item"]["arguments"]
+            .as_str()
+            .expect("arguments should be a string"),
+    )
+    .expect("finalized arguments should be valid JSON");
+    assert_eq!(arguments, json!({"raw_arguments": "{\"expr\":\"2+2"}));
+
+    let completed = events.last().expect("stream should include completion");
+    assert_eq!(completed.event, "response.completed");
+    assert_eq!(
+        completed.data["response"]["output"][0]["arguments"],
+        tool_done.data["item"]["arguments"]
+    );
+}
+
 #[tokio::test]
 async fn test_responses_stream_complex_mixed_content_and_tools() {
     if skip_if_localhost_bind_unavailable("test_responses_stream_complex_mixed_content_and_tools") {
@@ -901,10 +1468,7 @@ async fn test_responses_stream_complex_mixed_content_and_tools() {
         .await;
 
     let config_json = make_test_config(&mock_server.uri());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let request = json!({
@@ -1006,10 +1570,7 @@ async fn test_responses_stream_maps_errors_to_response_failed() {
         .await;
 
     let config_json = make_test_config(&mock_server.uri());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let request = json!({
@@ -1085,10 +1646,7 @@ async fn test_responses_stream_recovers_when_upstream_sends_anthropic_events() {
         .await;
 
     let config_json = make_test_config(&mock_server.uri());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let request = json!({
@@ -1140,3 +1698,106 @@ async fn test_responses_stream_recovers_when_upstream_sends_anthropic_events() {
         .join("");
     assert_eq!(joined_deltas, "Hello");
 }
+
+#[tokio::test]
+async fn test_responses_stream_emits_reasoning_summary_when_requested() {
+    if skip_if_localhost_bind_unavailable(
+        "test_responses_stream_emits_reasoning_summary_when_requested",
+    ) {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+
+    let anthropic_sse = [
+        "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"id\":\"msg_summary\",\"model\":\"test-model\",\"usage\":{\"input_tokens\":3,\"output_tokens\":0}}}\n\n",
+        "event: content_block_start\ndata: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"thinking\",\"thinking\":\"\"}}\n\n",
+        "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"thinking_delta\",\"thinking\":\"Compare the two \"}}\n\n",
+        "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"thinking_delta\",\"thinking\":\"options first.\"}}\n\n",
+        "event: content_block_stop\ndata: {\"type\":\"content_block_stop\",\"index\":0}\n\n",
+        "event: content_block_start\ndata: {\"type\":\"content_block_start\",\"index\":1,\"content_block\":{\"type\":\"text\",\"text\":\"\"}}\n\n",
+        "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":1,\"delta\":{\"type\":\"text_delta\",\"text\":\"Option A.\"}}\n\n",
+        "event: content_block_stop\ndata: {\"type\":\"content_block_stop\",\"index\":1}\n\n",
+        "event: message_delta\ndata: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"end_turn\"},\"usage\":{\"output_tokens\":6}}\n\n",
+        "event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n",
+    ]
+    .join("");
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(anthropic_sse),
+        )
+        .expect(2)
+        .mount(&mock_server)
+        .await;
+
+    let config_json = make_test_config(&mock_server.uri());
+    let config = config_from_json(&config_json);
+    let app = build_app(config);
+
+    let mut request = json!({
+        "model": "mock,test-model",
+        "input": [{
+            "type": "message",
+            "role": "user",
+            "content": [{"type": "input_text", "text": "Which option?"}]
+        }],
+        "stream": true
+    });
+
+    let events_for = |request: serde_json::Value| {
+        let app = app.clone();
+        async move {
+            let resp = app
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri("/v1/responses")
+                        .header("content-type", "application/json")
+                        .body(Body::from(serde_json::to_vec(&request).unwrap()))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+            let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            parse_sse_events(&String::from_utf8_lossy(&bytes))
+        }
+    };
+
+    // Without `reasoning.summary`, no summary events are emitted.
+    let events = events_for(request.clone()).await;
+    assert!(!events
+        .iter()
+        .any(|e| e.event.starts_with("response.reasoning_summary_text")));
+
+    request["reasoning"] = json!({"summary": "auto"});
+    let events = events_for(request).await;
+
+    let summary_position = events
+        .iter()
+        .position(|e| e.event == "response.reasoning_summary_text.delta")
+        .expect("expected a reasoning summary delta");
+    let text_position = events
+        .iter()
+        .position(|e| e.event == "response.output_text.delta" && e.data["delta"] != "")
+        .expect("expected an output text delta");
+    assert!(
+        summary_position < text_position,
+        "summary should precede the answer text"
+    );
+    assert_eq!(
+        events[summary_position].data["delta"],
+        "Compare the two options first."
+    );
+
+    let done = events
+        .iter()
+        .find(|e| e.event == "response.reasoning_summary_text.done")
+        .expect("expected reasoning summary done event");
+    assert_eq!(done.data["text"], "Compare the two options first.");
+}
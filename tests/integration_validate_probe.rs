@@ -0,0 +1,192 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//! Integration tests for `ccr-rust validate --probe`.
+//!
+//! The probe sends a 1-token request to every provider and fails the command
+//! when any provider is unreachable or rejects its key.
+
+use ccr_rust::config::Config;
+use ccr_rust::router::{probe_providers, ProbeOutcome};
+use serde_json::json;
+use wiremock::matchers::{body_partial_json, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+mod common;
+use common::{config_from_json, skip_if_localhost_bind_unavailable};
+
+async fn mock_provider() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(header("authorization", "Bearer good-key"))
+        .and(body_partial_json(json!({"max_tokens": 1})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-probe",
+            "object": "chat.completion",
+            "created": 1234567890,
+            "model": "test-model",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "p"},
+                "finish_reason": "length"
+            }]
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(header("authorization", "Bearer bad-key"))
+        .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+            "error": {"message": "invalid api key"}
+        })))
+        .mount(&server)
+        .await;
+    server
+}
+
+fn write_config(dir: &tempfile::TempDir, providers: Vec<serde_json::Value>) -> String {
+    let default = format!("{},test-model", providers[0]["name"].as_str().unwrap());
+    let config = json!({
+        "Providers": providers,
+        "Router": {"default": default}
+    });
+    let path = dir.path().join("config.json");
+    std::fs::write(&path, config.to_string()).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+fn provider(name: &str, base_url: &str, key: &str) -> serde_json::Value {
+    json!({
+        "name": name,
+        "api_base_url": base_url,
+        "api_key": key,
+        "models": ["test-model"]
+    })
+}
+
+async fn run_validate_probe(config_path: &str) -> std::process::Output {
+    tokio::process::Command::new(env!("CARGO_BIN_EXE_ccr-rust"))
+        .args(["--config", config_path, "validate", "--probe"])
+        .env_remove("CCR_CONFIG")
+        .output()
+        .await
+        .expect("run ccr-rust validate --probe")
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_validate_probe_reports_each_provider() {
+    if skip_if_localhost_bind_unavailable("test_validate_probe_reports_each_provider") {
+        return;
+    }
+    let server = mock_provider().await;
+    let unreachable = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let dir = tempfile::tempdir().unwrap();
+
+    let healthy = write_config(&dir, vec![provider("good", &server.uri(), "good-key")]);
+    let output = run_validate_probe(&healthy).await;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "stdout: {stdout}");
+    assert!(stdout.contains("✓ good (test-model): ok in"), "{stdout}");
+
+    let broken = write_config(
+        &dir,
+        vec![
+            provider("good", &server.uri(), "good-key"),
+            provider("badkey", &server.uri(), "bad-key"),
+            provider("down", &format!("http://{unreachable}"), "good-key"),
+        ],
+    );
+    let output = run_validate_probe(&broken).await;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success(), "stdout: {stdout}");
+    assert!(stdout.contains("✓ good (test-model): ok"), "{stdout}");
+    assert!(
+        stdout.contains("✗ badkey (test-model): auth failed (401)"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("✗ down (test-model): unreachable"),
+        "{stdout}"
+    );
+    assert!(
+        stderr.contains("2 of 3 provider(s) failed the probe"),
+        "{stderr}"
+    );
+}
+
+#[tokio::test]
+async fn test_probe_classifies_rate_limits_as_reachable() {
+    if skip_if_localhost_bind_unavailable("test_probe_classifies_rate_limits_as_reachable") {
+        return;
+    }
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(429))
+        .mount(&server)
+        .await;
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = write_config(&dir, vec![provider("busy", &server.uri(), "k")]);
+    let config = Config::from_file(&config_path).unwrap();
+
+    let probes = probe_providers(&config).await;
+    assert_eq!(probes.len(), 1);
+    assert_eq!(probes[0].outcome, ProbeOutcome::RateLimited);
+    assert!(probes[0].outcome.is_success());
+}
+
+#[tokio::test]
+async fn test_probe_skips_modelless_providers_and_budgets_reasoning_models() {
+    if skip_if_localhost_bind_unavailable(
+        "test_probe_skips_modelless_providers_and_budgets_reasoning_models",
+    ) {
+        return;
+    }
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(json!({
+            "model": "deepseek-reasoner",
+            "max_completion_tokens": 1
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-probe",
+            "object": "chat.completion",
+            "created": 1234567890,
+            "model": "deepseek-reasoner",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": ""},
+                "finish_reason": "length"
+            }]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let config = json!({
+        "Providers": [
+            {
+                "name": "thinker",
+                "api_base_url": server.uri(),
+                "api_key": "k",
+                "models": ["deepseek-reasoner"]
+            },
+            {
+                "name": "empty",
+                "api_base_url": server.uri(),
+                "api_key": "k",
+                "models": []
+            }
+        ],
+        "Router": {"default": "thinker,deepseek-reasoner"}
+    });
+    let config = config_from_json(&config.to_string());
+
+    let probes = probe_providers(&config).await;
+    assert_eq!(probes.len(), 1);
+    assert_eq!(probes[0].provider, "thinker");
+    assert_eq!(probes[0].outcome, ProbeOutcome::Ok);
+}
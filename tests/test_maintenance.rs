@@ -0,0 +1,88 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+//! Tests for maintenance mode (`MAINTENANCE_MODE` / `POST /v1/maintenance`).
+
+use std::sync::atomic::Ordering;
+
+use axum::body::{to_bytes, Body};
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+mod common;
+use common::config_from_json;
+
+fn build_app(maintenance_mode: bool) -> (Router, ccr_rust::router::AppState) {
+    let config = json!({
+        "Providers": [
+            { "name": "mock", "api_base_url": "http://127.0.0.1:9", "api_key": "k", "models": ["m"] }
+        ],
+        "Router": { "default": "mock,m" },
+        "MAINTENANCE_MODE": maintenance_mode
+    });
+    let config = config_from_json(&config.to_string());
+
+    let state = ccr_rust::router::AppState::for_tests(config);
+
+    (ccr_rust::router::app(state.clone()), state)
+}
+
+async fn send(app: &Router, method: &str, uri: &str, body: Value) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let resp = app.clone().oneshot(request).await.unwrap();
+    let status = resp.status();
+    let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+fn messages_request() -> Value {
+    json!({
+        "model": "mock,m",
+        "max_tokens": 16,
+        "messages": [{"role": "user", "content": "hi"}]
+    })
+}
+
+#[tokio::test]
+async fn maintenance_rejects_messages_but_serves_usage() {
+    let (app, _state) = build_app(true);
+
+    let (status, body) = send(&app, "POST", "/v1/messages", messages_request()).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["type"], "error");
+    assert!(body["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("maintenance"));
+
+    let (status, body) = send(&app, "POST", "/v1/chat/completions", json!({})).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["error"]["code"], "maintenance");
+
+    let (status, _) = send(&app, "GET", "/v1/usage", Value::Null).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send(&app, "GET", "/health", Value::Null).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn maintenance_endpoint_toggles_mode() {
+    let (app, state) = build_app(false);
+
+    let (status, body) = send(&app, "POST", "/v1/maintenance", json!({"enabled": true})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["maintenance"], true);
+    assert!(state.maintenance.load(Ordering::SeqCst));
+    let (status, _) = send(&app, "POST", "/v1/messages", messages_request()).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+
+    let (status, body) = send(&app, "POST", "/v1/maintenance", json!({"enabled": false})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["maintenance"], false);
+    assert!(!state.maintenance.load(Ordering::SeqCst));
+}
@@ -6,13 +6,17 @@ use axum::body::Body;
 use axum::http::{Request, StatusCode};
 use axum::routing::post;
 use axum::Router;
+use futures::StreamExt;
 use serde_json::json;
 use tower::ServiceExt;
-use wiremock::matchers::{method, path};
+use wiremock::matchers::{body_partial_json, method, path};
 use wiremock::{Mock, MockServer, ResponseTemplate};
 
 use ccr_rust::config::TierRetryConfig;
 
+mod common;
+use common::{config_from_json, counter_value, skip_if_localhost_bind_unavailable};
+
 // ---------------------------------------------------------------------------
 // Unit-level tests for TierRetryConfig::backoff_duration
 // ---------------------------------------------------------------------------
@@ -245,14 +249,11 @@ fn config_file_deserialize_no_tier_retries() {
 }
 
 // ---------------------------------------------------------------------------
-// Config::get_tier_retry resolution (requires on-disk config for Config::from_file)
+// Config::get_tier_retry resolution
 // ---------------------------------------------------------------------------
 
 #[test]
 fn get_tier_retry_with_custom_and_fallback() {
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-
     let config_json = json!({
         "Providers": [
             {
@@ -275,12 +276,7 @@ fn get_tier_retry_with_custom_and_fallback() {
         }
     });
 
-    std::fs::write(
-        &config_path,
-        serde_json::to_string_pretty(&config_json).unwrap(),
-    )
-    .unwrap();
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json.to_string());
 
     // tier-0 has custom config
     let tier0 = config.get_tier_retry("tier-0");
@@ -331,36 +327,39 @@ fn make_test_config(mock_url: &str, tier_retries: HashMap<String, TierRetryConfi
 }
 
 fn build_app(config: ccr_rust::config::Config) -> Router {
-    let ewma_tracker = std::sync::Arc::new(ccr_rust::routing::EwmaTracker::new());
-    let transformer_registry =
-        std::sync::Arc::new(ccr_rust::transformer::TransformerRegistry::new());
-    let active_streams = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
-    let ratelimit_tracker = std::sync::Arc::new(ccr_rust::ratelimit::RateLimitTracker::new());
-    let state = ccr_rust::router::AppState {
-        config,
-        ewma_tracker,
-        gp_router: None,
-        transformer_registry,
-        active_streams,
-        max_streams: 0,
-        ratelimit_tracker,
-        shutdown_timeout: 30,
-        debug_capture: None,
-    };
+    build_app_with_max_streams(config, 0)
+}
+
+fn build_app_with_max_streams(config: ccr_rust::config::Config, max_streams: usize) -> Router {
     Router::new()
         .route("/v1/messages", post(ccr_rust::router::handle_messages))
-        .with_state(state)
+        .route(
+            "/preset/:name/v1/messages",
+            post(ccr_rust::router::handle_preset_messages),
+        )
+        .route(
+            "/v1/presets",
+            axum::routing::get(ccr_rust::router::list_presets),
+        )
+        .route(
+            "/v1/transformers/preview",
+            post(ccr_rust::router::preview_transformers),
+        )
+        .route(
+            "/v1/providers",
+            axum::routing::get(ccr_rust::router::list_providers),
+        )
+        .route(
+            "/v1/usage",
+            axum::routing::get(ccr_rust::metrics::usage_handler),
+        )
+        .with_state(build_state(config, max_streams))
 }
 
-/// Skip integration tests that require opening localhost sockets when the
-/// execution environment forbids binding ports.
-fn skip_if_localhost_bind_unavailable(test_name: &str) -> bool {
-    if std::net::TcpListener::bind("127.0.0.1:0").is_ok() {
-        return false;
-    }
-
-    eprintln!("Skipping {test_name}: cannot bind localhost sockets in this environment");
-    true
+fn build_state(config: ccr_rust::config::Config, max_streams: usize) -> ccr_rust::router::AppState {
+    let mut state = ccr_rust::router::AppState::for_tests(config);
+    state.max_streams = max_streams;
+    state
 }
 
 fn test_request_body() -> serde_json::Value {
@@ -389,11 +388,7 @@ async fn successful_request_no_retry() {
         .await;
 
     let config_json = make_test_config(&mock_server.uri(), HashMap::new());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let resp = app
@@ -440,11 +435,7 @@ async fn retries_on_backend_failure_then_succeeds() {
         .await;
 
     let config_json = make_test_config(&mock_server.uri(), HashMap::new());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let resp = app
@@ -480,11 +471,7 @@ async fn all_retries_exhausted_returns_503() {
         .await;
 
     let config_json = make_test_config(&mock_server.uri(), HashMap::new());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let resp = app
@@ -527,11 +514,7 @@ async fn upstream_429_returns_structured_rate_limit_payload() {
         .await;
 
     let config_json = make_test_config(&mock_server.uri(), HashMap::new());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let resp = app
@@ -594,11 +577,7 @@ async fn backoff_introduces_measurable_delay() {
         .await;
 
     let config_json = make_test_config(&mock_server.uri(), HashMap::new());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let start = Instant::now();
@@ -676,11 +655,7 @@ async fn multi_tier_cascade_on_failure() {
         "API_TIMEOUT_MS": 5000
     });
 
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, serde_json::to_string_pretty(&config).unwrap()).unwrap();
-
-    let cfg = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let cfg = config_from_json(&config.to_string());
     let app = build_app(cfg);
 
     let body = json!({
@@ -705,230 +680,123 @@ async fn multi_tier_cascade_on_failure() {
     assert_eq!(resp.status(), StatusCode::OK);
 }
 
-// ---------------------------------------------------------------------------
-// Backoff edge cases
-// ---------------------------------------------------------------------------
-
-#[test]
-fn backoff_duration_max_smaller_than_base() {
-    // When max_backoff_ms < base_backoff_ms, every attempt clamps to max.
-    let cfg = TierRetryConfig {
-        max_retries: 3,
-        base_backoff_ms: 5000,
-        backoff_multiplier: 2.0,
-        max_backoff_ms: 1000,
-        ..Default::default()
-    };
-
-    assert_eq!(cfg.backoff_duration(0), Duration::from_millis(1000));
-    assert_eq!(cfg.backoff_duration(1), Duration::from_millis(1000));
-}
-
-#[test]
-fn backoff_duration_multiplier_less_than_one_decays() {
-    // Sub-1.0 multiplier means backoff decreases with each attempt.
-    let cfg = TierRetryConfig {
-        max_retries: 5,
-        base_backoff_ms: 1000,
-        backoff_multiplier: 0.5,
-        max_backoff_ms: 10_000,
-        ..Default::default()
-    };
-
-    assert_eq!(cfg.backoff_duration(0), Duration::from_millis(1000));
-    assert_eq!(cfg.backoff_duration(1), Duration::from_millis(500));
-    assert_eq!(cfg.backoff_duration(2), Duration::from_millis(250));
-    assert_eq!(cfg.backoff_duration(3), Duration::from_millis(125));
-}
-
-// ---------------------------------------------------------------------------
-// Config round-trip: tier_retries survive serialize -> deserialize
-// ---------------------------------------------------------------------------
-
-#[test]
-fn tier_retry_config_roundtrip_serde() {
-    let original = TierRetryConfig {
-        max_retries: 7,
-        base_backoff_ms: 250,
-        backoff_multiplier: 1.8,
-        max_backoff_ms: 15_000,
-        ..Default::default()
-    };
-
-    let json = serde_json::to_string(&original).unwrap();
-    let restored: TierRetryConfig = serde_json::from_str(&json).unwrap();
-
-    assert_eq!(restored.max_retries, original.max_retries);
-    assert_eq!(restored.base_backoff_ms, original.base_backoff_ms);
-    assert_eq!(restored.backoff_multiplier, original.backoff_multiplier);
-    assert_eq!(restored.max_backoff_ms, original.max_backoff_ms);
-}
-
-// ---------------------------------------------------------------------------
-// Adaptive backoff integration tests
-// These tests verify that backoff delays are applied during actual HTTP retries
-// ---------------------------------------------------------------------------
-
 #[tokio::test]
-async fn adaptive_backoff_applies_configured_delays() {
-    if skip_if_localhost_bind_unavailable("adaptive_backoff_applies_configured_delays") {
+async fn retry_on_finish_reason_cascades_content_filter() {
+    if skip_if_localhost_bind_unavailable("retry_on_finish_reason_cascades_content_filter") {
         return;
     }
-    let mock_server = MockServer::start().await;
+    let tier0_server = MockServer::start().await;
+    let tier1_server = MockServer::start().await;
+    let completion = |content: &str, finish_reason: &str| {
+        json!({
+            "id": "chatcmpl-finish",
+            "object": "chat.completion",
+            "created": 1234567890,
+            "model": "m",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": content},
+                "finish_reason": finish_reason
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 1}
+        })
+    };
 
-    // Mock server always fails with 500
+    // A filtered answer is not retried on the same tier.
     Mock::given(method("POST"))
         .and(path("/chat/completions"))
-        .respond_with(ResponseTemplate::new(500).set_body_string("fail"))
-        .mount(&mock_server)
+        .respond_with(ResponseTemplate::new(200).set_body_json(completion("", "content_filter")))
+        .expect(1)
+        .mount(&tier0_server)
         .await;
-
-    // Configure aggressive backoff for measurable delays: 50ms base, 1.5x multiplier
-    // NOTE: Currently router uses hardcoded backoff (100ms * 2^attempt), not config values.
-    // This test verifies the configured backoff values exist and the delay mechanism works.
-    let mut tier_retries = HashMap::new();
-    tier_retries.insert(
-        "tier-0".to_string(),
-        TierRetryConfig {
-            max_retries: 3,
-            base_backoff_ms: 50,
-            backoff_multiplier: 1.5,
-            max_backoff_ms: 10000,
-            ..Default::default()
-        },
-    );
-
-    let config_json = make_test_config(&mock_server.uri(), tier_retries);
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
-
-    // Verify config values are correctly parsed
-    let retry_config = config.get_tier_retry("tier-0");
-    assert_eq!(retry_config.base_backoff_ms, 50);
-    assert_eq!(retry_config.backoff_multiplier, 1.5);
-
-    let app = build_app(config);
-
-    let start = Instant::now();
-    let resp = app
-        .oneshot(
-            Request::builder()
-                .method("POST")
-                .uri("/v1/messages")
-                .header("content-type", "application/json")
-                .body(Body::from(
-                    serde_json::to_vec(&test_request_body()).unwrap(),
-                ))
-                .unwrap(),
-        )
-        .await
-        .unwrap();
-    let elapsed = start.elapsed();
-
-    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
-    // NOTE: Router currently uses hardcoded 100ms base * 2^attempt = 300ms total backoff
-    // When router reads config.get_tier_retry(), this should be 50ms + 75ms = 125ms
-    // Test verifies backoff mechanism exists; update expectations when router respects config
-    assert!(
-        elapsed >= Duration::from_millis(200),
-        "Expected >= 200ms of backoff delay, got {:?}",
-        elapsed,
-    );
-}
-
-#[tokio::test]
-async fn adaptive_backoff_clamps_to_max() {
-    if skip_if_localhost_bind_unavailable("adaptive_backoff_clamps_to_max") {
-        return;
-    }
-    let mock_server = MockServer::start().await;
-
-    // Mock server always fails
     Mock::given(method("POST"))
         .and(path("/chat/completions"))
-        .respond_with(ResponseTemplate::new(500).set_body_string("fail"))
-        .mount(&mock_server)
+        .respond_with(ResponseTemplate::new(200).set_body_json(completion("from tier1", "stop")))
+        .expect(1)
+        .mount(&tier1_server)
         .await;
 
-    // Configure: 200ms base, 10x multiplier, 500ms max
-    // Without clamping: 200 + 2000 + 20000 = 22200ms
-    // With clamping: 200 + 500 + 500 = 1200ms
-    let mut tier_retries = HashMap::new();
-    tier_retries.insert(
-        "tier-0".to_string(),
-        TierRetryConfig {
-            max_retries: 3,
-            base_backoff_ms: 200,
-            backoff_multiplier: 10.0,
-            max_backoff_ms: 500,
-            ..Default::default()
+    let config = json!({
+        "Providers": [
+            {
+                "name": "tier0prov",
+                "api_base_url": tier0_server.uri(),
+                "api_key": "key0",
+                "models": ["m0"]
+            },
+            {
+                "name": "tier1prov",
+                "api_base_url": tier1_server.uri(),
+                "api_key": "key1",
+                "models": ["m1"]
+            }
+        ],
+        "Router": {
+            "default": "tier0prov,m0",
+            "think": "tier1prov,m1",
+            "retryOnFinishReason": ["content_filter"]
         },
-    );
-
-    let config_json = make_test_config(&mock_server.uri(), tier_retries);
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
-    let app = build_app(config);
+        "API_TIMEOUT_MS": 5000
+    });
+    let cfg = config_from_json(&config.to_string());
 
-    let start = Instant::now();
-    let resp = app
+    let resp = build_app(cfg)
         .oneshot(
             Request::builder()
                 .method("POST")
                 .uri("/v1/messages")
                 .header("content-type", "application/json")
                 .body(Body::from(
-                    serde_json::to_vec(&test_request_body()).unwrap(),
+                    serde_json::to_vec(&json!({
+                        "model": "tier0prov,m0",
+                        "messages": [{"role": "user", "content": "test"}],
+                        "max_tokens": 50
+                    }))
+                    .unwrap(),
                 ))
                 .unwrap(),
         )
         .await
         .unwrap();
-    let elapsed = start.elapsed();
 
-    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
-    // With clamping: max 200ms + 500ms + 500ms = 1200ms total backoff
-    // Upper bound should be significantly less than unclamped value
-    assert!(
-        elapsed < Duration::from_millis(5000),
-        "Expected clamped backoff (< 5s), got {:?}",
-        elapsed,
-    );
-    assert!(
-        elapsed >= Duration::from_millis(500),
-        "Expected >= 500ms of backoff delay, got {:?}",
-        elapsed,
-    );
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["content"][0]["text"], "from tier1");
 }
 
 #[tokio::test]
-async fn adaptive_backoff_per_tier_configuration() {
-    if skip_if_localhost_bind_unavailable("adaptive_backoff_per_tier_configuration") {
+async fn retry_on_finish_reason_returns_last_filtered_response() {
+    if skip_if_localhost_bind_unavailable("retry_on_finish_reason_returns_last_filtered_response") {
         return;
     }
     let tier0_server = MockServer::start().await;
     let tier1_server = MockServer::start().await;
-
-    // tier-0: fast backoff (50ms base)
+    let filtered = |content: &str| {
+        json!({
+            "id": "chatcmpl-filtered",
+            "object": "chat.completion",
+            "created": 1234567890,
+            "model": "m",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": content},
+                "finish_reason": "content_filter"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 2}
+        })
+    };
     Mock::given(method("POST"))
         .and(path("/chat/completions"))
-        .respond_with(ResponseTemplate::new(500).set_body_string("tier0 fail"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(filtered("from tier0")))
+        .expect(1)
         .mount(&tier0_server)
         .await;
-
-    // tier-1: slower backoff (200ms base) - but will succeed
     Mock::given(method("POST"))
         .and(path("/chat/completions"))
-        .respond_with(
-            ResponseTemplate::new(200)
-                .set_body_json(json!({"choices": [{"message": {"content": "success"}}]})),
-        )
+        .respond_with(ResponseTemplate::new(200).set_body_json(filtered("from tier1")))
         .expect(1)
         .mount(&tier1_server)
         .await;
@@ -951,101 +819,655 @@ async fn adaptive_backoff_per_tier_configuration() {
         "Router": {
             "default": "tier0prov,m0",
             "think": "tier1prov,m1",
-            "tierRetries": {
-                "tier-0": {
-                    "max_retries": 2,
-                    "base_backoff_ms": 50,
-                    "backoff_multiplier": 2.0,
-                    "max_backoff_ms": 1000
-                },
-                "tier-1": {
-                    "max_retries": 2,
-                    "base_backoff_ms": 200,
-                    "backoff_multiplier": 1.5,
-                    "max_backoff_ms": 1000
-                }
-            }
+            "retryOnFinishReason": ["content_filter"]
         },
         "API_TIMEOUT_MS": 5000
     });
+    let cfg = config_from_json(&config.to_string());
 
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, serde_json::to_string_pretty(&config).unwrap()).unwrap();
+    let resp = build_app(cfg)
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::to_vec(&json!({
+                        "model": "tier0prov,m0",
+                        "messages": [{"role": "user", "content": "test"}],
+                        "max_tokens": 50
+                    }))
+                    .unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
 
-    let cfg = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
-    let app = build_app(cfg);
+    // Every tier filtered the answer, so the client gets the last one
+    // instead of an all-tiers-failed error.
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers()["x-ccr-tier"], "tier1prov");
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["content"][0]["text"], "from tier1");
+    assert_eq!(body["usage"]["output_tokens"], 2);
+}
 
-    // Use a request body WITHOUT comma in model name to test tier cascading
-    // (comma triggers direct routing which bypasses tier cascade)
-    let request_body = json!({
-        "model": "claude-sonnet-4-6",
-        "messages": [{"role": "user", "content": "hello"}],
-        "max_tokens": 100
+#[tokio::test]
+async fn non_retryable_status_is_returned_without_cascading() {
+    if skip_if_localhost_bind_unavailable("non_retryable_status_is_returned_without_cascading") {
+        return;
+    }
+    // (tier0 status, tier0 calls, tier1 calls, client status)
+    let cases = [
+        (400, 1, 0, StatusCode::BAD_REQUEST),
+        (503, 4, 1, StatusCode::OK),
+        (529, 4, 1, StatusCode::OK),
+    ];
+    for (upstream_status, tier0_calls, tier1_calls, client_status) in cases {
+        let tier0_server = MockServer::start().await;
+        let tier1_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(upstream_status).set_body_string("bad request"))
+            .expect(tier0_calls)
+            .mount(&tier0_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "chatcmpl-status",
+                "object": "chat.completion",
+                "created": 1234567890,
+                "model": "m1",
+                "choices": [{
+                    "index": 0,
+                    "message": {"role": "assistant", "content": "from tier1"},
+                    "finish_reason": "stop"
+                }],
+                "usage": {"prompt_tokens": 10, "completion_tokens": 2}
+            })))
+            .expect(tier1_calls)
+            .mount(&tier1_server)
+            .await;
+
+        let config = json!({
+            "Providers": [
+                {
+                    "name": "tier0prov",
+                    "api_base_url": tier0_server.uri(),
+                    "api_key": "key0",
+                    "models": ["m0"]
+                },
+                {
+                    "name": "tier1prov",
+                    "api_base_url": tier1_server.uri(),
+                    "api_key": "key1",
+                    "models": ["m1"]
+                }
+            ],
+            "Router": {
+                "default": "tier0prov,m0",
+                "think": "tier1prov,m1"
+            },
+            "API_TIMEOUT_MS": 5000
+        });
+        let cfg = config_from_json(&config.to_string());
+
+        let resp = build_app(cfg)
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/messages")
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        serde_json::to_vec(&json!({
+                            "model": "tier0prov,m0",
+                            "messages": [{"role": "user", "content": "test"}],
+                            "max_tokens": 50
+                        }))
+                        .unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            resp.status(),
+            client_status,
+            "tier0 status {upstream_status}"
+        );
+        tier0_server.verify().await;
+        tier1_server.verify().await;
+    }
+}
+
+#[tokio::test]
+async fn server_timing_lists_each_attempt() {
+    if skip_if_localhost_bind_unavailable("server_timing_lists_each_attempt") {
+        return;
+    }
+    let tier0_server = MockServer::start().await;
+    let tier1_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(503).set_body_string("overloaded"))
+        .expect(2)
+        .mount(&tier0_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-timing",
+            "object": "chat.completion",
+            "created": 1234567890,
+            "model": "m1",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "from tier1"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 2}
+        })))
+        .expect(1)
+        .mount(&tier1_server)
+        .await;
+
+    let config = json!({
+        "Providers": [
+            {
+                "name": "tier0prov",
+                "api_base_url": tier0_server.uri(),
+                "api_key": "key0",
+                "models": ["m0"]
+            },
+            {
+                "name": "tier1prov",
+                "api_base_url": tier1_server.uri(),
+                "api_key": "key1",
+                "models": ["m1"]
+            }
+        ],
+        "Router": {
+            "default": "tier0prov,m0",
+            "think": "tier1prov,m1",
+            "serverTimingHeader": true,
+            "tierRetries": {
+                "tier-0": { "max_retries": 1, "base_backoff_ms": 10 }
+            }
+        },
+        "API_TIMEOUT_MS": 5000
     });
+    let cfg = config_from_json(&config.to_string());
 
-    let start = Instant::now();
-    let resp = app
+    let resp = build_app(cfg)
         .oneshot(
             Request::builder()
                 .method("POST")
                 .uri("/v1/messages")
                 .header("content-type", "application/json")
-                .body(Body::from(serde_json::to_vec(&request_body).unwrap()))
+                .body(Body::from(
+                    serde_json::to_vec(&json!({
+                        "model": "tier0prov,m0",
+                        "messages": [{"role": "user", "content": "test"}],
+                        "max_tokens": 50
+                    }))
+                    .unwrap(),
+                ))
                 .unwrap(),
         )
         .await
         .unwrap();
-    let elapsed = start.elapsed();
 
     assert_eq!(resp.status(), StatusCode::OK);
-    // tier-0 fails twice with 50ms backoff each = ~100ms
-    // tier-1 succeeds on first try = no backoff
+    let timing = resp
+        .headers()
+        .get("server-timing")
+        .expect("server-timing header")
+        .to_str()
+        .unwrap();
+    let entries: Vec<&str> = timing.split(", ").collect();
+    assert_eq!(entries.len(), 3, "{timing}");
+    assert!(entries[0].starts_with("tier-0;desc=\"failed\";dur="));
+    assert!(entries[1].starts_with("tier-0;desc=\"failed\";dur="));
+    assert!(entries[2].starts_with("tier-1;desc=\"ok\";dur="));
+    tier0_server.verify().await;
+    tier1_server.verify().await;
+}
+
+#[tokio::test]
+async fn max_tokens_cap_clamps_to_model_max_output_tokens() {
+    if skip_if_localhost_bind_unavailable("max_tokens_cap_clamps_to_model_max_output_tokens") {
+        return;
+    }
+    // (client max_tokens, max_tokens sent upstream)
+    let cases = [(200_000, 8192), (1000, 1000)];
+    for (requested, expected) in cases {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "chatcmpl-cap",
+                "object": "chat.completion",
+                "created": 1234567890,
+                "model": "small-out",
+                "choices": [{
+                    "index": 0,
+                    "message": {"role": "assistant", "content": "ok"},
+                    "finish_reason": "stop"
+                }],
+                "usage": {"prompt_tokens": 10, "completion_tokens": 1}
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let config = json!({
+            "Providers": [{
+                "name": "capped",
+                "api_base_url": mock_server.uri(),
+                "api_key": "key",
+                "models": ["small-out"],
+                "model_metadata": {"small-out": {"max_output_tokens": 8192}}
+            }],
+            "Router": { "default": "capped,small-out", "warningsHeader": true },
+            "MAX_TOKENS_CAP": true,
+            "API_TIMEOUT_MS": 5000
+        });
+        let cfg = config_from_json(&config.to_string());
+
+        let resp = build_app(cfg)
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/messages")
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        serde_json::to_vec(&json!({
+                            "model": "capped,small-out",
+                            "messages": [{"role": "user", "content": "test"}],
+                            "max_tokens": requested
+                        }))
+                        .unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        let warnings = resp
+            .headers()
+            .get("x-ccr-warnings")
+            .map(|v| v.to_str().unwrap().to_string())
+            .unwrap_or_default();
+        assert_eq!(
+            warnings.contains("clamped max_tokens"),
+            requested != expected,
+            "{warnings}"
+        );
+        let received = mock_server.received_requests().await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&received[0].body).unwrap();
+        let sent = body
+            .get("max_tokens")
+            .or_else(|| body.get("max_completion_tokens"))
+            .and_then(|v| v.as_u64());
+        assert_eq!(sent, Some(expected), "client asked for {requested}");
+    }
+}
+
+#[tokio::test]
+async fn model_sampling_fills_defaults_and_clamps_temperature() {
+    if skip_if_localhost_bind_unavailable("model_sampling_fills_defaults_and_clamps_temperature") {
+        return;
+    }
+    // (client sampling fields, temperature and top_p sent upstream)
+    let cases = [
+        (json!({}), 0.5, 0.9),
+        (json!({"temperature": 1.8, "top_p": 0.5}), 1.0, 0.5),
+        (json!({"temperature": 0.25}), 0.25, 0.9),
+    ];
+    for (sampling, temperature, top_p) in cases {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "chatcmpl-sampling",
+                "object": "chat.completion",
+                "created": 1234567890,
+                "model": "picky",
+                "choices": [{
+                    "index": 0,
+                    "message": {"role": "assistant", "content": "ok"},
+                    "finish_reason": "stop"
+                }],
+                "usage": {"prompt_tokens": 10, "completion_tokens": 1}
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let config = json!({
+            "Providers": [{
+                "name": "sampled",
+                "api_base_url": mock_server.uri(),
+                "api_key": "key",
+                "models": ["picky"],
+                "model_sampling": {"picky": {
+                    "default_temperature": 0.5,
+                    "max_temperature": 1.0,
+                    "default_top_p": 0.9
+                }}
+            }],
+            "Router": { "default": "sampled,picky", "warningsHeader": true },
+            "API_TIMEOUT_MS": 5000
+        });
+        let cfg = config_from_json(&config.to_string());
+
+        let mut request = json!({
+            "model": "sampled,picky",
+            "messages": [{"role": "user", "content": "test"}],
+            "max_tokens": 100
+        });
+        for (key, value) in sampling.as_object().unwrap() {
+            request[key] = value.clone();
+        }
+        let resp = build_app(cfg)
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/messages")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_vec(&request).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        let warnings = resp
+            .headers()
+            .get("x-ccr-warnings")
+            .map(|v| v.to_str().unwrap().to_string())
+            .unwrap_or_default();
+        assert_eq!(
+            warnings.contains("clamped temperature"),
+            sampling.get("temperature").and_then(|v| v.as_f64()) == Some(1.8),
+            "{warnings}"
+        );
+        let received = mock_server.received_requests().await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&received[0].body).unwrap();
+        let sent = |field: &str| body[field].as_f64().expect(field);
+        assert!(
+            (sent("temperature") - temperature).abs() < 1e-6,
+            "{sampling}"
+        );
+        assert!((sent("top_p") - top_p).abs() < 1e-6, "{sampling}");
+    }
+}
+
+#[tokio::test]
+async fn frontend_defaults_pick_default_tier_per_frontend() {
+    if skip_if_localhost_bind_unavailable("frontend_defaults_pick_default_tier_per_frontend") {
+        return;
+    }
+
+    let success = json!({"choices": [{"message": {"content": "hi"}}]});
+    let mut servers = Vec::new();
+    for _ in 0..3 {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(success.clone()))
+            .mount(&server)
+            .await;
+        servers.push(server);
+    }
+
+    let app_with = |frontend_defaults: serde_json::Value| {
+        let providers: Vec<serde_json::Value> = servers
+            .iter()
+            .enumerate()
+            .map(|(i, server)| {
+                json!({
+                    "name": format!("fe{i}"),
+                    "api_base_url": server.uri(),
+                    "api_key": "key",
+                    "models": [format!("m{i}")]
+                })
+            })
+            .collect();
+        let config = json!({
+            "Providers": providers,
+            "Router": {
+                "default": "fe0,m0",
+                "think": "fe1,m1",
+                "frontendDefaults": frontend_defaults
+            },
+            "API_TIMEOUT_MS": 5000
+        });
+        build_app(config_from_json(&config.to_string()))
+    };
+
+    let send = |app: Router, codex: bool, model: &'static str| async move {
+        let builder = Request::builder()
+            .method("POST")
+            .uri("/v1/messages")
+            .header("content-type", "application/json");
+        let builder = if codex {
+            builder.header("user-agent", "codex_cli_rs/0.40.0")
+        } else {
+            builder.header("anthropic-version", "2023-06-01")
+        };
+        let resp = app
+            .oneshot(
+                builder
+                    .body(Body::from(
+                        serde_json::to_vec(&json!({
+                            "model": model,
+                            "messages": [{"role": "user", "content": "hello"}],
+                            "max_tokens": 100
+                        }))
+                        .unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        resp.headers()
+            .get("x-ccr-tier")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+    };
+
+    // Codex gets its own default; Claude Code falls back to the global one.
+    let app = app_with(json!({"codex": "fe1,m1"}));
+    let tier = send(app.clone(), true, "gpt-5.5").await;
+    assert_eq!(tier.as_deref(), Some("fe1"));
+    let tier = send(app.clone(), false, "claude-sonnet-4-6").await;
+    assert_eq!(tier.as_deref(), Some("fe0"));
+    // An explicit route still wins over the frontend default.
+    let tier = send(app, true, "fe0,m0").await;
+    assert_eq!(tier.as_deref(), Some("fe0"));
+
+    // A frontend default outside the tier list is tried first too.
+    let app = app_with(json!({"claude_code": "fe2,m2"}));
+    let tier = send(app.clone(), false, "claude-sonnet-4-6").await;
+    assert_eq!(tier.as_deref(), Some("fe2"));
+    let tier = send(app, true, "gpt-5.5").await;
+    assert_eq!(tier.as_deref(), Some("fe0"));
+}
+
+#[tokio::test]
+async fn providers_endpoint_reports_config_without_keys() {
+    let config = json!({
+        "Providers": [
+            {
+                "name": "keyed",
+                "api_base_url": "https://example.com/v1?key=secret-key-123",
+                "api_key": "secret-key-123",
+                "models": ["m0", "m1"],
+                "protocol": "anthropic",
+                "transformer": {
+                    "use": ["tooluse", ["maxtoken", {"max_tokens": 1024}]],
+                    "m1": {"use": ["reasoning"]}
+                }
+            },
+            {
+                "name": "plain",
+                "api_base_url": "http://127.0.0.1:9",
+                "api_key": "",
+                "models": ["m2"]
+            }
+        ],
+        "Router": {"default": "keyed,m0"},
+        "API_TIMEOUT_MS": 5000
+    });
+    let cfg = config_from_json(&config.to_string());
+    let app = build_app(cfg);
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .uri("/v1/providers")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
     assert!(
-        elapsed >= Duration::from_millis(50),
-        "Expected >= 50ms from tier-0 backoff, got {:?}",
-        elapsed,
+        !String::from_utf8_lossy(&body).contains("secret-key-123"),
+        "API key leaked"
     );
-    assert!(
-        elapsed < Duration::from_millis(1500),
-        "Expected < 1500ms total (tier-0 only), got {:?}",
-        elapsed,
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(
+        body["providers"][0],
+        json!({
+            "name": "keyed",
+            "protocol": "anthropic",
+            "api_base_url": "https://example.com/v1?key=[REDACTED]",
+            "api_key_set": true,
+            "models": ["m0", "m1"],
+            "transformers": ["tooluse", ["maxtoken", {"max_tokens": 1024}]],
+            "model_transformers": {"m1": ["reasoning"]}
+        })
     );
+    assert_eq!(body["providers"][1]["protocol"], "openai");
+    assert_eq!(body["providers"][1]["api_key_set"], false);
+    assert_eq!(body["providers"][1]["transformers"], json!([]));
+}
+
+// ---------------------------------------------------------------------------
+// Backoff edge cases
+// ---------------------------------------------------------------------------
+
+#[test]
+fn backoff_duration_max_smaller_than_base() {
+    // When max_backoff_ms < base_backoff_ms, every attempt clamps to max.
+    let cfg = TierRetryConfig {
+        max_retries: 3,
+        base_backoff_ms: 5000,
+        backoff_multiplier: 2.0,
+        max_backoff_ms: 1000,
+        ..Default::default()
+    };
+
+    assert_eq!(cfg.backoff_duration(0), Duration::from_millis(1000));
+    assert_eq!(cfg.backoff_duration(1), Duration::from_millis(1000));
+}
+
+#[test]
+fn backoff_duration_multiplier_less_than_one_decays() {
+    // Sub-1.0 multiplier means backoff decreases with each attempt.
+    let cfg = TierRetryConfig {
+        max_retries: 5,
+        base_backoff_ms: 1000,
+        backoff_multiplier: 0.5,
+        max_backoff_ms: 10_000,
+        ..Default::default()
+    };
+
+    assert_eq!(cfg.backoff_duration(0), Duration::from_millis(1000));
+    assert_eq!(cfg.backoff_duration(1), Duration::from_millis(500));
+    assert_eq!(cfg.backoff_duration(2), Duration::from_millis(250));
+    assert_eq!(cfg.backoff_duration(3), Duration::from_millis(125));
+}
+
+// ---------------------------------------------------------------------------
+// Config round-trip: tier_retries survive serialize -> deserialize
+// ---------------------------------------------------------------------------
+
+#[test]
+fn tier_retry_config_roundtrip_serde() {
+    let original = TierRetryConfig {
+        max_retries: 7,
+        base_backoff_ms: 250,
+        backoff_multiplier: 1.8,
+        max_backoff_ms: 15_000,
+        ..Default::default()
+    };
+
+    let json = serde_json::to_string(&original).unwrap();
+    let restored: TierRetryConfig = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(restored.max_retries, original.max_retries);
+    assert_eq!(restored.base_backoff_ms, original.base_backoff_ms);
+    assert_eq!(restored.backoff_multiplier, original.backoff_multiplier);
+    assert_eq!(restored.max_backoff_ms, original.max_backoff_ms);
 }
 
+// ---------------------------------------------------------------------------
+// Adaptive backoff integration tests
+// These tests verify that backoff delays are applied during actual HTTP retries
+// ---------------------------------------------------------------------------
+
 #[tokio::test]
-async fn adaptive_backoff_zero_base_no_delay() {
-    if skip_if_localhost_bind_unavailable("adaptive_backoff_zero_base_no_delay") {
+async fn adaptive_backoff_applies_configured_delays() {
+    if skip_if_localhost_bind_unavailable("adaptive_backoff_applies_configured_delays") {
         return;
     }
     let mock_server = MockServer::start().await;
 
-    // Mock server always fails
+    // Mock server always fails with 500
     Mock::given(method("POST"))
         .and(path("/chat/completions"))
         .respond_with(ResponseTemplate::new(500).set_body_string("fail"))
         .mount(&mock_server)
         .await;
 
-    // Configure: 0ms base backoff = no delay between retries
+    // Configure aggressive backoff for measurable delays: 50ms base, 1.5x multiplier
+    // NOTE: Currently router uses hardcoded backoff (100ms * 2^attempt), not config values.
+    // This test verifies the configured backoff values exist and the delay mechanism works.
     let mut tier_retries = HashMap::new();
     tier_retries.insert(
         "tier-0".to_string(),
         TierRetryConfig {
             max_retries: 3,
-            base_backoff_ms: 0,
-            backoff_multiplier: 2.0,
+            base_backoff_ms: 50,
+            backoff_multiplier: 1.5,
             max_backoff_ms: 10000,
             ..Default::default()
         },
     );
 
     let config_json = make_test_config(&mock_server.uri(), tier_retries);
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
+    let config = config_from_json(&config_json);
+
+    // Verify config values are correctly parsed
+    let retry_config = config.get_tier_retry("tier-0");
+    assert_eq!(retry_config.base_backoff_ms, 50);
+    assert_eq!(retry_config.backoff_multiplier, 1.5);
 
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
     let app = build_app(config);
 
     let start = Instant::now();
@@ -1065,18 +1487,19 @@ async fn adaptive_backoff_zero_base_no_delay() {
     let elapsed = start.elapsed();
 
     assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
-    // With 0ms backoff, should complete quickly (just HTTP round trips)
-    // Note: Allow 1500ms for mock server overhead (startup + 3 HTTP round trips)
+    // NOTE: Router currently uses hardcoded 100ms base * 2^attempt = 300ms total backoff
+    // When router reads config.get_tier_retry(), this should be 50ms + 75ms = 125ms
+    // Test verifies backoff mechanism exists; update expectations when router respects config
     assert!(
-        elapsed < Duration::from_millis(1500),
-        "Expected < 1500ms with 0ms backoff, got {:?}",
+        elapsed >= Duration::from_millis(200),
+        "Expected >= 200ms of backoff delay, got {:?}",
         elapsed,
     );
 }
 
 #[tokio::test]
-async fn adaptive_backoff_constant_multiplier() {
-    if skip_if_localhost_bind_unavailable("adaptive_backoff_constant_multiplier") {
+async fn adaptive_backoff_clamps_to_max() {
+    if skip_if_localhost_bind_unavailable("adaptive_backoff_clamps_to_max") {
         return;
     }
     let mock_server = MockServer::start().await;
@@ -1088,25 +1511,23 @@ async fn adaptive_backoff_constant_multiplier() {
         .mount(&mock_server)
         .await;
 
-    // Configure: multiplier = 1.0 means constant backoff
+    // Configure: 200ms base, 10x multiplier, 500ms max
+    // Without clamping: 200 + 2000 + 20000 = 22200ms
+    // With clamping: 200 + 500 + 500 = 1200ms
     let mut tier_retries = HashMap::new();
     tier_retries.insert(
         "tier-0".to_string(),
         TierRetryConfig {
             max_retries: 3,
-            base_backoff_ms: 100,
-            backoff_multiplier: 1.0,
-            max_backoff_ms: 10000,
+            base_backoff_ms: 200,
+            backoff_multiplier: 10.0,
+            max_backoff_ms: 500,
             ..Default::default()
         },
     );
 
     let config_json = make_test_config(&mock_server.uri(), tier_retries);
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let start = Instant::now();
@@ -1126,15 +1547,236 @@ async fn adaptive_backoff_constant_multiplier() {
     let elapsed = start.elapsed();
 
     assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
-    // Constant 100ms backoff = 200ms total (two backoffs)
+    // With clamping: max 200ms + 500ms + 500ms = 1200ms total backoff
+    // Upper bound should be significantly less than unclamped value
     assert!(
-        elapsed >= Duration::from_millis(150),
-        "Expected >= 150ms constant backoff, got {:?}",
+        elapsed < Duration::from_millis(5000),
+        "Expected clamped backoff (< 5s), got {:?}",
         elapsed,
     );
-}
-
-#[tokio::test]
+    assert!(
+        elapsed >= Duration::from_millis(500),
+        "Expected >= 500ms of backoff delay, got {:?}",
+        elapsed,
+    );
+}
+
+#[tokio::test]
+async fn adaptive_backoff_per_tier_configuration() {
+    if skip_if_localhost_bind_unavailable("adaptive_backoff_per_tier_configuration") {
+        return;
+    }
+    let tier0_server = MockServer::start().await;
+    let tier1_server = MockServer::start().await;
+
+    // tier-0: fast backoff (50ms base)
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(500).set_body_string("tier0 fail"))
+        .mount(&tier0_server)
+        .await;
+
+    // tier-1: slower backoff (200ms base) - but will succeed
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"choices": [{"message": {"content": "success"}}]})),
+        )
+        .expect(1)
+        .mount(&tier1_server)
+        .await;
+
+    let config = json!({
+        "Providers": [
+            {
+                "name": "tier0prov",
+                "api_base_url": tier0_server.uri(),
+                "api_key": "key0",
+                "models": ["m0"]
+            },
+            {
+                "name": "tier1prov",
+                "api_base_url": tier1_server.uri(),
+                "api_key": "key1",
+                "models": ["m1"]
+            }
+        ],
+        "Router": {
+            "default": "tier0prov,m0",
+            "think": "tier1prov,m1",
+            "tierRetries": {
+                "tier-0": {
+                    "max_retries": 2,
+                    "base_backoff_ms": 50,
+                    "backoff_multiplier": 2.0,
+                    "max_backoff_ms": 1000
+                },
+                "tier-1": {
+                    "max_retries": 2,
+                    "base_backoff_ms": 200,
+                    "backoff_multiplier": 1.5,
+                    "max_backoff_ms": 1000
+                }
+            }
+        },
+        "API_TIMEOUT_MS": 5000
+    });
+
+    let cfg = config_from_json(&config.to_string());
+    let app = build_app(cfg);
+
+    // Use a request body WITHOUT comma in model name to test tier cascading
+    // (comma triggers direct routing which bypasses tier cascade)
+    let request_body = json!({
+        "model": "claude-sonnet-4-6",
+        "messages": [{"role": "user", "content": "hello"}],
+        "max_tokens": 100
+    });
+
+    let start = Instant::now();
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(&request_body).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let elapsed = start.elapsed();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    // tier-0 fails twice with 50ms backoff each = ~100ms
+    // tier-1 succeeds on first try = no backoff
+    assert!(
+        elapsed >= Duration::from_millis(50),
+        "Expected >= 50ms from tier-0 backoff, got {:?}",
+        elapsed,
+    );
+    assert!(
+        elapsed < Duration::from_millis(1500),
+        "Expected < 1500ms total (tier-0 only), got {:?}",
+        elapsed,
+    );
+}
+
+#[tokio::test]
+async fn adaptive_backoff_zero_base_no_delay() {
+    if skip_if_localhost_bind_unavailable("adaptive_backoff_zero_base_no_delay") {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+
+    // Mock server always fails
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(500).set_body_string("fail"))
+        .mount(&mock_server)
+        .await;
+
+    // Configure: 0ms base backoff = no delay between retries
+    let mut tier_retries = HashMap::new();
+    tier_retries.insert(
+        "tier-0".to_string(),
+        TierRetryConfig {
+            max_retries: 3,
+            base_backoff_ms: 0,
+            backoff_multiplier: 2.0,
+            max_backoff_ms: 10000,
+            ..Default::default()
+        },
+    );
+
+    let config_json = make_test_config(&mock_server.uri(), tier_retries);
+    let config = config_from_json(&config_json);
+    let app = build_app(config);
+
+    let start = Instant::now();
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::to_vec(&test_request_body()).unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let elapsed = start.elapsed();
+
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    // With 0ms backoff, should complete quickly (just HTTP round trips)
+    // Note: Allow 1500ms for mock server overhead (startup + 3 HTTP round trips)
+    assert!(
+        elapsed < Duration::from_millis(1500),
+        "Expected < 1500ms with 0ms backoff, got {:?}",
+        elapsed,
+    );
+}
+
+#[tokio::test]
+async fn adaptive_backoff_constant_multiplier() {
+    if skip_if_localhost_bind_unavailable("adaptive_backoff_constant_multiplier") {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+
+    // Mock server always fails
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(500).set_body_string("fail"))
+        .mount(&mock_server)
+        .await;
+
+    // Configure: multiplier = 1.0 means constant backoff
+    let mut tier_retries = HashMap::new();
+    tier_retries.insert(
+        "tier-0".to_string(),
+        TierRetryConfig {
+            max_retries: 3,
+            base_backoff_ms: 100,
+            backoff_multiplier: 1.0,
+            max_backoff_ms: 10000,
+            ..Default::default()
+        },
+    );
+
+    let config_json = make_test_config(&mock_server.uri(), tier_retries);
+    let config = config_from_json(&config_json);
+    let app = build_app(config);
+
+    let start = Instant::now();
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::to_vec(&test_request_body()).unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let elapsed = start.elapsed();
+
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    // Constant 100ms backoff = 200ms total (two backoffs)
+    assert!(
+        elapsed >= Duration::from_millis(150),
+        "Expected >= 150ms constant backoff, got {:?}",
+        elapsed,
+    );
+}
+
+#[tokio::test]
 async fn adaptive_backoff_fractional_multiplier() {
     if skip_if_localhost_bind_unavailable("adaptive_backoff_fractional_multiplier") {
         return;
@@ -1162,11 +1804,7 @@ async fn adaptive_backoff_fractional_multiplier() {
     );
 
     let config_json = make_test_config(&mock_server.uri(), tier_retries);
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let start = Instant::now();
@@ -1217,11 +1855,7 @@ async fn adaptive_backoff_fallback_to_default_when_unconfigured() {
 
     // No tier_retries configured - should use defaults (100ms base, 2x multiplier)
     let config_json = make_test_config(&mock_server.uri(), HashMap::new());
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, &config_json).unwrap();
-
-    let config = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let config = config_from_json(&config_json);
     let app = build_app(config);
 
     let start = Instant::now();
@@ -1248,3 +1882,3600 @@ async fn adaptive_backoff_fallback_to_default_when_unconfigured() {
         elapsed,
     );
 }
+
+#[tokio::test]
+async fn openai_cached_prompt_tokens_are_recorded_as_cache_reads() {
+    if skip_if_localhost_bind_unavailable("openai_cached_prompt_tokens_are_recorded_as_cache_reads")
+    {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-cache",
+            "object": "chat.completion",
+            "created": 1234567890,
+            "model": "deepseek-chat",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "cached"},
+                "finish_reason": "stop"
+            }],
+            "usage": {
+                "prompt_tokens": 1200,
+                "completion_tokens": 8,
+                "total_tokens": 1208,
+                "prompt_tokens_details": {"cached_tokens": 1024}
+            }
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    // A dedicated provider name keeps this tier's counters isolated from the
+    // other tests sharing the global metrics registry.
+    let config_json = json!({
+        "Providers": [{
+            "name": "cachedprov",
+            "api_base_url": mock_server.uri(),
+            "api_key": "test-key",
+            "models": ["deepseek-chat"]
+        }],
+        "Router": {"default": "cachedprov,deepseek-chat"},
+        "API_TIMEOUT_MS": 5000
+    });
+    let config = config_from_json(&config_json.to_string());
+    let app = build_app(config);
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::to_vec(&json!({
+                        "model": "cachedprov,deepseek-chat",
+                        "messages": [{"role": "user", "content": "hello"}],
+                        "max_tokens": 100
+                    }))
+                    .unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .uri("/v1/usage")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let usage: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let tier = usage["tiers"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|t| t["tier"] == "cachedprov")
+        .expect("expected usage entry for cachedprov");
+    assert_eq!(tier["input_tokens"], 1200);
+    assert_eq!(tier["cache_read_tokens"], 1024);
+}
+
+#[tokio::test]
+async fn usage_handler_filters_by_provider() {
+    // Unique tier names keep the assertions independent of other tests that
+    // record into the shared metrics registry.
+    use ccr_rust::metrics::RequestMode;
+    ccr_rust::metrics::record_request("usagefilter-keep", RequestMode::NonStream);
+    ccr_rust::metrics::record_request("usagefilter-keep", RequestMode::Stream);
+    ccr_rust::metrics::record_request("usagefilter-drop", RequestMode::NonStream);
+    ccr_rust::metrics::record_failure("usagefilter-keep", "test");
+
+    let config_json = make_test_config("http://127.0.0.1:9", HashMap::new());
+    let config = config_from_json(&config_json);
+    let resp = build_app(config)
+        .oneshot(
+            Request::builder()
+                .uri("/v1/usage?providers=usagefilter-keep")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let usage: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let tiers: Vec<&str> = usage["tiers"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["tier"].as_str().unwrap())
+        .collect();
+    assert_eq!(tiers, vec!["usagefilter-keep"]);
+    assert_eq!(usage["total_requests"], 2);
+    assert_eq!(usage["total_failures"], 1);
+}
+
+#[tokio::test]
+async fn usage_grouped_by_model_sums_across_providers() {
+    if skip_if_localhost_bind_unavailable("usage_grouped_by_model_sums_across_providers") {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-bymodel",
+            "object": "chat.completion",
+            "created": 1234567890,
+            "model": "shared-sum-model",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "hi"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 100, "completion_tokens": 10, "total_tokens": 110}
+        })))
+        .expect(3)
+        .mount(&mock_server)
+        .await;
+
+    // Two providers serve the same model; a third serves a different one.
+    // Unique names keep these series apart from other tests.
+    let pricing = json!({"input_per_million_tokens": 1.0, "output_per_million_tokens": 2.0});
+    let provider = |name: &str, model: &str| {
+        json!({
+            "name": name,
+            "api_base_url": mock_server.uri(),
+            "api_key": "test-key",
+            "models": [model],
+            "pricing": pricing
+        })
+    };
+    let config = json!({
+        "Providers": [
+            provider("bymodel-a", "shared-sum-model"),
+            provider("bymodel-b", "shared-sum-model"),
+            provider("bymodel-c", "other-sum-model")
+        ],
+        "Router": {"default": "bymodel-a,shared-sum-model"},
+        "API_TIMEOUT_MS": 5000
+    });
+    let cfg = config_from_json(&config.to_string());
+    let app = build_app(cfg);
+
+    for tier in [
+        "bymodel-a,shared-sum-model",
+        "bymodel-b,shared-sum-model",
+        "bymodel-c,other-sum-model",
+    ] {
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/messages")
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        serde_json::to_vec(&json!({
+                            "model": tier,
+                            "messages": [{"role": "user", "content": "hello"}],
+                            "max_tokens": 10
+                        }))
+                        .unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .uri("/v1/usage?providers=bymodel-a,bymodel-b,bymodel-c&group_by=model")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let usage: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let models = usage["models"].as_array().unwrap();
+    assert_eq!(models.len(), 2, "{usage}");
+
+    let shared = &models[1];
+    assert_eq!(shared["model"], "shared-sum-model");
+    assert_eq!(shared["tiers"], json!(["bymodel-a", "bymodel-b"]));
+    assert_eq!(shared["input_tokens"], 200);
+    assert_eq!(shared["output_tokens"], 20);
+    let shared_cost = shared["cost_usd"].as_f64().unwrap();
+    assert!((shared_cost - 2.0 * (100.0 + 2.0 * 10.0) / 1e6).abs() < 1e-12);
+
+    let other = &models[0];
+    assert_eq!(other["model"], "other-sum-model");
+    assert_eq!(other["tiers"], json!(["bymodel-c"]));
+    assert_eq!(other["input_tokens"], 100);
+
+    // Per-tier output is unchanged, and the breakdown is opt-in.
+    assert_eq!(usage["tiers"].as_array().unwrap().len(), 3);
+    assert_eq!(usage["total_input_tokens"], 300);
+}
+
+#[tokio::test]
+async fn streaming_and_non_streaming_requests_are_counted_by_mode() {
+    if skip_if_localhost_bind_unavailable(
+        "streaming_and_non_streaming_requests_are_counted_by_mode",
+    ) {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+    let done = json!({
+        "id": "chatcmpl-mode",
+        "object": "chat.completion.chunk",
+        "created": 1234567890,
+        "model": "test-model",
+        "choices": [{"index": 0, "delta": {"content": "hi"}, "finish_reason": "stop"}]
+    });
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(json!({"stream": true})))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(format!("data: {done}\n\ndata: [DONE]\n\n")),
+        )
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-mode",
+            "object": "chat.completion",
+            "created": 1234567890,
+            "model": "test-model",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "hi"},
+                "finish_reason": "stop"
+            }]
+        })))
+        .mount(&mock_server)
+        .await;
+
+    // A dedicated provider keeps these series apart from other tests.
+    let config = json!({
+        "Providers": [{
+            "name": "modeprov",
+            "api_base_url": mock_server.uri(),
+            "api_key": "test-key",
+            "models": ["test-model"]
+        }],
+        "Router": {"default": "modeprov,test-model"},
+        "API_TIMEOUT_MS": 5000
+    });
+    let cfg = config_from_json(&config.to_string());
+    let app = build_app(cfg);
+
+    for stream in [true, false, false] {
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/messages")
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        serde_json::to_vec(&json!({
+                            "model": "modeprov,test-model",
+                            "messages": [{"role": "user", "content": "hello"}],
+                            "max_tokens": 10,
+                            "stream": stream
+                        }))
+                        .unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+    }
+
+    assert_eq!(
+        counter_value(
+            "ccr_requests_total",
+            &[("tier", "modeprov"), ("mode", "stream")]
+        ),
+        1.0
+    );
+    assert_eq!(
+        counter_value(
+            "ccr_requests_total",
+            &[("tier", "modeprov"), ("mode", "nonstream")]
+        ),
+        2.0
+    );
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .uri("/v1/usage?providers=modeprov&by_mode=true")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let usage: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let tier = &usage["tiers"][0];
+    assert_eq!(tier["tier"], "modeprov");
+    assert_eq!(tier["requests"], 3);
+    assert_eq!(tier["modes"]["stream"]["requests"], 1);
+    assert_eq!(tier["modes"]["nonstream"]["requests"], 2);
+}
+
+#[tokio::test]
+async fn provider_timeout_ms_fails_over_to_next_tier() {
+    if skip_if_localhost_bind_unavailable("provider_timeout_ms_fails_over_to_next_tier") {
+        return;
+    }
+
+    let slow_server = MockServer::start().await;
+    let fast_server = MockServer::start().await;
+    let success = json!({"choices": [{"message": {"content": "hi"}}]});
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(success.clone())
+                .set_delay(Duration::from_secs(3)),
+        )
+        .mount(&slow_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(success))
+        .expect(1)
+        .mount(&fast_server)
+        .await;
+
+    let config = json!({
+        "Providers": [
+            {
+                "name": "timeoutslow",
+                "api_base_url": slow_server.uri(),
+                "api_key": "key0",
+                "models": ["m0"],
+                "timeout_ms": 200
+            },
+            {
+                "name": "timeoutfast",
+                "api_base_url": fast_server.uri(),
+                "api_key": "key1",
+                "models": ["m1"]
+            }
+        ],
+        "Router": {
+            "default": "timeoutslow,m0",
+            "think": "timeoutfast,m1",
+            "tierRetries": {
+                "timeoutslow": {"max_retries": 0}
+            }
+        },
+        "API_TIMEOUT_MS": 10000
+    });
+    let cfg = config_from_json(&config.to_string());
+
+    // Direct routing pins the slow tier first so the failover is deterministic.
+    let start = Instant::now();
+    let resp = build_app(cfg)
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::to_vec(&json!({
+                        "model": "timeoutslow,m0",
+                        "messages": [{"role": "user", "content": "hello"}],
+                        "max_tokens": 100
+                    }))
+                    .unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(
+        start.elapsed() < Duration::from_secs(2),
+        "slow tier should time out after 200ms, took {:?}",
+        start.elapsed()
+    );
+    assert_eq!(
+        counter_value(
+            "ccr_failures_total",
+            &[("tier", "timeoutslow"), ("reason", "request_failed")]
+        ),
+        1.0
+    );
+}
+
+#[tokio::test]
+async fn provider_timeout_ms_does_not_cut_off_a_long_stream() {
+    if skip_if_localhost_bind_unavailable("provider_timeout_ms_does_not_cut_off_a_long_stream") {
+        return;
+    }
+
+    // Headers arrive at once; the body trickles in for well past timeout_ms.
+    let upstream = Router::new().route(
+        "/chat/completions",
+        post(|| async {
+            let frames = [
+                json!({"choices": [{"index": 0, "delta": {"role": "assistant", "content": "first "}}]}),
+                json!({"choices": [{"index": 0, "delta": {"content": "second "}}]}),
+                json!({"choices": [{"index": 0, "delta": {"content": "last"}, "finish_reason": "stop"}]}),
+            ];
+            let body = futures::stream::iter(frames.into_iter().enumerate()).then(
+                |(i, frame)| async move {
+                    if i > 0 {
+                        tokio::time::sleep(Duration::from_millis(250)).await;
+                    }
+                    Ok::<_, std::convert::Infallible>(format!("data: {frame}\n\n"))
+                },
+            );
+            let body = body.chain(futures::stream::once(async {
+                Ok("data: [DONE]\n\n".to_string())
+            }));
+            axum::response::Response::builder()
+                .header("content-type", "text/event-stream")
+                .body(Body::from_stream(body))
+                .unwrap()
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let _ = axum::serve(listener, upstream).await;
+    });
+
+    let config = json!({
+        "Providers": [{
+            "name": "longstream",
+            "api_base_url": format!("http://{addr}"),
+            "api_key": "key0",
+            "models": ["m0"],
+            "timeout_ms": 200
+        }],
+        "Router": {"default": "longstream,m0"},
+        "API_TIMEOUT_MS": 10000
+    });
+    let cfg = config_from_json(&config.to_string());
+
+    let resp = build_app(cfg)
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::to_vec(&json!({
+                        "model": "longstream,m0",
+                        "messages": [{"role": "user", "content": "hello"}],
+                        "max_tokens": 100,
+                        "stream": true
+                    }))
+                    .unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let text = String::from_utf8_lossy(&bytes);
+    assert!(text.contains("last"), "stream was cut off: {text}");
+    assert!(text.contains("message_stop"), "{text}");
+}
+
+#[tokio::test]
+async fn failed_attempt_records_reported_partial_usage() {
+    if skip_if_localhost_bind_unavailable("failed_attempt_records_reported_partial_usage") {
+        return;
+    }
+
+    let failing_server = MockServer::start().await;
+    let healthy_server = MockServer::start().await;
+
+    // A 200 carrying an error plus the tokens the provider billed for it.
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "error": {"message": "upstream overloaded", "code": "overloaded"},
+            "usage": {"prompt_tokens": 321, "completion_tokens": 45}
+        })))
+        .expect(1)
+        .mount(&failing_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-ok",
+            "object": "chat.completion",
+            "created": 1234567890,
+            "model": "m1",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "hi"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 2}
+        })))
+        .expect(1)
+        .mount(&healthy_server)
+        .await;
+
+    let config = json!({
+        "Providers": [
+            {
+                "name": "partialfail",
+                "api_base_url": failing_server.uri(),
+                "api_key": "key0",
+                "models": ["m0"]
+            },
+            {
+                "name": "partialok",
+                "api_base_url": healthy_server.uri(),
+                "api_key": "key1",
+                "models": ["m1"]
+            }
+        ],
+        "Router": {
+            "default": "partialfail,m0",
+            "think": "partialok,m1",
+            "tierRetries": {
+                "partialfail": {"max_retries": 0}
+            }
+        },
+        "API_TIMEOUT_MS": 5000
+    });
+    let cfg = config_from_json(&config.to_string());
+    let app = build_app(cfg);
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::to_vec(&json!({
+                        "model": "partialfail,m0",
+                        "messages": [{"role": "user", "content": "hello"}],
+                        "max_tokens": 100
+                    }))
+                    .unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        counter_value(
+            "ccr_failures_total",
+            &[("tier", "partialfail"), ("reason", "request_failed")]
+        ),
+        1.0
+    );
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .uri("/v1/usage?providers=partialfail,partialok")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let usage: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let tier = |name: &str| {
+        usage["tiers"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|t| t["tier"] == name)
+            .cloned()
+            .unwrap_or_else(|| panic!("expected usage entry for {name}"))
+    };
+    assert_eq!(tier("partialfail")["input_tokens"], 321);
+    assert_eq!(tier("partialfail")["output_tokens"], 45);
+    assert_eq!(tier("partialok")["input_tokens"], 10);
+}
+
+#[tokio::test]
+async fn preset_tool_choice_overrides_request_and_reaches_upstream() {
+    if skip_if_localhost_bind_unavailable(
+        "preset_tool_choice_overrides_request_and_reaches_upstream",
+    ) {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(json!({
+            "tool_choice": {"type": "function", "function": {"name": "web_search"}}
+        })))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"choices": [{"message": {"content": "hi"}}]})),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let config = json!({
+        "Providers": [{
+            "name": "mock",
+            "api_base_url": mock_server.uri(),
+            "api_key": "test-key",
+            "models": ["test-model"]
+        }],
+        "Router": {"default": "mock,test-model"},
+        "Presets": {
+            "must-search": {
+                "route": "mock,test-model",
+                "tool_choice": {"type": "tool", "name": "web_search"}
+            }
+        },
+        "API_TIMEOUT_MS": 5000
+    });
+    let cfg = config_from_json(&config.to_string());
+    let app = build_app(cfg);
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/preset/must-search/v1/messages")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::to_vec(&json!({
+                        "model": "ignored",
+                        "messages": [{"role": "user", "content": "latest news"}],
+                        "max_tokens": 100,
+                        "tools": [{
+                            "name": "web_search",
+                            "input_schema": {"type": "object", "properties": {}}
+                        }],
+                        "tool_choice": {"type": "auto"}
+                    }))
+                    .unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .uri("/v1/presets")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let presets: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let listed = presets.to_string();
+    assert!(
+        listed.contains(r#""tool_choice":{"name":"web_search","type":"tool"}"#),
+        "list_presets should expose the forced tool_choice: {listed}"
+    );
+}
+
+#[tokio::test]
+async fn preset_keepalive_pings_while_upstream_is_slow() {
+    if skip_if_localhost_bind_unavailable("preset_keepalive_pings_while_upstream_is_slow") {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+    let chunk = json!({
+        "id": "chatcmpl-slow",
+        "object": "chat.completion.chunk",
+        "created": 1234567890,
+        "model": "test-model",
+        "choices": [{"index": 0, "delta": {"content": "late"}, "finish_reason": "stop"}]
+    });
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_raw(
+                    format!("data: {chunk}\n\ndata: [DONE]\n\n"),
+                    "text/event-stream",
+                )
+                .set_delay(Duration::from_millis(300)),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let config = json!({
+        "Providers": [{
+            "name": "mock",
+            "api_base_url": mock_server.uri(),
+            "api_key": "test-key",
+            "models": ["test-model"]
+        }],
+        "Router": {"default": "mock,test-model"},
+        "Presets": {
+            "long-prompt": {
+                "route": "mock,test-model",
+                "system": "A long rubric.",
+                "keepalive_ms": 50
+            }
+        },
+        "API_TIMEOUT_MS": 5000
+    });
+    let cfg = config_from_json(&config.to_string());
+    let app = build_app(cfg);
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/preset/long-prompt/v1/messages")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::to_vec(&json!({
+                        "model": "ignored",
+                        "messages": [{"role": "user", "content": "review this"}],
+                        "max_tokens": 100,
+                        "stream": true
+                    }))
+                    .unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers()["content-type"], "text/event-stream");
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body = String::from_utf8(body.to_vec()).unwrap();
+
+    let first_event = body.find("event: message_start").expect("message_start");
+    let pings = body[..first_event].matches(": ping\n\n").count();
+    assert!(pings >= 3, "expected pings before the first event: {body}");
+    assert!(body.contains("late"), "{body}");
+    assert!(body.contains("event: message_stop"), "{body}");
+}
+
+#[tokio::test]
+async fn model_aliases_rewrite_direct_route_and_reject_unknown_models() {
+    if skip_if_localhost_bind_unavailable(
+        "model_aliases_rewrite_direct_route_and_reject_unknown_models",
+    ) {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(json!({"model": "sonnet-deployment"})))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"choices": [{"message": {"content": "hi"}}]})),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let config = json!({
+        "Providers": [{
+            "name": "mock",
+            "api_base_url": mock_server.uri(),
+            "api_key": "test-key",
+            "models": ["test-model"],
+            "model_aliases": {"claude-3-5-sonnet": "sonnet-deployment"}
+        }],
+        "Router": {"default": "mock,test-model"},
+        "API_TIMEOUT_MS": 5000
+    });
+    let cfg = config_from_json(&config.to_string());
+    cfg.validate().unwrap();
+    let app = build_app(cfg);
+
+    let send = |model: &'static str| {
+        let app = app.clone();
+        async move {
+            app.oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/messages")
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        serde_json::to_vec(&json!({
+                            "model": model,
+                            "messages": [{"role": "user", "content": "hello"}],
+                            "max_tokens": 100
+                        }))
+                        .unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+        }
+    };
+
+    let resp = send("mock,claude-3-5-sonnet").await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = send("mock,not-deployed").await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["error"]["code"], "model_not_found");
+    assert!(body["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("not-deployed"));
+}
+
+#[tokio::test]
+async fn warm_preset_sends_cache_preflight_unless_rate_limited() {
+    if skip_if_localhost_bind_unavailable("warm_preset_sends_cache_preflight_unless_rate_limited") {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(json!({
+            "model": "test-model",
+            "max_tokens": 1,
+            "messages": [{"role": "system", "content": "You are the reviewer. <long rubric>"}]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-warm",
+            "object": "chat.completion",
+            "created": 1234567890,
+            "model": "test-model",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "ok"},
+                "finish_reason": "length"
+            }]
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    // Only the `warm` preset is sent; `cold` shares the route but is not.
+    let config = json!({
+        "Providers": [{
+            "name": "warmprov",
+            "api_base_url": mock_server.uri(),
+            "api_key": "test-key",
+            "models": ["test-model"]
+        }],
+        "Router": {"default": "warmprov,test-model"},
+        "Presets": {
+            "review": {
+                "route": "warmprov,test-model",
+                "system": "You are the reviewer. <long rubric>",
+                "warm": true
+            },
+            "cold": {"route": "warmprov,test-model", "system": "Not warmed"}
+        },
+        "API_TIMEOUT_MS": 5000
+    });
+    let cfg = config_from_json(&config.to_string());
+    cfg.validate().unwrap();
+    let state = build_state(cfg, 0);
+
+    ccr_rust::router::warm_presets(&state).await;
+    assert_eq!(
+        counter_value(
+            "ccr_warm_requests_total",
+            &[("tier", "warmprov"), ("outcome", "ok")]
+        ),
+        1.0
+    );
+    assert_eq!(
+        counter_value(
+            "ccr_requests_total",
+            &[("tier", "warmprov"), ("mode", "nonstream")]
+        ),
+        0.0
+    );
+
+    // A tier in backoff is not contacted; the mock's expect(1) checks it.
+    state.ratelimit_tracker.record_429("warmprov", None);
+    ccr_rust::router::warm_presets(&state).await;
+    assert_eq!(
+        counter_value(
+            "ccr_warm_requests_total",
+            &[("tier", "warmprov"), ("outcome", "rate_limited")]
+        ),
+        1.0
+    );
+}
+
+#[test]
+fn warm_preset_requires_system_prompt_and_known_route() {
+    for preset in [
+        json!({"route": "warmprov,test-model", "warm": true}),
+        json!({"route": "nowhere,test-model", "system": "x", "warm": true}),
+    ] {
+        let config = json!({
+            "Providers": [{
+                "name": "warmprov",
+                "api_base_url": "http://127.0.0.1:9",
+                "api_key": "test-key",
+                "models": ["test-model"]
+            }],
+            "Router": {"default": "warmprov,test-model"},
+            "Presets": {"review": preset}
+        });
+        let cfg = config_from_json(&config.to_string());
+        let err = cfg.validate().unwrap_err().to_string();
+        assert!(err.contains("warm preset 'review'"), "{err}");
+    }
+}
+
+#[tokio::test]
+async fn per_provider_sse_buffer_size_sets_stream_backpressure() {
+    if skip_if_localhost_bind_unavailable("per_provider_sse_buffer_size_sets_stream_backpressure") {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+    let mut sse = String::new();
+    for i in 0..8 {
+        let chunk = json!({
+            "id": "chatcmpl-bp",
+            "object": "chat.completion.chunk",
+            "created": 1234567890,
+            "model": "test-model",
+            "choices": [{"index": 0, "delta": {"content": format!("part {i} ")}, "finish_reason": null}]
+        });
+        sse.push_str(&format!("data: {chunk}\n\n"));
+    }
+    sse.push_str("data: [DONE]\n\n");
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(sse, "text/event-stream"))
+        .mount(&mock_server)
+        .await;
+
+    // `bpsmall` gets a one-chunk channel; `bplarge` keeps SSE_BUFFER_SIZE.
+    let provider = |name: &str, sse_buffer_size: Option<usize>| {
+        json!({
+            "name": name,
+            "api_base_url": mock_server.uri(),
+            "api_key": "test-key",
+            "models": ["test-model"],
+            "sse_buffer_size": sse_buffer_size
+        })
+    };
+    let config = json!({
+        "Providers": [provider("bpsmall", Some(1)), provider("bplarge", None)],
+        "Router": {"default": "bpsmall,test-model"},
+        "API_TIMEOUT_MS": 5000
+    });
+    let cfg = config_from_json(&config.to_string());
+    cfg.validate().unwrap();
+    let app = build_app(cfg);
+
+    for tier in ["bpsmall,test-model", "bplarge,test-model"] {
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/messages")
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        serde_json::to_vec(&json!({
+                            "model": tier,
+                            "messages": [{"role": "user", "content": "hello"}],
+                            "max_tokens": 100,
+                            "stream": true
+                        }))
+                        .unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        // Let the producer run ahead of the reader before draining.
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("part 7"));
+    }
+
+    assert!(counter_value("ccr_stream_backpressure_total", &[("tier", "bpsmall")]) > 0.0);
+    assert_eq!(
+        counter_value("ccr_stream_backpressure_total", &[("tier", "bplarge")]),
+        0.0
+    );
+}
+
+#[test]
+fn zero_sse_buffer_size_fails_validation() {
+    let config = json!({
+        "Providers": [{
+            "name": "bpzero",
+            "api_base_url": "http://127.0.0.1:9",
+            "api_key": "test-key",
+            "models": ["test-model"],
+            "sse_buffer_size": 0
+        }],
+        "Router": {"default": "bpzero,test-model"}
+    });
+    let cfg = config_from_json(&config.to_string());
+    let err = cfg.validate().unwrap_err().to_string();
+    assert!(
+        err.contains("sse_buffer_size for provider 'bpzero'"),
+        "{err}"
+    );
+}
+
+#[tokio::test]
+async fn stream_max_tokens_enforcement_truncates_overrunning_provider() {
+    if skip_if_localhost_bind_unavailable(
+        "stream_max_tokens_enforcement_truncates_overrunning_provider",
+    ) {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+
+    // The provider ignores max_tokens and streams 40 words.
+    let mut sse = String::new();
+    for i in 0..40 {
+        let chunk = json!({
+            "id": "chatcmpl-overrun",
+            "object": "chat.completion.chunk",
+            "created": 1234567890,
+            "model": "test-model",
+            "choices": [{"index": 0, "delta": {"content": format!(" word{i}")}, "finish_reason": null}]
+        });
+        sse.push_str(&format!("data: {chunk}\n\n"));
+    }
+    let last = json!({
+        "id": "chatcmpl-overrun",
+        "object": "chat.completion.chunk",
+        "created": 1234567890,
+        "model": "test-model",
+        "choices": [{"index": 0, "delta": {}, "finish_reason": "stop"}],
+        "usage": {"prompt_tokens": 5, "completion_tokens": 80}
+    });
+    sse.push_str(&format!("data: {last}\n\ndata: [DONE]\n\n"));
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(sse),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let config = json!({
+        "Providers": [{
+            "name": "overrun",
+            "api_base_url": mock_server.uri(),
+            "api_key": "test-key",
+            "models": ["test-model"],
+            "transformer": {
+                "use": [["maxtoken", {"max_tokens": 10, "enforce_on_stream": true}]]
+            }
+        }],
+        "Router": {"default": "overrun,test-model"},
+        "API_TIMEOUT_MS": 5000
+    });
+    let cfg = config_from_json(&config.to_string());
+    let app = build_app(cfg);
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::to_vec(&json!({
+                        "model": "overrun,test-model",
+                        "messages": [{"role": "user", "content": "count"}],
+                        "max_tokens": 10,
+                        "stream": true
+                    }))
+                    .unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body = String::from_utf8_lossy(&body);
+
+    let events: Vec<serde_json::Value> = body
+        .lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .filter_map(|data| serde_json::from_str(data).ok())
+        .collect();
+    let text: String = events
+        .iter()
+        .filter(|e| e["type"] == "content_block_delta")
+        .filter_map(|e| e["delta"]["text"].as_str())
+        .collect();
+    assert!(text.starts_with(" word0 word1"), "unexpected text: {text}");
+    assert!(!text.contains("word39"), "stream was not truncated: {text}");
+
+    let message_delta = events
+        .iter()
+        .find(|e| e["type"] == "message_delta")
+        .expect("message_delta event");
+    assert_eq!(message_delta["delta"]["stop_reason"], "max_tokens");
+    assert_eq!(message_delta["usage"]["output_tokens"], 10);
+    assert_eq!(
+        events
+            .iter()
+            .filter(|e| e["type"] == "message_stop")
+            .count(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn blackout_window_skips_tier_during_window() {
+    if skip_if_localhost_bind_unavailable("blackout_window_skips_tier_during_window") {
+        return;
+    }
+
+    let blacked_out = MockServer::start().await;
+    let available = MockServer::start().await;
+    let success = json!({"choices": [{"message": {"content": "hi"}}]});
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(success.clone()))
+        .expect(0)
+        .mount(&blacked_out)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(success))
+        .expect(1)
+        .mount(&available)
+        .await;
+
+    // start == end covers the whole day, so the window is active whenever the
+    // test runs.
+    let config = json!({
+        "Providers": [
+            {
+                "name": "blackoutprov",
+                "api_base_url": blacked_out.uri(),
+                "api_key": "key0",
+                "models": ["m0"]
+            },
+            {
+                "name": "openprov",
+                "api_base_url": available.uri(),
+                "api_key": "key1",
+                "models": ["m1"]
+            }
+        ],
+        "Router": {
+            "default": "blackoutprov,m0",
+            "think": "openprov,m1",
+            "blackoutWindows": {
+                "blackoutprov": [{"start": "00:00", "end": "00:00"}]
+            }
+        },
+        "API_TIMEOUT_MS": 5000
+    });
+    let cfg = config_from_json(&config.to_string());
+
+    // Even a direct request for the blacked-out tier falls through.
+    let resp = build_app(cfg)
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::to_vec(&json!({
+                        "model": "blackoutprov,m0",
+                        "messages": [{"role": "user", "content": "hello"}],
+                        "max_tokens": 100
+                    }))
+                    .unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers()
+            .get("x-ccr-tier")
+            .and_then(|v| v.to_str().ok()),
+        Some("openprov")
+    );
+}
+
+// Fault injection is compiled into debug builds only.
+#[cfg(debug_assertions)]
+#[tokio::test]
+async fn fault_injection_fails_tier_over_only_when_enabled() {
+    if skip_if_localhost_bind_unavailable("fault_injection_fails_tier_over_only_when_enabled") {
+        return;
+    }
+
+    let faulty = MockServer::start().await;
+    let fallback = MockServer::start().await;
+    let success = json!({"choices": [{"message": {"content": "hi"}}]});
+
+    // Only the run with injection disabled reaches the faulty upstream.
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(success.clone()))
+        .expect(1)
+        .mount(&faulty)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(success))
+        .expect(1)
+        .mount(&fallback)
+        .await;
+
+    for (enabled, expected_tier) in [(true, "fallbackprov"), (false, "faultprov")] {
+        let config = json!({
+            "Providers": [
+                {
+                    "name": "faultprov",
+                    "api_base_url": faulty.uri(),
+                    "api_key": "key0",
+                    "models": ["m0"]
+                },
+                {
+                    "name": "fallbackprov",
+                    "api_base_url": fallback.uri(),
+                    "api_key": "key1",
+                    "models": ["m1"]
+                }
+            ],
+            "Router": {
+                "default": "faultprov,m0",
+                "think": "fallbackprov,m1",
+                "tierRetries": {
+                    "faultprov": {"max_retries": 0}
+                },
+                "faultInjection": {
+                    "faultprov": {"error_rate": 1.0}
+                }
+            },
+            "ENABLE_FAULT_INJECTION": enabled,
+            "API_TIMEOUT_MS": 5000
+        });
+        let cfg = config_from_json(&config.to_string());
+
+        let resp = build_app(cfg)
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/messages")
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        serde_json::to_vec(&json!({
+                            "model": "faultprov,m0",
+                            "messages": [{"role": "user", "content": "hello"}],
+                            "max_tokens": 100
+                        }))
+                        .unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers()
+                .get("x-ccr-tier")
+                .and_then(|v| v.to_str().ok()),
+            Some(expected_tier),
+            "fault injection enabled: {enabled}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn sticky_session_prefers_pinned_tier_until_rate_limited() {
+    if skip_if_localhost_bind_unavailable("sticky_session_prefers_pinned_tier_until_rate_limited") {
+        return;
+    }
+
+    let server_a = MockServer::start().await;
+    let server_b = MockServer::start().await;
+    let success = json!({"choices": [{"message": {"content": "hi"}}]});
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(success.clone()))
+        .mount(&server_a)
+        .await;
+    // Tier B is slower than A. It serves the first seven requests, then starts
+    // rate limiting.
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(success)
+                .set_delay(Duration::from_millis(150)),
+        )
+        .up_to_n_times(7)
+        .mount(&server_b)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "60"))
+        .mount(&server_b)
+        .await;
+
+    let config = json!({
+        "Providers": [
+            {
+                "name": "stickya",
+                "api_base_url": server_a.uri(),
+                "api_key": "key0",
+                "models": ["m0"]
+            },
+            {
+                "name": "stickyb",
+                "api_base_url": server_b.uri(),
+                "api_key": "key1",
+                "models": ["m1"]
+            }
+        ],
+        "Router": {
+            "default": "stickya,m0",
+            "think": "stickyb,m1",
+            "stickySessions": {"enabled": true, "ttl_secs": 600},
+            "routingTemperature": 0.001,
+            "tierRetries": {"stickyb": {"max_retries": 0}}
+        },
+        "API_TIMEOUT_MS": 5000
+    });
+    let cfg = config_from_json(&config.to_string());
+    let app = build_app(cfg);
+
+    let send_with = |model: &'static str, session: Option<&'static str>| {
+        let app = app.clone();
+        async move {
+            let mut builder = Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json");
+            if let Some(session) = session {
+                builder = builder.header("x-ccr-session", session);
+            }
+            let resp = app
+                .oneshot(
+                    builder
+                        .body(Body::from(
+                            serde_json::to_vec(&json!({
+                                "model": model,
+                                "messages": [{"role": "user", "content": "hello"}],
+                                "max_tokens": 100
+                            }))
+                            .unwrap(),
+                        ))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            let tier = resp
+                .headers()
+                .get("x-ccr-tier")
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string);
+            (resp.status(), tier)
+        }
+    };
+
+    let send = |model: &'static str| send_with(model, Some("conv-1"));
+
+    // Warm both tiers so the latency-ordered tier list puts A first.
+    for (model, expected) in [("stickya,m0", "stickya"), ("stickyb,m1", "stickyb")] {
+        for _ in 0..3 {
+            let (status, tier) = send_with(model, None).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(tier.as_deref(), Some(expected));
+        }
+    }
+    let (_, tier) = send_with("claude-sonnet-4-6", Some("other-conv")).await;
+    assert_eq!(tier.as_deref(), Some("stickya"));
+
+    // Direct routing to B pins the session to B.
+    let (status, tier) = send("stickyb,m1").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(tier.as_deref(), Some("stickyb"));
+
+    // Follow-up requests without a direct target stay on B.
+    for _ in 0..3 {
+        let (status, tier) = send("claude-sonnet-4-6").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(tier.as_deref(), Some("stickyb"));
+    }
+
+    // B rate limits: the request cascades to A, the session is re-pinned to
+    // A, and later requests stay there while B backs off.
+    for _ in 0..3 {
+        let (status, tier) = send("claude-sonnet-4-6").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(tier.as_deref(), Some("stickya"));
+    }
+}
+
+#[tokio::test]
+async fn transformer_preview_lists_changed_paths() {
+    let config = json!({
+        "Providers": [{
+            "name": "capped",
+            "api_base_url": "http://127.0.0.1:9",
+            "api_key": "test-key",
+            "models": ["test-model"],
+            "transformer": {
+                "use": ["tooluse", ["maxtoken", {"max_tokens": 1024}]]
+            }
+        }],
+        "Router": {"default": "capped,test-model"},
+        "API_TIMEOUT_MS": 5000
+    });
+    let cfg = config_from_json(&config.to_string());
+    let app = build_app(cfg);
+
+    let sample = json!({
+        "model": "test-model",
+        "max_tokens": 4096,
+        "messages": [{"role": "user", "content": "hi"}],
+        "tools": [{"name": "lookup"}]
+    });
+    let preview = |request: serde_json::Value| {
+        let app = app.clone();
+        async move {
+            let resp = app
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri("/v1/transformers/preview")
+                        .header("content-type", "application/json")
+                        .body(Body::from(serde_json::to_vec(&request).unwrap()))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            let status = resp.status();
+            let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            (
+                status,
+                serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+            )
+        }
+    };
+
+    // The provider's configured chain and the same chain given inline agree.
+    for request in [
+        json!({"body": sample, "provider": "capped"}),
+        json!({"body": sample, "transformers": ["tooluse", ["maxtoken", {"max_tokens": 1024}]]}),
+    ] {
+        let (status, body) = preview(request).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["transformers"], json!(["tooluse", "maxtoken"]));
+        assert_eq!(
+            body["changes"],
+            json!([
+                {"op": "modified", "path": "/max_tokens", "before": 4096, "after": 1024},
+                {"op": "added", "path": "/tools/0/input_schema", "value": {}}
+            ])
+        );
+        assert_eq!(body["result"]["max_tokens"], 1024);
+    }
+
+    let (status, body) = preview(json!({"body": sample, "transformers": ["nope"]})).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["error"].as_str().unwrap().contains("nope"));
+}
+
+#[tokio::test]
+async fn tier_headers_apply_only_to_their_route() {
+    if skip_if_localhost_bind_unavailable("tier_headers_apply_only_to_their_route") {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"choices": [{"message": {"content": "hi"}}]})),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let config = json!({
+        "Providers": [{
+            "name": "billing",
+            "api_base_url": mock_server.uri(),
+            "api_key": "test-key",
+            "models": ["model-a", "model-b"],
+            "extra_headers": {"OpenAI-Project": "proj-shared", "X-Team": "core"}
+        }],
+        "Router": {
+            "default": "billing,model-a",
+            "tierHeaders": {"billing,model-a": {"OpenAI-Project": "proj-a"}}
+        },
+        "API_TIMEOUT_MS": 5000
+    });
+    let cfg = config_from_json(&config.to_string());
+    cfg.validate().unwrap();
+    let app = build_app(cfg);
+
+    for model in ["billing,model-a", "billing,model-b"] {
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/messages")
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        serde_json::to_vec(&json!({
+                            "model": model,
+                            "messages": [{"role": "user", "content": "hello"}],
+                            "max_tokens": 100
+                        }))
+                        .unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    let requests = mock_server.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 2);
+    let header_for = |model: &str, name: &str| {
+        let request = requests
+            .iter()
+            .find(|r| r.body_json::<serde_json::Value>().unwrap()["model"] == model)
+            .unwrap();
+        request
+            .headers
+            .get(name)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string()
+    };
+    assert_eq!(header_for("model-a", "openai-project"), "proj-a");
+    assert_eq!(header_for("model-b", "openai-project"), "proj-shared");
+    // Provider headers the tier does not override still apply.
+    assert_eq!(header_for("model-a", "x-team"), "core");
+}
+
+#[tokio::test]
+async fn upstream_cap_queues_then_rejects() {
+    if skip_if_localhost_bind_unavailable("upstream_cap_queues_then_rejects") {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"choices": [{"message": {"content": "hi"}}]}))
+                .set_delay(Duration::from_millis(300)),
+        )
+        .mount(&mock_server)
+        .await;
+
+    // Two concurrent requests against a cap of one: the second waits for the
+    // first's slot, and gets 503 if the queue timeout is shorter than that.
+    let run_pair = |queue_timeout_ms: u64| {
+        let mut config: serde_json::Value =
+            serde_json::from_str(&make_test_config(&mock_server.uri(), HashMap::new())).unwrap();
+        config["MAX_UPSTREAM_INFLIGHT"] = json!(1);
+        config["UPSTREAM_QUEUE_TIMEOUT_MS"] = json!(queue_timeout_ms);
+        let app = build_app(config_from_json(&config.to_string()));
+
+        async move {
+            // Read each body as a server would, which releases its slot.
+            let send = || async {
+                let resp = app
+                    .clone()
+                    .oneshot(
+                        Request::builder()
+                            .method("POST")
+                            .uri("/v1/messages")
+                            .header("content-type", "application/json")
+                            .body(Body::from(
+                                serde_json::to_vec(&test_request_body()).unwrap(),
+                            ))
+                            .unwrap(),
+                    )
+                    .await
+                    .unwrap();
+                let status = resp.status();
+                let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                (
+                    status,
+                    serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+                )
+            };
+            let (first, second) = tokio::join!(send(), send());
+            let mut results = [first, second];
+            results.sort_by_key(|(status, _)| *status);
+            results
+        }
+    };
+
+    let [served, rejected] = run_pair(50).await;
+    assert_eq!(served.0, StatusCode::OK);
+    assert_eq!(rejected.0, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(rejected.1["error"]["code"], "upstream_saturated");
+    assert_eq!(mock_server.received_requests().await.unwrap().len(), 1);
+
+    let [first, second] = run_pair(5000).await;
+    assert_eq!(first.0, StatusCode::OK);
+    assert_eq!(second.0, StatusCode::OK);
+    assert_eq!(mock_server.received_requests().await.unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn streams_over_max_streams_get_503() {
+    if skip_if_localhost_bind_unavailable("streams_over_max_streams_get_503") {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+    let chunk = json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion.chunk",
+        "created": 1234567890,
+        "model": "test-model",
+        "choices": [{"index": 0, "delta": {"content": "hi"}, "finish_reason": "stop"}]
+    });
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(format!("data: {chunk}\n\ndata: [DONE]\n\n")),
+        )
+        .expect(3)
+        .mount(&mock_server)
+        .await;
+
+    let config_json = make_test_config(&mock_server.uri(), HashMap::new());
+    let config = config_from_json(&config_json);
+    let app = build_app_with_max_streams(config, 2);
+
+    let open_stream = || {
+        let mut body = test_request_body();
+        body["stream"] = json!(true);
+        app.clone().oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json")
+                .header("anthropic-version", "2023-06-01")
+                .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                .unwrap(),
+        )
+    };
+
+    // Unread response bodies keep their stream slots.
+    let first = open_stream().await.unwrap();
+    let second = open_stream().await.unwrap();
+    assert_eq!(first.status(), StatusCode::OK);
+    assert_eq!(second.status(), StatusCode::OK);
+
+    let rejected = open_stream().await.unwrap();
+    assert_eq!(rejected.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let body = axum::body::to_bytes(rejected.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["type"], "error");
+    assert_eq!(body["error"]["type"], "overloaded_error");
+
+    drop(first);
+    let third = open_stream().await.unwrap();
+    assert_eq!(third.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn streams_are_counted_until_the_body_is_dropped() {
+    if skip_if_localhost_bind_unavailable("streams_are_counted_until_the_body_is_dropped") {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+    let chunk = json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion.chunk",
+        "created": 1234567890,
+        "model": "test-model",
+        "choices": [{"index": 0, "delta": {"content": "hi"}, "finish_reason": "stop"}]
+    });
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(json!({"stream": true})))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(format!("data: {chunk}\n\ndata: [DONE]\n\n")),
+        )
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(json!({"stream": false})))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"choices": [{"message": {"content": "hi"}}]})),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let config_json = make_test_config(&mock_server.uri(), HashMap::new());
+    let config = config_from_json(&config_json);
+    let state = build_state(config, 0);
+    let app = Router::new()
+        .route("/v1/messages", post(ccr_rust::router::handle_messages))
+        .with_state(state.clone());
+
+    let send = |stream: bool| {
+        let mut body = test_request_body();
+        body["stream"] = json!(stream);
+        app.clone().oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                .unwrap(),
+        )
+    };
+    let active = || {
+        state
+            .active_streams
+            .load(std::sync::atomic::Ordering::SeqCst)
+    };
+
+    let response = send(true).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(active(), 1);
+
+    let plain = send(false).await.unwrap();
+    assert_eq!(plain.status(), StatusCode::OK);
+    assert_eq!(active(), 1);
+
+    drop(response);
+    assert_eq!(active(), 0);
+}
+
+#[tokio::test]
+async fn client_message_id_is_echoed_only_when_provided() {
+    if skip_if_localhost_bind_unavailable("client_message_id_is_echoed_only_when_provided") {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"choices": [{"message": {"content": "hi"}}]})),
+        )
+        .expect(2)
+        .mount(&mock_server)
+        .await;
+
+    let config_json = make_test_config(&mock_server.uri(), HashMap::new());
+    let config = config_from_json(&config_json);
+    let app = build_app(config);
+
+    let send = |body: serde_json::Value| {
+        app.clone().oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                .unwrap(),
+        )
+    };
+
+    let mut tagged = test_request_body();
+    tagged["metadata"] = json!({"client_message_id": "msg-42"});
+    let resp = send(tagged).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers()[ccr_rust::router::CLIENT_MESSAGE_ID_HEADER],
+        "msg-42"
+    );
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["client_message_id"], "msg-42");
+
+    let resp = send(test_request_body()).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(resp
+        .headers()
+        .get(ccr_rust::router::CLIENT_MESSAGE_ID_HEADER)
+        .is_none());
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert!(body.get("client_message_id").is_none());
+}
+
+#[tokio::test]
+async fn streamed_openai_request_asks_for_and_reports_real_usage() {
+    if skip_if_localhost_bind_unavailable("streamed_openai_request_asks_for_and_reports_real_usage")
+    {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+    let content = json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion.chunk",
+        "created": 1234567890,
+        "model": "test-model",
+        "choices": [{"index": 0, "delta": {"content": "hi"}, "finish_reason": "stop"}]
+    });
+    let usage = json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion.chunk",
+        "created": 1234567890,
+        "model": "test-model",
+        "choices": [],
+        "usage": {"prompt_tokens": 321, "completion_tokens": 45, "total_tokens": 366}
+    });
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(
+            json!({"stream": true, "stream_options": {"include_usage": true}}),
+        ))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(format!(
+                    "data: {content}\n\ndata: {usage}\n\ndata: [DONE]\n\n"
+                )),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let config_json = make_test_config(&mock_server.uri(), HashMap::new());
+    let config = config_from_json(&config_json);
+    let app = build_app(config);
+
+    let mut body = test_request_body();
+    body["stream"] = json!(true);
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body = String::from_utf8(body.to_vec()).unwrap();
+
+    let message_delta: serde_json::Value = body
+        .lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .filter_map(|data| serde_json::from_str::<serde_json::Value>(data).ok())
+        .find(|event| event["type"] == "message_delta")
+        .expect("message_delta event");
+    assert_eq!(message_delta["usage"]["input_tokens"], 321);
+    assert_eq!(message_delta["usage"]["output_tokens"], 45);
+}
+
+#[tokio::test]
+async fn stream_usage_false_omits_stream_options() {
+    if skip_if_localhost_bind_unavailable("stream_usage_false_omits_stream_options") {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+    let chunk = json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion.chunk",
+        "created": 1234567890,
+        "model": "test-model",
+        "choices": [{"index": 0, "delta": {"content": "hi"}, "finish_reason": "stop"}]
+    });
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(format!("data: {chunk}\n\ndata: [DONE]\n\n")),
+        )
+        .expect(2)
+        .mount(&mock_server)
+        .await;
+
+    let mut config_json: serde_json::Value =
+        serde_json::from_str(&make_test_config(&mock_server.uri(), HashMap::new())).unwrap();
+    config_json["Providers"][0]["stream_usage"] = json!(false);
+    let config = config_from_json(&config_json.to_string());
+    let app = build_app(config);
+
+    // Translated (Claude Code) and passthrough (Codex) requests alike.
+    let mut anthropic = test_request_body();
+    anthropic["stream"] = json!(true);
+    let codex = json!({
+        "model": "mock,test-model",
+        "messages": [{"role": "user", "content": "Hello"}],
+        "stream": true
+    });
+    for (body, (header, value)) in [
+        (anthropic, ("anthropic-version", "2023-06-01")),
+        (codex, ("user-agent", "codex-cli/1.0.0")),
+    ] {
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/messages")
+                    .header("content-type", "application/json")
+                    .header(header, value)
+                    .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+    }
+
+    for request in mock_server.received_requests().await.unwrap() {
+        let sent: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(sent["stream"], true);
+        assert!(sent.get("stream_options").is_none(), "sent {sent}");
+    }
+}
+
+#[tokio::test]
+async fn non_streaming_responses_honor_accept_encoding() {
+    if skip_if_localhost_bind_unavailable("non_streaming_responses_honor_accept_encoding") {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(json!({"stream": false})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-gzip",
+            "object": "chat.completion",
+            "created": 1234567890,
+            "model": "test-model",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "compressed reply"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 3}
+        })))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(json!({"stream": true})))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(
+                    "data: {\"id\":\"chatcmpl-s\",\"object\":\"chat.completion.chunk\",\"created\":1,\"model\":\"test-model\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"hi\"},\"finish_reason\":\"stop\"}]}\n\ndata: [DONE]\n\n",
+                ),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let config_json = make_test_config(&mock_server.uri(), HashMap::new());
+    let config = config_from_json(&config_json);
+    let app = build_app(config).layer(ccr_rust::router::response_compression_layer());
+
+    let send = |stream: bool| {
+        let app = app.clone();
+        let mut body = test_request_body();
+        body["stream"] = json!(stream);
+        async move {
+            app.oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/messages")
+                    .header("content-type", "application/json")
+                    .header("accept-encoding", "gzip")
+                    .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+        }
+    };
+
+    let resp = send(false).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers()["content-encoding"], "gzip");
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let mut decoded = String::new();
+    std::io::Read::read_to_string(&mut flate2::read::GzDecoder::new(&bytes[..]), &mut decoded)
+        .unwrap();
+    let decoded: serde_json::Value = serde_json::from_str(&decoded).unwrap();
+    assert_eq!(decoded["content"][0]["text"], "compressed reply");
+
+    let resp = send(true).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(resp.headers().get("content-encoding").is_none());
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert!(String::from_utf8_lossy(&bytes).contains("event: message_start"));
+}
+
+#[tokio::test]
+async fn hedged_request_returns_faster_tier_and_drops_slow_one() {
+    if skip_if_localhost_bind_unavailable("hedged_request_returns_faster_tier_and_drops_slow_one") {
+        return;
+    }
+
+    let slow_server = MockServer::start().await;
+    let fast_server = MockServer::start().await;
+    let reply = |text: &str| {
+        json!({
+            "id": "chatcmpl-hedge",
+            "object": "chat.completion",
+            "created": 1234567890,
+            "model": "test-model",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": text},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 1}
+        })
+    };
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(reply("slow"))
+                .set_delay(Duration::from_secs(3)),
+        )
+        .expect(1)
+        .mount(&slow_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(reply("fast")))
+        .expect(1)
+        .mount(&fast_server)
+        .await;
+
+    let config = json!({
+        "Providers": [
+            {
+                "name": "hedgeslow",
+                "api_base_url": slow_server.uri(),
+                "api_key": "key0",
+                "models": ["m0"]
+            },
+            {
+                "name": "hedgefast",
+                "api_base_url": fast_server.uri(),
+                "api_key": "key1",
+                "models": ["m1"]
+            }
+        ],
+        "Router": {
+            "default": "hedgeslow,m0",
+            "think": "hedgefast,m1",
+            "hedging": {"hedge_after_ms": 100}
+        },
+        "API_TIMEOUT_MS": 10000
+    });
+    let cfg = config_from_json(&config.to_string());
+
+    // Direct routing pins the slow tier first; the hedge should overtake it.
+    let start = Instant::now();
+    let resp = build_app(cfg)
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::to_vec(&json!({
+                        "model": "hedgeslow,m0",
+                        "messages": [{"role": "user", "content": "hello"}],
+                        "max_tokens": 100
+                    }))
+                    .unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers()
+            .get("x-ccr-tier")
+            .and_then(|v| v.to_str().ok()),
+        Some("hedgefast")
+    );
+    let body: serde_json::Value = serde_json::from_slice(
+        &axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(body["content"][0]["text"], "fast");
+    assert!(
+        start.elapsed() < Duration::from_secs(2),
+        "hedged tier should win well before the slow tier answers, took {:?}",
+        start.elapsed()
+    );
+    assert_eq!(
+        counter_value(
+            "ccr_hedge_wins_total",
+            &[("tier", "hedgefast"), ("attempt", "hedge")]
+        ),
+        1.0
+    );
+    assert_eq!(
+        counter_value(
+            "ccr_hedge_wins_total",
+            &[("tier", "hedgeslow"), ("attempt", "primary")]
+        ),
+        0.0
+    );
+}
+
+#[test]
+fn zero_hedge_delay_is_rejected() {
+    let config = json!({
+        "Providers": [{
+            "name": "mock",
+            "api_base_url": "http://127.0.0.1:9",
+            "api_key": "test-key",
+            "models": ["m"]
+        }],
+        "Router": {"default": "mock,m", "hedging": {"hedge_after_ms": 0}}
+    });
+    let cfg = config_from_json(&config.to_string());
+    let err = cfg.validate().unwrap_err().to_string();
+    assert!(err.contains("hedge_after_ms"), "{err}");
+}
+
+#[test]
+fn frontend_default_routes_are_validated() {
+    for route in ["missing,m", "mock,unlisted", "mock"] {
+        let config = json!({
+            "Providers": [{
+                "name": "mock",
+                "api_base_url": "http://127.0.0.1:9",
+                "api_key": "test-key",
+                "models": ["m"]
+            }],
+            "Router": {"default": "mock,m", "frontendDefaults": {"codex": route}}
+        });
+        let cfg = config_from_json(&config.to_string());
+        let err = cfg.validate().unwrap_err().to_string();
+        assert!(err.contains("frontendDefaults"), "{route}: {err}");
+    }
+}
+
+#[tokio::test]
+async fn hedged_error_reply_does_not_beat_slow_success() {
+    if skip_if_localhost_bind_unavailable("hedged_error_reply_does_not_beat_slow_success") {
+        return;
+    }
+
+    for hedge_status in [400, 429] {
+        let slow_server = MockServer::start().await;
+        let failing_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({
+                        "id": "chatcmpl-hedge",
+                        "object": "chat.completion",
+                        "created": 1234567890,
+                        "model": "test-model",
+                        "choices": [{
+                            "index": 0,
+                            "message": {"role": "assistant", "content": "slow"},
+                            "finish_reason": "stop"
+                        }],
+                        "usage": {"prompt_tokens": 10, "completion_tokens": 1}
+                    }))
+                    .set_delay(Duration::from_millis(500)),
+            )
+            .expect(1)
+            .mount(&slow_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(hedge_status).set_body_string("rejected"))
+            .expect(1)
+            .mount(&failing_server)
+            .await;
+
+        let config = json!({
+            "Providers": [
+                {
+                    "name": "raceslow",
+                    "api_base_url": slow_server.uri(),
+                    "api_key": "key0",
+                    "models": ["m0"]
+                },
+                {
+                    "name": "racefail",
+                    "api_base_url": failing_server.uri(),
+                    "api_key": "key1",
+                    "models": ["m1"]
+                }
+            ],
+            "Router": {
+                "default": "raceslow,m0",
+                "think": "racefail,m1",
+                "hedging": {"hedge_after_ms": 100}
+            },
+            "API_TIMEOUT_MS": 10000
+        });
+        let cfg = config_from_json(&config.to_string());
+
+        let resp = build_app(cfg)
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/messages")
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        serde_json::to_vec(&json!({
+                            "model": "raceslow,m0",
+                            "messages": [{"role": "user", "content": "hello"}],
+                            "max_tokens": 100
+                        }))
+                        .unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::OK, "hedge status {hedge_status}");
+        let body: serde_json::Value = serde_json::from_slice(
+            &axum::body::to_bytes(resp.into_body(), usize::MAX)
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(body["content"][0]["text"], "slow");
+        failing_server.verify().await;
+    }
+}
+
+#[tokio::test]
+async fn warnings_header_reports_dropped_request_fields() {
+    if skip_if_localhost_bind_unavailable("warnings_header_reports_dropped_request_fields") {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-warn",
+            "object": "chat.completion",
+            "created": 1234567890,
+            "model": "test-model",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "ok"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 1}
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let send = |warnings_header: bool| {
+        let mut config: serde_json::Value =
+            serde_json::from_str(&make_test_config(&mock_server.uri(), HashMap::new())).unwrap();
+        config["Router"]["warningsHeader"] = json!(warnings_header);
+        async move {
+            let config = config_from_json(&config.to_string());
+            let mut body = test_request_body();
+            body["logit_bias"] = json!({"50256": -100});
+            body["top_k"] = json!(5);
+            build_app(config)
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri("/v1/messages")
+                        .header("content-type", "application/json")
+                        .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                        .unwrap(),
+                )
+                .await
+                .unwrap()
+        }
+    };
+
+    let resp = send(true).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let warnings: Vec<String> =
+        serde_json::from_slice(resp.headers()["x-ccr-warnings"].as_bytes()).unwrap();
+    assert_eq!(
+        warnings,
+        vec![
+            "dropped logit_bias (not supported by CCR)",
+            "dropped top_k (not supported by CCR)"
+        ]
+    );
+
+    let resp = send(false).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(resp.headers().get("x-ccr-warnings").is_none());
+}
+
+#[tokio::test]
+async fn route_headers_report_transformers_and_resolved_model() {
+    if skip_if_localhost_bind_unavailable("route_headers_report_transformers_and_resolved_model") {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-route",
+            "object": "chat.completion",
+            "created": 1234567890,
+            "model": "test-model-2025",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "ok"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 1}
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let send = |route_headers: bool| {
+        let config = json!({
+            "Providers": [{
+                "name": "mock",
+                "api_base_url": mock_server.uri(),
+                "api_key": "test-key",
+                "models": ["test-model"],
+                "model_aliases": {"test-model": "test-model-2025"},
+                "transformer": {"use": ["tooluse", ["maxtoken", {"max_tokens": 1024}]]}
+            }],
+            "Router": {"default": "mock,test-model", "routeHeaders": route_headers},
+            "API_TIMEOUT_MS": 5000
+        });
+        async move {
+            let config = config_from_json(&config.to_string());
+            build_app(config)
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri("/v1/messages")
+                        .header("content-type", "application/json")
+                        .body(Body::from(
+                            serde_json::to_vec(&test_request_body()).unwrap(),
+                        ))
+                        .unwrap(),
+                )
+                .await
+                .unwrap()
+        }
+    };
+
+    let resp = send(true).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers()["x-ccr-transformers"], "tooluse,maxtoken");
+    assert_eq!(resp.headers()["x-ccr-model-resolved"], "test-model-2025");
+    assert_eq!(resp.headers()["x-ccr-tier"], "mock");
+
+    let resp = send(false).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(resp.headers().get("x-ccr-transformers").is_none());
+    assert!(resp.headers().get("x-ccr-model-resolved").is_none());
+}
+
+#[tokio::test]
+async fn provider_headers_reach_upstream_and_override_builtins() {
+    if skip_if_localhost_bind_unavailable("provider_headers_reach_upstream_and_override_builtins") {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"choices": [{"message": {"content": "hi"}}]})),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let config = json!({
+        "Providers": [{
+            "name": "mock",
+            "api_base_url": mock_server.uri(),
+            "api_key": "test-key",
+            "models": ["test-model"],
+            "headers": {
+                "x-portkey-provider": "openai",
+                "api-version": "2024-06-01",
+                "Content-Type": "application/json; charset=utf-8"
+            }
+        }],
+        "Router": {"default": "mock,test-model"},
+        "API_TIMEOUT_MS": 5000
+    });
+    let cfg = config_from_json(&config.to_string());
+    cfg.validate().unwrap();
+
+    let resp = build_app(cfg)
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::to_vec(&test_request_body()).unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let requests = mock_server.received_requests().await.unwrap();
+    let headers = &requests[0].headers;
+    let header = |name: &str| headers.get(name).unwrap().to_str().unwrap().to_string();
+    assert_eq!(header("x-portkey-provider"), "openai");
+    assert_eq!(header("api-version"), "2024-06-01");
+    assert_eq!(header("content-type"), "application/json; charset=utf-8");
+    assert_eq!(header("authorization"), "Bearer test-key");
+}
+
+#[test]
+fn invalid_provider_headers_fail_validation() {
+    for (name, value) in [("bad header", "ok"), ("x-ok", "line\nbreak")] {
+        let config = json!({
+            "Providers": [{
+                "name": "gateway",
+                "api_base_url": "http://127.0.0.1:1",
+                "api_key": "test-key",
+                "models": ["test-model"],
+                "headers": {name: value}
+            }],
+            "Router": {"default": "gateway,test-model"}
+        });
+        let cfg = config_from_json(&config.to_string());
+        let error = cfg.validate().unwrap_err().to_string();
+        assert!(error.contains("headers for provider 'gateway'"), "{error}");
+    }
+}
+
+#[tokio::test]
+async fn audio_output_params_reach_upstream_and_audio_block_is_preserved() {
+    if skip_if_localhost_bind_unavailable(
+        "audio_output_params_reach_upstream_and_audio_block_is_preserved",
+    ) {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(json!({
+            "modalities": ["text", "audio"],
+            "audio": {"voice": "alloy", "format": "wav"}
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-audio",
+            "object": "chat.completion",
+            "created": 1234567890,
+            "model": "test-model",
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "content": null,
+                    "audio": {
+                        "id": "audio_abc",
+                        "data": "UklGRg==",
+                        "expires_at": 1234571490,
+                        "transcript": "Hello there"
+                    }
+                },
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 3}
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let config_json = make_test_config(&mock_server.uri(), HashMap::new());
+    let config = config_from_json(&config_json);
+
+    let mut body = test_request_body();
+    body["modalities"] = json!(["text", "audio"]);
+    body["audio"] = json!({"voice": "alloy", "format": "wav"});
+    let resp = build_app(config)
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(
+        json["content"],
+        json!([{
+            "type": "audio",
+            "audio": {
+                "id": "audio_abc",
+                "data": "UklGRg==",
+                "expires_at": 1234571490,
+                "transcript": "Hello there"
+            }
+        }])
+    );
+}
+
+#[tokio::test]
+async fn image_url_response_parts_become_anthropic_image_blocks() {
+    if skip_if_localhost_bind_unavailable("image_url_response_parts_become_anthropic_image_blocks")
+    {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-image",
+            "object": "chat.completion",
+            "created": 1234567890,
+            "model": "test-model",
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "content": [
+                        {"type": "text", "text": "Here are two images"},
+                        {"type": "image_url", "image_url": {"url": "data:image/png;base64,iVBORw0KGgo="}},
+                        {"type": "image_url", "image_url": {"url": "https://example.com/cat.jpg", "detail": "high"}}
+                    ]
+                },
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 3}
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let config_json = make_test_config(&mock_server.uri(), HashMap::new());
+    let config = config_from_json(&config_json);
+
+    let resp = build_app(config)
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::to_vec(&test_request_body()).unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(
+        json["content"],
+        json!([
+            {"type": "text", "text": "Here are two images"},
+            {
+                "type": "image",
+                "source": {"type": "base64", "media_type": "image/png", "data": "iVBORw0KGgo="}
+            },
+            {
+                "type": "image",
+                "source": {"type": "url", "url": "https://example.com/cat.jpg"}
+            }
+        ])
+    );
+}
+
+#[tokio::test]
+async fn error_classification_overrides_status_handling() {
+    if skip_if_localhost_bind_unavailable("error_classification_overrides_status_handling") {
+        return;
+    }
+    let rules = json!([
+        {"contains": "temporary failure", "class": "retryable"},
+        {"contains": "Context Length Exceeded", "class": "terminal"},
+        {"regex": "model .* overloaded", "class": "rate_limit"},
+        {"contains": "invalid api key", "class": "auth"}
+    ]);
+    // (upstream status, body, upstream calls, client status)
+    let cases = [
+        // 429s normally skip the tier's retries; this one is retried.
+        (
+            429,
+            "temporary failure, try again",
+            4,
+            StatusCode::SERVICE_UNAVAILABLE,
+        ),
+        (400, "context length exceeded", 1, StatusCode::BAD_REQUEST),
+        (
+            500,
+            "model glm-5 is overloaded",
+            1,
+            StatusCode::TOO_MANY_REQUESTS,
+        ),
+        (
+            500,
+            "Invalid API key provided",
+            1,
+            StatusCode::SERVICE_UNAVAILABLE,
+        ),
+    ];
+
+    for (upstream_status, body, calls, client_status) in cases {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(upstream_status).set_body_string(body))
+            .expect(calls)
+            .mount(&mock_server)
+            .await;
+
+        let mut config: serde_json::Value =
+            serde_json::from_str(&make_test_config(&mock_server.uri(), HashMap::new())).unwrap();
+        config["Providers"][0]["error_classification"] = rules.clone();
+        let config = config_from_json(&config.to_string());
+
+        let resp = build_app(config)
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/messages")
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        serde_json::to_vec(&test_request_body()).unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), client_status, "upstream body: {body}");
+        if client_status == StatusCode::BAD_REQUEST {
+            let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+            assert_eq!(json["error"]["code"], "upstream_terminal_error");
+            assert!(json["error"]["message"]
+                .as_str()
+                .unwrap()
+                .contains("context length exceeded"));
+        }
+        mock_server.verify().await;
+    }
+}
+
+#[test]
+fn invalid_error_classification_rules_fail_validation() {
+    for rule in [
+        json!({"regex": "model (overloaded", "class": "rate_limit"}),
+        json!({"contains": "a", "regex": "b", "class": "terminal"}),
+        json!({"class": "auth"}),
+    ] {
+        let config = json!({
+            "Providers": [{
+                "name": "gateway",
+                "api_base_url": "http://127.0.0.1:1",
+                "api_key": "test-key",
+                "models": ["test-model"],
+                "error_classification": [rule]
+            }],
+            "Router": {"default": "gateway,test-model"}
+        });
+        let cfg = config_from_json(&config.to_string());
+        let error = cfg.validate().unwrap_err().to_string();
+        assert!(
+            error.contains("error_classification") && error.contains("'gateway'"),
+            "{error}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn all_tiers_failed_reports_last_error_per_tier() {
+    if skip_if_localhost_bind_unavailable("all_tiers_failed_reports_last_error_per_tier") {
+        return;
+    }
+    let broken = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(500).set_body_string("database unavailable"))
+        .mount(&broken)
+        .await;
+    let verbose = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(502).set_body_string("x".repeat(4096)))
+        .mount(&verbose)
+        .await;
+
+    let no_retries = json!({"max_retries": 0, "base_backoff_ms": 1});
+    let config = json!({
+        "Providers": [
+            {
+                "name": "broken",
+                "api_base_url": broken.uri(),
+                "api_key": "key",
+                "models": ["m"]
+            },
+            {
+                "name": "verbose",
+                "api_base_url": verbose.uri(),
+                "api_key": "key",
+                "models": ["m"]
+            }
+        ],
+        "Router": {
+            "default": "broken,m",
+            "tiers": ["broken,m", "verbose,m"],
+            "tierRetries": {"broken": no_retries, "verbose": no_retries}
+        },
+        "API_TIMEOUT_MS": 5000
+    });
+    let cfg = config_from_json(&config.to_string());
+
+    let resp = build_app(cfg)
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::to_vec(&test_request_body()).unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let body: serde_json::Value = serde_json::from_slice(
+        &axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(body["error"]["code"], "service_unavailable");
+    assert!(body["error"]["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("All 2 backend tier(s) failed"));
+
+    let mut tier_errors = body["error"]["tier_errors"].as_array().unwrap().clone();
+    tier_errors.sort_by_key(|e| e["tier"].as_str().unwrap().to_string());
+    assert_eq!(tier_errors.len(), 2, "{body}");
+    assert_eq!(tier_errors[0]["tier"], "broken");
+    assert_eq!(tier_errors[0]["status"], 500);
+    assert_eq!(tier_errors[0]["message"], "database unavailable");
+    assert_eq!(tier_errors[1]["tier"], "verbose");
+    assert_eq!(tier_errors[1]["status"], 502);
+    let message = tier_errors[1]["message"].as_str().unwrap();
+    assert!(
+        message.len() < 1024 && message.ends_with("..."),
+        "{message}"
+    );
+}
+
+#[tokio::test]
+async fn tier_errors_omit_the_request_url() {
+    // Nothing listens on port 9, so the request fails in transport; the
+    // error must not echo the key in the provider URL's query string.
+    let config = json!({
+        "Providers": [{
+            "name": "keyed",
+            "api_base_url": "http://127.0.0.1:9/v1?key=url-secret",
+            "api_key": "key",
+            "models": ["m"]
+        }],
+        "Router": {
+            "default": "keyed,m",
+            "tierRetries": {"keyed": {"max_retries": 0, "base_backoff_ms": 1}}
+        },
+        "API_TIMEOUT_MS": 5000
+    });
+    let cfg = config_from_json(&config.to_string());
+
+    let resp = build_app(cfg)
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::to_vec(&test_request_body()).unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body = String::from_utf8_lossy(&body);
+    assert!(body.contains("\"tier\":\"keyed\""), "{body}");
+    assert!(!body.contains("url-secret"), "{body}");
+}
+
+#[tokio::test]
+async fn require_healthy_fails_fast_when_no_tier_is_healthy() {
+    if skip_if_localhost_bind_unavailable("require_healthy_fails_fast_when_no_tier_is_healthy") {
+        return;
+    }
+    // The first request fails 4 attempts in a row, opening the circuit. The
+    // second must be rejected without reaching upstream.
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(500).set_body_string("internal error"))
+        .expect(4)
+        .mount(&mock_server)
+        .await;
+
+    let mut config: serde_json::Value =
+        serde_json::from_str(&make_test_config(&mock_server.uri(), HashMap::new())).unwrap();
+    config["Router"]["requireHealthy"] = json!({"enabled": true, "failure_threshold": 3});
+    let config = config_from_json(&config.to_string());
+    let app = build_app(config);
+
+    let send = || {
+        app.clone().oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::to_vec(&test_request_body()).unwrap(),
+                ))
+                .unwrap(),
+        )
+    };
+
+    let first = send().await.unwrap();
+    assert_eq!(first.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let body: serde_json::Value = serde_json::from_slice(
+        &axum::body::to_bytes(first.into_body(), usize::MAX)
+            .await
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(body["error"]["code"], "service_unavailable");
+
+    let second = send().await.unwrap();
+    assert_eq!(second.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let body: serde_json::Value = serde_json::from_slice(
+        &axum::body::to_bytes(second.into_body(), usize::MAX)
+            .await
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(body["error"]["code"], "no_healthy_tiers");
+    assert_eq!(mock_server.received_requests().await.unwrap().len(), 4);
+}
+
+#[tokio::test]
+async fn require_healthy_returns_429_when_tiers_are_only_rate_limited() {
+    if skip_if_localhost_bind_unavailable(
+        "require_healthy_returns_429_when_tiers_are_only_rate_limited",
+    ) {
+        return;
+    }
+    // The first request's 429 backs the only tier off. The second finds no
+    // healthy tier, but only because of the rate limit.
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "30"))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let mut config: serde_json::Value =
+        serde_json::from_str(&make_test_config(&mock_server.uri(), HashMap::new())).unwrap();
+    config["Router"]["requireHealthy"] = json!({"enabled": true, "failure_threshold": 3});
+    let config = config_from_json(&config.to_string());
+    let app = build_app(config);
+
+    let send = || {
+        app.clone().oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::to_vec(&test_request_body()).unwrap(),
+                ))
+                .unwrap(),
+        )
+    };
+
+    let first = send().await.unwrap();
+    assert_eq!(first.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    let second = send().await.unwrap();
+    assert_eq!(second.status(), StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = second.headers()["retry-after"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(retry_after > 0);
+    let body: serde_json::Value = serde_json::from_slice(
+        &axum::body::to_bytes(second.into_body(), usize::MAX)
+            .await
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(body["error"]["code"], "rate_limited");
+    mock_server.verify().await;
+}
+
+#[tokio::test]
+async fn connection_reuse_counters_track_pooled_and_fresh_connections() {
+    if skip_if_localhost_bind_unavailable(
+        "connection_reuse_counters_track_pooled_and_fresh_connections",
+    ) {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-conn",
+            "object": "chat.completion",
+            "created": 1730000000,
+            "model": "test-model",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "ok"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2}
+        })))
+        .mount(&mock_server)
+        .await;
+
+    // Two providers on the same host share the client's pool.
+    let config = json!({
+        "Providers": ["connstats", "connstats2"].map(|name| json!({
+            "name": name,
+            "api_base_url": mock_server.uri(),
+            "api_key": "test-key",
+            "models": ["test-model"]
+        })),
+        "Router": {"default": "connstats,test-model"},
+        "API_TIMEOUT_MS": 5000
+    });
+    let load = || config_from_json(&config.to_string());
+
+    let send_to = |app: Router, provider: &'static str| async move {
+        let mut body = test_request_body();
+        body["model"] = json!(format!("{provider},test-model"));
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/messages")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        // Drain the body so the connection goes back to the pool.
+        axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+    };
+    let send = |app: Router| send_to(app, "connstats");
+
+    let app = build_app(load());
+    for _ in 0..3 {
+        send(app.clone()).await;
+    }
+    assert_eq!(
+        counter_value("ccr_connection_new_total", &[("provider", "connstats")]),
+        1.0
+    );
+    assert_eq!(
+        counter_value("ccr_connection_reused_total", &[("provider", "connstats")]),
+        2.0
+    );
+
+    // The second provider reuses the pooled connection, and the first
+    // provider's counts are left alone.
+    send_to(app.clone(), "connstats2").await;
+    assert_eq!(
+        counter_value("ccr_connection_new_total", &[("provider", "connstats2")]),
+        0.0
+    );
+    assert_eq!(
+        counter_value("ccr_connection_reused_total", &[("provider", "connstats2")]),
+        1.0
+    );
+    assert_eq!(
+        counter_value("ccr_connection_new_total", &[("provider", "connstats")]),
+        1.0
+    );
+
+    // A reloaded config builds a new client, and with it a new pool.
+    send(build_app(load())).await;
+    assert_eq!(
+        counter_value("ccr_connection_new_total", &[("provider", "connstats")]),
+        2.0
+    );
+    assert_eq!(
+        counter_value("ccr_connection_reused_total", &[("provider", "connstats")]),
+        2.0
+    );
+}
+
+#[tokio::test]
+async fn multimodal_reorder_applies_only_to_configured_provider() {
+    if skip_if_localhost_bind_unavailable("multimodal_reorder_applies_only_to_configured_provider")
+    {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-mm",
+            "object": "chat.completion",
+            "created": 1730000000,
+            "model": "test-model",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "ok"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2}
+        })))
+        .expect(2)
+        .mount(&mock_server)
+        .await;
+
+    let config = json!({
+        "Providers": [
+            {
+                "name": "mock",
+                "api_base_url": mock_server.uri(),
+                "api_key": "test-key",
+                "models": ["test-model"],
+                "transformer": {
+                    "use": [["multimodal_reorder", {"order": "text_first"}]]
+                }
+            },
+            {
+                "name": "plain",
+                "api_base_url": mock_server.uri(),
+                "api_key": "test-key",
+                "models": ["test-model"]
+            }
+        ],
+        "Router": {"default": "mock,test-model"},
+        "API_TIMEOUT_MS": 5000
+    });
+    let config = config_from_json(&config.to_string());
+    let app = build_app(config);
+
+    for model in ["mock,test-model", "plain,test-model"] {
+        let body = json!({
+            "model": model,
+            "max_tokens": 100,
+            "messages": [{
+                "role": "user",
+                "content": [
+                    {"type": "image", "source": {
+                        "type": "base64", "media_type": "image/png", "data": "iVBORw0KGgo="
+                    }},
+                    {"type": "text", "text": "What is in this picture?"}
+                ]
+            }]
+        });
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/messages")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK, "{model}");
+    }
+
+    let part_types: Vec<Vec<String>> = mock_server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|request| {
+            let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            let user = body["messages"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|m| m["role"] == "user")
+                .unwrap()
+                .clone();
+            user["content"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|part| part["type"].as_str().unwrap().to_string())
+                .collect()
+        })
+        .collect();
+    assert_eq!(part_types[0], ["text", "image_url"]);
+    assert_eq!(part_types[1], ["image_url", "text"]);
+}
+
+#[tokio::test]
+async fn unrecognizable_request_is_rejected_unless_default_frontend_is_set() {
+    let config_with = |default_frontend: Option<&str>| {
+        let mut config: serde_json::Value =
+            serde_json::from_str(&make_test_config("http://127.0.0.1:9", HashMap::new())).unwrap();
+        if let Some(frontend) = default_frontend {
+            config["Router"]["defaultFrontend"] = json!(frontend);
+        }
+        config_from_json(&config.to_string())
+    };
+    let send = |config| async move {
+        let resp = build_app(config)
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/messages")
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        json!({"prompt": "Hello", "max_tokens": 10}).to_string(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = resp.status();
+        let body: serde_json::Value = serde_json::from_slice(
+            &axum::body::to_bytes(resp.into_body(), usize::MAX)
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+        (status, body)
+    };
+
+    let (status, body) = send(config_with(None)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["code"], "unknown_frontend");
+    assert_eq!(
+        body["error"]["signals"],
+        json!({
+            "anthropic_headers": false,
+            "codex_user_agent": false,
+            "anthropic_body": false,
+            "openai_body": false
+        })
+    );
+
+    // With a default the request is parsed as that frontend's, and the parse
+    // error comes back in its shape.
+    let (status, body) = send(config_with(Some("claude_code"))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["type"], "error");
+    assert_eq!(body["error"]["type"], "invalid_request_error");
+    assert!(body["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("missing field `model`"));
+}
+
+#[tokio::test]
+async fn request_id_is_echoed_and_stored_in_debug_captures() {
+    if skip_if_localhost_bind_unavailable("request_id_is_echoed_and_stored_in_debug_captures") {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(json!({"stream": true})))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_raw(
+                    "data: {\"choices\":[{\"delta\":{\"content\":\"hi\"}}]}\n\n\
+                     data: {\"choices\":[{\"delta\":{},\"finish_reason\":\"stop\"}]}\n\n\
+                     data: [DONE]\n\n",
+                    "text/event-stream",
+                ),
+        )
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(500).set_body_string("upstream broke"))
+        .mount(&mock_server)
+        .await;
+
+    let mut tier_retries = HashMap::new();
+    tier_retries.insert(
+        "mock".to_string(),
+        TierRetryConfig {
+            max_retries: 0,
+            ..Default::default()
+        },
+    );
+    let mut config_json: serde_json::Value =
+        serde_json::from_str(&make_test_config(&mock_server.uri(), tier_retries)).unwrap();
+    config_json["Presets"] = json!({"plain": {"route": "mock,test-model"}});
+    let config = config_from_json(&config_json.to_string());
+    let capture_dir = tempfile::tempdir().unwrap();
+    let capture = std::sync::Arc::new(
+        ccr_rust::debug_capture::DebugCapture::new(ccr_rust::debug_capture::DebugCaptureConfig {
+            enabled: true,
+            output_dir: capture_dir.path().to_string_lossy().into_owned(),
+            ..Default::default()
+        })
+        .unwrap(),
+    );
+    let mut state = ccr_rust::router::AppState::for_tests(config);
+    state.debug_capture = Some(capture.clone());
+    let app = Router::new()
+        .route("/v1/messages", post(ccr_rust::router::handle_messages))
+        .route(
+            "/preset/:name/v1/messages",
+            post(ccr_rust::router::handle_preset_messages),
+        )
+        .layer(axum::middleware::from_fn(
+            ccr_rust::router::assign_request_id,
+        ))
+        .with_state(state);
+    let send_to = |uri: &'static str, body: serde_json::Value, request_id: Option<&str>| {
+        let mut request = Request::builder()
+            .method("POST")
+            .uri(uri)
+            .header("content-type", "application/json");
+        if let Some(id) = request_id {
+            request = request.header("x-request-id", id);
+        }
+        app.clone()
+            .oneshot(request.body(Body::from(body.to_string())).unwrap())
+    };
+    let send = |body, request_id| send_to("/v1/messages", body, request_id);
+
+    // A client-chosen ID is kept and reaches the capture of the failed attempt.
+    let resp = send(test_request_body(), Some("trace-abc-123"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(resp.headers()["x-ccr-request-id"], "trace-abc-123");
+    let captures = capture.list_captures(None, 10).unwrap();
+    assert_eq!(captures.len(), 1);
+    assert_eq!(captures[0].request_id, "trace-abc-123");
+
+    // Preset routes keep the client's headers, so their captures carry it too.
+    let resp = send_to(
+        "/preset/plain/v1/messages",
+        test_request_body(),
+        Some("trace-preset"),
+    )
+    .await
+    .unwrap();
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let captures = capture.list_captures(None, 10).unwrap();
+    assert!(captures.iter().any(|c| c.request_id == "trace-preset"));
+
+    // Without one (or with an unusable one), a UUID is generated, and streams
+    // carry it too.
+    let mut body = test_request_body();
+    body["stream"] = json!(true);
+    for inbound in [None, Some("has spaces")] {
+        let resp = send(body.clone(), inbound).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let id = resp.headers()["x-ccr-request-id"].to_str().unwrap();
+        assert!(uuid::Uuid::parse_str(id).is_ok(), "not a UUID: {id}");
+    }
+}
+
+#[tokio::test]
+async fn on_drift_capture_keeps_only_high_drift_requests() {
+    if skip_if_localhost_bind_unavailable("on_drift_capture_keeps_only_high_drift_requests") {
+        return;
+    }
+    let estimate = ccr_rust::metrics::record_pre_request_tokens(
+        "drift-estimate",
+        &[json!({"role": "user", "content": "hello"})],
+        None,
+        None,
+    );
+    let completion = |prompt_tokens: u64| {
+        json!({
+            "id": "chatcmpl-drift",
+            "object": "chat.completion",
+            "created": 1730000000,
+            "model": "test-model",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "ok"},
+                "finish_reason": "stop"
+            }],
+            "usage": {
+                "prompt_tokens": prompt_tokens,
+                "completion_tokens": 1,
+                "total_tokens": prompt_tokens + 1
+            }
+        })
+    };
+    let mock_server = MockServer::start().await;
+    // The first request matches the local estimate, the second is 10x off.
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(completion(estimate)))
+        .up_to_n_times(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(completion(estimate * 10)))
+        .mount(&mock_server)
+        .await;
+
+    let config_json = make_test_config(&mock_server.uri(), HashMap::new());
+    let config = config_from_json(&config_json);
+    let capture_dir = tempfile::tempdir().unwrap();
+    let capture = std::sync::Arc::new(
+        ccr_rust::debug_capture::DebugCapture::new(ccr_rust::debug_capture::DebugCaptureConfig {
+            enabled: true,
+            output_dir: capture_dir.path().to_string_lossy().into_owned(),
+            trigger: ccr_rust::debug_capture::CaptureTrigger::OnDrift,
+            drift_threshold_pct: 50.0,
+            ..Default::default()
+        })
+        .unwrap(),
+    );
+    let mut state = ccr_rust::router::AppState::for_tests(config);
+    state.debug_capture = Some(capture.clone());
+    let app = Router::new()
+        .route("/v1/messages", post(ccr_rust::router::handle_messages))
+        .with_state(state);
+    let send = || {
+        app.clone().oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json")
+                .body(Body::from(test_request_body().to_string()))
+                .unwrap(),
+        )
+    };
+
+    let resp = send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(capture.list_captures(None, 10).unwrap().is_empty());
+
+    let resp = send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let captures = capture.list_captures(None, 10).unwrap();
+    assert_eq!(captures.len(), 1);
+    assert!(captures[0].success);
+    let drift = captures[0].metadata.as_ref().unwrap()["token_drift_pct"]
+        .as_f64()
+        .unwrap();
+    assert!(drift < -50.0, "drift {drift}");
+}
+
+#[tokio::test]
+async fn explicit_null_fields_are_sent_only_to_configured_provider() {
+    if skip_if_localhost_bind_unavailable(
+        "explicit_null_fields_are_sent_only_to_configured_provider",
+    ) {
+        return;
+    }
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-null",
+            "object": "chat.completion",
+            "created": 1730000000,
+            "model": "test-model",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "ok"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2}
+        })))
+        .expect(2)
+        .mount(&mock_server)
+        .await;
+
+    let config = json!({
+        "Providers": [
+            {
+                "name": "strict",
+                "api_base_url": mock_server.uri(),
+                "api_key": "test-key",
+                "models": ["test-model"],
+                "explicit_null_fields": ["temperature", "max_tokens"]
+            },
+            {
+                "name": "plain",
+                "api_base_url": mock_server.uri(),
+                "api_key": "test-key",
+                "models": ["test-model"]
+            }
+        ],
+        "Router": {"default": "strict,test-model"},
+        "API_TIMEOUT_MS": 5000
+    });
+    let config = config_from_json(&config.to_string());
+    let app = build_app(config);
+
+    for model in ["strict,test-model", "plain,test-model"] {
+        let body = json!({
+            "model": model,
+            "max_tokens": 100,
+            "messages": [{"role": "user", "content": "hi"}]
+        });
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/messages")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK, "{model}");
+    }
+
+    let bodies: Vec<serde_json::Value> = mock_server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|request| serde_json::from_slice(&request.body).unwrap())
+        .collect();
+    assert_eq!(bodies[0].get("temperature"), Some(&serde_json::Value::Null));
+    assert_eq!(bodies[0]["max_tokens"], 100, "set fields keep their value");
+    assert_eq!(bodies[1].get("temperature"), None);
+}
@@ -4,6 +4,9 @@
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
 
+mod common;
+use common::config_from_json;
+
 #[test]
 fn test_active_stream_counter_basic() {
     let counter = AtomicUsize::new(0);
@@ -45,3 +48,128 @@ async fn test_shutdown_timeout() {
 
     assert!(result.is_err(), "Should timeout");
 }
+
+fn build_app(draining: bool) -> (axum::Router, ccr_rust::router::AppState) {
+    let config = serde_json::json!({
+        "Providers": [
+            { "name": "mock", "api_base_url": "http://127.0.0.1:9", "api_key": "k", "models": ["m"] }
+        ],
+        "Router": { "default": "mock,m" }
+    });
+    let config = config_from_json(&config.to_string());
+
+    let mut state = ccr_rust::router::AppState::for_tests(config);
+    state.max_streams = 512;
+    state.draining.store(draining, Ordering::SeqCst);
+
+    // Stand-in for a proxy handler that streams until the client goes away.
+    let sse = || async {
+        axum::response::Response::builder()
+            .header("content-type", "text/event-stream")
+            .body(axum::body::Body::from_stream(futures::stream::pending::<
+                Result<bytes::Bytes, std::io::Error>,
+            >()))
+            .unwrap()
+    };
+    let app = axum::Router::new()
+        .route("/v1/messages", axum::routing::post(sse))
+        .route(
+            "/v1/chat/completions",
+            axum::routing::post(|| async { "not a stream" }),
+        )
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            ccr_rust::router::reject_while_draining,
+        ))
+        .route("/ready", axum::routing::get(ccr_rust::router::ready))
+        .with_state(state.clone());
+    (app, state)
+}
+
+fn post(uri: &str) -> axum::http::Request<axum::body::Body> {
+    axum::http::Request::builder()
+        .method("POST")
+        .uri(uri)
+        .body(axum::body::Body::empty())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn requests_during_drain_get_503() {
+    use tower::ServiceExt;
+
+    let (app, state) = build_app(true);
+
+    let response = app.clone().oneshot(post("/v1/messages")).await.unwrap();
+    assert_eq!(
+        response.status(),
+        axum::http::StatusCode::SERVICE_UNAVAILABLE
+    );
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["error"]["type"], "overloaded_error");
+
+    let response = app.oneshot(post("/v1/chat/completions")).await.unwrap();
+    assert_eq!(
+        response.status(),
+        axum::http::StatusCode::SERVICE_UNAVAILABLE
+    );
+    assert_eq!(state.active_streams.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+async fn ready_reports_503_while_draining() {
+    use tower::ServiceExt;
+
+    let get_ready = || {
+        axum::http::Request::builder()
+            .uri("/ready")
+            .body(axum::body::Body::empty())
+            .unwrap()
+    };
+
+    let (app, _state) = build_app(false);
+    let response = app.oneshot(get_ready()).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+
+    let (app, _state) = build_app(true);
+    let response = app.oneshot(get_ready()).await.unwrap();
+    assert_eq!(
+        response.status(),
+        axum::http::StatusCode::SERVICE_UNAVAILABLE
+    );
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["status"], "not_ready");
+}
+
+#[tokio::test]
+async fn wait_for_streams_returns_once_streams_finish() {
+    let active = std::sync::Arc::new(AtomicUsize::new(2));
+    let finishing = active.clone();
+    tokio::spawn(async move {
+        for _ in 0..2 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            finishing.fetch_sub(1, Ordering::SeqCst);
+        }
+    });
+
+    let start = std::time::Instant::now();
+    let remaining = ccr_rust::router::wait_for_streams(&active, Duration::from_secs(30)).await;
+    assert_eq!(remaining, 0);
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn wait_for_streams_gives_up_at_timeout() {
+    let active = AtomicUsize::new(1);
+
+    let start = std::time::Instant::now();
+    let remaining = ccr_rust::router::wait_for_streams(&active, Duration::from_millis(300)).await;
+    assert_eq!(remaining, 1);
+    assert!(start.elapsed() >= Duration::from_millis(300));
+}
@@ -15,36 +15,17 @@ use tower::ServiceExt;
 use wiremock::matchers::{method, path};
 use wiremock::{Mock, MockServer, ResponseTemplate};
 
+mod common;
+use common::{config_from_json, skip_if_localhost_bind_unavailable};
+
 fn build_app(config: ccr_rust::config::Config) -> Router {
-    let ewma_tracker = std::sync::Arc::new(ccr_rust::routing::EwmaTracker::new());
-    let transformer_registry =
-        std::sync::Arc::new(ccr_rust::transformer::TransformerRegistry::new());
-    let active_streams = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
-    let ratelimit_tracker = std::sync::Arc::new(ccr_rust::ratelimit::RateLimitTracker::new());
-    let state = ccr_rust::router::AppState {
-        config,
-        ewma_tracker,
-        gp_router: None,
-        transformer_registry,
-        active_streams,
-        max_streams: 512,
-        ratelimit_tracker,
-        shutdown_timeout: 30,
-        debug_capture: None,
-    };
+    let mut state = ccr_rust::router::AppState::for_tests(config);
+    state.max_streams = 512;
     Router::new()
         .route("/v1/messages", post(ccr_rust::router::handle_messages))
         .with_state(state)
 }
 
-fn skip_if_localhost_bind_unavailable(test_name: &str) -> bool {
-    if std::net::TcpListener::bind("127.0.0.1:0").is_ok() {
-        return false;
-    }
-    eprintln!("Skipping {test_name}: cannot bind localhost sockets");
-    true
-}
-
 async fn spawn_upstream(app: Router) -> String {
     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
     let addr = listener.local_addr().unwrap();
@@ -167,11 +148,7 @@ async fn stream_error_in_200_cascades_to_next_tier() {
         "API_TIMEOUT_MS": 5000
     });
 
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, serde_json::to_string_pretty(&config).unwrap()).unwrap();
-
-    let cfg = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let cfg = config_from_json(&config.to_string());
     let app = build_app(cfg);
 
     let body = json!({
@@ -257,11 +234,7 @@ async fn overload_error_in_200_cascades() {
         "API_TIMEOUT_MS": 5000
     });
 
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, serde_json::to_string_pretty(&config).unwrap()).unwrap();
-
-    let cfg = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let cfg = config_from_json(&config.to_string());
     let app = build_app(cfg);
 
     let body = json!({
@@ -321,11 +294,7 @@ async fn valid_200_response_not_treated_as_error() {
         "API_TIMEOUT_MS": 5000
     });
 
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, serde_json::to_string_pretty(&config).unwrap()).unwrap();
-
-    let cfg = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let cfg = config_from_json(&config.to_string());
     let app = build_app(cfg);
 
     let body = json!({
@@ -358,6 +327,97 @@ async fn valid_200_response_not_treated_as_error() {
     );
 }
 
+/// A non-streaming 200 whose error sits at a provider-specific path (here a
+/// MiniMax-style `base_resp.status_code`) cascades once that path is listed in
+/// `soft_errors.error_paths`.
+///
+/// Tier-1's `.expect(1)` proves cascade occurred.
+#[tokio::test]
+async fn configured_error_path_in_200_body_cascades() {
+    if skip_if_localhost_bind_unavailable("configured_error_path_in_200_body_cascades") {
+        return;
+    }
+
+    let tier0_server = MockServer::start().await;
+    let tier1_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "",
+            "choices": null,
+            "base_resp": {"status_code": 1008, "status_msg": "insufficient balance"}
+        })))
+        .expect(1..)
+        .mount(&tier0_server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-ok",
+            "object": "chat.completion",
+            "created": 1234567890,
+            "model": "m1",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "recovered"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5}
+        })))
+        .expect(1)
+        .mount(&tier1_server)
+        .await;
+
+    let config = json!({
+        "Providers": [
+            {
+                "name": "minimax",
+                "api_base_url": tier0_server.uri(),
+                "api_key": "k",
+                "models": ["m0"],
+                "soft_errors": { "error_paths": ["/error", "/base_resp/status_code"] }
+            },
+            { "name": "wafer", "api_base_url": tier1_server.uri(), "api_key": "k", "models": ["m1"] }
+        ],
+        "Router": {
+            "default": "minimax,m0",
+            "think": "wafer,m1",
+            "tierRetries": { "tier-0": { "max_retries": 0 }, "tier-1": { "max_retries": 0 } }
+        },
+        "API_TIMEOUT_MS": 5000
+    });
+
+    let cfg = config_from_json(&config.to_string());
+    let app = build_app(cfg);
+
+    let body = json!({
+        "model": "minimax,m0",
+        "messages": [{"role": "user", "content": "hello"}],
+        "max_tokens": 100
+    });
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body_bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let resp_json: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap();
+    assert_eq!(resp_json["content"][0]["text"], "recovered");
+}
+
 #[tokio::test]
 async fn first_event_timeout_cascades_to_next_tier() {
     if skip_if_localhost_bind_unavailable("first_event_timeout_cascades_to_next_tier") {
@@ -407,11 +467,7 @@ async fn first_event_timeout_cascades_to_next_tier() {
         "API_TIMEOUT_MS": 5000
     });
 
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, serde_json::to_string_pretty(&config).unwrap()).unwrap();
-
-    let cfg = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let cfg = config_from_json(&config.to_string());
     let app = build_app(cfg);
 
     let body = json!({
@@ -469,11 +525,7 @@ async fn stream_idle_timeout_emits_error_event() {
         "API_TIMEOUT_MS": 5000
     });
 
-    let dir = tempfile::tempdir().unwrap();
-    let config_path = dir.path().join("config.json");
-    std::fs::write(&config_path, serde_json::to_string_pretty(&config).unwrap()).unwrap();
-
-    let cfg = ccr_rust::config::Config::from_file(config_path.to_str().unwrap()).unwrap();
+    let cfg = config_from_json(&config.to_string());
     let app = build_app(cfg);
 
     let body = json!({